@@ -72,6 +72,110 @@ fn is_match(c: &mut Criterion) {
     });
 }
 
+fn count(c: &mut Criterion) {
+    let corpus = SHERLOCK_HUGE;
+    define(c, "count", "sherlock-huge-count", corpus, move |b| {
+        let re = RegexBuilder::new().build(r"[A-Z][a-z]+").unwrap();
+        b.iter(|| {
+            assert!(re.count(corpus) > 0);
+        });
+    });
+
+    let corpus = SHERLOCK_HUGE;
+    define(c, "count", "sherlock-huge-find-iter", corpus, move |b| {
+        let re = RegexBuilder::new().build(r"[A-Z][a-z]+").unwrap();
+        b.iter(|| {
+            assert!(re.find_iter(corpus).count() > 0);
+        });
+    });
+}
+
+fn reverse_suffix(c: &mut Criterion) {
+    let corpus = SHERLOCK_HUGE;
+    define(c, "reverse-suffix", "sherlock-huge-no-match", corpus, move |b| {
+        let re = RegexBuilder::new().build(r"[a-z]+\.tar\.gz").unwrap();
+        b.iter(|| {
+            assert!(!re.is_match(corpus));
+        });
+    });
+
+    let corpus = SHERLOCK_HUGE;
+    define(
+        c,
+        "reverse-suffix",
+        "sherlock-huge-no-prefilter",
+        corpus,
+        move |b| {
+            let re = RegexBuilder::new()
+                .prefilter(false)
+                .build(r"[a-z]+\.tar\.gz")
+                .unwrap();
+            b.iter(|| {
+                assert!(!re.is_match(corpus));
+            });
+        },
+    );
+}
+
+// Every byte of these haystacks leads to a state that is neither a match,
+// dead nor self-looping state, so the search loop never leaves its fast path.
+fn unrolled(c: &mut Criterion) {
+    let corpus = SHERLOCK_HUGE;
+    define(c, "unrolled", "sherlock-huge-find", corpus, move |b| {
+        let dfa =
+            dense::Builder::new().build(r"[a-z]+ing [a-z]+ly[0-9]").unwrap();
+        b.iter(|| {
+            assert_eq!(None, dfa.find(corpus));
+        });
+    });
+
+    let corpus = SHERLOCK_HUGE;
+    define(c, "unrolled", "sherlock-huge-is-match", corpus, move |b| {
+        let dfa = dense::Builder::new()
+            .accelerate(false)
+            .build(r"(?i)Sherlock Holmes was (?:dead|alive)")
+            .unwrap();
+        b.iter(|| {
+            assert!(!dfa.is_match(corpus));
+        });
+    });
+}
+
+fn teddy(c: &mut Criterion) {
+    const PATTERNS: &[&str] =
+        &["Sherlock", "Holmes", "Watson", "Irene", "Adler", "Moriarty"];
+
+    let corpus = SHERLOCK_HUGE;
+    define(c, "teddy", "sherlock-huge-prefilter", corpus, move |b| {
+        let builder = dense::Builder::new();
+        let dfa = builder.build_many(PATTERNS).unwrap();
+        let pre = builder.build_prefilter_many(PATTERNS).unwrap().unwrap();
+        b.iter(|| {
+            let mut at = 0;
+            let mut count = 0;
+            while let Some((_, end)) = pre.find_pattern_at(&dfa, corpus, at) {
+                at = end;
+                count += 1;
+            }
+            assert!(count > 0);
+        });
+    });
+
+    let corpus = SHERLOCK_HUGE;
+    define(c, "teddy", "sherlock-huge-no-prefilter", corpus, move |b| {
+        let dfa = dense::Builder::new().build_many(PATTERNS).unwrap();
+        b.iter(|| {
+            let mut at = 0;
+            let mut count = 0;
+            while let Some((_, end)) = dfa.find_pattern_at(corpus, at) {
+                at = end;
+                count += 1;
+            }
+            assert!(count > 0);
+        });
+    });
+}
+
 // \w has 128,640 codepoints.
 fn compile_unicode_word(c: &mut Criterion) {
     define_compile(c, "unicode-word", r"\w");
@@ -237,4 +341,5 @@ criterion_group!(g2, compile_unicode_other_math);
 criterion_group!(g3, compile_unicode_other_uppercase);
 criterion_group!(g4, compile_muammar);
 criterion_group!(g5, compile_unicode_word);
-criterion_main!(g1, g2, g3, g4, g5);
+criterion_group!(g6, count, reverse_suffix, teddy, unrolled);
+criterion_main!(g1, g2, g3, g4, g5, g6);
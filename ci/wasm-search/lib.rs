@@ -0,0 +1,80 @@
+// This exercises every routine a no_std program needs to search precompiled
+// DFAs: peeking at a header, deserializing dense and sparse DFAs and regexes,
+// and searching them. The panic handler never formats its message, so if the
+// compiled module contains any formatting machinery, then something in the
+// search path uses it directly. See ci/check-wasm-search.
+
+#![no_std]
+
+extern crate regex_automata;
+
+use core::slice;
+
+use regex_automata::{peek, DenseDFA, Regex, SparseDFA, DFA};
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+    slice::from_raw_parts(ptr, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn state_count(dfa: *const u8, len: usize) -> isize {
+    match peek(bytes(dfa, len)) {
+        Ok(info) => info.state_count() as isize,
+        Err(_) => -1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dense_find(
+    dfa: *const u8,
+    dfa_len: usize,
+    haystack: *const u8,
+    haystack_len: usize,
+) -> isize {
+    let dfa: DenseDFA<&[u16], u16> =
+        match DenseDFA::try_from_bytes(bytes(dfa, dfa_len)) {
+            Ok(dfa) => dfa,
+            Err(_) => return -2,
+        };
+    match dfa.try_find(bytes(haystack, haystack_len)) {
+        Ok(Some(end)) => end as isize,
+        Ok(None) => -1,
+        Err(_) => -3,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sparse_find(
+    dfa: *const u8,
+    dfa_len: usize,
+    haystack: *const u8,
+    haystack_len: usize,
+) -> isize {
+    let dfa: SparseDFA<&[u8], u16> =
+        SparseDFA::from_bytes(bytes(dfa, dfa_len));
+    match dfa.try_find(bytes(haystack, haystack_len)) {
+        Ok(Some(end)) => end as isize,
+        Ok(None) => -1,
+        Err(_) => -3,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn regex_count(
+    re: *const u8,
+    re_len: usize,
+    haystack: *const u8,
+    haystack_len: usize,
+) -> isize {
+    let re: Regex<DenseDFA<&[u16], u16>> =
+        match Regex::try_from_bytes(bytes(re, re_len)) {
+            Ok(re) => re,
+            Err(_) => return -2,
+        };
+    re.find_iter(bytes(haystack, haystack_len)).count() as isize
+}
@@ -0,0 +1,82 @@
+#ifndef _REGEX_AUTOMATA_H
+#define _REGEX_AUTOMATA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * regex_automata_dfa is a dense DFA loaded from bytes serialized by
+ * regex-automata, e.g., with DenseDFA::to_bytes_native_endian. Only dense
+ * DFAs serialized with the endianness of the target are supported.
+ *
+ * A regex_automata_dfa may be searched from multiple threads
+ * simultaneously.
+ */
+typedef struct regex_automata_dfa regex_automata_dfa;
+
+/*
+ * regex_automata_dfa_new loads a DFA from the len bytes at bytes, which are
+ * validated first.
+ *
+ * The bytes must be aligned to the size of the DFA's state identifiers,
+ * e.g., to 8 bytes to be safe. The DFA borrows the bytes without copying
+ * them, so they must not be modified or freed until the DFA is freed.
+ *
+ * On success, this returns a DFA that must be freed with
+ * regex_automata_dfa_free.
+ *
+ * On failure, this returns NULL. If error isn't NULL, then *error is set to
+ * a NUL terminated message describing the failure, which must be freed with
+ * regex_automata_error_free.
+ */
+regex_automata_dfa *regex_automata_dfa_new(const uint8_t *bytes, size_t len,
+                                           char **error);
+
+/*
+ * regex_automata_dfa_free frees the given DFA. This does nothing if dfa is
+ * NULL.
+ */
+void regex_automata_dfa_free(regex_automata_dfa *dfa);
+
+/*
+ * regex_automata_error_free frees an error message returned by
+ * regex_automata_dfa_new. This does nothing if error is NULL.
+ */
+void regex_automata_error_free(char *error);
+
+/*
+ * regex_automata_dfa_is_match searches haystack[start..len] for a match.
+ * The bytes before start are taken into account by look-around assertions,
+ * such as word boundaries.
+ *
+ * This returns 1 if there is a match and 0 if there isn't. It returns -1 if
+ * the search quit on one of the DFA's quit bytes before it could tell, or if
+ * start > len.
+ */
+int regex_automata_dfa_is_match(const regex_automata_dfa *dfa,
+                                const uint8_t *haystack, size_t len,
+                                size_t start);
+
+/*
+ * regex_automata_dfa_find searches haystack[start..len] for the end of the
+ * leftmost match. The bytes before start are taken into account by
+ * look-around assertions, such as word boundaries.
+ *
+ * This returns 1 and sets *end to the end offset of the match, relative to
+ * the beginning of haystack, if there is a match. It returns 0, leaving *end
+ * untouched, if there isn't. It returns -1 if the search quit on one of the
+ * DFA's quit bytes before it could tell, or if start > len.
+ */
+int regex_automata_dfa_find(const regex_automata_dfa *dfa,
+                            const uint8_t *haystack, size_t len,
+                            size_t start, size_t *end);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
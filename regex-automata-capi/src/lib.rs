@@ -0,0 +1,353 @@
+/*!
+A C API for searching DFAs that were built and serialized ahead of time by
+`regex-automata`.
+
+A C program embeds the bytes of a dense DFA, e.g., as written by
+`DenseDFA::to_bytes_native_endian`, loads them with
+`regex_automata_dfa_new` and searches them with `regex_automata_dfa_is_match`
+and `regex_automata_dfa_find`. The declarations of these functions are in
+`include/regex_automata.h`.
+
+Loading a DFA never copies its transition table. The DFA borrows the bytes it
+was loaded from, so they must outlive it. Since the bytes are untrusted from
+the perspective of Rust, they are always validated when loaded.
+
+Sparse DFAs aren't supported, since they can't be validated when loaded.
+*/
+
+#![deny(missing_docs)]
+
+extern crate regex_automata;
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::slice;
+
+use regex_automata::{peek, DenseDFA, DeserializeError, DFA};
+
+/// A dense DFA loaded from serialized bytes, for any state identifier
+/// representation.
+///
+/// The bytes are borrowed for the `'static` lifetime, since the C caller is
+/// responsible for keeping them alive until the DFA is freed.
+#[derive(Debug)]
+enum Repr {
+    U8(DenseDFA<&'static [u8], u8>),
+    U16(DenseDFA<&'static [u16], u16>),
+    U32(DenseDFA<&'static [u32], u32>),
+    #[cfg(target_pointer_width = "64")]
+    U64(DenseDFA<&'static [u64], u64>),
+}
+
+/// Evaluate the given expression with `$dfa` bound to the DFA wrapped by each
+/// variant of a `Repr`.
+macro_rules! repr {
+    ($repr:expr, $dfa:ident => $expr:expr) => {
+        match *$repr {
+            Repr::U8(ref $dfa) => $expr,
+            Repr::U16(ref $dfa) => $expr,
+            Repr::U32(ref $dfa) => $expr,
+            #[cfg(target_pointer_width = "64")]
+            Repr::U64(ref $dfa) => $expr,
+        }
+    };
+}
+
+/// An opaque handle to a DFA, as exposed to C.
+#[derive(Debug)]
+pub struct Dfa {
+    repr: Repr,
+    /// Whether any byte makes a search with this DFA quit. If not, then
+    /// searches can never fail, and `is_match` can stop at the first match.
+    has_quit_bytes: bool,
+}
+
+impl Dfa {
+    fn from_bytes(buf: &'static [u8]) -> Result<Dfa, String> {
+        let info = peek(buf).map_err(|err| err.to_string())?;
+        if info.is_sparse() {
+            return Err("sparse DFAs are not supported".to_string());
+        }
+        let repr = match info.state_id_size() {
+            1 => DenseDFA::try_from_bytes(buf).map(Repr::U8),
+            2 => DenseDFA::try_from_bytes(buf).map(Repr::U16),
+            4 => DenseDFA::try_from_bytes(buf).map(Repr::U32),
+            #[cfg(target_pointer_width = "64")]
+            8 => DenseDFA::try_from_bytes(buf).map(Repr::U64),
+            size => Err(DeserializeError::InvalidStateIDSize { size }),
+        }
+        .map_err(|err| err.to_string())?;
+        let has_quit_bytes =
+            repr!(&repr, dfa => (0..256).any(|b| dfa.is_quit_byte(b as u8)));
+        Ok(Dfa { repr, has_quit_bytes })
+    }
+
+    /// Returns `1` if there is a match at or after `start`, `0` if there
+    /// isn't and `-1` if the search failed.
+    fn is_match(&self, haystack: &[u8], start: usize) -> c_int {
+        if !self.has_quit_bytes {
+            let matched =
+                repr!(&self.repr, dfa => dfa.is_match_at(haystack, start));
+            return matched as c_int;
+        }
+        let mut end = 0;
+        self.find(haystack, start, &mut end)
+    }
+
+    /// Returns `1` and writes the end of the match to `end` if there is a
+    /// match at or after `start`, `0` if there isn't and `-1` if the search
+    /// failed.
+    fn find(&self, haystack: &[u8], start: usize, end: &mut usize) -> c_int {
+        match repr!(&self.repr, dfa => dfa.try_find_at(haystack, start)) {
+            Ok(Some(e)) => {
+                *end = e;
+                1
+            }
+            Ok(None) => 0,
+            Err(_) => -1,
+        }
+    }
+}
+
+/// Converts a pointer and length from C to a slice, permitting a `NULL`
+/// pointer when the length is zero.
+unsafe fn to_slice<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Load a dense DFA from the serialized bytes at `bytes`.
+///
+/// On success, this returns a DFA that must be freed with
+/// `regex_automata_dfa_free`. The DFA borrows the bytes, so they must not be
+/// modified or freed until the DFA is freed.
+///
+/// On failure, this returns `NULL`. If `error` isn't `NULL`, then it is set
+/// to a message describing the failure, which must be freed with
+/// `regex_automata_error_free`.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes, unless `len` is zero. `error`
+/// must be `NULL` or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn regex_automata_dfa_new(
+    bytes: *const u8,
+    len: usize,
+    error: *mut *mut c_char,
+) -> *mut Dfa {
+    if !error.is_null() {
+        *error = ptr::null_mut();
+    }
+    match Dfa::from_bytes(to_slice(bytes, len)) {
+        Ok(dfa) => Box::into_raw(Box::new(dfa)),
+        Err(msg) => {
+            if !error.is_null() {
+                // Messages never contain a NUL byte.
+                *error = CString::new(msg).unwrap().into_raw();
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a DFA returned by `regex_automata_dfa_new`. This does nothing if
+/// `dfa` is `NULL`.
+///
+/// # Safety
+///
+/// `dfa` must be `NULL` or a DFA returned by `regex_automata_dfa_new` that
+/// wasn't freed already.
+#[no_mangle]
+pub unsafe extern "C" fn regex_automata_dfa_free(dfa: *mut Dfa) {
+    if !dfa.is_null() {
+        drop(Box::from_raw(dfa));
+    }
+}
+
+/// Free an error message returned by `regex_automata_dfa_new`. This does
+/// nothing if `error` is `NULL`.
+///
+/// # Safety
+///
+/// `error` must be `NULL` or a message returned by `regex_automata_dfa_new`
+/// that wasn't freed already.
+#[no_mangle]
+pub unsafe extern "C" fn regex_automata_error_free(error: *mut c_char) {
+    if !error.is_null() {
+        drop(CString::from_raw(error));
+    }
+}
+
+/// Search `haystack[start..len]` for a match, taking the bytes before `start`
+/// into account for look-around assertions.
+///
+/// This returns `1` if there is a match and `0` if there isn't. It returns
+/// `-1` if the search quit on a quit byte before it could tell, or if
+/// `start > len`.
+///
+/// # Safety
+///
+/// `dfa` must be a live DFA returned by `regex_automata_dfa_new`.
+/// `haystack` must point to `len` readable bytes, unless `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn regex_automata_dfa_is_match(
+    dfa: *const Dfa,
+    haystack: *const u8,
+    len: usize,
+    start: usize,
+) -> c_int {
+    if start > len {
+        return -1;
+    }
+    (*dfa).is_match(to_slice(haystack, len), start)
+}
+
+/// Search `haystack[start..len]` for the end of the leftmost match, taking
+/// the bytes before `start` into account for look-around assertions.
+///
+/// This returns `1` and writes the end offset of the match, relative to the
+/// beginning of `haystack`, to `end` if there is a match. It returns `0`,
+/// leaving `end` untouched, if there isn't. It returns `-1` if the search
+/// quit on a quit byte before it could tell, or if `start > len`.
+///
+/// # Safety
+///
+/// `dfa` must be a live DFA returned by `regex_automata_dfa_new`.
+/// `haystack` must point to `len` readable bytes, unless `len` is zero.
+/// `end` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn regex_automata_dfa_find(
+    dfa: *const Dfa,
+    haystack: *const u8,
+    len: usize,
+    start: usize,
+    end: *mut usize,
+) -> c_int {
+    if start > len {
+        return -1;
+    }
+    (*dfa).find(to_slice(haystack, len), start, &mut *end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    use regex_automata::{dense, AlignedBytes, DenseDFA};
+
+    use super::*;
+
+    fn load(bytes: &AlignedBytes) -> *mut Dfa {
+        let mut error = ptr::null_mut();
+        let dfa = unsafe {
+            regex_automata_dfa_new(bytes.as_ptr(), bytes.len(), &mut error)
+        };
+        assert!(error.is_null());
+        assert!(!dfa.is_null());
+        dfa
+    }
+
+    fn find(dfa: *const Dfa, haystack: &[u8], start: usize) -> (c_int, usize) {
+        let mut end = !0;
+        let rc = unsafe {
+            regex_automata_dfa_find(
+                dfa,
+                haystack.as_ptr(),
+                haystack.len(),
+                start,
+                &mut end,
+            )
+        };
+        (rc, end)
+    }
+
+    fn is_match(dfa: *const Dfa, haystack: &[u8], start: usize) -> c_int {
+        unsafe {
+            regex_automata_dfa_is_match(
+                dfa,
+                haystack.as_ptr(),
+                haystack.len(),
+                start,
+            )
+        }
+    }
+
+    #[test]
+    fn search() {
+        let dfa = DenseDFA::new(r"foo[0-9]+").unwrap();
+        let all = vec![
+            dfa.to_u8().map(|d| d.to_bytes_native_endian().unwrap()),
+            dfa.to_u16().map(|d| d.to_bytes_native_endian().unwrap()),
+            dfa.to_u32().map(|d| d.to_bytes_native_endian().unwrap()),
+            dfa.to_u64().map(|d| d.to_bytes_native_endian().unwrap()),
+        ];
+        for bytes in all.into_iter().filter_map(|r| r.ok()) {
+            let dfa = load(&bytes);
+            assert_eq!((1, 8), find(dfa, b"foo12345", 0));
+            assert_eq!((1, 11), find(dfa, b"xx foo12345", 1));
+            assert_eq!((0, !0), find(dfa, b"foo12345", 1));
+            assert_eq!(1, is_match(dfa, b"xx foo1", 0));
+            assert_eq!(0, is_match(dfa, b"xx foo", 0));
+            assert_eq!(-1, is_match(dfa, b"foo1", 5));
+            assert_eq!(0, is_match(dfa, &[], 0));
+            unsafe { regex_automata_dfa_free(dfa) };
+        }
+    }
+
+    #[test]
+    fn search_lookaround() {
+        let dfa = DenseDFA::new(r"(?-u:\b)foo(?-u:\b)").unwrap();
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        let dfa = load(&bytes);
+        assert_eq!((1, 5), find(dfa, b"a foo b", 0));
+        assert_eq!((0, !0), find(dfa, b"afoo", 1));
+        assert_eq!(1, is_match(dfa, b"afoo foo", 1));
+        unsafe { regex_automata_dfa_free(dfa) };
+    }
+
+    #[test]
+    fn search_quit() {
+        let dfa = dense::Builder::new()
+            .unicode_word_boundary(true)
+            .build(r"\bfoo\b")
+            .unwrap();
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        let dfa = load(&bytes);
+        assert_eq!((1, 3), find(dfa, b"foo bar", 0));
+        assert_eq!(-1, find(dfa, "foo☃".as_bytes(), 0).0);
+        assert_eq!(1, is_match(dfa, b"a foo", 0));
+        assert_eq!(-1, is_match(dfa, "☃ foo".as_bytes(), 0));
+        unsafe { regex_automata_dfa_free(dfa) };
+    }
+
+    #[test]
+    fn load_errors() {
+        let mut error = ptr::null_mut();
+        let dfa =
+            unsafe { regex_automata_dfa_new(b"nope".as_ptr(), 4, &mut error) };
+        assert!(dfa.is_null());
+        assert!(!error.is_null());
+        unsafe { regex_automata_error_free(error) };
+
+        let sparse = DenseDFA::new("foo").unwrap().to_sparse().unwrap();
+        let bytes = sparse.to_bytes_native_endian().unwrap();
+        let dfa = unsafe {
+            regex_automata_dfa_new(bytes.as_ptr(), bytes.len(), &mut error)
+        };
+        assert!(dfa.is_null());
+        let msg = unsafe { CStr::from_ptr(error) }.to_str().unwrap();
+        assert_eq!("sparse DFAs are not supported", msg);
+        unsafe { regex_automata_error_free(error) };
+
+        // A NULL error pointer is permitted.
+        let dfa =
+            unsafe { regex_automata_dfa_new(ptr::null(), 0, ptr::null_mut()) };
+        assert!(dfa.is_null());
+    }
+}
@@ -0,0 +1,182 @@
+use memchr::{memchr, memchr2, memchr3};
+
+use byteorder::{ByteOrder, NativeEndian};
+use error::DeserializeError;
+use state_id::StateID;
+use stats;
+
+/// The maximum number of accelerated states in a single DFA.
+///
+/// States worth accelerating are typically few, e.g., the states for `.*`
+/// or `[^"]*`, so a small fixed bound keeps the accelerators inline, which
+/// permits using them without an allocator.
+pub const MAX_ACCELS: usize = 8;
+
+/// The number of bytes in the serialized form of a set of accelerators: the
+/// number of accelerated states, followed by each possible accelerated state
+/// and its needles.
+pub const ACCELS_LEN: usize = 8 + MAX_ACCELS * 16;
+
+/// The accelerated states of a DFA.
+///
+/// An accelerated state transitions to itself on every byte except for one,
+/// two or three "needle" bytes. When a search enters such a state, it can
+/// skip ahead to the next needle with `memchr`, instead of following one
+/// transition for every byte in between. Accelerated states are never match
+/// states or dead states.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Accels<S> {
+    /// The number of accelerated states.
+    len: usize,
+    /// The identifiers of the accelerated states. Only the first `len` are
+    /// used.
+    ids: [S; MAX_ACCELS],
+    /// The needles of each accelerated state, where the first byte is the
+    /// number of needles, followed by the needles themselves.
+    needles: [[u8; 4]; MAX_ACCELS],
+}
+
+impl<S: StateID> Accels<S> {
+    /// Create a new set of accelerators without any accelerated states.
+    pub fn empty() -> Accels<S> {
+        Accels {
+            len: 0,
+            ids: [S::from_usize(0); MAX_ACCELS],
+            needles: [[0; 4]; MAX_ACCELS],
+        }
+    }
+
+    /// Read a set of accelerators from its serialized form in native
+    /// endianness, as written by `write`.
+    ///
+    /// This does not check that the accelerators are valid. In particular,
+    /// every state identifier must fit in `S` and the number of needles of
+    /// each state must be 1, 2 or 3.
+    pub fn from_bytes_unchecked(buf: &[u8]) -> Accels<S> {
+        let mut accels = Accels::empty();
+        let len = NativeEndian::read_u64(buf) as usize;
+        for i in 0..len {
+            let entry = &buf[8 + i * 16..];
+            accels.ids[i] =
+                S::from_usize(NativeEndian::read_u64(entry) as usize);
+            accels.needles[i].copy_from_slice(&entry[8..12]);
+        }
+        accels.len = len;
+        accels
+    }
+
+    /// Read a set of accelerators from its serialized form in native
+    /// endianness, as written by `write`, and check that they are well
+    /// formed.
+    ///
+    /// Each accelerated state's identifier must satisfy `is_valid`, which
+    /// should reject identifiers that don't refer to a state of the DFA.
+    pub fn from_bytes<F: Fn(u64) -> bool>(
+        buf: &[u8],
+        is_valid: F,
+    ) -> Result<Accels<S>, DeserializeError> {
+        let len = NativeEndian::read_u64(buf);
+        if len > MAX_ACCELS as u64 {
+            return Err(DeserializeError::InvalidAccelerators);
+        }
+        for i in 0..len as usize {
+            let entry = &buf[8 + i * 16..];
+            let id = NativeEndian::read_u64(entry);
+            if id > S::max_id() as u64 || !is_valid(id) {
+                return Err(DeserializeError::InvalidStateID { id });
+            }
+            if entry[8] < 1 || entry[8] > 3 {
+                return Err(DeserializeError::InvalidAccelerators);
+            }
+        }
+        Ok(Accels::from_bytes_unchecked(buf))
+    }
+
+    /// Write these accelerators to the given buffer, which must have length
+    /// at least `ACCELS_LEN`.
+    #[cfg(feature = "std")]
+    pub fn write<A: ByteOrder>(&self, buf: &mut [u8]) {
+        for b in &mut buf[..ACCELS_LEN] {
+            *b = 0;
+        }
+        A::write_u64(buf, self.len as u64);
+        for i in 0..self.len {
+            let entry = &mut buf[8 + i * 16..];
+            A::write_u64(entry, self.ids[i].to_usize() as u64);
+            entry[8..12].copy_from_slice(&self.needles[i]);
+        }
+    }
+
+    /// Add an accelerated state with the given needles, which must have
+    /// length 1, 2 or 3. If there is no room for another accelerated state,
+    /// then this returns false and does nothing.
+    #[cfg(feature = "std")]
+    pub fn add(&mut self, id: S, needles: &[u8]) -> bool {
+        assert!(!needles.is_empty() && needles.len() <= 3);
+        if self.len == MAX_ACCELS {
+            return false;
+        }
+        self.ids[self.len] = id;
+        self.needles[self.len][0] = needles.len() as u8;
+        self.needles[self.len][1..=needles.len()].copy_from_slice(needles);
+        self.len += 1;
+        true
+    }
+
+    /// Returns the number of accelerated states.
+    #[cfg(feature = "std")]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the needles of the given state, or an empty slice if the state
+    /// isn't accelerated.
+    #[inline]
+    pub fn needles(&self, id: S) -> &[u8] {
+        for i in 0..self.len {
+            if self.ids[i] == id {
+                let n = self.needles[i][0] as usize;
+                return &self.needles[i][1..=n];
+            }
+        }
+        &[]
+    }
+
+    /// Returns an iterator over every accelerated state and its needles.
+    #[cfg(feature = "std")]
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (S, &'a [u8])> + 'a {
+        (0..self.len).map(move |i| {
+            let n = self.needles[i][0] as usize;
+            (self.ids[i], &self.needles[i][1..=n])
+        })
+    }
+
+    /// Returns a copy of these accelerators, where each state identifier is
+    /// mapped to a new one with the given function.
+    #[cfg(feature = "std")]
+    pub fn remap<T: StateID, F: FnMut(S) -> T>(
+        &self,
+        mut map: F,
+    ) -> Accels<T> {
+        let mut accels = Accels::empty();
+        for (id, needles) in self.iter() {
+            accels.add(map(id), needles);
+        }
+        accels
+    }
+}
+
+/// Returns the position of the first needle in `bytes` at or after `at`, or
+/// the length of `bytes` if there is none.
+#[inline]
+pub fn find_needle(needles: &[u8], bytes: &[u8], at: usize) -> usize {
+    let rest = &bytes[at..];
+    let i = match needles.len() {
+        1 => memchr(needles[0], rest),
+        2 => memchr2(needles[0], needles[1], rest),
+        _ => memchr3(needles[0], needles[1], needles[2], rest),
+    };
+    let next = i.map_or(bytes.len(), |i| at + i);
+    stats::skipped(next - at);
+    next
+}
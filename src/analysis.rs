@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+use dfa::DFA;
+
+/// Walk the product of the two DFAs given in lockstep, and return the
+/// smallest depth at which they diverge. If the DFAs never diverge, then
+/// `None` is returned.
+///
+/// A pair of states, one from each DFA, diverges when exactly one of them is
+/// a match state, or when there is a byte for which exactly one of them
+/// transitions to a dead state. Since the walk is breadth first, the depth
+/// returned corresponds to the length of the longest prefix on which both
+/// DFAs agree.
+///
+/// For DFAs with look-around, a state also counts as a match state when the
+/// state reached from it at the end of the input is a match state.
+pub fn shared_prefix_len<A: DFA, B: DFA>(a: &A, b: &B) -> Option<usize> {
+    let start = (a.start_state_after(None), b.start_state_after(None));
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back((start, 0));
+    while let Some(((sa, sb), depth)) = queue.pop_front() {
+        if is_accepting(a, sa) != is_accepting(b, sb) {
+            return Some(depth);
+        }
+        let mut next = vec![];
+        for byte in 0..256 {
+            let (na, nb) =
+                (a.next_state(sa, byte as u8), b.next_state(sb, byte as u8));
+            match (a.is_dead_state(na), b.is_dead_state(nb)) {
+                (true, true) => {}
+                (false, false) => next.push((na, nb)),
+                _ => return Some(depth),
+            }
+        }
+        for pair in next {
+            if seen.insert(pair) {
+                queue.push_back((pair, depth + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Returns true if and only if the language recognized by the given DFA is
+/// finite.
+///
+/// The language is finite precisely when there is no cycle among the states
+/// that are both reachable from the start state and capable of reaching a
+/// match state. Cycles among states that can never lead to a match (such as
+/// the dead state's own loop) do not contribute to the language. For DFAs
+/// with look-around, a state can also reach a match at the end of the input.
+pub fn is_finite_language<D: DFA>(dfa: &D) -> bool {
+    let graph = Graph::new(dfa);
+
+    // Find all states that can reach a match state by walking backwards
+    // from every match state.
+    let mut useful = vec![false; graph.len()];
+    let mut stack: Vec<usize> =
+        (0..graph.len()).filter(|&i| graph.is_match[i]).collect();
+    for &i in &stack {
+        useful[i] = true;
+    }
+    while let Some(i) = stack.pop() {
+        for &j in &graph.incoming[i] {
+            if !useful[j] {
+                useful[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+
+    // Now look for a cycle among useful states using an iterative depth
+    // first search. A state is on the stack while its successors are being
+    // visited, so reaching a state on the stack again indicates a cycle.
+    #[derive(Clone, Copy, Eq, PartialEq)]
+    enum Mark {
+        Unvisited,
+        OnStack,
+        Done,
+    }
+    let mut marks = vec![Mark::Unvisited; graph.len()];
+    for root in 0..graph.len() {
+        if !useful[root] || marks[root] != Mark::Unvisited {
+            continue;
+        }
+        marks[root] = Mark::OnStack;
+        let mut stack = vec![(root, 0)];
+        while let Some(&mut (i, ref mut next)) = stack.last_mut() {
+            if *next == graph.outgoing[i].len() {
+                marks[i] = Mark::Done;
+                stack.pop();
+                continue;
+            }
+            let j = graph.outgoing[i][*next];
+            *next += 1;
+            if !useful[j] {
+                continue;
+            }
+            match marks[j] {
+                Mark::OnStack => return false,
+                Mark::Done => {}
+                Mark::Unvisited => {
+                    marks[j] = Mark::OnStack;
+                    stack.push((j, 0));
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Returns the shortest input on which a search with the given DFA reports
+/// a match, or `None` if the DFA never matches anything.
+///
+/// This is computed by a breadth first search from the start state, so the
+/// input returned is as short as possible. Among inputs of the same length,
+/// the one that is smallest lexicographically is returned.
+///
+/// For DFAs with look-around, every match is delayed by one byte, so an
+/// input matches when a match state is reached by a transition on one of its
+/// bytes, or when the state reached at the end of the input is a match
+/// state.
+pub fn shortest_matching_input<D: DFA>(dfa: &D) -> Option<Vec<u8>> {
+    let start = dfa.start_state_after(None);
+    if dfa.is_dead_state(start) {
+        return None;
+    }
+    // Each visited state records its predecessor and the byte used to reach
+    // it, which permits rebuilding the input once a match is found.
+    let mut parents: HashMap<D::ID, Option<(D::ID, u8)>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(start, None);
+    queue.push_back(start);
+    while let Some(id) = queue.pop_front() {
+        if is_accepting(dfa, id) {
+            let mut input = vec![];
+            let mut cur = id;
+            while let Some((prev, byte)) = parents[&cur] {
+                input.push(byte);
+                cur = prev;
+            }
+            input.reverse();
+            return Some(input);
+        }
+        for byte in 0..256 {
+            let next = dfa.next_state(id, byte as u8);
+            if dfa.is_dead_state(next) || parents.contains_key(&next) {
+                continue;
+            }
+            parents.insert(next, Some((id, byte as u8)));
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// Returns true if and only if the given state is a match state, or, for
+/// DFAs with look-around, if the state reached from it at the end of the
+/// input is a match state.
+fn is_accepting<D: DFA>(dfa: &D, id: D::ID) -> bool {
+    dfa.is_match_state(id)
+        || (dfa.has_lookaround() && dfa.is_match_state(dfa.next_eoi_state(id)))
+}
+
+/// Returns true if and only if the two DFAs given match precisely the same
+/// inputs.
+///
+/// This uses the algorithm of Hopcroft and Karp: pairs of states, one from
+/// each DFA, that are reached by the same input are merged into the same
+/// set of a union-find structure, and the DFAs are equivalent when no set
+/// contains both a match state and a non-match state. Only pairs that merge
+/// two distinct sets need their transitions explored, so the work done is
+/// nearly linear in the number of states.
+///
+/// DFAs that differ in whether they are anchored or whether they evaluate
+/// look-around are never equivalent, since their match states have
+/// different meanings. For DFAs with look-around, the states reached at the
+/// end of the input must also agree.
+pub fn is_equivalent<A: DFA, B: DFA>(a: &A, b: &B) -> bool {
+    if a.is_anchored() != b.is_anchored()
+        || a.has_lookaround() != b.has_lookaround()
+    {
+        return false;
+    }
+    let accepts_a =
+        |id| (a.is_match_state(id), a.is_match_state(a.next_eoi_state(id)));
+    let accepts_b =
+        |id| (b.is_match_state(id), b.is_match_state(b.next_eoi_state(id)));
+
+    let mut sets = PairSets::new();
+    let start = (a.start_state_after(None), b.start_state_after(None));
+    sets.union(start.0, start.1);
+    let mut stack = vec![start];
+    while let Some((sa, sb)) = stack.pop() {
+        if accepts_a(sa) != accepts_b(sb) {
+            return false;
+        }
+        for byte in 0..256 {
+            let na = a.next_state(sa, byte as u8);
+            let nb = b.next_state(sb, byte as u8);
+            if sets.union(na, nb) {
+                stack.push((na, nb));
+            }
+        }
+    }
+    true
+}
+
+/// A union-find structure over the states of two DFAs.
+struct PairSets<A, B> {
+    a: HashMap<A, usize>,
+    b: HashMap<B, usize>,
+    parents: Vec<usize>,
+}
+
+impl<A: Eq + Hash, B: Eq + Hash> PairSets<A, B> {
+    fn new() -> PairSets<A, B> {
+        PairSets { a: HashMap::new(), b: HashMap::new(), parents: vec![] }
+    }
+
+    /// Merge the sets containing the given states, and return true if and
+    /// only if they were previously distinct.
+    fn union(&mut self, a: A, b: B) -> bool {
+        let next = self.parents.len();
+        let ia = *self.a.entry(a).or_insert(next);
+        if ia == next {
+            self.parents.push(ia);
+        }
+        let next = self.parents.len();
+        let ib = *self.b.entry(b).or_insert(next);
+        if ib == next {
+            self.parents.push(ib);
+        }
+        let (ra, rb) = (self.find(ia), self.find(ib));
+        if ra == rb {
+            return false;
+        }
+        self.parents[rb] = ra;
+        true
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+}
+
+/// The graph of all non-dead states reachable from a DFA's start state.
+///
+/// States are identified by their index in the order in which they were
+/// discovered. The start state always has index `0`.
+struct Graph {
+    is_match: Vec<bool>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn new<D: DFA>(dfa: &D) -> Graph {
+        let mut graph =
+            Graph { is_match: vec![], outgoing: vec![], incoming: vec![] };
+        let start = dfa.start_state_after(None);
+        if dfa.is_dead_state(start) {
+            return graph;
+        }
+        let mut index = HashMap::new();
+        let mut ids = vec![start];
+        index.insert(start, 0);
+        graph.is_match.push(is_accepting(dfa, start));
+        graph.outgoing.push(vec![]);
+        graph.incoming.push(vec![]);
+
+        let mut i = 0;
+        while i < ids.len() {
+            let id = ids[i];
+            for byte in 0..256 {
+                let next = dfa.next_state(id, byte as u8);
+                if dfa.is_dead_state(next) {
+                    continue;
+                }
+                let j = match index.get(&next) {
+                    Some(&j) => j,
+                    None => {
+                        let j = ids.len();
+                        ids.push(next);
+                        index.insert(next, j);
+                        graph.is_match.push(is_accepting(dfa, next));
+                        graph.outgoing.push(vec![]);
+                        graph.incoming.push(vec![]);
+                        j
+                    }
+                };
+                if !graph.outgoing[i].contains(&j) {
+                    graph.outgoing[i].push(j);
+                    graph.incoming[j].push(i);
+                }
+            }
+            i += 1;
+        }
+        graph
+    }
+
+    fn len(&self) -> usize {
+        self.is_match.len()
+    }
+}
@@ -0,0 +1,535 @@
+use std::mem::size_of;
+
+use dense;
+use dfa::Match;
+use error::{Result, SearchError};
+use nfa::{self, NFA};
+
+/// The default number of bytes that the set of visited states may use.
+const DEFAULT_VISITED_CAPACITY: usize = 256 * (1 << 10);
+
+/// The number of bits in each block of the set of visited states.
+const BLOCK_BITS: usize = 64;
+
+/// A bounded backtracking regex engine.
+///
+/// A bounded backtracker searches by walking the NFA of a pattern depth
+/// first, exploring alternatives in order of preference. To avoid the
+/// exponential worst case of a classical backtracker, it records every pair
+/// of NFA state and input position that it visits, and never visits the same
+/// pair twice. This guarantees that a search takes time proportional to the
+/// number of NFA states multiplied by the length of the input.
+///
+/// Recording visited pairs takes one bit per NFA state for each position in
+/// the input, so the length of the inputs that can be searched is bounded
+/// by a configurable memory budget. Searching a longer input returns an
+/// error instead of exceeding the budget. See
+/// [`max_haystack_len`](struct.BoundedBacktracker.html#method.max_haystack_len).
+///
+/// Since it doesn't build any DFA states, a bounded backtracker is very
+/// cheap to build, and is typically the fastest way to find the start and
+/// end of a match in a short input. Unlike a DFA, which only reports the end
+/// of a match, a bounded backtracker reports both the start and the end of
+/// the leftmost first match.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::backtrack::BoundedBacktracker;
+/// use regex_automata::Match;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let re = BoundedBacktracker::new("foo[0-9]+")?;
+/// let mut cache = re.new_cache();
+/// assert_eq!(Ok(Some(Match::new(3, 8))), re.find(&mut cache, b"zzzfoo12"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct BoundedBacktracker {
+    nfa: NFA,
+    visited_capacity: usize,
+}
+
+impl BoundedBacktracker {
+    /// Parse the given regular expression using a default configuration and
+    /// return the corresponding bounded backtracker.
+    ///
+    /// The default configuration permits the set of visited states to use
+    /// 256KB. To use a different configuration, use a
+    /// [`backtrack::Builder`](struct.Builder.html).
+    pub fn new(pattern: &str) -> Result<BoundedBacktracker> {
+        Builder::new().build(pattern)
+    }
+
+    /// Create a new cache for searching with this bounded backtracker.
+    ///
+    /// A cache should only be used with the bounded backtracker that created
+    /// it.
+    pub fn new_cache(&self) -> Cache {
+        Cache { visited: vec![], stack: vec![] }
+    }
+
+    /// Returns true if and only if this bounded backtracker is anchored.
+    ///
+    /// When anchored, it is only allowed to report matches that start at
+    /// index `0`.
+    pub fn is_anchored(&self) -> bool {
+        self.nfa.is_anchored()
+    }
+
+    /// Returns the length of the longest input that this bounded backtracker
+    /// can search without exceeding its memory budget.
+    ///
+    /// This is determined by the configured capacity of the set of visited
+    /// states and the number of states in the NFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{backtrack, Match, SearchError};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = backtrack::Builder::new()
+    ///     .visited_capacity(64)
+    ///     .build("[a-z]+")?;
+    /// let mut cache = re.new_cache();
+    /// let max = re.max_haystack_len();
+    /// let haystack = vec![b'a'; max + 1];
+    /// assert_eq!(
+    ///     Ok(Some(Match::new(0, max))),
+    ///     re.find(&mut cache, &haystack[..max]),
+    /// );
+    /// assert_eq!(
+    ///     Err(SearchError::InputTooLong { max, got: max + 1 }),
+    ///     re.find(&mut cache, &haystack),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn max_haystack_len(&self) -> usize {
+        // One bit is needed for every NFA state at every position, including
+        // the position at the end of the input.
+        let positions = (8 * self.visited_capacity) / self.nfa.len();
+        positions.saturating_sub(1)
+    }
+
+    /// Returns true if and only if the given bytes match.
+    ///
+    /// If the input is longer than
+    /// [`max_haystack_len`](struct.BoundedBacktracker.html#method.max_haystack_len),
+    /// then an error is returned.
+    pub fn is_match(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+    ) -> ::std::result::Result<bool, SearchError> {
+        self.find(cache, bytes).map(|m| m.is_some())
+    }
+
+    /// Returns the start and end offsets of the leftmost first match. If no
+    /// match exists, then `None` is returned.
+    ///
+    /// If the input is longer than
+    /// [`max_haystack_len`](struct.BoundedBacktracker.html#method.max_haystack_len),
+    /// then an error is returned.
+    pub fn find(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+    ) -> ::std::result::Result<Option<Match>, SearchError> {
+        self.find_at(cache, bytes, 0)
+    }
+
+    /// Returns the same as `find`, but starts the search at the given
+    /// offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the bounded backtracker is
+    /// anchored, then a match can only occur when `start == 0`.
+    ///
+    /// Note that the whole input counts towards the memory budget, even
+    /// when the search starts after its beginning.
+    pub fn find_at(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+        start: usize,
+    ) -> ::std::result::Result<Option<Match>, SearchError> {
+        let max = self.max_haystack_len();
+        if bytes.len() > max {
+            return Err(SearchError::InputTooLong { max, got: bytes.len() });
+        }
+        if self.is_anchored() && start > 0 {
+            return Ok(None);
+        }
+
+        cache.reset(self.nfa.len(), bytes.len());
+        let nfa_start = self.nfa.start_anchored();
+        if self.is_anchored() {
+            return Ok(self.step(cache, bytes, nfa_start, start));
+        }
+        // States that were visited from an earlier starting position but
+        // didn't lead to a match can't lead to a match from a later starting
+        // position either, so the set of visited states is never cleared.
+        for at in start..=bytes.len() {
+            if let Some(m) = self.step(cache, bytes, nfa_start, at) {
+                return Ok(Some(m));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Search for a match beginning at `start` by walking the NFA depth first
+    /// from the given NFA state, and return its bounds if one is found.
+    fn step(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+        nfa_start: nfa::StateID,
+        start: usize,
+    ) -> Option<Match> {
+        cache.stack.push((nfa_start, start));
+        while let Some((mut id, mut at)) = cache.stack.pop() {
+            loop {
+                if !cache.insert(id, at, bytes.len()) {
+                    break;
+                }
+                match *self.nfa.state(id) {
+                    nfa::State::Range { ref range } => match bytes.get(at) {
+                        Some(&b) if range.start <= b && b <= range.end => {
+                            id = range.next;
+                            at += 1;
+                        }
+                        _ => break,
+                    },
+                    nfa::State::Sparse { ref ranges } => {
+                        let b = match bytes.get(at) {
+                            None => break,
+                            Some(&b) => b,
+                        };
+                        match ranges
+                            .iter()
+                            .find(|r| r.start <= b && b <= r.end)
+                        {
+                            None => break,
+                            Some(r) => {
+                                id = r.next;
+                                at += 1;
+                            }
+                        }
+                    }
+                    nfa::State::Union { ref alternates } => {
+                        id = match alternates.first() {
+                            None => break,
+                            Some(&id) => id,
+                        };
+                        cache.stack.extend(
+                            alternates[1..].iter().rev().map(|&alt| (alt, at)),
+                        );
+                    }
+                    nfa::State::Look { look, next } => {
+                        if !look.is_match(bytes, at) {
+                            break;
+                        }
+                        id = next;
+                    }
+                    nfa::State::Fail => break,
+                    nfa::State::Match { .. } => {
+                        cache.stack.clear();
+                        return Some(Match::new(start, at));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Scratch space used by a bounded backtracker during a search.
+///
+/// A cache is created for a specific bounded backtracker with
+/// [`BoundedBacktracker::new_cache`](struct.BoundedBacktracker.html#method.new_cache),
+/// and must only be used with it. Reusing the same cache for many searches
+/// amortizes the cost of allocating the set of visited states.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    /// A bit set recording each pair of NFA state and input position
+    /// visited during the current search.
+    visited: Vec<u64>,
+    /// A stack of NFA states and input positions left to explore.
+    stack: Vec<(nfa::StateID, usize)>,
+}
+
+impl Cache {
+    /// Returns the number of bytes of heap memory used by this cache.
+    pub fn memory_usage(&self) -> usize {
+        self.visited.capacity() * size_of::<u64>()
+            + self.stack.capacity() * size_of::<(nfa::StateID, usize)>()
+    }
+
+    /// Prepare this cache for a search of an input with the given length
+    /// with an NFA with the given number of states.
+    fn reset(&mut self, nfa_len: usize, haystack_len: usize) {
+        let bits = nfa_len * (haystack_len + 1);
+        let blocks = bits / BLOCK_BITS + 1;
+        self.visited.clear();
+        self.visited.resize(blocks, 0);
+        self.stack.clear();
+    }
+
+    /// Record that the given NFA state was visited at the given position,
+    /// and return true if and only if it wasn't visited before.
+    fn insert(
+        &mut self,
+        id: nfa::StateID,
+        at: usize,
+        haystack_len: usize,
+    ) -> bool {
+        let bit = id * (haystack_len + 1) + at;
+        let (block, mask) = (bit / BLOCK_BITS, 1 << (bit % BLOCK_BITS));
+        if self.visited[block] & mask != 0 {
+            return false;
+        }
+        self.visited[block] |= mask;
+        true
+    }
+}
+
+/// A builder for constructing a bounded backtracker.
+///
+/// This builder permits configuring the syntax of the regular expression,
+/// whether matches must be anchored and how much memory the set of visited
+/// states may use.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    dfa: dense::Builder,
+    visited_capacity: usize,
+}
+
+impl Builder {
+    /// Create a new bounded backtracker builder with the default
+    /// configuration.
+    pub fn new() -> Builder {
+        Builder {
+            dfa: dense::Builder::new(),
+            visited_capacity: DEFAULT_VISITED_CAPACITY,
+        }
+    }
+
+    /// Build a bounded backtracker from the given pattern.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    pub fn build(&self, pattern: &str) -> Result<BoundedBacktracker> {
+        let nfa = self.dfa.build_nfa(pattern)?;
+        Ok(self.build_from_nfa(&nfa))
+    }
+
+    /// Build a bounded backtracker from the given NFA.
+    pub fn build_from_nfa(&self, nfa: &NFA) -> BoundedBacktracker {
+        BoundedBacktracker {
+            nfa: nfa.clone(),
+            visited_capacity: self.visited_capacity,
+        }
+    }
+
+    /// Set the maximum number of bytes that the set of visited states may
+    /// use during a search.
+    ///
+    /// Since one bit is needed for each NFA state at each position in the
+    /// input, this bounds the length of the inputs that can be searched.
+    ///
+    /// By default, this is 256KB.
+    pub fn visited_capacity(&mut self, bytes: usize) -> &mut Builder {
+        self.visited_capacity = bytes;
+        self
+    }
+
+    /// Set whether matching must be anchored at the beginning of the input.
+    ///
+    /// When enabled, a match must begin at the start of the input. When
+    /// disabled, a match may begin anywhere in the input.
+    ///
+    /// By default this is disabled.
+    pub fn anchored(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.anchored(yes);
+        self
+    }
+
+    /// Enable or disable the case insensitive flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `i` flag.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.case_insensitive(yes);
+        self
+    }
+
+    /// Enable or disable the "dot matches any character" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `s` flag.
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.dot_matches_new_line(yes);
+        self
+    }
+
+    /// Enable or disable the "multi line" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `m` flag.
+    pub fn multi_line(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.multi_line(yes);
+        self
+    }
+
+    /// Enable or disable CRLF mode for the line anchors, which makes `^`
+    /// and `$` in multi-line mode treat `\r\n` as a line terminator.
+    ///
+    /// This is disabled by default.
+    pub fn crlf(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.crlf(yes);
+        self
+    }
+
+    /// Enable or disable the Unicode flag (`u`) by default.
+    ///
+    /// By default this is **enabled**. It may alternatively be selectively
+    /// disabled in the regular expression itself via the `u` flag.
+    pub fn unicode(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.unicode(yes);
+        self
+    }
+
+    /// When enabled, the builder will permit the construction of a bounded
+    /// backtracker that may match invalid UTF-8.
+    ///
+    /// By default this is disabled.
+    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.allow_invalid_utf8(yes);
+        self
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::RegexBuilder;
+
+    const PATTERNS: &[&str] = &[
+        r"a",
+        r"[a-z]+[0-9]",
+        r"foo|foobar",
+        r"(?i)Σ+\w+",
+        r"[0-9]{2,4}-[0-9]{2}",
+        r"",
+        r"a*",
+        r"(a|ab)(c|bcd)",
+        r"\pL{3}",
+        r"(?-u:\b)[a-z]+(?-u:\b)",
+        r"^a|b$",
+        r"(?m)^[a-z]+$",
+    ];
+
+    const HAYSTACKS: &[&[u8]] = &[
+        b"",
+        b"a",
+        b"xyz abc9 foobar",
+        b"12-345-6789-01",
+        b"abcd",
+        b"\xCE\xA3\xCF\x83x \xCE\xB1\xCE\xB2\xCE\xB3",
+        b"bbbbaaaa",
+        b"foo foobar xfoo",
+        b"ab\nba\n",
+    ];
+
+    #[test]
+    fn agrees_with_regex() {
+        for &anchored in &[false, true] {
+            for &pattern in PATTERNS {
+                let re = RegexBuilder::new()
+                    .anchored(anchored)
+                    .build(pattern)
+                    .unwrap();
+                let bt =
+                    Builder::new().anchored(anchored).build(pattern).unwrap();
+                let mut cache = bt.new_cache();
+                for &haystack in HAYSTACKS {
+                    for start in 0..haystack.len() + 1 {
+                        // A DFA's unanchored prefix only matches valid
+                        // UTF-8, so it never finds a match when it starts
+                        // in the middle of a codepoint.
+                        let b = haystack.get(start).cloned().unwrap_or(0);
+                        if (0x80..=0xBF).contains(&b) {
+                            continue;
+                        }
+                        assert_eq!(
+                            Ok(re.find_at(haystack, start)),
+                            bt.find_at(&mut cache, haystack, start),
+                            "find_at for {:?} at {} on {:?}",
+                            pattern,
+                            start,
+                            haystack,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn crlf_agrees_with_regex() {
+        let haystacks: &[&[u8]] =
+            &[b"a\r\nb", b"\r\n\r\n", b"\n\r", b"a\rb\n"];
+        for &pattern in &[r"^", r"$", r"^$", r"(?s:.)$", r"^(?s:.)"] {
+            let re = RegexBuilder::new()
+                .multi_line(true)
+                .crlf(true)
+                .build(pattern)
+                .unwrap();
+            let bt = Builder::new()
+                .multi_line(true)
+                .crlf(true)
+                .build(pattern)
+                .unwrap();
+            let mut cache = bt.new_cache();
+            for &haystack in haystacks {
+                for start in 0..haystack.len() + 1 {
+                    assert_eq!(
+                        Ok(re.find_at(haystack, start)),
+                        bt.find_at(&mut cache, haystack, start),
+                        "find_at for {:?} at {} on {:?}",
+                        pattern,
+                        start,
+                        haystack,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_exponential_blowup() {
+        let bt = BoundedBacktracker::new(r"(a*)*b").unwrap();
+        let mut cache = bt.new_cache();
+        let haystack = vec![b'a'; 1000];
+        assert_eq!(Ok(false), bt.is_match(&mut cache, &haystack));
+    }
+
+    #[test]
+    fn budget_exceeded() {
+        let bt = Builder::new().visited_capacity(0).build("a").unwrap();
+        let mut cache = bt.new_cache();
+        assert_eq!(0, bt.max_haystack_len());
+        assert_eq!(Ok(None), bt.find(&mut cache, b""));
+        assert_eq!(
+            Err(SearchError::InputTooLong { max: 0, got: 1 }),
+            bt.find(&mut cache, b"a"),
+        );
+    }
+}
@@ -11,6 +11,7 @@ pub trait ByteOrder {
     fn write_uint(buf: &mut [u8], n: u64, nbytes: usize);
 }
 
+#[cfg(feature = "std")]
 pub enum BigEndian {}
 pub enum LittleEndian {}
 pub enum NativeEndian {}
@@ -63,6 +64,7 @@ macro_rules! impl_endian {
     };
 }
 
+#[cfg(feature = "std")]
 impl_endian! {
     BigEndian, from_be_bytes, to_be_bytes
 }
@@ -0,0 +1,1202 @@
+use core::mem;
+#[cfg(feature = "std")]
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "std")]
+use core::{fmt, slice};
+
+use accel::ACCELS_LEN;
+#[cfg(feature = "std")]
+use accel::MAX_ACCELS;
+use byteorder::{ByteOrder, LittleEndian, NativeEndian};
+use classes::ByteClasses;
+use dense::{
+    MASK_ANCHORED, MASK_ANCHORED_START, MASK_CHECKSUM, MASK_COMPACT,
+    MASK_KNOWN_REQUIRED, MASK_LOOKAROUND, MASK_PATTERN_STARTS,
+    MASK_PATTERN_TAGS, MASK_PREMULTIPLIED, MASK_REQUIRED, MASK_UTF8,
+};
+use dfa::SearchState;
+use error::DeserializeError;
+use state_id::StateID;
+
+/// The version of the format used to serialize DFAs.
+///
+/// This version is written to the header of every serialized dense and
+/// sparse DFA, and deserialization only accepts DFAs serialized with this
+/// exact version. It is incremented whenever the serialization format
+/// changes in an incompatible way.
+///
+/// Features that don't change the layout of a DFA are instead recorded in a
+/// word of flags in the header. A reader rejects a DFA that uses a required
+/// feature it doesn't know with
+/// [`DeserializeError::UnsupportedFeatures`](enum.DeserializeError.html#variant.UnsupportedFeatures),
+/// but ignores optional features it doesn't know, so neither kind requires
+/// a new version.
+pub const FORMAT_VERSION: u16 = 8;
+
+/// The label at the beginning of every serialized dense DFA.
+const DENSE_LABEL: &[u8] = b"rust-regex-automata-dfa\x00";
+
+/// The label at the beginning of every serialized sparse DFA.
+const SPARSE_LABEL: &[u8] = b"rust-regex-automata-sparse-dfa\x00";
+
+/// The number of bytes in a header following its label: the endianness
+/// check, version, state ID size, options, start state, anchored start
+/// state, state count, max match state, pattern count, length of the table of
+/// matching patterns, byte class map, set of quit bytes, accelerated
+/// states and checksum.
+const HEADER_FIELDS_LEN: usize =
+    2 + 2 + 2 + 2 + 8 + 8 + 8 + 8 + 8 + 8 + 256 + 32 + ACCELS_LEN + 8;
+
+/// The CRC-32 of each 4-bit value, used to compute checksums a nibble at a
+/// time. This uses the reflected IEEE polynomial `0xEDB88320`.
+const CRC32_NIBBLES: [u32; 16] = [
+    0x0000_0000,
+    0x1DB7_1064,
+    0x3B6E_20C8,
+    0x26D9_30AC,
+    0x76DC_4190,
+    0x6B6B_51F4,
+    0x4DB2_6158,
+    0x5005_713C,
+    0xEDB8_8320,
+    0xF00F_9344,
+    0xD6D6_A3E8,
+    0xCB61_B38C,
+    0x9B64_C2B0,
+    0x86D3_D2D4,
+    0xA00A_E278,
+    0xBDBD_F21C,
+];
+
+/// The label at the beginning of every archive.
+const ARCHIVE_LABEL: &[u8] = b"rust-regex-automata-archive\x00";
+
+/// The version of the format used to write archives.
+const ARCHIVE_VERSION: u16 = 1;
+
+/// The number of bytes in the header of an archive: its label (28 bytes),
+/// version, two reserved bytes, the number of entries and four bytes of
+/// padding, so that the index that follows is aligned to 8 bytes.
+const ARCHIVE_HEADER_LEN: usize = 28 + 2 + 2 + 4 + 4;
+
+/// The number of bytes in each entry of the index of an archive: the offset
+/// and length of its name, followed by the offset and length of its
+/// contents. Offsets are relative to the start of the archive.
+const ARCHIVE_ENTRY_LEN: usize = 4 + 4 + 8 + 8;
+
+/// The label at the beginning of every serialized search state.
+const SEARCH_STATE_LABEL: &[u8] = b"rust-regex-automata-search-state\x00";
+
+/// The version of the format used to serialize search states.
+const SEARCH_STATE_VERSION: u16 = 1;
+
+/// The number of bytes in a serialized search state: its label (33 bytes),
+/// version, state ID size, flags, current state, offset and the end of the
+/// last match.
+///
+/// This is the length of the array returned by
+/// [`SearchState::to_bytes`](struct.SearchState.html#method.to_bytes).
+pub const SEARCH_STATE_LEN: usize = 33 + 2 + 2 + 2 + 8 + 8 + 8;
+
+/// Set in the flags of a serialized search state when the search has begun.
+const SEARCH_STARTED: u16 = 0b001;
+/// Set in the flags of a serialized search state when a match was seen.
+const SEARCH_MATCHED: u16 = 0b010;
+/// Set in the flags of a serialized search state when the search is done.
+const SEARCH_DONE: u16 = 0b100;
+
+/// A summary of a serialized DFA, as read from its header by
+/// [`peek`](fn.peek.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DfaHeaderInfo {
+    sparse: bool,
+    state_id_size: usize,
+    premultiplied: bool,
+    anchored: bool,
+    anchored_start: bool,
+    pattern_starts: bool,
+    pattern_tags: bool,
+    lookaround: bool,
+    utf8: bool,
+    compact: bool,
+    checksum: Option<u32>,
+    state_count: usize,
+    pattern_count: usize,
+    match_table_len: usize,
+    alphabet_len: usize,
+    header_len: usize,
+    start: u64,
+    start_anchored: u64,
+    max_match: u64,
+}
+
+impl DfaHeaderInfo {
+    /// Returns true if and only if the bytes contain a sparse DFA. Otherwise,
+    /// they contain a dense DFA.
+    pub fn is_sparse(&self) -> bool {
+        self.sparse
+    }
+
+    /// Returns the size, in bytes, of the state identifier representation
+    /// used by the DFA. This is always 1, 2, 4 or 8.
+    pub fn state_id_size(&self) -> usize {
+        self.state_id_size
+    }
+
+    /// Returns true if and only if the state identifiers of the DFA are
+    /// premultiplied. This is always false for sparse DFAs.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    /// Returns true if and only if the DFA is anchored.
+    pub fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    /// Returns true if and only if the DFA has a separate start state for
+    /// anchored searches. This is always false for anchored DFAs, whose
+    /// only start state is already anchored.
+    pub fn has_anchored_start_state(&self) -> bool {
+        self.anchored_start
+    }
+
+    /// Returns true if and only if the DFA has a start state for anchored
+    /// searches of each pattern, as built with
+    /// [`dense::Builder::starts_for_each_pattern`](dense/struct.Builder.html#method.starts_for_each_pattern).
+    /// This is always false for sparse DFAs.
+    pub fn has_pattern_starts(&self) -> bool {
+        self.pattern_starts
+    }
+
+    /// Returns true if and only if the DFA has a tag for each pattern, as
+    /// built with
+    /// [`dense::Builder::build_many_tagged`](dense/struct.Builder.html#method.build_many_tagged).
+    /// This is always false for sparse DFAs.
+    pub fn has_pattern_tags(&self) -> bool {
+        self.pattern_tags
+    }
+
+    /// Returns true if and only if the DFA evaluates look-around
+    /// assertions, such as word boundaries. This is always false for sparse
+    /// DFAs.
+    pub fn has_lookaround(&self) -> bool {
+        self.lookaround
+    }
+
+    /// Returns true if and only if the DFA was built in UTF-8 mode, in which
+    /// case every match it reports on valid UTF-8 begins and ends at a
+    /// codepoint boundary.
+    pub fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    /// Returns true if and only if the DFA is a sparse DFA serialized in the
+    /// compact format, as written by
+    /// [`SparseDFA::to_bytes_compact`](enum.SparseDFA.html#method.to_bytes_compact).
+    /// Such a DFA can only be deserialized with
+    /// [`SparseDFA::from_bytes_compact`](enum.SparseDFA.html#method.from_bytes_compact).
+    pub fn is_compact(&self) -> bool {
+        self.compact
+    }
+
+    /// Returns the CRC-32 checksum of the serialized DFA recorded in its
+    /// header, if there is one.
+    ///
+    /// Dense DFAs always record a checksum, which is verified by
+    /// [`DenseDFA::try_from_bytes`](enum.DenseDFA.html#method.try_from_bytes).
+    /// Sparse DFAs never do.
+    pub fn checksum(&self) -> Option<u32> {
+        self.checksum
+    }
+
+    /// Returns the total number of states in the DFA, including the dead
+    /// state.
+    pub fn state_count(&self) -> usize {
+        self.state_count
+    }
+
+    /// Returns the total number of patterns that the DFA was built from.
+    pub fn pattern_count(&self) -> usize {
+        self.pattern_count
+    }
+
+    /// Returns the number of transitions of each state of the DFA. This is
+    /// the number of equivalence classes of bytes used by the DFA, plus one
+    /// for the end of input when the DFA has look-around. If byte classes
+    /// are disabled, then this is always `256`.
+    pub fn alphabet_len(&self) -> usize {
+        self.alphabet_len
+    }
+
+    /// Returns the number of bytes occupied by the header of the DFA, which
+    /// precedes its transition table.
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Returns the total number of bytes occupied by the serialized DFA.
+    ///
+    /// The size of a sparse DFA's transition table cannot be determined
+    /// from its header alone, so this always returns `None` for sparse DFAs.
+    pub fn serialized_len(&self) -> Option<usize> {
+        if self.sparse {
+            return None;
+        }
+        let trans_len =
+            self.state_count * self.alphabet_len * self.state_id_size;
+        let matches_len = self.match_table_len * self.state_id_size;
+        Some(self.header_len + trans_len + matches_len)
+    }
+
+    /// Returns the number of state identifiers in the table recording which
+    /// patterns match in each match state, which follows the transition
+    /// table.
+    pub(crate) fn match_table_len(&self) -> usize {
+        self.match_table_len
+    }
+
+    /// Returns the raw start state identifier recorded in the header.
+    pub(crate) fn start_state(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the raw anchored start state identifier recorded in the
+    /// header. This is zero when the DFA has no anchored start state.
+    pub(crate) fn anchored_start_state(&self) -> u64 {
+        self.start_anchored
+    }
+
+    /// Returns the raw identifier of the last match state recorded in the
+    /// header.
+    pub(crate) fn max_match_state(&self) -> u64 {
+        self.max_match
+    }
+}
+
+/// Read the header of a serialized dense or sparse DFA, without
+/// deserializing the DFA itself.
+///
+/// The bytes given should start with a DFA serialized in native endian
+/// format, e.g., with
+/// [`DenseDFA::to_bytes_native_endian`](enum.DenseDFA.html#method.to_bytes_native_endian)
+/// or
+/// [`SparseDFA::to_bytes_native_endian`](enum.SparseDFA.html#method.to_bytes_native_endian).
+/// Only the header is read, so this takes constant time regardless of the
+/// size of the DFA, and the bytes given need not be aligned. This makes it
+/// cheap to inspect many DFAs, for example, to check which state identifier
+/// representation to deserialize each one with.
+///
+/// Note that the header doesn't describe the transition table, so a
+/// successful peek doesn't guarantee that the rest of the bytes contain a
+/// valid DFA.
+///
+/// If the bytes don't start with a valid header, then an error is returned.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{peek, DenseDFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = DenseDFA::new("foo[0-9]+")?.to_u16()?;
+/// let bytes = dfa.to_bytes_native_endian()?;
+///
+/// let info = peek(&bytes).unwrap();
+/// assert!(!info.is_sparse());
+/// assert_eq!(2, info.state_id_size());
+/// assert_eq!(dfa.state_count(), info.state_count());
+/// assert_eq!(Some(bytes.len()), info.serialized_len());
+/// # Ok(()) }; example().unwrap()
+/// ```
+pub fn peek(buf: &[u8]) -> Result<DfaHeaderInfo, DeserializeError> {
+    let (sparse, label_len) = if buf.starts_with(DENSE_LABEL) {
+        (false, DENSE_LABEL.len())
+    } else if buf.starts_with(SPARSE_LABEL) {
+        (true, SPARSE_LABEL.len())
+    } else {
+        return Err(DeserializeError::InvalidLabel);
+    };
+    let header_len = label_len + HEADER_FIELDS_LEN;
+    if buf.len() < header_len {
+        return Err(DeserializeError::BufferTooSmall {
+            needed: header_len,
+            got: buf.len(),
+        });
+    }
+    let buf = &buf[label_len..];
+
+    let endian_check = NativeEndian::read_u16(buf);
+    if endian_check != 0xFEFF {
+        return Err(DeserializeError::EndiannessMismatch);
+    }
+    let version = NativeEndian::read_u16(&buf[2..]);
+    if version != FORMAT_VERSION {
+        return Err(DeserializeError::UnsupportedVersion {
+            expected: FORMAT_VERSION,
+            found: version,
+        });
+    }
+    let state_id_size = NativeEndian::read_u16(&buf[4..]) as usize;
+    if ![1, 2, 4, 8].contains(&state_id_size) {
+        return Err(DeserializeError::InvalidStateIDSize {
+            size: state_id_size,
+        });
+    }
+    let opts = NativeEndian::read_u16(&buf[6..]);
+    let unknown = opts & MASK_REQUIRED & !MASK_KNOWN_REQUIRED;
+    if unknown != 0 {
+        return Err(DeserializeError::UnsupportedFeatures { flags: unknown });
+    }
+    if !sparse && opts & MASK_COMPACT > 0 {
+        return Err(DeserializeError::UnsupportedFeatures {
+            flags: MASK_COMPACT,
+        });
+    }
+    if sparse && opts & MASK_PATTERN_STARTS > 0 {
+        return Err(DeserializeError::UnsupportedFeatures {
+            flags: MASK_PATTERN_STARTS,
+        });
+    }
+    if sparse && opts & MASK_PATTERN_TAGS > 0 {
+        return Err(DeserializeError::UnsupportedFeatures {
+            flags: MASK_PATTERN_TAGS,
+        });
+    }
+    let start = NativeEndian::read_u64(&buf[8..]);
+    let start_anchored = NativeEndian::read_u64(&buf[16..]);
+    let state_count = NativeEndian::read_u64(&buf[24..]) as usize;
+    let max_match = NativeEndian::read_u64(&buf[32..]);
+    let pattern_count = NativeEndian::read_u64(&buf[40..]) as usize;
+    let match_table_len = NativeEndian::read_u64(&buf[48..]) as usize;
+    let byte_classes = ByteClasses::from_slice(&buf[56..56 + 256]);
+    let checksum = NativeEndian::read_u64(&buf[HEADER_FIELDS_LEN - 8..]);
+    Ok(DfaHeaderInfo {
+        sparse,
+        state_id_size,
+        premultiplied: opts & MASK_PREMULTIPLIED > 0,
+        anchored: opts & MASK_ANCHORED > 0,
+        anchored_start: opts & MASK_ANCHORED_START > 0,
+        pattern_starts: opts & MASK_PATTERN_STARTS > 0,
+        pattern_tags: opts & MASK_PATTERN_TAGS > 0,
+        lookaround: opts & MASK_LOOKAROUND > 0,
+        utf8: opts & MASK_UTF8 > 0,
+        compact: opts & MASK_COMPACT > 0,
+        checksum: if opts & MASK_CHECKSUM > 0 {
+            Some(checksum as u32)
+        } else {
+            None
+        },
+        state_count,
+        pattern_count,
+        match_table_len,
+        alphabet_len: byte_classes.alphabet_len()
+            + (opts & MASK_LOOKAROUND > 0) as usize,
+        header_len,
+        start,
+        start_anchored,
+        max_match,
+    })
+}
+
+/// Update the CRC-32 checksum `crc` of some bytes with the bytes that
+/// follow them. The checksum of no bytes is `0`.
+pub(crate) fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &b in bytes {
+        let b = b as u32;
+        crc = CRC32_NIBBLES[((crc ^ b) & 0xF) as usize] ^ (crc >> 4);
+        crc = CRC32_NIBBLES[((crc ^ (b >> 4)) & 0xF) as usize] ^ (crc >> 4);
+    }
+    !crc
+}
+
+/// Compute the checksum of a serialized DFA whose checksum field starts at
+/// the given offset. The checksum field itself is treated as zero.
+pub(crate) fn dfa_checksum(buf: &[u8], at: usize) -> u32 {
+    let crc = crc32(0, &buf[..at]);
+    let crc = crc32(crc, &[0; 8]);
+    crc32(crc, &buf[at + 8..])
+}
+
+/// Convert a dense DFA serialized in little endian format to native endian
+/// format.
+///
+/// The checksum, if any, is verified against the little endian bytes and then
+/// recomputed for the native endian bytes. Otherwise, the DFA isn't
+/// validated.
+#[cfg(feature = "std")]
+pub(crate) fn dense_to_native_endian(
+    buf: &[u8],
+) -> Result<AlignedBytes, DeserializeError> {
+    if !buf.starts_with(DENSE_LABEL) {
+        return Err(DeserializeError::InvalidLabel);
+    }
+    let label_len = DENSE_LABEL.len();
+    let header_len = label_len + HEADER_FIELDS_LEN;
+    if buf.len() < header_len {
+        return Err(DeserializeError::BufferTooSmall {
+            needed: header_len,
+            got: buf.len(),
+        });
+    }
+    let fields = &buf[label_len..];
+    if LittleEndian::read_u16(fields) != 0xFEFF {
+        return Err(DeserializeError::EndiannessMismatch);
+    }
+    let version = LittleEndian::read_u16(&fields[2..]);
+    if version != FORMAT_VERSION {
+        return Err(DeserializeError::UnsupportedVersion {
+            expected: FORMAT_VERSION,
+            found: version,
+        });
+    }
+    let state_id_size = LittleEndian::read_u16(&fields[4..]) as usize;
+    if ![1, 2, 4, 8].contains(&state_id_size) {
+        return Err(DeserializeError::InvalidStateIDSize {
+            size: state_id_size,
+        });
+    }
+    let opts = LittleEndian::read_u16(&fields[6..]);
+    let state_count = LittleEndian::read_u64(&fields[24..]) as usize;
+    let match_table_len = LittleEndian::read_u64(&fields[48..]) as usize;
+    let byte_classes = ByteClasses::from_slice(&fields[56..56 + 256]);
+    let alphabet_len =
+        byte_classes.alphabet_len() + (opts & MASK_LOOKAROUND > 0) as usize;
+    let needed = state_count
+        .saturating_mul(alphabet_len)
+        .saturating_add(match_table_len)
+        .saturating_mul(state_id_size)
+        .saturating_add(header_len);
+    if buf.len() < needed {
+        return Err(DeserializeError::BufferTooSmall {
+            needed,
+            got: buf.len(),
+        });
+    }
+    let buf = &buf[..needed];
+    let checksum_at = header_len - 8;
+    if opts & MASK_CHECKSUM > 0 {
+        let expected = LittleEndian::read_u64(&buf[checksum_at..]) as u32;
+        let found = dfa_checksum(buf, checksum_at);
+        if found != expected {
+            return Err(DeserializeError::ChecksumMismatch {
+                expected,
+                found,
+            });
+        }
+    }
+
+    let mut out = AlignedBytes::zeroed(needed);
+    out[..label_len].copy_from_slice(DENSE_LABEL);
+    let mut i = label_len;
+    // the endianness check, version, state ID size and flags
+    for _ in 0..4 {
+        to_native_endian(&buf[i..], &mut out[i..], 2);
+        i += 2;
+    }
+    // the start states, state count, max match state, pattern count and
+    // the length of the table of matching patterns
+    for _ in 0..6 {
+        to_native_endian(&buf[i..], &mut out[i..], 8);
+        i += 8;
+    }
+    // the byte class map and the set of quit bytes
+    out[i..i + 256 + 32].copy_from_slice(&buf[i..i + 256 + 32]);
+    i += 256 + 32;
+    // the number of accelerated states, followed by each state and its
+    // needles
+    to_native_endian(&buf[i..], &mut out[i..], 8);
+    i += 8;
+    for _ in 0..MAX_ACCELS {
+        to_native_endian(&buf[i..], &mut out[i..], 8);
+        out[i + 8..i + 16].copy_from_slice(&buf[i + 8..i + 16]);
+        i += 16;
+    }
+    // the checksum, which is recomputed below
+    i += 8;
+    // the transition table and the table of matching patterns
+    while i < needed {
+        to_native_endian(&buf[i..], &mut out[i..], state_id_size);
+        i += state_id_size;
+    }
+    if opts & MASK_CHECKSUM > 0 {
+        let checksum = dfa_checksum(&out, checksum_at);
+        NativeEndian::write_u64(&mut out[checksum_at..], checksum as u64);
+    }
+    Ok(out)
+}
+
+/// Convert a sparse DFA serialized in the compact format to the native
+/// endian format read by `SparseDFA::from_bytes`.
+///
+/// The header of a compact DFA is in little endian format. Its transition
+/// table follows, where each state is written as its number of transitions
+/// as a varint, then the input range of each transition as two bytes, and
+/// then the next state of each transition as a zig-zag encoded varint. The
+/// next state is recorded as the difference between its index and the index
+/// of the current state, where states are indexed in the order they appear.
+/// Since the expanded transition table lays out states in the same order,
+/// the state identifiers in the header refer to the expanded table.
+///
+/// Every transition and every state identifier in the header is checked to
+/// refer to a state. The accelerated states aren't otherwise validated.
+#[cfg(feature = "std")]
+pub(crate) fn sparse_compact_to_native_endian(
+    buf: &[u8],
+) -> Result<AlignedBytes, DeserializeError> {
+    if !buf.starts_with(SPARSE_LABEL) {
+        return Err(DeserializeError::InvalidLabel);
+    }
+    let label_len = SPARSE_LABEL.len();
+    let header_len = label_len + HEADER_FIELDS_LEN;
+    if buf.len() < header_len {
+        return Err(DeserializeError::BufferTooSmall {
+            needed: header_len,
+            got: buf.len(),
+        });
+    }
+    let fields = &buf[label_len..];
+    if LittleEndian::read_u16(fields) != 0xFEFF {
+        return Err(DeserializeError::EndiannessMismatch);
+    }
+    let version = LittleEndian::read_u16(&fields[2..]);
+    if version != FORMAT_VERSION {
+        return Err(DeserializeError::UnsupportedVersion {
+            expected: FORMAT_VERSION,
+            found: version,
+        });
+    }
+    let state_id_size = LittleEndian::read_u16(&fields[4..]) as usize;
+    if ![1, 2, 4, 8].contains(&state_id_size) {
+        return Err(DeserializeError::InvalidStateIDSize {
+            size: state_id_size,
+        });
+    }
+    let opts = LittleEndian::read_u16(&fields[6..]);
+    let unknown = opts & MASK_REQUIRED & !MASK_KNOWN_REQUIRED;
+    if unknown != 0 {
+        return Err(DeserializeError::UnsupportedFeatures { flags: unknown });
+    }
+    if opts & MASK_COMPACT == 0 {
+        return Err(DeserializeError::InvalidCompactTable);
+    }
+    let state_count = LittleEndian::read_u64(&fields[24..]);
+
+    // Decode every state first, since the identifier of a state in the
+    // expanded table depends on the size of every state before it.
+    let mut states = vec![];
+    let mut nexts = vec![];
+    let mut i = header_len;
+    for index in 0..state_count {
+        let (ntrans, nread) = read_varu64(&buf[i..]);
+        if nread == 0 || ntrans > 256 {
+            return Err(DeserializeError::InvalidCompactTable);
+        }
+        i += nread;
+        let ntrans = ntrans as usize;
+        if buf.len() - i < ntrans * 2 {
+            return Err(DeserializeError::InvalidCompactTable);
+        }
+        states.push((ntrans, &buf[i..i + ntrans * 2]));
+        i += ntrans * 2;
+        for _ in 0..ntrans {
+            let (delta, nread) = read_vari64(&buf[i..]);
+            if nread == 0 {
+                return Err(DeserializeError::InvalidCompactTable);
+            }
+            i += nread;
+            let next = (index as i64).wrapping_add(delta) as u64;
+            if next >= state_count {
+                return Err(DeserializeError::InvalidStateID { id: next });
+            }
+            nexts.push(next as usize);
+        }
+    }
+    let mut ids = Vec::with_capacity(states.len());
+    let mut trans_len = 0usize;
+    for &(ntrans, _) in &states {
+        ids.push(trans_len as u64);
+        trans_len += 2 + ntrans * 2 + ntrans * state_id_size;
+    }
+    if state_id_size < 8 && trans_len as u64 > 1 << (8 * state_id_size) {
+        return Err(DeserializeError::InvalidStateID { id: trans_len as u64 });
+    }
+    let is_valid = |id: u64| ids.binary_search(&id).is_ok();
+
+    let mut out = AlignedBytes::zeroed(header_len + trans_len);
+    out[..label_len].copy_from_slice(SPARSE_LABEL);
+    let mut i = label_len;
+    // the endianness check, version, state ID size and flags, where the
+    // compact flag is cleared since the expanded table isn't compact
+    for _ in 0..4 {
+        to_native_endian(&buf[i..], &mut out[i..], 2);
+        i += 2;
+    }
+    NativeEndian::write_u16(&mut out[i - 2..], opts & !MASK_COMPACT);
+    // the start states, state count, max match state, pattern count and
+    // the length of the table of matching patterns
+    for _ in 0..6 {
+        to_native_endian(&buf[i..], &mut out[i..], 8);
+        i += 8;
+    }
+    let start = LittleEndian::read_u64(&fields[8..]);
+    let max_match = LittleEndian::read_u64(&fields[32..]);
+    for &id in &[start, max_match] {
+        if !is_valid(id) {
+            return Err(DeserializeError::InvalidStateID { id });
+        }
+    }
+    // the byte class map and the set of quit bytes
+    out[i..i + 256 + 32].copy_from_slice(&buf[i..i + 256 + 32]);
+    i += 256 + 32;
+    // the number of accelerated states, followed by each state and its
+    // needles
+    let accels_len = LittleEndian::read_u64(&buf[i..]);
+    if accels_len > MAX_ACCELS as u64 {
+        return Err(DeserializeError::InvalidAccelerators);
+    }
+    to_native_endian(&buf[i..], &mut out[i..], 8);
+    i += 8;
+    for j in 0..MAX_ACCELS {
+        let id = LittleEndian::read_u64(&buf[i..]);
+        if (j as u64) < accels_len && !is_valid(id) {
+            return Err(DeserializeError::InvalidStateID { id });
+        }
+        to_native_endian(&buf[i..], &mut out[i..], 8);
+        out[i + 8..i + 16].copy_from_slice(&buf[i + 8..i + 16]);
+        i += 16;
+    }
+    // the checksum, which sparse DFAs don't record
+    i += 8;
+    // the transition table
+    let mut nexts = nexts.into_iter();
+    for (ntrans, ranges) in states {
+        NativeEndian::write_u16(&mut out[i..], ntrans as u16);
+        i += 2;
+        out[i..i + ranges.len()].copy_from_slice(ranges);
+        i += ranges.len();
+        for next in nexts.by_ref().take(ntrans) {
+            let id = ids[next];
+            match state_id_size {
+                1 => out[i] = id as u8,
+                2 => NativeEndian::write_u16(&mut out[i..], id as u16),
+                4 => NativeEndian::write_u32(&mut out[i..], id as u32),
+                8 => NativeEndian::write_u64(&mut out[i..], id),
+                _ => unreachable!(),
+            }
+            i += state_id_size;
+        }
+    }
+    assert_eq!(out.len(), i, "expected to fill entire buffer");
+    Ok(out)
+}
+
+/// Write an unsigned integer as a varint, in which each byte holds 7 bits of
+/// the integer, least significant first, and a set high bit indicates that
+/// more bytes follow.
+#[cfg(feature = "std")]
+pub(crate) fn write_varu64(data: &mut Vec<u8>, mut n: u64) {
+    while n >= 0b1000_0000 {
+        data.push((n as u8) | 0b1000_0000);
+        n >>= 7;
+    }
+    data.push(n as u8);
+}
+
+/// Read an unsigned integer written by `write_varu64`, returning it along
+/// with the number of bytes read. If the bytes don't start with a valid
+/// varint, then the number of bytes read is zero.
+#[cfg(feature = "std")]
+pub(crate) fn read_varu64(data: &[u8]) -> (u64, usize) {
+    let mut n: u64 = 0;
+    let mut shift: u32 = 0;
+    for (i, &b) in data.iter().enumerate() {
+        if shift >= 64 || (shift == 63 && b > 1) {
+            return (0, 0);
+        }
+        n |= ((b & 0b0111_1111) as u64) << shift;
+        if b < 0b1000_0000 {
+            return (n, i + 1);
+        }
+        shift += 7;
+    }
+    (0, 0)
+}
+
+/// Write a signed integer as a varint, after zig-zag encoding it so that
+/// integers with a small magnitude use few bytes regardless of their sign.
+#[cfg(feature = "std")]
+pub(crate) fn write_vari64(data: &mut Vec<u8>, n: i64) {
+    write_varu64(data, ((n << 1) ^ (n >> 63)) as u64);
+}
+
+/// Read a signed integer written by `write_vari64`, returning it along with
+/// the number of bytes read. If the bytes don't start with a valid varint,
+/// then the number of bytes read is zero.
+#[cfg(feature = "std")]
+pub(crate) fn read_vari64(data: &[u8]) -> (i64, usize) {
+    let (n, nread) = read_varu64(data);
+    (((n >> 1) as i64) ^ -((n & 1) as i64), nread)
+}
+
+/// Copy an integer of the given size, which must be 1, 2, 4 or 8, from
+/// little endian format in `src` to native endian format in `dst`.
+#[cfg(feature = "std")]
+fn to_native_endian(src: &[u8], dst: &mut [u8], size: usize) {
+    match size {
+        1 => dst[0] = src[0],
+        2 => NativeEndian::write_u16(dst, LittleEndian::read_u16(src)),
+        4 => NativeEndian::write_u32(dst, LittleEndian::read_u32(src)),
+        8 => NativeEndian::write_u64(dst, LittleEndian::read_u64(src)),
+        _ => unreachable!("invalid integer size {}", size),
+    }
+}
+
+/// An owned buffer of bytes that is always aligned to an 8 byte boundary.
+///
+/// This is returned by the routines that serialize dense DFAs, such as
+/// [`DenseDFA::to_bytes_native_endian`](enum.DenseDFA.html#method.to_bytes_native_endian).
+/// Since 8 bytes is at least the alignment of every state identifier
+/// representation, the bytes can always be deserialized in place, e.g.,
+/// with
+/// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes), without
+/// copying them or checking their alignment first. This isn't guaranteed
+/// for a `Vec<u8>`, whose alignment is only 1.
+///
+/// An `AlignedBytes` dereferences to a `[u8]`, and so it can be used
+/// wherever a byte slice is expected.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{AlignedBytes, DenseDFA, DFA};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// // Copy serialized bytes, e.g., as read from a file, to an aligned buffer.
+/// let unaligned: Vec<u8> = DenseDFA::new("foo[0-9]+")?
+///     .to_u32()?
+///     .to_bytes_native_endian()?
+///     .into_vec();
+/// let bytes = AlignedBytes::from_slice(&unaligned);
+/// let dfa: DenseDFA<&[u32], u32> = DenseDFA::try_from_bytes(&bytes)?;
+/// assert_eq!(Some(8), dfa.find(b"foo12345"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Default, Eq, Hash, PartialEq)]
+pub struct AlignedBytes {
+    /// The storage of the bytes. Any bytes beyond `len` are always zero.
+    words: Vec<u64>,
+    /// The number of bytes.
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl AlignedBytes {
+    /// Create an aligned buffer of `len` zero bytes.
+    pub fn zeroed(len: usize) -> AlignedBytes {
+        // Round up to whole words, since `len` may not be a multiple of 8.
+        AlignedBytes { words: vec![0; len / 8 + 1], len }
+    }
+
+    /// Create an aligned buffer holding a copy of the given bytes.
+    pub fn from_slice(bytes: &[u8]) -> AlignedBytes {
+        let mut aligned = AlignedBytes::zeroed(bytes.len());
+        aligned.copy_from_slice(bytes);
+        aligned
+    }
+
+    /// Copy these bytes to a `Vec<u8>`, which doesn't preserve their
+    /// alignment.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Deref for AlignedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: Every byte of a `u64` is a valid `u8`, and `len` is at
+        // most the number of bytes in `words`.
+        unsafe {
+            slice::from_raw_parts(self.words.as_ptr() as *const u8, self.len)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl DerefMut for AlignedBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: Every byte of a `u64` is a valid `u8`, and `len` is at
+        // most the number of bytes in `words`.
+        unsafe {
+            slice::from_raw_parts_mut(
+                self.words.as_mut_ptr() as *mut u8,
+                self.len,
+            )
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<[u8]> for AlignedBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for AlignedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A read-only view of a container of named byte strings, such as several
+/// serialized DFAs along with metadata about them.
+///
+/// An archive is written by an
+/// [`ArchiveBuilder`](struct.ArchiveBuilder.html). Its index and framing
+/// are always stored in little endian format, while the byte strings it
+/// contains are stored as given. Every byte string starts at an offset that
+/// is a multiple of 8 from the start of the archive, so when the archive is
+/// aligned to an 8 byte boundary, so is every byte string in it. This
+/// permits deserializing dense DFAs stored in an archive without copying
+/// them.
+///
+/// Opening an archive only validates its index, which takes time
+/// proportional to the number of entries. The contents of each entry are
+/// not validated.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{Archive, ArchiveBuilder, DenseDFA, DFA};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let dfa = DenseDFA::new("foo[0-9]+")?.to_u16()?;
+/// let bytes = ArchiveBuilder::new()
+///     .add("dfa", &dfa.to_bytes_native_endian()?)
+///     .add("version", b"1.2.3")
+///     .to_bytes();
+///
+/// let archive = Archive::from_bytes(&bytes)?;
+/// assert_eq!(Some(&b"1.2.3"[..]), archive.get("version"));
+/// let dfa: DenseDFA<&[u16], u16> =
+///     DenseDFA::try_from_bytes(archive.get("dfa").unwrap())?;
+/// assert_eq!(Some(8), dfa.find(b"foo12345"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Archive<'a> {
+    buf: &'a [u8],
+    len: usize,
+}
+
+impl<'a> Archive<'a> {
+    /// Open the archive at the beginning of the given bytes.
+    ///
+    /// If the bytes don't start with a valid archive, then an error is
+    /// returned.
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Archive<'a>, DeserializeError> {
+        if !buf.starts_with(ARCHIVE_LABEL) {
+            return Err(DeserializeError::InvalidLabel);
+        }
+        if buf.len() < ARCHIVE_HEADER_LEN {
+            return Err(DeserializeError::BufferTooSmall {
+                needed: ARCHIVE_HEADER_LEN,
+                got: buf.len(),
+            });
+        }
+        let fields = &buf[ARCHIVE_LABEL.len()..];
+        let version = LittleEndian::read_u16(fields);
+        if version != ARCHIVE_VERSION {
+            return Err(DeserializeError::UnsupportedVersion {
+                expected: ARCHIVE_VERSION,
+                found: version,
+            });
+        }
+        let len = LittleEndian::read_u32(&fields[4..]) as usize;
+        let needed =
+            len.saturating_mul(ARCHIVE_ENTRY_LEN) + ARCHIVE_HEADER_LEN;
+        if buf.len() < needed {
+            return Err(DeserializeError::BufferTooSmall {
+                needed,
+                got: buf.len(),
+            });
+        }
+        let archive = Archive { buf, len };
+        for i in 0..len {
+            let (name, contents) = archive.ranges(i);
+            let in_bounds = |(start, len): (u64, u64)| {
+                start
+                    .checked_add(len)
+                    .map(|end| end <= buf.len() as u64)
+                    .unwrap_or(false)
+            };
+            if !in_bounds(name) || !in_bounds(contents) || contents.0 % 8 > 0 {
+                return Err(DeserializeError::InvalidArchive);
+            }
+            let name = &buf[name.0 as usize..(name.0 + name.1) as usize];
+            if core::str::from_utf8(name).is_err() {
+                return Err(DeserializeError::InvalidArchive);
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Returns the number of entries in this archive.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if and only if this archive has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the contents of the entry with the given name, or `None` if
+    /// there is no such entry.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.iter().find(|&(n, _)| n == name).map(|(_, contents)| contents)
+    }
+
+    /// Returns an iterator over the name and contents of every entry in
+    /// this archive, in the order in which they were added.
+    pub fn iter(&self) -> ArchiveIter<'a> {
+        ArchiveIter { archive: *self, i: 0 }
+    }
+
+    /// Returns the start and length of the name and of the contents of the
+    /// entry at the given index, which must be less than `len`.
+    fn ranges(&self, i: usize) -> ((u64, u64), (u64, u64)) {
+        let entry = &self.buf[ARCHIVE_HEADER_LEN + i * ARCHIVE_ENTRY_LEN..];
+        let name = (
+            LittleEndian::read_u32(entry) as u64,
+            LittleEndian::read_u32(&entry[4..]) as u64,
+        );
+        let contents = (
+            LittleEndian::read_u64(&entry[8..]),
+            LittleEndian::read_u64(&entry[16..]),
+        );
+        (name, contents)
+    }
+}
+
+/// An iterator over the entries of an [`Archive`](struct.Archive.html).
+///
+/// This yields the name and contents of each entry. The lifetime `'a`
+/// refers to the bytes of the archive.
+#[derive(Clone, Debug)]
+pub struct ArchiveIter<'a> {
+    archive: Archive<'a>,
+    i: usize,
+}
+
+impl<'a> Iterator for ArchiveIter<'a> {
+    type Item = (&'a str, &'a [u8]);
+
+    fn next(&mut self) -> Option<(&'a str, &'a [u8])> {
+        if self.i >= self.archive.len {
+            return None;
+        }
+        let ((name_start, name_len), (start, len)) =
+            self.archive.ranges(self.i);
+        self.i += 1;
+        let buf = self.archive.buf;
+        let name = &buf[name_start as usize..(name_start + name_len) as usize];
+        // Names were checked to be valid UTF-8 when the archive was opened.
+        let name = core::str::from_utf8(name).unwrap();
+        Some((name, &buf[start as usize..(start + len) as usize]))
+    }
+}
+
+/// A builder for writing an [`Archive`](struct.Archive.html).
+///
+/// Entries are written in the order in which they are added.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct ArchiveBuilder {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+#[cfg(feature = "std")]
+impl ArchiveBuilder {
+    /// Create a new builder for an archive without any entries.
+    pub fn new() -> ArchiveBuilder {
+        ArchiveBuilder::default()
+    }
+
+    /// Add an entry with the given name and contents. If an entry with the
+    /// same name was already added, then its contents are replaced.
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> &mut ArchiveBuilder {
+        match self.entries.iter_mut().find(|(n, _)| n == name) {
+            Some(entry) => entry.1 = contents.to_vec(),
+            None => self.entries.push((name.to_string(), contents.to_vec())),
+        }
+        self
+    }
+
+    /// Write the archive to bytes, which can be read with
+    /// [`Archive::from_bytes`](struct.Archive.html#method.from_bytes).
+    ///
+    /// # Panics
+    ///
+    /// This panics if there are more than `u32::MAX` entries, or if the
+    /// names of the entries occupy more than `u32::MAX` bytes altogether.
+    pub fn to_bytes(&self) -> AlignedBytes {
+        let index_len = self.entries.len() * ARCHIVE_ENTRY_LEN;
+        let names_len: usize = self.entries.iter().map(|e| e.0.len()).sum();
+        let names_start = ARCHIVE_HEADER_LEN + index_len;
+        assert!(self.entries.len() <= !0u32 as usize, "too many entries");
+        assert!(names_start + names_len <= !0u32 as usize, "names too long");
+
+        let mut buf = vec![0; names_start];
+        buf[..ARCHIVE_LABEL.len()].copy_from_slice(ARCHIVE_LABEL);
+        let fields = &mut buf[ARCHIVE_LABEL.len()..];
+        LittleEndian::write_u16(fields, ARCHIVE_VERSION);
+        LittleEndian::write_u32(&mut fields[4..], self.entries.len() as u32);
+        for (name, _) in &self.entries {
+            buf.extend_from_slice(name.as_bytes());
+        }
+        let mut name_start = names_start;
+        for (i, (name, contents)) in self.entries.iter().enumerate() {
+            // Pad every entry's contents to an 8 byte boundary.
+            let padding = (8 - buf.len() % 8) % 8;
+            buf.resize(buf.len() + padding, 0);
+            let start = buf.len();
+            let entry = &mut buf[ARCHIVE_HEADER_LEN + i * ARCHIVE_ENTRY_LEN..];
+            LittleEndian::write_u32(entry, name_start as u32);
+            LittleEndian::write_u32(&mut entry[4..], name.len() as u32);
+            LittleEndian::write_u64(&mut entry[8..], start as u64);
+            LittleEndian::write_u64(&mut entry[16..], contents.len() as u64);
+            name_start += name.len();
+            buf.extend_from_slice(contents);
+        }
+        AlignedBytes::from_slice(&buf)
+    }
+}
+
+impl<S: StateID> SearchState<S> {
+    /// Serialize this search state to bytes.
+    ///
+    /// This permits checkpointing a search over a stream, and resuming it
+    /// later, possibly in another process, with
+    /// [`SearchState::from_bytes`](struct.SearchState.html#method.from_bytes).
+    /// The bytes are always written in little endian format, so they can be
+    /// deserialized on any target.
+    ///
+    /// A search state only makes sense for the DFA that produced it, so the
+    /// search must be resumed with the same DFA, e.g., a DFA deserialized
+    /// from the same bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, SearchState, DFA};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dfa = dense::Builder::new().build("foo[0-9]+")?;
+    /// let mut state = SearchState::start();
+    /// assert_eq!(None, dfa.find_chunk(b"xyz fo", &mut state));
+    /// let checkpoint = state.to_bytes();
+    ///
+    /// let mut state: SearchState<usize> =
+    ///     SearchState::from_bytes(&checkpoint)?;
+    /// assert_eq!(6, state.offset());
+    /// assert_eq!(Some(10), dfa.find_chunk(b"o123 foo4", &mut state));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_bytes(&self) -> [u8; SEARCH_STATE_LEN] {
+        let mut buf = [0; SEARCH_STATE_LEN];
+        buf[..SEARCH_STATE_LABEL.len()].copy_from_slice(SEARCH_STATE_LABEL);
+        let mut flags = 0;
+        if self.id.is_some() {
+            flags |= SEARCH_STARTED;
+        }
+        if self.last_match.is_some() {
+            flags |= SEARCH_MATCHED;
+        }
+        if self.done {
+            flags |= SEARCH_DONE;
+        }
+
+        let fields = &mut buf[SEARCH_STATE_LABEL.len()..];
+        LittleEndian::write_u16(fields, SEARCH_STATE_VERSION);
+        LittleEndian::write_u16(&mut fields[2..], mem::size_of::<S>() as u16);
+        LittleEndian::write_u16(&mut fields[4..], flags);
+        let id = self.id.map_or(0, |id| id.to_usize());
+        LittleEndian::write_u64(&mut fields[6..], id as u64);
+        LittleEndian::write_u64(&mut fields[14..], self.offset as u64);
+        let last_match = self.last_match.unwrap_or(0);
+        LittleEndian::write_u64(&mut fields[22..], last_match as u64);
+        buf
+    }
+
+    /// Deserialize a search state from bytes written by
+    /// [`SearchState::to_bytes`](struct.SearchState.html#method.to_bytes).
+    ///
+    /// The state identifier representation `S` must be the same as the one
+    /// used by the search that was serialized.
+    ///
+    /// Since the bytes can't be checked against the DFA that produced them,
+    /// resuming a search with a different DFA produces unspecified (but
+    /// safe) results.
+    ///
+    /// If the bytes don't contain a valid search state, then an error is
+    /// returned.
+    pub fn from_bytes(buf: &[u8]) -> Result<SearchState<S>, DeserializeError> {
+        if !buf.starts_with(SEARCH_STATE_LABEL) {
+            return Err(DeserializeError::InvalidLabel);
+        }
+        if buf.len() < SEARCH_STATE_LEN {
+            return Err(DeserializeError::BufferTooSmall {
+                needed: SEARCH_STATE_LEN,
+                got: buf.len(),
+            });
+        }
+        let fields = &buf[SEARCH_STATE_LABEL.len()..];
+
+        let version = LittleEndian::read_u16(fields);
+        if version != SEARCH_STATE_VERSION {
+            return Err(DeserializeError::UnsupportedVersion {
+                expected: SEARCH_STATE_VERSION,
+                found: version,
+            });
+        }
+        let state_id_size = LittleEndian::read_u16(&fields[2..]) as usize;
+        if state_id_size != mem::size_of::<S>() {
+            return Err(DeserializeError::StateIDSizeMismatch {
+                expected: mem::size_of::<S>(),
+                found: state_id_size,
+            });
+        }
+        let flags = LittleEndian::read_u16(&fields[4..]);
+        let id = LittleEndian::read_u64(&fields[6..]);
+        let offset = LittleEndian::read_u64(&fields[14..]);
+        let last_match = LittleEndian::read_u64(&fields[22..]);
+        if id > S::max_id() as u64 {
+            return Err(DeserializeError::InvalidStateID { id });
+        }
+        let max_offset = !0usize as u64;
+        let started = flags & SEARCH_STARTED > 0;
+        let matched = flags & SEARCH_MATCHED > 0;
+        let done = flags & SEARCH_DONE > 0;
+        if flags & !(SEARCH_STARTED | SEARCH_MATCHED | SEARCH_DONE) > 0
+            || offset > max_offset
+            || last_match > offset
+            || (!started && (id != 0 || offset != 0 || matched || done))
+        {
+            return Err(DeserializeError::InvalidSearchState);
+        }
+        Ok(SearchState {
+            id: if started { Some(S::from_usize(id as usize)) } else { None },
+            offset: offset as usize,
+            last_match: if matched { Some(last_match as usize) } else { None },
+            done,
+        })
+    }
+}
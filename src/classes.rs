@@ -5,13 +5,42 @@ use core::fmt;
 /// This is used in a DFA to reduce the size of the transition table. This can
 /// have a particularly large impact not only on the total size of a dense DFA,
 /// but also on compile times.
-#[derive(Clone, Copy)]
+///
+/// Two bytes are in the same equivalence class when no pattern the classes
+/// were computed from can distinguish them, so a DFA transitions the same
+/// way on both. Each class is a contiguous range of bytes, and classes are
+/// numbered in increasing order of their bytes, starting at `0`. A dense DFA
+/// has one transition per class in every state, in the order of the classes.
+///
+/// The classes used by a DFA are returned by, e.g.,
+/// [`DenseDFA::byte_classes`](enum.DenseDFA.html#method.byte_classes), which
+/// permits writing custom search loops over its transitions. The same
+/// classes can be shared by several DFAs built from related patterns with
+/// [`dense::Builder::shared_byte_classes`](dense/struct.Builder.html#method.shared_byte_classes).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::DenseDFA;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = DenseDFA::new("[a-z]+[0-9]")?;
+/// let classes = dfa.byte_classes();
+/// assert_eq!(classes.get(b'a'), classes.get(b'z'));
+/// assert_ne!(classes.get(b'a'), classes.get(b'0'));
+///
+/// // There is one representative byte in each class.
+/// let reps: Vec<u8> = classes.representatives().collect();
+/// assert_eq!(classes.alphabet_len(), reps.len());
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub struct ByteClasses([u8; 256]);
 
 impl ByteClasses {
     /// Creates a new set of equivalence classes where all bytes are mapped to
     /// the same class.
-    pub fn empty() -> ByteClasses {
+    pub(crate) fn empty() -> ByteClasses {
         ByteClasses([0; 256])
     }
 
@@ -25,10 +54,51 @@ impl ByteClasses {
         classes
     }
 
+    /// Creates equivalence classes from the class of each byte, indexed by
+    /// byte.
+    ///
+    /// The classes must follow the rules described above: the class of the
+    /// byte `0` is `0`, and the class of every other byte is either the
+    /// class of the previous byte or one more than it. If they don't, then
+    /// `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::ByteClasses;
+    ///
+    /// // Digits in one class, and every other byte in its own class.
+    /// let mut map = [0u8; 256];
+    /// for b in 0..256 {
+    ///     map[b] = match b as u8 {
+    ///         b'0'..=b'9' => b'0',
+    ///         b if b < b'0' => b,
+    ///         b => b - 9,
+    ///     };
+    /// }
+    /// let classes = ByteClasses::from_array(map).unwrap();
+    /// assert_eq!(247, classes.alphabet_len());
+    /// assert_eq!(classes.get(b'0'), classes.get(b'9'));
+    ///
+    /// map[0] = 1;
+    /// assert!(ByteClasses::from_array(map).is_none());
+    /// ```
+    pub fn from_array(classes: [u8; 256]) -> Option<ByteClasses> {
+        if classes[0] != 0 {
+            return None;
+        }
+        for pair in classes.windows(2) {
+            if pair[1] != pair[0] && pair[1] != pair[0].wrapping_add(1) {
+                return None;
+            }
+        }
+        Some(ByteClasses(classes))
+    }
+
     /// Copies the byte classes given. The given slice must have length 0 or
     /// length 256. Slices of length 0 are treated as singletons (every byte
     /// is its own class).
-    pub fn from_slice(slice: &[u8]) -> ByteClasses {
+    pub(crate) fn from_slice(slice: &[u8]) -> ByteClasses {
         assert!(slice.is_empty() || slice.len() == 256);
 
         if slice.is_empty() {
@@ -44,7 +114,7 @@ impl ByteClasses {
 
     /// Set the equivalence class for the given byte.
     #[inline]
-    pub fn set(&mut self, byte: u8, class: u8) {
+    pub(crate) fn set(&mut self, byte: u8, class: u8) {
         self.0[byte as usize] = class;
     }
 
@@ -57,7 +127,7 @@ impl ByteClasses {
     /// Get the equivalence class for the given byte while forcefully
     /// eliding bounds checks.
     #[inline]
-    pub unsafe fn get_unchecked(&self, byte: u8) -> u8 {
+    pub(crate) unsafe fn get_unchecked(&self, byte: u8) -> u8 {
         *self.0.get_unchecked(byte as usize)
     }
 
@@ -86,11 +156,71 @@ impl ByteClasses {
     /// hasn't been converted to equivalence classes yet. Picking an arbitrary
     /// byte from each equivalence class then permits a full exploration of
     /// the NFA instead of using every possible byte value.
+    ///
+    /// The representatives are yielded in the order of their classes, and
+    /// each is the smallest byte in its class.
     #[cfg(feature = "std")]
     pub fn representatives(&self) -> ByteClassRepresentatives {
         ByteClassRepresentatives { classes: self, byte: 0, last_class: None }
     }
 
+    /// Returns true if and only if every pair of bytes in distinct classes
+    /// of `other` are also in distinct classes of these equivalence classes.
+    /// In that case, these classes can be used in place of `other`.
+    ///
+    /// Like `with_singletons`, this assumes that every equivalence class is
+    /// a contiguous range of bytes.
+    #[cfg(feature = "std")]
+    pub(crate) fn refines(&self, other: &ByteClasses) -> bool {
+        (1..256).map(|b| b as u8).all(|b| {
+            other.get(b - 1) == other.get(b) || self.get(b - 1) != self.get(b)
+        })
+    }
+
+    /// Returns a copy of these equivalence classes, where each byte in the
+    /// given set is in its own equivalence class.
+    ///
+    /// This assumes that every equivalence class is a contiguous range of
+    /// bytes, which is true of all equivalence classes computed by a
+    /// `ByteClassSet`.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_singletons(&self, set: &ByteSet) -> ByteClasses {
+        let mut classes = ByteClasses::empty();
+        let mut class = 0u8;
+        for b in 1..256 {
+            let (prev, b) = ((b - 1) as u8, b as u8);
+            if self.get(prev) != self.get(b)
+                || set.contains(prev)
+                || set.contains(b)
+            {
+                class += 1;
+            }
+            classes.set(b, class);
+        }
+        classes
+    }
+
+    /// Returns the coarsest equivalence classes that refine both these
+    /// classes and `other`. That is, two bytes are in the same class of the
+    /// result only when they are in the same class of both.
+    ///
+    /// Like `with_singletons`, this assumes that every equivalence class is
+    /// a contiguous range of bytes.
+    #[cfg(feature = "std")]
+    pub(crate) fn merge(&self, other: &ByteClasses) -> ByteClasses {
+        let mut classes = ByteClasses::empty();
+        let mut class = 0u8;
+        for b in 1..256 {
+            let (prev, b) = ((b - 1) as u8, b as u8);
+            if self.get(prev) != self.get(b) || other.get(prev) != other.get(b)
+            {
+                class += 1;
+            }
+            classes.set(b, class);
+        }
+        classes
+    }
+
     /// Returns all of the bytes in the given equivalence class.
     ///
     /// The second element in the tuple indicates the number of elements in
@@ -122,7 +252,67 @@ impl fmt::Debug for ByteClasses {
     }
 }
 
+/// A set of bytes.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ByteSet([u8; 32]);
+
+impl ByteSet {
+    /// Create a new set with no bytes in it.
+    pub fn empty() -> ByteSet {
+        ByteSet([0; 32])
+    }
+
+    /// Copies the set given in its serialized form, as returned by
+    /// `as_bytes`. The given slice must have length 32.
+    pub fn from_slice(slice: &[u8]) -> ByteSet {
+        let mut set = ByteSet::empty();
+        set.0.copy_from_slice(slice);
+        set
+    }
+
+    /// Add the given byte to this set.
+    #[cfg(feature = "std")]
+    pub fn add(&mut self, byte: u8) {
+        self.0[byte as usize / 8] |= 1 << (byte % 8);
+    }
+
+    /// Remove the given byte from this set.
+    #[cfg(feature = "std")]
+    pub fn remove(&mut self, byte: u8) {
+        self.0[byte as usize / 8] &= !(1 << (byte % 8));
+    }
+
+    /// Returns true if and only if the given byte is in this set.
+    #[inline]
+    pub fn contains(&self, byte: u8) -> bool {
+        self.0[byte as usize / 8] & (1 << (byte % 8)) != 0
+    }
+
+    /// Returns an iterator over the bytes in this set, in ascending order.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = u8> + 'a {
+        (0..256).map(|b| b as u8).filter(move |&b| self.contains(b))
+    }
+
+    /// Returns this set in its serialized form, where the byte `b` is in the
+    /// set if and only if bit `b % 8` of the byte at index `b / 8` is set.
+    #[cfg(feature = "std")]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ByteSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ByteSet(")?;
+        f.debug_list().entries(self.iter()).finish()?;
+        write!(f, ")")
+    }
+}
+
 /// An iterator over representative bytes from each equivalence class.
+///
+/// This is created by
+/// [`ByteClasses::representatives`](struct.ByteClasses.html#method.representatives).
 #[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct ByteClassRepresentatives<'a> {
@@ -220,9 +410,85 @@ impl ByteClassSet {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
-    #[cfg(feature = "std")]
+    use super::{ByteClasses, ByteSet};
+
+    #[test]
+    fn byte_set() {
+        let mut set = ByteSet::empty();
+        assert_eq!(ByteSet::empty(), set);
+        set.add(b'\n');
+        set.add(0);
+        set.add(255);
+        assert!(set.contains(b'\n') && set.contains(0) && set.contains(255));
+        assert!(!set.contains(b'\r'));
+        set.remove(0);
+        assert!(!set.contains(0));
+        assert_eq!(set, ByteSet::from_slice(&set.as_bytes()[..]),);
+        let mut bytes = [0u8; 2];
+        for (i, b) in set.iter().enumerate() {
+            bytes[i] = b;
+        }
+        assert_eq!([b'\n', 255], bytes);
+    }
+
+    #[test]
+    fn with_singletons() {
+        let mut classes = ByteClasses::empty();
+        for b in b'a'..=b'z' {
+            classes.set(b, 1);
+        }
+        for b in b'z' + 1..=255 {
+            classes.set(b, 2);
+        }
+        let mut set = ByteSet::empty();
+        set.add(b'\n');
+        set.add(b'z');
+        let classes = classes.with_singletons(&set);
+        assert_eq!(6, classes.alphabet_len());
+        assert_eq!(classes.get(0), classes.get(b'\t'));
+        assert_ne!(classes.get(b'\t'), classes.get(b'\n'));
+        assert_ne!(classes.get(b'\n'), classes.get(b'\x0B'));
+        assert_eq!(classes.get(b'\x0B'), classes.get(b'a' - 1));
+        assert_eq!(classes.get(b'a'), classes.get(b'y'));
+        assert_ne!(classes.get(b'y'), classes.get(b'z'));
+        assert_ne!(classes.get(b'z'), classes.get(b'z' + 1));
+    }
+
+    #[test]
+    fn refines() {
+        let mut classes = ByteClasses::empty();
+        for b in b'a'..=255 {
+            classes.set(b, 1);
+        }
+        let mut set = ByteSet::empty();
+        set.add(b'z');
+        let finer = classes.with_singletons(&set);
+        assert!(finer.refines(&classes));
+        assert!(finer.refines(&finer));
+        assert!(!classes.refines(&finer));
+        assert!(ByteClasses::singletons().refines(&finer));
+    }
+
+    #[test]
+    fn merge() {
+        let mut a = ByteClasses::empty();
+        for b in b'a'..=255 {
+            a.set(b, 1);
+        }
+        let mut b = ByteClasses::empty();
+        for byte in b'0'..=255 {
+            b.set(byte, 1);
+        }
+        let merged = a.merge(&b);
+        assert_eq!(3, merged.alphabet_len());
+        assert!(merged.refines(&a));
+        assert!(merged.refines(&b));
+        assert_eq!(merged, b.merge(&a));
+        assert_eq!(a, a.merge(&a));
+    }
+
     #[test]
     fn byte_classes() {
         use super::ByteClassSet;
@@ -257,7 +523,6 @@ mod tests {
         assert_eq!(classes.get(255), 3);
     }
 
-    #[cfg(feature = "std")]
     #[test]
     fn full_byte_classes() {
         use super::ByteClassSet;
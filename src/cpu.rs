@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The widest set of SIMD instructions usable by the search loops that have
+/// vectorized implementations.
+///
+/// On each architecture, each level implies the levels before it, such that
+/// a search loop may use the implementation for any level of the same
+/// architecture no wider than the detected one. This permits a single
+/// binary, compiled without `-C target-cpu=native`, to use AVX2 where the CPU
+/// supports it and fall back to narrower or scalar loops elsewhere.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Simd {
+    /// No SIMD instructions are used.
+    Scalar,
+    /// The SSSE3 instructions on x86-64, with 128-bit vectors.
+    Ssse3,
+    /// The AVX2 instructions on x86-64, with 256-bit vectors.
+    Avx2,
+    /// The NEON instructions on aarch64, with 128-bit vectors.
+    Neon,
+}
+
+/// The cached result of detection, or `UNDETECTED` before the first call
+/// to `simd`.
+static DETECTED: AtomicUsize = AtomicUsize::new(UNDETECTED);
+
+/// A value of `DETECTED` that doesn't correspond to any SIMD level.
+const UNDETECTED: usize = 4;
+
+/// Returns the widest SIMD level supported by the current CPU.
+///
+/// Detection only happens on the first call, after which the result is
+/// cached, so this is cheap enough to call whenever a searcher is built.
+pub fn simd() -> Simd {
+    match DETECTED.load(Ordering::Relaxed) {
+        UNDETECTED => {
+            let simd = detect();
+            DETECTED.store(simd as usize, Ordering::Relaxed);
+            simd
+        }
+        level => Simd::from_usize(level),
+    }
+}
+
+/// Returns every SIMD level supported by the current CPU, from the
+/// narrowest to the widest.
+#[cfg(test)]
+pub fn supported() -> Vec<Simd> {
+    let levels: &[Simd] = if cfg!(target_arch = "x86_64") {
+        &[Simd::Scalar, Simd::Ssse3, Simd::Avx2]
+    } else if cfg!(all(feature = "neon", target_arch = "aarch64")) {
+        &[Simd::Scalar, Simd::Neon]
+    } else {
+        &[Simd::Scalar]
+    };
+    levels.iter().cloned().filter(|&level| level <= simd()).collect()
+}
+
+impl Simd {
+    fn from_usize(level: usize) -> Simd {
+        match level {
+            0 => Simd::Scalar,
+            1 => Simd::Ssse3,
+            2 => Simd::Avx2,
+            3 => Simd::Neon,
+            _ => unreachable!("invalid SIMD level {}", level),
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn detect() -> Simd {
+    if !is_x86_feature_detected!("ssse3") {
+        Simd::Scalar
+    } else if !is_x86_feature_detected!("avx2") {
+        Simd::Ssse3
+    } else {
+        Simd::Avx2
+    }
+}
+
+/// NEON is a mandatory part of every aarch64 target supported by Rust's
+/// standard library, so it never needs to be detected at runtime. It is only
+/// used with the `neon` feature, since the NEON intrinsics were stabilized
+/// after this crate's minimum supported Rust version.
+#[cfg(all(
+    feature = "neon",
+    target_arch = "aarch64",
+    target_feature = "neon"
+))]
+fn detect() -> Simd {
+    Simd::Neon
+}
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(feature = "neon", target_arch = "aarch64", target_feature = "neon")
+)))]
+fn detect() -> Simd {
+    Simd::Scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, simd, supported, Simd};
+
+    #[test]
+    fn cached_detection() {
+        let first = simd();
+        assert_eq!(detect(), first);
+        assert_eq!(first, simd());
+        assert_eq!(Some(&first), supported().last());
+        assert_eq!(Simd::Scalar, supported()[0]);
+    }
+}
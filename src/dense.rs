@@ -1,32 +1,63 @@
+use core::cmp;
 #[cfg(feature = "std")]
 use core::fmt;
 #[cfg(feature = "std")]
 use core::iter;
 use core::mem;
+use core::ops::RangeInclusive;
+use core::result;
 use core::slice;
+#[cfg(feature = "std")]
+use std::collections::hash_map::{Entry, HashMap};
+#[cfg(feature = "std")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "std")]
 use byteorder::{BigEndian, LittleEndian};
 use byteorder::{ByteOrder, NativeEndian};
 #[cfg(feature = "std")]
+use bytes::AlignedBytes;
+use bytes::{self, FORMAT_VERSION};
+#[cfg(feature = "std")]
+use regex_syntax::hir::Hir;
+#[cfg(feature = "std")]
 use regex_syntax::ParserBuilder;
 
-use classes::ByteClasses;
+use accel::{Accels, ACCELS_LEN};
+#[cfg(feature = "std")]
+use analysis;
+use classes::{ByteClasses, ByteSet};
+#[cfg(feature = "std")]
+pub use determinize::BuildCache;
 #[cfg(feature = "std")]
-use determinize::Determinizer;
-use dfa::DFA;
+use determinize::{Cancel, Determinizer};
+use dfa::{self, MemoryUsage, PatternID, DFA};
 #[cfg(feature = "std")]
-use error::{Error, Result};
+use diagnostics::{BuildWarning, Diagnostics};
 #[cfg(feature = "std")]
-use minimize::Minimizer;
+use dot;
+use error::DeserializeError;
 #[cfg(feature = "std")]
-use nfa::{self, NFA};
+use error::{Error, Result, SearchError};
+#[cfg(feature = "std")]
+use minimize::{self, Minimizer};
+#[cfg(feature = "std")]
+use nfa::{self, LiteralTrie, NFA};
+#[cfg(feature = "std")]
+use prefilter::Prefilter;
+#[cfg(feature = "std")]
+use product;
 #[cfg(feature = "std")]
 use sparse::SparseDFA;
 use state_id::{dead_id, StateID};
 #[cfg(feature = "std")]
 use state_id::{
-    next_state_id, premultiply_overflow_error, write_state_id_bytes,
+    next_state_id, premultiply_overflow_error, usize_to_state_id,
+    write_state_id_bytes,
 };
 
 /// The size of the alphabet in a standard DFA.
@@ -42,8 +73,36 @@ use state_id::{
 const ALPHABET_LEN: usize = 256;
 
 /// Masks used in serialization of DFAs.
+///
+/// The flags in the low byte are required: they change how the DFA must be
+/// read or searched, so a reader must reject a DFA with a required flag it
+/// doesn't know. The flags in the high byte are optional: a reader that
+/// doesn't know one may ignore it. This permits adding new flags without
+/// changing the version of the format.
 pub(crate) const MASK_PREMULTIPLIED: u16 = 0b0000_0000_0000_0001;
 pub(crate) const MASK_ANCHORED: u16 = 0b0000_0000_0000_0010;
+pub(crate) const MASK_ANCHORED_START: u16 = 0b0000_0000_0000_0100;
+pub(crate) const MASK_LOOKAROUND: u16 = 0b0000_0000_0000_1000;
+pub(crate) const MASK_COMPACT: u16 = 0b0000_0000_0001_0000;
+pub(crate) const MASK_PATTERN_STARTS: u16 = 0b0000_0000_0010_0000;
+pub(crate) const MASK_PATTERN_TAGS: u16 = 0b0000_0000_0100_0000;
+pub(crate) const MASK_UTF8: u16 = 0b0000_0001_0000_0000;
+pub(crate) const MASK_CHECKSUM: u16 = 0b0000_0010_0000_0000;
+
+/// The mask of all required flags, known or not.
+pub(crate) const MASK_REQUIRED: u16 = 0b0000_0000_1111_1111;
+/// The mask of all required flags known to this version of the crate.
+pub(crate) const MASK_KNOWN_REQUIRED: u16 = MASK_PREMULTIPLIED
+    | MASK_ANCHORED
+    | MASK_ANCHORED_START
+    | MASK_LOOKAROUND
+    | MASK_COMPACT
+    | MASK_PATTERN_STARTS
+    | MASK_PATTERN_TAGS;
+
+/// The number of bytes in the header of a serialized dense DFA, which
+/// precedes its transition table.
+const HEADER_LEN: usize = 512;
 
 /// A dense table-based deterministic finite automaton (DFA).
 ///
@@ -260,12 +319,34 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
         }
     }
 
-    /// Return an owned version of this sparse DFA. Specifically, the DFA
-    /// returned always uses `Vec<u8>` for its transition table while keeping
+    /// Return an owned version of this dense DFA. Specifically, the DFA
+    /// returned always uses `Vec<S>` for its transition table while keeping
     /// the same state identifier representation.
     ///
-    /// Effectively, this returns a sparse DFA whose transition table lives
-    /// on the heap.
+    /// Effectively, this returns a dense DFA whose transition table lives
+    /// on the heap. Since the DFA returned doesn't borrow anything, this is
+    /// the way to keep a DFA deserialized with
+    /// [`from_bytes`](enum.DenseDFA.html#method.from_bytes) around after the
+    /// buffer it was deserialized from has been dropped. The transition
+    /// table is copied into a new allocation, which is always correctly
+    /// aligned for `S`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let owned: DenseDFA<Vec<usize>, usize> = {
+    ///     let bytes = DenseDFA::new("foo[0-9]+")?.to_bytes_native_endian()?;
+    ///     let dfa: DenseDFA<&[usize], usize> = unsafe {
+    ///         DenseDFA::from_bytes(&bytes)
+    ///     };
+    ///     dfa.to_owned()
+    /// };
+    /// assert_eq!(Some(8), owned.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[cfg(feature = "std")]
     pub fn to_owned(&self) -> DenseDFA<Vec<S>, S> {
         match *self {
@@ -286,6 +367,15 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
         }
     }
 
+    /// Returns the equivalence classes of bytes used by this DFA.
+    ///
+    /// Every state of this DFA has one transition for each class, so bytes
+    /// in the same class always lead to the same state. When byte classes
+    /// are disabled, every byte is in its own class.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        self.repr().byte_classes()
+    }
+
     /// Returns the memory usage, in bytes, of this DFA.
     ///
     /// The memory usage is computed based on the number of bytes used to
@@ -297,2008 +387,8980 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
     pub fn memory_usage(&self) -> usize {
         self.repr().memory_usage()
     }
-}
 
-/// Routines for converting a dense DFA to other representations, such as
-/// sparse DFAs, smaller state identifiers or raw bytes suitable for persistent
-/// storage.
-#[cfg(feature = "std")]
-impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
-    /// Convert this dense DFA to a sparse DFA.
-    ///
-    /// This is a convenience routine for `to_sparse_sized` that fixes the
-    /// state identifier representation of the sparse DFA to the same
-    /// representation used for this dense DFA.
+    /// Returns the memory usage of this DFA, broken down per component.
     ///
-    /// If the chosen state identifier representation is too small to represent
-    /// all states in the sparse DFA, then this returns an error. In most
-    /// cases, if a dense DFA is constructable with `S` then a sparse DFA will
-    /// be as well. However, it is not guaranteed.
+    /// The total size of the components stored in this DFA's buffer is equal
+    /// to [`memory_usage`](enum.DenseDFA.html#method.memory_usage), while the
+    /// other components, such as the byte class map, are stored inline.
     ///
     /// # Example
     ///
+    /// This example rejects a pattern whose DFA exceeds a memory budget.
+    ///
     /// ```
-    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::DenseDFA;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let dense = DenseDFA::new("foo[0-9]+")?;
-    /// let sparse = dense.to_sparse()?;
-    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// let usage = dfa.memory_usage_breakdown();
+    /// assert_eq!(dfa.memory_usage(), usage.heap());
+    /// assert_eq!(dfa.transition_table_bytes(), usage.transitions());
+    /// assert_eq!(256, usage.byte_classes());
+    /// assert!(usage.total() < 10 * (1 << 10));
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn to_sparse(&self) -> Result<SparseDFA<Vec<u8>, S>> {
-        self.to_sparse_sized()
+    pub fn memory_usage_breakdown(&self) -> MemoryUsage {
+        self.repr().memory_usage_breakdown()
     }
 
-    /// Convert this dense DFA to a sparse DFA.
+    /// Returns the total number of states in this DFA, including the dead
+    /// state. Every DFA has at least one state.
+    pub fn state_count(&self) -> usize {
+        self.repr().state_count()
+    }
+
+    /// Returns the total number of patterns that this DFA was built from.
     ///
-    /// Using this routine requires supplying a type hint to choose the state
-    /// identifier representation for the resulting sparse DFA.
+    /// This is `1` for a DFA built from a single pattern, and `0` for a DFA
+    /// that can never match, such as one returned by
+    /// [`empty`](enum.DenseDFA.html#method.empty). Each match state reports
+    /// which of these patterns match via
+    /// [`DFA::match_pattern`](trait.DFA.html#method.match_pattern).
+    pub fn pattern_count(&self) -> usize {
+        self.repr().pattern_count()
+    }
+
+    /// Returns the tag attached to the given pattern, if this DFA was built
+    /// with
+    /// [`Builder::build_many_tagged`](dense/struct.Builder.html#method.build_many_tagged).
     ///
-    /// If the chosen state identifier representation is too small to represent
-    /// all states in the sparse DFA, then this returns an error.
+    /// Tags are stored in the serialized form of this DFA, so they survive
+    /// a round trip through `to_bytes_native_endian` and `from_bytes`. This
+    /// returns `None` if this DFA has no tags or if `pattern` is not less
+    /// than [`pattern_count`](enum.DenseDFA.html#method.pattern_count).
+    pub fn pattern_tag(&self, pattern: PatternID) -> Option<u64> {
+        self.repr().pattern_tag(pattern)
+    }
+
+    /// Returns true if and only if this DFA was built with a tag for each
+    /// pattern, as retrieved by
+    /// [`pattern_tag`](enum.DenseDFA.html#method.pattern_tag).
+    pub fn has_pattern_tags(&self) -> bool {
+        self.repr().has_pattern_tags()
+    }
+
+    /// Returns true if and only if this DFA can execute anchored searches
+    /// with [`search_at`](enum.DenseDFA.html#method.search_at).
+    ///
+    /// This is always true for anchored DFAs. For unanchored DFAs, this is
+    /// only true when the DFA was built with
+    /// [`Builder::anchored_start_state`](dense/struct.Builder.html#method.anchored_start_state)
+    /// enabled.
+    pub fn has_anchored_start_state(&self) -> bool {
+        self.repr().anchored_start_state().is_some()
+    }
+
+    /// Returns the logical index of the state with the given identifier,
+    /// where the identifier is one returned by the methods of the
+    /// [`DFA`](trait.DFA.html) trait, such as `start_state` or `next_state`.
+    ///
+    /// The states of a DFA have indices `0` through `state_count() - 1`,
+    /// where the dead state always has index `0`. These are the identifiers
+    /// reported by [`states`](enum.DenseDFA.html#method.states), whether or
+    /// not this DFA is premultiplied.
     ///
     /// # Example
     ///
     /// ```
-    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::{dense, DFA};
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let dense = DenseDFA::new("foo[0-9]+")?;
-    /// let sparse = dense.to_sparse_sized::<u8>()?;
-    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// let dfa = dense::Builder::new().premultiply(true).build("abc")?;
+    /// let start = dfa.start_state();
+    /// let index = dfa.state_index(start);
+    /// assert!(index < dfa.state_count());
+    /// assert!(dfa.states().nth(index).unwrap().is_start());
+    /// assert_eq!(start, dfa.state_id(index));
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn to_sparse_sized<A: StateID>(
-        &self,
-    ) -> Result<SparseDFA<Vec<u8>, A>> {
-        self.repr().to_sparse_sized()
+    pub fn state_index(&self, id: S) -> usize {
+        self.repr().state_id_to_index(id)
     }
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u8` for the representation of state identifiers.
-    /// If `u8` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// Returns the identifier of the state with the given logical index,
+    /// suitable for use with the methods of the [`DFA`](trait.DFA.html)
+    /// trait. This is the inverse of
+    /// [`state_index`](enum.DenseDFA.html#method.state_index).
     ///
-    /// This is a convenience routine for `to_sized::<u8>()`.
-    pub fn to_u8(&self) -> Result<DenseDFA<Vec<u8>, u8>> {
-        self.to_sized()
-    }
-
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u16` for the representation of state identifiers.
-    /// If `u16` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// # Panics
     ///
-    /// This is a convenience routine for `to_sized::<u16>()`.
-    pub fn to_u16(&self) -> Result<DenseDFA<Vec<u16>, u16>> {
-        self.to_sized()
+    /// This panics if `index` is not less than `state_count()`.
+    pub fn state_id(&self, index: usize) -> S {
+        assert!(index < self.state_count(), "invalid state index");
+        self.repr().state_index_to_id(index)
     }
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u32` for the representation of state identifiers.
-    /// If `u32` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// Returns an iterator over all states in this DFA, in the order in
+    /// which they appear in its transition table.
     ///
-    /// This is a convenience routine for `to_sized::<u32>()`.
-    #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
-    pub fn to_u32(&self) -> Result<DenseDFA<Vec<u32>, u32>> {
-        self.to_sized()
-    }
-
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u64` for the representation of state identifiers.
-    /// If `u64` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// Each state is a read-only view, which reports whether the state is a
+    /// match state or the dead state, and which can iterate over the state's
+    /// transitions grouped into byte ranges. This is useful for analyzing a
+    /// DFA, e.g., computing reachability or statistics about its states.
     ///
-    /// This is a convenience routine for `to_sized::<u64>()`.
-    #[cfg(target_pointer_width = "64")]
-    pub fn to_u64(&self) -> Result<DenseDFA<Vec<u64>, u64>> {
-        self.to_sized()
-    }
-
-    /// Create a new DFA whose match semantics are equivalent to this DFA, but
-    /// attempt to use `A` for the representation of state identifiers. If `A`
-    /// is insufficient to represent all state identifiers in this DFA, then
-    /// this returns an error.
+    /// States and their transitions are always identified by their logical
+    /// index, even when this DFA is premultiplied. Thus, the `i`th state
+    /// yielded by this iterator has identifier `i`, and the target of every
+    /// transition can be used to index the states directly. Use
+    /// [`state_id`](enum.DenseDFA.html#method.state_id) and
+    /// [`state_index`](enum.DenseDFA.html#method.state_index) to convert
+    /// between these identifiers and the ones used by the methods of the
+    /// [`DFA`](trait.DFA.html) trait.
     ///
-    /// An alternative way to construct such a DFA is to use
-    /// [`dense::Builder::build_with_size`](dense/struct.Builder.html#method.build_with_size).
-    /// In general, using the builder is preferred since it will use the given
-    /// state identifier representation throughout determinization (and
-    /// minimization, if done), and thereby using less memory throughout the
-    /// entire construction process. However, these routines are necessary
-    /// in cases where, say, a minimized DFA could fit in a smaller state
-    /// identifier representation, but the initial determinized DFA would not.
-    pub fn to_sized<A: StateID>(&self) -> Result<DenseDFA<Vec<A>, A>> {
-        self.repr().to_sized().map(|r| r.into_dense_dfa())
-    }
-
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in little
-    /// endian format.
+    /// # Example
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    pub fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<LittleEndian>()
+    /// This example finds the live transitions out of the start state.
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("a[0-9]")?;
+    /// let states: Vec<dense::State<_, _>> = dfa.states().collect();
+    /// assert_eq!(4, states.len());
+    /// assert!(states[0].is_dead());
+    /// assert!(states[1].is_match());
+    ///
+    /// let start = states.iter().find(|s| s.is_start()).unwrap();
+    /// let live: Vec<_> = start
+    ///     .transitions()
+    ///     .filter(|&(_, next)| !states[next].is_dead())
+    ///     .collect();
+    /// assert_eq!(1, live.len());
+    /// assert_eq!(b'a'..=b'a', live[0].0);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn states<'a>(&'a self) -> States<'a, T, S> {
+        States { dfa: self.repr(), index: 0 }
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in big
-    /// endian format.
+    /// Returns the same as `find_at`, but permits choosing whether the match
+    /// must begin at `start`.
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    pub fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<BigEndian>()
+    /// When `anchored` is false, this is equivalent to `find_at`. When
+    /// `anchored` is true and this DFA is unanchored, the search begins in
+    /// this DFA's anchored start state, and thus only reports a match that
+    /// begins at `start`. Anchored DFAs always execute anchored searches,
+    /// which means, like `find_at`, they never match when `start > 0`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `anchored` is true and this DFA has no anchored start
+    /// state. See
+    /// [`has_anchored_start_state`](enum.DenseDFA.html#method.has_anchored_start_state).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored_start_state(true)
+    ///     .build("foo[0-9]+")?;
+    /// assert_eq!(Some(8), dfa.search_at(b"xxfoo123", 0, false));
+    /// assert_eq!(None, dfa.search_at(b"xxfoo123", 0, true));
+    /// assert_eq!(Some(8), dfa.search_at(b"xxfoo123", 2, true));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn search_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        anchored: bool,
+    ) -> Option<usize> {
+        if !anchored || self.is_anchored() {
+            return self.find_at(bytes, start);
+        }
+        let prev = dfa::byte_before(bytes, start);
+        let state = match self.repr().anchored_start_state_after(prev) {
+            Some(state) => state,
+            None => panic!("DFA was built without an anchored start state"),
+        };
+        match *self {
+            DenseDFA::Standard(ref r) => find_from(r, state, bytes, start),
+            DenseDFA::ByteClass(ref r) => find_from(r, state, bytes, start),
+            DenseDFA::Premultiplied(ref r) => {
+                find_from(r, state, bytes, start)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                find_from(r, state, bytes, start)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in native
-    /// endian format. Generally, it is better to pick an explicit endianness
-    /// using either `to_bytes_little_endian` or `to_bytes_big_endian`. This
-    /// routine is useful in tests where the DFA is serialized and deserialized
-    /// on the same platform.
+    /// Returns true if and only if this DFA has a start state for anchored
+    /// searches of each pattern, which is required by
+    /// [`search_pattern_at`](enum.DenseDFA.html#method.search_pattern_at).
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    pub fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<NativeEndian>()
+    /// This is only true when the DFA was built with
+    /// [`Builder::starts_for_each_pattern`](dense/struct.Builder.html#method.starts_for_each_pattern)
+    /// enabled.
+    pub fn has_starts_for_each_pattern(&self) -> bool {
+        self.repr().has_pattern_starts()
     }
-}
 
-impl<'a, S: StateID> DenseDFA<&'a [S], S> {
-    /// Deserialize a DFA with a specific state identifier representation.
-    ///
-    /// Deserializing a DFA using this routine will never allocate heap memory.
-    /// This is also guaranteed to be a constant time operation that does not
-    /// vary with the size of the DFA.
-    ///
-    /// The bytes given should be generated by the serialization of a DFA with
-    /// either the
-    /// [`to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)
-    /// method or the
-    /// [`to_bytes_big_endian`](enum.DenseDFA.html#method.to_bytes_big_endian)
-    /// endian, depending on the endianness of the machine you are
-    /// deserializing this DFA from.
+    /// Returns the end offset of the leftmost first match of the given
+    /// pattern that begins at `start`, ignoring every other pattern.
     ///
-    /// If the state identifier representation is `usize`, then deserialization
-    /// is dependent on the pointer size. For this reason, it is best to
-    /// serialize DFAs using a fixed size representation for your state
-    /// identifiers, such as `u8`, `u16`, `u32` or `u64`.
+    /// This is an anchored search: a match is only reported if it begins at
+    /// `start`, regardless of whether this DFA is anchored. It uses the same
+    /// match semantics as
+    /// [`DFA::find_at`](trait.DFA.html#method.find_at) otherwise. To check
+    /// whether the entire input matches the pattern, compare the offset
+    /// returned to the length of the input.
     ///
     /// # Panics
     ///
-    /// The bytes given should be *trusted*. In particular, if the bytes
-    /// are not a valid serialization of a DFA, or if the given bytes are
-    /// not aligned to an 8 byte boundary, or if the endianness of the
-    /// serialized bytes is different than the endianness of the machine that
-    /// is deserializing the DFA, then this routine will panic. Moreover, it is
-    /// possible for this deserialization routine to succeed even if the given
-    /// bytes do not represent a valid serialized dense DFA.
-    ///
-    /// # Safety
-    ///
-    /// This routine is unsafe because it permits callers to provide an
-    /// arbitrary transition table with possibly incorrect transitions. While
-    /// the various serialization routines will never return an incorrect
-    /// transition table, there is no guarantee that the bytes provided here
-    /// are correct. While deserialization does many checks (as documented
-    /// above in the panic conditions), this routine does not check that the
-    /// transition table is correct. Given an incorrect transition table, it is
-    /// possible for the search routines to access out-of-bounds memory because
-    /// of explicit bounds check elision.
+    /// This panics if this DFA was built without a start state for each
+    /// pattern (see
+    /// [`has_starts_for_each_pattern`](enum.DenseDFA.html#method.has_starts_for_each_pattern)),
+    /// or if `pattern` is not less than
+    /// [`pattern_count`](enum.DenseDFA.html#method.pattern_count).
     ///
     /// # Example
     ///
-    /// This example shows how to serialize a DFA to raw bytes, deserialize it
-    /// and then use it for searching. Note that we first convert the DFA to
-    /// using `u16` for its state identifier representation before serializing
-    /// it. While this isn't strictly necessary, it's good practice in order to
-    /// decrease the size of the DFA and to avoid platform specific pitfalls
-    /// such as differing pointer sizes.
+    /// This example validates input against one rule of a DFA built from
+    /// several rules.
     ///
     /// ```
-    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::dense;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let initial = DenseDFA::new("foo[0-9]+")?;
-    /// let bytes = initial.to_u16()?.to_bytes_native_endian()?;
-    /// let dfa: DenseDFA<&[u16], u16> = unsafe {
-    ///     DenseDFA::from_bytes(&bytes)
+    /// let rules = &[r"[0-9]{4}-[0-9]{2}-[0-9]{2}", r"[a-z]+@[a-z]+\.com"];
+    /// let dfa = dense::Builder::new()
+    ///     .starts_for_each_pattern(true)
+    ///     .build_many(rules)?;
+    /// let is_valid = |rule: usize, input: &[u8]| {
+    ///     dfa.search_pattern_at(rule, input, 0) == Some(input.len())
     /// };
-    ///
-    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// assert!(is_valid(0, b"2020-01-31"));
+    /// assert!(!is_valid(1, b"2020-01-31"));
+    /// assert!(is_valid(1, b"foo@example.com"));
+    /// assert!(!is_valid(1, b"foo@example.com!"));
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub unsafe fn from_bytes(buf: &'a [u8]) -> DenseDFA<&'a [S], S> {
-        Repr::from_bytes(buf).into_dense_dfa()
+    pub fn search_pattern_at(
+        &self,
+        pattern: PatternID,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<usize> {
+        assert!(
+            self.has_starts_for_each_pattern(),
+            "DFA was built without a start state for each pattern"
+        );
+        assert!(pattern < self.pattern_count(), "invalid pattern");
+        let prev = dfa::byte_before(bytes, start);
+        let state =
+            self.repr().pattern_start_state_after(pattern, prev).unwrap();
+        match *self {
+            DenseDFA::Standard(ref r) => find_from(r, state, bytes, start),
+            DenseDFA::ByteClass(ref r) => find_from(r, state, bytes, start),
+            DenseDFA::Premultiplied(ref r) => {
+                find_from(r, state, bytes, start)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                find_from(r, state, bytes, start)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
-}
 
-#[cfg(feature = "std")]
-impl<S: StateID> DenseDFA<Vec<S>, S> {
-    /// Minimize this DFA in place.
+    /// Returns a deterministic, line oriented textual description of this
+    /// DFA's states and transitions.
     ///
-    /// This is not part of the public API. It is only exposed to allow for
-    /// more granular external benchmarking.
-    #[doc(hidden)]
-    pub fn minimize(&mut self) {
-        self.repr_mut().minimize();
-    }
-
-    /// Return a mutable reference to the internal DFA representation.
-    fn repr_mut(&mut self) -> &mut Repr<Vec<S>, S> {
-        match *self {
-            DenseDFA::Standard(ref mut r) => &mut r.0,
-            DenseDFA::ByteClass(ref mut r) => &mut r.0,
-            DenseDFA::Premultiplied(ref mut r) => &mut r.0,
-            DenseDFA::PremultipliedByteClass(ref mut r) => &mut r.0,
-            DenseDFA::__Nonexhaustive => unreachable!(),
+    /// Unlike the `Debug` output, the text format is intended to be stable
+    /// so that it can be checked into version control and diffed. Namely,
+    /// building the same pattern with the same configuration always produces
+    /// the same text. States are identified by their index in the transition
+    /// table, regardless of whether this DFA is premultiplied, and
+    /// transitions are given in terms of bytes, regardless of whether this
+    /// DFA uses byte classes.
+    ///
+    /// The first line gives the start state. Each state then starts with a
+    /// line of the form `state <index>`, followed by the flags `dead` or
+    /// `match` when applicable. When this DFA was built from more than one
+    /// pattern, `match` is followed by a comma separated list of the
+    /// patterns that match. Each transition from that state to a state
+    /// other than the dead state follows on its own line, as an inclusive
+    /// range of bytes and the index of the target state. Bytes are escaped
+    /// in the same way as `std::ascii::escape_default`. When this DFA has
+    /// look-around, the transition taken at the end of the input follows as
+    /// `EOI`.
+    ///
+    /// There is currently no routine for parsing this format back into a DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("a[0-9]")?;
+    /// let expected = "\
+    /// start 3
+    /// state 0 dead
+    /// state 1 match
+    /// state 2
+    ///   0-9 -> 1
+    /// state 3
+    ///   a -> 2
+    /// ";
+    /// assert_eq!(expected, dfa.to_text());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_text(&self) -> String {
+        let repr = self.repr();
+        let mut text = String::new();
+        text.push_str(&format!(
+            "start {}\n",
+            repr.state_id_to_index(repr.start_state())
+        ));
+        if let Some(id) = repr.start_anchored {
+            text.push_str(&format!(
+                "anchored start {}\n",
+                repr.state_id_to_index(id)
+            ));
         }
-    }
-}
-
-impl<T: AsRef<[S]>, S: StateID> DFA for DenseDFA<T, S> {
-    type ID = S;
-
-    #[inline]
-    fn start_state(&self) -> S {
-        self.repr().start_state()
-    }
-
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.repr().is_match_state(id)
-    }
-
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.repr().is_dead_state(id)
-    }
-
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.repr().is_match_or_dead_state(id)
-    }
-
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.repr().is_anchored()
-    }
-
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        match *self {
-            DenseDFA::Standard(ref r) => r.next_state(current, input),
-            DenseDFA::ByteClass(ref r) => r.next_state(current, input),
-            DenseDFA::Premultiplied(ref r) => r.next_state(current, input),
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.next_state(current, input)
+        for (id, _) in repr.states() {
+            text.push_str(&format!("state {}", repr.state_id_to_index(id)));
+            if id == dead_id() {
+                text.push_str(" dead");
+            } else if repr.is_match_state(id) {
+                text.push_str(" match");
+                if repr.pattern_count() > 1 {
+                    let patterns: Vec<String> = (0..repr.match_count(id))
+                        .map(|i| repr.match_pattern(id, i).to_string())
+                        .collect();
+                    text.push_str(&format!(" {}", patterns.join(",")));
+                }
+            }
+            text.push('\n');
+            // Transitions are computed over every byte, rather than over
+            // equivalence classes, so that enabling or disabling byte
+            // classes doesn't change the output.
+            let mut ranges: Vec<(u8, u8, S)> = vec![];
+            for b in 0..=255u8 {
+                let next = self.next_state(id, b);
+                match ranges.last_mut() {
+                    Some(&mut (_, ref mut end, last)) if last == next => {
+                        *end = b;
+                        continue;
+                    }
+                    _ => {}
+                }
+                ranges.push((b, b, next));
+            }
+            for (start, end, next) in ranges {
+                if next == dead_id() {
+                    continue;
+                }
+                let next = repr.state_id_to_index(next);
+                if start == end {
+                    text.push_str(&format!(
+                        "  {} -> {}\n",
+                        escape(start),
+                        next
+                    ));
+                } else {
+                    text.push_str(&format!(
+                        "  {}-{} -> {}\n",
+                        escape(start),
+                        escape(end),
+                        next
+                    ));
+                }
+            }
+            let eoi = repr.next_eoi_state(id);
+            if eoi != dead_id() {
+                let eoi = repr.state_id_to_index(eoi);
+                text.push_str(&format!("  EOI -> {}\n", eoi));
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+        text
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        match *self {
-            DenseDFA::Standard(ref r) => {
-                r.next_state_unchecked(current, input)
+    /// Render this DFA as a directed graph in the Graphviz DOT language.
+    ///
+    /// Each state is drawn as a node labelled with the state's index, as in
+    /// [`to_text`](enum.DenseDFA.html#method.to_text), where match states are
+    /// drawn with a double circle. All of the transitions between a pair of
+    /// states are drawn as a single edge labelled with the byte ranges of the
+    /// transitions, and the transition taken at the end of the input, if
+    /// any, is drawn as a dotted edge. The dead state and transitions to it
+    /// are omitted.
+    ///
+    /// The output can be rendered with Graphviz, e.g., with
+    /// `dot -Tsvg dfa.dot > dfa.svg`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("a[0-9]")?;
+    /// let expected = r#"digraph {
+    ///   rankdir=LR;
+    ///   node [shape=circle];
+    ///   start [shape=point];
+    ///   start -> 3;
+    ///   1 [label="1", shape=doublecircle];
+    ///   2 [label="2"];
+    ///   2 -> 1 [label="0-9"];
+    ///   3 [label="3"];
+    ///   3 -> 2 [label="a"];
+    /// }
+    /// "#;
+    /// assert_eq!(expected, dfa.to_dot());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_dot(&self) -> String {
+        let repr = self.repr();
+        let dead = repr.state_id_to_index(dead_id());
+        let mut dot = dot::Writer::new();
+        dot.start("start", repr.state_id_to_index(repr.start_state()), None);
+        if let Some(id) = repr.start_anchored {
+            dot.start(
+                "anchored",
+                repr.state_id_to_index(id),
+                Some("anchored"),
+            );
+        }
+        for (id, _) in repr.states() {
+            let index = repr.state_id_to_index(id);
+            if index == dead {
+                continue;
             }
-            DenseDFA::ByteClass(ref r) => {
-                r.next_state_unchecked(current, input)
+            if repr.is_match_state(id) {
+                let mut label = index.to_string();
+                if repr.pattern_count() > 1 {
+                    let patterns: Vec<String> = (0..repr.match_count(id))
+                        .map(|i| repr.match_pattern(id, i).to_string())
+                        .collect();
+                    label.push_str(&format!(" ({})", patterns.join(",")));
+                }
+                dot.node(index, &label, Some("doublecircle"));
+            } else {
+                dot.node(index, &index.to_string(), None);
             }
-            DenseDFA::Premultiplied(ref r) => {
-                r.next_state_unchecked(current, input)
+            let trans = (0..=255u8)
+                .map(|b| (b, repr.state_id_to_index(self.next_state(id, b))));
+            for (next, ranges) in dot::group_ranges(trans, dead) {
+                dot.edge(index, next, &ranges);
             }
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.next_state_unchecked(current, input)
+            let eoi = repr.state_id_to_index(repr.next_eoi_state(id));
+            if eoi != dead {
+                dot.eoi(index, eoi);
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+        dot.finish()
     }
 
-    // We specialize the following methods because it lets us lift the
-    // case analysis between the different types of dense DFAs. Instead of
-    // doing the case analysis for every transition, we do it once before
-    // searching.
+    /// Returns the number of bytes that this DFA occupies when serialized
+    /// with any of the `to_bytes` routines.
+    ///
+    /// This is the sum of the size of the fixed header, which includes the
+    /// byte class map,
+    /// [`transition_table_bytes`](enum.DenseDFA.html#method.transition_table_bytes)
+    /// and, for a DFA built from more than one pattern, the size of the table
+    /// recording which patterns match in each match state. When this DFA has
+    /// a start state or a tag for each pattern, their size is included too.
+    pub fn serialized_len(&self) -> usize {
+        HEADER_LEN
+            + self.transition_table_bytes()
+            + mem::size_of_val(self.repr().matches())
+            + mem::size_of_val(self.repr().starts())
+            + mem::size_of_val(self.repr().tags())
+    }
 
-    #[inline]
-    fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
-        match *self {
-            DenseDFA::Standard(ref r) => r.is_match_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.is_match_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => r.is_match_at(bytes, start),
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.is_match_at(bytes, start)
-            }
-            DenseDFA::__Nonexhaustive => unreachable!(),
-        }
+    /// Returns the number of bytes that this DFA's transition table occupies
+    /// when serialized, excluding the header.
+    ///
+    /// The transition table has one entry for each state and each equivalence
+    /// class of bytes, where each entry is the size of the state identifier
+    /// representation. Disabling byte classes therefore usually increases the
+    /// size of the table, while premultiplication never changes it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let pattern = "foo[0-9]+";
+    /// let classes = DenseDFA::new(pattern)?;
+    /// let no_classes = dense::Builder::new().byte_classes(false).build(pattern)?;
+    /// assert!(classes.transition_table_bytes() < no_classes.transition_table_bytes());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn transition_table_bytes(&self) -> usize {
+        mem::size_of_val(self.repr().trans())
     }
+}
 
-    #[inline]
-    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        match *self {
-            DenseDFA::Standard(ref r) => r.shortest_match_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.shortest_match_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => {
-                r.shortest_match_at(bytes, start)
+/// Routines for analyzing the language recognized by a dense DFA.
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
+    /// Returns the same as `find`, but also records every state visited
+    /// during the search in the given coverage tracker.
+    ///
+    /// This is useful for checking how thoroughly a corpus of inputs
+    /// exercises this DFA. Since recording visited states adds overhead to
+    /// every transition, this routine is much slower than `find` and should
+    /// only be used for diagnostics.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the given tracker was not created for a DFA with the
+    /// same number of states as this DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense::{self, CoverageTracker};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("a|bc")?;
+    /// let mut tracker = CoverageTracker::new(&dfa);
+    /// assert_eq!(Some(1), dfa.find_covered(b"a", &mut tracker));
+    /// let before = tracker.coverage();
+    /// assert_eq!(Some(2), dfa.find_covered(b"bc", &mut tracker));
+    /// assert!(tracker.coverage() > before);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_covered(
+        &self,
+        bytes: &[u8],
+        tracker: &mut CoverageTracker,
+    ) -> Option<usize> {
+        assert_eq!(
+            self.state_count(),
+            tracker.visited.len(),
+            "coverage tracker must have one entry for each DFA state",
+        );
+        let repr = self.repr();
+        let mut state = self.start_state_after(None);
+        tracker.visit(repr.state_id_to_index(state));
+        let mut last_match = if self.is_dead_state(state) {
+            return None;
+        } else if self.is_match_state(state) {
+            Some(0)
+        } else {
+            None
+        };
+        let delay = self.has_lookaround() as usize;
+        for (i, &b) in bytes.iter().enumerate() {
+            state = self.next_state(state, b);
+            tracker.visit(repr.state_id_to_index(state));
+            if self.is_match_or_dead_state(state) {
+                if self.is_dead_state(state) {
+                    return last_match;
+                }
+                last_match = Some(i + 1 - delay);
             }
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.shortest_match_at(bytes, start)
+        }
+        if self.has_lookaround() {
+            state = self.next_eoi_state(state);
+            tracker.visit(repr.state_id_to_index(state));
+            if self.is_match_state(state) {
+                last_match = Some(bytes.len());
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+        last_match
     }
 
-    #[inline]
-    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        match *self {
-            DenseDFA::Standard(ref r) => r.find_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.find_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => r.find_at(bytes, start),
-            DenseDFA::PremultipliedByteClass(ref r) => r.find_at(bytes, start),
-            DenseDFA::__Nonexhaustive => unreachable!(),
-        }
+    /// Returns the length of the longest prefix on which this DFA and the
+    /// given DFA agree, or `None` if they never disagree.
+    ///
+    /// This is computed by walking both DFAs in lockstep, breadth first,
+    /// until a pair of states is found where exactly one of them is a match
+    /// state, or where some byte leads exactly one of them to a dead state.
+    /// The depth of the first such pair is returned.
+    ///
+    /// This is most useful with anchored DFAs. For example, this can be
+    /// used to discover that `foobar` and `foobaz` could be factored into
+    /// `fooba(?:r|z)`. For unanchored DFAs, the unanchored prefix is walked
+    /// like any other part of the DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    /// let dfa1 = builder.build("foobar")?;
+    /// let dfa2 = builder.build("foobaz")?;
+    /// assert_eq!(Some(5), dfa1.shared_prefix_len(&dfa2));
+    /// assert_eq!(None, dfa1.shared_prefix_len(&dfa1));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn shared_prefix_len<D: DFA>(&self, other: &D) -> Option<usize> {
+        analysis::shared_prefix_len(self, other)
     }
 
-    #[inline]
-    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        match *self {
-            DenseDFA::Standard(ref r) => r.rfind_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.rfind_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => r.rfind_at(bytes, start),
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.rfind_at(bytes, start)
-            }
-            DenseDFA::__Nonexhaustive => unreachable!(),
-        }
+    /// Returns true if and only if the language recognized by this DFA is
+    /// finite. That is, this returns false when this DFA can match inputs of
+    /// arbitrary length.
+    ///
+    /// The language is finite precisely when there is no cycle among the
+    /// states that are reachable from the start state and that can reach a
+    /// match state.
+    ///
+    /// Note that the language of an unanchored DFA includes an implicit
+    /// `.*?` prefix, which means the language of an unanchored DFA is only
+    /// finite when it never matches anything. Therefore, this routine is
+    /// most useful with anchored DFAs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    /// assert!(builder.build(r"[0-9]{3}")?.is_finite_language());
+    /// assert!(!builder.build(r"[0-9]+")?.is_finite_language());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn is_finite_language(&self) -> bool {
+        analysis::is_finite_language(self)
     }
-}
 
-/// A standard dense DFA that does not use premultiplication or byte classes.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct Standard<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
-
-impl<T: AsRef<[S]>, S: StateID> DFA for Standard<T, S> {
-    type ID = S;
-
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
+    /// Returns true if and only if this DFA never matches any input.
+    ///
+    /// This is useful for detecting patterns that can never match, such as
+    /// `a$b`, when a DFA is built instead of when it is first used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// assert!(dense::Builder::new().build(r"a$b")?.is_empty_language());
+    /// assert!(!dense::Builder::new().build(r"[0-9]+")?.is_empty_language());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn is_empty_language(&self) -> bool {
+        self.some_matching_input().is_none()
     }
 
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+    /// Returns the length of the shortest input on which this DFA reports a
+    /// match, or `None` if this DFA never matches anything.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build(r"[a-z]{3}|[0-9]{2}")?;
+    /// assert_eq!(Some(2), dfa.shortest_match_len());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn shortest_match_len(&self) -> Option<usize> {
+        self.some_matching_input().map(|input| input.len())
     }
 
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+    /// Returns an input on which this DFA reports a match, or `None` if this
+    /// DFA never matches anything.
+    ///
+    /// The input returned is as short as possible, and among inputs of the
+    /// same length, it is the smallest lexicographically. It is found by a
+    /// breadth first search over this DFA's transitions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build(r"[a-z]{3}|[0-9]{2}")?;
+    /// assert_eq!(Some(b"00".to_vec()), dfa.some_matching_input());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn some_matching_input(&self) -> Option<Vec<u8>> {
+        analysis::shortest_matching_input(self)
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+    /// Returns true if and only if this DFA and the given DFA match
+    /// precisely the same inputs.
+    ///
+    /// Two DFAs are equivalent when every input leads both of them to a
+    /// match state or both of them to a non-match state. This is decided
+    /// without minimizing either DFA, using the algorithm of Hopcroft and
+    /// Karp. Which patterns match in each match state is not compared.
+    ///
+    /// DFAs that differ in whether they are anchored or whether they
+    /// evaluate look-around assertions are never equivalent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    /// let old = builder.build(r"(?:ab|ac)+")?;
+    /// let new = builder.build(r"(?:a[bc])+")?;
+    /// assert!(old.is_equivalent(&new));
+    /// assert!(old.is_equivalent(&new.to_sparse()?));
+    /// assert!(!old.is_equivalent(&builder.build(r"(?:a[bc])*")?));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn is_equivalent<D: DFA>(&self, other: &D) -> bool {
+        analysis::is_equivalent(self, other)
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
-    }
-
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() * ALPHABET_LEN + input as usize;
-        self.0.trans()[o]
-    }
-
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() * ALPHABET_LEN + input as usize;
-        *self.0.trans().get_unchecked(o)
+    /// Returns true if and only if this DFA and the given DFA agree on all
+    /// prefixes of length `len` or less.
+    ///
+    /// See [`shared_prefix_len`](enum.DenseDFA.html#method.shared_prefix_len)
+    /// for more details.
+    pub fn shares_prefix_of_len<D: DFA>(&self, other: &D, len: usize) -> bool {
+        match self.shared_prefix_len(other) {
+            None => true,
+            Some(n) => n >= len,
+        }
     }
 }
 
-/// A dense DFA that shrinks its alphabet.
-///
-/// Alphabet shrinking is achieved by using a set of equivalence classes
-/// instead of using all possible byte values. Any two bytes belong to the same
-/// equivalence class if and only if they can be used interchangeably anywhere
-/// in the DFA while never discriminating between a match and a non-match.
-///
-/// This type of DFA can result in significant space reduction with a very
-/// small match time performance penalty.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct ByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
-
-impl<T: AsRef<[S]>, S: StateID> DFA for ByteClass<T, S> {
-    type ID = S;
-
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
-    }
-
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
-    }
-
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
-    }
-
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+/// Routines for combining dense DFAs with set operations on the languages
+/// they recognize.
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
+    /// Returns a DFA that matches every input matched by both this DFA and
+    /// the given DFA.
+    ///
+    /// The languages combined are the sets of inputs on which each DFA ends
+    /// in a match state, so both DFAs must be anchored. They must also be
+    /// free of look-around assertions and quit bytes. Otherwise, an error is
+    /// returned. An error is also returned when the result doesn't fit in
+    /// the state identifier representation `S`.
+    ///
+    /// The DFA returned is minimized, is built from a single pattern and
+    /// uses byte classes that refine those of both DFAs.
+    ///
+    /// This is useful for checking whether two patterns overlap, by testing
+    /// whether their intersection is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    /// let ids = builder.build(r"[a-z]+[0-9]*")?;
+    /// let hex = builder.build(r"[0-9a-f]+")?;
+    /// let both = ids.intersect(&hex)?;
+    /// assert_eq!(Some(b"a".to_vec()), both.some_matching_input());
+    /// assert!(both.is_match(b"beef01"));
+    /// assert!(!both.is_match(b"01"));
+    ///
+    /// let digits = builder.build(r"[0-9]+")?;
+    /// assert!(ids.intersect(&digits)?.is_empty_language());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn intersect<U: AsRef<[S]>>(
+        &self,
+        other: &DenseDFA<U, S>,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.combine(other, |a, b| a && b)
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+    /// Returns a DFA that matches every input matched by either this DFA or
+    /// the given DFA.
+    ///
+    /// This has the same requirements as
+    /// [`intersect`](enum.DenseDFA.html#method.intersect).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    /// let letters = builder.build(r"[a-z]+")?;
+    /// let digits = builder.build(r"[0-9]+")?;
+    /// let either = letters.union(&digits)?;
+    /// assert_eq!(Some(3), either.find(b"abc"));
+    /// assert_eq!(Some(3), either.find(b"123"));
+    /// assert_eq!(None, either.find(b"-"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn union<U: AsRef<[S]>>(
+        &self,
+        other: &DenseDFA<U, S>,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.combine(other, |a, b| a || b)
     }
 
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get(input);
-        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
-        self.0.trans()[o]
+    /// Returns a DFA that matches every input not matched by this DFA.
+    ///
+    /// Since the dead state of this DFA becomes a match state that
+    /// transitions to itself on every byte, the complement of a DFA that
+    /// matches only finitely many inputs matches every sufficiently long
+    /// input.
+    ///
+    /// This has the same requirements as
+    /// [`intersect`](enum.DenseDFA.html#method.intersect).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    /// let words = builder.build(r"[a-z]+")?;
+    /// let other = words.complement()?;
+    /// assert_eq!(Some(vec![]), other.some_matching_input());
+    /// assert!(other.is_match(b"1abc"));
+    /// assert!(words.intersect(&other)?.is_empty_language());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn complement(&self) -> Result<DenseDFA<Vec<S>, S>> {
+        self.combine(self, |a, _| !a)
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get_unchecked(input);
-        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
-        *self.0.trans().get_unchecked(o)
+    fn combine<U: AsRef<[S]>>(
+        &self,
+        other: &DenseDFA<U, S>,
+        is_match: fn(bool, bool) -> bool,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let (a, b) = (self.repr(), other.repr());
+        for repr in &[a.as_ref(), b.as_ref()] {
+            if !repr.is_anchored()
+                || repr.has_lookaround()
+                || repr.quit_bytes().iter().next().is_some()
+            {
+                return Err(Error::unsupported_product());
+            }
+        }
+        let classes = a.byte_classes().merge(b.byte_classes());
+        let mut dfa = product::product(self, other, classes, is_match)?;
+        dfa.minimize();
+        dfa.premultiply()?;
+        dfa.accelerate();
+        Ok(dfa.into_dense_dfa())
     }
 }
 
-/// A dense DFA that premultiplies all of its state identifiers in its
-/// transition table.
-///
-/// This saves an instruction per byte at match time which improves search
-/// performance.
-///
-/// The only downside of premultiplication is that it may prevent one from
-/// using a smaller state identifier representation than you otherwise could.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct Premultiplied<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
-
-impl<T: AsRef<[S]>, S: StateID> DFA for Premultiplied<T, S> {
-    type ID = S;
+/// Routines for converting a dense DFA to other representations, such as
+/// sparse DFAs, smaller state identifiers or raw bytes suitable for persistent
+/// storage.
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
+    /// Convert this dense DFA to a DFA whose states share identical rows of
+    /// transitions.
+    ///
+    /// See [`SharedRowDFA`](dense/struct.SharedRowDFA.html) for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dense = DenseDFA::new("foo[0-9]+")?;
+    /// let shared = dense.to_shared_rows();
+    /// assert!(shared.row_count() <= dense.state_count());
+    /// assert_eq!(Some(8), shared.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_shared_rows(&self) -> SharedRowDFA<S> {
+        SharedRowDFA::new(self.repr())
+    }
 
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
+    /// Convert this dense DFA to a sparse DFA.
+    ///
+    /// This is a convenience routine for `to_sparse_sized` that fixes the
+    /// state identifier representation of the sparse DFA to the same
+    /// representation used for this dense DFA.
+    ///
+    /// If the chosen state identifier representation is too small to represent
+    /// all states in the sparse DFA, then this returns an error. In most
+    /// cases, if a dense DFA is constructable with `S` then a sparse DFA will
+    /// be as well. However, it is not guaranteed.
+    ///
+    /// Sparse DFAs cannot record which patterns match in each match state,
+    /// so this also returns an error if this DFA was built from more than one
+    /// pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dense = DenseDFA::new("foo[0-9]+")?;
+    /// let sparse = dense.to_sparse()?;
+    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_sparse(&self) -> Result<SparseDFA<Vec<u8>, S>> {
+        self.to_sparse_sized()
     }
 
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+    /// Convert this dense DFA to a sparse DFA.
+    ///
+    /// Using this routine requires supplying a type hint to choose the state
+    /// identifier representation for the resulting sparse DFA.
+    ///
+    /// If the chosen state identifier representation is too small to represent
+    /// all states in the sparse DFA, then this returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dense = DenseDFA::new("foo[0-9]+")?;
+    /// let sparse = dense.to_sparse_sized::<u8>()?;
+    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_sparse_sized<A: StateID>(
+        &self,
+    ) -> Result<SparseDFA<Vec<u8>, A>> {
+        self.repr().to_sparse_sized()
     }
 
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u8` for the representation of state identifiers.
+    /// If `u8` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u8>()`. Since state
+    /// identifiers in a premultiplied DFA grow with the size of its
+    /// alphabet, premultiplication is undone when that is required for the
+    /// DFA to fit. That is, any DFA with at most 256 states can be converted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .minimize(true)
+    ///     .build("if|else|while|for|return")?;
+    /// let small = dfa.to_u8()?;
+    /// assert_eq!(dfa.state_count(), small.state_count());
+    /// assert!(small.memory_usage() < dfa.memory_usage());
+    /// assert_eq!(Some(6), small.find(b"x else"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_u8(&self) -> Result<DenseDFA<Vec<u8>, u8>> {
+        self.to_sized()
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u16` for the representation of state identifiers.
+    /// If `u16` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u16>()`.
+    pub fn to_u16(&self) -> Result<DenseDFA<Vec<u16>, u16>> {
+        self.to_sized()
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u32` for the representation of state identifiers.
+    /// If `u32` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u32>()`.
+    #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
+    pub fn to_u32(&self) -> Result<DenseDFA<Vec<u32>, u32>> {
+        self.to_sized()
     }
 
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() + input as usize;
-        self.0.trans()[o]
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u64` for the representation of state identifiers.
+    /// If `u64` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u64>()`.
+    #[cfg(target_pointer_width = "64")]
+    pub fn to_u64(&self) -> Result<DenseDFA<Vec<u64>, u64>> {
+        self.to_sized()
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() + input as usize;
-        *self.0.trans().get_unchecked(o)
+    /// Create a new DFA whose match semantics are equivalent to this DFA, but
+    /// attempt to use `A` for the representation of state identifiers. If `A`
+    /// is insufficient to represent all state identifiers in this DFA, then
+    /// this returns an error.
+    ///
+    /// An alternative way to construct such a DFA is to use
+    /// [`dense::Builder::build_with_size`](dense/struct.Builder.html#method.build_with_size).
+    /// In general, using the builder is preferred since it will use the given
+    /// state identifier representation throughout determinization (and
+    /// minimization, if done), and thereby using less memory throughout the
+    /// entire construction process. However, these routines are necessary
+    /// in cases where, say, a minimized DFA could fit in a smaller state
+    /// identifier representation, but the initial determinized DFA would not.
+    ///
+    /// If this DFA is premultiplied, but its premultiplied state identifiers
+    /// don't fit in `A` while its state indices do, then the DFA returned is
+    /// not premultiplied. Searching a DFA without premultiplication is
+    /// slightly slower, but this permits converting any DFA to `A` as long
+    /// as its number of states fits.
+    ///
+    /// This can also be used to widen the representation of state
+    /// identifiers, which always succeeds. For example, a DFA that was
+    /// deserialized from a blob using `u16` state identifiers can be
+    /// converted to a DFA using `u32` state identifiers with `to_u32`. Every
+    /// transition is re-encoded, and the resulting DFA has precisely the same
+    /// states, transitions and match semantics as the original.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let small = DenseDFA::new("foo[0-9]+")?.to_u16()?;
+    /// let big = small.to_u32()?;
+    /// assert_eq!(small.state_count(), big.state_count());
+    /// assert_eq!(Some(8), big.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_sized<A: StateID>(&self) -> Result<DenseDFA<Vec<A>, A>> {
+        self.repr().to_sized().map(|r| r.into_dense_dfa())
+    }
+
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// using the smallest state identifier representation, among `u8`, `u16`
+    /// and `u32`, that can fit all of this DFA's state identifiers.
+    ///
+    /// This is useful for shrinking a DFA built with `usize` state
+    /// identifiers, such as a minimized DFA, without needing to know ahead of
+    /// time how many states it has. If no representation can fit this DFA,
+    /// then this returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?.to_smallest()?;
+    /// assert_eq!(1, dfa.state_id_size());
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    ///
+    /// // The wrapped DFA can also be used directly.
+    /// if let dense::SizedDFA::U8(ref small) = dfa {
+    ///     assert_eq!(Some(8), small.find(b"foo12345"));
+    /// }
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_smallest(&self) -> Result<SizedDFA> {
+        SizedDFA::smallest(self)
+    }
+
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in little
+    /// endian format.
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
+    /// implementations of `StateID` provided by this crate satisfy this
+    /// requirement.
+    pub fn to_bytes_little_endian(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<LittleEndian>()
+    }
+
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in big
+    /// endian format.
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
+    /// implementations of `StateID` provided by this crate satisfy this
+    /// requirement.
+    pub fn to_bytes_big_endian(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<BigEndian>()
+    }
+
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in native
+    /// endian format. Generally, it is better to pick an explicit endianness
+    /// using either `to_bytes_little_endian` or `to_bytes_big_endian`. This
+    /// routine is useful in tests where the DFA is serialized and deserialized
+    /// on the same platform.
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
+    /// implementations of `StateID` provided by this crate satisfy this
+    /// requirement.
+    pub fn to_bytes_native_endian(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<NativeEndian>()
+    }
+
+    /// Serialize a DFA to raw bytes in a portable format, which can be
+    /// deserialized on targets of any endianness.
+    ///
+    /// The portable format is the little endian format. Bytes in this format
+    /// can be deserialized with
+    /// [`from_bytes_portable`](enum.DenseDFA.html#method.from_bytes_portable),
+    /// which converts them to native endian format in a single pass over the
+    /// DFA. This permits shipping a single serialized DFA for every target,
+    /// at the cost of copying it when it's loaded. When loading must be
+    /// free, ship a DFA serialized in the endianness of each target instead,
+    /// and deserialize it with
+    /// [`from_bytes`](enum.DenseDFA.html#method.from_bytes).
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
+    /// implementations of `StateID` provided by this crate satisfy this
+    /// requirement.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial = DenseDFA::new("foo[0-9]+")?.to_u16()?;
+    /// let bytes = initial.to_bytes_portable()?;
+    /// let dfa: DenseDFA<Vec<u16>, u16> =
+    ///     DenseDFA::from_bytes_portable(&bytes).unwrap();
+    ///
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_bytes_portable(&self) -> Result<AlignedBytes> {
+        self.to_bytes_little_endian()
     }
 }
 
-/// The default configuration of a dense DFA, which uses byte classes and
-/// premultiplies its state identifiers.
+/// Embed a serialized dense DFA into the binary and deserialize it.
 ///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct PremultipliedByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+/// This takes the path of a file containing a dense DFA serialized in the
+/// endianness of the target, relative to the file invoking the macro, and
+/// the state identifier representation used by the DFA. It evaluates to a
+/// `DenseDFA<&'static [S], S>` that borrows the embedded bytes.
+///
+/// Bytes embedded with `include_bytes!` are only guaranteed to be aligned
+/// to a 1 byte boundary, while a dense DFA must be aligned to its state
+/// identifier representation. This macro embeds the bytes in a static that
+/// is aligned to an 8 byte boundary, and then deserializes them with
+/// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes). This
+/// never allocates and takes constant time: only the fields of the header
+/// are read and checked, while the transition table is used in place.
+/// This makes it suitable for `no_std` environments.
+///
+/// Since `from_bytes` is unsafe, this macro must be invoked in an unsafe
+/// context. The caller must guarantee that the file contains a dense DFA
+/// serialized by this crate. Typically, the file is generated by a build
+/// step with the same version of this crate.
+///
+/// # Panics
+///
+/// This panics under the same conditions as `DenseDFA::from_bytes`, e.g.,
+/// if the file was serialized with a different endianness or state
+/// identifier representation.
+///
+/// # Example
+///
+/// This example assumes that `date.dfa` contains a dense DFA using `u16`
+/// state identifiers, serialized for the target with, e.g.,
+/// [`DenseDFA::to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian).
+///
+/// ```ignore
+/// #[macro_use]
+/// extern crate regex_automata;
+///
+/// use regex_automata::{DenseDFA, DFA};
+///
+/// fn main() {
+///     let dfa: DenseDFA<&'static [u16], u16> =
+///         unsafe { include_dfa!("date.dfa", u16) };
+///     assert_eq!(Some(10), dfa.find(b"2018-12-24"));
+/// }
+/// ```
+#[macro_export]
+macro_rules! include_dfa {
+    ($path:expr, $S:ty) => {{
+        // The empty array forces the alignment of the bytes that follow it,
+        // and the unsized field lets the static hold bytes of any length.
+        #[repr(C)]
+        struct Aligned<B: ?Sized> {
+            _align: [u64; 0],
+            bytes: B,
+        }
+        static ALIGNED: &'static Aligned<[u8]> =
+            &Aligned { _align: [], bytes: *include_bytes!($path) };
+        $crate::DenseDFA::<&'static [$S], $S>::from_bytes(&ALIGNED.bytes)
+    }};
+}
 
-impl<T: AsRef<[S]>, S: StateID> DFA for PremultipliedByteClass<T, S> {
+impl<'a, S: StateID> DenseDFA<&'a [S], S> {
+    /// Deserialize a DFA with a specific state identifier representation.
+    ///
+    /// Deserializing a DFA using this routine will never allocate heap memory.
+    /// This is also guaranteed to be a constant time operation that does not
+    /// vary with the size of the DFA.
+    ///
+    /// The bytes given should be generated by the serialization of a DFA with
+    /// either the
+    /// [`to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)
+    /// method or the
+    /// [`to_bytes_big_endian`](enum.DenseDFA.html#method.to_bytes_big_endian)
+    /// endian, depending on the endianness of the machine you are
+    /// deserializing this DFA from.
+    ///
+    /// The bytes must be aligned to the state identifier representation.
+    /// The serialization routines return an
+    /// [`AlignedBytes`](struct.AlignedBytes.html), which is always suitably
+    /// aligned. Bytes from elsewhere, e.g., read from a file into a
+    /// `Vec<u8>`, can be copied to an `AlignedBytes` with
+    /// [`AlignedBytes::from_slice`](struct.AlignedBytes.html#method.from_slice).
+    ///
+    /// If the state identifier representation is `usize`, then deserialization
+    /// is dependent on the pointer size. For this reason, it is best to
+    /// serialize DFAs using a fixed size representation for your state
+    /// identifiers, such as `u8`, `u16`, `u32` or `u64`.
+    ///
+    /// # Panics
+    ///
+    /// The bytes given should be *trusted*. In particular, if the bytes
+    /// are not a valid serialization of a DFA, or if the given bytes are
+    /// not aligned to an 8 byte boundary, or if the endianness of the
+    /// serialized bytes is different than the endianness of the machine that
+    /// is deserializing the DFA, then this routine will panic. Moreover, it is
+    /// possible for this deserialization routine to succeed even if the given
+    /// bytes do not represent a valid serialized dense DFA.
+    ///
+    /// # Safety
+    ///
+    /// This routine is unsafe because it permits callers to provide an
+    /// arbitrary transition table with possibly incorrect transitions. While
+    /// the various serialization routines will never return an incorrect
+    /// transition table, there is no guarantee that the bytes provided here
+    /// are correct. While deserialization does many checks (as documented
+    /// above in the panic conditions), this routine does not check that the
+    /// transition table is correct. Given an incorrect transition table, it is
+    /// possible for the search routines to access out-of-bounds memory because
+    /// of explicit bounds check elision. To deserialize bytes that aren't
+    /// trusted, use
+    /// [`try_from_bytes`](enum.DenseDFA.html#method.try_from_bytes) instead.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to serialize a DFA to raw bytes, deserialize it
+    /// and then use it for searching. Note that we first convert the DFA to
+    /// using `u16` for its state identifier representation before serializing
+    /// it. While this isn't strictly necessary, it's good practice in order to
+    /// decrease the size of the DFA and to avoid platform specific pitfalls
+    /// such as differing pointer sizes.
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial = DenseDFA::new("foo[0-9]+")?;
+    /// let bytes = initial.to_u16()?.to_bytes_native_endian()?;
+    /// let dfa: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes(&bytes)
+    /// };
+    ///
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub unsafe fn from_bytes(buf: &'a [u8]) -> DenseDFA<&'a [S], S> {
+        Repr::from_bytes(buf).into_dense_dfa()
+    }
+
+    /// Deserialize a DFA with a specific state identifier representation,
+    /// after validating that the bytes given represent a valid DFA.
+    ///
+    /// This is the safe counterpart to
+    /// [`from_bytes`](enum.DenseDFA.html#method.from_bytes), and accepts the
+    /// same bytes. Like `from_bytes`, this never allocates heap memory.
+    /// Unlike `from_bytes`, this never panics and never produces a DFA whose
+    /// searches may access memory out of bounds. Namely, in addition to the
+    /// checks done on the header, this checks that the start state, the last
+    /// match state and every transition in the transition table refer to a
+    /// valid state, and that the dead state only transitions to itself.
+    /// This makes it suitable for loading DFAs from untrusted sources, such
+    /// as files on disk.
+    ///
+    /// The cost of this validation is that deserialization takes time
+    /// linear in the size of the transition table. For trusted bytes that
+    /// are, say, embedded in a binary, `from_bytes` is cheaper.
+    ///
+    /// Validation also checks the bytes against the CRC-32 checksum recorded
+    /// in their header, so accidental corruption, e.g., of a file on disk,
+    /// is reported as a
+    /// [`DeserializeError::ChecksumMismatch`](enum.DeserializeError.html#variant.ChecksumMismatch)
+    /// error. However, a checksum is no defense against bytes that were
+    /// crafted maliciously, and so validation can still only ensure that the
+    /// DFA is well formed. It cannot ensure that the DFA matches the
+    /// language that the bytes were originally produced from.
+    ///
+    /// If the bytes are not a valid serialized dense DFA using the state
+    /// identifier representation `S` and the endianness of the current
+    /// target, or if the bytes are not properly aligned, then an error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial = DenseDFA::new("foo[0-9]+")?;
+    /// let bytes = initial.to_u16()?.to_bytes_native_endian()?;
+    /// let dfa: DenseDFA<&[u16], u16> =
+    ///     DenseDFA::try_from_bytes(&bytes).unwrap();
+    ///
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn try_from_bytes(
+        buf: &'a [u8],
+    ) -> result::Result<DenseDFA<&'a [S], S>, DeserializeError> {
+        Repr::try_from_bytes(buf).map(|r| r.into_dense_dfa())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> DenseDFA<Vec<S>, S> {
+    /// Deserialize a DFA serialized in the portable format, as written by
+    /// [`to_bytes_portable`](enum.DenseDFA.html#method.to_bytes_portable),
+    /// with a specific state identifier representation.
+    ///
+    /// Unlike `from_bytes`, this copies the DFA into a new allocation,
+    /// converting it to native endian format along the way, and so the
+    /// bytes given need not be aligned. The copy is validated in the same
+    /// way as by
+    /// [`try_from_bytes`](enum.DenseDFA.html#method.try_from_bytes).
+    ///
+    /// If the bytes are not a valid dense DFA serialized in the portable
+    /// format using the state identifier representation `S`, then an error
+    /// is returned.
+    pub fn from_bytes_portable(
+        buf: &[u8],
+    ) -> result::Result<DenseDFA<Vec<S>, S>, DeserializeError> {
+        let native = bytes::dense_to_native_endian(buf)?;
+        Repr::<&[S], S>::try_from_bytes(&native)
+            .map(|r| r.to_owned().into_dense_dfa())
+    }
+
+    /// Shrink the allocations of this DFA to fit its tables exactly.
+    ///
+    /// The tables of a DFA grow as its states are added during construction,
+    /// and so a freshly built DFA may hold on to much more memory than
+    /// [`memory_usage`](enum.DenseDFA.html#method.memory_usage) reports.
+    /// This releases the excess, which is worthwhile for a DFA that is kept
+    /// around for a long time. Searching is unaffected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut dfa = DenseDFA::new("foo[0-9]+")?;
+    /// dfa.shrink_to_fit();
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.repr_mut().shrink_to_fit();
+    }
+
+    /// Minimize this DFA in place.
+    ///
+    /// This is not part of the public API. It is only exposed to allow for
+    /// more granular external benchmarking.
+    #[doc(hidden)]
+    pub fn minimize(&mut self) {
+        self.repr_mut().minimize();
+    }
+
+    /// Return a mutable reference to the internal DFA representation.
+    fn repr_mut(&mut self) -> &mut Repr<Vec<S>, S> {
+        match *self {
+            DenseDFA::Standard(ref mut r) => &mut r.0,
+            DenseDFA::ByteClass(ref mut r) => &mut r.0,
+            DenseDFA::Premultiplied(ref mut r) => &mut r.0,
+            DenseDFA::PremultipliedByteClass(ref mut r) => &mut r.0,
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
+    }
+}
+
+impl<T: AsRef<[S]>, S: StateID> DFA for DenseDFA<T, S> {
     type ID = S;
 
     #[inline]
     fn start_state(&self) -> S {
-        self.0.start_state()
+        self.repr().start_state()
     }
 
     #[inline]
     fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+        self.repr().is_match_state(id)
     }
 
     #[inline]
     fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+        self.repr().is_dead_state(id)
     }
 
     #[inline]
     fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+        self.repr().is_match_or_dead_state(id)
     }
 
     #[inline]
     fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+        self.repr().is_anchored()
     }
 
     #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get(input);
-        let o = current.to_usize() + input as usize;
-        self.0.trans()[o]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.repr().is_quit_byte(byte)
     }
 
     #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get_unchecked(input);
-        let o = current.to_usize() + input as usize;
-        *self.0.trans().get_unchecked(o)
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.repr().accelerator(id)
     }
-}
 
-/// The internal representation of a dense DFA.
-///
-/// This representation is shared by all DFA variants.
-#[derive(Clone)]
-#[cfg_attr(not(feature = "std"), derive(Debug))]
-pub(crate) struct Repr<T, S> {
-    /// Whether the state identifiers in the transition table have been
-    /// premultiplied or not.
-    ///
-    /// Premultiplied identifiers means that instead of your matching loop
-    /// looking something like this:
-    ///
-    ///   state = dfa.start
-    ///   for byte in haystack:
-    ///       next = dfa.transitions[state * len(alphabet) + byte]
-    ///       if dfa.is_match(next):
-    ///           return true
-    ///   return false
-    ///
-    /// it can instead look like this:
-    ///
-    ///   state = dfa.start
-    ///   for byte in haystack:
-    ///       next = dfa.transitions[state + byte]
-    ///       if dfa.is_match(next):
-    ///           return true
-    ///   return false
-    ///
-    /// In other words, we save a multiplication instruction in the critical
-    /// path. This turns out to be a decent performance win. The cost of using
-    /// premultiplied state ids is that they can require a bigger state id
-    /// representation.
-    premultiplied: bool,
-    /// Whether this DFA can only match at the beginning of input or not.
-    ///
-    /// When true, a match should only be reported if it begins at the 0th
-    /// index of the haystack.
-    anchored: bool,
-    /// The initial start state ID.
-    start: S,
-    /// The total number of states in this DFA. Note that a DFA always has at
-    /// least one state---the dead state---even the empty DFA. In particular,
-    /// the dead state always has ID 0 and is correspondingly always the first
-    /// state. The dead state is never a match state.
-    state_count: usize,
-    /// States in a DFA have a *partial* ordering such that a match state
-    /// always precedes any non-match state (except for the special dead
-    /// state).
-    ///
-    /// `max_match` corresponds to the last state that is a match state. This
-    /// encoding has two critical benefits. Firstly, we are not required to
-    /// store any additional per-state information about whether it is a match
-    /// state or not. Secondly, when searching with the DFA, we can do a single
-    /// comparison with `max_match` for each byte instead of two comparisons
-    /// for each byte (one testing whether it is a match and the other testing
-    /// whether we've reached a dead state). Namely, to determine the status
-    /// of the next state, we can do this:
-    ///
-    ///   next_state = transition[cur_state * alphabet_len + cur_byte]
-    ///   if next_state <= max_match:
-    ///       // next_state is either dead (no-match) or a match
-    ///       return next_state != dead
-    max_match: S,
-    /// A set of equivalence classes, where a single equivalence class
-    /// represents a set of bytes that never discriminate between a match
-    /// and a non-match in the DFA. Each equivalence class corresponds to
-    /// a single letter in this DFA's alphabet, where the maximum number of
-    /// letters is 256 (each possible value of a byte). Consequently, the
-    /// number of equivalence classes corresponds to the number of transitions
-    /// for each DFA state.
-    ///
-    /// The only time the number of equivalence classes is fewer than 256 is
-    /// if the DFA's kind uses byte classes. If the DFA doesn't use byte
-    /// classes, then this vector is empty.
-    byte_classes: ByteClasses,
-    /// A contiguous region of memory representing the transition table in
-    /// row-major order. The representation is dense. That is, every state has
-    /// precisely the same number of transitions. The maximum number of
-    /// transitions is 256. If a DFA has been instructed to use byte classes,
-    /// then the number of transitions can be much less.
-    ///
-    /// In practice, T is either Vec<S> or &[S].
-    trans: T,
-}
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.repr().is_utf8()
+    }
 
-#[cfg(feature = "std")]
-impl<S: StateID> Repr<Vec<S>, S> {
-    /// Create a new empty DFA with singleton byte classes (every byte is its
-    /// own equivalence class).
-    pub fn empty() -> Repr<Vec<S>, S> {
-        Repr::empty_with_byte_classes(ByteClasses::singletons())
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.repr().has_lookaround()
     }
 
-    /// Create a new empty DFA with the given set of byte equivalence classes.
-    /// An empty DFA never matches any input.
-    pub fn empty_with_byte_classes(
-        byte_classes: ByteClasses,
-    ) -> Repr<Vec<S>, S> {
-        let mut dfa = Repr {
-            premultiplied: false,
-            anchored: true,
-            start: dead_id(),
-            state_count: 0,
-            max_match: S::from_usize(0),
-            byte_classes,
-            trans: vec![],
-        };
-        // Every state ID repr must be able to fit at least one state.
-        dfa.add_empty_state().unwrap();
-        dfa
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        self.repr().next_eoi_state(current)
     }
 
-    /// Sets whether this DFA is anchored or not.
-    pub fn anchored(mut self, yes: bool) -> Repr<Vec<S>, S> {
-        self.anchored = yes;
-        self
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        self.repr().start_state_after(prev)
     }
-}
 
-impl<T: AsRef<[S]>, S: StateID> Repr<T, S> {
-    /// Convert this internal DFA representation to a DenseDFA based on its
-    /// transition table access pattern.
-    pub fn into_dense_dfa(self) -> DenseDFA<T, S> {
-        match (self.premultiplied, self.byte_classes().is_singleton()) {
-            // no premultiplication, no byte classes
-            (false, true) => DenseDFA::Standard(Standard(self)),
-            // no premultiplication, yes byte classes
-            (false, false) => DenseDFA::ByteClass(ByteClass(self)),
-            // yes premultiplication, no byte classes
-            (true, true) => DenseDFA::Premultiplied(Premultiplied(self)),
-            // yes premultiplication, yes byte classes
-            (true, false) => {
-                DenseDFA::PremultipliedByteClass(PremultipliedByteClass(self))
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        self.repr().match_count(id)
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        self.repr().match_pattern(id, index)
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        match *self {
+            DenseDFA::Standard(ref r) => r.next_state(current, input),
+            DenseDFA::ByteClass(ref r) => r.next_state(current, input),
+            DenseDFA::Premultiplied(ref r) => r.next_state(current, input),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.next_state(current, input)
             }
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
 
-    fn as_ref<'a>(&'a self) -> Repr<&'a [S], S> {
-        Repr {
-            premultiplied: self.premultiplied,
-            anchored: self.anchored,
-            start: self.start,
-            state_count: self.state_count,
-            max_match: self.max_match,
-            byte_classes: self.byte_classes().clone(),
-            trans: self.trans(),
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        match *self {
+            DenseDFA::Standard(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::ByteClass(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::Premultiplied(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
 
-    #[cfg(feature = "std")]
-    fn to_owned(&self) -> Repr<Vec<S>, S> {
-        Repr {
-            premultiplied: self.premultiplied,
-            anchored: self.anchored,
-            start: self.start,
-            state_count: self.state_count,
-            max_match: self.max_match,
-            byte_classes: self.byte_classes().clone(),
-            trans: self.trans().to_vec(),
+    // We specialize the following methods because it lets us lift the
+    // case analysis between the different types of dense DFAs. Instead of
+    // doing the case analysis for every transition, we do it once before
+    // searching.
+
+    #[inline]
+    fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
+        match *self {
+            DenseDFA::Standard(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.is_match_at(bytes, start)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
 
-    /// Return the starting state of this DFA.
-    ///
-    /// All searches using this DFA must begin at this state. There is exactly
-    /// one starting state for every DFA. A starting state may be a dead state
-    /// or a matching state or neither.
-    pub fn start_state(&self) -> S {
-        self.start
+    #[inline]
+    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        match *self {
+            DenseDFA::Standard(ref r) => r.shortest_match_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.shortest_match_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => {
+                r.shortest_match_at(bytes, start)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.shortest_match_at(bytes, start)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Returns true if and only if the given identifier corresponds to a match
-    /// state.
-    pub fn is_match_state(&self, id: S) -> bool {
-        id <= self.max_match && id != dead_id()
+    #[inline]
+    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        match *self {
+            DenseDFA::Standard(ref r) => r.find_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.find_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => r.find_at(bytes, start),
+            DenseDFA::PremultipliedByteClass(ref r) => r.find_at(bytes, start),
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Returns true if and only if the given identifier corresponds to a dead
-    /// state.
-    pub fn is_dead_state(&self, id: S) -> bool {
-        id == dead_id()
+    #[inline]
+    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        match *self {
+            DenseDFA::Standard(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.rfind_at(bytes, start)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Returns true if and only if the given identifier could correspond to
-    /// either a match state or a dead state. If this returns false, then the
-    /// given identifier does not correspond to either a match state or a dead
-    /// state.
-    pub fn is_match_or_dead_state(&self, id: S) -> bool {
-        id <= self.max_match_state()
+    #[inline]
+    fn is_match_in(&self, bytes: &[u8], start: usize, end: usize) -> bool {
+        match *self {
+            DenseDFA::Standard(ref r) => r.is_match_in(bytes, start, end),
+            DenseDFA::ByteClass(ref r) => r.is_match_in(bytes, start, end),
+            DenseDFA::Premultiplied(ref r) => r.is_match_in(bytes, start, end),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.is_match_in(bytes, start, end)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Returns the maximum identifier for which a match state can exist.
-    ///
-    /// More specifically, the return identifier always corresponds to either
-    /// a match state or a dead state. Namely, either
-    /// `is_match_state(returned)` or `is_dead_state(returned)` is guaranteed
-    /// to be true.
-    pub fn max_match_state(&self) -> S {
-        self.max_match
+    #[inline]
+    fn shortest_match_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        match *self {
+            DenseDFA::Standard(ref r) => {
+                r.shortest_match_in(bytes, start, end)
+            }
+            DenseDFA::ByteClass(ref r) => {
+                r.shortest_match_in(bytes, start, end)
+            }
+            DenseDFA::Premultiplied(ref r) => {
+                r.shortest_match_in(bytes, start, end)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.shortest_match_in(bytes, start, end)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Returns true if and only if this DFA is anchored.
-    pub fn is_anchored(&self) -> bool {
-        self.anchored
+    #[inline]
+    fn find_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        match *self {
+            DenseDFA::Standard(ref r) => r.find_in(bytes, start, end),
+            DenseDFA::ByteClass(ref r) => r.find_in(bytes, start, end),
+            DenseDFA::Premultiplied(ref r) => r.find_in(bytes, start, end),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.find_in(bytes, start, end)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Return the byte classes used by this DFA.
-    pub fn byte_classes(&self) -> &ByteClasses {
-        &self.byte_classes
+    #[inline]
+    fn rfind_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        match *self {
+            DenseDFA::Standard(ref r) => r.rfind_in(bytes, start, end),
+            DenseDFA::ByteClass(ref r) => r.rfind_in(bytes, start, end),
+            DenseDFA::Premultiplied(ref r) => r.rfind_in(bytes, start, end),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.rfind_in(bytes, start, end)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
+}
 
-    /// Returns an iterator over all states in this DFA.
-    ///
-    /// This iterator yields a tuple for each state. The first element of the
-    /// tuple corresponds to a state's identifier, and the second element
-    /// corresponds to the state itself (comprised of its transitions).
+/// A dense DFA whose state identifier representation is chosen at runtime.
+///
+/// Each variant wraps a dense DFA using `u8`, `u16`, `u32` or `u64` state
+/// identifiers. This is typically constructed with
+/// [`Builder::build_sized`](struct.Builder.html#method.build_sized) or
+/// [`DenseDFA::to_smallest`](enum.DenseDFA.html#method.to_smallest), which
+/// pick the smallest representation that can fit every state identifier of
+/// the DFA. Since the size of a DFA's transition table is proportional to
+/// the size of its state identifiers, this usually results in a DFA that is
+/// two to eight times smaller than one using `usize`.
+///
+/// A `SizedDFA` implements the `DFA` trait using `usize` state identifiers,
+/// so it can be searched like any other DFA. The search routines, such as
+/// `find_at`, inspect the representation once before searching, so
+/// searching costs the same as searching the wrapped DFA directly.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense, DFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = dense::Builder::new().minimize(true).build_sized("foo[0-9]+")?;
+/// assert_eq!(1, dfa.state_id_size());
+/// assert_eq!(Some(8), dfa.find(b"foo12345"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub enum SizedDFA {
+    /// A DFA using `u8` state identifiers.
+    U8(DenseDFA<Vec<u8>, u8>),
+    /// A DFA using `u16` state identifiers.
+    U16(DenseDFA<Vec<u16>, u16>),
+    /// A DFA using `u32` state identifiers.
+    #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
+    U32(DenseDFA<Vec<u32>, u32>),
+    /// A DFA using `u64` state identifiers.
     ///
-    /// If this DFA is premultiplied, then the state identifiers are in
-    /// turn premultiplied as well, making them usable without additional
-    /// modification.
-    #[cfg(feature = "std")]
-    pub fn states(&self) -> StateIter<T, S> {
-        let it = self.trans().chunks(self.alphabet_len());
-        StateIter { dfa: self, it: it.enumerate() }
+    /// This is never chosen automatically. It must be requested with
+    /// [`Builder::state_id_size`](struct.Builder.html#method.state_id_size).
+    #[cfg(target_pointer_width = "64")]
+    U64(DenseDFA<Vec<u64>, u64>),
+}
+
+/// Evaluate the given expression with `$dfa` bound to the DFA wrapped by
+/// each variant of a `SizedDFA`, or to a mutable reference to it when
+/// `$dfa` is preceded by `mut`.
+#[cfg(feature = "std")]
+macro_rules! sized {
+    ($sized:expr, mut $dfa:ident => $expr:expr) => {
+        match *$sized {
+            SizedDFA::U8(ref mut $dfa) => $expr,
+            SizedDFA::U16(ref mut $dfa) => $expr,
+            #[cfg(any(
+                target_pointer_width = "32",
+                target_pointer_width = "64"
+            ))]
+            SizedDFA::U32(ref mut $dfa) => $expr,
+            #[cfg(target_pointer_width = "64")]
+            SizedDFA::U64(ref mut $dfa) => $expr,
+        }
+    };
+    ($sized:expr, $dfa:ident => $expr:expr) => {
+        match *$sized {
+            SizedDFA::U8(ref $dfa) => $expr,
+            SizedDFA::U16(ref $dfa) => $expr,
+            #[cfg(any(
+                target_pointer_width = "32",
+                target_pointer_width = "64"
+            ))]
+            SizedDFA::U32(ref $dfa) => $expr,
+            #[cfg(target_pointer_width = "64")]
+            SizedDFA::U64(ref $dfa) => $expr,
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+impl SizedDFA {
+    /// Convert the given DFA to the smallest state identifier representation
+    /// that can fit all of its state identifiers.
+    fn smallest<T: AsRef<[S]>, S: StateID>(
+        dfa: &DenseDFA<T, S>,
+    ) -> Result<SizedDFA> {
+        if let Ok(dfa) = dfa.to_u8() {
+            return Ok(SizedDFA::U8(dfa));
+        }
+        #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
+        {
+            if let Ok(dfa) = dfa.to_u16() {
+                return Ok(SizedDFA::U16(dfa));
+            }
+            dfa.to_u32().map(SizedDFA::U32)
+        }
+        #[cfg(not(any(
+            target_pointer_width = "32",
+            target_pointer_width = "64"
+        )))]
+        {
+            dfa.to_u16().map(SizedDFA::U16)
+        }
     }
 
-    /// Return the total number of states in this DFA. Every DFA has at least
-    /// 1 state, even the empty DFA.
-    #[cfg(feature = "std")]
-    pub fn state_count(&self) -> usize {
-        self.state_count
+    /// Convert the given DFA to a representation using state identifiers of
+    /// the given size in bytes, which must be 1, 2, 4 or 8. If the DFA's state
+    /// identifiers don't fit, then this returns an error.
+    fn with_size<T: AsRef<[S]>, S: StateID>(
+        dfa: &DenseDFA<T, S>,
+        size: usize,
+    ) -> Result<SizedDFA> {
+        match size {
+            1 => dfa.to_u8().map(SizedDFA::U8),
+            2 => dfa.to_u16().map(SizedDFA::U16),
+            #[cfg(any(
+                target_pointer_width = "32",
+                target_pointer_width = "64"
+            ))]
+            4 => dfa.to_u32().map(SizedDFA::U32),
+            #[cfg(target_pointer_width = "64")]
+            8 => dfa.to_u64().map(SizedDFA::U64),
+            _ => panic!("unsupported state id size: {}", size),
+        }
     }
 
-    /// Return the number of elements in this DFA's alphabet.
-    ///
-    /// If this DFA doesn't use byte classes, then this is always equivalent
-    /// to 256. Otherwise, it is guaranteed to be some value less than or equal
-    /// to 256.
-    pub fn alphabet_len(&self) -> usize {
-        self.byte_classes().alphabet_len()
+    /// Returns the size, in bytes, of this DFA's state identifiers.
+    pub fn state_id_size(&self) -> usize {
+        match *self {
+            SizedDFA::U8(_) => 1,
+            SizedDFA::U16(_) => 2,
+            #[cfg(any(
+                target_pointer_width = "32",
+                target_pointer_width = "64"
+            ))]
+            SizedDFA::U32(_) => 4,
+            #[cfg(target_pointer_width = "64")]
+            SizedDFA::U64(_) => 8,
+        }
     }
 
     /// Returns the memory usage, in bytes, of this DFA.
     pub fn memory_usage(&self) -> usize {
-        self.trans().len() * mem::size_of::<S>()
+        sized!(self, dfa => dfa.memory_usage())
     }
 
-    /// Convert the given state identifier to the state's index. The state's
-    /// index corresponds to the position in which it appears in the transition
-    /// table. When a DFA is NOT premultiplied, then a state's identifier is
-    /// also its index. When a DFA is premultiplied, then a state's identifier
-    /// is equal to `index * alphabet_len`. This routine reverses that.
-    #[cfg(feature = "std")]
-    pub fn state_id_to_index(&self, id: S) -> usize {
-        if self.premultiplied {
-            id.to_usize() / self.alphabet_len()
-        } else {
-            id.to_usize()
-        }
+    /// Returns the total number of states in this DFA, including the dead
+    /// state.
+    pub fn state_count(&self) -> usize {
+        sized!(self, dfa => dfa.state_count())
     }
 
-    /// Return this DFA's transition table as a slice.
-    fn trans(&self) -> &[S] {
-        self.trans.as_ref()
+    /// Shrink the allocations of this DFA to fit its tables exactly.
+    ///
+    /// See
+    /// [`DenseDFA::shrink_to_fit`](enum.DenseDFA.html#method.shrink_to_fit).
+    pub fn shrink_to_fit(&mut self) {
+        sized!(self, mut dfa => dfa.shrink_to_fit())
     }
 
-    /// Create a sparse DFA from the internal representation of a dense DFA.
-    #[cfg(feature = "std")]
-    pub fn to_sparse_sized<A: StateID>(
-        &self,
-    ) -> Result<SparseDFA<Vec<u8>, A>> {
-        SparseDFA::from_dense_sized(self)
+    /// Serialize this DFA to raw bytes in little endian format.
+    ///
+    /// Deserializing the bytes requires using the same state identifier
+    /// representation, which can be found with
+    /// [`state_id_size`](enum.SizedDFA.html#method.state_id_size).
+    pub fn to_bytes_little_endian(&self) -> Result<AlignedBytes> {
+        sized!(self, dfa => dfa.to_bytes_little_endian())
     }
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA, but
-    /// attempt to use `A` for the representation of state identifiers. If `A`
-    /// is insufficient to represent all state identifiers in this DFA, then
-    /// this returns an error.
-    #[cfg(feature = "std")]
-    pub fn to_sized<A: StateID>(&self) -> Result<Repr<Vec<A>, A>> {
-        // Check that this DFA can fit into A's representation.
-        let mut last_state_id = self.state_count - 1;
-        if self.premultiplied {
-            last_state_id *= self.alphabet_len();
-        }
-        if last_state_id > A::max_id() {
-            return Err(Error::state_id_overflow(A::max_id()));
-        }
+    /// Serialize this DFA to raw bytes in big endian format.
+    ///
+    /// Deserializing the bytes requires using the same state identifier
+    /// representation, which can be found with
+    /// [`state_id_size`](enum.SizedDFA.html#method.state_id_size).
+    pub fn to_bytes_big_endian(&self) -> Result<AlignedBytes> {
+        sized!(self, dfa => dfa.to_bytes_big_endian())
+    }
 
-        // We're off to the races. The new DFA is the same as the old one,
-        // but its transition table is truncated.
-        let mut new = Repr {
-            premultiplied: self.premultiplied,
-            anchored: self.anchored,
-            start: A::from_usize(self.start.to_usize()),
-            state_count: self.state_count,
-            max_match: A::from_usize(self.max_match.to_usize()),
-            byte_classes: self.byte_classes().clone(),
-            trans: vec![dead_id::<A>(); self.trans().len()],
-        };
-        for (i, id) in new.trans.iter_mut().enumerate() {
-            *id = A::from_usize(self.trans()[i].to_usize());
-        }
-        Ok(new)
+    /// Serialize this DFA to raw bytes in native endian format.
+    ///
+    /// Deserializing the bytes requires using the same state identifier
+    /// representation, which can be found with
+    /// [`state_id_size`](enum.SizedDFA.html#method.state_id_size).
+    pub fn to_bytes_native_endian(&self) -> Result<AlignedBytes> {
+        sized!(self, dfa => dfa.to_bytes_native_endian())
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary.
+    /// Serialize this DFA to raw bytes in the portable format, which is
+    /// described by
+    /// [`DenseDFA::to_bytes_portable`](enum.DenseDFA.html#method.to_bytes_portable).
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    #[cfg(feature = "std")]
-    pub(crate) fn to_bytes<A: ByteOrder>(&self) -> Result<Vec<u8>> {
-        let label = b"rust-regex-automata-dfa\x00";
-        assert_eq!(24, label.len());
+    /// Deserializing the bytes requires using the same state identifier
+    /// representation, which can be found with
+    /// [`state_id_size`](enum.SizedDFA.html#method.state_id_size).
+    pub fn to_bytes_portable(&self) -> Result<AlignedBytes> {
+        sized!(self, dfa => dfa.to_bytes_portable())
+    }
+}
 
-        let trans_size = mem::size_of::<S>() * self.trans().len();
-        let size =
-            // For human readable label.
-            label.len()
-            // endiannes check, must be equal to 0xFEFF for native endian
-            + 2
-            // For version number.
-            + 2
-            // Size of state ID representation, in bytes.
-            // Must be 1, 2, 4 or 8.
-            + 2
-            // For DFA misc options.
-            + 2
-            // For start state.
-            + 8
-            // For state count.
-            + 8
-            // For max match state.
-            + 8
-            // For byte class map.
-            + 256
-            // For transition table.
-            + trans_size;
-        // sanity check, this can be updated if need be
-        assert_eq!(312 + trans_size, size);
-        // This must always pass. It checks that the transition table is at
-        // a properly aligned address.
-        assert_eq!(0, (size - trans_size) % 8);
+#[cfg(feature = "std")]
+impl DFA for SizedDFA {
+    type ID = usize;
 
-        let mut buf = vec![0; size];
-        let mut i = 0;
+    #[inline]
+    fn start_state(&self) -> usize {
+        sized!(self, dfa => dfa.start_state().to_usize())
+    }
 
-        // write label
-        for &b in label {
-            buf[i] = b;
-            i += 1;
-        }
-        // endianness check
-        A::write_u16(&mut buf[i..], 0xFEFF);
-        i += 2;
-        // version number
-        A::write_u16(&mut buf[i..], 1);
-        i += 2;
-        // size of state ID
-        let state_size = mem::size_of::<S>();
-        if ![1, 2, 4, 8].contains(&state_size) {
-            return Err(Error::serialize(&format!(
-                "state size of {} not supported, must be 1, 2, 4 or 8",
-                state_size
-            )));
-        }
-        A::write_u16(&mut buf[i..], state_size as u16);
-        i += 2;
-        // DFA misc options
-        let mut options = 0u16;
-        if self.premultiplied {
-            options |= MASK_PREMULTIPLIED;
-        }
-        if self.anchored {
-            options |= MASK_ANCHORED;
-        }
-        A::write_u16(&mut buf[i..], options);
-        i += 2;
-        // start state
-        A::write_u64(&mut buf[i..], self.start.to_usize() as u64);
-        i += 8;
-        // state count
-        A::write_u64(&mut buf[i..], self.state_count as u64);
-        i += 8;
-        // max match state
-        A::write_u64(&mut buf[i..], self.max_match.to_usize() as u64);
-        i += 8;
-        // byte class map
-        for b in (0..256).map(|b| b as u8) {
-            buf[i] = self.byte_classes().get(b);
-            i += 1;
-        }
-        // transition table
-        for &id in self.trans() {
-            write_state_id_bytes::<A, _>(&mut buf[i..], id);
-            i += state_size;
-        }
-        assert_eq!(size, i, "expected to consume entire buffer");
+    #[inline]
+    fn is_match_state(&self, id: usize) -> bool {
+        sized!(self, dfa => dfa.is_match_state(StateID::from_usize(id)))
+    }
 
-        Ok(buf)
+    #[inline]
+    fn is_dead_state(&self, id: usize) -> bool {
+        sized!(self, dfa => dfa.is_dead_state(StateID::from_usize(id)))
     }
-}
 
-impl<'a, S: StateID> Repr<&'a [S], S> {
-    /// The implementation for deserializing a DFA from raw bytes.
-    unsafe fn from_bytes(mut buf: &'a [u8]) -> Repr<&'a [S], S> {
-        assert_eq!(
-            0,
-            buf.as_ptr() as usize % mem::align_of::<S>(),
-            "DenseDFA starting at address {} is not aligned to {} bytes",
-            buf.as_ptr() as usize,
-            mem::align_of::<S>()
-        );
+    #[inline]
+    fn is_match_or_dead_state(&self, id: usize) -> bool {
+        sized!(self, dfa => {
+            dfa.is_match_or_dead_state(StateID::from_usize(id))
+        })
+    }
 
-        // skip over label
-        match buf.iter().position(|&b| b == b'\x00') {
-            None => panic!("could not find label"),
-            Some(i) => buf = &buf[i + 1..],
-        }
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        sized!(self, dfa => dfa.is_anchored())
+    }
 
-        // check that current endianness is same as endianness of DFA
-        let endian_check = NativeEndian::read_u16(buf);
-        buf = &buf[2..];
-        if endian_check != 0xFEFF {
-            panic!(
-                "endianness mismatch, expected 0xFEFF but got 0x{:X}. \
-                 are you trying to load a DenseDFA serialized with a \
-                 different endianness?",
-                endian_check,
-            );
-        }
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        sized!(self, dfa => dfa.is_quit_byte(byte))
+    }
 
-        // check that the version number is supported
-        let version = NativeEndian::read_u16(buf);
-        buf = &buf[2..];
-        if version != 1 {
-            panic!(
-                "expected version 1, but found unsupported version {}",
-                version,
-            );
-        }
+    #[inline]
+    fn accelerator(&self, id: usize) -> &[u8] {
+        sized!(self, dfa => dfa.accelerator(StateID::from_usize(id)))
+    }
 
-        // read size of state
-        let state_size = NativeEndian::read_u16(buf) as usize;
-        if state_size != mem::size_of::<S>() {
-            panic!(
-                "state size of DenseDFA ({}) does not match \
-                 requested state size ({})",
-                state_size,
-                mem::size_of::<S>(),
-            );
-        }
-        buf = &buf[2..];
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        sized!(self, dfa => dfa.is_utf8())
+    }
 
-        // read miscellaneous options
-        let opts = NativeEndian::read_u16(buf);
-        buf = &buf[2..];
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        sized!(self, dfa => dfa.has_lookaround())
+    }
 
-        // read start state
-        let start = S::from_usize(NativeEndian::read_u64(buf) as usize);
-        buf = &buf[8..];
+    #[inline]
+    fn next_eoi_state(&self, current: usize) -> usize {
+        sized!(self, dfa => {
+            dfa.next_eoi_state(StateID::from_usize(current)).to_usize()
+        })
+    }
 
-        // read state count
-        let state_count = NativeEndian::read_u64(buf) as usize;
-        buf = &buf[8..];
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> usize {
+        sized!(self, dfa => dfa.start_state_after(prev).to_usize())
+    }
 
-        // read max match state
-        let max_match = S::from_usize(NativeEndian::read_u64(buf) as usize);
-        buf = &buf[8..];
+    #[inline]
+    fn match_count(&self, id: usize) -> usize {
+        sized!(self, dfa => dfa.match_count(StateID::from_usize(id)))
+    }
 
-        // read byte classes
-        let byte_classes = ByteClasses::from_slice(&buf[..256]);
-        buf = &buf[256..];
+    #[inline]
+    fn match_pattern(&self, id: usize, index: usize) -> PatternID {
+        sized!(self, dfa => {
+            dfa.match_pattern(StateID::from_usize(id), index)
+        })
+    }
 
-        let len = state_count * byte_classes.alphabet_len();
-        let len_bytes = len * state_size;
-        assert!(
-            buf.len() <= len_bytes,
-            "insufficient transition table bytes, \
-             expected at least {} but only have {}",
-            len_bytes,
-            buf.len()
-        );
-        assert_eq!(
-            0,
-            buf.as_ptr() as usize % mem::align_of::<S>(),
-            "DenseDFA transition table is not properly aligned"
-        );
+    #[inline]
+    fn next_state(&self, current: usize, input: u8) -> usize {
+        sized!(self, dfa => {
+            dfa.next_state(StateID::from_usize(current), input).to_usize()
+        })
+    }
 
-        // SAFETY: This is the only actual not-safe thing in this entire
-        // routine. The key things we need to worry about here are alignment
-        // and size. The two asserts above should cover both conditions.
-        let trans = slice::from_raw_parts(buf.as_ptr() as *const S, len);
-        Repr {
-            premultiplied: opts & MASK_PREMULTIPLIED > 0,
-            anchored: opts & MASK_ANCHORED > 0,
-            start,
-            state_count,
-            max_match,
-            byte_classes,
-            trans,
-        }
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: usize, input: u8) -> usize {
+        sized!(self, dfa => {
+            dfa.next_state_unchecked(StateID::from_usize(current), input)
+                .to_usize()
+        })
+    }
+
+    // As with `DenseDFA`, we specialize the search routines so that the case
+    // analysis on the representation happens once per search instead of
+    // once per transition.
+
+    #[inline]
+    fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
+        sized!(self, dfa => dfa.is_match_at(bytes, start))
+    }
+
+    #[inline]
+    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        sized!(self, dfa => dfa.shortest_match_at(bytes, start))
+    }
+
+    #[inline]
+    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        sized!(self, dfa => dfa.find_at(bytes, start))
+    }
+
+    #[inline]
+    fn try_find_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+    ) -> result::Result<Option<usize>, SearchError> {
+        sized!(self, dfa => dfa.try_find_at(bytes, start))
+    }
+
+    #[inline]
+    fn find_pattern_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<(PatternID, usize)> {
+        sized!(self, dfa => dfa.find_pattern_at(bytes, start))
+    }
+
+    #[inline]
+    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        sized!(self, dfa => dfa.rfind_at(bytes, start))
+    }
+
+    #[inline]
+    fn is_match_in(&self, bytes: &[u8], start: usize, end: usize) -> bool {
+        sized!(self, dfa => dfa.is_match_in(bytes, start, end))
+    }
+
+    #[inline]
+    fn shortest_match_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        sized!(self, dfa => dfa.shortest_match_in(bytes, start, end))
+    }
+
+    #[inline]
+    fn find_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        sized!(self, dfa => dfa.find_in(bytes, start, end))
+    }
+
+    #[inline]
+    fn rfind_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        sized!(self, dfa => dfa.rfind_in(bytes, start, end))
     }
 }
 
-/// The following methods implement mutable routines on the internal
-/// representation of a DFA. As such, we must fix the first type parameter to
-/// a `Vec<S>` since a generic `T: AsRef<[S]>` does not permit mutation. We
-/// can get away with this because these methods are internal to the crate and
-/// are exclusively used during construction of the DFA.
-#[cfg(feature = "std")]
-impl<S: StateID> Repr<Vec<S>, S> {
-    pub fn premultiply(&mut self) -> Result<()> {
-        if self.premultiplied || self.state_count <= 1 {
-            return Ok(());
-        }
+/// A standard dense DFA that does not use premultiplication or byte classes.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct Standard<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
 
-        let alpha_len = self.alphabet_len();
-        premultiply_overflow_error(
-            S::from_usize(self.state_count - 1),
-            alpha_len,
-        )?;
+impl<T: AsRef<[S]>, S: StateID> DFA for Standard<T, S> {
+    type ID = S;
 
-        for id in (0..self.state_count).map(S::from_usize) {
-            for (_, next) in self.get_state_mut(id).iter_mut() {
-                *next = S::from_usize(next.to_usize() * alpha_len);
-            }
-        }
-        self.premultiplied = true;
-        self.start = S::from_usize(self.start.to_usize() * alpha_len);
-        self.max_match = S::from_usize(self.max_match.to_usize() * alpha_len);
-        Ok(())
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
     }
 
-    /// Minimize this DFA using Hopcroft's algorithm.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn minimize(&mut self) {
-        assert!(!self.premultiplied, "can't minimize premultiplied DFA");
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
 
-        Minimizer::new(self).run();
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
     }
 
-    /// Set the start state of this DFA.
-    ///
-    /// Note that a start state cannot be set on a premultiplied DFA. Instead,
-    /// DFAs should first be completely constructed and then premultiplied.
-    pub fn set_start_state(&mut self, start: S) {
-        assert!(!self.premultiplied, "can't set start on premultiplied DFA");
-        assert!(start.to_usize() < self.state_count, "invalid start state");
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
 
-        self.start = start;
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
     }
 
-    /// Set the maximum state identifier that could possible correspond to a
-    /// match state.
-    ///
-    /// Callers must uphold the invariant that any state identifier less than
-    /// or equal to the identifier given is either a match state or the special
-    /// dead state (which always has identifier 0 and whose transitions all
-    /// lead back to itself).
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn set_max_match_state(&mut self, id: S) {
-        assert!(!self.premultiplied, "can't set match on premultiplied DFA");
-        assert!(id.to_usize() < self.state_count, "invalid max match state");
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.0.is_quit_byte(byte)
+    }
 
-        self.max_match = id;
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.0.accelerator(id)
     }
 
-    /// Add the given transition to this DFA. Both the `from` and `to` states
-    /// must already exist.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn add_transition(&mut self, from: S, byte: u8, to: S) {
-        assert!(!self.premultiplied, "can't add trans to premultiplied DFA");
-        assert!(from.to_usize() < self.state_count, "invalid from state");
-        assert!(to.to_usize() < self.state_count, "invalid to state");
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.0.is_utf8()
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.0.has_lookaround()
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        self.0.next_eoi_state(current)
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        self.0.start_state_after(prev)
+    }
+
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        self.0.match_count(id)
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        self.0.match_pattern(id, index)
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() * ALPHABET_LEN + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() * ALPHABET_LEN + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+}
+
+/// A dense DFA that shrinks its alphabet.
+///
+/// Alphabet shrinking is achieved by using a set of equivalence classes
+/// instead of using all possible byte values. Any two bytes belong to the same
+/// equivalence class if and only if they can be used interchangeably anywhere
+/// in the DFA while never discriminating between a match and a non-match.
+///
+/// This type of DFA can result in significant space reduction with a very
+/// small match time performance penalty.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct ByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for ByteClass<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.0.is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.0.accelerator(id)
+    }
+
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.0.is_utf8()
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.0.has_lookaround()
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        self.0.next_eoi_state(current)
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        self.0.start_state_after(prev)
+    }
+
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        self.0.match_count(id)
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        self.0.match_pattern(id, index)
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get(input);
+        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get_unchecked(input);
+        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+}
+
+/// A dense DFA that premultiplies all of its state identifiers in its
+/// transition table.
+///
+/// This saves an instruction per byte at match time which improves search
+/// performance.
+///
+/// The only downside of premultiplication is that it may prevent one from
+/// using a smaller state identifier representation than you otherwise could.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct Premultiplied<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for Premultiplied<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.0.is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.0.accelerator(id)
+    }
+
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.0.is_utf8()
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.0.has_lookaround()
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        self.0.next_eoi_state(current)
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        self.0.start_state_after(prev)
+    }
+
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        self.0.match_count(id)
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        self.0.match_pattern(id, index)
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+}
+
+/// The default configuration of a dense DFA, which uses byte classes and
+/// premultiplies its state identifiers.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct PremultipliedByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for PremultipliedByteClass<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.0.is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.0.accelerator(id)
+    }
+
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.0.is_utf8()
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.0.has_lookaround()
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        self.0.next_eoi_state(current)
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        self.0.start_state_after(prev)
+    }
+
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        self.0.match_count(id)
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        self.0.match_pattern(id, index)
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get(input);
+        let o = current.to_usize() + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get_unchecked(input);
+        let o = current.to_usize() + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+}
+
+/// A dense DFA that reads its transition table directly from serialized
+/// bytes, without any alignment requirement.
+///
+/// A [`DenseDFA`](enum.DenseDFA.html) deserialized with `from_bytes` or
+/// `try_from_bytes` reinterprets the bytes as a slice of state identifiers,
+/// which requires the bytes to be aligned to the state identifier
+/// representation. This is awkward for bytes embedded with
+/// `include_bytes!`, whose alignment is only 1. An `UnalignedDFA` instead
+/// reads each state identifier with an unaligned load, which makes every
+/// transition slightly more expensive to follow.
+///
+/// Deserializing an `UnalignedDFA` is safe and takes constant time: only
+/// the header is checked. Every read from the transition table is bounds
+/// checked, so a corrupt table can make searches panic or return incorrect
+/// results, but never results in memory unsafety. (To detect corruption up
+/// front, use
+/// [`DenseDFA::try_from_bytes`](enum.DenseDFA.html#method.try_from_bytes)
+/// on an aligned copy of the bytes.)
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense::UnalignedDFA, DenseDFA, DFA};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let bytes = DenseDFA::new("foo[0-9]+")?.to_u16()?.to_bytes_native_endian()?;
+/// // Misalign the bytes on purpose.
+/// let mut unaligned = vec![0];
+/// unaligned.extend_from_slice(&bytes);
+///
+/// let dfa: UnalignedDFA<u16> = UnalignedDFA::from_bytes(&unaligned[1..])?;
+/// assert_eq!(Some(8), dfa.find(b"foo12345"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct UnalignedDFA<'a, S: StateID> {
+    premultiplied: bool,
+    anchored: bool,
+    lookaround: bool,
+    utf8: bool,
+    start: S,
+    max_match: S,
+    alphabet_len: usize,
+    byte_classes: ByteClasses,
+    quit: ByteSet,
+    accels: Accels<S>,
+    /// The transition table, as serialized.
+    trans: &'a [u8],
+    /// The table of matching patterns, as serialized.
+    matches: &'a [u8],
+}
+
+impl<'a, S: StateID> UnalignedDFA<'a, S> {
+    /// Deserialize a DFA with a specific state identifier representation
+    /// from bytes of any alignment.
+    ///
+    /// The bytes given should be generated by the serialization of a dense
+    /// DFA in the endianness of the current target, e.g., with
+    /// [`DenseDFA::to_bytes_native_endian`](enum.DenseDFA.html#method.to_bytes_native_endian).
+    ///
+    /// If the header of the bytes is invalid, if the bytes use a different
+    /// state identifier representation or if they are too short to contain
+    /// the DFA described by the header, then an error is returned.
+    pub fn from_bytes(
+        buf: &'a [u8],
+    ) -> result::Result<UnalignedDFA<'a, S>, DeserializeError> {
+        let info = bytes::peek(buf)?;
+        if info.is_sparse() {
+            return Err(DeserializeError::InvalidLabel);
+        }
+        let size = mem::size_of::<S>();
+        if info.state_id_size() != size {
+            return Err(DeserializeError::StateIDSizeMismatch {
+                expected: size,
+                found: info.state_id_size(),
+            });
+        }
+        let alphabet_len = info.alphabet_len();
+        let trans_len = info.state_count().saturating_mul(alphabet_len);
+        let matches_len = info.match_table_len();
+        let needed = trans_len
+            .saturating_add(matches_len)
+            .saturating_mul(size)
+            .saturating_add(info.header_len());
+        if buf.len() < needed {
+            return Err(DeserializeError::BufferTooSmall {
+                needed,
+                got: buf.len(),
+            });
+        }
+        if info.state_count() == 0 {
+            return Err(DeserializeError::InvalidDeadState);
+        }
+        for &id in &[info.start_state(), info.max_match_state()] {
+            if id > S::max_id() as u64 {
+                return Err(DeserializeError::InvalidStateID { id });
+            }
+        }
+
+        let header = &buf[info.header_len() - 256 - 32 - ACCELS_LEN - 8..];
+        let trans_start = info.header_len();
+        let matches_start = trans_start + trans_len * size;
+        Ok(UnalignedDFA {
+            premultiplied: info.is_premultiplied(),
+            anchored: info.is_anchored(),
+            lookaround: info.has_lookaround(),
+            utf8: info.is_utf8(),
+            start: S::from_usize(info.start_state() as usize),
+            max_match: S::from_usize(info.max_match_state() as usize),
+            alphabet_len,
+            byte_classes: ByteClasses::from_slice(&header[..256]),
+            quit: ByteSet::from_slice(&header[256..256 + 32]),
+            accels: Accels::from_bytes(&header[256 + 32..], |_| true)?,
+            trans: &buf[trans_start..matches_start],
+            matches: &buf[matches_start..needed],
+        })
+    }
+
+    /// Return the state identifier at the given index of the given table.
+    #[inline]
+    fn read(&self, table: &[u8], index: usize) -> S {
+        let size = mem::size_of::<S>();
+        S::read_bytes(&table[index * size..(index + 1) * size])
+    }
+
+    /// Return the offset of the given state's row in the transition table.
+    #[inline]
+    fn row_offset(&self, id: S) -> usize {
+        if self.premultiplied {
+            id.to_usize()
+        } else {
+            id.to_usize() * self.alphabet_len
+        }
+    }
+
+    /// Return the index of the given state in the transition table.
+    #[inline]
+    fn state_index(&self, id: S) -> usize {
+        if self.premultiplied {
+            id.to_usize() / self.alphabet_len
+        } else {
+            id.to_usize()
+        }
+    }
+}
+
+impl<'a, S: StateID> DFA for UnalignedDFA<'a, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.start
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        id <= self.max_match && id != dead_id()
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        id == dead_id()
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        id <= self.max_match
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.quit.contains(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.accels.needles(id)
+    }
+
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.lookaround
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        if !self.lookaround {
+            return dead_id();
+        }
+        let eoi = self.byte_classes.alphabet_len();
+        self.read(self.trans, self.row_offset(current) + eoi)
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        if !self.lookaround {
+            return self.start;
+        }
+        match prev {
+            None => self.next_eoi_state(self.start),
+            Some(b) => self.next_state(self.start, b),
+        }
+    }
+
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        if !self.is_match_state(id) {
+            return 0;
+        }
+        if self.matches.is_empty() {
+            return 1;
+        }
+        let index = self.state_index(id);
+        let end = self.read(self.matches, index).to_usize();
+        end - self.read(self.matches, index - 1).to_usize()
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        if self.matches.is_empty() {
+            return 0;
+        }
+        let start = self.read(self.matches, self.state_index(id) - 1);
+        self.read(self.matches, start.to_usize() + index).to_usize()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let class = self.byte_classes.get(input) as usize;
+        self.read(self.trans, self.row_offset(current) + class)
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        // Reads are always bounds checked, since the table isn't validated.
+        self.next_state(current, input)
+    }
+}
+
+/// A dense DFA whose states share identical rows of transitions.
+///
+/// Many states of a dense DFA can have exactly the same transitions. When
+/// minimization is disabled, which it is by default since it's expensive
+/// for large Unicode automata, this is common for DFAs built from several
+/// patterns, since the states for the codepoints shared by the patterns are
+/// duplicated. Even a minimal DFA keeps states with identical transitions
+/// distinct when they match different patterns.
+///
+/// A `SharedRowDFA` stores each distinct row once, along with a table that
+/// maps every state to its row. Finding the distinct rows takes a single
+/// linear pass over the DFA. This shrinks the transition table when many
+/// rows are duplicated, at the cost of one more memory access for every
+/// transition followed, which makes searching somewhat slower than with a
+/// [`DenseDFA`](../enum.DenseDFA.html). When few rows are duplicated, the
+/// table mapping states to rows makes a `SharedRowDFA` slightly bigger, so
+/// compare the [`memory_usage`](struct.SharedRowDFA.html#method.memory_usage)
+/// of both before choosing one. It is built from a dense DFA with
+/// [`DenseDFA::to_shared_rows`](../enum.DenseDFA.html#method.to_shared_rows).
+///
+/// The state identifiers of a `SharedRowDFA` are never premultiplied. Every
+/// state identifier of the dense DFA is mapped to its index, which never
+/// requires a larger state identifier representation.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense, DFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = dense::Builder::new()
+///     .anchored(true)
+///     .build_many(&[r"\w+", r"\pL+", r"\d+"])?;
+/// let shared = dfa.to_shared_rows();
+/// assert!(shared.row_count() < shared.state_count());
+/// assert!(shared.memory_usage() < dfa.memory_usage());
+/// assert_eq!(dfa.find_pattern(b"foo bar"), shared.find_pattern(b"foo bar"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct SharedRowDFA<S: StateID = usize> {
+    anchored: bool,
+    lookaround: bool,
+    utf8: bool,
+    start: S,
+    max_match: S,
+    alphabet_len: usize,
+    byte_classes: ByteClasses,
+    quit: ByteSet,
+    accels: Accels<S>,
+    /// The index of each state's row in `trans`, indexed by state.
+    rows: Vec<S>,
+    /// The distinct rows of the transition table, each with `alphabet_len`
+    /// transitions.
+    trans: Vec<S>,
+    /// The patterns that match in each match state, in the same format as
+    /// for a dense DFA that isn't premultiplied.
+    matches: Vec<S>,
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> SharedRowDFA<S> {
+    /// Build a DFA sharing identical rows from the given dense DFA.
+    fn new<T: AsRef<[S]>>(repr: &Repr<T, S>) -> SharedRowDFA<S> {
+        let index = |id: S| S::from_usize(repr.state_id_to_index(id));
+        let mut rows = Vec::with_capacity(repr.state_count());
+        let mut trans = vec![];
+        let mut seen: HashMap<Vec<S>, S> = HashMap::new();
+        for (_, state) in repr.states() {
+            let row: Vec<S> =
+                state.transitions().map(|(_, next)| index(next)).collect();
+            let next_row = S::from_usize(seen.len());
+            let row = match seen.entry(row) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => {
+                    trans.extend_from_slice(e.key());
+                    *e.insert(next_row)
+                }
+            };
+            rows.push(row);
+        }
+        SharedRowDFA {
+            anchored: repr.is_anchored(),
+            lookaround: repr.has_lookaround(),
+            utf8: repr.is_utf8(),
+            start: index(repr.start_state()),
+            max_match: index(repr.max_match_state()),
+            alphabet_len: repr.alphabet_len(),
+            byte_classes: *repr.byte_classes(),
+            quit: *repr.quit_bytes(),
+            accels: repr.accels().remap(index),
+            rows,
+            trans,
+            matches: repr.matches().to_vec(),
+        }
+    }
+
+    /// Returns the total number of states in this DFA, including the dead
+    /// state.
+    pub fn state_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the number of distinct rows in this DFA's transition table.
+    /// This is at most the number of states.
+    pub fn row_count(&self) -> usize {
+        self.trans.len() / self.alphabet_len
+    }
+
+    /// Returns the memory usage, in bytes, of this DFA.
+    ///
+    /// This includes the distinct rows, the table mapping each state to its
+    /// row and the table of matching patterns.
+    pub fn memory_usage(&self) -> usize {
+        (self.rows.len() + self.trans.len() + self.matches.len())
+            * mem::size_of::<S>()
+    }
+
+    /// Return the offset of the given state's row in the transition table.
+    #[inline]
+    fn row_offset(&self, id: S) -> usize {
+        self.rows[id.to_usize()].to_usize() * self.alphabet_len
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> DFA for SharedRowDFA<S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.start
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        id <= self.max_match && id != dead_id()
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        id == dead_id()
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        id <= self.max_match
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.quit.contains(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.accels.needles(id)
+    }
+
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        self.lookaround
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: S) -> S {
+        if !self.lookaround {
+            return dead_id();
+        }
+        let eoi = self.byte_classes.alphabet_len();
+        self.trans[self.row_offset(current) + eoi]
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> S {
+        if !self.lookaround {
+            return self.start;
+        }
+        match prev {
+            None => self.next_eoi_state(self.start),
+            Some(b) => self.next_state(self.start, b),
+        }
+    }
+
+    #[inline]
+    fn match_count(&self, id: S) -> usize {
+        if !self.is_match_state(id) {
+            return 0;
+        }
+        if self.matches.is_empty() {
+            return 1;
+        }
+        let index = id.to_usize();
+        self.matches[index].to_usize() - self.matches[index - 1].to_usize()
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        if self.matches.is_empty() {
+            return 0;
+        }
+        let start = self.matches[id.to_usize() - 1].to_usize();
+        self.matches[start + index].to_usize()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let class = self.byte_classes.get(input) as usize;
+        self.trans[self.row_offset(current) + class]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let class = self.byte_classes.get(input) as usize;
+        let row = self.rows.get_unchecked(current.to_usize()).to_usize();
+        *self.trans.get_unchecked(row * self.alphabet_len + class)
+    }
+}
+
+/// The internal representation of a dense DFA.
+///
+/// This representation is shared by all DFA variants.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "std"), derive(Debug))]
+pub(crate) struct Repr<T, S> {
+    /// Whether the state identifiers in the transition table have been
+    /// premultiplied or not.
+    ///
+    /// Premultiplied identifiers means that instead of your matching loop
+    /// looking something like this:
+    ///
+    ///   state = dfa.start
+    ///   for byte in haystack:
+    ///       next = dfa.transitions[state * len(alphabet) + byte]
+    ///       if dfa.is_match(next):
+    ///           return true
+    ///   return false
+    ///
+    /// it can instead look like this:
+    ///
+    ///   state = dfa.start
+    ///   for byte in haystack:
+    ///       next = dfa.transitions[state + byte]
+    ///       if dfa.is_match(next):
+    ///           return true
+    ///   return false
+    ///
+    /// In other words, we save a multiplication instruction in the critical
+    /// path. This turns out to be a decent performance win. The cost of using
+    /// premultiplied state ids is that they can require a bigger state id
+    /// representation.
+    premultiplied: bool,
+    /// Whether this DFA can only match at the beginning of input or not.
+    ///
+    /// When true, a match should only be reported if it begins at the 0th
+    /// index of the haystack.
+    anchored: bool,
+    /// Whether this DFA evaluates look-around assertions, such as word
+    /// boundaries.
+    ///
+    /// Whether an assertion holds at a position depends on the bytes on
+    /// either side of it, so a match is only known once the byte following
+    /// it has been read. Therefore, when this is true, a match state is
+    /// entered one byte after the end of the match, and every state has one
+    /// more transition, following the transitions on each equivalence class,
+    /// that is taken at the end of the input.
+    ///
+    /// In this case, the start state is a special state that is never
+    /// entered by a search. Instead, its transition on the byte preceding
+    /// the search, or its transition at the end of input when the search
+    /// begins at the start of the input, leads to the state in which the
+    /// search begins. The same is true of the anchored start state.
+    lookaround: bool,
+    /// Whether this DFA was built in UTF-8 mode, in which case every match
+    /// it reports on valid UTF-8 begins and ends at a codepoint boundary.
+    utf8: bool,
+    /// The initial start state ID.
+    start: S,
+    /// The start state ID for anchored searches, if this DFA is unanchored
+    /// and was built with a separate start state for anchored searches.
+    ///
+    /// When this DFA is anchored, `start` is used for anchored searches
+    /// instead, and this is always `None`.
+    start_anchored: Option<S>,
+    /// The total number of states in this DFA. Note that a DFA always has at
+    /// least one state---the dead state---even the empty DFA. In particular,
+    /// the dead state always has ID 0 and is correspondingly always the first
+    /// state. The dead state is never a match state.
+    state_count: usize,
+    /// States in a DFA have a *partial* ordering such that a match state
+    /// always precedes any non-match state (except for the special dead
+    /// state).
+    ///
+    /// `max_match` corresponds to the last state that is a match state. This
+    /// encoding has two critical benefits. Firstly, we are not required to
+    /// store any additional per-state information about whether it is a match
+    /// state or not. Secondly, when searching with the DFA, we can do a single
+    /// comparison with `max_match` for each byte instead of two comparisons
+    /// for each byte (one testing whether it is a match and the other testing
+    /// whether we've reached a dead state). Namely, to determine the status
+    /// of the next state, we can do this:
+    ///
+    ///   next_state = transition[cur_state * alphabet_len + cur_byte]
+    ///   if next_state <= max_match:
+    ///       // next_state is either dead (no-match) or a match
+    ///       return next_state != dead
+    max_match: S,
+    /// The total number of patterns that this DFA was built from. This is
+    /// `0` for a DFA that can never match.
+    pattern_count: usize,
+    /// A set of equivalence classes, where a single equivalence class
+    /// represents a set of bytes that never discriminate between a match
+    /// and a non-match in the DFA. Each equivalence class corresponds to
+    /// a single letter in this DFA's alphabet, where the maximum number of
+    /// letters is 256 (each possible value of a byte). Consequently, the
+    /// number of equivalence classes corresponds to the number of transitions
+    /// for each DFA state.
+    ///
+    /// The only time the number of equivalence classes is fewer than 256 is
+    /// if the DFA's kind uses byte classes. If the DFA doesn't use byte
+    /// classes, then this vector is empty.
+    byte_classes: ByteClasses,
+    /// The bytes on which every state transitions to the dead state, such
+    /// that a search quits as soon as it sees one of them. Each of these
+    /// bytes is in its own equivalence class.
+    quit: ByteSet,
+    /// The states that transition to themselves on all but a few bytes,
+    /// along with those bytes. A search in one of these states can skip
+    /// ahead to the next of those bytes with `memchr`.
+    accels: Accels<S>,
+    /// A contiguous region of memory representing the transition table in
+    /// row-major order. The representation is dense. That is, every state has
+    /// precisely the same number of transitions. The maximum number of
+    /// transitions is 256. If a DFA has been instructed to use byte classes,
+    /// then the number of transitions can be much less.
+    ///
+    /// In practice, T is either Vec<S> or &[S].
+    trans: T,
+    /// The patterns that match in each match state. This is only used when
+    /// the DFA was built from more than one pattern, and is empty otherwise,
+    /// in which case every match state matches the pattern `0`.
+    ///
+    /// When there are `n` match states, the first `n + 1` elements are
+    /// offsets into this table, such that the patterns for the match state
+    /// at index `i` (where `1 <= i <= n`) are found in the range
+    /// `matches[i - 1]..matches[i]`. Patterns are stored in order of
+    /// priority, using the same representation as state identifiers.
+    ///
+    /// In practice, T is either Vec<S> or &[S].
+    matches: T,
+    /// The start state for anchored searches of each pattern, indexed by
+    /// pattern identifier. This is empty unless the DFA was built with a
+    /// start state for each pattern. When this DFA has look-around, these
+    /// are special start states, like `start`.
+    ///
+    /// In practice, T is either Vec<S> or &[S].
+    starts: T,
+    /// The tag of each pattern, indexed by pattern identifier. This is empty
+    /// unless the DFA was built with tagged patterns. Each tag is split into
+    /// `tag_units::<S>()` identifiers, least significant bits first.
+    ///
+    /// In practice, T is either Vec<S> or &[S].
+    tags: T,
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> Repr<Vec<S>, S> {
+    /// Create a new empty DFA with singleton byte classes (every byte is its
+    /// own equivalence class).
+    pub fn empty() -> Repr<Vec<S>, S> {
+        Repr::empty_with_byte_classes(ByteClasses::singletons())
+    }
+
+    /// Create a new empty DFA with the given set of byte equivalence classes.
+    /// An empty DFA never matches any input.
+    pub fn empty_with_byte_classes(
+        byte_classes: ByteClasses,
+    ) -> Repr<Vec<S>, S> {
+        let mut dfa = Repr {
+            premultiplied: false,
+            anchored: true,
+            lookaround: false,
+            utf8: false,
+            start: dead_id(),
+            start_anchored: None,
+            state_count: 0,
+            max_match: S::from_usize(0),
+            pattern_count: 0,
+            byte_classes,
+            quit: ByteSet::empty(),
+            accels: Accels::empty(),
+            trans: vec![],
+            matches: vec![],
+            starts: vec![],
+            tags: vec![],
+        };
+        // Every state ID repr must be able to fit at least one state.
+        dfa.add_empty_state().unwrap();
+        dfa
+    }
+
+    /// Sets whether this DFA is anchored or not.
+    pub fn anchored(mut self, yes: bool) -> Repr<Vec<S>, S> {
+        self.anchored = yes;
+        self
+    }
+
+    /// Sets whether this DFA evaluates look-around assertions. When enabled,
+    /// every state gets an extra transition taken at the end of the input.
+    ///
+    /// This must be set before any state other than the dead state is
+    /// added.
+    pub fn lookaround(mut self, yes: bool) -> Repr<Vec<S>, S> {
+        assert_eq!(1, self.state_count, "lookaround must be set first");
+        self.lookaround = yes;
+        self.trans = vec![dead_id(); self.alphabet_len()];
+        self
+    }
+
+    /// Sets whether this DFA was built in UTF-8 mode.
+    pub fn utf8(mut self, yes: bool) -> Repr<Vec<S>, S> {
+        self.utf8 = yes;
+        self
+    }
+
+    /// Sets the total number of patterns that this DFA is built from.
+    pub fn patterns(mut self, count: usize) -> Repr<Vec<S>, S> {
+        self.pattern_count = count;
+        self
+    }
+
+    /// Sets the bytes on which a search with this DFA quits.
+    pub fn quit(mut self, quit: ByteSet) -> Repr<Vec<S>, S> {
+        self.quit = quit;
+        self
+    }
+}
+
+impl<T: AsRef<[S]>, S: StateID> Repr<T, S> {
+    /// Convert this internal DFA representation to a DenseDFA based on its
+    /// transition table access pattern.
+    pub fn into_dense_dfa(self) -> DenseDFA<T, S> {
+        // A DFA with look-around has one more transition for each state
+        // than there are bytes, so it can't index its transitions by byte.
+        let singleton = self.byte_classes().is_singleton() && !self.lookaround;
+        match (self.premultiplied, singleton) {
+            // no premultiplication, no byte classes
+            (false, true) => DenseDFA::Standard(Standard(self)),
+            // no premultiplication, yes byte classes
+            (false, false) => DenseDFA::ByteClass(ByteClass(self)),
+            // yes premultiplication, no byte classes
+            (true, true) => DenseDFA::Premultiplied(Premultiplied(self)),
+            // yes premultiplication, yes byte classes
+            (true, false) => {
+                DenseDFA::PremultipliedByteClass(PremultipliedByteClass(self))
+            }
+        }
+    }
+
+    fn as_ref<'a>(&'a self) -> Repr<&'a [S], S> {
+        Repr {
+            premultiplied: self.premultiplied,
+            anchored: self.anchored,
+            lookaround: self.lookaround,
+            utf8: self.utf8,
+            start: self.start,
+            start_anchored: self.start_anchored,
+            state_count: self.state_count,
+            max_match: self.max_match,
+            pattern_count: self.pattern_count,
+            byte_classes: self.byte_classes().clone(),
+            quit: self.quit,
+            accels: self.accels,
+            trans: self.trans(),
+            matches: self.matches(),
+            starts: self.starts(),
+            tags: self.tags(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn to_owned(&self) -> Repr<Vec<S>, S> {
+        Repr {
+            premultiplied: self.premultiplied,
+            anchored: self.anchored,
+            lookaround: self.lookaround,
+            utf8: self.utf8,
+            start: self.start,
+            start_anchored: self.start_anchored,
+            state_count: self.state_count,
+            max_match: self.max_match,
+            pattern_count: self.pattern_count,
+            byte_classes: self.byte_classes().clone(),
+            quit: self.quit,
+            accels: self.accels,
+            trans: self.trans().to_vec(),
+            matches: self.matches().to_vec(),
+            starts: self.starts().to_vec(),
+            tags: self.tags().to_vec(),
+        }
+    }
+
+    /// Return the starting state of this DFA.
+    ///
+    /// All searches using this DFA must begin at this state. There is exactly
+    /// one starting state for every DFA. A starting state may be a dead state
+    /// or a matching state or neither.
+    ///
+    /// When this DFA has look-around, this is the special start state that
+    /// is never entered by a search. See `start_state_after`.
+    pub fn start_state(&self) -> S {
+        self.start
+    }
+
+    /// Return the state in which a search begins, given the byte preceding
+    /// the search or `None` when the search begins at the start of the
+    /// input.
+    ///
+    /// This is always the start state when this DFA has no look-around.
+    #[inline]
+    pub fn start_state_after(&self, prev: Option<u8>) -> S {
+        self.start_after(self.start, prev)
+    }
+
+    /// Return the state in which an anchored search begins, given the byte
+    /// preceding the search, if this DFA has an anchored start state.
+    pub fn anchored_start_state_after(&self, prev: Option<u8>) -> Option<S> {
+        self.anchored_start_state().map(|id| self.start_after(id, prev))
+    }
+
+    /// Return the state in which an anchored search for only the given
+    /// pattern begins, given the byte preceding the search, if this DFA has
+    /// a start state for each pattern.
+    pub fn pattern_start_state_after(
+        &self,
+        pattern: PatternID,
+        prev: Option<u8>,
+    ) -> Option<S> {
+        self.starts().get(pattern).map(|&id| self.start_after(id, prev))
+    }
+
+    /// Returns true if and only if this DFA has a start state for anchored
+    /// searches of each pattern.
+    pub fn has_pattern_starts(&self) -> bool {
+        !self.starts().is_empty()
+    }
+
+    /// Return the tag of the given pattern, if this DFA has tagged patterns.
+    pub fn pattern_tag(&self, pattern: PatternID) -> Option<u64> {
+        let units = tag_units::<S>();
+        let start = pattern.checked_mul(units)?;
+        self.tags().get(start..start + units).map(decode_tag)
+    }
+
+    /// Returns true if and only if this DFA has a tag for each pattern.
+    pub fn has_pattern_tags(&self) -> bool {
+        !self.tags().is_empty()
+    }
+
+    /// Return the state in which a search from the given start state begins.
+    #[inline]
+    fn start_after(&self, start: S, prev: Option<u8>) -> S {
+        if !self.lookaround {
+            return start;
+        }
+        match prev {
+            None => self.next_eoi_state(start),
+            Some(b) => {
+                let class = self.byte_classes().get(b) as usize;
+                self.trans()[self.row_offset(start) + class]
+            }
+        }
+    }
+
+    /// Returns true if and only if this DFA evaluates look-around
+    /// assertions, in which case match states are entered one byte after the
+    /// end of the match and each state has a transition taken at the end of
+    /// the input.
+    #[inline]
+    pub fn has_lookaround(&self) -> bool {
+        self.lookaround
+    }
+
+    /// Return the state that the given state transitions to at the end of
+    /// the input. This is always the dead state when this DFA has no
+    /// look-around.
+    #[inline]
+    pub fn next_eoi_state(&self, current: S) -> S {
+        if !self.lookaround {
+            return dead_id();
+        }
+        let eoi = self.byte_classes().alphabet_len();
+        self.trans()[self.row_offset(current) + eoi]
+    }
+
+    /// Return the offset of the given state's row in the transition table.
+    #[inline]
+    fn row_offset(&self, id: S) -> usize {
+        if self.premultiplied {
+            id.to_usize()
+        } else {
+            id.to_usize() * self.alphabet_len()
+        }
+    }
+
+    /// Return the start state for anchored searches, if one exists.
+    ///
+    /// When this DFA is anchored, this is always its starting state.
+    /// Otherwise, this only exists if the DFA was built with a separate start
+    /// state for anchored searches.
+    pub fn anchored_start_state(&self) -> Option<S> {
+        if self.anchored {
+            Some(self.start)
+        } else {
+            self.start_anchored
+        }
+    }
+
+    /// Returns true if and only if the given identifier corresponds to a match
+    /// state.
+    pub fn is_match_state(&self, id: S) -> bool {
+        id <= self.max_match && id != dead_id()
+    }
+
+    /// Returns true if and only if the given identifier corresponds to a dead
+    /// state.
+    pub fn is_dead_state(&self, id: S) -> bool {
+        id == dead_id()
+    }
+
+    /// Returns true if and only if the given identifier could correspond to
+    /// either a match state or a dead state. If this returns false, then the
+    /// given identifier does not correspond to either a match state or a dead
+    /// state.
+    pub fn is_match_or_dead_state(&self, id: S) -> bool {
+        id <= self.max_match_state()
+    }
+
+    /// Returns the maximum identifier for which a match state can exist.
+    ///
+    /// More specifically, the return identifier always corresponds to either
+    /// a match state or a dead state. Namely, either
+    /// `is_match_state(returned)` or `is_dead_state(returned)` is guaranteed
+    /// to be true.
+    pub fn max_match_state(&self) -> S {
+        self.max_match
+    }
+
+    /// Returns true if and only if this DFA is anchored.
+    pub fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    /// Returns true if and only if this DFA was built in UTF-8 mode.
+    pub fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    /// Returns the total number of patterns that this DFA was built from.
+    pub fn pattern_count(&self) -> usize {
+        self.pattern_count
+    }
+
+    /// Returns the number of patterns that match in the given state. This
+    /// is `0` if and only if the given state is not a match state.
+    pub fn match_count(&self, id: S) -> usize {
+        if !self.is_match_state(id) {
+            return 0;
+        }
+        let matches = self.matches();
+        if matches.is_empty() {
+            return 1;
+        }
+        let index = self.state_id_to_index(id);
+        matches[index].to_usize() - matches[index - 1].to_usize()
+    }
+
+    /// Returns the pattern at the given index among the patterns that match
+    /// in the given match state.
+    pub fn match_pattern(&self, id: S, index: usize) -> PatternID {
+        let matches = self.matches();
+        if matches.is_empty() {
+            return 0;
+        }
+        let start = matches[self.state_id_to_index(id) - 1].to_usize();
+        matches[start + index].to_usize()
+    }
+
+    /// Returns the patterns that match in each match state, in the order in
+    /// which match states appear in the transition table.
+    #[cfg(feature = "std")]
+    pub fn match_state_patterns(&self) -> Vec<Vec<PatternID>> {
+        let count = self.state_id_to_index(self.max_match);
+        let mut patterns = Vec::with_capacity(count);
+        for index in 1..count + 1 {
+            let id = if self.premultiplied {
+                S::from_usize(index * self.alphabet_len())
+            } else {
+                S::from_usize(index)
+            };
+            patterns.push(
+                (0..self.match_count(id))
+                    .map(|i| self.match_pattern(id, i))
+                    .collect(),
+            );
+        }
+        patterns
+    }
+
+    /// Return the byte classes used by this DFA.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        &self.byte_classes
+    }
+
+    /// Return the bytes on which a search with this DFA quits.
+    #[cfg(feature = "std")]
+    pub fn quit_bytes(&self) -> &ByteSet {
+        &self.quit
+    }
+
+    /// Returns true if and only if a search with this DFA quits on the given
+    /// byte.
+    pub fn is_quit_byte(&self, byte: u8) -> bool {
+        self.quit.contains(byte)
+    }
+
+    /// Returns the bytes on which the given state leaves itself, if it is
+    /// an accelerated state. Otherwise, this returns an empty slice.
+    #[inline]
+    pub fn accelerator(&self, id: S) -> &[u8] {
+        self.accels.needles(id)
+    }
+
+    /// Returns the accelerated states of this DFA.
+    #[cfg(feature = "std")]
+    pub(crate) fn accels(&self) -> &Accels<S> {
+        &self.accels
+    }
+
+    /// Returns an iterator over all states in this DFA.
+    ///
+    /// This iterator yields a tuple for each state. The first element of the
+    /// tuple corresponds to a state's identifier, and the second element
+    /// corresponds to the state itself (comprised of its transitions).
+    ///
+    /// If this DFA is premultiplied, then the state identifiers are in
+    /// turn premultiplied as well, making them usable without additional
+    /// modification.
+    #[cfg(feature = "std")]
+    pub fn states(&self) -> StateIter<T, S> {
+        let it = self.trans().chunks(self.alphabet_len());
+        StateIter { dfa: self, it: it.enumerate() }
+    }
+
+    /// Return the state corresponding to the given id.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    #[cfg(feature = "std")]
+    pub fn get_state<'a>(&'a self, id: S) -> StateRow<'a, S> {
+        assert!(!self.premultiplied, "can't get state in premultiplied DFA");
+
+        let alphabet_len = self.alphabet_len();
+        let offset = id.to_usize() * alphabet_len;
+        StateRow {
+            transitions: &self.trans()[offset..offset + alphabet_len],
+            eoi: self.lookaround,
+        }
+    }
+
+    /// Return the total number of states in this DFA. Every DFA has at least
+    /// 1 state, even the empty DFA.
+    pub fn state_count(&self) -> usize {
+        self.state_count
+    }
+
+    /// Return the number of elements in this DFA's alphabet, which is the
+    /// number of transitions of each state.
+    ///
+    /// If this DFA doesn't use byte classes, then this is always equivalent
+    /// to 256. Otherwise, it is guaranteed to be some value less than or equal
+    /// to 256. When this DFA has look-around, the end of input is one more
+    /// element of the alphabet.
+    pub fn alphabet_len(&self) -> usize {
+        self.byte_classes().alphabet_len() + self.lookaround as usize
+    }
+
+    /// Returns the memory usage, in bytes, of this DFA.
+    pub fn memory_usage(&self) -> usize {
+        (self.trans().len()
+            + self.matches().len()
+            + self.starts().len()
+            + self.tags().len())
+            * mem::size_of::<S>()
+    }
+
+    /// Returns the memory usage of this DFA, broken down per component.
+    pub fn memory_usage_breakdown(&self) -> MemoryUsage {
+        MemoryUsage {
+            transitions: mem::size_of_val(self.trans()),
+            matches: mem::size_of_val(self.matches())
+                + mem::size_of_val(self.starts())
+                + mem::size_of_val(self.tags()),
+            byte_classes: mem::size_of::<ByteClasses>(),
+            quit_bytes: mem::size_of::<ByteSet>(),
+            accelerators: mem::size_of::<Accels<S>>(),
+        }
+    }
+
+    /// Convert the given state identifier to the state's index. The state's
+    /// index corresponds to the position in which it appears in the transition
+    /// table. When a DFA is NOT premultiplied, then a state's identifier is
+    /// also its index. When a DFA is premultiplied, then a state's identifier
+    /// is equal to `index * alphabet_len`. This routine reverses that.
+    pub fn state_id_to_index(&self, id: S) -> usize {
+        if self.premultiplied {
+            id.to_usize() / self.alphabet_len()
+        } else {
+            id.to_usize()
+        }
+    }
+
+    /// Convert the index of a state into its identifier, which is
+    /// premultiplied when this DFA is premultiplied. This is the inverse of
+    /// `state_id_to_index`.
+    pub fn state_index_to_id(&self, index: usize) -> S {
+        if self.premultiplied {
+            S::from_usize(index * self.alphabet_len())
+        } else {
+            S::from_usize(index)
+        }
+    }
+
+    /// Return this DFA's transition table as a slice.
+    fn trans(&self) -> &[S] {
+        self.trans.as_ref()
+    }
+
+    /// Return this DFA's table of matching patterns as a slice.
+    fn matches(&self) -> &[S] {
+        self.matches.as_ref()
+    }
+
+    /// Return the start state of each pattern as a slice.
+    fn starts(&self) -> &[S] {
+        self.starts.as_ref()
+    }
+
+    /// Return the encoded tag of each pattern as a slice.
+    fn tags(&self) -> &[S] {
+        self.tags.as_ref()
+    }
+
+    /// Create a sparse DFA from the internal representation of a dense DFA.
+    #[cfg(feature = "std")]
+    pub fn to_sparse_sized<A: StateID>(
+        &self,
+    ) -> Result<SparseDFA<Vec<u8>, A>> {
+        SparseDFA::from_dense_sized(self)
+    }
+
+    /// Create a new DFA whose match semantics are equivalent to this DFA, but
+    /// attempt to use `A` for the representation of state identifiers. If `A`
+    /// is insufficient to represent all state identifiers in this DFA, then
+    /// this returns an error.
+    #[cfg(feature = "std")]
+    pub fn to_sized<A: StateID>(&self) -> Result<Repr<Vec<A>, A>> {
+        // Check that this DFA can fit into A's representation. If it's
+        // premultiplied but only fits when it isn't, then we undo the
+        // premultiplication while converting. This is what permits small
+        // DFAs to use `u8` state identifiers, since premultiplied
+        // identifiers quickly exceed 255.
+        let last_state_id = self.state_count - 1;
+        let mut premultiplied = self.premultiplied;
+        if premultiplied && last_state_id * self.alphabet_len() > A::max_id() {
+            premultiplied = false;
+        }
+        if last_state_id > A::max_id() {
+            return Err(Error::state_id_overflow(
+                A::max_id(),
+                self.state_count,
+            ));
+        }
+        let divisor = if self.premultiplied && !premultiplied {
+            self.alphabet_len()
+        } else {
+            1
+        };
+        let convert = |id: S| A::from_usize(id.to_usize() / divisor);
+
+        // We're off to the races. The new DFA is the same as the old one,
+        // but its transition table is truncated.
+        let mut new = Repr {
+            premultiplied,
+            anchored: self.anchored,
+            lookaround: self.lookaround,
+            utf8: self.utf8,
+            start: convert(self.start),
+            start_anchored: self.start_anchored.map(convert),
+            state_count: self.state_count,
+            max_match: convert(self.max_match),
+            pattern_count: self.pattern_count,
+            byte_classes: self.byte_classes().clone(),
+            quit: self.quit,
+            accels: self.accels.remap(convert),
+            trans: vec![dead_id::<A>(); self.trans().len()],
+            matches: vec![],
+            starts: self.starts().iter().map(|&id| convert(id)).collect(),
+            tags: vec![],
+        };
+        for (i, id) in new.trans.iter_mut().enumerate() {
+            let old = self.trans()[i].to_usize() / divisor;
+            *id = A::from_usize(old);
+            // This should be guaranteed by the check above, but we make sure
+            // that no state identifier was silently truncated anyway.
+            if id.to_usize() != old {
+                return Err(Error::state_id_overflow(
+                    A::max_id(),
+                    self.state_count,
+                ));
+            }
+        }
+        for &old in self.matches() {
+            new.matches.push(usize_to_state_id(old.to_usize())?);
+        }
+        if self.has_pattern_tags() {
+            let tags: Vec<u64> = (0..self.pattern_count)
+                .map(|pattern| self.pattern_tag(pattern).unwrap())
+                .collect();
+            new.tags = encode_tags(&tags);
+        }
+        Ok(new)
+    }
+
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary.
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
+    /// implementations of `StateID` provided by this crate satisfy this
+    /// requirement.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_bytes<A: ByteOrder>(&self) -> Result<AlignedBytes> {
+        let label = b"rust-regex-automata-dfa\x00";
+        assert_eq!(24, label.len());
+
+        let trans_size = mem::size_of::<S>() * self.trans().len();
+        let matches_size = mem::size_of::<S>()
+            * (self.matches().len() + self.starts().len() + self.tags().len());
+        let size =
+            // For human readable label.
+            label.len()
+            // endiannes check, must be equal to 0xFEFF for native endian
+            + 2
+            // For version number.
+            + 2
+            // Size of state ID representation, in bytes.
+            // Must be 1, 2, 4 or 8.
+            + 2
+            // For DFA flags.
+            + 2
+            // For start state.
+            + 8
+            // For anchored start state.
+            + 8
+            // For state count.
+            + 8
+            // For max match state.
+            + 8
+            // For pattern count.
+            + 8
+            // For the length of the table of matching patterns.
+            + 8
+            // For byte class map.
+            + 256
+            // For the set of quit bytes.
+            + 32
+            // For the accelerated states.
+            + ACCELS_LEN
+            // For the checksum.
+            + 8
+            // For transition table.
+            + trans_size
+            // For the table of matching patterns, followed by the start state
+            // of each pattern.
+            + matches_size;
+        // sanity check, this can be updated if need be
+        assert_eq!(HEADER_LEN + trans_size + matches_size, size);
+        // This must always pass. It checks that the transition table is at
+        // a properly aligned address.
+        assert_eq!(0, (size - trans_size - matches_size) % 8);
+
+        let mut buf = AlignedBytes::zeroed(size);
+        let mut i = 0;
+
+        // write label
+        for &b in label {
+            buf[i] = b;
+            i += 1;
+        }
+        // endianness check
+        A::write_u16(&mut buf[i..], 0xFEFF);
+        i += 2;
+        // version number
+        A::write_u16(&mut buf[i..], FORMAT_VERSION);
+        i += 2;
+        // size of state ID
+        let state_size = mem::size_of::<S>();
+        if ![1, 2, 4, 8].contains(&state_size) {
+            return Err(Error::serialize(&format!(
+                "state size of {} not supported, must be 1, 2, 4 or 8",
+                state_size
+            )));
+        }
+        A::write_u16(&mut buf[i..], state_size as u16);
+        i += 2;
+        // DFA flags
+        let mut options = 0u16;
+        if self.premultiplied {
+            options |= MASK_PREMULTIPLIED;
+        }
+        if self.anchored {
+            options |= MASK_ANCHORED;
+        }
+        if self.start_anchored.is_some() {
+            options |= MASK_ANCHORED_START;
+        }
+        if self.lookaround {
+            options |= MASK_LOOKAROUND;
+        }
+        if self.has_pattern_starts() {
+            options |= MASK_PATTERN_STARTS;
+        }
+        if self.has_pattern_tags() {
+            options |= MASK_PATTERN_TAGS;
+        }
+        if self.utf8 {
+            options |= MASK_UTF8;
+        }
+        options |= MASK_CHECKSUM;
+        A::write_u16(&mut buf[i..], options);
+        i += 2;
+        // start state
+        A::write_u64(&mut buf[i..], self.start.to_usize() as u64);
+        i += 8;
+        // anchored start state, or zero if there isn't one
+        let start_anchored = self.start_anchored.unwrap_or(dead_id());
+        A::write_u64(&mut buf[i..], start_anchored.to_usize() as u64);
+        i += 8;
+        // state count
+        A::write_u64(&mut buf[i..], self.state_count as u64);
+        i += 8;
+        // max match state
+        A::write_u64(&mut buf[i..], self.max_match.to_usize() as u64);
+        i += 8;
+        // pattern count
+        A::write_u64(&mut buf[i..], self.pattern_count as u64);
+        i += 8;
+        // length of the table of matching patterns, including the start
+        // state and the tag of each pattern that follow it
+        let matches_len =
+            self.matches().len() + self.starts().len() + self.tags().len();
+        A::write_u64(&mut buf[i..], matches_len as u64);
+        i += 8;
+        // byte class map
+        for b in (0..256).map(|b| b as u8) {
+            buf[i] = self.byte_classes().get(b);
+            i += 1;
+        }
+        // set of quit bytes
+        buf[i..i + 32].copy_from_slice(self.quit.as_bytes());
+        i += 32;
+        // accelerated states
+        self.accels.write::<A>(&mut buf[i..]);
+        i += ACCELS_LEN;
+        // checksum, which is written once the rest of the buffer is
+        let checksum_at = i;
+        i += 8;
+        // transition table
+        for &id in self.trans() {
+            write_state_id_bytes::<A, _>(&mut buf[i..], id);
+            i += state_size;
+        }
+        // table of matching patterns
+        for &id in self.matches() {
+            write_state_id_bytes::<A, _>(&mut buf[i..], id);
+            i += state_size;
+        }
+        // start state of each pattern
+        for &id in self.starts() {
+            write_state_id_bytes::<A, _>(&mut buf[i..], id);
+            i += state_size;
+        }
+        // tag of each pattern
+        for &unit in self.tags() {
+            write_state_id_bytes::<A, _>(&mut buf[i..], unit);
+            i += state_size;
+        }
+        assert_eq!(size, i, "expected to consume entire buffer");
+        let checksum = bytes::dfa_checksum(&buf, checksum_at);
+        A::write_u64(&mut buf[checksum_at..], checksum as u64);
+
+        Ok(buf)
+    }
+}
+
+impl<'a, S: StateID> Repr<&'a [S], S> {
+    /// The implementation for deserializing and validating a DFA from raw
+    /// bytes.
+    fn try_from_bytes(
+        buf: &'a [u8],
+    ) -> result::Result<Repr<&'a [S], S>, DeserializeError> {
+        let info = bytes::peek(buf)?;
+        if info.is_sparse() {
+            return Err(DeserializeError::InvalidLabel);
+        }
+        if info.state_id_size() != mem::size_of::<S>() {
+            return Err(DeserializeError::StateIDSizeMismatch {
+                expected: mem::size_of::<S>(),
+                found: info.state_id_size(),
+            });
+        }
+        // Since the header length is a multiple of 8, the transition table
+        // is aligned if and only if the bytes are.
+        let misalignment = buf.as_ptr() as usize % mem::align_of::<S>();
+        if misalignment != 0 {
+            return Err(DeserializeError::Misaligned);
+        }
+
+        // The alphabet length is derived from the last byte class, so every
+        // other class must be in bounds of it, or else a search would index
+        // past the end of a state's transitions.
+        let header = &buf
+            [info.header_len() - 256 - 32 - ACCELS_LEN - 8..info.header_len()];
+        let mut class_map = [0u8; 256];
+        class_map.copy_from_slice(&header[..256]);
+        let byte_classes = match ByteClasses::from_array(class_map) {
+            Some(byte_classes) => byte_classes,
+            None => return Err(DeserializeError::InvalidByteClasses),
+        };
+
+        let state_count = info.state_count();
+        let alphabet_len = info.alphabet_len();
+        let matches_len = info.match_table_len();
+        // The arithmetic saturates so that a bogus state count results in
+        // an impossibly large size instead of overflowing.
+        let needed = state_count
+            .saturating_mul(alphabet_len)
+            .saturating_add(matches_len)
+            .saturating_mul(mem::size_of::<S>())
+            .saturating_add(info.header_len());
+        if buf.len() < needed {
+            return Err(DeserializeError::BufferTooSmall {
+                needed,
+                got: buf.len(),
+            });
+        }
+        if state_count == 0 {
+            return Err(DeserializeError::InvalidDeadState);
+        }
+        let len = state_count * alphabet_len;
+
+        let premultiplied = info.is_premultiplied();
+        let is_valid = |id: u64| -> bool {
+            if id > S::max_id() as u64 {
+                return false;
+            }
+            let id = id as usize;
+            if premultiplied {
+                let (index, offset) = (id / alphabet_len, id % alphabet_len);
+                index < state_count && offset == 0
+            } else {
+                id < state_count
+            }
+        };
+        let start_anchored = info.anchored_start_state();
+        let ids = [info.start_state(), start_anchored, info.max_match_state()];
+        for &id in &ids {
+            if !is_valid(id) {
+                return Err(DeserializeError::InvalidStateID { id });
+            }
+        }
+        let table = &buf[info.header_len()..needed];
+        // SAFETY: The alignment of the table was checked above, and its
+        // length is exactly `(len + matches_len) * size_of::<S>()` bytes.
+        let (trans, matches) = unsafe {
+            let ptr = table.as_ptr() as *const S;
+            (
+                slice::from_raw_parts(ptr, len),
+                slice::from_raw_parts(ptr.add(len), matches_len),
+            )
+        };
+        // When present, the start state and then the tag of each pattern
+        // follow the table of matching patterns.
+        let starts_len =
+            if info.has_pattern_starts() { info.pattern_count() } else { 0 };
+        let tags_len = if info.has_pattern_tags() {
+            info.pattern_count().saturating_mul(tag_units::<S>())
+        } else {
+            0
+        };
+        if starts_len.saturating_add(tags_len) > matches.len() {
+            return Err(DeserializeError::InvalidMatchTable);
+        }
+        let (matches, tags) = matches.split_at(matches.len() - tags_len);
+        let (matches, starts) = matches.split_at(matches.len() - starts_len);
+        for &id in starts {
+            let id = id.to_usize() as u64;
+            if !is_valid(id) {
+                return Err(DeserializeError::InvalidStateID { id });
+            }
+        }
+        for (i, &id) in trans.iter().enumerate() {
+            let id = id.to_usize() as u64;
+            if !is_valid(id) {
+                return Err(DeserializeError::InvalidStateID { id });
+            }
+            if i < alphabet_len && id != 0 {
+                return Err(DeserializeError::InvalidDeadState);
+            }
+        }
+        if !matches.is_empty() {
+            let mut match_count = info.max_match_state() as usize;
+            if premultiplied {
+                match_count /= alphabet_len;
+            }
+            // Every match state must have at least one pattern, and every
+            // pattern must have been counted in the header.
+            let first = match_count + 1;
+            if matches.len() < first
+                || matches[0].to_usize() != first
+                || matches[match_count].to_usize() != matches.len()
+                || matches[..first].windows(2).any(|w| w[1] <= w[0])
+                || matches[first..]
+                    .iter()
+                    .any(|p| p.to_usize() >= info.pattern_count())
+            {
+                return Err(DeserializeError::InvalidMatchTable);
+            }
+        }
+
+        let accels = Accels::from_bytes(&header[256 + 32..], is_valid)?;
+        // The checksum is verified last, so that corruption that makes the
+        // DFA structurally invalid is reported more precisely above.
+        if let Some(expected) = info.checksum() {
+            let found =
+                bytes::dfa_checksum(&buf[..needed], info.header_len() - 8);
+            if found != expected {
+                return Err(DeserializeError::ChecksumMismatch {
+                    expected,
+                    found,
+                });
+            }
+        }
+        Ok(Repr {
+            premultiplied,
+            anchored: info.is_anchored(),
+            lookaround: info.has_lookaround(),
+            utf8: info.is_utf8(),
+            start: S::from_usize(info.start_state() as usize),
+            start_anchored: if info.has_anchored_start_state() {
+                Some(S::from_usize(start_anchored as usize))
+            } else {
+                None
+            },
+            state_count,
+            max_match: S::from_usize(info.max_match_state() as usize),
+            pattern_count: info.pattern_count(),
+            byte_classes,
+            quit: ByteSet::from_slice(&header[256..256 + 32]),
+            accels,
+            trans,
+            matches,
+            starts,
+            tags,
+        })
+    }
+
+    /// The implementation for deserializing a DFA from raw bytes.
+    unsafe fn from_bytes(mut buf: &'a [u8]) -> Repr<&'a [S], S> {
+        assert_eq!(
+            0,
+            buf.as_ptr() as usize % mem::align_of::<S>(),
+            "DenseDFA starting at address {} is not aligned to {} bytes",
+            buf.as_ptr() as usize,
+            mem::align_of::<S>()
+        );
+
+        // skip over label
+        match buf.iter().position(|&b| b == b'\x00') {
+            None => panic!("could not find label"),
+            Some(i) => buf = &buf[i + 1..],
+        }
+
+        // check that current endianness is same as endianness of DFA
+        let endian_check = NativeEndian::read_u16(buf);
+        buf = &buf[2..];
+        if endian_check != 0xFEFF {
+            panic!(
+                "endianness mismatch, expected 0xFEFF but got 0x{:X}. \
+                 are you trying to load a DenseDFA serialized with a \
+                 different endianness?",
+                endian_check,
+            );
+        }
+
+        // check that the version number is supported
+        let version = NativeEndian::read_u16(buf);
+        buf = &buf[2..];
+        if version != FORMAT_VERSION {
+            panic!(
+                "expected version {}, but found unsupported version {}",
+                FORMAT_VERSION, version,
+            );
+        }
+
+        // read size of state
+        let state_size = NativeEndian::read_u16(buf) as usize;
+        if state_size != mem::size_of::<S>() {
+            panic!(
+                "state size of DenseDFA ({}) does not match \
+                 requested state size ({})",
+                state_size,
+                mem::size_of::<S>(),
+            );
+        }
+        buf = &buf[2..];
+
+        // read flags, rejecting unknown required flags
+        let opts = NativeEndian::read_u16(buf);
+        buf = &buf[2..];
+        let unknown = opts & MASK_REQUIRED & !MASK_KNOWN_REQUIRED;
+        if unknown != 0 {
+            panic!(
+                "DenseDFA requires unsupported features (flags 0x{:04X})",
+                unknown,
+            );
+        }
+        if opts & MASK_COMPACT > 0 {
+            panic!("only sparse DFAs can be serialized in the compact format");
+        }
+
+        // read start state
+        let start = S::from_usize(NativeEndian::read_u64(buf) as usize);
+        buf = &buf[8..];
+
+        // read anchored start state
+        let start_anchored = if opts & MASK_ANCHORED_START > 0 {
+            Some(S::from_usize(NativeEndian::read_u64(buf) as usize))
+        } else {
+            None
+        };
+        buf = &buf[8..];
+
+        // read state count
+        let state_count = NativeEndian::read_u64(buf) as usize;
+        buf = &buf[8..];
+
+        // read max match state
+        let max_match = S::from_usize(NativeEndian::read_u64(buf) as usize);
+        buf = &buf[8..];
+
+        // read pattern count
+        let pattern_count = NativeEndian::read_u64(buf) as usize;
+        buf = &buf[8..];
+
+        // read length of the table of matching patterns
+        let matches_len = NativeEndian::read_u64(buf) as usize;
+        buf = &buf[8..];
+
+        // read byte classes
+        let byte_classes = ByteClasses::from_slice(&buf[..256]);
+        buf = &buf[256..];
+
+        // read set of quit bytes
+        let quit = ByteSet::from_slice(&buf[..32]);
+        buf = &buf[32..];
+
+        // read accelerated states
+        let accels = Accels::from_bytes_unchecked(&buf[..ACCELS_LEN]);
+        buf = &buf[ACCELS_LEN..];
+
+        // skip the checksum, which is only verified by `try_from_bytes`
+        buf = &buf[8..];
+
+        let lookaround = opts & MASK_LOOKAROUND > 0;
+        let alphabet_len = byte_classes.alphabet_len() + lookaround as usize;
+        let len = state_count * alphabet_len;
+        let len_bytes = (len + matches_len) * state_size;
+        assert!(
+            buf.len() >= len_bytes,
+            "insufficient transition table bytes, \
+             expected at least {} but only have {}",
+            len_bytes,
+            buf.len()
+        );
+        assert_eq!(
+            0,
+            buf.as_ptr() as usize % mem::align_of::<S>(),
+            "DenseDFA transition table is not properly aligned"
+        );
+
+        // SAFETY: This is the only actual not-safe thing in this entire
+        // routine. The key things we need to worry about here are alignment
+        // and size. The two asserts above should cover both conditions.
+        let trans = slice::from_raw_parts(buf.as_ptr() as *const S, len);
+        let matches = slice::from_raw_parts(
+            buf.as_ptr().add(len * state_size) as *const S,
+            matches_len,
+        );
+        let starts_len =
+            if opts & MASK_PATTERN_STARTS > 0 { pattern_count } else { 0 };
+        let tags_len = if opts & MASK_PATTERN_TAGS > 0 {
+            pattern_count * tag_units::<S>()
+        } else {
+            0
+        };
+        let (matches, tags) = matches.split_at(matches_len - tags_len);
+        let (matches, starts) = matches.split_at(matches.len() - starts_len);
+        Repr {
+            premultiplied: opts & MASK_PREMULTIPLIED > 0,
+            anchored: opts & MASK_ANCHORED > 0,
+            lookaround,
+            utf8: opts & MASK_UTF8 > 0,
+            start,
+            start_anchored,
+            state_count,
+            max_match,
+            pattern_count,
+            byte_classes,
+            quit,
+            accels,
+            trans,
+            matches,
+            starts,
+            tags,
+        }
+    }
+}
+
+/// The following methods implement mutable routines on the internal
+/// representation of a DFA. As such, we must fix the first type parameter to
+/// a `Vec<S>` since a generic `T: AsRef<[S]>` does not permit mutation. We
+/// can get away with this because these methods are internal to the crate and
+/// are exclusively used during construction of the DFA.
+#[cfg(feature = "std")]
+impl<S: StateID> Repr<Vec<S>, S> {
+    /// Shrink the capacity of every table in this DFA to fit its length.
+    pub fn shrink_to_fit(&mut self) {
+        self.trans.shrink_to_fit();
+        self.matches.shrink_to_fit();
+        self.starts.shrink_to_fit();
+        self.tags.shrink_to_fit();
+    }
+
+    pub fn premultiply(&mut self) -> Result<()> {
+        if self.premultiplied || self.state_count <= 1 {
+            return Ok(());
+        }
+
+        let alpha_len = self.alphabet_len();
+        premultiply_overflow_error(
+            S::from_usize(self.state_count - 1),
+            alpha_len,
+        )?;
+
+        for id in (0..self.state_count).map(S::from_usize) {
+            for (_, next) in self.get_state_mut(id).iter_mut() {
+                *next = S::from_usize(next.to_usize() * alpha_len);
+            }
+        }
+        self.premultiplied = true;
+        self.start = S::from_usize(self.start.to_usize() * alpha_len);
+        self.start_anchored = self
+            .start_anchored
+            .map(|id| S::from_usize(id.to_usize() * alpha_len));
+        for id in &mut self.starts {
+            *id = S::from_usize(id.to_usize() * alpha_len);
+        }
+        self.max_match = S::from_usize(self.max_match.to_usize() * alpha_len);
+        self.accels =
+            self.accels.remap(|id| S::from_usize(id.to_usize() * alpha_len));
+        Ok(())
+    }
+
+    /// Minimize this DFA using Hopcroft's algorithm.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn minimize(&mut self) {
+        self.minimize_with(Cancel::default());
+    }
+
+    /// Minimize this DFA using Hopcroft's algorithm, giving up once the given
+    /// cancellation condition is triggered. This returns false if
+    /// minimization was abandoned, in which case this DFA is unchanged.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn minimize_with(&mut self, cancel: Cancel) -> bool {
+        assert!(!self.premultiplied, "can't minimize premultiplied DFA");
+
+        let accelerated = self.accels.len() > 0;
+        self.accels = Accels::empty();
+        let minimized = Minimizer::new(self).cancel(cancel).run();
+        if accelerated {
+            self.accelerate();
+        }
+        minimized
+    }
+
+    /// Merge states of this DFA that have identical transitions and match
+    /// the same patterns, in a single pass over its states. This is a cheap
+    /// approximation of minimization.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn reduce(&mut self) {
+        assert!(!self.premultiplied, "can't reduce premultiplied DFA");
+
+        let accelerated = self.accels.len() > 0;
+        self.accels = Accels::empty();
+        minimize::reduce(self);
+        if accelerated {
+            self.accelerate();
+        }
+    }
+
+    /// Find the states of this DFA that transition to themselves on all but
+    /// one, two or three bytes, and record them as accelerated states, up to
+    /// a maximum of `MAX_ACCELS`. Match states and the dead state are never
+    /// accelerated. Any previously accelerated states are forgotten.
+    pub fn accelerate(&mut self) {
+        let mut accels = Accels::empty();
+        for (id, state) in self.states() {
+            if self.is_match_or_dead_state(id) {
+                continue;
+            }
+            // A DFA with look-around has one more transition than there are
+            // equivalence classes, but it's never taken on a byte.
+            let mut leaves = [false; 257];
+            for (class, next) in state.transitions() {
+                leaves[class] = next != id;
+            }
+            let (mut needles, mut len) = ([0; 3], 0);
+            for b in (0..256).map(|b| b as u8) {
+                if !leaves[self.byte_classes.get(b) as usize] {
+                    continue;
+                }
+                if len == needles.len() {
+                    len = 0;
+                    break;
+                }
+                needles[len] = b;
+                len += 1;
+            }
+            if len > 0 && !accels.add(id, &needles[..len]) {
+                break;
+            }
+        }
+        self.accels = accels;
+    }
+
+    /// Set the start state of this DFA.
+    ///
+    /// Note that a start state cannot be set on a premultiplied DFA. Instead,
+    /// DFAs should first be completely constructed and then premultiplied.
+    pub fn set_start_state(&mut self, start: S) {
+        assert!(!self.premultiplied, "can't set start on premultiplied DFA");
+        assert!(start.to_usize() < self.state_count, "invalid start state");
+
+        self.start = start;
+    }
+
+    /// Set the start state of this DFA for anchored searches.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn set_anchored_start_state(&mut self, start: S) {
+        assert!(!self.premultiplied, "can't set start on premultiplied DFA");
+        assert!(start.to_usize() < self.state_count, "invalid start state");
+
+        self.start_anchored = Some(start);
+    }
+
+    /// Set the start state for anchored searches of each pattern, indexed
+    /// by pattern identifier.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn set_pattern_start_states(&mut self, starts: Vec<S>) {
+        assert!(!self.premultiplied, "can't set start on premultiplied DFA");
+        assert!(
+            starts.iter().all(|id| id.to_usize() < self.state_count),
+            "invalid start state"
+        );
+
+        self.starts = starts;
+    }
+
+    /// Return the start state for anchored searches of each pattern, which
+    /// is empty if this DFA has no start state for each pattern.
+    pub fn pattern_start_states(&self) -> &[S] {
+        &self.starts
+    }
+
+    /// Set the tag of each pattern, indexed by pattern identifier.
+    pub fn set_pattern_tags(&mut self, tags: &[u64]) {
+        assert_eq!(self.pattern_count, tags.len(), "invalid pattern tags");
+
+        self.tags = encode_tags(tags);
+    }
+
+    /// Reorder the patterns that match in each match state, such that
+    /// patterns with a higher priority come first. Patterns with equal
+    /// priorities keep their relative order. Priorities are indexed by
+    /// pattern identifier.
+    pub fn prioritize_matches(&mut self, priorities: &[u32]) {
+        assert_eq!(self.pattern_count, priorities.len(), "invalid priorities");
+
+        if self.matches.is_empty() {
+            return;
+        }
+        let count = self.state_id_to_index(self.max_match);
+        for index in 1..count + 1 {
+            let start = self.matches[index - 1].to_usize();
+            let end = self.matches[index].to_usize();
+            self.matches[start..end]
+                .sort_by_key(|&pid| cmp::Reverse(priorities[pid.to_usize()]));
+        }
+    }
+
+    /// Set the maximum state identifier that could possible correspond to a
+    /// match state.
+    ///
+    /// Callers must uphold the invariant that any state identifier less than
+    /// or equal to the identifier given is either a match state or the special
+    /// dead state (which always has identifier 0 and whose transitions all
+    /// lead back to itself).
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn set_max_match_state(&mut self, id: S) {
+        assert!(!self.premultiplied, "can't set match on premultiplied DFA");
+        assert!(id.to_usize() < self.state_count, "invalid max match state");
+
+        self.max_match = id;
+    }
+
+    /// Add the given transition to this DFA. Both the `from` and `to` states
+    /// must already exist.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn add_transition(&mut self, from: S, byte: u8, to: S) {
+        assert!(!self.premultiplied, "can't add trans to premultiplied DFA");
+        assert!(from.to_usize() < self.state_count, "invalid from state");
+        assert!(to.to_usize() < self.state_count, "invalid to state");
+
+        let class = self.byte_classes().get(byte);
+        let offset = from.to_usize() * self.alphabet_len() + class as usize;
+        self.trans[offset] = to;
+    }
+
+    /// Add the transition taken at the end of the input to this DFA. Both the
+    /// `from` and `to` states must already exist, and this DFA must have
+    /// look-around.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn add_eoi_transition(&mut self, from: S, to: S) {
+        assert!(!self.premultiplied, "can't add trans to premultiplied DFA");
+        assert!(self.lookaround, "DFA has no end of input transitions");
+        assert!(from.to_usize() < self.state_count, "invalid from state");
+        assert!(to.to_usize() < self.state_count, "invalid to state");
+
+        let eoi = self.byte_classes().alphabet_len();
+        let offset = from.to_usize() * self.alphabet_len() + eoi;
+        self.trans[offset] = to;
+    }
+
+    /// An an empty state (a state where all transitions lead to a dead state)
+    /// and return its identifier. The identifier returned is guaranteed to
+    /// not point to any other existing state.
+    ///
+    /// If adding a state would exhaust the state identifier space (given by
+    /// `S`), then this returns an error. In practice, this means that the
+    /// state identifier representation chosen is too small.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn add_empty_state(&mut self) -> Result<S> {
+        assert!(!self.premultiplied, "can't add state to premultiplied DFA");
+
+        let id = if self.state_count == 0 {
+            S::from_usize(0)
+        } else {
+            next_state_id(S::from_usize(self.state_count - 1))?
+        };
+        let alphabet_len = self.alphabet_len();
+        self.trans.extend(iter::repeat(dead_id::<S>()).take(alphabet_len));
+        // This should never panic, since state_count is a usize. The
+        // transition table size would have run out of room long ago.
+        self.state_count = self.state_count.checked_add(1).unwrap();
+        Ok(id)
+    }
+
+    /// Return a mutable representation of the state corresponding to the given
+    /// id. This is useful for implementing routines that manipulate DFA states
+    /// (e.g., swapping states).
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn get_state_mut(&mut self, id: S) -> StateMut<S> {
+        assert!(!self.premultiplied, "can't get state in premultiplied DFA");
+
+        let alphabet_len = self.alphabet_len();
+        let offset = id.to_usize() * alphabet_len;
+        StateMut {
+            transitions: &mut self.trans[offset..offset + alphabet_len],
+            eoi: self.lookaround,
+        }
+    }
+
+    /// Swap the two states given in the transition table.
+    ///
+    /// This routine does not do anything to check the correctness of this
+    /// swap. Callers must ensure that other states pointing to id1 and id2 are
+    /// updated appropriately.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn swap_states(&mut self, id1: S, id2: S) {
+        assert!(!self.premultiplied, "can't swap states in premultiplied DFA");
+
+        let o1 = id1.to_usize() * self.alphabet_len();
+        let o2 = id2.to_usize() * self.alphabet_len();
+        for b in 0..self.alphabet_len() {
+            self.trans.swap(o1 + b, o2 + b);
+        }
+    }
+
+    /// Truncate the states in this DFA to the given count.
+    ///
+    /// This routine does not do anything to check the correctness of this
+    /// truncation. Callers must ensure that other states pointing to truncated
+    /// states are updated appropriately.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn truncate_states(&mut self, count: usize) {
+        assert!(!self.premultiplied, "can't truncate in premultiplied DFA");
+
+        let alphabet_len = self.alphabet_len();
+        self.trans.truncate(count * alphabet_len);
+        self.state_count = count;
+    }
+
+    /// Set the patterns that match in each match state, given in the order
+    /// in which match states appear in the transition table. The patterns
+    /// for each match state should be given in order of priority.
+    ///
+    /// This only records anything when this DFA was built from more than
+    /// one pattern. If a pattern identifier or the size of the resulting
+    /// table cannot be represented by `S`, then this returns an error.
+    pub fn set_match_patterns<P: AsRef<[PatternID]>>(
+        &mut self,
+        patterns: &[P],
+    ) -> Result<()> {
+        self.matches.clear();
+        if self.pattern_count <= 1 {
+            return Ok(());
+        }
+        let mut offset = patterns.len() + 1;
+        self.matches.push(usize_to_state_id(offset)?);
+        for ps in patterns {
+            offset += ps.as_ref().len();
+            self.matches.push(usize_to_state_id(offset)?);
+        }
+        for ps in patterns {
+            for &p in ps.as_ref() {
+                self.matches.push(usize_to_state_id(p)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// This routine shuffles all match states in this DFA---according to the
+    /// given map---to the beginning of the DFA such that every non-match state
+    /// appears after every match state. (With one exception: the special dead
+    /// state remains as the first state.) The given map should have length
+    /// exactly equivalent to the number of states in this DFA, and maps each
+    /// state to the patterns that match in it, which is empty for non-match
+    /// states.
+    ///
+    /// The purpose of doing this shuffling is to avoid the need to store
+    /// additional state to determine whether a state is a match state or not.
+    /// It also enables a single conditional in the core matching loop instead
+    /// of two.
+    ///
+    /// This updates `self.max_match` to point to the last matching state as
+    /// well as `self.start` and `self.start_anchored` if either starting
+    /// state was moved. The patterns
+    /// for each match state are recorded via `set_match_patterns`, which may
+    /// return an error.
+    pub fn shuffle_match_states(
+        &mut self,
+        matches: &[&[PatternID]],
+    ) -> Result<()> {
+        assert!(
+            !self.premultiplied,
+            "cannot shuffle match states of premultiplied DFA"
+        );
+        assert_eq!(self.state_count, matches.len());
+
+        if self.state_count <= 1 {
+            return Ok(());
+        }
+
+        let is_match: Vec<bool> =
+            matches.iter().map(|m| !m.is_empty()).collect();
+        let mut matches = matches.to_vec();
+
+        let mut first_non_match = 1;
+        while first_non_match < self.state_count && is_match[first_non_match] {
+            first_non_match += 1;
+        }
+
+        let mut swaps: Vec<S> = vec![dead_id(); self.state_count];
+        let mut cur = self.state_count - 1;
+        while cur > first_non_match {
+            if is_match[cur] {
+                self.swap_states(
+                    S::from_usize(cur),
+                    S::from_usize(first_non_match),
+                );
+                swaps[cur] = S::from_usize(first_non_match);
+                swaps[first_non_match] = S::from_usize(cur);
+                matches.swap(cur, first_non_match);
+
+                first_non_match += 1;
+                while first_non_match < cur && is_match[first_non_match] {
+                    first_non_match += 1;
+                }
+            }
+            cur -= 1;
+        }
+        for id in (0..self.state_count).map(S::from_usize) {
+            for (_, next) in self.get_state_mut(id).iter_mut() {
+                if swaps[next.to_usize()] != dead_id() {
+                    *next = swaps[next.to_usize()];
+                }
+            }
+        }
+        if swaps[self.start.to_usize()] != dead_id() {
+            self.start = swaps[self.start.to_usize()];
+        }
+        if let Some(start) = self.start_anchored {
+            if swaps[start.to_usize()] != dead_id() {
+                self.start_anchored = Some(swaps[start.to_usize()]);
+            }
+        }
+        for start in &mut self.starts {
+            if swaps[start.to_usize()] != dead_id() {
+                *start = swaps[start.to_usize()];
+            }
+        }
+        self.max_match = S::from_usize(first_non_match - 1);
+        self.set_match_patterns(&matches[1..first_non_match])
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> fmt::Debug for Repr<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn state_status<T: AsRef<[S]>, S: StateID>(
+            dfa: &Repr<T, S>,
+            id: S,
+        ) -> &'static str {
+            if id == dead_id() {
+                if dfa.is_match_state(id) {
+                    "D*"
+                } else {
+                    "D "
+                }
+            } else if id == dfa.start_state() {
+                if dfa.is_match_state(id) {
+                    ">*"
+                } else {
+                    "> "
+                }
+            } else {
+                if dfa.is_match_state(id) {
+                    " *"
+                } else {
+                    "  "
+                }
+            }
+        }
+
+        writeln!(f, "DenseDFA(")?;
+        for (id, state) in self.states() {
+            let status = state_status(self, id);
+            writeln!(f, "{}{:06}: {:?}", status, id.to_usize(), state)?;
+        }
+        writeln!(f, ")")?;
+        Ok(())
+    }
+}
+
+/// An iterator over all states in a DFA.
+///
+/// This iterator yields a tuple for each state. The first element of the
+/// tuple corresponds to a state's identifier, and the second element
+/// corresponds to the state itself (comprised of its transitions).
+///
+/// If this DFA is premultiplied, then the state identifiers are in turn
+/// premultiplied as well, making them usable without additional modification.
+///
+/// `'a` corresponding to the lifetime of original DFA, `T` corresponds to
+/// the type of the transition table itself and `S` corresponds to the state
+/// identifier representation.
+#[cfg(feature = "std")]
+pub(crate) struct StateIter<'a, T: 'a, S: 'a> {
+    dfa: &'a Repr<T, S>,
+    it: iter::Enumerate<slice::Chunks<'a, S>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: AsRef<[S]>, S: StateID> Iterator for StateIter<'a, T, S> {
+    type Item = (S, StateRow<'a, S>);
+
+    fn next(&mut self) -> Option<(S, StateRow<'a, S>)> {
+        self.it.next().map(|(id, chunk)| {
+            let state =
+                StateRow { transitions: chunk, eoi: self.dfa.lookaround };
+            let id = if self.dfa.premultiplied {
+                id * self.dfa.alphabet_len()
+            } else {
+                id
+            };
+            (S::from_usize(id), state)
+        })
+    }
+}
+
+/// An immutable representation of a single row of a DFA's transition table.
+///
+/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
+/// corresponds to the state identifier representation.
+#[cfg(feature = "std")]
+pub(crate) struct StateRow<'a, S: 'a> {
+    transitions: &'a [S],
+    /// Whether the last transition is taken at the end of the input rather
+    /// than on an equivalence class of bytes.
+    eoi: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> StateRow<'a, S> {
+    /// Return an iterator over all transitions in this state. This yields
+    /// a number of transitions equivalent to the alphabet length of the
+    /// corresponding DFA.
+    ///
+    /// Each transition is represented by a tuple. The first element is
+    /// the index of the transition in the row, which is the equivalence
+    /// class of its input bytes or the end of input, and the second element
+    /// is the transitions itself.
+    pub fn transitions(&self) -> StateTransitionIter<'a, S> {
+        StateTransitionIter { it: self.transitions.iter().enumerate() }
+    }
+
+    /// Return the transition taken at the end of the input, if this state
+    /// has one.
+    pub fn eoi(&self) -> Option<S> {
+        if self.eoi {
+            self.transitions.last().cloned()
+        } else {
+            None
+        }
+    }
+
+    /// Return the transitions of this state on equivalence classes of bytes.
+    fn byte_transitions(&self) -> &'a [S] {
+        &self.transitions[..self.transitions.len() - self.eoi as usize]
+    }
+
+    /// Return an iterator over a sparse representation of the transitions in
+    /// this state. Only non-dead transitions are returned.
+    ///
+    /// The "sparse" representation in this case corresponds to a sequence of
+    /// triples. The first two elements of the triple comprise an inclusive
+    /// byte range while the last element corresponds to the transition taken
+    /// for all bytes in the range.
+    ///
+    /// This is somewhat more condensed than the classical sparse
+    /// representation (where you have an element for every non-dead
+    /// transition), but in practice, checking if a byte is in a range is very
+    /// cheap and using ranges tends to conserve quite a bit more space.
+    pub fn sparse_transitions(&self) -> StateSparseTransitionIter<S> {
+        let dense = StateTransitionIter {
+            it: self.byte_transitions().iter().enumerate(),
+        };
+        StateSparseTransitionIter { dense, cur: None }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> fmt::Debug for StateRow<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut transitions = vec![];
+        for (start, end, next_id) in self.sparse_transitions() {
+            let line = if start == end {
+                format!("{} => {}", escape(start), next_id.to_usize())
+            } else {
+                format!(
+                    "{}-{} => {}",
+                    escape(start),
+                    escape(end),
+                    next_id.to_usize(),
+                )
+            };
+            transitions.push(line);
+        }
+        if let Some(next_id) = self.eoi() {
+            if next_id != dead_id() {
+                transitions.push(format!("EOI => {}", next_id.to_usize()));
+            }
+        }
+        write!(f, "{}", transitions.join(", "))?;
+        Ok(())
+    }
+}
+
+/// An iterator over all transitions in a single DFA state. This yields
+/// a number of transitions equivalent to the alphabet length of the
+/// corresponding DFA.
+///
+/// Each transition is represented by a tuple. The first element is the index
+/// of that transition in the row and the second element is the transitions
+/// itself.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct StateTransitionIter<'a, S: 'a> {
+    it: iter::Enumerate<slice::Iter<'a, S>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateTransitionIter<'a, S> {
+    type Item = (usize, S);
+
+    fn next(&mut self) -> Option<(usize, S)> {
+        self.it.next().map(|(i, &id)| (i, id))
+    }
+}
+
+/// An iterator over all transitions in a single DFA state using a sparse
+/// representation.
+///
+/// Each transition is represented by a triple. The first two elements of the
+/// triple comprise an inclusive byte range while the last element corresponds
+/// to the transition taken for all bytes in the range.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct StateSparseTransitionIter<'a, S: 'a> {
+    dense: StateTransitionIter<'a, S>,
+    cur: Option<(u8, u8, S)>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateSparseTransitionIter<'a, S> {
+    type Item = (u8, u8, S);
+
+    fn next(&mut self) -> Option<(u8, u8, S)> {
+        while let Some((b, next)) = self.dense.next() {
+            let (prev_start, prev_end, prev_next) = match self.cur {
+                Some(t) => t,
+                None => {
+                    self.cur = Some((b as u8, b as u8, next));
+                    continue;
+                }
+            };
+            if prev_next == next {
+                self.cur = Some((prev_start, b as u8, prev_next));
+            } else {
+                self.cur = Some((b as u8, b as u8, next));
+                if prev_next != dead_id() {
+                    return Some((prev_start, prev_end, prev_next));
+                }
+            }
+        }
+        if let Some((start, end, next)) = self.cur.take() {
+            if next != dead_id() {
+                return Some((start, end, next));
+            }
+        }
+        None
+    }
+}
+
+/// A mutable representation of a single DFA state.
+///
+/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
+/// corresponds to the state identifier representation.
+#[cfg(feature = "std")]
+pub(crate) struct StateMut<'a, S: 'a> {
+    transitions: &'a mut [S],
+    eoi: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> StateMut<'a, S> {
+    /// Return an iterator over all transitions in this state. This yields
+    /// a number of transitions equivalent to the alphabet length of the
+    /// corresponding DFA.
+    ///
+    /// Each transition is represented by a tuple. The first element is the
+    /// index of that transition in the row and the second element is a
+    /// mutable reference to the transition itself.
+    pub fn iter_mut(&mut self) -> StateTransitionIterMut<S> {
+        StateTransitionIterMut { it: self.transitions.iter_mut().enumerate() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> fmt::Debug for StateMut<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let row = StateRow { transitions: self.transitions, eoi: self.eoi };
+        fmt::Debug::fmt(&row, f)
+    }
+}
+
+/// A mutable iterator over all transitions in a DFA state.
+///
+/// Each transition is represented by a tuple. The first element is the
+/// index of that transition in the row and the second element is a mutable
+/// reference to the transition itself.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct StateTransitionIterMut<'a, S: 'a> {
+    it: iter::Enumerate<slice::IterMut<'a, S>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateTransitionIterMut<'a, S> {
+    type Item = (usize, &'a mut S);
+
+    fn next(&mut self) -> Option<(usize, &'a mut S)> {
+        self.it.next()
+    }
+}
+
+/// An iterator over all states in a dense DFA.
+///
+/// This iterator is created by
+/// [`DenseDFA::states`](enum.DenseDFA.html#method.states).
+///
+/// `'a` corresponds to the lifetime of the DFA, `T` corresponds to the type
+/// of the transition table itself and `S` corresponds to the state
+/// identifier representation.
+#[derive(Clone, Debug)]
+pub struct States<'a, T: AsRef<[S]> + 'a, S: StateID + 'a> {
+    dfa: &'a Repr<T, S>,
+    index: usize,
+}
+
+impl<'a, T: AsRef<[S]>, S: StateID> Iterator for States<'a, T, S> {
+    type Item = State<'a, T, S>;
+
+    fn next(&mut self) -> Option<State<'a, T, S>> {
+        if self.index >= self.dfa.state_count {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        Some(State { dfa: self.dfa, id: self.dfa.state_index_to_id(index) })
+    }
+}
+
+/// A read-only view of a single state in a dense DFA.
+///
+/// States are yielded by
+/// [`DenseDFA::states`](enum.DenseDFA.html#method.states).
+///
+/// `'a` corresponds to the lifetime of the DFA, `T` corresponds to the type
+/// of the transition table itself and `S` corresponds to the state
+/// identifier representation.
+#[derive(Clone, Copy, Debug)]
+pub struct State<'a, T: AsRef<[S]> + 'a, S: StateID + 'a> {
+    dfa: &'a Repr<T, S>,
+    id: S,
+}
+
+impl<'a, T: AsRef<[S]>, S: StateID> State<'a, T, S> {
+    /// Returns the identifier of this state.
+    ///
+    /// This is always the logical identifier of this state, which is equal
+    /// to its [`index`](struct.State.html#method.index), regardless of
+    /// whether the DFA is premultiplied. To give this state to the methods
+    /// of the [`DFA`](../trait.DFA.html) trait, such as `next_state`,
+    /// convert it with
+    /// [`DenseDFA::state_id`](enum.DenseDFA.html#method.state_id).
+    pub fn id(&self) -> S {
+        S::from_usize(self.index())
+    }
+
+    /// Returns the position of this state in the DFA's transition table.
+    /// The states of a DFA have indices `0` through `state_count() - 1`,
+    /// where the dead state always has index `0`.
+    pub fn index(&self) -> usize {
+        self.dfa.state_id_to_index(self.id)
+    }
+
+    /// Returns true if and only if this is a match state.
+    pub fn is_match(&self) -> bool {
+        self.dfa.is_match_state(self.id)
+    }
+
+    /// Returns true if and only if this is the dead state.
+    pub fn is_dead(&self) -> bool {
+        self.dfa.is_dead_state(self.id)
+    }
+
+    /// Returns true if and only if this is the start state of the DFA.
+    pub fn is_start(&self) -> bool {
+        self.id == self.dfa.start_state()
+    }
+
+    /// Returns an iterator over the transitions out of this state.
+    ///
+    /// Each transition is a pair of an inclusive range of bytes and the
+    /// logical identifier of the state reached on any byte in the range,
+    /// which is equal to that state's
+    /// [`index`](struct.State.html#method.index). Ranges are
+    /// yielded in ascending order, and cover every byte exactly once, so
+    /// transitions to the dead state are included. Adjacent bytes leading to
+    /// the same state are always part of the same range.
+    pub fn transitions(&self) -> Transitions<'a, T, S> {
+        Transitions { dfa: self.dfa, id: self.id, next: Some(0) }
+    }
+}
+
+/// An iterator over the transitions out of a single state in a dense DFA.
+///
+/// This iterator is created by
+/// [`State::transitions`](struct.State.html#method.transitions).
+#[derive(Clone, Debug)]
+pub struct Transitions<'a, T: AsRef<[S]> + 'a, S: StateID + 'a> {
+    dfa: &'a Repr<T, S>,
+    id: S,
+    next: Option<u8>,
+}
+
+impl<'a, T: AsRef<[S]>, S: StateID> Transitions<'a, T, S> {
+    fn next_state(&self, input: u8) -> S {
+        let class = self.dfa.byte_classes().get(input) as usize;
+        let row = self.dfa.state_id_to_index(self.id);
+        let next = self.dfa.trans()[row * self.dfa.alphabet_len() + class];
+        S::from_usize(self.dfa.state_id_to_index(next))
+    }
+}
+
+impl<'a, T: AsRef<[S]>, S: StateID> Iterator for Transitions<'a, T, S> {
+    type Item = (RangeInclusive<u8>, S);
+
+    fn next(&mut self) -> Option<(RangeInclusive<u8>, S)> {
+        let start = self.next?;
+        let next = self.next_state(start);
+        let mut end = start;
+        self.next = None;
+        while end < 255 {
+            if self.next_state(end + 1) != next {
+                self.next = Some(end + 1);
+                break;
+            }
+            end += 1;
+        }
+        Some((start..=end, next))
+    }
+}
+
+/// The match semantics of a DFA.
+///
+/// The match semantics determine which match is reported when more than one
+/// match is possible. In every case, a match that starts earlier is
+/// preferred over a match that starts later. The match semantics only
+/// determine which match is reported among the matches that start at the
+/// same position.
+///
+/// This is configured with
+/// [`Builder::match_kind`](struct.Builder.html#method.match_kind).
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MatchKind {
+    /// Report the match preferred by the regex itself, which is how Perl-like
+    /// regex engines behave. That is, alternations prefer their earlier
+    /// branches and repetitions prefer to match as much as possible, unless
+    /// they are non-greedy. For example, `a|ab` reports `a` when searching
+    /// `ab`.
+    ///
+    /// This is the default.
+    LeftmostFirst,
+    /// Report the longest match, as prescribed by POSIX. The order of
+    /// alternations and the greediness of repetitions have no effect. For
+    /// example, `a|ab` reports `ab` when searching `ab`.
+    LeftmostLongest,
+}
+
+/// A builder for constructing a deterministic finite automaton from regular
+/// expressions.
+///
+/// This builder permits configuring several aspects of the construction
+/// process such as case insensitivity, Unicode support and various options
+/// that impact the size of the generated DFA. In some cases, options (like
+/// performing DFA minimization) can come with a substantial additional cost.
+///
+/// This builder always constructs a *single* DFA. As such, this builder can
+/// only be used to construct regexes that either detect the presence of a
+/// match or find the end location of a match. A single DFA cannot produce both
+/// the start and end of a match. For that information, use a
+/// [`Regex`](struct.Regex.html), which can be similarly configured using
+/// [`RegexBuilder`](struct.RegexBuilder.html).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Builder {
+    parser: ParserBuilder,
+    nfa: nfa::Builder,
+    case_insensitive: bool,
+    anchored: bool,
+    minimize: bool,
+    reduce: bool,
+    premultiply: bool,
+    byte_classes: bool,
+    shared_byte_classes: Option<ByteClasses>,
+    reverse: bool,
+    match_kind: MatchKind,
+    match_only: bool,
+    anchored_start: bool,
+    starts_for_each_pattern: bool,
+    all_matches: bool,
+    quit: ByteSet,
+    unicode_word_boundary: bool,
+    accelerate: bool,
+    state_id_size: Option<usize>,
+    dfa_size_limit: Option<usize>,
+    time_limit: Option<Duration>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+}
+
+#[cfg(feature = "std")]
+impl Builder {
+    /// Create a new DenseDFA builder with the default configuration.
+    pub fn new() -> Builder {
+        let mut nfa = nfa::Builder::new();
+        // This is enabled by default, but we set it here anyway. Since we're
+        // building a DFA, shrinking the NFA is always a good idea.
+        nfa.shrink(true);
+        Builder {
+            parser: ParserBuilder::new(),
+            nfa,
+            case_insensitive: false,
+            anchored: false,
+            minimize: false,
+            reduce: false,
+            premultiply: true,
+            byte_classes: true,
+            shared_byte_classes: None,
+            reverse: false,
+            match_kind: MatchKind::LeftmostFirst,
+            match_only: false,
+            anchored_start: false,
+            starts_for_each_pattern: false,
+            all_matches: false,
+            quit: ByteSet::empty(),
+            unicode_word_boundary: false,
+            accelerate: true,
+            state_id_size: None,
+            dfa_size_limit: None,
+            time_limit: None,
+            cancel_flag: None,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+        }
+    }
+
+    /// Build a DFA from the given pattern.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    pub fn build(&self, pattern: &str) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_with_size::<usize>(pattern)
+    }
+
+    /// Build a DFA from the given pattern using a specific representation for
+    /// the DFA's state IDs.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// The representation of state IDs is determined by the `S` type
+    /// parameter. In general, `S` is usually one of `u8`, `u16`, `u32`, `u64`
+    /// or `usize`, where `usize` is the default used for `build`. The purpose
+    /// of specifying a representation for state IDs is to reduce the memory
+    /// footprint of a DFA.
+    ///
+    /// When using this routine, the chosen state ID representation will be
+    /// used throughout determinization and minimization, if minimization
+    /// was requested. Even if the minimized DFA can fit into the chosen
+    /// state ID representation but the initial determinized DFA cannot,
+    /// then this will still return an error. To get a minimized DFA with a
+    /// smaller state ID representation, first build it with a bigger state ID
+    /// representation, and then shrink the size of the DFA using one of its
+    /// conversion routines, such as
+    /// [`DenseDFA::to_u16`](enum.DenseDFA.html#method.to_u16).
+    pub fn build_with_size<S: StateID>(
+        &self,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.build_from_nfa(&self.build_nfa(pattern)?)
+    }
+
+    /// Build a DFA from the given pattern using the smallest state
+    /// identifier representation that fits.
+    ///
+    /// The DFA is built with `usize` state identifiers, and then converted to
+    /// the smallest representation, among `u8`, `u16` and `u32`, that can fit
+    /// all of its state identifiers. Unlike
+    /// [`build_with_size`](struct.Builder.html#method.build_with_size), this
+    /// takes minimization into account: a minimized DFA gets the smallest
+    /// representation that fits the minimized DFA. A specific representation
+    /// can be forced with
+    /// [`state_id_size`](struct.Builder.html#method.state_id_size).
+    ///
+    /// If there was a problem parsing or compiling the pattern, or if the DFA
+    /// doesn't fit in the chosen representation, then an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build_sized("foo[0-9]+")?;
+    /// assert_eq!(1, dfa.state_id_size());
+    ///
+    /// let dfa = dense::Builder::new()
+    ///     .state_id_size(Some(4))
+    ///     .build_sized("foo[0-9]+")?;
+    /// assert_eq!(4, dfa.state_id_size());
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_sized(&self, pattern: &str) -> Result<SizedDFA> {
+        let dfa = self.build(pattern)?;
+        match self.state_id_size {
+            None => SizedDFA::smallest(&dfa),
+            Some(size) => SizedDFA::with_size(&dfa, size),
+        }
+    }
+
+    /// Build a DFA from the given pattern, along with the non-fatal warnings
+    /// reported while building it.
+    ///
+    /// This builds the same DFA as
+    /// [`build`](struct.Builder.html#method.build). The
+    /// [`Diagnostics`](../struct.Diagnostics.html) returned beside it report
+    /// each way in which the DFA was quietly downgraded, such as a Unicode
+    /// word boundary compiled as an ASCII word boundary when
+    /// [`unicode_word_boundary`](struct.Builder.html#method.unicode_word_boundary)
+    /// is enabled.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, BuildWarning};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let (_, diagnostics) = dense::Builder::new()
+    ///     .unicode_word_boundary(true)
+    ///     .build_with_diagnostics(r"\bfoo\b")?;
+    /// assert_eq!(
+    ///     &[BuildWarning::UnicodeWordBoundaryAsAscii],
+    ///     diagnostics.warnings(),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_with_diagnostics(
+        &self,
+        pattern: &str,
+    ) -> Result<(DenseDFA<Vec<usize>, usize>, Diagnostics)> {
+        let nfa = self.build_nfa(pattern)?;
+        let dfa = self.build_from_nfa(&nfa)?;
+        Ok((dfa, self.diagnostics(&nfa)))
+    }
+
+    /// Returns the warnings that building a DFA from the given NFA with this
+    /// builder reports.
+    fn diagnostics(&self, nfa: &NFA) -> Diagnostics {
+        let mut diagnostics = Diagnostics::new();
+        if self.unicode_word_boundary && nfa.has_unicode_look() {
+            diagnostics.warn(BuildWarning::UnicodeWordBoundaryAsAscii);
+        }
+        diagnostics
+    }
+
+    /// Build a DFA from the given high-level intermediate representation
+    /// (HIR) of a regular expression.
+    ///
+    /// This is useful when the caller has already parsed, and possibly
+    /// transformed, a pattern with `regex-syntax`, since it avoids parsing
+    /// the pattern again. Since the HIR is already parsed, the syntax options
+    /// of this builder, such as `case_insensitive` or `unicode`, are ignored.
+    /// All other options, including `anchored` and `reverse`, apply as usual.
+    ///
+    /// If the HIR uses a feature that is unsupported, such as anchors or word
+    /// boundaries, then an error is returned.
+    ///
+    /// # Example
+    ///
+    /// This example parses a pattern, and then builds a DFA from its HIR.
+    ///
+    /// ```
+    /// extern crate regex_automata;
+    /// extern crate regex_syntax;
+    ///
+    /// use regex_automata::{dense, DFA};
+    /// use regex_syntax::ParserBuilder;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let hir = ParserBuilder::new()
+    ///     .case_insensitive(true)
+    ///     .build()
+    ///     .parse("foo[0-9]+")?;
+    /// let dfa = dense::Builder::new().build_from_hir(&hir)?;
+    /// assert_eq!(Some(8), dfa.find(b"FoO12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_from_hir(
+        &self,
+        hir: &Hir,
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_from_hir_with_size::<usize>(hir)
+    }
+
+    /// Build a DFA from the given high-level intermediate representation
+    /// (HIR) of a regular expression using a specific representation for the
+    /// DFA's state IDs.
+    ///
+    /// This is like [`build_from_hir`](struct.Builder.html#method.build_from_hir),
+    /// except the representation of state IDs is determined by the `S` type
+    /// parameter, just like in
+    /// [`build_with_size`](struct.Builder.html#method.build_with_size).
+    pub fn build_from_hir_with_size<S: StateID>(
+        &self,
+        hir: &Hir,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.build_from_nfa(&self.nfa.build(hir)?)
+    }
+
+    /// Build a DFA that matches any of the given literals, without parsing
+    /// any pattern.
+    ///
+    /// The DFA built matches the same strings as an alternation of the
+    /// literals, escaped and in the same order, with the same priority
+    /// among them. Since every literal belongs to the same pattern, a match
+    /// never says which literal matched. To find out, build the DFA from
+    /// each escaped literal with
+    /// [`build_many`](struct.Builder.html#method.build_many) instead.
+    ///
+    /// The literals are arbitrary bytes, and their common prefixes are
+    /// factored out before compiling them. This is much faster than parsing
+    /// and compiling the equivalent alternation, especially for large sets
+    /// of literals, and yields a smaller NFA. When
+    /// [`case_insensitive`](struct.Builder.html#method.case_insensitive) is
+    /// enabled, ASCII letters in the literals match regardless of case. The
+    /// other syntax options of this builder are ignored.
+    ///
+    /// If no literals are given, then the DFA never matches. If there was a
+    /// problem building the DFA, then an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let literals: &[&[u8]] = &[b"foo", b"foobar", b"a.b"];
+    /// let dfa = dense::Builder::new().build_literals(literals)?;
+    /// assert_eq!(Some(6), dfa.find(b"xyzfoobar"));
+    /// assert_eq!(None, dfa.find(b"axb"));
+    ///
+    /// let dfa = dense::Builder::new()
+    ///     .case_insensitive(true)
+    ///     .build_literals(literals)?;
+    /// assert_eq!(Some(3), dfa.find(b"FoObar"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_literals(
+        &self,
+        literals: &[&[u8]],
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_literals_with_size::<usize>(literals)
+    }
+
+    /// Build a DFA that matches any of the given literals using a specific
+    /// representation for the DFA's state IDs.
+    ///
+    /// This is like [`build_literals`](struct.Builder.html#method.build_literals),
+    /// except the representation of state IDs is determined by the `S` type
+    /// parameter, just like in
+    /// [`build_with_size`](struct.Builder.html#method.build_with_size).
+    pub fn build_literals_with_size<S: StateID>(
+        &self,
+        literals: &[&[u8]],
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let mut trie = LiteralTrie::new(self.case_insensitive);
+        for literal in literals {
+            trie.insert(literal);
+        }
+        self.build_from_hir_with_size(&trie.to_hir())
+    }
+
+    /// Build a DFA that matches if and only if any of the given patterns
+    /// match.
+    ///
+    /// Unlike building a DFA from an alternation of the given patterns, the
+    /// DFA returned is only suitable for answering whether a match exists.
+    /// That is, as soon as a match is seen, the DFA stops searching. This
+    /// permits all match states to be collapsed into a single state, which
+    /// can result in a much smaller DFA. As with
+    /// [`build_match_only`](struct.Builder.html#method.build_match_only),
+    /// it is therefore returned as a
+    /// [`MatchOnlyDFA`](struct.MatchOnlyDFA.html), which can't report the
+    /// end of a match or which pattern matched.
+    ///
+    /// If no patterns are given, then the DFA returned never matches.
+    ///
+    /// If there was a problem parsing or compiling any of the patterns, then
+    /// an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build_union(&["foo", "bar[0-9]+"])?;
+    /// assert!(dfa.is_match(b"xyz bar123"));
+    /// assert!(dfa.is_match(b"foo"));
+    /// assert!(!dfa.is_match(b"quux"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_union(
+        &self,
+        patterns: &[&str],
+    ) -> Result<MatchOnlyDFA<Vec<usize>, usize>> {
+        self.build_union_with_size::<usize>(patterns)
+    }
+
+    /// Build a union DFA, as in
+    /// [`build_union`](struct.Builder.html#method.build_union), using a
+    /// specific representation for the DFA's state IDs.
+    pub fn build_union_with_size<S: StateID>(
+        &self,
+        patterns: &[&str],
+    ) -> Result<MatchOnlyDFA<Vec<S>, S>> {
+        let mut builder = self.clone();
+        builder.match_only = true;
+        if patterns.is_empty() {
+            let dfa = builder.build_from_nfa(&NFA::never_match())?;
+            return Ok(MatchOnlyDFA { dfa });
+        }
+        let mut exprs = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            exprs.push(
+                self.parser.build().parse(pattern).map_err(Error::syntax)?,
+            );
+        }
+        let nfa = self.nfa.build(&Hir::alternation(exprs))?;
+        Ok(MatchOnlyDFA { dfa: builder.build_from_nfa(&nfa)? })
+    }
+
+    /// Build a single DFA from several patterns, such that each match state
+    /// records which of the patterns match.
+    ///
+    /// Patterns are identified by their index in the slice given, and the
+    /// pattern that matches at any particular position can be found with
+    /// [`DFA::find_pattern`](../trait.DFA.html#method.find_pattern) or by
+    /// inspecting match states with
+    /// [`DFA::match_pattern`](../trait.DFA.html#method.match_pattern).
+    /// When several patterns match at the same position, leftmost first
+    /// match semantics report only the pattern that appears first, while
+    /// longest match semantics report all of them, in the order in which
+    /// they were given. To report them in another order, use
+    /// [`build_many_prioritized`](struct.Builder.html#method.build_many_prioritized).
+    ///
+    /// Searching a single DFA built from many patterns is typically much
+    /// faster than searching each pattern separately, since each byte of
+    /// input is only visited once.
+    ///
+    /// If no patterns are given, then the DFA returned never matches.
+    ///
+    /// If there was a problem parsing or compiling any of the patterns, then
+    /// an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .build_many(&["/users/[0-9]+", "/users/me", "/posts/[a-z]+"])?;
+    /// assert_eq!(3, dfa.pattern_count());
+    /// assert_eq!(Some((0, 9)), dfa.find_pattern(b"/users/42"));
+    /// assert_eq!(Some((1, 9)), dfa.find_pattern(b"/users/me"));
+    /// assert_eq!(Some((2, 11)), dfa.find_pattern(b"/posts/rust"));
+    /// assert_eq!(None, dfa.find_pattern(b"/about"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_many(
+        &self,
+        patterns: &[&str],
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_many_with_size::<usize>(patterns)
+    }
+
+    /// Build a DFA from several patterns, as in
+    /// [`build_many`](struct.Builder.html#method.build_many), using a
+    /// specific representation for the DFA's state IDs.
+    ///
+    /// Note that pattern identifiers use the same representation as state
+    /// identifiers, so building a DFA from more patterns than `S` can
+    /// represent returns an error.
+    pub fn build_many_with_size<S: StateID>(
+        &self,
+        patterns: &[&str],
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let mut nfa = match patterns.first() {
+            None => return self.build_from_nfa(&NFA::never_match()),
+            Some(pattern) => self.build_nfa(pattern)?,
+        };
+        for pattern in &patterns[1..] {
+            let hir =
+                self.parser.build().parse(pattern).map_err(Error::syntax)?;
+            self.nfa.add_pattern(&mut nfa, &hir)?;
+        }
+        self.build_from_nfa(&nfa)
+    }
+
+    /// Build a DFA from several patterns, as in
+    /// [`build_many`](struct.Builder.html#method.build_many), attaching an
+    /// arbitrary tag to each pattern.
+    ///
+    /// The tag of a matching pattern can be retrieved with
+    /// [`DenseDFA::pattern_tag`](enum.DenseDFA.html#method.pattern_tag).
+    /// Unlike pattern identifiers, which depend on the order in which
+    /// patterns are given, tags are chosen by the caller and are stored in
+    /// the serialized form of the DFA. This makes it possible to map
+    /// matches back to an external table of rules, even when the DFA was
+    /// compiled separately from that table. Tags need not be unique.
+    ///
+    /// Tags are not retained when converting to a sparse DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .build_many_tagged(&[(1001, "[0-9]+"), (2002, "[a-z]+")])?;
+    /// let (pattern, end) = dfa.find_pattern(b"abc").unwrap();
+    /// assert_eq!(3, end);
+    /// assert_eq!(Some(2002), dfa.pattern_tag(pattern));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_many_tagged(
+        &self,
+        patterns: &[(u64, &str)],
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_many_tagged_with_size::<usize>(patterns)
+    }
+
+    /// Build a DFA from several tagged patterns, as in
+    /// [`build_many_tagged`](struct.Builder.html#method.build_many_tagged),
+    /// using a specific representation for the DFA's state IDs.
+    pub fn build_many_tagged_with_size<S: StateID>(
+        &self,
+        patterns: &[(u64, &str)],
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let exprs: Vec<&str> = patterns.iter().map(|&(_, p)| p).collect();
+        let tags: Vec<u64> = patterns.iter().map(|&(tag, _)| tag).collect();
+        let mut dfa = self.build_many_with_size::<S>(&exprs)?;
+        dfa.repr_mut().set_pattern_tags(&tags);
+        Ok(dfa)
+    }
+
+    /// Build a DFA from several patterns, as in
+    /// [`build_many`](struct.Builder.html#method.build_many), where each
+    /// pattern has a priority that decides which pattern is reported when
+    /// several of them match the same bytes.
+    ///
+    /// With
+    /// [`MatchKind::LeftmostLongest`](enum.MatchKind.html#variant.LeftmostLongest),
+    /// the longest match is always preferred, and when several patterns
+    /// match up to the same end offset, the one with the highest priority is
+    /// reported. Ties between equal priorities are broken by pattern order,
+    /// which is also what `build_many` does when every priority is equal.
+    /// This is the maximal munch rule used by lexers, where a keyword should
+    /// win over an identifier of the same length, but not over a longer
+    /// one. With
+    /// [`all_matches`](struct.Builder.html#method.all_matches), the
+    /// patterns matching in each match state are ordered by priority in the
+    /// same way.
+    ///
+    /// Priorities have no effect on leftmost first match semantics, where
+    /// the pattern that appears first always wins, and so this returns an
+    /// error if the match kind is `MatchKind::LeftmostFirst` and
+    /// `all_matches` is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense::{self, MatchKind}, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build_many_prioritized(&[(1, "[a-z]+"), (2, "if|else")])?;
+    /// assert_eq!(Some((1, 2)), dfa.find_pattern(b"if x"));
+    /// assert_eq!(Some((0, 4)), dfa.find_pattern(b"iffy"));
+    /// assert_eq!(Some((0, 1)), dfa.find_pattern(b"x"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_many_prioritized(
+        &self,
+        patterns: &[(u32, &str)],
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_many_prioritized_with_size::<usize>(patterns)
+    }
+
+    /// Build a DFA from several prioritized patterns, as in
+    /// [`build_many_prioritized`](struct.Builder.html#method.build_many_prioritized),
+    /// using a specific representation for the DFA's state IDs.
+    pub fn build_many_prioritized_with_size<S: StateID>(
+        &self,
+        patterns: &[(u32, &str)],
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        if self.match_kind == MatchKind::LeftmostFirst && !self.all_matches {
+            return Err(Error::unsupported_priorities());
+        }
+        let exprs: Vec<&str> = patterns.iter().map(|&(_, p)| p).collect();
+        let priorities: Vec<u32> =
+            patterns.iter().map(|&(priority, _)| priority).collect();
+        let mut dfa = self.build_many_with_size::<S>(&exprs)?;
+        dfa.repr_mut().prioritize_matches(&priorities);
+        Ok(dfa)
+    }
+
+    /// Build a prefilter for a DFA built from the given patterns with
+    /// [`build_many`](struct.Builder.html#method.build_many).
+    ///
+    /// The prefilter reports candidates for the start of a match of any of
+    /// the patterns. When the patterns start with many different short
+    /// literals, the prefilter finds them with a packed searcher that
+    /// examines 16 or 32 positions at a time, depending on the SIMD
+    /// instructions supported by the CPU. Searching with
+    /// [`Prefilter::find_pattern_at`](../struct.Prefilter.html#method.find_pattern_at)
+    /// only runs the DFA from the candidates.
+    ///
+    /// If this builder is configured for anchored or reverse DFAs, or if no
+    /// useful literals could be extracted from the patterns, then `None` is
+    /// returned. If there was a problem parsing a pattern, then an error is
+    /// returned.
+    pub fn build_prefilter_many(
+        &self,
+        patterns: &[&str],
+    ) -> Result<Option<Prefilter>> {
+        if self.anchored || self.reverse {
+            return Ok(None);
+        }
+        let mut hirs = vec![];
+        for pattern in patterns {
+            hirs.push(self.parse(pattern)?);
+        }
+        Ok(Prefilter::from_hirs(&hirs))
+    }
+
+    /// Build a dense DFA directly from an NFA.
+    ///
+    /// This permits building an NFA once, e.g., with
+    /// [`build_nfa`](struct.Builder.html#method.build_nfa), and then deriving
+    /// several DFAs from it without parsing or compiling the pattern again.
+    /// Every option of this builder that affects the NFA, such as the regex
+    /// syntax options or `reverse`, is ignored, since the NFA is already
+    /// built. The exception is `anchored`: when enabled, the DFA built from
+    /// an unanchored NFA is anchored. A reverse DFA can be derived from a
+    /// forward NFA via [`NFA::reverse`](../nfa/struct.NFA.html#method.reverse).
+    ///
+    /// The representation of state IDs is determined by the `S` type
+    /// parameter.
+    ///
+    /// # Example
+    ///
+    /// This example builds an NFA once and derives an unanchored, an anchored
+    /// and a reverse DFA from it.
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let nfa = dense::Builder::new().build_nfa("[a-z]+[0-9]")?;
+    ///
+    /// let fwd = dense::Builder::new().build_from_nfa::<usize>(&nfa)?;
+    /// assert_eq!(Some(5), fwd.find(b"12ab3"));
+    ///
+    /// let anchored = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .build_from_nfa::<usize>(&nfa)?;
+    /// assert_eq!(None, anchored.find(b"12ab3"));
+    /// assert_eq!(Some(3), anchored.find(b"ab3"));
+    ///
+    /// let rev = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .longest_match(true)
+    ///     .build_from_nfa::<usize>(&nfa.reverse())?;
+    /// assert_eq!(Some(2), rev.rfind(b"12ab3"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_from_nfa<S: StateID>(
+        &self,
+        nfa: &NFA,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.build_from_nfa_imp(nfa, None)
+    }
+
+    /// Build a DFA from several patterns, as in
+    /// [`build_many`](struct.Builder.html#method.build_many), reusing the
+    /// work recorded in the given cache by a previous build.
+    ///
+    /// The DFA returned is the same as the one returned by `build_many`.
+    /// Once it's built, the cache is replaced by the cache of this build,
+    /// such that the next build can reuse its work. See
+    /// [`BuildCache`](struct.BuildCache.html) for when work can be reused.
+    ///
+    /// If there was a problem parsing or compiling a pattern, or building
+    /// the DFA, then an error is returned and the cache is left unchanged.
+    pub fn build_many_with_cache(
+        &self,
+        patterns: &[&str],
+        cache: &mut BuildCache,
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        let mut nfa = match patterns.first() {
+            None => NFA::never_match(),
+            Some(pattern) => self.build_nfa(pattern)?,
+        };
+        for pattern in patterns.iter().skip(1) {
+            let hir =
+                self.parser.build().parse(pattern).map_err(Error::syntax)?;
+            self.nfa.add_pattern(&mut nfa, &hir)?;
+        }
+        self.build_from_nfa_with_cache(&nfa, cache)
+    }
+
+    /// Build a dense DFA directly from an NFA, as in
+    /// [`build_from_nfa`](struct.Builder.html#method.build_from_nfa),
+    /// reusing the work recorded in the given cache by a previous build.
+    ///
+    /// Once the DFA is built, the cache is replaced by the cache of this
+    /// build. If building fails, then an error is returned and the cache is
+    /// left unchanged.
+    pub fn build_from_nfa_with_cache<S: StateID>(
+        &self,
+        nfa: &NFA,
+        cache: &mut BuildCache,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.build_from_nfa_imp(nfa, Some(cache))
+    }
+
+    /// Build a dense DFA from an NFA, with or without a cache.
+    fn build_from_nfa_imp<S: StateID>(
+        &self,
+        nfa: &NFA,
+        cache: Option<&mut BuildCache>,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let (mut dfa, _) = self.build_repr(nfa, None, cache)?;
+        if self.premultiply {
+            dfa.premultiply()?;
+        }
+        if self.accelerate {
+            dfa.accelerate();
+        }
+        Ok(dfa.into_dense_dfa())
+    }
+
+    /// Build a DFA directly from an explicit transition table, such as one
+    /// produced by another tool.
+    ///
+    /// The transition table is given in row-major order, with one row per
+    /// state and one transition per equivalence class of `byte_classes` in
+    /// each row, where each transition is the index of the row of the state
+    /// it leads to. When `byte_classes` is `None`, every byte is its own
+    /// class, so each row has 256 transitions. The first state must be the
+    /// dead state, whose transitions all lead back to itself. A search
+    /// begins in the `start` state, and a match ends right after the byte
+    /// whose transition leads to one of the `match_states`. A search stops
+    /// once it enters the dead state.
+    ///
+    /// The DFA matches a single pattern, and is unanchored unless
+    /// [`anchored`](struct.Builder.html#method.anchored) is enabled. Being
+    /// unanchored only means that a search may begin anywhere: the table
+    /// given must itself loop on every byte in its start state for a search
+    /// to find matches beginning anywhere after it. The options of this
+    /// builder that apply to DFAs, such as
+    /// [`minimize`](struct.Builder.html#method.minimize),
+    /// [`premultiply`](struct.Builder.html#method.premultiply) and
+    /// [`accelerate`](struct.Builder.html#method.accelerate), are applied to
+    /// the DFA, while those that apply to patterns are ignored. The state
+    /// identifiers of the resulting DFA generally differ from the indices in
+    /// the table given.
+    ///
+    /// If the parts given don't describe a valid DFA, or if the DFA doesn't
+    /// fit in the state identifier representation `S`, then an error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// This builds a DFA equivalent to `ab+` by hand.
+    ///
+    /// ```
+    /// use regex_automata::{dense, DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// // 0 is the dead state, 1 the start state, 2 is after reading `a`, and
+    /// // 3 is after reading `ab`, `abb` and so on.
+    /// let mut trans = vec![0; 4 * 256];
+    /// trans[1 * 256 + b'a' as usize] = 2;
+    /// trans[2 * 256 + b'b' as usize] = 3;
+    /// trans[3 * 256 + b'b' as usize] = 3;
+    ///
+    /// let dfa: DenseDFA<Vec<usize>, usize> =
+    ///     dense::Builder::new().build_from_parts(&trans, 1, &[3], None)?;
+    /// assert_eq!(Some(3), dfa.find(b"abbc"));
+    /// assert_eq!(None, dfa.find(b"ac"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_from_parts<S: StateID>(
+        &self,
+        transitions: &[usize],
+        start: usize,
+        match_states: &[usize],
+        byte_classes: Option<ByteClasses>,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let classes = byte_classes.unwrap_or_else(ByteClasses::singletons);
+        let stride = classes.alphabet_len();
+        let state_count = transitions.len() / stride;
+        if state_count == 0 || state_count * stride != transitions.len() {
+            return Err(Error::invalid_parts(format!(
+                "the transition table has {} transitions, which is not a \
+                 positive multiple of the alphabet length {}",
+                transitions.len(),
+                stride,
+            )));
+        }
+        if let Some(&next) = transitions.iter().find(|&&n| n >= state_count) {
+            return Err(Error::invalid_parts(format!(
+                "a transition leads to state {}, but there are only {} \
+                 states",
+                next, state_count,
+            )));
+        }
+        if transitions[..stride].iter().any(|&next| next != 0) {
+            return Err(Error::invalid_parts(
+                "the transitions of the dead state, which is the first \
+                 state, must all lead back to it"
+                    .to_string(),
+            ));
+        }
+        if start >= state_count {
+            return Err(Error::invalid_parts(format!(
+                "the start state {} doesn't exist, since there are only {} \
+                 states",
+                start, state_count,
+            )));
+        }
+        let mut matches: Vec<&[PatternID]> = vec![&[]; state_count];
+        for &id in match_states {
+            if id == 0 || id >= state_count {
+                return Err(Error::invalid_parts(format!(
+                    "the match state {} is the dead state or doesn't exist",
+                    id,
+                )));
+            }
+            matches[id] = &[0];
+        }
+
+        let mut dfa = Repr::empty_with_byte_classes(classes)
+            .anchored(self.anchored)
+            .patterns(1);
+        for _ in 1..state_count {
+            dfa.add_empty_state()?;
+        }
+        for (slot, &next) in dfa.trans.iter_mut().zip(transitions) {
+            *slot = S::from_usize(next);
+        }
+        dfa.set_start_state(S::from_usize(start));
+        dfa.shuffle_match_states(&matches)?;
+        if self.minimize {
+            if !dfa.minimize_with(self.cancel()) {
+                return Err(Error::cancelled());
+            }
+        } else if self.reduce {
+            dfa.reduce();
+        }
+        if self.premultiply {
+            dfa.premultiply()?;
+        }
+        if self.accelerate {
+            dfa.accelerate();
+        }
+        Ok(dfa.into_dense_dfa())
+    }
+
+    /// Estimate the size of the DFA that would be built from the given
+    /// pattern, without retaining the DFA.
+    ///
+    /// This performs determinization (and minimization, if enabled), and
+    /// therefore costs about as much time as building the DFA itself. But
+    /// the DFA's transition table is dropped before returning, which makes
+    /// this useful for scoring or rejecting many candidate patterns without
+    /// keeping any of them around.
+    ///
+    /// The estimated serialized size assumes the DFA uses `usize` state
+    /// identifiers, which is the representation used by
+    /// [`build`](struct.Builder.html#method.build).
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let builder = dense::Builder::new();
+    /// let estimate = builder.estimate_size("foo[0-9]+")?;
+    /// let dfa = builder.build("foo[0-9]+")?;
+    /// assert_eq!(estimate.state_count(), dfa.state_count());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn estimate_size(&self, pattern: &str) -> Result<SizeEstimate> {
+        let nfa = self.build_nfa(pattern)?;
+        let (dfa, _) = self.build_repr::<usize>(&nfa, None, None)?;
+        let trans_len = dfa.state_count() * dfa.alphabet_len();
+        Ok(SizeEstimate {
+            state_count: dfa.state_count(),
+            alphabet_len: dfa.alphabet_len(),
+            serialized_len: HEADER_LEN + trans_len * mem::size_of::<usize>(),
+        })
+    }
+
+    /// Build a DFA from the given pattern, but give up on determinization
+    /// once the given time budget is exhausted.
+    ///
+    /// If determinization completes within the budget, then the DFA returned
+    /// is complete and equivalent to the one returned by
+    /// [`build`](struct.Builder.html#method.build). Otherwise, the DFA
+    /// returned is partial: every transition that was never computed leads
+    /// to a special "unknown" state. Searching a partial DFA with the
+    /// routines on [`PartialDFA`](struct.PartialDFA.html) returns a
+    /// [`SearchError::Incomplete`](../enum.SearchError.html) error whenever
+    /// the unknown state is reached, instead of a wrong answer. Searches that
+    /// only visit the explored part of the DFA behave exactly as they would
+    /// with a complete DFA.
+    ///
+    /// Minimization is never performed on a partial DFA, even if it was
+    /// requested.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let budget = Duration::from_secs(60);
+    /// let dfa = dense::Builder::new().build_partial("foo[0-9]+", budget)?;
+    /// assert!(!dfa.is_partial());
+    /// assert_eq!(Ok(Some(8)), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_partial(
+        &self,
+        pattern: &str,
+        budget: Duration,
+    ) -> Result<PartialDFA<Vec<usize>, usize>> {
+        let nfa = self.build_nfa(pattern)?;
+        let deadline = Instant::now().checked_add(budget);
+        let (mut dfa, partial) =
+            self.build_repr::<usize>(&nfa, deadline, None)?;
+        if self.premultiply {
+            dfa.premultiply()?;
+        }
+        let unknown = if partial {
+            let index = dfa.state_count() - 1;
+            Some(if dfa.premultiplied {
+                index * dfa.alphabet_len()
+            } else {
+                index
+            })
+        } else {
+            None
+        };
+        Ok(PartialDFA { dfa: dfa.into_dense_dfa(), unknown })
+    }
+
+    /// Build a DFA from the given pattern that is only capable of reporting
+    /// whether a match exists.
+    ///
+    /// Determinization stops tracking the rest of the pattern as soon as any
+    /// match is seen, and all match states are collapsed into a single state
+    /// whose transitions all lead to the dead state. This typically produces
+    /// a smaller DFA (especially after minimization) that also stops
+    /// searching sooner, which makes it well suited for membership tests.
+    ///
+    /// Since such a DFA can no longer tell matches of different lengths
+    /// apart, it is returned as a
+    /// [`MatchOnlyDFA`](struct.MatchOnlyDFA.html), which only permits
+    /// searches whose answers it can compute. In particular, it can't be used
+    /// to find the end of a match, nor to build a
+    /// [`Regex`](../struct.Regex.html).
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let pattern = r"\w+@\w+";
+    /// let full = dense::Builder::new().build(pattern)?;
+    /// let only = dense::Builder::new().build_match_only(pattern)?;
+    /// assert!(only.state_count() < full.state_count());
+    /// assert!(only.is_match(b"foo@example"));
+    /// assert_eq!(Some(5), only.shortest_match(b"foo@example"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_match_only(
+        &self,
+        pattern: &str,
+    ) -> Result<MatchOnlyDFA<Vec<usize>, usize>> {
+        self.build_match_only_with_size::<usize>(pattern)
+    }
+
+    /// Build a DFA that only reports whether a match exists, as in
+    /// [`build_match_only`](struct.Builder.html#method.build_match_only),
+    /// using a specific representation for the DFA's state IDs.
+    pub fn build_match_only_with_size<S: StateID>(
+        &self,
+        pattern: &str,
+    ) -> Result<MatchOnlyDFA<Vec<S>, S>> {
+        let mut builder = self.clone();
+        builder.match_only = true;
+        Ok(MatchOnlyDFA { dfa: builder.build_with_size(pattern)? })
+    }
+
+    /// Build the internal representation of a DFA from an NFA, before any
+    /// premultiplication is applied.
+    ///
+    /// If a deadline is given and determinization doesn't complete before
+    /// it, then the DFA returned is partial and its last state is the
+    /// unknown state. The boolean returned indicates whether this happened.
+    ///
+    /// If a cache is given, then determinization reuses its work and the
+    /// cache is replaced by the cache of this build. A deadline can't be
+    /// given along with a cache.
+    fn build_repr<S: StateID>(
+        &self,
+        nfa: &NFA,
+        deadline: Option<Instant>,
+        cache: Option<&mut BuildCache>,
+    ) -> Result<(Repr<Vec<S>, S>, bool)> {
+        let cancel = self.cancel();
+        let mut determinizer = Determinizer::new(nfa)
+            .longest_match(self.match_kind == MatchKind::LeftmostLongest)
+            .all_matches(self.all_matches)
+            .match_only(self.match_only)
+            .anchored_start(self.anchored_start)
+            .starts_for_each_pattern(self.starts_for_each_pattern)
+            .anchored(self.anchored)
+            .quit(self.quit)
+            .unicode_word_boundary(self.unicode_word_boundary)
+            .size_limit(self.dfa_size_limit)
+            .cancel(cancel.clone());
+        if let Some(classes) = self.shared_byte_classes {
+            let required = nfa.byte_classes().with_singletons(&self.quit);
+            if !classes.refines(&required) {
+                return Err(Error::unsupported_shared_byte_classes());
+            }
+            determinizer = determinizer.with_given_byte_classes(classes);
+        } else if self.byte_classes {
+            determinizer = determinizer.with_byte_classes();
+        }
+        #[cfg(feature = "parallel")]
+        {
+            determinizer = determinizer.parallel(self.parallel);
+        }
+        let (mut dfa, partial) = match (deadline, cache) {
+            (None, None) => (determinizer.build()?, false),
+            (None, Some(cache)) => {
+                let (dfa, next) = determinizer.build_with_cache(cache)?;
+                *cache = next;
+                (dfa, false)
+            }
+            (Some(_), Some(_)) => unreachable!("deadline with a cache"),
+            (Some(deadline), None) => {
+                let (dfa, unknown) =
+                    determinizer.deadline(deadline).build_partial()?;
+                (dfa, unknown.is_some())
+            }
+        };
+        if !partial && self.minimize {
+            if !dfa.minimize_with(cancel) {
+                return Err(Error::cancelled());
+            }
+        } else if !partial && self.reduce {
+            dfa.reduce();
+        }
+        Ok((dfa, partial))
+    }
+
+    /// Returns the conditions under which building a DFA with this builder
+    /// is abandoned, starting from now.
+    fn cancel(&self) -> Cancel {
+        Cancel {
+            deadline: self.time_limit.map(|limit| Instant::now() + limit),
+            flag: self.cancel_flag.clone(),
+        }
+    }
+
+    /// Build an NFA from the given pattern, using the syntax options of this
+    /// builder, along with `anchored` and `reverse`.
+    ///
+    /// The NFA can then be used to build one or more DFAs with
+    /// [`build_from_nfa`](struct.Builder.html#method.build_from_nfa).
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    pub fn build_nfa(&self, pattern: &str) -> Result<NFA> {
+        let hir = self.parse(pattern)?;
+        Ok(self.nfa.build(&hir)?)
+    }
+
+    /// Parse the given pattern using the syntax options of this builder,
+    /// compile it and add it as a new pattern to the given NFA, returning
+    /// the new pattern's identifier.
+    ///
+    /// This is like
+    /// [`NFA::add_pattern`](../nfa/struct.NFA.html#method.add_pattern),
+    /// except the pattern is parsed and compiled with the options of this
+    /// builder instead of the default ones. Only the new pattern is
+    /// compiled, so this is cheap even when the NFA already has many
+    /// patterns. Whether the new pattern is anchored is determined by the
+    /// given NFA.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned and the given NFA is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.case_insensitive(true);
+    /// let mut nfa = builder.build_nfa("foo[0-9]+")?;
+    /// assert_eq!(1, builder.add_pattern(&mut nfa, "bar")?);
+    /// let dfa = builder.build_from_nfa::<usize>(&nfa)?;
+    /// assert_eq!(Some((1, 3)), dfa.find_pattern(b"BAR"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn add_pattern(
+        &self,
+        nfa: &mut NFA,
+        pattern: &str,
+    ) -> Result<PatternID> {
+        let hir = self.parse(pattern)?;
+        self.nfa.add_pattern(nfa, &hir)
+    }
+
+    /// Parse the given pattern using the syntax options of this builder.
+    pub(crate) fn parse(&self, pattern: &str) -> Result<Hir> {
+        self.parser.build().parse(pattern).map_err(Error::syntax)
+    }
+
+    /// Set whether matching must be anchored at the beginning of the input.
+    ///
+    /// When enabled, a match must begin at the start of the input. When
+    /// disabled, the DFA will act as if the pattern started with a `.*?`,
+    /// which enables a match to appear anywhere.
+    ///
+    /// By default this is disabled.
+    pub fn anchored(&mut self, yes: bool) -> &mut Builder {
+        self.anchored = yes;
+        self.nfa.anchored(yes);
+        self
+    }
+
+    /// Enable or disable the case insensitive flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `i` flag.
+    ///
+    /// This also makes the literals given to
+    /// [`build_literals`](struct.Builder.html#method.build_literals) match
+    /// regardless of the case of their ASCII letters.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Builder {
+        self.parser.case_insensitive(yes);
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Enable verbose mode in the regular expression.
+    ///
+    /// When enabled, verbose mode permits insigificant whitespace in many
+    /// places in the regular expression, as well as comments. Comments are
+    /// started using `#` and continue until the end of the line.
+    ///
+    /// By default, this is disabled. It may be selectively enabled in the
+    /// regular expression by using the `x` flag regardless of this setting.
+    pub fn ignore_whitespace(&mut self, yes: bool) -> &mut Builder {
+        self.parser.ignore_whitespace(yes);
+        self
+    }
+
+    /// Enable or disable the "dot matches any character" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `s` flag.
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Builder {
+        self.parser.dot_matches_new_line(yes);
+        self
+    }
+
+    /// Enable or disable the "multi line" flag by default.
+    ///
+    /// When enabled, `^` matches at the beginning of the input and after
+    /// every `\n`, and `$` matches at the end of the input and before every
+    /// `\n`. When disabled, they only match at the beginning and end of the
+    /// input.
+    ///
+    /// Like every other look-around assertion, line anchors are evaluated
+    /// by choosing a start state based on the byte preceding the start of
+    /// the search, so searching a whole buffer finds matches on every line.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `m` flag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().multi_line(true).build("^[a-z]+$")?;
+    /// assert_eq!(Some(7), dfa.find_at(b"123\nabc\n456", 4));
+    /// assert_eq!(None, dfa.find_at(b"123\nabc1\n456", 4));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn multi_line(&mut self, yes: bool) -> &mut Builder {
+        self.parser.multi_line(yes);
+        self
+    }
+
+    /// Enable or disable CRLF mode for the line anchors.
+    ///
+    /// When enabled, the multi-line anchors `^` and `$` treat `\r\n` as a
+    /// single line terminator, in addition to `\n` and `\r` on their own.
+    /// That is, `$` matches before `\r\n` and `^` matches after it, but
+    /// neither matches between `\r` and `\n`. This permits searching text
+    /// with Windows line endings without splitting it into lines first.
+    ///
+    /// This only has an effect on `^` and `$` in
+    /// [multi-line mode](struct.Builder.html#method.multi_line).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .multi_line(true)
+    ///     .crlf(true)
+    ///     .build("^[a-z]+$")?;
+    /// assert_eq!(Some(8), dfa.find_at(b"123\r\nabc\r\n456", 5));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// This is disabled by default.
+    pub fn crlf(&mut self, yes: bool) -> &mut Builder {
+        self.nfa.crlf(yes);
+        self
+    }
+
+    /// Enable or disable the "swap greed" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `U` flag.
+    pub fn swap_greed(&mut self, yes: bool) -> &mut Builder {
+        self.parser.swap_greed(yes);
+        self
+    }
+
+    /// Enable or disable the Unicode flag (`u`) by default.
+    ///
+    /// By default this is **enabled**. It may alternatively be selectively
+    /// disabled in the regular expression itself via the `u` flag.
+    ///
+    /// Note that unless `allow_invalid_utf8` is enabled (it's disabled by
+    /// default), a regular expression will fail to parse if Unicode mode is
+    /// disabled and a sub-expression could possibly match invalid UTF-8.
+    pub fn unicode(&mut self, yes: bool) -> &mut Builder {
+        self.parser.unicode(yes);
+        self
+    }
+
+    /// When enabled, the builder will permit the construction of a regular
+    /// expression that may match invalid UTF-8.
+    ///
+    /// When disabled (the default), the builder is guaranteed to produce a
+    /// regex that will only ever match valid UTF-8 (otherwise, the builder
+    /// will return an error).
+    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut Builder {
+        self.parser.allow_invalid_utf8(yes);
+        self.nfa.allow_invalid_utf8(yes);
+        self
+    }
+
+    /// Set whether patterns are compiled in UTF-8 mode or in byte mode.
+    ///
+    /// In UTF-8 mode, which is the default, a pattern that could match
+    /// invalid UTF-8 is rejected, and an unanchored DFA only skips over whole
+    /// codepoints before the start of a match. As a result, every match
+    /// found in valid UTF-8 begins and ends at a codepoint boundary. In byte
+    /// mode, a pattern may match any sequence of bytes, and so its matches
+    /// may begin or end in the middle of a codepoint.
+    ///
+    /// The mode is recorded in the DFA, including in its serialized form,
+    /// and is reported by [`DFA::is_utf8`](../trait.DFA.html#method.is_utf8).
+    ///
+    /// Disabling UTF-8 mode is equivalent to enabling
+    /// [`allow_invalid_utf8`](struct.Builder.html#method.allow_invalid_utf8).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build("a")?;
+    /// assert!(dfa.is_utf8());
+    ///
+    /// let dfa = dense::Builder::new().utf8(false).build(r"(?-u:\xFF)")?;
+    /// assert!(!dfa.is_utf8());
+    /// assert_eq!(Some(2), dfa.find(b"a\xFF"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn utf8(&mut self, yes: bool) -> &mut Builder {
+        self.allow_invalid_utf8(!yes)
+    }
+
+    /// Set the nesting limit used for the regular expression parser.
+    ///
+    /// The nesting limit controls how deep the abstract syntax tree is allowed
+    /// to be. If the AST exceeds the given limit (e.g., with too many nested
+    /// groups), then an error is returned by the parser.
+    ///
+    /// The purpose of this limit is to act as a heuristic to prevent stack
+    /// overflow when building a finite automaton from a regular expression's
+    /// abstract syntax tree. In particular, construction currently uses
+    /// recursion. In the future, the implementation may stop using recursion
+    /// and this option will no longer be necessary.
+    ///
+    /// This limit is not checked until the entire AST is parsed. Therefore,
+    /// if callers want to put a limit on the amount of heap space used, then
+    /// they should impose a limit on the length, in bytes, of the concrete
+    /// pattern string. In particular, this is viable since the parser will
+    /// limit itself to heap space proportional to the lenth of the pattern
+    /// string.
+    ///
+    /// Note that a nest limit of `0` will return a nest limit error for most
+    /// patterns but not all. For example, a nest limit of `0` permits `a` but
+    /// not `ab`, since `ab` requires a concatenation AST item, which results
+    /// in a nest depth of `1`. In general, a nest limit is not something that
+    /// manifests in an obvious way in the concrete syntax, therefore, it
+    /// should not be used in a granular way.
+    pub fn nest_limit(&mut self, limit: u32) -> &mut Builder {
+        self.parser.nest_limit(limit);
+        self
+    }
+
+    /// Minimize the DFA.
+    ///
+    /// When enabled, the DFA built will be minimized such that it is as small
+    /// as possible.
+    ///
+    /// Whether one enables minimization or not depends on the types of costs
+    /// you're willing to pay and how much you care about its benefits. In
+    /// particular, minimization has worst case `O(n*k*logn)` time and `O(k*n)`
+    /// space, where `n` is the number of DFA states and `k` is the alphabet
+    /// size. In practice, minimization can be quite costly in terms of both
+    /// space and time, so it should only be done if you're willing to wait
+    /// longer to produce a DFA. In general, you might want a minimal DFA in
+    /// the following circumstances:
+    ///
+    /// 1. You would like to optimize for the size of the automaton. This can
+    ///    manifest in one of two ways. Firstly, if you're converting the
+    ///    DFA into Rust code (or a table embedded in the code), then a minimal
+    ///    DFA will translate into a corresponding reduction in code  size, and
+    ///    thus, also the final compiled binary size. Secondly, if you are
+    ///    building many DFAs and putting them on the heap, you'll be able to
+    ///    fit more if they are smaller. Note though that building a minimal
+    ///    DFA itself requires additional space; you only realize the space
+    ///    savings once the minimal DFA is constructed (at which point, the
+    ///    space used for minimization is freed).
+    /// 2. You've observed that a smaller DFA results in faster match
+    ///    performance. Naively, this isn't guaranteed since there is no
+    ///    inherent difference between matching with a bigger-than-minimal
+    ///    DFA and a minimal DFA. However, a smaller DFA may make use of your
+    ///    CPU's cache more efficiently.
+    /// 3. You are trying to establish an equivalence between regular
+    ///    languages. The standard method for this is to build a minimal DFA
+    ///    for each language and then compare them. If the DFAs are equivalent
+    ///    (up to state renaming), then the languages are equivalent.
+    ///
+    /// This option is disabled by default.
+    pub fn minimize(&mut self, yes: bool) -> &mut Builder {
+        self.minimize = yes;
+        self
+    }
+
+    /// Merge states with identical transitions after determinization.
+    ///
+    /// This is a cheaper alternative to
+    /// [`minimize`](struct.Builder.html#method.minimize). It merges states
+    /// that are obviously equivalent, because they have the same transition
+    /// on every byte and match the same patterns, in a single linear pass
+    /// over the DFA. Since successors are merged before the states leading
+    /// to them, this merges entire chains of states, such as those for the
+    /// common suffixes of literals. But states in a cycle are only merged if
+    /// their transitions are exactly identical, so the DFA produced isn't
+    /// necessarily minimal. Nevertheless, for DFAs built from many literals,
+    /// this usually captures most of the reduction in size at a fraction of
+    /// the cost.
+    ///
+    /// This option has no effect when minimization is enabled.
+    ///
+    /// This option is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let pattern = "foobar|fooquux|zapbar|zapquux";
+    /// let dfa = dense::Builder::new().build(pattern)?;
+    /// let reduced = dense::Builder::new().reduce(true).build(pattern)?;
+    /// let minimal = dense::Builder::new().minimize(true).build(pattern)?;
+    /// assert!(reduced.state_count() < dfa.state_count());
+    /// assert_eq!(minimal.state_count(), reduced.state_count());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn reduce(&mut self, yes: bool) -> &mut Builder {
+        self.reduce = yes;
+        self
+    }
+
+    /// Premultiply state identifiers in the DFA's transition table.
+    ///
+    /// When enabled, state identifiers are premultiplied to point to their
+    /// corresponding row in the DFA's transition table. That is, given the
+    /// `i`th state, its corresponding premultiplied identifier is `i * k`
+    /// where `k` is the alphabet size of the DFA. (The alphabet size is at
+    /// most 256, but is in practice smaller if byte classes is enabled.)
+    ///
+    /// When state identifiers are not premultiplied, then the identifier of
+    /// the `i`th state is `i`.
+    ///
+    /// This only changes the identifiers used internally by the transition
+    /// table and by the methods of the [`DFA`](../trait.DFA.html) trait.
+    /// Introspection, such as
+    /// [`DenseDFA::states`](../enum.DenseDFA.html#method.states), always
+    /// reports logical state indices, and
+    /// [`DenseDFA::state_index`](../enum.DenseDFA.html#method.state_index)
+    /// converts any identifier into one.
+    ///
+    /// The advantage of premultiplying state identifiers is that is saves
+    /// a multiplication instruction per byte when searching with the DFA.
+    /// This has been observed to lead to a 20% performance benefit in
+    /// micro-benchmarks.
+    ///
+    /// The primary disadvantage of premultiplying state identifiers is
+    /// that they require a larger integer size to represent. For example,
+    /// if your DFA has 200 states, then its premultiplied form requires
+    /// 16 bits to represent every possible state identifier, where as its
+    /// non-premultiplied form only requires 8 bits.
+    ///
+    /// This option is enabled by default.
+    pub fn premultiply(&mut self, yes: bool) -> &mut Builder {
+        self.premultiply = yes;
+        self
+    }
+
+    /// Shrink the size of the DFA's alphabet by mapping bytes to their
+    /// equivalence classes.
+    ///
+    /// When enabled, each DFA will use a map from all possible bytes to their
+    /// corresponding equivalence class. Each equivalence class represents a
+    /// set of bytes that does not discriminate between a match and a non-match
+    /// in the DFA. For example, the pattern `[ab]+` has at least two
+    /// equivalence classes: a set containing `a` and `b` and a set containing
+    /// every byte except for `a` and `b`. `a` and `b` are in the same
+    /// equivalence classes because they never discriminate between a match
+    /// and a non-match.
+    ///
+    /// The advantage of this map is that the size of the transition table can
+    /// be reduced drastically from `#states * 256 * sizeof(id)` to
+    /// `#states * k * sizeof(id)` where `k` is the number of equivalence
+    /// classes. As a result, total space usage can decrease substantially.
+    /// Moreover, since a smaller alphabet is used, compilation becomes faster
+    /// as well.
+    ///
+    /// The disadvantage of this map is that every byte searched must be
+    /// passed through this map before it can be used to determine the next
+    /// transition. This has a small match time performance cost.
+    ///
+    /// This option is enabled by default.
+    pub fn byte_classes(&mut self, yes: bool) -> &mut Builder {
+        self.byte_classes = yes;
+        self
+    }
+
+    /// Use the given byte classes for the DFA instead of computing them
+    /// from the pattern.
+    ///
+    /// This permits several DFAs built from related patterns to share the
+    /// same byte classes, so that a custom search loop running them together
+    /// only maps each byte of the haystack to its class once. Shared classes
+    /// are typically taken from a DFA built from all of the related
+    /// patterns with [`build_many`](struct.Builder.html#method.build_many),
+    /// since its classes distinguish every byte that any of the patterns
+    /// distinguish.
+    ///
+    /// The classes given must distinguish every pair of bytes that the
+    /// pattern distinguishes, and put each quit byte in its own class.
+    /// Otherwise, building the DFA returns an error. When classes are given,
+    /// they are used even if byte classes are disabled with
+    /// [`byte_classes`](struct.Builder.html#method.byte_classes).
+    ///
+    /// This is not set by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let patterns = &["[a-z]+", "[0-9]+"];
+    /// let all = dense::Builder::new().build_many(patterns)?;
+    /// let classes = *all.byte_classes();
+    ///
+    /// let mut builder = dense::Builder::new();
+    /// builder.shared_byte_classes(Some(classes));
+    /// let words = builder.build(patterns[0])?;
+    /// let numbers = builder.build(patterns[1])?;
+    /// assert_eq!(words.byte_classes(), numbers.byte_classes());
+    /// assert_eq!(Some(3), words.find(b"abc123"));
+    /// assert_eq!(Some(3), numbers.find(b"123abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn shared_byte_classes(
+        &mut self,
+        classes: Option<ByteClasses>,
+    ) -> &mut Builder {
+        self.shared_byte_classes = classes;
+        self
+    }
+
+    /// Reverse the DFA.
+    ///
+    /// A DFA reversal is performed by reversing all of the concatenated
+    /// sub-expressions in the original pattern, recursively. The resulting
+    /// DFA can be used to match the pattern starting from the end of a string
+    /// instead of the beginning of a string.
+    ///
+    /// Generally speaking, a reversed DFA is most useful for finding the start
+    /// of a match, since a single forward DFA is only capable of finding the
+    /// end of a match. This start of match handling is done for you
+    /// automatically if you build a [`Regex`](struct.Regex.html).
+    pub fn reverse(&mut self, yes: bool) -> &mut Builder {
+        self.reverse = yes;
+        self.nfa.reverse(yes);
+        self
+    }
+
+    /// Set the match semantics of the DFA.
+    ///
+    /// With [`MatchKind::LeftmostLongest`](enum.MatchKind.html), the DFA
+    /// reports the longest match among the matches that start at the leftmost
+    /// position. This treats all NFA states that could begin a match at the
+    /// same position as having equivalent priority. In other words, it is not
+    /// possible to implement non-greedy match semantics with it. That is,
+    /// `a+` and `a+?` are equivalent.
+    ///
+    /// Note that since a DFA only reports the end of a match, a DFA with
+    /// leftmost-longest semantics reports the end of the longest match that
+    /// starts at the earliest possible position, which may be before the end
+    /// of a longer match that starts later.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense::{self, MatchKind}, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let first = dense::Builder::new().build("sam|samwise")?;
+    /// assert_eq!(Some(6), first.find(b"xx sam"));
+    /// assert_eq!(Some(6), first.find(b"xx samwise"));
+    ///
+    /// let longest = dense::Builder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build("sam|samwise")?;
+    /// assert_eq!(Some(6), longest.find(b"xx sam"));
+    /// assert_eq!(Some(10), longest.find(b"xx samwise"));
+    ///
+    /// // A longer match that starts later is never preferred.
+    /// let longest = dense::Builder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build("ab|bcdef")?;
+    /// assert_eq!(Some(2), longest.find(b"abcdef"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default, this is `MatchKind::LeftmostFirst`.
+    pub fn match_kind(&mut self, kind: MatchKind) -> &mut Builder {
+        self.match_kind = kind;
+        self
+    }
+
+    /// Find the longest possible match.
+    ///
+    /// This is a convenience for setting
+    /// [`match_kind`](struct.Builder.html#method.match_kind) to
+    /// `MatchKind::LeftmostLongest` when enabled, and to
+    /// `MatchKind::LeftmostFirst` otherwise.
+    ///
+    /// This option is principally useful when building a reverse DFA for
+    /// finding the start of a match. If you are building a regex with
+    /// [`RegexBuilder`](struct.RegexBuilder.html), then this is handled for
+    /// you automatically. The reason why this is necessary for start of match
+    /// handling is because we want to find the earliest possible starting
+    /// position of a match to satisfy leftmost-first match semantics. When
+    /// matching in reverse, this means finding the longest possible match,
+    /// hence, this option.
+    ///
+    /// By default this is disabled.
+    pub fn longest_match(&mut self, yes: bool) -> &mut Builder {
+        self.match_kind(if yes {
+            MatchKind::LeftmostLongest
+        } else {
+            MatchKind::LeftmostFirst
+        })
+    }
+
+    /// Build a DFA that keeps searching after a match is found, such that it
+    /// sees every match of every pattern.
+    ///
+    /// When enabled, determinization never drops the rest of the patterns
+    /// (or the unanchored prefix) once a match is seen, and each match state
+    /// records every pattern that matches in it, in the order in which the
+    /// patterns were given. This is required for
+    /// overlapping searches with
+    /// [`DFA::find_overlapping_at`](trait.DFA.html#method.find_overlapping_at).
+    ///
+    /// Since such a DFA no longer prefers earlier matches, `find` and
+    /// `find_pattern` on it report the end of the last match seen before
+    /// the DFA enters a dead state, rather than the end of the leftmost first
+    /// match. It should therefore not be used to build a
+    /// [`Regex`](struct.Regex.html).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .all_matches(true)
+    ///     .build_many(&["foo", "[a-z]+o", "o"])?;
+    /// let matches: Vec<_> = dfa.find_overlapping_iter(b"xfoo").collect();
+    /// assert_eq!(matches, vec![(1, 3), (2, 3), (0, 4), (1, 4), (2, 4)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default this is disabled.
+    pub fn all_matches(&mut self, yes: bool) -> &mut Builder {
+        self.all_matches = yes;
+        self
+    }
+
+    /// Add or remove a byte on which a search with the DFA quits.
+    ///
+    /// When the DFA sees a quit byte, it stops immediately, as if it had
+    /// entered a dead state. This guarantees that a search never scans past
+    /// a delimiter, such as `\n` or `\x00`, without having to build the
+    /// delimiter into every pattern. Any match found before the quit byte is
+    /// still reported by routines like `find`. To distinguish a search that
+    /// quit from one that simply found no match, use
+    /// [`DFA::try_find_at`](trait.DFA.html#method.try_find_at).
+    ///
+    /// Since every quit byte gets its own equivalence class, each quit byte
+    /// may slightly increase the size of the DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, SearchError, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().quit(b'\n', true).build("[a-z]+")?;
+    /// assert_eq!(Some(3), dfa.find(b"foo\nbar"));
+    /// assert_eq!(None, dfa.find(b"\nbar"));
+    /// assert_eq!(
+    ///     Err(SearchError::Quit { byte: b'\n', offset: 0 }),
+    ///     dfa.try_find_at(b"\nbar", 0),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default, there are no quit bytes.
+    pub fn quit(&mut self, byte: u8, yes: bool) -> &mut Builder {
+        if yes {
+            self.quit.add(byte);
+        } else {
+            self.quit.remove(byte);
+        }
+        self
+    }
+
+    /// Enable heuristic support for Unicode word boundaries.
+    ///
+    /// A DFA can't evaluate a Unicode word boundary, i.e., `\b` or `\B`,
+    /// so building a DFA from a pattern containing one returns an error by
+    /// default. When this is enabled, each Unicode word boundary is compiled
+    /// as an ASCII word boundary instead, and every non-ASCII byte becomes a
+    /// [quit byte](struct.Builder.html#method.quit). The two agree on ASCII
+    /// text, and on any other text, a search that sees a non-ASCII byte
+    /// quits instead of returning a wrong answer. Callers can then fall back
+    /// to an engine that supports Unicode word boundaries, such as a
+    /// [`BoundedBacktracker`](../backtrack/struct.BoundedBacktracker.html).
+    ///
+    /// This has no effect on patterns without Unicode word boundaries.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, SearchError, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .unicode_word_boundary(true)
+    ///     .build(r"\bfoo\b")?;
+    /// assert_eq!(Ok(Some(7)), dfa.try_find(b"bar foo"));
+    /// assert_eq!(
+    ///     Err(SearchError::Quit { byte: 0xCE, offset: 4 }),
+    ///     dfa.try_find("bar \u{03B4}foo".as_bytes()),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// This is disabled by default.
+    pub fn unicode_word_boundary(&mut self, yes: bool) -> &mut Builder {
+        self.unicode_word_boundary = yes;
+        self
+    }
+
+    /// Enable or disable accelerated states.
+    ///
+    /// When enabled, states that transition to themselves on all but one,
+    /// two or three bytes are detected after the DFA is built, and a search
+    /// that enters one of them skips ahead to the next of those bytes with
+    /// `memchr` instead of following one transition per byte. This can
+    /// speed up searches for patterns like `.*error.*` or `"[^"]*"`
+    /// dramatically. See
+    /// [`DFA::accelerator`](trait.DFA.html#method.accelerator).
+    ///
+    /// At most 8 states are accelerated in any one DFA. Accelerated states
+    /// are recorded when a DFA is serialized, and survive conversion to a
+    /// sparse DFA or to a smaller state identifier representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let pattern = r#"(?-u)[^"]*"[0-9]"#;
+    /// let mut builder = dense::Builder::new();
+    /// builder.allow_invalid_utf8(true);
+    /// let dfa = builder.build(pattern)?;
+    /// assert_eq!(b"\"", dfa.accelerator(dfa.start_state()));
+    /// assert_eq!(Some(9), dfa.find(b"abcdefg\"1"));
+    ///
+    /// let dfa = builder.accelerate(false).build(pattern)?;
+    /// assert!(dfa.accelerator(dfa.start_state()).is_empty());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default this is enabled.
+    pub fn accelerate(&mut self, yes: bool) -> &mut Builder {
+        self.accelerate = yes;
+        self
+    }
+
+    /// Set a limit, in bytes, on the approximate memory used during
+    /// determinization.
+    ///
+    /// Some patterns, such as `[\w#$%]{1,50}`, produce DFAs that are
+    /// exponentially larger than the pattern itself. When building DFAs from
+    /// untrusted patterns, a size limit permits rejecting such patterns with
+    /// an error of kind
+    /// [`ErrorKind::DFASizeLimitExceeded`](../enum.ErrorKind.html#variant.DFASizeLimitExceeded)
+    /// instead of exhausting memory. The memory counted includes the DFA's
+    /// transition table as it's built, along with the sets of NFA states that
+    /// make up each DFA state. Since the latter is discarded once the DFA is
+    /// built, the limit is usually larger than the memory used by the DFA
+    /// returned.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let result = dense::Builder::new()
+    ///     .dfa_size_limit(Some(1 << 20))
+    ///     .build(r"\w{50}");
+    /// match *result.unwrap_err().kind() {
+    ///     ErrorKind::DFASizeLimitExceeded { limit, actual } => {
+    ///         assert_eq!(1 << 20, limit);
+    ///         assert!(actual > limit);
+    ///     }
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn dfa_size_limit(&mut self, limit: Option<usize>) -> &mut Builder {
+        self.dfa_size_limit = limit;
+        self
+    }
+
+    /// Set a limit on the time spent building each DFA.
+    ///
+    /// The limit is checked periodically during determinization and
+    /// minimization. Once it has elapsed, building fails with an error of
+    /// kind [`ErrorKind::Cancelled`](../enum.ErrorKind.html#variant.Cancelled).
+    /// The limit applies separately to each DFA built, so building a
+    /// [`Regex`](../struct.Regex.html), which requires two DFAs, may take up
+    /// to twice as long. Time spent parsing the pattern and compiling it to
+    /// an NFA is not counted.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let result = dense::Builder::new()
+    ///     .time_limit(Some(Duration::from_secs(0)))
+    ///     .build(r"\w{50}");
+    /// match *result.unwrap_err().kind() {
+    ///     ErrorKind::Cancelled => {}
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn time_limit(&mut self, limit: Option<Duration>) -> &mut Builder {
+        self.time_limit = limit;
+        self
+    }
+
+    /// Set a flag that, once set to `true`, causes any build in progress to
+    /// be abandoned.
+    ///
+    /// This permits cancelling a build from another thread. The flag is
+    /// checked periodically during determinization and minimization, and
+    /// when it's observed to be set, building fails with an error of kind
+    /// [`ErrorKind::Cancelled`](../enum.ErrorKind.html#variant.Cancelled).
+    /// The flag is never reset by this builder.
+    ///
+    /// By default, there is no flag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let mut builder = dense::Builder::new();
+    /// builder.cancel_flag(Some(cancel.clone()));
+    ///
+    /// // This would typically be set from another thread while the build is
+    /// // in progress.
+    /// cancel.store(true, Ordering::SeqCst);
+    /// match *builder.build(r"\w{50}").unwrap_err().kind() {
+    ///     ErrorKind::Cancelled => {}
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn cancel_flag(
+        &mut self,
+        flag: Option<Arc<AtomicBool>>,
+    ) -> &mut Builder {
+        self.cancel_flag = flag;
+        self
+    }
+
+    /// Enable or disable parallel determinization.
+    ///
+    /// When enabled, the transitions of many DFA states are computed at once
+    /// using the global thread pool of the
+    /// [`rayon`](https://docs.rs/rayon) crate. This speeds up building large
+    /// DFAs, such as those built from many patterns with
+    /// [`build_many`](struct.Builder.html#method.build_many), on machines
+    /// with several cores. Small DFAs are usually built faster without it.
+    ///
+    /// The DFA built recognizes the same language either way, but its states
+    /// may be numbered differently, so its serialized form differs. The
+    /// numbering never depends on the number of threads or on how work was
+    /// scheduled between them, so building the same patterns in parallel
+    /// always produces the same DFA.
+    ///
+    /// This is only available when the `parallel` feature is enabled.
+    ///
+    /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let patterns = &[r"\w+@\w+\.com", r"[0-9]{3}-[0-9]{4}", r"foo\w*bar"];
+    /// let dfa = dense::Builder::new().parallel(true).build_many(patterns)?;
+    /// assert_eq!(Some((1, 8)), dfa.find_pattern(b"555-1234"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn parallel(&mut self, yes: bool) -> &mut Builder {
+        self.parallel = yes;
+        self
+    }
+
+    /// Set a limit, in bytes, on the approximate memory used by the NFA
+    /// compiled from a pattern, before determinization begins.
+    ///
+    /// This complements
+    /// [`dfa_size_limit`](struct.Builder.html#method.dfa_size_limit) by
+    /// rejecting pathological patterns before any time is spent on
+    /// determinization. When the limit is exceeded, building fails with an
+    /// error of kind
+    /// [`ErrorKind::NFASizeLimitExceeded`](../enum.ErrorKind.html#variant.NFASizeLimitExceeded).
+    /// See [`nfa::Builder::size_limit`](../nfa/struct.Builder.html#method.size_limit)
+    /// for details.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let result = dense::Builder::new()
+    ///     .nfa_size_limit(Some(10 * (1 << 10)))
+    ///     .build(r"\w{100}");
+    /// match *result.unwrap_err().kind() {
+    ///     ErrorKind::NFASizeLimitExceeded { .. } => {}
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn nfa_size_limit(&mut self, limit: Option<usize>) -> &mut Builder {
+        self.nfa.size_limit(limit);
+        self
+    }
+
+    /// Force the size, in bytes, of the state identifiers used by DFAs built
+    /// with [`build_sized`](struct.Builder.html#method.build_sized).
+    ///
+    /// The size given must be 1, 2 or 4, corresponding to `u8`, `u16` and
+    /// `u32` state identifiers, respectively. If the DFA built doesn't fit in
+    /// the size given, then `build_sized` returns an error. When `None` is
+    /// given, the smallest size that fits is chosen automatically.
+    ///
+    /// On 64-bit targets, the size may also be 8, corresponding to `u64`
+    /// state identifiers. This is never chosen automatically, since it
+    /// doubles the size of the transition table compared to `u32`, but it
+    /// permits building DFAs whose premultiplied state identifiers don't fit
+    /// in a `u32`. Such DFAs are serialized in the same format as any other,
+    /// whose header records the size of their state identifiers, and they
+    /// are deserialized with
+    /// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes) using
+    /// `u64` state identifiers.
+    ///
+    /// This has no effect on the other build routines, whose state identifier
+    /// representation is chosen by their type parameter.
+    ///
+    /// By default, this is `None`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the size given is not 1, 2 or 4, or 8 on 64-bit
+    /// targets.
+    pub fn state_id_size(&mut self, size: Option<usize>) -> &mut Builder {
+        if let Some(size) = size {
+            assert!(
+                size == 1
+                    || size == 2
+                    || size == 4
+                    || (size == 8 && cfg!(target_pointer_width = "64")),
+                "state id size must be 1, 2, 4 or 8, but got {}",
+                size,
+            );
+        }
+        self.state_id_size = size;
+        self
+    }
+
+    /// Embed a second start state for anchored searches in an unanchored DFA.
+    ///
+    /// When enabled, the DFA built contains both its usual unanchored start
+    /// state and a start state that only permits matches beginning at the
+    /// position the search starts at. Either one can then be selected for
+    /// each search with
+    /// [`DenseDFA::search_at`](enum.DenseDFA.html#method.search_at), which
+    /// avoids building a separate anchored DFA for the same pattern.
+    ///
+    /// This has no effect when the DFA is anchored, since its only start
+    /// state is already anchored. Sparse DFAs built from a DFA with this
+    /// option enabled only retain the unanchored start state.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored_start_state(true)
+    ///     .build("[0-9]+")?;
+    /// assert_eq!(Some(6), dfa.search_at(b"foo123", 0, false));
+    /// assert_eq!(None, dfa.search_at(b"foo123", 0, true));
+    /// assert_eq!(Some(6), dfa.search_at(b"foo123", 3, true));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default this is disabled.
+    pub fn anchored_start_state(&mut self, yes: bool) -> &mut Builder {
+        self.anchored_start = yes;
+        self
+    }
+
+    /// Embed a start state for anchored searches of each pattern.
+    ///
+    /// When enabled, the DFA built contains, in addition to its usual start
+    /// state, one start state for each pattern it was built from. A search
+    /// beginning in the start state of a pattern only reports matches of
+    /// that pattern, and only matches beginning at the position the search
+    /// starts at. Such a search is executed with
+    /// [`DenseDFA::search_pattern_at`](enum.DenseDFA.html#method.search_pattern_at).
+    ///
+    /// This permits checking input against one specific pattern of a DFA
+    /// built from many patterns, without building a separate DFA for each
+    /// pattern.
+    ///
+    /// Sparse DFAs built from a DFA with this option enabled don't retain
+    /// these start states.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .starts_for_each_pattern(true)
+    ///     .build_many(&["[a-z]+", "[0-9]+"])?;
+    /// assert_eq!(Some(3), dfa.search_pattern_at(0, b"abc123", 0));
+    /// assert_eq!(None, dfa.search_pattern_at(1, b"abc123", 0));
+    /// assert_eq!(Some(6), dfa.search_pattern_at(1, b"abc123", 3));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default this is disabled.
+    pub fn starts_for_each_pattern(&mut self, yes: bool) -> &mut Builder {
+        self.starts_for_each_pattern = yes;
+        self
+    }
+
+    /// Apply best effort heuristics to shrink the NFA at the expense of more
+    /// time/memory.
+    ///
+    /// This may be exposed in the future, but for now is exported for use in
+    /// the `regex-automata-debug` tool.
+    #[doc(hidden)]
+    pub fn shrink(&mut self, yes: bool) -> &mut Builder {
+        self.nfa.shrink(yes);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// A dense DFA that may have been only partially built.
+///
+/// A partial DFA is returned by
+/// [`Builder::build_partial`](struct.Builder.html#method.build_partial) when
+/// determinization doesn't complete within its time budget. Every transition
+/// that was never computed leads to a special "unknown" state. The search
+/// routines on this type report a
+/// [`SearchError::Incomplete`](../enum.SearchError.html) error when they
+/// reach the unknown state, since the correct answer can't be determined.
+///
+/// If the DFA is not partial, then searches never return an error.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct PartialDFA<T: AsRef<[S]>, S: StateID> {
+    dfa: DenseDFA<T, S>,
+    unknown: Option<S>,
+}
 
-        let class = self.byte_classes().get(byte);
-        let offset = from.to_usize() * self.alphabet_len() + class as usize;
-        self.trans[offset] = to;
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> PartialDFA<T, S> {
+    /// Returns true if and only if determinization did not complete, and
+    /// thus, some searches may report an incomplete result.
+    pub fn is_partial(&self) -> bool {
+        self.unknown.is_some()
     }
 
-    /// An an empty state (a state where all transitions lead to a dead state)
-    /// and return its identifier. The identifier returned is guaranteed to
-    /// not point to any other existing state.
+    /// Return the underlying dense DFA.
     ///
-    /// If adding a state would exhaust the state identifier space (given by
-    /// `S`), then this returns an error. In practice, this means that the
-    /// state identifier representation chosen is too small.
+    /// Note that if this DFA is partial, then the unknown state in the DFA
+    /// returned behaves like a non-match state whose transitions all lead
+    /// back to itself. Searching the DFA returned directly may therefore
+    /// report incorrect results.
+    pub fn dfa(&self) -> &DenseDFA<T, S> {
+        &self.dfa
+    }
+
+    /// Returns true if and only if the given bytes match this DFA, as in
+    /// [`DFA::is_match`](../trait.DFA.html#method.is_match).
     ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn add_empty_state(&mut self) -> Result<S> {
-        assert!(!self.premultiplied, "can't add state to premultiplied DFA");
+    /// If the search reaches the unexplored part of this DFA before a match
+    /// is found, then an error is returned.
+    pub fn is_match(&self, bytes: &[u8]) -> result::Result<bool, SearchError> {
+        let mut state = self.dfa.start_state_after(None);
+        if self.dfa.is_match_or_dead_state(state) {
+            return Ok(self.dfa.is_match_state(state));
+        }
+        for (i, &b) in bytes.iter().enumerate() {
+            state = self.dfa.next_state(state, b);
+            if Some(state) == self.unknown {
+                return Err(SearchError::Incomplete { offset: i });
+            }
+            if self.dfa.is_match_or_dead_state(state) {
+                return Ok(self.dfa.is_match_state(state));
+            }
+        }
+        let eoi = self.next_eoi_state(state, bytes.len())?;
+        Ok(self.dfa.is_match_state(eoi))
+    }
 
-        let id = if self.state_count == 0 {
-            S::from_usize(0)
+    /// Returns the end offset of the leftmost first match of this DFA in the
+    /// given bytes, as in [`DFA::find`](../trait.DFA.html#method.find).
+    ///
+    /// If the search reaches the unexplored part of this DFA before the
+    /// outcome of the search is known, then an error is returned.
+    pub fn find(
+        &self,
+        bytes: &[u8],
+    ) -> result::Result<Option<usize>, SearchError> {
+        let mut state = self.dfa.start_state_after(None);
+        let mut last_match = if self.dfa.is_dead_state(state) {
+            return Ok(None);
+        } else if self.dfa.is_match_state(state) {
+            Some(0)
         } else {
-            next_state_id(S::from_usize(self.state_count - 1))?
+            None
         };
-        let alphabet_len = self.alphabet_len();
-        self.trans.extend(iter::repeat(dead_id::<S>()).take(alphabet_len));
-        // This should never panic, since state_count is a usize. The
-        // transition table size would have run out of room long ago.
-        self.state_count = self.state_count.checked_add(1).unwrap();
-        Ok(id)
+        let delay = self.dfa.has_lookaround() as usize;
+        for (i, &b) in bytes.iter().enumerate() {
+            state = self.dfa.next_state(state, b);
+            if Some(state) == self.unknown {
+                return Err(SearchError::Incomplete { offset: i });
+            }
+            if self.dfa.is_match_or_dead_state(state) {
+                if self.dfa.is_dead_state(state) {
+                    return Ok(last_match);
+                }
+                last_match = Some(i + 1 - delay);
+            }
+        }
+        if self.dfa.is_match_state(self.next_eoi_state(state, bytes.len())?) {
+            last_match = Some(bytes.len());
+        }
+        Ok(last_match)
     }
 
-    /// Return a mutable representation of the state corresponding to the given
-    /// id. This is useful for implementing routines that manipulate DFA states
-    /// (e.g., swapping states).
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn get_state_mut(&mut self, id: S) -> StateMut<S> {
-        assert!(!self.premultiplied, "can't get state in premultiplied DFA");
-
-        let alphabet_len = self.alphabet_len();
-        let offset = id.to_usize() * alphabet_len;
-        StateMut {
-            transitions: &mut self.trans[offset..offset + alphabet_len],
+    /// Returns the state reached by following the end of input transition
+    /// from the given state, or an error if it leads to the unexplored part
+    /// of this DFA.
+    fn next_eoi_state(
+        &self,
+        state: S,
+        offset: usize,
+    ) -> result::Result<S, SearchError> {
+        let eoi = self.dfa.next_eoi_state(state);
+        if Some(eoi) == self.unknown {
+            return Err(SearchError::Incomplete { offset });
         }
+        Ok(eoi)
     }
+}
 
-    /// Swap the two states given in the transition table.
-    ///
-    /// This routine does not do anything to check the correctness of this
-    /// swap. Callers must ensure that other states pointing to id1 and id2 are
-    /// updated appropriately.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn swap_states(&mut self, id1: S, id2: S) {
-        assert!(!self.premultiplied, "can't swap states in premultiplied DFA");
+/// A dense DFA that is only capable of reporting whether a match exists.
+///
+/// A match-only DFA is returned by
+/// [`Builder::build_match_only`](struct.Builder.html#method.build_match_only)
+/// and
+/// [`Builder::build_union`](struct.Builder.html#method.build_union).
+/// Its match states have been collapsed into a single state, so that it
+/// stops searching as soon as any match is seen. Since the end of the
+/// leftmost first (or longest) match can't be recovered from such a DFA,
+/// this type only exposes searches that report whether a match exists, or
+/// the end of the earliest match. Notably, it doesn't implement the
+/// [`DFA`](../trait.DFA.html) trait, and thus, can't be used to build a
+/// [`Regex`](../struct.Regex.html).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct MatchOnlyDFA<T: AsRef<[S]>, S: StateID> {
+    dfa: DenseDFA<T, S>,
+}
 
-        let o1 = id1.to_usize() * self.alphabet_len();
-        let o2 = id2.to_usize() * self.alphabet_len();
-        for b in 0..self.alphabet_len() {
-            self.trans.swap(o1 + b, o2 + b);
-        }
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> MatchOnlyDFA<T, S> {
+    /// Returns true if and only if the given bytes match this DFA, as in
+    /// [`DFA::is_match`](../trait.DFA.html#method.is_match).
+    pub fn is_match(&self, bytes: &[u8]) -> bool {
+        self.dfa.is_match(bytes)
     }
 
-    /// Truncate the states in this DFA to the given count.
-    ///
-    /// This routine does not do anything to check the correctness of this
-    /// truncation. Callers must ensure that other states pointing to truncated
-    /// states are updated appropriately.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn truncate_states(&mut self, count: usize) {
-        assert!(!self.premultiplied, "can't truncate in premultiplied DFA");
+    /// Returns the same as `is_match`, but starts the search at the given
+    /// offset, as in
+    /// [`DFA::is_match_at`](../trait.DFA.html#method.is_match_at).
+    pub fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
+        self.dfa.is_match_at(bytes, start)
+    }
 
-        let alphabet_len = self.alphabet_len();
-        self.trans.truncate(count * alphabet_len);
-        self.state_count = count;
+    /// Returns the end offset of the earliest match, as in
+    /// [`DFA::shortest_match`](../trait.DFA.html#method.shortest_match).
+    pub fn shortest_match(&self, bytes: &[u8]) -> Option<usize> {
+        self.dfa.shortest_match(bytes)
     }
 
-    /// This routine shuffles all match states in this DFA---according to the
-    /// given map---to the beginning of the DFA such that every non-match state
-    /// appears after every match state. (With one exception: the special dead
-    /// state remains as the first state.) The given map should have length
-    /// exactly equivalent to the number of states in this DFA.
-    ///
-    /// The purpose of doing this shuffling is to avoid the need to store
-    /// additional state to determine whether a state is a match state or not.
-    /// It also enables a single conditional in the core matching loop instead
-    /// of two.
-    ///
-    /// This updates `self.max_match` to point to the last matching state as
-    /// well as `self.start` if the starting state was moved.
-    pub fn shuffle_match_states(&mut self, is_match: &[bool]) {
-        assert!(
-            !self.premultiplied,
-            "cannot shuffle match states of premultiplied DFA"
-        );
-        assert_eq!(self.state_count, is_match.len());
+    /// Returns the same as `shortest_match`, but starts the search at the
+    /// given offset, as in
+    /// [`DFA::shortest_match_at`](../trait.DFA.html#method.shortest_match_at).
+    pub fn shortest_match_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<usize> {
+        self.dfa.shortest_match_at(bytes, start)
+    }
 
-        if self.state_count <= 1 {
-            return;
-        }
+    /// Returns the total number of states in this DFA, including the dead
+    /// state.
+    pub fn state_count(&self) -> usize {
+        self.dfa.state_count()
+    }
 
-        let mut first_non_match = 1;
-        while first_non_match < self.state_count && is_match[first_non_match] {
-            first_non_match += 1;
-        }
+    /// Returns the memory usage, in bytes, of this DFA.
+    pub fn memory_usage(&self) -> usize {
+        self.dfa.memory_usage()
+    }
+}
 
-        let mut swaps: Vec<S> = vec![dead_id(); self.state_count];
-        let mut cur = self.state_count - 1;
-        while cur > first_non_match {
-            if is_match[cur] {
-                self.swap_states(
-                    S::from_usize(cur),
-                    S::from_usize(first_non_match),
-                );
-                swaps[cur] = S::from_usize(first_non_match);
-                swaps[first_non_match] = S::from_usize(cur);
+/// A record of which states of a dense DFA have been visited by searches.
+///
+/// A tracker is created for a particular DFA with
+/// [`CoverageTracker::new`](struct.CoverageTracker.html#method.new), and is
+/// updated by searching with
+/// [`DenseDFA::find_covered`](enum.DenseDFA.html#method.find_covered).
+/// Visited states accumulate across searches.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct CoverageTracker {
+    visited: Vec<bool>,
+    visited_count: usize,
+}
 
-                first_non_match += 1;
-                while first_non_match < cur && is_match[first_non_match] {
-                    first_non_match += 1;
-                }
-            }
-            cur -= 1;
+#[cfg(feature = "std")]
+impl CoverageTracker {
+    /// Create a new tracker for the given DFA, where no states have been
+    /// visited.
+    pub fn new<T: AsRef<[S]>, S: StateID>(
+        dfa: &DenseDFA<T, S>,
+    ) -> CoverageTracker {
+        CoverageTracker {
+            visited: vec![false; dfa.state_count()],
+            visited_count: 0,
         }
-        for id in (0..self.state_count).map(S::from_usize) {
-            for (_, next) in self.get_state_mut(id).iter_mut() {
-                if swaps[next.to_usize()] != dead_id() {
-                    *next = swaps[next.to_usize()];
-                }
-            }
+    }
+
+    /// Returns the fraction of states, in the range `[0, 1]`, that have been
+    /// visited. Note that the dead state is counted like any other state.
+    pub fn coverage(&self) -> f64 {
+        self.visited_count as f64 / self.visited.len() as f64
+    }
+
+    /// Returns the total number of distinct states that have been visited.
+    pub fn visited_count(&self) -> usize {
+        self.visited_count
+    }
+
+    /// Forget all visited states.
+    pub fn clear(&mut self) {
+        for visited in &mut self.visited {
+            *visited = false;
         }
-        if swaps[self.start.to_usize()] != dead_id() {
-            self.start = swaps[self.start.to_usize()];
+        self.visited_count = 0;
+    }
+
+    fn visit(&mut self, index: usize) {
+        if !self.visited[index] {
+            self.visited[index] = true;
+            self.visited_count += 1;
         }
-        self.max_match = S::from_usize(first_non_match - 1);
     }
 }
 
+/// An estimate of the size of a DFA, as returned by
+/// [`Builder::estimate_size`](struct.Builder.html#method.estimate_size).
 #[cfg(feature = "std")]
-impl<T: AsRef<[S]>, S: StateID> fmt::Debug for Repr<T, S> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fn state_status<T: AsRef<[S]>, S: StateID>(
-            dfa: &Repr<T, S>,
-            id: S,
-        ) -> &'static str {
-            if id == dead_id() {
-                if dfa.is_match_state(id) {
-                    "D*"
-                } else {
-                    "D "
-                }
-            } else if id == dfa.start_state() {
-                if dfa.is_match_state(id) {
-                    ">*"
-                } else {
-                    "> "
-                }
-            } else {
-                if dfa.is_match_state(id) {
-                    " *"
-                } else {
-                    "  "
-                }
-            }
-        }
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SizeEstimate {
+    state_count: usize,
+    alphabet_len: usize,
+    serialized_len: usize,
+}
 
-        writeln!(f, "DenseDFA(")?;
-        for (id, state) in self.states() {
-            let status = state_status(self, id);
-            writeln!(f, "{}{:06}: {:?}", status, id.to_usize(), state)?;
+#[cfg(feature = "std")]
+impl SizeEstimate {
+    /// Returns the total number of states in the DFA, including the dead
+    /// state.
+    pub fn state_count(&self) -> usize {
+        self.state_count
+    }
+
+    /// Returns the number of equivalence classes of bytes used by the DFA.
+    /// If byte classes are disabled, then this is always `256`.
+    pub fn alphabet_len(&self) -> usize {
+        self.alphabet_len
+    }
+
+    /// Returns the estimated number of bytes required to serialize the DFA.
+    pub fn serialized_len(&self) -> usize {
+        self.serialized_len
+    }
+}
+
+/// Returns the end of the leftmost first match in `bytes[start..]`, where the
+/// search begins in the given state instead of the DFA's start state.
+#[inline(always)]
+fn find_from<D: DFA>(
+    dfa: &D,
+    mut state: D::ID,
+    bytes: &[u8],
+    start: usize,
+) -> Option<usize> {
+    let mut last_match = if dfa.is_dead_state(state) {
+        return None;
+    } else if dfa.is_match_state(state) {
+        Some(start)
+    } else {
+        None
+    };
+    let delay = dfa.has_lookaround() as usize;
+    for (i, &b) in bytes[start..].iter().enumerate() {
+        state = unsafe { dfa.next_state_unchecked(state, b) };
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                return last_match;
+            }
+            last_match = Some(start + i + 1 - delay);
         }
-        writeln!(f, ")")?;
-        Ok(())
     }
+    if dfa.is_match_state(dfa.next_eoi_state(state)) {
+        last_match = Some(bytes.len());
+    }
+    last_match
 }
 
-/// An iterator over all states in a DFA.
-///
-/// This iterator yields a tuple for each state. The first element of the
-/// tuple corresponds to a state's identifier, and the second element
-/// corresponds to the state itself (comprised of its transitions).
-///
-/// If this DFA is premultiplied, then the state identifiers are in turn
-/// premultiplied as well, making them usable without additional modification.
+/// Return the number of state identifiers used to store each pattern tag.
 ///
-/// `'a` corresponding to the lifetime of original DFA, `T` corresponds to
-/// the type of the transition table itself and `S` corresponds to the state
-/// identifier representation.
+/// Each identifier stores at most 32 bits of a tag, so that the encoding
+/// for 8 byte identifiers doesn't depend on the width of `usize`.
+fn tag_units<S: StateID>() -> usize {
+    8 / cmp::min(mem::size_of::<S>(), 4)
+}
+
+/// Encode the given pattern tags as state identifiers, using
+/// `tag_units::<S>()` identifiers for each tag, least significant bits
+/// first.
 #[cfg(feature = "std")]
-pub(crate) struct StateIter<'a, T: 'a, S: 'a> {
-    dfa: &'a Repr<T, S>,
-    it: iter::Enumerate<slice::Chunks<'a, S>>,
+fn encode_tags<S: StateID>(tags: &[u64]) -> Vec<S> {
+    let units = tag_units::<S>();
+    let bits = 64 / units;
+    let mask = !0u64 >> (64 - bits);
+    let mut encoded = Vec::with_capacity(tags.len() * units);
+    for &tag in tags {
+        for i in 0..units {
+            encoded.push(S::from_usize(((tag >> (i * bits)) & mask) as usize));
+        }
+    }
+    encoded
+}
+
+/// Decode a single pattern tag encoded by `encode_tags`.
+fn decode_tag<S: StateID>(units: &[S]) -> u64 {
+    let bits = 64 / units.len();
+    units
+        .iter()
+        .enumerate()
+        .fold(0, |tag, (i, unit)| tag | (unit.to_usize() as u64) << (i * bits))
 }
 
+/// Return the given byte as its escaped string form.
 #[cfg(feature = "std")]
-impl<'a, T: AsRef<[S]>, S: StateID> Iterator for StateIter<'a, T, S> {
-    type Item = (S, State<'a, S>);
+fn escape(b: u8) -> String {
+    use std::ascii;
 
-    fn next(&mut self) -> Option<(S, State<'a, S>)> {
-        self.it.next().map(|(id, chunk)| {
-            let state = State { transitions: chunk };
-            let id = if self.dfa.premultiplied {
-                id * self.dfa.alphabet_len()
-            } else {
-                id
-            };
-            (S::from_usize(id), state)
-        })
+    String::from_utf8(ascii::escape_default(b).collect::<Vec<_>>()).unwrap()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use error::ErrorKind;
+    use regex_syntax::hir;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn errors_when_converting_to_smaller_dfa() {
+        let pattern = r"\w{10}";
+        let dfa = Builder::new()
+            .byte_classes(false)
+            .anchored(true)
+            .premultiply(false)
+            .build_with_size::<u16>(pattern)
+            .unwrap();
+        assert!(dfa.to_u8().is_err());
+    }
+
+    #[test]
+    fn to_u8_undoes_premultiplication() {
+        let pattern = r"[a-z]+ing|foo[0-9]{2}|bar";
+        let dfa = Builder::new().minimize(true).build(pattern).unwrap();
+        assert!(dfa.repr().premultiplied);
+        assert!((dfa.state_count() - 1) * dfa.repr().alphabet_len() > 255);
+
+        let small = dfa.to_u8().unwrap();
+        assert!(!small.repr().premultiplied);
+        assert_eq!(dfa.state_count(), small.state_count());
+        let bytes = small.to_bytes_native_endian().unwrap();
+        let loaded: DenseDFA<&[u8], u8> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        let wide = small.to_u32().unwrap();
+        let haystacks: &[&[u8]] =
+            &[b"", b"singing", b"foo12", b"xbarx", b"foo1ing", b"zzz"];
+        for &haystack in haystacks {
+            let expected = dfa.find(haystack);
+            assert_eq!(expected, small.find(haystack));
+            assert_eq!(expected, loaded.find(haystack));
+            assert_eq!(expected, wide.find(haystack));
+            assert_eq!(dfa.rfind(haystack), small.rfind(haystack));
+        }
+    }
+
+    #[test]
+    fn state_transitions_agree_with_next_state() {
+        let pattern = r"[a-z]+ing|foo[0-9]{2}";
+        for &(premultiply, byte_classes) in
+            &[(false, false), (false, true), (true, false), (true, true)]
+        {
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .byte_classes(byte_classes)
+                .build(pattern)
+                .unwrap();
+            let mut count = 0;
+            for (index, state) in dfa.states().enumerate() {
+                assert_eq!(index, state.index());
+                assert_eq!(index, state.id());
+                assert_eq!(index == 0, state.is_dead());
+                let id = dfa.state_id(index);
+                assert_eq!(index, dfa.state_index(id));
+                assert_eq!(dfa.is_match_state(id), state.is_match());
+                let mut expected = 0u16;
+                for (range, next) in state.transitions() {
+                    assert_eq!(expected, *range.start() as u16);
+                    assert!(next < dfa.state_count());
+                    for b in range.clone() {
+                        assert_eq!(dfa.next_state(id, b), dfa.state_id(next));
+                    }
+                    expected = *range.end() as u16 + 1;
+                }
+                assert_eq!(256, expected);
+                count += 1;
+            }
+            assert_eq!(dfa.state_count(), count);
+            assert_eq!(1, dfa.states().filter(|s| s.is_start()).count());
+        }
     }
-}
 
-/// An immutable representation of a single DFA state.
-///
-/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
-/// corresponds to the state identifier representation.
-#[cfg(feature = "std")]
-pub(crate) struct State<'a, S: 'a> {
-    transitions: &'a [S],
-}
+    #[test]
+    fn state_index_round_trip() {
+        for &premultiply in &[false, true] {
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .build(r"foo[0-9]+|[a-z]+bar")
+                .unwrap();
+            let stride = dfa.repr().alphabet_len();
+            for index in 0..dfa.state_count() {
+                let id = dfa.state_id(index);
+                if premultiply {
+                    assert_eq!(index * stride, id);
+                } else {
+                    assert_eq!(index, id);
+                }
+                assert_eq!(index, dfa.state_index(id));
+            }
+            assert_eq!(0, dfa.state_index(dead_id()));
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> State<'a, S> {
-    /// Return an iterator over all transitions in this state. This yields
-    /// a number of transitions equivalent to the alphabet length of the
-    /// corresponding DFA.
-    ///
-    /// Each transition is represented by a tuple. The first element is
-    /// the input byte for that transition and the second element is the
-    /// transitions itself.
-    pub fn transitions(&self) -> StateTransitionIter<S> {
-        StateTransitionIter { it: self.transitions.iter().enumerate() }
+            let start = dfa.start_state();
+            let index = dfa.state_index(start);
+            assert!(dfa.states().nth(index).unwrap().is_start());
+            assert_eq!(start, dfa.state_id(index));
+        }
     }
 
-    /// Return an iterator over a sparse representation of the transitions in
-    /// this state. Only non-dead transitions are returned.
-    ///
-    /// The "sparse" representation in this case corresponds to a sequence of
-    /// triples. The first two elements of the triple comprise an inclusive
-    /// byte range while the last element corresponds to the transition taken
-    /// for all bytes in the range.
-    ///
-    /// This is somewhat more condensed than the classical sparse
-    /// representation (where you have an element for every non-dead
-    /// transition), but in practice, checking if a byte is in a range is very
-    /// cheap and using ranges tends to conserve quite a bit more space.
-    pub fn sparse_transitions(&self) -> StateSparseTransitionIter<S> {
-        StateSparseTransitionIter { dense: self.transitions(), cur: None }
+    #[test]
+    #[should_panic]
+    fn state_id_rejects_invalid_index() {
+        let dfa = Builder::new().premultiply(true).build("foo").unwrap();
+        dfa.state_id(dfa.state_count());
     }
-}
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> fmt::Debug for State<'a, S> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut transitions = vec![];
-        for (start, end, next_id) in self.sparse_transitions() {
-            let line = if start == end {
-                format!("{} => {}", escape(start), next_id.to_usize())
-            } else {
-                format!(
-                    "{}-{} => {}",
-                    escape(start),
-                    escape(end),
-                    next_id.to_usize(),
-                )
-            };
-            transitions.push(line);
+    #[test]
+    fn dfa_size_limit() {
+        let pattern = r"[\w#$%]{1,50}";
+        let err = Builder::new()
+            .dfa_size_limit(Some(1 << 16))
+            .build(pattern)
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::DFASizeLimitExceeded { limit, actual } => {
+                assert_eq!(1 << 16, limit);
+                assert!(actual > limit);
+            }
+            ref kind => panic!("unexpected error: {:?}", kind),
         }
-        write!(f, "{}", transitions.join(", "))?;
-        Ok(())
+
+        let pattern = "foo[0-9]+";
+        let limited =
+            Builder::new().dfa_size_limit(Some(1 << 16)).build(pattern);
+        let unlimited = Builder::new().build(pattern).unwrap();
+        assert_eq!(unlimited.to_text(), limited.unwrap().to_text());
+        assert!(Builder::new()
+            .dfa_size_limit(Some(0))
+            .build(pattern)
+            .is_err());
     }
-}
 
-/// An iterator over all transitions in a single DFA state. This yields
-/// a number of transitions equivalent to the alphabet length of the
-/// corresponding DFA.
-///
-/// Each transition is represented by a tuple. The first element is the input
-/// byte for that transition and the second element is the transitions itself.
-#[cfg(feature = "std")]
-#[derive(Debug)]
-pub(crate) struct StateTransitionIter<'a, S: 'a> {
-    it: iter::Enumerate<slice::Iter<'a, S>>,
-}
+    #[test]
+    fn cancelled() {
+        let pattern = r"[\w#$%]{1,50}";
+        let flag = Arc::new(AtomicBool::new(true));
+        let err =
+            Builder::new().cancel_flag(Some(flag)).build(pattern).unwrap_err();
+        match *err.kind() {
+            ErrorKind::Cancelled => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> Iterator for StateTransitionIter<'a, S> {
-    type Item = (u8, S);
+        let err = Builder::new()
+            .time_limit(Some(Duration::from_secs(0)))
+            .build(pattern)
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::Cancelled => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
 
-    fn next(&mut self) -> Option<(u8, S)> {
-        self.it.next().map(|(i, &id)| (i as u8, id))
+        let pattern = "foo[0-9]+";
+        let flag = Arc::new(AtomicBool::new(false));
+        let dfa = Builder::new()
+            .minimize(true)
+            .cancel_flag(Some(flag))
+            .time_limit(Some(Duration::from_secs(3600)))
+            .build(pattern)
+            .unwrap();
+        let expected = Builder::new().minimize(true).build(pattern).unwrap();
+        assert_eq!(expected.to_text(), dfa.to_text());
     }
-}
 
-/// An iterator over all transitions in a single DFA state using a sparse
-/// representation.
-///
-/// Each transition is represented by a triple. The first two elements of the
-/// triple comprise an inclusive byte range while the last element corresponds
-/// to the transition taken for all bytes in the range.
-#[cfg(feature = "std")]
-#[derive(Debug)]
-pub(crate) struct StateSparseTransitionIter<'a, S: 'a> {
-    dense: StateTransitionIter<'a, S>,
-    cur: Option<(u8, u8, S)>,
-}
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_agrees() {
+        let patterns = &[
+            r"[a-z]+ing",
+            r"(?-u:\b)foo[0-9]{2}(?-u:\b)",
+            r"(?m)^bar$",
+            r"\w{3}@\w{3}",
+        ];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"singing foo42 bar",
+            b"xfoo42 \nbar\nabc@def",
+            b"foo123 zzz@yyy",
+        ];
+        let mut builder = Builder::new();
+        builder.starts_for_each_pattern(true);
+        let seq = builder.build_many(patterns).unwrap();
+        let par = builder.parallel(true).build_many(patterns).unwrap();
+        let again = builder.build_many(patterns).unwrap();
+        assert_eq!(
+            par.to_bytes_native_endian().unwrap(),
+            again.to_bytes_native_endian().unwrap(),
+        );
+        for &haystack in haystacks {
+            for start in 0..haystack.len() + 1 {
+                assert_eq!(
+                    seq.find_pattern_at(haystack, start),
+                    par.find_pattern_at(haystack, start),
+                );
+            }
+        }
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> Iterator for StateSparseTransitionIter<'a, S> {
-    type Item = (u8, u8, S);
+        let rev = builder.reverse(true).longest_match(true);
+        let par = rev.build_many(patterns).unwrap();
+        let seq = rev.parallel(false).build_many(patterns).unwrap();
+        for &haystack in haystacks {
+            assert_eq!(seq.rfind(haystack), par.rfind(haystack));
+        }
 
-    fn next(&mut self) -> Option<(u8, u8, S)> {
-        while let Some((b, next)) = self.dense.next() {
-            let (prev_start, prev_end, prev_next) = match self.cur {
-                Some(t) => t,
-                None => {
-                    self.cur = Some((b, b, next));
-                    continue;
+        let pattern = r"[01]*1[01]{10}";
+        let full = Builder::new().build(pattern).unwrap();
+        let partial = Builder::new()
+            .parallel(true)
+            .build_partial(pattern, Duration::from_secs(0))
+            .unwrap();
+        assert!(partial.is_partial());
+        for &input in &[&b"0"[..], b"1", b"100000000001", b"0110110110110"] {
+            if let Ok(got) = partial.find(input) {
+                assert_eq!(full.find(input), got);
+            }
+        }
+    }
+
+    #[test]
+    fn build_literals() {
+        let sets: &[&[&str]] = &[
+            &["foo", "foobar", "fox"],
+            &["abxq", "ab", "abxy"],
+            &["ab", "ab", "a", "", "b"],
+            &["samwise", "sam", "Sam", "sAMwise", "s.m"],
+            &[],
+        ];
+        let haystacks =
+            &["", "foobar", "xfoxfoo", "abxy", "abxq", "samwise", "Samwise"];
+        for literals in sets {
+            let pattern: Vec<String> =
+                literals.iter().map(|lit| regex_syntax::escape(lit)).collect();
+            let bytes: Vec<&[u8]> =
+                literals.iter().map(|lit| lit.as_bytes()).collect();
+            for &longest in &[false, true] {
+                for &case_insensitive in &[false, true] {
+                    let mut builder = Builder::new();
+                    builder.longest_match(longest);
+                    builder.case_insensitive(case_insensitive);
+                    let got = builder.build_literals(&bytes).unwrap();
+                    let expected = if literals.is_empty() {
+                        None
+                    } else {
+                        Some(builder.build(&pattern.join("|")).unwrap())
+                    };
+                    for haystack in haystacks {
+                        let haystack = haystack.as_bytes();
+                        assert_eq!(
+                            expected.as_ref().and_then(|e| e.find(haystack)),
+                            got.find(haystack),
+                            "literals: {:?}, haystack: {:?}",
+                            literals,
+                            haystack,
+                        );
+                    }
                 }
+            }
+        }
+
+        let literals: &[&[u8]] = &[b"\xFFa", b"\xFF"];
+        let dfa = Builder::new().build_literals(literals).unwrap();
+        assert_eq!(Some(3), dfa.find(b"x\xFFa"));
+    }
+
+    #[test]
+    fn build_from_parts() {
+        // An anchored `[0-9]+`, with the digits in one class.
+        let mut map = [0u8; 256];
+        for b in 0..256 {
+            map[b] = match b as u8 {
+                b'0'..=b'9' => b'0',
+                b if b < b'0' => b,
+                b => b - 9,
             };
-            if prev_next == next {
-                self.cur = Some((prev_start, b, prev_next));
-            } else {
-                self.cur = Some((b, b, next));
-                if prev_next != dead_id() {
-                    return Some((prev_start, prev_end, prev_next));
+        }
+        let classes = ByteClasses::from_array(map).unwrap();
+        let digit = classes.get(b'0') as usize;
+        let stride = classes.alphabet_len();
+        let mut trans = vec![0; 4 * stride];
+        trans[stride + digit] = 3;
+        trans[3 * stride + digit] = 2;
+        trans[2 * stride + digit] = 3;
+
+        let expected = Builder::new().anchored(true).build("[0-9]+").unwrap();
+        for &minimize in &[false, true] {
+            for &premultiply in &[false, true] {
+                let dfa: DenseDFA<Vec<u16>, u16> = Builder::new()
+                    .anchored(true)
+                    .minimize(minimize)
+                    .premultiply(premultiply)
+                    .build_from_parts(&trans, 1, &[2, 3], Some(classes))
+                    .unwrap();
+                if minimize {
+                    assert_eq!(3, dfa.state_count());
+                }
+                for haystack in &["123a", "a1", "", "9", "12 3"] {
+                    assert_eq!(
+                        expected.find(haystack.as_bytes()),
+                        dfa.find(haystack.as_bytes()),
+                    );
                 }
             }
         }
-        if let Some((start, end, next)) = self.cur.take() {
-            if next != dead_id() {
-                return Some((start, end, next));
+
+        let builder = Builder::new();
+        let build = |trans: &[usize], start, matches: &[usize]| {
+            let result: Result<DenseDFA<Vec<usize>, usize>> =
+                builder.build_from_parts(trans, start, matches, Some(classes));
+            match *result.unwrap_err().kind() {
+                ErrorKind::InvalidParts(_) => {}
+                ref kind => panic!("unexpected error: {:?}", kind),
             }
+        };
+        build(&trans[1..], 1, &[2]);
+        build(&trans, 4, &[2]);
+        build(&trans, 1, &[0]);
+        build(&trans, 1, &[4]);
+        let mut bad = trans.clone();
+        bad[0] = 1;
+        build(&bad, 1, &[2]);
+        bad = trans.clone();
+        bad[stride] = 4;
+        build(&bad, 1, &[2]);
+    }
+
+    #[test]
+    fn build_cache() {
+        let bytes = |dfa: &DenseDFA<Vec<usize>, usize>| {
+            dfa.to_bytes_native_endian().unwrap().into_vec()
+        };
+        let patterns = &[
+            r"[a-z]+ing",
+            r"(?-u:\b)foo[0-9]{2}(?-u:\b)",
+            r"(?m)^bar$",
+            r"\w{3}@\w{3}",
+        ];
+        let mut cache = BuildCache::new();
+
+        // Rebuilding the same patterns reuses every transition.
+        let builder = Builder::new();
+        let first =
+            builder.build_many_with_cache(patterns, &mut cache).unwrap();
+        assert_eq!(0, cache.reused_transitions());
+        let state_count = cache.state_count();
+        let again =
+            builder.build_many_with_cache(patterns, &mut cache).unwrap();
+        assert!(cache.reused_transitions() > 0);
+        assert_eq!(state_count, cache.state_count());
+        assert_eq!(bytes(&first), bytes(&again));
+
+        // A cache of other patterns is only reused where their NFA states
+        // are the same, so the DFA built is still correct.
+        let mut stale = BuildCache::new();
+        let changed = &[r"[a-z]+ed", patterns[1], patterns[2], patterns[3]];
+        builder.build_many_with_cache(changed, &mut stale).unwrap();
+        let stale = BuildCache::from_bytes_unchecked(&stale.to_bytes());
+        let mut stale = stale.unwrap();
+        let got = builder.build_many_with_cache(patterns, &mut stale).unwrap();
+        assert_eq!(bytes(&first), bytes(&got));
+
+        // Other options that change how transitions are computed reuse
+        // nothing.
+        let mut longest = Builder::new();
+        longest.longest_match(true);
+        let got = longest.build_many_with_cache(patterns, &mut cache).unwrap();
+        assert_eq!(0, cache.reused_transitions());
+        assert_eq!(bytes(&longest.build_many(patterns).unwrap()), bytes(&got));
+
+        // Adding a pattern to an anchored DFA reuses the transitions within
+        // the previous patterns, and the cache survives serialization.
+        let mut anchored = Builder::new();
+        anchored.anchored(true);
+        anchored.build_many_with_cache(&patterns[..3], &mut cache).unwrap();
+        let mut cache =
+            BuildCache::from_bytes_unchecked(&cache.to_bytes()).unwrap();
+        assert_eq!(0, cache.reused_transitions());
+        let got =
+            anchored.build_many_with_cache(patterns, &mut cache).unwrap();
+        assert!(cache.reused_transitions() > 0);
+        assert_eq!(
+            bytes(&anchored.build_many(patterns).unwrap()),
+            bytes(&got)
+        );
+        let serialized = cache.to_bytes();
+        let roundtrip = BuildCache::from_bytes_unchecked(&serialized).unwrap();
+        assert_eq!(serialized, roundtrip.to_bytes());
+
+        let mut corrupt = serialized.clone();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        match BuildCache::from_bytes_unchecked(&corrupt) {
+            Err(DeserializeError::ChecksumMismatch { .. }) => {}
+            got => panic!("unexpected result: {:?}", got),
         }
-        None
+        assert_eq!(
+            Err(DeserializeError::InvalidLabel),
+            BuildCache::from_bytes_unchecked(&serialized[1..]).map(|_| ()),
+        );
+
+        // A failed build leaves the cache unchanged.
+        assert!(anchored.build_many_with_cache(&["("], &mut cache).is_err());
+        assert_eq!(serialized, cache.to_bytes());
     }
-}
 
-/// A mutable representation of a single DFA state.
-///
-/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
-/// corresponds to the state identifier representation.
-#[cfg(feature = "std")]
-pub(crate) struct StateMut<'a, S: 'a> {
-    transitions: &'a mut [S],
-}
+    #[test]
+    fn minimize_cancelled() {
+        let pattern = "foo[0-9]+|bar[0-9]+";
+        let mut dfa =
+            Builder::new().premultiply(false).build(pattern).unwrap();
+        let before = dfa.to_text();
+        let cancel = Cancel {
+            deadline: None,
+            flag: Some(Arc::new(AtomicBool::new(true))),
+        };
+        assert!(!dfa.repr_mut().minimize_with(cancel));
+        assert_eq!(before, dfa.to_text());
+    }
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> StateMut<'a, S> {
-    /// Return an iterator over all transitions in this state. This yields
-    /// a number of transitions equivalent to the alphabet length of the
-    /// corresponding DFA.
-    ///
-    /// Each transition is represented by a tuple. The first element is the
-    /// input byte for that transition and the second element is a mutable
-    /// reference to the transition itself.
-    pub fn iter_mut(&mut self) -> StateTransitionIterMut<S> {
-        StateTransitionIterMut { it: self.transitions.iter_mut().enumerate() }
+    #[test]
+    fn errors_when_determinization_would_overflow() {
+        let pattern = r"\w{10}";
+
+        let mut builder = Builder::new();
+        builder.byte_classes(false).anchored(true).premultiply(false);
+        // using u16 is fine
+        assert!(builder.build_with_size::<u16>(pattern).is_ok());
+        // // ... but u8 results in overflow (because there are >256 states)
+        assert!(builder.build_with_size::<u8>(pattern).is_err());
     }
-}
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> fmt::Debug for StateMut<'a, S> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&State { transitions: self.transitions }, f)
+    #[test]
+    fn errors_when_premultiply_would_overflow() {
+        let pattern = r"[a-z]";
+
+        let mut builder = Builder::new();
+        builder.byte_classes(false).anchored(true).premultiply(false);
+        // without premultiplication is OK
+        assert!(builder.build_with_size::<u8>(pattern).is_ok());
+        // ... but with premultiplication overflows u8
+        builder.premultiply(true);
+        assert!(builder.build_with_size::<u8>(pattern).is_err());
     }
-}
 
-/// A mutable iterator over all transitions in a DFA state.
-///
-/// Each transition is represented by a tuple. The first element is the
-/// input byte for that transition and the second element is a mutable
-/// reference to the transition itself.
-#[cfg(feature = "std")]
-#[derive(Debug)]
-pub(crate) struct StateTransitionIterMut<'a, S: 'a> {
-    it: iter::Enumerate<slice::IterMut<'a, S>>,
-}
+    #[test]
+    fn union_is_smaller_than_alternation() {
+        let patterns = &[r"[a-z]+ing", r"foo[0-9]{2}", r"\w+bar"];
+        let union = Builder::new().build_union(patterns).unwrap();
+        let alternation = Builder::new().build(&patterns.join("|")).unwrap();
+        assert!(
+            union.memory_usage() < alternation.memory_usage(),
+            "expected {} < {}",
+            union.memory_usage(),
+            alternation.memory_usage(),
+        );
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> Iterator for StateTransitionIterMut<'a, S> {
-    type Item = (u8, &'a mut S);
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"ing",
+            b"sing",
+            b"foo1",
+            b"foo12",
+            b"xfoo99y",
+            b"bar",
+            b"zbar",
+            b"nothing to see",
+            b"FOO12",
+            b"!!!",
+        ];
+        for &haystack in haystacks {
+            assert_eq!(
+                alternation.is_match(haystack),
+                union.is_match(haystack),
+                "mismatch on {:?}",
+                haystack,
+            );
+        }
+    }
+
+    #[test]
+    fn union_is_smaller_than_many() {
+        let patterns = &[r"[a-z]+ing", r"foo[0-9]{2}", r"\w+bar"];
+        let union = Builder::new().build_union(patterns).unwrap();
+        let many = Builder::new().build_many(patterns).unwrap();
+        assert!(
+            union.state_count() < many.state_count(),
+            "expected {} < {}",
+            union.state_count(),
+            many.state_count(),
+        );
 
-    fn next(&mut self) -> Option<(u8, &'a mut S)> {
-        self.it.next().map(|(i, id)| (i as u8, id))
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"ing",
+            b"sing",
+            b"foo12",
+            b"xfoo99y",
+            b"zbar",
+            b"nothing to see",
+            b"FOO12",
+        ];
+        for &haystack in haystacks {
+            assert_eq!(
+                many.is_match(haystack),
+                union.is_match(haystack),
+                "mismatch on {:?}",
+                haystack,
+            );
+        }
     }
-}
 
-/// A builder for constructing a deterministic finite automaton from regular
-/// expressions.
-///
-/// This builder permits configuring several aspects of the construction
-/// process such as case insensitivity, Unicode support and various options
-/// that impact the size of the generated DFA. In some cases, options (like
-/// performing DFA minimization) can come with a substantial additional cost.
-///
-/// This builder always constructs a *single* DFA. As such, this builder can
-/// only be used to construct regexes that either detect the presence of a
-/// match or find the end location of a match. A single DFA cannot produce both
-/// the start and end of a match. For that information, use a
-/// [`Regex`](struct.Regex.html), which can be similarly configured using
-/// [`RegexBuilder`](struct.RegexBuilder.html).
-#[cfg(feature = "std")]
-#[derive(Clone, Debug)]
-pub struct Builder {
-    parser: ParserBuilder,
-    nfa: nfa::Builder,
-    anchored: bool,
-    minimize: bool,
-    premultiply: bool,
-    byte_classes: bool,
-    reverse: bool,
-    longest_match: bool,
-}
+    #[test]
+    fn serialized_len_matches_bytes() {
+        let patterns = &["[a-z]+", "[0-9]+"];
+        let mut builder = Builder::new();
+        let tagged = &[(7, "[a-z]+"), (9, "[0-9]+")];
+        let dfas = vec![
+            builder.build_many(patterns).unwrap(),
+            builder.build_many_tagged(tagged).unwrap(),
+            builder
+                .starts_for_each_pattern(true)
+                .build_many(patterns)
+                .unwrap(),
+            builder.build_many_tagged(tagged).unwrap(),
+        ];
+        for dfa in &dfas {
+            let bytes = dfa.to_bytes_native_endian().unwrap();
+            assert_eq!(bytes.len(), dfa.serialized_len());
+        }
+    }
+
+    #[test]
+    fn estimate_size_matches_built_dfa() {
+        let patterns =
+            &[r"a", r"foo[0-9]+", r"\w{3}", r"[01]*1[01]{5}", r"(?i)hello"];
+        for &minimize in &[false, true] {
+            let mut builder = Builder::new();
+            builder.minimize(minimize);
+            for &pattern in patterns {
+                let estimate = builder.estimate_size(pattern).unwrap();
+                let dfa = builder.build(pattern).unwrap();
+                assert_eq!(estimate.state_count(), dfa.state_count());
+                assert_eq!(estimate.alphabet_len(), dfa.repr().alphabet_len());
+                assert_eq!(
+                    estimate.serialized_len(),
+                    dfa.to_bytes_native_endian().unwrap().len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shared_prefix() {
+        let mut builder = Builder::new();
+        builder.anchored(true);
+        let foobar = builder.build("foobar").unwrap();
+        let foobaz = builder.build("foobaz").unwrap();
+        let fooba = builder.build("fooba").unwrap();
+        let any = builder.build("foo[a-z]+").unwrap();
+
+        assert_eq!(Some(5), foobar.shared_prefix_len(&foobaz));
+        assert_eq!(Some(5), foobaz.shared_prefix_len(&foobar));
+        assert!(foobar.shares_prefix_of_len(&foobaz, 5));
+        assert!(!foobar.shares_prefix_of_len(&foobaz, 6));
+        assert_eq!(Some(5), foobar.shared_prefix_len(&fooba));
+        assert_eq!(Some(3), foobar.shared_prefix_len(&any));
+        assert_eq!(
+            None,
+            foobar.shared_prefix_len(&foobar.to_sparse().unwrap())
+        );
+    }
+
+    #[test]
+    fn shortest_matching_input() {
+        let dfa = Builder::new().build(r"[a-z]{3}|[0-9]{2}").unwrap();
+        assert_eq!(Some(b"00".to_vec()), dfa.some_matching_input());
+        assert_eq!(Some(2), dfa.shortest_match_len());
+        assert!(!dfa.is_empty_language());
+
+        let dfa = Builder::new().build("").unwrap();
+        assert_eq!(Some(vec![]), dfa.some_matching_input());
+
+        let dfa = Builder::new().build(r"a$b").unwrap();
+        assert!(dfa.is_empty_language());
+        assert_eq!(None, dfa.shortest_match_len());
+        let dfa = Builder::new().build(r"a$b|c$").unwrap();
+        assert_eq!(Some(b"c".to_vec()), dfa.some_matching_input());
+
+        // With look-around, a match is only reported once the byte after
+        // it is seen, or once the end of the input is reached.
+        let mut builder = Builder::new();
+        builder.allow_invalid_utf8(true);
+        let dfa = builder.build(r"(?-u:\b)ab(?-u:\B)").unwrap();
+        let input = dfa.some_matching_input().unwrap();
+        assert_eq!(b"ab0".to_vec(), input);
+        assert!(dfa.is_match(&input));
+        let dfa = builder.build(r"(?-u:\b)ab(?-u:\b)").unwrap();
+        assert_eq!(Some(b"ab".to_vec()), dfa.some_matching_input());
+        let dfa = builder.build(r"(?-u:\b)(?-u:\B)a").unwrap();
+        assert!(dfa.is_empty_language());
+    }
+
+    #[test]
+    fn set_operations_agree_with_operands() {
+        fn accepts<D: DFA>(dfa: &D, input: &[u8]) -> bool {
+            dfa.find(input) == Some(input.len())
+        }
 
-#[cfg(feature = "std")]
-impl Builder {
-    /// Create a new DenseDFA builder with the default configuration.
-    pub fn new() -> Builder {
-        let mut nfa = nfa::Builder::new();
-        // This is enabled by default, but we set it here anyway. Since we're
-        // building a DFA, shrinking the NFA is always a good idea.
-        nfa.shrink(true);
-        Builder {
-            parser: ParserBuilder::new(),
-            nfa,
-            anchored: false,
-            minimize: false,
-            premultiply: true,
-            byte_classes: true,
-            reverse: false,
-            longest_match: false,
+        let mut builder = Builder::new();
+        builder.anchored(true);
+        let patterns = &["a+b?", "[ab]{2}", "b*", "a[a0]*0", ""];
+        let dfas: Vec<DenseDFA<Vec<usize>, usize>> =
+            patterns.iter().map(|p| builder.build(p).unwrap()).collect();
+        let mut inputs = vec![vec![]];
+        for len in 1..5 {
+            let prev: Vec<Vec<u8>> = inputs
+                .iter()
+                .filter(|input| input.len() == len - 1)
+                .cloned()
+                .collect();
+            for input in prev {
+                for &b in b"ab0" {
+                    let mut next = input.clone();
+                    next.push(b);
+                    inputs.push(next);
+                }
+            }
+        }
+        for a in &dfas {
+            let not = a.complement().unwrap();
+            for input in &inputs {
+                assert_eq!(!accepts(a, input), accepts(&not, input));
+            }
+            for b in &dfas {
+                let and = a.intersect(b).unwrap();
+                let or = a.union(b).unwrap();
+                for input in &inputs {
+                    let (x, y) = (accepts(a, input), accepts(b, input));
+                    assert_eq!(x && y, accepts(&and, input));
+                    assert_eq!(x || y, accepts(&or, input));
+                }
+            }
         }
+
+        let unanchored = Builder::new().build("a").unwrap();
+        assert!(dfas[0].intersect(&unanchored).is_err());
+        assert!(unanchored.complement().is_err());
+        let look = builder.build("a$").unwrap();
+        assert!(look.union(&dfas[0]).is_err());
     }
 
-    /// Build a DFA from the given pattern.
-    ///
-    /// If there was a problem parsing or compiling the pattern, then an error
-    /// is returned.
-    pub fn build(&self, pattern: &str) -> Result<DenseDFA<Vec<usize>, usize>> {
-        self.build_with_size::<usize>(pattern)
+    #[test]
+    fn equivalence() {
+        let mut builder = Builder::new();
+        builder.anchored(true).minimize(false);
+        let a = builder.build("[0-9]+|[a-f]+").unwrap();
+        let b = builder.minimize(true).build("[a-f]+|[0-9]+").unwrap();
+        let c = builder.premultiply(false).byte_classes(false);
+        let c = c.build("(?:[0-9]|[a-f])+").unwrap();
+        assert!(a.is_equivalent(&b));
+        assert!(b.is_equivalent(&a));
+        assert!(a.is_equivalent(&a.to_sparse().unwrap()));
+        assert!(!a.is_equivalent(&c));
+        assert!(a.union(&b).unwrap().is_equivalent(&a));
+        // Two DFAs differ precisely when their symmetric difference is
+        // non-empty.
+        let diff = a
+            .intersect(&c.complement().unwrap())
+            .unwrap()
+            .union(&c.intersect(&a.complement().unwrap()).unwrap())
+            .unwrap();
+        assert_eq!(Some(b"0a".to_vec()), diff.some_matching_input());
+
+        let unanchored = Builder::new().build("[0-9]+|[a-f]+").unwrap();
+        assert!(!a.is_equivalent(&unanchored));
+        let look = Builder::new().build(r"(?-u:\b)a+").unwrap();
+        let same = Builder::new().build(r"(?-u:\b)(?:a|aa)+").unwrap();
+        let other = Builder::new().build(r"a+").unwrap();
+        assert!(look.is_equivalent(&same));
+        assert!(!look.is_equivalent(&other));
     }
 
-    /// Build a DFA from the given pattern using a specific representation for
-    /// the DFA's state IDs.
-    ///
-    /// If there was a problem parsing or compiling the pattern, then an error
-    /// is returned.
-    ///
-    /// The representation of state IDs is determined by the `S` type
-    /// parameter. In general, `S` is usually one of `u8`, `u16`, `u32`, `u64`
-    /// or `usize`, where `usize` is the default used for `build`. The purpose
-    /// of specifying a representation for state IDs is to reduce the memory
-    /// footprint of a DFA.
-    ///
-    /// When using this routine, the chosen state ID representation will be
-    /// used throughout determinization and minimization, if minimization
-    /// was requested. Even if the minimized DFA can fit into the chosen
-    /// state ID representation but the initial determinized DFA cannot,
-    /// then this will still return an error. To get a minimized DFA with a
-    /// smaller state ID representation, first build it with a bigger state ID
-    /// representation, and then shrink the size of the DFA using one of its
-    /// conversion routines, such as
-    /// [`DenseDFA::to_u16`](enum.DenseDFA.html#method.to_u16).
-    pub fn build_with_size<S: StateID>(
-        &self,
-        pattern: &str,
-    ) -> Result<DenseDFA<Vec<S>, S>> {
-        self.build_from_nfa(&self.build_nfa(pattern)?)
+    #[test]
+    fn analysis_with_lookaround() {
+        let mut builder = Builder::new();
+        builder.anchored(true);
+        let build = |pattern| builder.build(pattern).unwrap();
+
+        assert!(!build("a+$").is_finite_language());
+        assert!(build("a{2}$").is_finite_language());
+        assert!(!build(r"a+(?-u:\b)").is_finite_language());
+        assert!(build(r"(?-u:\b)ab(?-u:\b)").is_finite_language());
+
+        assert_eq!(Some(3), build("foo$").shared_prefix_len(&build("fooo$")));
+        assert_eq!(None, build("foo$").shared_prefix_len(&build("foo$")));
+        assert_eq!(
+            Some(1),
+            build(r"a(?-u:\b)").shared_prefix_len(&build(r"ab(?-u:\b)")),
+        );
     }
 
-    /// An internal only (for now) API for building a dense DFA directly from
-    /// an NFA.
-    pub(crate) fn build_from_nfa<S: StateID>(
-        &self,
-        nfa: &NFA,
-    ) -> Result<DenseDFA<Vec<S>, S>> {
-        if self.longest_match && !self.anchored {
-            return Err(Error::unsupported_longest_match());
+    #[test]
+    fn widen_u16_to_u32() {
+        let original = Builder::new().build(r"[a-z]{3}[0-9]+").unwrap();
+        let small = original.to_u16().unwrap();
+        let bytes = small.to_bytes_native_endian().unwrap();
+        let loaded: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        for small in &[small.as_ref(), loaded] {
+            let big = small.to_u32().unwrap();
+            let (srepr, brepr) = (small.repr(), big.repr());
+            assert_eq!(srepr.state_count(), brepr.state_count());
+            assert_eq!(srepr.start_state() as u32, brepr.start_state());
+            assert_eq!(
+                srepr.max_match_state() as u32,
+                brepr.max_match_state()
+            );
+            assert_eq!(srepr.trans().len(), brepr.trans().len());
+            for (&s, &b) in srepr.trans().iter().zip(brepr.trans()) {
+                assert_eq!(s as u32, b);
+            }
+            assert_eq!(Some(7), big.find(b"abc1234"));
+            assert_eq!(None, big.find(b"abc"));
+            // And narrowing back gives us the same DFA again.
+            assert_eq!(srepr.trans(), big.to_u16().unwrap().repr().trans());
         }
+    }
 
-        let mut dfa = if self.byte_classes {
-            Determinizer::new(nfa)
-                .with_byte_classes()
-                .longest_match(self.longest_match)
-                .build()
-        } else {
-            Determinizer::new(nfa).longest_match(self.longest_match).build()
-        }?;
-        if self.minimize {
-            dfa.minimize();
+    #[test]
+    fn finite_language() {
+        let mut builder = Builder::new();
+        builder.anchored(true);
+        assert!(builder.build(r"\d{3}").unwrap().is_finite_language());
+        assert!(!builder.build(r"\d+").unwrap().is_finite_language());
+        assert!(!builder.build(r"a(?:bc)*d").unwrap().is_finite_language());
+        assert!(builder.build(r"").unwrap().is_finite_language());
+        // The `a+` loop can never lead to a match, since it must be followed
+        // by a class that matches nothing.
+        // The parser rejects empty classes, so we build the equivalent of
+        // `a+[^\x00-\xFF]|b` by hand.
+        let a_plus = Hir::repetition(hir::Repetition {
+            kind: hir::RepetitionKind::OneOrMore,
+            greedy: true,
+            hir: Box::new(Hir::literal(hir::Literal::Unicode('a'))),
+        });
+        let empty = Hir::class(hir::Class::Bytes(hir::ClassBytes::empty()));
+        let hir = Hir::alternation(vec![
+            Hir::concat(vec![a_plus, empty]),
+            Hir::literal(hir::Literal::Unicode('b')),
+        ]);
+        let dfa = builder.build_from_hir(&hir).unwrap();
+        let a = dfa.next_state(dfa.start_state(), b'a');
+        assert!(!dfa.is_dead_state(a));
+        assert_eq!(a, dfa.next_state(a, b'a'));
+        assert!(dfa.find(b"aaa").is_none());
+        assert!(dfa.is_finite_language());
+
+        builder.anchored(false);
+        assert!(!builder.build(r"\d{3}").unwrap().is_finite_language());
+    }
+
+    #[test]
+    fn build_from_hir() {
+        let parser = || ParserBuilder::new().build();
+        let hir = parser().parse(r"[a-z]+").unwrap();
+        let hir =
+            Hir::concat(vec![hir, Hir::literal(hir::Literal::Unicode('0'))]);
+        let dfa = Builder::new()
+            .case_insensitive(true)
+            .build_from_hir(&hir)
+            .unwrap();
+        assert_eq!(Some(4), dfa.find(b"abc0"));
+        // The syntax options of the builder don't apply to a HIR.
+        assert_eq!(None, dfa.find(b"ABC0"));
+
+        let dfa = Builder::new()
+            .anchored(true)
+            .reverse(true)
+            .build_from_hir_with_size::<u16>(&hir)
+            .unwrap();
+        assert_eq!(Some(1), dfa.rfind(b"9abc0"));
+
+        let hir = parser().parse(r"\bfoo").unwrap();
+        assert!(Builder::new().build_from_hir(&hir).is_err());
+    }
+
+    #[test]
+    fn coverage_increases() {
+        let dfa = Builder::new().build(r"[a-z]+@[a-z]+\.(?:com|org)").unwrap();
+        let mut tracker = CoverageTracker::new(&dfa);
+        assert_eq!(0, tracker.visited_count());
+
+        let mut last = tracker.coverage();
+        let inputs: &[&[u8]] =
+            &[b"", b"x", b"abc@", b"abc@def.com", b"abc@def.org"];
+        for &input in inputs {
+            assert_eq!(dfa.find(input), dfa.find_covered(input, &mut tracker));
+            assert!(
+                tracker.coverage() > last,
+                "no new states for {:?}",
+                input
+            );
+            last = tracker.coverage();
         }
-        if self.premultiply {
-            dfa.premultiply()?;
+        // Searching the same inputs again does not visit anything new.
+        for &input in inputs {
+            dfa.find_covered(input, &mut tracker);
         }
-        Ok(dfa.into_dense_dfa())
+        assert_eq!(last, tracker.coverage());
+        assert!(tracker.coverage() <= 1.0);
+
+        tracker.clear();
+        assert_eq!(0.0, tracker.coverage());
     }
 
-    /// Builds an NFA from the given pattern.
-    pub(crate) fn build_nfa(&self, pattern: &str) -> Result<NFA> {
-        let hir = self.parser.build().parse(pattern).map_err(Error::syntax)?;
-        Ok(self.nfa.build(&hir)?)
+    #[test]
+    fn partial_agrees_with_full_where_explored() {
+        use std::time::Duration;
+
+        let pattern = r"[01]*1[01]{10}";
+        let full = Builder::new().build(pattern).unwrap();
+        let partial = Builder::new()
+            .build_partial(pattern, Duration::from_secs(0))
+            .unwrap();
+        assert!(partial.is_partial());
+        assert!(partial.dfa().state_count() < full.state_count());
+
+        assert_eq!(Ok(None), partial.find(b""));
+        assert_eq!(Ok(false), partial.is_match(b""));
+        let mut incomplete = 0;
+        for len in 0..14 {
+            for bits in 0..(1u32 << len) {
+                let input: Vec<u8> = (0..len)
+                    .map(|i| if bits & (1 << i) > 0 { b'1' } else { b'0' })
+                    .collect();
+                match partial.find(&input) {
+                    Ok(got) => assert_eq!(full.find(&input), got),
+                    Err(SearchError::Incomplete { offset }) => {
+                        assert!(offset < input.len());
+                        incomplete += 1;
+                    }
+                    Err(err) => panic!("unexpected error: {:?}", err),
+                }
+                if let Ok(got) = partial.is_match(&input) {
+                    assert_eq!(full.is_match(&input), got);
+                }
+            }
+        }
+        assert!(incomplete > 0);
     }
 
-    /// Set whether matching must be anchored at the beginning of the input.
-    ///
-    /// When enabled, a match must begin at the start of the input. When
-    /// disabled, the DFA will act as if the pattern started with a `.*?`,
-    /// which enables a match to appear anywhere.
-    ///
-    /// By default this is disabled.
-    pub fn anchored(&mut self, yes: bool) -> &mut Builder {
-        self.anchored = yes;
-        self.nfa.anchored(yes);
-        self
+    #[test]
+    fn partial_with_enough_time_is_complete() {
+        use std::time::Duration;
+
+        let pattern = r"[a-z]+@[a-z]+\.(?:com|org)";
+        let full = Builder::new().build(pattern).unwrap();
+        let partial = Builder::new()
+            .build_partial(pattern, Duration::from_secs(3600))
+            .unwrap();
+        assert!(!partial.is_partial());
+        assert_eq!(full.state_count(), partial.dfa().state_count());
+        for &input in &[&b"abc@def.com"[..], b"abc@def.net", b"", b"@x.org"] {
+            assert_eq!(Ok(full.find(input)), partial.find(input));
+            assert_eq!(Ok(full.is_match(input)), partial.is_match(input));
+        }
     }
 
-    /// Enable or disable the case insensitive flag by default.
-    ///
-    /// By default this is disabled. It may alternatively be selectively
-    /// enabled in the regular expression itself via the `i` flag.
-    pub fn case_insensitive(&mut self, yes: bool) -> &mut Builder {
-        self.parser.case_insensitive(yes);
-        self
+    #[test]
+    fn partial_with_lookaround() {
+        use std::time::Duration;
+
+        for &pattern in &["^a", "a$", r"(?-u:\b)a(?-u:\b)"] {
+            let full = Builder::new().build(pattern).unwrap();
+            let partial = Builder::new()
+                .build_partial(pattern, Duration::from_secs(3600))
+                .unwrap();
+            assert!(!partial.is_partial());
+            for &input in &[&b"a"[..], b"", b"ab", b"ba", b" a ", b"aa"] {
+                assert_eq!(Ok(full.find(input)), partial.find(input));
+                assert_eq!(Ok(full.is_match(input)), partial.is_match(input));
+            }
+            assert_eq!(Ok(Some(1)), partial.find(b"a"));
+            assert_eq!(Ok(true), partial.is_match(b"a"));
+        }
     }
 
-    /// Enable verbose mode in the regular expression.
-    ///
-    /// When enabled, verbose mode permits insigificant whitespace in many
-    /// places in the regular expression, as well as comments. Comments are
-    /// started using `#` and continue until the end of the line.
-    ///
-    /// By default, this is disabled. It may be selectively enabled in the
-    /// regular expression by using the `x` flag regardless of this setting.
-    pub fn ignore_whitespace(&mut self, yes: bool) -> &mut Builder {
-        self.parser.ignore_whitespace(yes);
-        self
+    #[test]
+    fn transition_table_bytes() {
+        let pattern = r"[a-z]+[0-9]{2}";
+        let build = |premultiply, byte_classes| {
+            Builder::new()
+                .premultiply(premultiply)
+                .byte_classes(byte_classes)
+                .build(pattern)
+                .unwrap()
+        };
+        for &(premultiply, byte_classes) in
+            &[(false, false), (false, true), (true, false), (true, true)]
+        {
+            let dfa = build(premultiply, byte_classes);
+            let bytes = dfa.to_bytes_native_endian().unwrap();
+            assert_eq!(bytes.len(), dfa.serialized_len());
+            assert!(dfa.transition_table_bytes() < dfa.serialized_len());
+            assert_eq!(
+                dfa.state_count()
+                    * dfa.repr().alphabet_len()
+                    * mem::size_of::<usize>(),
+                dfa.transition_table_bytes(),
+            );
+        }
+
+        assert_eq!(
+            build(false, true).transition_table_bytes(),
+            build(true, true).transition_table_bytes(),
+        );
+        assert!(
+            build(true, true).transition_table_bytes()
+                < build(true, false).transition_table_bytes()
+        );
+
+        let small = build(true, true).to_u16().unwrap();
+        assert_eq!(
+            build(true, true).transition_table_bytes() / 4,
+            small.transition_table_bytes(),
+        );
     }
 
-    /// Enable or disable the "dot matches any character" flag by default.
-    ///
-    /// By default this is disabled. It may alternatively be selectively
-    /// enabled in the regular expression itself via the `s` flag.
-    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Builder {
-        self.parser.dot_matches_new_line(yes);
-        self
+    #[test]
+    fn to_text_is_stable() {
+        let pattern = r"(?i)[a-z]+@[a-z]+\.(?:com|org)";
+        let text1 = Builder::new().build(pattern).unwrap().to_text();
+        let text2 = Builder::new().build(pattern).unwrap().to_text();
+        assert_eq!(text1, text2);
+
+        // Neither premultiplication nor byte classes change the output.
+        let text3 = Builder::new()
+            .premultiply(false)
+            .byte_classes(false)
+            .build(pattern)
+            .unwrap()
+            .to_text();
+        assert_eq!(text1, text3);
+
+        let dfa = Builder::new().build(pattern).unwrap();
+        let states = text1.lines().filter(|l| l.starts_with("state")).count();
+        assert_eq!(dfa.state_count(), states);
     }
 
-    /// Enable or disable the "swap greed" flag by default.
-    ///
-    /// By default this is disabled. It may alternatively be selectively
-    /// enabled in the regular expression itself via the `U` flag.
-    pub fn swap_greed(&mut self, yes: bool) -> &mut Builder {
-        self.parser.swap_greed(yes);
-        self
+    #[test]
+    fn try_from_bytes_rejects_invalid() {
+        let dfa = Builder::new().build(r"[a-z]+[0-9]").unwrap();
+        let bytes = dfa.to_u16().unwrap().to_bytes_native_endian().unwrap();
+        let ok: DenseDFA<&[u16], u16> =
+            DenseDFA::try_from_bytes(&bytes).unwrap();
+        assert_eq!(Some(4), ok.find(b"abc1"));
+
+        let got = DenseDFA::<&[u32], u32>::try_from_bytes(&bytes);
+        assert_eq!(
+            Some(DeserializeError::StateIDSizeMismatch {
+                expected: 4,
+                found: 2,
+            }),
+            got.err(),
+        );
+
+        let short = &bytes[..bytes.len() - 2];
+        match DenseDFA::<&[u16], u16>::try_from_bytes(short) {
+            Err(DeserializeError::BufferTooSmall { needed, got }) => {
+                assert_eq!(bytes.len(), needed);
+                assert_eq!(short.len(), got);
+            }
+            got => panic!("unexpected result: {:?}", got),
+        }
+
+        // Point the last transition at a state that doesn't exist.
+        let mut bad = bytes.clone();
+        let last = bad.len() - 2;
+        NativeEndian::write_u16(&mut bad[last..], 0xFFFF);
+        assert_eq!(
+            Some(DeserializeError::InvalidStateID { id: 0xFFFF }),
+            DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+        );
+
+        // Point a premultiplied ID into the middle of a state.
+        let mut bad = bytes.clone();
+        NativeEndian::write_u16(&mut bad[last..], 1);
+        assert_eq!(
+            Some(DeserializeError::InvalidStateID { id: 1 }),
+            DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+        );
+
+        // Make the dead state transition to the start state.
+        let mut bad = bytes.clone();
+        let start = dfa.to_u16().unwrap().start_state();
+        NativeEndian::write_u16(&mut bad[HEADER_LEN..], start);
+        assert_eq!(
+            Some(DeserializeError::InvalidDeadState),
+            DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+        );
+
+        // Redirect the last transition to another valid state, which can
+        // only be detected by the checksum.
+        let mut bad = bytes.clone();
+        let next = if NativeEndian::read_u16(&bad[last..]) == start {
+            0
+        } else {
+            start
+        };
+        NativeEndian::write_u16(&mut bad[last..], next);
+        match DenseDFA::<&[u16], u16>::try_from_bytes(&bad) {
+            Err(DeserializeError::ChecksumMismatch { expected, .. }) => {
+                assert_eq!(
+                    bytes::peek(&bytes).unwrap().checksum(),
+                    Some(expected),
+                );
+            }
+            got => panic!("unexpected result: {:?}", got),
+        }
+
+        let sparse =
+            dfa.to_sparse().unwrap().to_bytes_native_endian().unwrap();
+        assert_eq!(
+            Some(DeserializeError::InvalidLabel),
+            DenseDFA::<&[usize], usize>::try_from_bytes(&sparse).err(),
+        );
     }
 
-    /// Enable or disable the Unicode flag (`u`) by default.
-    ///
-    /// By default this is **enabled**. It may alternatively be selectively
-    /// disabled in the regular expression itself via the `u` flag.
-    ///
-    /// Note that unless `allow_invalid_utf8` is enabled (it's disabled by
-    /// default), a regular expression will fail to parse if Unicode mode is
-    /// disabled and a sub-expression could possibly match invalid UTF-8.
-    pub fn unicode(&mut self, yes: bool) -> &mut Builder {
-        self.parser.unicode(yes);
-        self
+    #[test]
+    fn try_from_bytes_rejects_invalid_byte_classes() {
+        let dfa = Builder::new().build(r"[a-z]+[0-9]").unwrap();
+        let bytes = dfa.to_u16().unwrap().to_bytes_native_endian().unwrap();
+        let classes_at = HEADER_LEN - 256 - 32 - ACCELS_LEN - 8;
+        let checksum_at = HEADER_LEN - 8;
+        assert!(bytes[classes_at + 255] < 250);
+
+        // Put one byte in a class beyond the alphabet, and recompute the
+        // checksum so that only the class map is at fault.
+        let mut bad = bytes.clone();
+        bad[classes_at + b'1' as usize] = 250;
+        let checksum = bytes::dfa_checksum(&bad, checksum_at);
+        NativeEndian::write_u64(&mut bad[checksum_at..], checksum as u64);
+        assert_eq!(
+            Some(DeserializeError::InvalidByteClasses),
+            DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+        );
+
+        // The first byte must always be in the first class.
+        let mut bad = bytes.clone();
+        bad[classes_at] = 1;
+        let checksum = bytes::dfa_checksum(&bad, checksum_at);
+        NativeEndian::write_u64(&mut bad[checksum_at..], checksum as u64);
+        assert_eq!(
+            Some(DeserializeError::InvalidByteClasses),
+            DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+        );
     }
 
-    /// When enabled, the builder will permit the construction of a regular
-    /// expression that may match invalid UTF-8.
-    ///
-    /// When disabled (the default), the builder is guaranteed to produce a
-    /// regex that will only ever match valid UTF-8 (otherwise, the builder
-    /// will return an error).
-    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut Builder {
-        self.parser.allow_invalid_utf8(yes);
-        self.nfa.allow_invalid_utf8(yes);
-        self
+    #[test]
+    fn try_from_bytes_rejects_invalid_accelerators() {
+        let mut builder = Builder::new();
+        builder.allow_invalid_utf8(true);
+        let dfa = builder.build(r#"(?-u)[^"]*""#).unwrap().to_u16().unwrap();
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        let first_accel_at = HEADER_LEN - 8 - ACCELS_LEN + 8;
+        let checksum_at = HEADER_LEN - 8;
+        let accel = NativeEndian::read_u64(&bytes[first_accel_at..]);
+        assert_eq!(dfa.start_state() as u64, accel);
+
+        // Each ID fits in a u16, but doesn't refer to a state of the DFA.
+        let stride = dfa.repr().alphabet_len() as u64;
+        let past_end = dfa.state_count() as u64 * stride;
+        for &id in &[past_end, accel + 1] {
+            let mut bad = bytes.clone();
+            NativeEndian::write_u64(&mut bad[first_accel_at..], id);
+            let checksum = bytes::dfa_checksum(&bad, checksum_at);
+            NativeEndian::write_u64(&mut bad[checksum_at..], checksum as u64);
+            assert_eq!(
+                Some(DeserializeError::InvalidStateID { id }),
+                DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+            );
+        }
     }
 
-    /// Set the nesting limit used for the regular expression parser.
-    ///
-    /// The nesting limit controls how deep the abstract syntax tree is allowed
-    /// to be. If the AST exceeds the given limit (e.g., with too many nested
-    /// groups), then an error is returned by the parser.
-    ///
-    /// The purpose of this limit is to act as a heuristic to prevent stack
-    /// overflow when building a finite automaton from a regular expression's
-    /// abstract syntax tree. In particular, construction currently uses
-    /// recursion. In the future, the implementation may stop using recursion
-    /// and this option will no longer be necessary.
-    ///
-    /// This limit is not checked until the entire AST is parsed. Therefore,
-    /// if callers want to put a limit on the amount of heap space used, then
-    /// they should impose a limit on the length, in bytes, of the concrete
-    /// pattern string. In particular, this is viable since the parser will
-    /// limit itself to heap space proportional to the lenth of the pattern
-    /// string.
-    ///
-    /// Note that a nest limit of `0` will return a nest limit error for most
-    /// patterns but not all. For example, a nest limit of `0` permits `a` but
-    /// not `ab`, since `ab` requires a concatenation AST item, which results
-    /// in a nest depth of `1`. In general, a nest limit is not something that
-    /// manifests in an obvious way in the concrete syntax, therefore, it
-    /// should not be used in a granular way.
-    pub fn nest_limit(&mut self, limit: u32) -> &mut Builder {
-        self.parser.nest_limit(limit);
-        self
+    #[test]
+    fn checksum_is_crc32() {
+        // The standard check value of CRC-32.
+        assert_eq!(0xCBF4_3926, bytes::crc32(0, b"123456789"));
+        let split = bytes::crc32(bytes::crc32(0, b"1234"), b"56789");
+        assert_eq!(0xCBF4_3926, split);
+    }
+
+    #[test]
+    fn minimize_keeps_patterns_apart() {
+        let mut builder = Builder::new();
+        builder.anchored(true).premultiply(false);
+        let patterns = &["ab", "cb", "xb|yb"];
+        let dfa = builder.build_many(patterns).unwrap();
+        let min = builder.minimize(true).build_many(patterns).unwrap();
+        // The states after reading `x` and `y` are merged. Without pattern
+        // information, they would also be merged with the states after
+        // reading `a` and `c`.
+        assert!(min.state_count() < dfa.state_count());
+        for &haystack in &[&b"ab"[..], b"cb", b"xb", b"yb", b"ad", b"zz"] {
+            assert_eq!(
+                dfa.find_pattern(haystack),
+                min.find_pattern(haystack),
+                "mismatch on {:?}",
+                haystack,
+            );
+        }
+        assert_eq!(Some((0, 2)), min.find_pattern(b"ab"));
+        assert_eq!(Some((1, 2)), min.find_pattern(b"cb"));
+        assert_eq!(Some((2, 2)), min.find_pattern(b"yb"));
     }
 
-    /// Minimize the DFA.
-    ///
-    /// When enabled, the DFA built will be minimized such that it is as small
-    /// as possible.
-    ///
-    /// Whether one enables minimization or not depends on the types of costs
-    /// you're willing to pay and how much you care about its benefits. In
-    /// particular, minimization has worst case `O(n*k*logn)` time and `O(k*n)`
-    /// space, where `n` is the number of DFA states and `k` is the alphabet
-    /// size. In practice, minimization can be quite costly in terms of both
-    /// space and time, so it should only be done if you're willing to wait
-    /// longer to produce a DFA. In general, you might want a minimal DFA in
-    /// the following circumstances:
-    ///
-    /// 1. You would like to optimize for the size of the automaton. This can
-    ///    manifest in one of two ways. Firstly, if you're converting the
-    ///    DFA into Rust code (or a table embedded in the code), then a minimal
-    ///    DFA will translate into a corresponding reduction in code  size, and
-    ///    thus, also the final compiled binary size. Secondly, if you are
-    ///    building many DFAs and putting them on the heap, you'll be able to
-    ///    fit more if they are smaller. Note though that building a minimal
-    ///    DFA itself requires additional space; you only realize the space
-    ///    savings once the minimal DFA is constructed (at which point, the
-    ///    space used for minimization is freed).
-    /// 2. You've observed that a smaller DFA results in faster match
-    ///    performance. Naively, this isn't guaranteed since there is no
-    ///    inherent difference between matching with a bigger-than-minimal
-    ///    DFA and a minimal DFA. However, a smaller DFA may make use of your
-    ///    CPU's cache more efficiently.
-    /// 3. You are trying to establish an equivalence between regular
-    ///    languages. The standard method for this is to build a minimal DFA
-    ///    for each language and then compare them. If the DFAs are equivalent
-    ///    (up to state renaming), then the languages are equivalent.
-    ///
-    /// This option is disabled by default.
-    pub fn minimize(&mut self, yes: bool) -> &mut Builder {
-        self.minimize = yes;
-        self
+    /// Count the states of the minimal DFA equivalent to the given DFA using
+    /// Moore's algorithm, which repeatedly splits every class of states by
+    /// the classes of their successors until no class is split.
+    fn moore_state_count(dfa: &DenseDFA<Vec<usize>, usize>) -> usize {
+        let repr = dfa.repr();
+        let mut classes: Vec<usize> = vec![0; repr.state_count()];
+        let mut count = 0;
+        loop {
+            let mut signatures = BTreeMap::new();
+            let mut next_classes = vec![];
+            for (id, state) in repr.states() {
+                let patterns: Vec<PatternID> = (0..dfa.match_count(id))
+                    .map(|i| dfa.match_pattern(id, i))
+                    .collect();
+                let successors: Vec<usize> = state
+                    .transitions()
+                    .map(|(_, next)| classes[next])
+                    .collect();
+                let len = signatures.len();
+                let key = (classes[id], patterns, successors);
+                next_classes.push(*signatures.entry(key).or_insert(len));
+            }
+            classes = next_classes;
+            if signatures.len() == count {
+                return count;
+            }
+            count = signatures.len();
+        }
     }
 
-    /// Premultiply state identifiers in the DFA's transition table.
-    ///
-    /// When enabled, state identifiers are premultiplied to point to their
-    /// corresponding row in the DFA's transition table. That is, given the
-    /// `i`th state, its corresponding premultiplied identifier is `i * k`
-    /// where `k` is the alphabet size of the DFA. (The alphabet size is at
-    /// most 256, but is in practice smaller if byte classes is enabled.)
-    ///
-    /// When state identifiers are not premultiplied, then the identifier of
-    /// the `i`th state is `i`.
-    ///
-    /// The advantage of premultiplying state identifiers is that is saves
-    /// a multiplication instruction per byte when searching with the DFA.
-    /// This has been observed to lead to a 20% performance benefit in
-    /// micro-benchmarks.
-    ///
-    /// The primary disadvantage of premultiplying state identifiers is
-    /// that they require a larger integer size to represent. For example,
-    /// if your DFA has 200 states, then its premultiplied form requires
-    /// 16 bits to represent every possible state identifier, where as its
-    /// non-premultiplied form only requires 8 bits.
-    ///
-    /// This option is enabled by default.
-    pub fn premultiply(&mut self, yes: bool) -> &mut Builder {
-        self.premultiply = yes;
-        self
+    #[test]
+    fn minimize_is_minimal() {
+        let patterns = &[
+            r"X(.?){7,}Y",
+            r"(a|ab|c|bcd){4,10}(d*)",
+            r"[a-z]+[0-9]|[a-z0-9]+!",
+            r"\w{3}",
+            r"(?i)foo|bar|baz",
+        ];
+        for &pattern in patterns {
+            for &byte_classes in &[false, true] {
+                let mut builder = Builder::new();
+                builder.premultiply(false).byte_classes(byte_classes);
+                let dfa = builder.build(pattern).unwrap();
+                let min = builder.minimize(true).build(pattern).unwrap();
+                assert_eq!(
+                    moore_state_count(&dfa),
+                    min.state_count(),
+                    "pattern: {:?}, byte classes: {:?}",
+                    pattern,
+                    byte_classes,
+                );
+            }
+        }
     }
 
-    /// Shrink the size of the DFA's alphabet by mapping bytes to their
-    /// equivalence classes.
-    ///
-    /// When enabled, each DFA will use a map from all possible bytes to their
-    /// corresponding equivalence class. Each equivalence class represents a
-    /// set of bytes that does not discriminate between a match and a non-match
-    /// in the DFA. For example, the pattern `[ab]+` has at least two
-    /// equivalence classes: a set containing `a` and `b` and a set containing
-    /// every byte except for `a` and `b`. `a` and `b` are in the same
-    /// equivalence classes because they never discriminate between a match
-    /// and a non-match.
-    ///
-    /// The advantage of this map is that the size of the transition table can
-    /// be reduced drastically from `#states * 256 * sizeof(id)` to
-    /// `#states * k * sizeof(id)` where `k` is the number of equivalence
-    /// classes. As a result, total space usage can decrease substantially.
-    /// Moreover, since a smaller alphabet is used, compilation becomes faster
-    /// as well.
-    ///
-    /// The disadvantage of this map is that every byte searched must be
-    /// passed through this map before it can be used to determine the next
-    /// transition. This has a small match time performance cost.
-    ///
-    /// This option is enabled by default.
-    pub fn byte_classes(&mut self, yes: bool) -> &mut Builder {
-        self.byte_classes = yes;
-        self
+    #[test]
+    fn reduce_merges_identical_states() {
+        let patterns = &[
+            "foo|bar|baz|quux",
+            "foobar|fooquux|zapbar|zapquux",
+            r"X(.?){7,}Y",
+            r"[a-z]+[0-9]|[a-z0-9]+!",
+            r"(?i)foo|bar|baz",
+        ];
+        for &pattern in patterns {
+            let mut builder = Builder::new();
+            let dfa = builder.build(pattern).unwrap();
+            let reduced = builder.reduce(true).build(pattern).unwrap();
+            let min = builder.minimize(true).build(pattern).unwrap();
+            assert!(reduced.state_count() <= dfa.state_count());
+            assert!(min.state_count() <= reduced.state_count());
+            if pattern.starts_with("foobar") {
+                // The common suffixes of literals are merged completely.
+                assert!(reduced.state_count() < dfa.state_count());
+                assert_eq!(min.state_count(), reduced.state_count());
+            }
+            let haystacks =
+                &[&b"foo"[..], b"xbaz", b"zapbar", b"X1234567Y", b"ab1"];
+            for &haystack in haystacks {
+                assert_eq!(
+                    dfa.find(haystack),
+                    reduced.find(haystack),
+                    "pattern: {:?}, haystack: {:?}",
+                    pattern,
+                    haystack,
+                );
+            }
+        }
     }
 
-    /// Reverse the DFA.
-    ///
-    /// A DFA reversal is performed by reversing all of the concatenated
-    /// sub-expressions in the original pattern, recursively. The resulting
-    /// DFA can be used to match the pattern starting from the end of a string
-    /// instead of the beginning of a string.
-    ///
-    /// Generally speaking, a reversed DFA is most useful for finding the start
-    /// of a match, since a single forward DFA is only capable of finding the
-    /// end of a match. This start of match handling is done for you
-    /// automatically if you build a [`Regex`](struct.Regex.html).
-    pub fn reverse(&mut self, yes: bool) -> &mut Builder {
-        self.reverse = yes;
-        self.nfa.reverse(yes);
-        self
+    #[test]
+    fn match_patterns_survive_conversion() {
+        let dfa = Builder::new()
+            .build_many(&["[a-z]+", "[0-9]+", "[a-z0-9]+!"])
+            .unwrap();
+        assert!(dfa.to_sparse().is_err());
+
+        let small = dfa.to_u16().unwrap();
+        let bytes = small.to_bytes_native_endian().unwrap();
+        assert_eq!(small.serialized_len(), bytes.len());
+        let loaded: DenseDFA<&[u16], u16> =
+            DenseDFA::try_from_bytes(&bytes).unwrap();
+        assert_eq!(3, loaded.pattern_count());
+        for &haystack in &[&b"abc"[..], b"123", b"a1!", b"-", b"x9"] {
+            assert_eq!(
+                dfa.find_pattern(haystack),
+                loaded.find_pattern(haystack)
+            );
+        }
+        assert_eq!(Some((1, 3)), loaded.find_pattern(b"123"));
+
+        // Point the last match state at a pattern that doesn't exist.
+        let mut bad = bytes.clone();
+        let last = bad.len() - 2;
+        NativeEndian::write_u16(&mut bad[last..], 3);
+        assert_eq!(
+            Some(DeserializeError::InvalidMatchTable),
+            DenseDFA::<&[u16], u16>::try_from_bytes(&bad).err(),
+        );
     }
 
-    /// Find the longest possible match.
-    ///
-    /// This is distinct from the default leftmost-first match semantics in
-    /// that it treats all NFA states as having equivalent priority. In other
-    /// words, the longest possible match is always found and it is not
-    /// possible to implement non-greedy match semantics when this is set. That
-    /// is, `a+` and `a+?` are equivalent when this is enabled.
-    ///
-    /// In particular, a practical issue with this option at the moment is that
-    /// it prevents unanchored searches from working correctly, since
-    /// unanchored searches are implemented by prepending an non-greedy `.*?`
-    /// to the beginning of the pattern. As stated above, non-greedy match
-    /// semantics aren't supported. Therefore, if this option is enabled and
-    /// an unanchored search is requested, then building a DFA will return an
-    /// error.
-    ///
-    /// This option is principally useful when building a reverse DFA for
-    /// finding the start of a match. If you are building a regex with
-    /// [`RegexBuilder`](struct.RegexBuilder.html), then this is handled for
-    /// you automatically. The reason why this is necessary for start of match
-    /// handling is because we want to find the earliest possible starting
-    /// position of a match to satisfy leftmost-first match semantics. When
-    /// matching in reverse, this means finding the longest possible match,
-    /// hence, this option.
-    ///
-    /// By default this is disabled.
-    pub fn longest_match(&mut self, yes: bool) -> &mut Builder {
-        // There is prior art in RE2 that shows how this can support unanchored
-        // searches. Instead of treating all NFA states as having equivalent
-        // priority, we instead group NFA states into sets, and treat members
-        // of each set as having equivalent priority, but having greater
-        // priority than all following members of different sets. We then
-        // essentially assign a higher priority to everything over the prefix
-        // `.*?`.
-        self.longest_match = yes;
-        self
+    #[test]
+    fn prioritized_patterns() {
+        let patterns = &[(0, "[a-z]+"), (1, "if|in"), (1, "int"), (0, "i")];
+        let mut builder = Builder::new();
+        builder.match_kind(MatchKind::LeftmostLongest);
+        let dfas = vec![
+            builder.build_many_prioritized(patterns).unwrap(),
+            builder.minimize(true).build_many_prioritized(patterns).unwrap(),
+            builder.anchored(true).build_many_prioritized(patterns).unwrap(),
+        ];
+        for dfa in dfas {
+            assert_eq!(Some((1, 2)), dfa.find_pattern(b"if"));
+            assert_eq!(Some((2, 3)), dfa.find_pattern(b"int"));
+            assert_eq!(Some((0, 4)), dfa.find_pattern(b"into"));
+            assert_eq!(Some((0, 1)), dfa.find_pattern(b"i"));
+
+            let bytes =
+                dfa.to_u16().unwrap().to_bytes_native_endian().unwrap();
+            let loaded: DenseDFA<&[u16], u16> =
+                DenseDFA::try_from_bytes(&bytes).unwrap();
+            assert_eq!(Some((1, 2)), loaded.find_pattern(b"if"));
+        }
+
+        // Without priorities, pattern order breaks ties.
+        let dfa = Builder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build_many(&["[a-z]+", "if"])
+            .unwrap();
+        assert_eq!(Some((0, 2)), dfa.find_pattern(b"if"));
+
+        let dfa = Builder::new()
+            .all_matches(true)
+            .build_many_prioritized(&[(0, "[a-z]+"), (1, "if")])
+            .unwrap();
+        let matches: Vec<_> = dfa.find_overlapping_iter(b"if").collect();
+        assert_eq!(matches, vec![(0, 1), (1, 2), (0, 2)]);
+
+        let err = Builder::new()
+            .build_many_prioritized(&[(0, "[a-z]+"), (1, "if")])
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::Unsupported(_) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
     }
 
-    /// Apply best effort heuristics to shrink the NFA at the expense of more
-    /// time/memory.
-    ///
-    /// This may be exposed in the future, but for now is exported for use in
-    /// the `regex-automata-debug` tool.
-    #[doc(hidden)]
-    pub fn shrink(&mut self, yes: bool) -> &mut Builder {
-        self.nfa.shrink(yes);
-        self
+    #[test]
+    fn anchored_start_state_survives_conversion() {
+        let mut builder = Builder::new();
+        builder.anchored_start_state(true);
+        let dfas = vec![
+            builder.build("[a-z]+[0-9]").unwrap(),
+            builder.minimize(true).build("[a-z]+[0-9]").unwrap(),
+            builder.premultiply(false).build("[a-z]+[0-9]").unwrap(),
+        ];
+        for dfa in dfas {
+            assert!(dfa.has_anchored_start_state());
+            let bytes = dfa.to_bytes_native_endian().unwrap();
+            let loaded: DenseDFA<&[usize], usize> =
+                DenseDFA::try_from_bytes(&bytes).unwrap();
+            let small = dfa.to_u16().unwrap();
+            for &(start, anchored, expected) in &[
+                (0, false, Some(6)),
+                (0, true, None),
+                (3, true, Some(6)),
+                (4, true, Some(6)),
+                (5, true, None),
+            ] {
+                let haystack = b"12 ab3";
+                assert_eq!(expected, dfa.search_at(haystack, start, anchored));
+                assert_eq!(
+                    expected,
+                    loaded.search_at(haystack, start, anchored)
+                );
+                assert_eq!(
+                    expected,
+                    small.search_at(haystack, start, anchored)
+                );
+            }
+        }
+
+        let dfa = Builder::new().build("[a-z]+[0-9]").unwrap();
+        assert!(!dfa.has_anchored_start_state());
+        assert!(!dfa.to_text().contains("anchored start"));
     }
-}
 
-#[cfg(feature = "std")]
-impl Default for Builder {
-    fn default() -> Builder {
-        Builder::new()
+    #[test]
+    fn starts_for_each_pattern_agree_with_single_patterns() {
+        let patterns = &["[a-z]+[0-9]", r"(?-u:\b)foo(?-u:\b)", "fo+", "a*"];
+        let haystacks: &[&[u8]] = &[b"", b"ab3 foo", b"xfoo fooo", b"aa9"];
+        let mut builder = Builder::new();
+        builder.starts_for_each_pattern(true);
+        let dfas = vec![
+            builder.build_many(patterns).unwrap(),
+            builder.minimize(true).build_many(patterns).unwrap(),
+            builder.premultiply(false).build_many(patterns).unwrap(),
+            builder.reduce(true).anchored(true).build_many(patterns).unwrap(),
+        ];
+        for dfa in dfas {
+            assert!(dfa.has_starts_for_each_pattern());
+            let bytes = dfa.to_bytes_native_endian().unwrap();
+            let loaded: DenseDFA<&[usize], usize> =
+                DenseDFA::try_from_bytes(&bytes).unwrap();
+            let small = dfa.to_u16().unwrap();
+            for (pattern, &p) in patterns.iter().enumerate() {
+                let single = Builder::new()
+                    .anchored_start_state(true)
+                    .build(p)
+                    .unwrap();
+                for &haystack in haystacks {
+                    for start in 0..haystack.len() + 1 {
+                        let expected = single.search_at(haystack, start, true);
+                        let got =
+                            dfa.search_pattern_at(pattern, haystack, start);
+                        assert_eq!(expected, got, "{:?} at {}", p, start);
+                        assert_eq!(
+                            expected,
+                            loaded.search_pattern_at(pattern, haystack, start)
+                        );
+                        assert_eq!(
+                            expected,
+                            small.search_pattern_at(pattern, haystack, start)
+                        );
+                    }
+                }
+            }
+        }
+
+        let dfa = Builder::new().build_many(patterns).unwrap();
+        assert!(!dfa.has_starts_for_each_pattern());
     }
-}
 
-/// Return the given byte as its escaped string form.
-#[cfg(feature = "std")]
-fn escape(b: u8) -> String {
-    use std::ascii;
+    #[test]
+    fn starts_for_each_pattern_reverse() {
+        let patterns = &["[a-z]+[0-9]", "[0-9]+", "[a-z]+"];
+        let dfa = Builder::new()
+            .starts_for_each_pattern(true)
+            .reverse(true)
+            .anchored(true)
+            .longest_match(true)
+            .build_many(patterns)
+            .unwrap();
+        let repr = dfa.repr();
+        for &(haystack, expected) in &[
+            (&b"ab3"[..], [true, false, false]),
+            (&b"123"[..], [false, true, false]),
+            (&b"a1b"[..], [false, false, false]),
+        ] {
+            for (pattern, &is_match) in expected.iter().enumerate() {
+                let mut state =
+                    repr.pattern_start_state_after(pattern, None).unwrap();
+                for &b in haystack.iter().rev() {
+                    state = dfa.next_state(state, b);
+                }
+                assert_eq!(is_match, dfa.is_match_state(state));
+            }
+        }
+    }
 
-    String::from_utf8(ascii::escape_default(b).collect::<Vec<_>>()).unwrap()
-}
+    #[test]
+    fn pattern_tags_survive_serialization() {
+        let patterns = &[
+            (!0, "[0-9]+"),
+            (0, "[a-z]+"),
+            (0x1234_5678_9ABC_DEF0, "[A-Z]+"),
+        ];
+        let mut builder = Builder::new();
+        builder.anchored(true);
+        let dfas = vec![
+            builder.build_many_tagged(patterns).unwrap(),
+            builder.minimize(true).build_many_tagged(patterns).unwrap(),
+            builder.premultiply(false).build_many_tagged(patterns).unwrap(),
+        ];
+        for dfa in dfas {
+            assert!(dfa.has_pattern_tags());
+            let bytes = dfa.to_bytes_native_endian().unwrap();
+            let loaded: DenseDFA<&[usize], usize> =
+                DenseDFA::try_from_bytes(&bytes).unwrap();
+            let small = dfa.to_u8().unwrap();
+            let small_bytes = small.to_bytes_native_endian().unwrap();
+            let small_loaded: DenseDFA<&[u8], u8> =
+                DenseDFA::try_from_bytes(&small_bytes).unwrap();
+            for &(haystack, tag) in &[
+                (&b"42"[..], !0),
+                (&b"abc"[..], 0),
+                (&b"XYZ"[..], 0x1234_5678_9ABC_DEF0),
+            ] {
+                let (pattern, _) = dfa.find_pattern(haystack).unwrap();
+                assert_eq!(Some(tag), dfa.pattern_tag(pattern));
+                assert_eq!(Some(tag), loaded.pattern_tag(pattern));
+                assert_eq!(Some(tag), small.pattern_tag(pattern));
+                assert_eq!(Some(tag), small_loaded.pattern_tag(pattern));
+            }
+            assert_eq!(None, dfa.pattern_tag(3));
+        }
 
-#[cfg(all(test, feature = "std"))]
-mod tests {
-    use super::*;
+        let dfa = Builder::new().build_many(&["[0-9]+", "[a-z]+"]).unwrap();
+        assert_eq!(None, dfa.pattern_tag(0));
+        assert!(!dfa.has_pattern_tags());
+    }
 
     #[test]
-    fn errors_when_converting_to_smaller_dfa() {
-        let pattern = r"\w{10}";
+    #[should_panic]
+    fn anchored_search_requires_anchored_start_state() {
+        let dfa = Builder::new().build("a").unwrap();
+        dfa.search_at(b"a", 0, true);
+    }
+
+    #[test]
+    fn leftmost_longest_unanchored() {
+        let patterns = &[
+            "a|ab",
+            "ab|bcdef",
+            "abcd|bc",
+            "a+?",
+            "[a-z]+[0-9]?",
+            "(foo|foobar)(baz)?",
+            "\\w+",
+            "x*",
+            "β+|ββγ",
+        ];
+        let haystacks = &[
+            "",
+            "ab",
+            "abcdef",
+            "xxabcd",
+            "zzz123",
+            "foobarbaz foobaz",
+            "ββββγ ββγ",
+            "12 34",
+        ];
+        for &pattern in patterns {
+            let unanchored = Builder::new()
+                .match_kind(MatchKind::LeftmostLongest)
+                .build(pattern)
+                .unwrap();
+            let anchored = Builder::new()
+                .anchored(true)
+                .match_kind(MatchKind::LeftmostLongest)
+                .build(pattern)
+                .unwrap();
+            for &haystack in haystacks {
+                let haystack = haystack.as_bytes();
+                // The leftmost longest match is the longest match at the
+                // earliest position at which any match starts.
+                let expected = (0..haystack.len() + 1)
+                    .filter_map(|s| {
+                        anchored.find(&haystack[s..]).map(|e| s + e)
+                    })
+                    .next();
+                assert_eq!(
+                    expected,
+                    unanchored.find(haystack),
+                    "pattern: {:?}, haystack: {:?}",
+                    pattern,
+                    haystack,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leftmost_longest_patterns() {
         let dfa = Builder::new()
-            .byte_classes(false)
-            .anchored(true)
-            .premultiply(false)
-            .build_with_size::<u16>(pattern)
+            .match_kind(MatchKind::LeftmostLongest)
+            .build_many(&["[a-z]+", "int", "[a-z]+[0-9]"])
             .unwrap();
-        assert!(dfa.to_u8().is_err());
+        assert_eq!(Some((0, 3)), dfa.find_pattern(b"int"));
+        assert_eq!(Some((2, 4)), dfa.find_pattern(b"int9"));
+        assert_eq!(Some((0, 6)), dfa.find_pattern(b"12 int"));
+
+        let dfa = Builder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build_match_only("a+|b")
+            .unwrap();
+        assert_eq!(Some(2), dfa.shortest_match(b"xab"));
     }
 
     #[test]
-    fn errors_when_determinization_would_overflow() {
-        let pattern = r"\w{10}";
+    fn sized_dfa() {
+        let dfa = Builder::new().build_sized("foo[0-9]+").unwrap();
+        assert_eq!(1, dfa.state_id_size());
+        assert_eq!(Some(8), dfa.find(b"foo12345"));
+        assert_eq!(None, dfa.find(b"foo"));
+
+        let pattern = "(?-u)[a-z]{300}";
+        let big = Builder::new().build(pattern).unwrap();
+        let small = big.to_smallest().unwrap();
+        assert_eq!(2, small.state_id_size());
+        assert_eq!(big.state_count(), small.state_count());
+        assert!(small.memory_usage() < big.memory_usage());
+        let haystack = vec![b'a'; 400];
+        assert_eq!(big.find(&haystack), small.find(&haystack));
+        assert_eq!(big.find(&haystack[..299]), small.find(&haystack[..299]));
 
         let mut builder = Builder::new();
-        builder.byte_classes(false).anchored(true).premultiply(false);
-        // using u16 is fine
-        assert!(builder.build_with_size::<u16>(pattern).is_ok());
-        // // ... but u8 results in overflow (because there are >256 states)
-        assert!(builder.build_with_size::<u8>(pattern).is_err());
+        assert!(builder.state_id_size(Some(1)).build_sized(pattern).is_err());
+        let forced = builder.state_id_size(Some(4)).build_sized(pattern);
+        assert_eq!(4, forced.unwrap().state_id_size());
     }
 
+    #[cfg(target_pointer_width = "64")]
     #[test]
-    fn errors_when_premultiply_would_overflow() {
-        let pattern = r"[a-z]";
+    fn sized_dfa_u64() {
+        let pattern = "foo[0-9]+";
+        let dfa = Builder::new()
+            .state_id_size(Some(8))
+            .build_sized(pattern)
+            .unwrap();
+        assert_eq!(8, dfa.state_id_size());
+        assert_eq!(Some(8), dfa.find(b"foo12345"));
+
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        assert_eq!(8, bytes::peek(&bytes).unwrap().state_id_size());
+        let wide: DenseDFA<&[u64], u64> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(Some(8), wide.find(b"foo12345"));
+        let narrow: result::Result<DenseDFA<&[u32], u32>, _> =
+            DenseDFA::try_from_bytes(&bytes);
+        assert!(narrow.is_err());
+    }
 
+    #[test]
+    fn accelerated_states() {
+        let pattern = r"(?-u).*error.*|[^x]*x";
         let mut builder = Builder::new();
-        builder.byte_classes(false).anchored(true).premultiply(false);
-        // without premultiplication is OK
-        assert!(builder.build_with_size::<u8>(pattern).is_ok());
-        // ... but with premultiplication overflows u8
-        builder.premultiply(true);
-        assert!(builder.build_with_size::<u8>(pattern).is_err());
+        builder.allow_invalid_utf8(true);
+        let dfa = builder.build(r#"(?-u)[^"]*""#).unwrap();
+        assert_eq!(b"\"", dfa.accelerator(dfa.start_state()));
+
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"error",
+            b"some text with an error in it",
+            b"\n\n\nerr error\nx",
+            b"no match here",
+        ];
+        let fast = builder.build(pattern).unwrap();
+        let slow = builder.accelerate(false).build(pattern).unwrap();
+        assert!(fast.repr().accels.len() > 0);
+        let bytes = fast.to_u16().unwrap().to_bytes_native_endian().unwrap();
+        let loaded: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(fast.repr().accels.len(), loaded.repr().accels.len());
+        let sparse = fast.to_sparse().unwrap();
+        for &haystack in haystacks {
+            for start in 0..haystack.len() + 1 {
+                let expected = slow.find_at(haystack, start);
+                assert_eq!(expected, fast.find_at(haystack, start));
+                assert_eq!(expected, loaded.find_at(haystack, start));
+                assert_eq!(expected, sparse.find_at(haystack, start));
+                assert_eq!(
+                    slow.shortest_match_at(haystack, start),
+                    fast.shortest_match_at(haystack, start),
+                );
+                assert_eq!(
+                    slow.is_match_at(haystack, start),
+                    sparse.is_match_at(haystack, start),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn union_of_nothing_never_matches() {
+        let dfa = Builder::new().build_union(&[]).unwrap();
+        assert!(!dfa.is_match(b""));
+        assert!(!dfa.is_match(b"a"));
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut dfa = Builder::new()
+            .anchored_start_state(true)
+            .build_many(&[r"[a-z]+[0-9]", r"[0-9]+"])
+            .unwrap();
+        let expected = dfa.find(b"abc1 23");
+        dfa.shrink_to_fit();
+        {
+            let repr = dfa.repr();
+            assert_eq!(repr.trans.len(), repr.trans.capacity());
+            assert_eq!(repr.matches.len(), repr.matches.capacity());
+            assert_eq!(repr.starts.len(), repr.starts.capacity());
+            assert_eq!(repr.tags.len(), repr.tags.capacity());
+        }
+        assert_eq!(expected, dfa.find(b"abc1 23"));
+
+        let mut sized = Builder::new().build_sized(r"[a-z]+[0-9]").unwrap();
+        sized.shrink_to_fit();
+        assert_eq!(Some(4), sized.find(b"abc1"));
     }
 
     // let data = ::std::fs::read_to_string("/usr/share/dict/words").unwrap();
@@ -1,10 +1,20 @@
 use std::collections::HashMap;
 use std::mem;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use regex_syntax::is_word_byte;
+
+use byteorder::{ByteOrder, LittleEndian};
+use bytes::{crc32, read_varu64, write_varu64};
+use classes::{ByteClasses, ByteSet};
 use dense;
-use error::Result;
-use nfa::{self, NFA};
+use error::{DeserializeError, Error, Result};
+use nfa::{self, Look, PatternID, NFA};
 use sparse_set::SparseSet;
 use state_id::{dead_id, StateID};
 
@@ -30,8 +40,7 @@ pub(crate) struct Determinizer<'a, S: StateID> {
     /// The DFA we're building.
     dfa: DFARepr<S>,
     /// Each DFA state being built is defined as an *ordered* set of NFA
-    /// states, along with a flag indicating whether the state is a match
-    /// state or not.
+    /// states, along with the patterns that match in that state, if any.
     ///
     /// This is never empty. The first state is always a dummy state such that
     /// a state id == 0 corresponds to a dead state.
@@ -39,23 +48,307 @@ pub(crate) struct Determinizer<'a, S: StateID> {
     /// A cache of DFA states that already exist and can be easily looked up
     /// via ordered sets of NFA states.
     cache: HashMap<Rc<State>, S>,
-    /// Scratch space for a stack of NFA states to visit, for depth first
-    /// visiting without recursion.
-    stack: Vec<nfa::StateID>,
-    /// Scratch space for storing an ordered sequence of NFA states, for
-    /// amortizing allocation.
-    scratch_nfa_states: Vec<nfa::StateID>,
-    /// Whether to build a DFA that finds the longest possible match.
+    /// How to compute the DFA state reached from another DFA state.
+    step: Step<'a>,
+    /// Whether to add a separate start state for anchored searches.
+    anchored_start: bool,
+    /// Whether to add a start state for anchored searches of each pattern.
+    starts_for_each_pattern: bool,
+    /// Whether to build an anchored DFA, even if the NFA is unanchored.
+    anchored: bool,
+    /// When set, determinization stops once this deadline has passed.
+    deadline: Option<Instant>,
+    /// When triggered, determinization fails with an error.
+    cancel: Cancel,
+    /// When set, determinization fails once the approximate memory used by
+    /// the DFA and the states being built exceeds this many bytes.
+    size_limit: Option<usize>,
+    /// The approximate memory used by the DFA and the states being built,
+    /// in bytes.
+    memory_usage: usize,
+    /// The bytes on which a search quits. Every transition on one of these
+    /// bytes leads to the dead state.
+    quit: ByteSet,
+    /// Whether Unicode word boundaries are compiled as ASCII word
+    /// boundaries, with every non-ASCII byte as a quit byte.
+    unicode_word_boundary: bool,
+    /// Whether to compute the transitions of many DFA states at once, using
+    /// a pool of threads.
+    parallel: bool,
+    /// When building with a cache, the cache of a previous build.
+    prior: Option<&'a BuildCache>,
+    /// How to reuse the transitions of the previous build, when its cache
+    /// was built with the same options as this build.
+    reuse: Option<Reuse<'a>>,
+    /// When building with a cache, the DFA state reached by each transition
+    /// computed so far, with one row of `alphabet_len + 1` entries for each
+    /// DFA state, the last of which is for the end of the input. Entries of
+    /// transitions that weren't computed are `NONE`.
+    record: Vec<usize>,
+}
+
+/// The configuration needed to compute the DFA state reached from another
+/// DFA state.
+///
+/// This is kept apart from the rest of the determinizer, which is only ever
+/// used by one thread, such that it can be shared by the threads that
+/// compute transitions in parallel.
+#[derive(Debug)]
+struct Step<'a> {
+    /// The NFA we're converting into a DFA.
+    nfa: &'a NFA,
+    /// Whether to build a DFA that finds the leftmost longest match.
     longest_match: bool,
+    /// Whether to build a DFA that keeps every NFA state after a match, such
+    /// that it sees every match.
+    all_matches: bool,
+    /// When finding the leftmost longest match in an unanchored DFA, this
+    /// records which NFA states make up the unanchored prefix. Otherwise,
+    /// this is empty.
+    prefix: Vec<bool>,
+    /// Whether to build a DFA that only reports whether a match exists.
+    match_only: bool,
+    /// Whether the NFA has look-around assertions, in which case the DFA
+    /// delays every match by one byte. See `State::look_behind`.
+    lookaround: bool,
+}
+
+/// Scratch space used while computing DFA states, for amortizing
+/// allocation. Each thread computing transitions needs its own.
+#[derive(Debug)]
+struct Scratch {
+    /// A set of NFA states, with enough capacity to hold every NFA state.
+    sparse: SparseSet,
+    /// A stack of NFA states to visit, for depth first visiting without
+    /// recursion.
+    stack: Vec<nfa::StateID>,
+    /// An ordered sequence of NFA states, whose allocation is reused by the
+    /// next DFA state built.
+    nfa_states: Vec<nfa::StateID>,
+}
+
+/// A DFA state reached by a transition computed in parallel.
+#[cfg(feature = "parallel")]
+enum Successor {
+    /// The transition was reused from a previous build, and leads to the
+    /// DFA state with this index in its cache.
+    Reused(usize),
+    /// The transition was computed, and leads to this DFA state.
+    Computed(State),
+}
+
+/// The number of DFA states whose transitions are computed together when
+/// determinizing in parallel. This bounds the memory used by DFA states
+/// that are computed but not yet added to the DFA, and how long it takes to
+/// notice that building was cancelled.
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK: usize = 256;
+
+/// A condition under which building a DFA is abandoned, as configured with
+/// `dense::Builder::time_limit` and `dense::Builder::cancel_flag`.
+///
+/// This is checked periodically during determinization and minimization.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Cancel {
+    /// When set, building is abandoned once this deadline has passed.
+    pub deadline: Option<Instant>,
+    /// When set, building is abandoned once this flag is set to true.
+    pub flag: Option<Arc<AtomicBool>>,
+}
+
+impl Cancel {
+    /// Returns true if and only if building should be abandoned.
+    pub fn is_cancelled(&self) -> bool {
+        if let Some(ref flag) = self.flag {
+            if flag.load(Ordering::Relaxed) {
+                return true;
+            }
+        }
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
 }
 
 /// An intermediate representation for a DFA state during determinization.
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 struct State {
-    /// Whether this state is a match state or not.
-    is_match: bool,
+    /// The patterns that match in this state, in order of priority. This is
+    /// empty if and only if this state is not a match state.
+    matches: Vec<PatternID>,
     /// An ordered sequence of NFA states that make up this DFA state.
     nfa_states: Vec<nfa::StateID>,
+    /// When finding the leftmost longest match in an unanchored DFA, the NFA
+    /// states are grouped by the position at which their match would start,
+    /// from earliest to latest. Each element is the end of a group in
+    /// `nfa_states`, and every NFA state following the last group is part of
+    /// the unanchored prefix. Otherwise, this is empty.
+    groups: Vec<usize>,
+    /// When the NFA has look-around assertions, the assertions satisfied by
+    /// the byte preceding this state, as a set of `LOOK_*` flags.
+    ///
+    /// In that case, whether an assertion holds can only be decided once
+    /// the byte following it is known. So a DFA state contains every NFA
+    /// state reached before the assertions at the current position are
+    /// evaluated, including `Look` and `Match` states, and its transition on
+    /// the next byte first resolves the assertions, then records the
+    /// patterns that match at the current position and finally steps over
+    /// the byte. The matches of a DFA state are therefore those that end
+    /// one byte before it.
+    ///
+    /// This is always zero when none of the NFA states is a `Look` state,
+    /// since the preceding byte can't matter then.
+    look_behind: u8,
+}
+
+/// Set in the look-behind of a DFA state when the preceding byte is an ASCII
+/// word byte.
+const LOOK_WORD: u8 = 1 << 0;
+/// Set in the look-behind of a DFA state at the beginning of the input.
+const LOOK_START_TEXT: u8 = 1 << 1;
+/// Set in the look-behind of a DFA state when the preceding byte is `\n`.
+const LOOK_LF: u8 = 1 << 2;
+/// Set in the look-behind of a DFA state when the preceding byte is `\r`.
+const LOOK_CR: u8 = 1 << 3;
+
+/// The label at the beginning of every serialized build cache.
+const BUILD_CACHE_LABEL: &[u8] = b"rust-regex-automata-build-cache\x00";
+
+/// The version of the format used to serialize build caches.
+const BUILD_CACHE_VERSION: u16 = 1;
+
+/// Set in the flags of a build cache when it was built for the leftmost
+/// longest match.
+const CACHE_LONGEST_MATCH: u8 = 1 << 0;
+/// Set in the flags of a build cache when it was built to see every match.
+const CACHE_ALL_MATCHES: u8 = 1 << 1;
+/// Set in the flags of a build cache when it was built to only report
+/// whether a match exists.
+const CACHE_MATCH_ONLY: u8 = 1 << 2;
+/// Set in the flags of a build cache when its NFA has look-around
+/// assertions.
+const CACHE_LOOKAROUND: u8 = 1 << 3;
+/// Set in the flags of a build cache when its NFA is reversed.
+const CACHE_REVERSE: u8 = 1 << 4;
+
+/// The entry of a transition that wasn't computed in a build cache, and
+/// of a state without a counterpart when reusing one.
+const NONE: usize = !0;
+
+/// A cache of the subset construction performed when building a dense DFA,
+/// which makes it cheaper to build a DFA again from a similar set of
+/// patterns.
+///
+/// The cache records every DFA state built, as a set of NFA states, along
+/// with the transitions computed between them. When a DFA is built with
+/// [`Builder::build_many_with_cache`](struct.Builder.html#method.build_many_with_cache)
+/// or
+/// [`Builder::build_from_nfa_with_cache`](struct.Builder.html#method.build_from_nfa_with_cache),
+/// the transitions of the previous build are reused instead of being
+/// computed again, and the cache is then replaced by the cache of the new
+/// build. A cache can be written to bytes with
+/// [`to_bytes`](struct.BuildCache.html#method.to_bytes), such that it can
+/// be kept between runs of a program.
+///
+/// A transition is only reused when every NFA state it depends on is the
+/// same in both builds, which is checked by comparing the two NFAs state by
+/// state. With a cache written by a previous build, the DFA built is exactly
+/// the DFA that would have been built without a cache, so only how long the
+/// build takes changes. How much work is saved depends on how the NFA
+/// changed:
+///
+/// * Building the same patterns again with the same options reuses every
+///   transition.
+/// * Adding a pattern at the end of an anchored DFA reuses most transitions
+///   within the previous patterns, since their NFA states keep their
+///   identifiers.
+/// * Changing or adding a pattern of an unanchored DFA reuses little, since
+///   every state of an unanchored DFA may start a match of any pattern.
+///   Changing a pattern also changes the identifiers of the NFA states of
+///   every pattern following it.
+///
+/// The options that change how transitions are computed, such as the match
+/// semantics, must also be the same. When they aren't, nothing is reused.
+///
+/// Reused transitions are taken from the cache as is, without computing them
+/// again, since checking them would cost as much as computing them. A stale
+/// cache, written by a build from other patterns, is safe to use, because
+/// its NFA is compared with the current one. But when reading a cache with
+/// [`from_bytes_unchecked`](struct.BuildCache.html#method.from_bytes_unchecked),
+/// only its checksum is verified, which guards against accidental
+/// corruption and nothing else. A cache crafted to pass the checksum can
+/// make a build produce a DFA that matches something else entirely, so
+/// caches from untrusted sources must never be loaded.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense, DFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let mut builder = dense::Builder::new();
+/// builder.anchored(true);
+/// let mut cache = dense::BuildCache::new();
+///
+/// builder.build_many_with_cache(&["[a-z]+", "[0-9]+"], &mut cache)?;
+/// let bytes = cache.to_bytes();
+///
+/// // Later, possibly in another process.
+/// let mut cache = dense::BuildCache::from_bytes_unchecked(&bytes)
+///     .unwrap();
+/// let patterns = &["[a-z]+", "[0-9]+", "[A-Z]+"];
+/// let dfa = builder.build_many_with_cache(patterns, &mut cache)?;
+/// assert!(cache.reused_transitions() > 0);
+/// assert_eq!(Some(3), dfa.find(b"XYZ"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct BuildCache {
+    /// The options of the build that change how transitions are computed,
+    /// as a set of `CACHE_*` flags.
+    flags: u8,
+    /// The states of the NFA the DFA was built from.
+    nfa: Vec<nfa::State>,
+    /// Which NFA states make up the unanchored prefix, as computed by the
+    /// determinizer. This is empty when they weren't needed.
+    prefix: Vec<bool>,
+    /// The equivalence classes of bytes used by the DFA.
+    classes: ByteClasses,
+    /// Every DFA state built, indexed by its identifier before match states
+    /// were shuffled to the beginning of the DFA.
+    states: Vec<State>,
+    /// The index in `states` of the state reached by each transition, with
+    /// one row of `alphabet_len + 1` entries for each state, the last of
+    /// which is for the end of the input. Entries of transitions that
+    /// weren't computed are `NONE`.
+    trans: Vec<usize>,
+    /// The number of transitions reused from the previous cache when this
+    /// one was built.
+    reused: usize,
+}
+
+/// The transitions of a previous build that can be reused by the current
+/// build, along with the correspondence between the DFA states of both.
+#[derive(Debug)]
+struct Reuse<'a> {
+    /// The cache of the previous build.
+    prior: &'a BuildCache,
+    /// A map from each DFA state of the previous build to its index in the
+    /// cache.
+    index: HashMap<&'a State, usize>,
+    /// Whether the transitions of each DFA state of the previous build can
+    /// be reused, which is when none of the NFA states they depend on
+    /// changed.
+    reusable: Vec<bool>,
+    /// The index of the DFA state of the previous build corresponding to each
+    /// DFA state of the current build, or `NONE`.
+    new_to_old: Vec<usize>,
+    /// The identifier of the DFA state of the current build corresponding to
+    /// each DFA state of the previous build, or `NONE`.
+    old_to_new: Vec<usize>,
+    /// The number of transitions reused so far.
+    reused: usize,
 }
 
 impl<'a, S: StateID> Determinizer<'a, S> {
@@ -67,29 +360,173 @@ impl<'a, S: StateID> Determinizer<'a, S> {
 
         Determinizer {
             nfa,
-            dfa: DFARepr::empty().anchored(nfa.is_anchored()),
+            dfa: DFARepr::empty()
+                .anchored(nfa.is_anchored())
+                .patterns(nfa.pattern_count())
+                .utf8(nfa.is_utf8()),
             builder_states: vec![dead],
             cache,
-            stack: vec![],
-            scratch_nfa_states: vec![],
-            longest_match: false,
+            step: Step {
+                nfa,
+                longest_match: false,
+                all_matches: false,
+                prefix: vec![],
+                match_only: false,
+                lookaround: false,
+            },
+            anchored_start: false,
+            starts_for_each_pattern: false,
+            anchored: nfa.is_anchored(),
+            deadline: None,
+            cancel: Cancel::default(),
+            size_limit: None,
+            memory_usage: 0,
+            quit: ByteSet::empty(),
+            unicode_word_boundary: false,
+            parallel: false,
+            prior: None,
+            reuse: None,
+            record: vec![],
         }
     }
 
     /// Instruct the determinizer to use equivalence classes as the transition
     /// alphabet instead of all possible byte values.
-    pub fn with_byte_classes(mut self) -> Determinizer<'a, S> {
-        let byte_classes = self.nfa.byte_classes().clone();
+    ///
+    /// Each quit byte gets its own equivalence class, so quit bytes must be
+    /// set before calling this.
+    pub fn with_byte_classes(self) -> Determinizer<'a, S> {
+        let byte_classes = self.nfa.byte_classes().with_singletons(&self.quit);
+        self.with_given_byte_classes(byte_classes)
+    }
+
+    /// Instruct the determinizer to use the given equivalence classes as the
+    /// transition alphabet. The classes must refine those of the NFA, with
+    /// each quit byte in its own class.
+    pub fn with_given_byte_classes(
+        mut self,
+        byte_classes: ByteClasses,
+    ) -> Determinizer<'a, S> {
         self.dfa = DFARepr::empty_with_byte_classes(byte_classes)
-            .anchored(self.nfa.is_anchored());
+            .anchored(self.anchored)
+            .patterns(self.nfa.pattern_count())
+            .utf8(self.nfa.is_utf8())
+            .quit(self.quit);
+        self
+    }
+
+    /// Instruct the determinizer to make every transition on one of the
+    /// given bytes lead to the dead state, such that a search quits as soon
+    /// as it sees one of them.
+    pub fn quit(mut self, quit: ByteSet) -> Determinizer<'a, S> {
+        self.quit = quit;
+        let dfa = mem::replace(&mut self.dfa, DFARepr::empty());
+        self.dfa = dfa.quit(quit);
+        self
+    }
+
+    /// Instruct the determinizer to compile Unicode word boundaries as ASCII
+    /// word boundaries. If the NFA contains any, then every non-ASCII byte is
+    /// added to the quit bytes, so this must be called after `quit`.
+    pub fn unicode_word_boundary(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.unicode_word_boundary = yes;
+        if yes && self.nfa.has_unicode_look() {
+            let mut quit = self.quit;
+            for b in 0x80..=0xFF {
+                quit.add(b);
+            }
+            self = self.quit(quit);
+        }
+        self
+    }
+
+    /// Instruct the determinizer to build an anchored DFA. When the NFA is
+    /// unanchored, this builds the DFA from the NFA's anchored start state,
+    /// which skips its unanchored prefix. This has no effect when the NFA is
+    /// anchored.
+    pub fn anchored(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.anchored = yes || self.nfa.is_anchored();
+        let dfa = mem::replace(&mut self.dfa, DFARepr::empty());
+        self.dfa = dfa.anchored(self.anchored);
         self
     }
 
-    /// Instruct the determinizer to build a DFA that recognizes the longest
-    /// possible match instead of the leftmost first match. This is useful when
+    /// Instruct the determinizer to build a DFA that recognizes the leftmost
+    /// longest match instead of the leftmost first match. This is useful when
     /// constructing reverse DFAs for finding the start of a match.
     pub fn longest_match(mut self, yes: bool) -> Determinizer<'a, S> {
-        self.longest_match = yes;
+        self.step.longest_match = yes;
+        self
+    }
+
+    /// Instruct the determinizer to build a DFA that never drops an NFA state
+    /// because of a match, such that every match can be reported. This takes
+    /// precedence over `longest_match`.
+    pub fn all_matches(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.step.all_matches = yes;
+        self
+    }
+
+    /// Instruct the determinizer to build a DFA that is only capable of
+    /// reporting whether a match exists or not. Once a match is seen, all
+    /// other NFA states are dropped, which makes every match state for the
+    /// same pattern identical. All such match states are therefore collapsed
+    /// into a single state whose transitions all lead to the dead state.
+    pub fn match_only(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.step.match_only = yes;
+        self
+    }
+
+    /// Instruct the determinizer to add a second start state for anchored
+    /// searches, in addition to the usual start state. This has no effect
+    /// when the NFA is anchored.
+    pub fn anchored_start(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.anchored_start = yes;
+        self
+    }
+
+    /// Instruct the determinizer to add a start state for anchored searches
+    /// of each pattern, such that a search from it only matches that
+    /// pattern.
+    pub fn starts_for_each_pattern(
+        mut self,
+        yes: bool,
+    ) -> Determinizer<'a, S> {
+        self.starts_for_each_pattern = yes;
+        self
+    }
+
+    /// Instruct the determinizer to stop once the given deadline has passed.
+    /// This only has an effect when building a DFA with `build_partial`.
+    pub fn deadline(mut self, deadline: Instant) -> Determinizer<'a, S> {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Instruct the determinizer to fail with an error once the given
+    /// cancellation condition is triggered.
+    pub fn cancel(mut self, cancel: Cancel) -> Determinizer<'a, S> {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Instruct the determinizer to fail once the approximate memory used by
+    /// the DFA being built exceeds the given number of bytes.
+    pub fn size_limit(mut self, limit: Option<usize>) -> Determinizer<'a, S> {
+        self.size_limit = limit;
+        self
+    }
+
+    /// Instruct the determinizer to compute the transitions of many DFA
+    /// states at once, using the global pool of threads of `rayon`.
+    ///
+    /// Uncompiled DFA states are then visited breadth first instead of depth
+    /// first, so state identifiers are assigned in a different order than
+    /// without this. They are still assigned deterministically, regardless
+    /// of the number of threads.
+    #[cfg(feature = "parallel")]
+    pub fn parallel(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.parallel = yes;
         self
     }
 
@@ -97,39 +534,359 @@ impl<'a, S: StateID> Determinizer<'a, S> {
     /// the chosen state identifier representation is too small), then an error
     /// is returned.
     pub fn build(mut self) -> Result<DFARepr<S>> {
-        let representative_bytes: Vec<u8> =
-            self.dfa.byte_classes().representatives().collect();
-        let mut sparse = self.new_sparse_set();
-        let mut uncompiled = vec![self.add_start(&mut sparse)?];
-        while let Some(dfa_id) = uncompiled.pop() {
-            for &b in &representative_bytes {
-                let (next_dfa_id, is_new) =
-                    self.cached_state(dfa_id, b, &mut sparse)?;
-                self.dfa.add_transition(dfa_id, b, next_dfa_id);
+        self.deadline = None;
+        self.build_partial().map(|(dfa, _)| dfa)
+    }
+
+    /// Build the DFA, but stop early if the deadline set on this determinizer
+    /// passes before determinization is complete.
+    ///
+    /// When determinization stops early, every transition that hasn't been
+    /// computed leads to a special "unknown" state, whose transitions all
+    /// lead back to itself. The identifier of the unknown state is returned
+    /// along with the DFA. The unknown state is never a match state and is
+    /// always the last state in the DFA.
+    pub fn build_partial(self) -> Result<(DFARepr<S>, Option<S>)> {
+        self.build_imp().map(|(dfa, unknown, _)| (dfa, unknown))
+    }
+
+    /// Build the DFA, reusing the transitions recorded in the given cache of
+    /// a previous build wherever the NFA states they depend on are
+    /// unchanged. The cache of this build is returned along with the DFA.
+    pub fn build_with_cache(
+        mut self,
+        prior: &'a BuildCache,
+    ) -> Result<(DFARepr<S>, BuildCache)> {
+        self.deadline = None;
+        self.prior = Some(prior);
+        self.build_imp().map(|(dfa, _, cache)| {
+            (dfa, cache.expect("cache of a build with a prior cache"))
+        })
+    }
+
+    /// Build the DFA as described by `build_partial`. When a prior cache is
+    /// set, the cache of this build is also returned.
+    fn build_imp(
+        mut self,
+    ) -> Result<(DFARepr<S>, Option<S>, Option<BuildCache>)> {
+        let quit = self.quit;
+        let representative_bytes: Vec<u8> = self
+            .dfa
+            .byte_classes()
+            .representatives()
+            .filter(|&b| !quit.contains(b))
+            .collect();
+        if self.step.longest_match && !self.step.all_matches && !self.anchored
+        {
+            self.step.prefix = self.unanchored_prefix();
+        }
+        if self.nfa.has_unicode_look() && !self.unicode_word_boundary {
+            return Err(Error::unsupported_word());
+        }
+        if self.nfa.has_look() {
+            self.step.lookaround = true;
+            let dfa = mem::replace(&mut self.dfa, DFARepr::empty());
+            self.dfa = dfa.lookaround(true);
+        }
+        if let Some(prior) = self.prior {
+            self.reuse = Reuse::new(prior, &self.step);
+            if let Some(ref mut reuse) = self.reuse {
+                reuse.register(&self.builder_states[0], dead_id::<S>());
+            }
+        }
+        let mut scratch = Scratch::new(self.nfa.len());
+        let mut uncompiled = vec![];
+        if self.step.lookaround {
+            let start = if self.anchored {
+                self.nfa.start_anchored()
+            } else {
+                self.nfa.start()
+            };
+            let id = self.add_lookaround_start(
+                start,
+                &mut scratch,
+                &mut uncompiled,
+            )?;
+            self.dfa.set_start_state(id);
+            if self.anchored_start && !self.anchored {
+                let id = self.add_lookaround_start(
+                    self.nfa.start_anchored(),
+                    &mut scratch,
+                    &mut uncompiled,
+                )?;
+                self.dfa.set_anchored_start_state(id);
+            }
+        } else {
+            uncompiled.push(self.add_start(&mut scratch)?);
+            if self.anchored_start && !self.anchored {
+                let (id, is_new) = self.add_anchored_start(&mut scratch)?;
                 if is_new {
-                    uncompiled.push(next_dfa_id);
+                    uncompiled.push(id);
                 }
             }
         }
+        if self.starts_for_each_pattern {
+            let mut starts = vec![];
+            for start in self.nfa.anchored_pattern_starts() {
+                let id = match start {
+                    None => dead_id(),
+                    Some(start) if self.step.lookaround => self
+                        .add_lookaround_start(
+                            start,
+                            &mut scratch,
+                            &mut uncompiled,
+                        )?,
+                    Some(start) => {
+                        let (id, is_new) =
+                            self.add_start_from(start, &mut scratch)?;
+                        if is_new {
+                            uncompiled.push(id);
+                        }
+                        id
+                    }
+                };
+                starts.push(id);
+            }
+            self.dfa.set_pattern_start_states(starts);
+        }
+        let mut inputs: Vec<Option<u8>> =
+            representative_bytes.iter().cloned().map(Some).collect();
+        if self.step.lookaround {
+            inputs.push(None);
+        }
+        let uncompiled = if self.parallel {
+            self.compile_parallel(&inputs, uncompiled, &mut scratch)?
+        } else {
+            self.compile(&inputs, uncompiled, &mut scratch)?
+        };
+
+        let mut unknown = None;
+        if !uncompiled.is_empty() {
+            let id = self.dfa.add_empty_state()?;
+            self.builder_states.push(Rc::new(State::dead()));
+            for &b in &representative_bytes {
+                self.dfa.add_transition(id, b, id);
+                for &dfa_id in &uncompiled {
+                    self.dfa.add_transition(dfa_id, b, id);
+                }
+            }
+            if self.step.lookaround {
+                self.dfa.add_eoi_transition(id, id);
+                for &dfa_id in &uncompiled {
+                    self.dfa.add_eoi_transition(dfa_id, id);
+                }
+            }
+            unknown = Some(id);
+        }
+        let cache = self.prior.map(|_| self.new_cache());
 
         // At this point, we shuffle the matching states in the final DFA to
         // the beginning. This permits a DFA's match loop to detect a match
         // condition by merely inspecting the current state's identifier, and
-        // avoids the need for any additional auxiliary storage.
-        let is_match: Vec<bool> =
-            self.builder_states.iter().map(|s| s.is_match).collect();
-        self.dfa.shuffle_match_states(&is_match);
-        Ok(self.dfa)
+        // avoids the need for any additional auxiliary storage, except for
+        // recording which patterns match when there is more than one.
+        //
+        // Note that shuffling only ever moves a non-match state if a match
+        // state appears after it, so the unknown state (which is last) never
+        // moves.
+        let matches: Vec<&[PatternID]> =
+            self.builder_states.iter().map(|s| &*s.matches).collect();
+        self.dfa.shuffle_match_states(&matches)?;
+        Ok((self.dfa, unknown, cache))
+    }
+
+    /// Return the cache of this build, which records every DFA state built
+    /// so far along with the transitions computed between them.
+    fn new_cache(&self) -> BuildCache {
+        let stride = self.dfa.byte_classes().alphabet_len() + 1;
+        let mut trans = self.record.clone();
+        trans.resize(self.builder_states.len() * stride, NONE);
+        BuildCache {
+            flags: self.step.cache_flags(),
+            nfa: (0..self.nfa.len())
+                .map(|id| self.nfa.state(id).clone())
+                .collect(),
+            prefix: self.step.prefix.clone(),
+            classes: *self.dfa.byte_classes(),
+            states: self
+                .builder_states
+                .iter()
+                .map(|s| (**s).clone())
+                .collect(),
+            trans,
+            reused: self.reuse.as_ref().map(|r| r.reused).unwrap_or(0),
+        }
+    }
+
+    /// Compute the transitions of the given uncompiled DFA states, and of
+    /// every new DFA state they lead to, on each of the given inputs, where
+    /// `None` stands for the end of the input. This visits uncompiled DFA
+    /// states depth first.
+    ///
+    /// If the deadline set on this determinizer passes before every DFA
+    /// state is compiled, then the DFA states that are left uncompiled are
+    /// returned.
+    fn compile(
+        &mut self,
+        inputs: &[Option<u8>],
+        mut uncompiled: Vec<S>,
+        scratch: &mut Scratch,
+    ) -> Result<Vec<S>> {
+        while let Some(dfa_id) = uncompiled.pop() {
+            for &input in inputs {
+                let (next_dfa_id, is_new) =
+                    self.cached_state(dfa_id, input, scratch)?;
+                self.add_transition(dfa_id, input, next_dfa_id);
+                if is_new {
+                    uncompiled.push(next_dfa_id);
+                }
+            }
+            if self.cancel.is_cancelled() {
+                return Err(Error::cancelled());
+            }
+            if self.is_past_deadline() {
+                break;
+            }
+        }
+        Ok(uncompiled)
+    }
+
+    /// Like `compile`, but visits uncompiled DFA states breadth first, and
+    /// computes the DFA states reached from several of them at once, using
+    /// the global pool of threads of `rayon`.
+    ///
+    /// Computing a DFA state from another only reads the NFA, so that's done
+    /// in parallel. Looking up each DFA state in the cache, and adding it to
+    /// the DFA when it's new, is then done by this thread, in the order of
+    /// the uncompiled DFA states and of the inputs. This is what makes the
+    /// identifiers assigned to new DFA states independent of how the work
+    /// was split between threads.
+    #[cfg(feature = "parallel")]
+    fn compile_parallel(
+        &mut self,
+        inputs: &[Option<u8>],
+        mut uncompiled: Vec<S>,
+        scratch: &mut Scratch,
+    ) -> Result<Vec<S>> {
+        let nfa_len = self.nfa.len();
+        let mut next_uncompiled = vec![];
+        while !uncompiled.is_empty() {
+            for (i, chunk) in uncompiled.chunks(PARALLEL_CHUNK).enumerate() {
+                let nexts: Vec<Vec<Successor>> = {
+                    let step = &self.step;
+                    let reuse = self.reuse.as_ref();
+                    let states: Vec<(usize, &State)> = chunk
+                        .iter()
+                        .map(|id| {
+                            let id = id.to_usize();
+                            (id, &*self.builder_states[id])
+                        })
+                        .collect();
+                    states
+                        .par_iter()
+                        .map_init(
+                            || Scratch::new(nfa_len),
+                            |scratch, &(id, state)| {
+                                inputs
+                                    .iter()
+                                    .map(|&input| {
+                                        match reuse.and_then(|r| {
+                                            r.successor(id, input)
+                                        }) {
+                                            Some(old) => {
+                                                Successor::Reused(old)
+                                            }
+                                            None => Successor::Computed(
+                                                step.successor(
+                                                    state, input, scratch,
+                                                ),
+                                            ),
+                                        }
+                                    })
+                                    .collect()
+                            },
+                        )
+                        .collect()
+                };
+                for (&dfa_id, nexts) in chunk.iter().zip(nexts) {
+                    for (&input, next) in inputs.iter().zip(nexts) {
+                        let (next_dfa_id, is_new) = match next {
+                            Successor::Reused(old) => {
+                                self.reused_state(old, scratch)?
+                            }
+                            Successor::Computed(next) => {
+                                self.cached_or_add(next, scratch)?
+                            }
+                        };
+                        self.add_transition(dfa_id, input, next_dfa_id);
+                        if is_new {
+                            next_uncompiled.push(next_dfa_id);
+                        }
+                    }
+                }
+                if self.cancel.is_cancelled() {
+                    return Err(Error::cancelled());
+                }
+                if self.is_past_deadline() {
+                    let done = (i + 1) * PARALLEL_CHUNK;
+                    let mut rest =
+                        uncompiled[done.min(uncompiled.len())..].to_vec();
+                    rest.extend(next_uncompiled);
+                    return Ok(rest);
+                }
+            }
+            uncompiled = mem::replace(&mut next_uncompiled, vec![]);
+        }
+        Ok(uncompiled)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn compile_parallel(
+        &mut self,
+        inputs: &[Option<u8>],
+        uncompiled: Vec<S>,
+        scratch: &mut Scratch,
+    ) -> Result<Vec<S>> {
+        self.compile(inputs, uncompiled, scratch)
+    }
+
+    /// Returns true if and only if a deadline is set on this determinizer
+    /// and it has passed.
+    fn is_past_deadline(&self) -> bool {
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+
+    /// Add a transition from one DFA state to another on the given byte, or
+    /// at the end of the input when the byte is `None`.
+    fn add_transition(&mut self, from: S, input: Option<u8>, to: S) {
+        match input {
+            Some(b) => self.dfa.add_transition(from, b, to),
+            None => self.dfa.add_eoi_transition(from, to),
+        }
+        if self.prior.is_some() {
+            let stride = self.dfa.byte_classes().alphabet_len() + 1;
+            let slot = match input {
+                Some(b) => self.dfa.byte_classes().get(b) as usize,
+                None => stride - 1,
+            };
+            let row = from.to_usize() * stride;
+            if self.record.len() < row + stride {
+                self.record.resize(row + stride, NONE);
+            }
+            self.record[row + slot] = to.to_usize();
+        }
     }
 
     /// Return the identifier for the next DFA state given an existing DFA
-    /// state and an input byte. If the next DFA state already exists, then
-    /// return its identifier from the cache. Otherwise, build the state, cache
-    /// it and return its identifier.
+    /// state and an input byte, or `None` for the end of the input. If the
+    /// next DFA state already exists, then return its identifier from the
+    /// cache. Otherwise, build the state, cache it and return its identifier.
     ///
-    /// The given sparse set is used for scratch space. It must have a capacity
-    /// equivalent to the total number of NFA states, but its contents are
-    /// otherwise unspecified.
+    /// The end of the input only has a transition when the NFA has
+    /// look-around assertions.
     ///
     /// This routine returns a boolean indicating whether a new state was
     /// built. If a new state is built, then the caller needs to add it to its
@@ -137,119 +894,449 @@ impl<'a, S: StateID> Determinizer<'a, S> {
     fn cached_state(
         &mut self,
         dfa_id: S,
-        b: u8,
-        sparse: &mut SparseSet,
+        input: Option<u8>,
+        scratch: &mut Scratch,
+    ) -> Result<(S, bool)> {
+        let reused = match self.reuse {
+            None => None,
+            Some(ref reuse) => reuse.successor(dfa_id.to_usize(), input),
+        };
+        if let Some(old) = reused {
+            return self.reused_state(old, scratch);
+        }
+        let state = self.builder_states[dfa_id.to_usize()].clone();
+        let next = self.step.successor(&state, input, scratch);
+        // Check if the candidate state has already been built.
+        self.cached_or_add(next, scratch)
+    }
+
+    /// Return the identifier of the given state if it has already been
+    /// built, along with `false`. Otherwise, add it and return its new
+    /// identifier along with `true`.
+    fn cached_or_add(
+        &mut self,
+        state: State,
+        scratch: &mut Scratch,
     ) -> Result<(S, bool)> {
-        sparse.clear();
-        // Compute the set of all reachable NFA states, including epsilons.
-        self.next(dfa_id, b, sparse);
-        // Build a candidate state and check if it has already been built.
-        let state = self.new_state(sparse);
         if let Some(&cached_id) = self.cache.get(&state) {
             // Since we have a cached state, put the constructed state's
             // memory back into our scratch space, so that it can be reused.
-            let _ =
-                mem::replace(&mut self.scratch_nfa_states, state.nfa_states);
+            scratch.nfa_states = state.nfa_states;
             return Ok((cached_id, false));
         }
         // Nothing was in the cache, so add this state to the cache.
         self.add_state(state).map(|s| (s, true))
     }
 
-    /// Compute the set of all eachable NFA states, including the full epsilon
-    /// closure, from a DFA state for a single byte of input.
-    fn next(&mut self, dfa_id: S, b: u8, next_nfa_states: &mut SparseSet) {
-        next_nfa_states.clear();
-        for i in 0..self.builder_states[dfa_id.to_usize()].nfa_states.len() {
-            let nfa_id = self.builder_states[dfa_id.to_usize()].nfa_states[i];
-            match *self.nfa.state(nfa_id) {
-                nfa::State::Union { .. }
-                | nfa::State::Fail
-                | nfa::State::Match => {}
-                nfa::State::Range { range: ref r } => {
-                    if r.start <= b && b <= r.end {
-                        self.epsilon_closure(r.next, next_nfa_states);
-                    }
-                }
-                nfa::State::Sparse { ref ranges } => {
-                    for r in ranges.iter() {
-                        if r.start > b {
-                            break;
-                        } else if r.start <= b && b <= r.end {
-                            self.epsilon_closure(r.next, next_nfa_states);
-                            break;
-                        }
-                    }
-                }
+    /// Return the identifier of the DFA state corresponding to the given
+    /// state of the previous build, along with `false` if it has already
+    /// been built. Otherwise, add it and return its new identifier along
+    /// with `true`.
+    fn reused_state(
+        &mut self,
+        old: usize,
+        scratch: &mut Scratch,
+    ) -> Result<(S, bool)> {
+        let state = {
+            let reuse = self.reuse.as_mut().expect("reused transition");
+            reuse.reused += 1;
+            if reuse.old_to_new[old] != NONE {
+                return Ok((S::from_usize(reuse.old_to_new[old]), false));
             }
-        }
+            reuse.prior.states[old].clone()
+        };
+        self.cached_or_add(state, scratch)
     }
 
-    /// Compute the epsilon closure for the given NFA state.
-    fn epsilon_closure(&mut self, start: nfa::StateID, set: &mut SparseSet) {
-        if !self.nfa.state(start).is_epsilon() {
-            set.insert(start);
-            return;
-        }
-
-        self.stack.push(start);
-        while let Some(mut id) = self.stack.pop() {
-            loop {
-                if set.contains(id) {
-                    break;
-                }
-                set.insert(id);
-                match *self.nfa.state(id) {
-                    nfa::State::Range { .. }
-                    | nfa::State::Sparse { .. }
-                    | nfa::State::Fail
-                    | nfa::State::Match => break,
-                    nfa::State::Union { ref alternates } => {
-                        id = match alternates.get(0) {
-                            None => break,
-                            Some(&id) => id,
-                        };
-                        self.stack.extend(alternates[1..].iter().rev());
-                    }
-                }
+    /// Add a start state for a DFA with look-around assertions, beginning
+    /// with the given NFA state, and return its identifier.
+    ///
+    /// This state is never entered by a search. Instead, its transition on
+    /// the byte preceding the search, or its transition at the end of input
+    /// when the search begins at the start of the input, leads to the state
+    /// in which the search begins. Those states are added to the given list
+    /// of uncompiled states when they are new.
+    fn add_lookaround_start(
+        &mut self,
+        start: nfa::StateID,
+        scratch: &mut Scratch,
+        uncompiled: &mut Vec<S>,
+    ) -> Result<S> {
+        let id = self.add_unique_state()?;
+        scratch.sparse.clear();
+        epsilon_closure(
+            self.nfa,
+            start,
+            &mut scratch.stack,
+            &mut scratch.sparse,
+        );
+        let nfa_states: Vec<nfa::StateID> = scratch
+            .sparse
+            .into_iter()
+            .cloned()
+            .filter(|&id| match *self.nfa.state(id) {
+                nfa::State::Union { .. } => false,
+                nfa::State::Range { .. }
+                | nfa::State::Sparse { .. }
+                | nfa::State::Look { .. }
+                | nfa::State::Fail
+                | nfa::State::Match { .. } => true,
+            })
+            .collect();
+        let has_look =
+            nfa_states.iter().any(|&id| self.nfa.state(id).is_look());
+        // When Unicode word boundaries are compiled heuristically, the word
+        // class of a non-ASCII byte is unknown, so a search that starts
+        // after one quits.
+        let heuristic =
+            self.unicode_word_boundary && self.nfa.has_unicode_look();
+        let representatives: Vec<u8> = self
+            .dfa
+            .byte_classes()
+            .representatives()
+            .filter(|&b| !heuristic || b.is_ascii())
+            .collect();
+        let mut prevs = vec![None];
+        prevs.extend(representatives.into_iter().map(Some));
+        for prev in prevs {
+            let state = State {
+                matches: vec![],
+                nfa_states: nfa_states.clone(),
+                groups: vec![],
+                look_behind: if has_look { look_behind(prev) } else { 0 },
+            };
+            let (next_id, is_new) = self.cached_or_add(state, scratch)?;
+            if is_new {
+                uncompiled.push(next_id);
+            }
+            match prev {
+                None => self.dfa.add_eoi_transition(id, next_id),
+                Some(b) => self.dfa.add_transition(id, b, next_id),
             }
         }
+        Ok(id)
     }
 
     /// Compute the initial DFA state and return its identifier.
     ///
-    /// The sparse set given is used for scratch space, and must have capacity
-    /// equal to the total number of NFA states. Its contents are unspecified.
-    fn add_start(&mut self, sparse: &mut SparseSet) -> Result<S> {
-        sparse.clear();
-        self.epsilon_closure(self.nfa.start(), sparse);
-        let state = self.new_state(&sparse);
+    /// The given scratch space must have been created for the NFA being
+    /// converted. Its contents are unspecified.
+    fn add_start(&mut self, scratch: &mut Scratch) -> Result<S> {
+        let start = if self.anchored {
+            self.nfa.start_anchored()
+        } else {
+            self.nfa.start()
+        };
+        let state = self.step.start(start, scratch);
         let id = self.add_state(state)?;
         self.dfa.set_start_state(id);
         Ok(id)
     }
 
+    /// Compute the initial DFA state for anchored searches and return its
+    /// identifier, along with whether the state was newly built.
+    ///
+    /// The given scratch space must have been created for the NFA being
+    /// converted. Its contents are unspecified.
+    fn add_anchored_start(
+        &mut self,
+        scratch: &mut Scratch,
+    ) -> Result<(S, bool)> {
+        let start = self.nfa.start_anchored();
+        let (id, is_new) = self.add_start_from(start, scratch)?;
+        self.dfa.set_anchored_start_state(id);
+        Ok((id, is_new))
+    }
+
+    /// Compute the DFA state for the epsilon closure of the given NFA state
+    /// and return its identifier, along with whether the state was newly
+    /// built.
+    ///
+    /// The given scratch space must have been created for the NFA being
+    /// converted. Its contents are unspecified.
+    fn add_start_from(
+        &mut self,
+        start: nfa::StateID,
+        scratch: &mut Scratch,
+    ) -> Result<(S, bool)> {
+        let state = self.step.start(start, scratch);
+        match self.cache.get(&state) {
+            Some(&id) => Ok((id, false)),
+            None => Ok((self.add_state(state)?, true)),
+        }
+    }
+
     /// Add the given state to the DFA and make it available in the cache.
     ///
     /// The state initially has no transitions. That is, it transitions to the
     /// dead state for all possible inputs.
     fn add_state(&mut self, state: State) -> Result<S> {
+        self.memory_usage += self.dfa.alphabet_len() * mem::size_of::<S>()
+            + state.memory_usage();
+        if let Some(limit) = self.size_limit {
+            if self.memory_usage > limit {
+                return Err(Error::dfa_size_limit_exceeded(
+                    limit,
+                    self.memory_usage,
+                ));
+            }
+        }
         let id = self.dfa.add_empty_state()?;
+        if let Some(ref mut reuse) = self.reuse {
+            reuse.register(&state, id);
+        }
         let rstate = Rc::new(state);
         self.builder_states.push(rstate.clone());
         self.cache.insert(rstate, id);
         Ok(id)
     }
 
-    /// Convert the given set of ordered NFA states to a DFA state.
-    fn new_state(&mut self, set: &SparseSet) -> State {
+    /// Add a state to the DFA that doesn't correspond to any set of NFA
+    /// states, and so is never returned from the cache. It is never a match
+    /// state.
+    ///
+    /// The state initially has no transitions. That is, it transitions to the
+    /// dead state for all possible inputs.
+    fn add_unique_state(&mut self) -> Result<S> {
+        self.add_state(State::dead())?;
+        // Adding the state cached it under the dead state's key, so put the
+        // dead state back.
+        self.cache.insert(self.builder_states[0].clone(), dead_id());
+        Ok(S::from_usize(self.builder_states.len() - 1))
+    }
+
+    /// Return a map from each NFA state to whether it's part of the
+    /// unanchored prefix of the NFA.
+    ///
+    /// The unanchored prefix consists of every state reachable from the
+    /// unanchored start state without passing through the anchored start
+    /// state.
+    fn unanchored_prefix(&self) -> Vec<bool> {
+        let mut prefix = vec![false; self.nfa.len()];
+        let mut stack = vec![self.nfa.start()];
+        while let Some(id) = stack.pop() {
+            if prefix[id] || id == self.nfa.start_anchored() {
+                continue;
+            }
+            prefix[id] = true;
+            match *self.nfa.state(id) {
+                nfa::State::Range { ref range } => stack.push(range.next),
+                nfa::State::Sparse { ref ranges } => {
+                    stack.extend(ranges.iter().map(|r| r.next));
+                }
+                nfa::State::Union { ref alternates } => {
+                    stack.extend(alternates.iter().cloned());
+                }
+                nfa::State::Look { next, .. } => stack.push(next),
+                nfa::State::Fail | nfa::State::Match { .. } => {}
+            }
+        }
+        prefix
+    }
+}
+
+impl<'a> Step<'a> {
+    /// Returns the options of this step that must be the same for a build
+    /// to reuse the transitions of another, as a set of `CACHE_*` flags.
+    fn cache_flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.longest_match {
+            flags |= CACHE_LONGEST_MATCH;
+        }
+        if self.all_matches {
+            flags |= CACHE_ALL_MATCHES;
+        }
+        if self.match_only {
+            flags |= CACHE_MATCH_ONLY;
+        }
+        if self.lookaround {
+            flags |= CACHE_LOOKAROUND;
+        }
+        if self.nfa.is_reverse() {
+            flags |= CACHE_REVERSE;
+        }
+        flags
+    }
+
+    /// Compute the DFA state for the epsilon closure of the given NFA state.
+    fn start(&self, start: nfa::StateID, scratch: &mut Scratch) -> State {
+        scratch.sparse.clear();
+        epsilon_closure(
+            self.nfa,
+            start,
+            &mut scratch.stack,
+            &mut scratch.sparse,
+        );
+        self.new_state(scratch, &[])
+    }
+
+    /// Compute the DFA state reached from the given DFA state on the given
+    /// byte, or at the end of the input when the byte is `None`.
+    ///
+    /// The end of the input only has a transition when the NFA has
+    /// look-around assertions.
+    fn successor(
+        &self,
+        state: &State,
+        input: Option<u8>,
+        scratch: &mut Scratch,
+    ) -> State {
+        // Compute the set of all reachable NFA states, including epsilons.
+        let mut groups = vec![];
+        if self.lookaround {
+            self.next_lookaround(state, input, scratch, &mut groups)
+        } else {
+            let b = input.expect("end of input transition without lookaround");
+            self.next(state, b, scratch, &mut groups);
+            self.new_state(scratch, &groups)
+        }
+    }
+
+    /// Compute the set of all eachable NFA states, including the full epsilon
+    /// closure, from a DFA state for a single byte of input, and store it in
+    /// the given scratch space.
+    ///
+    /// When the DFA state groups its NFA states, then the end of each group
+    /// in the set computed is added to `groups`. The NFA states reached from
+    /// the unanchored prefix follow the last group.
+    fn next(
+        &self,
+        state: &State,
+        b: u8,
+        scratch: &mut Scratch,
+        groups: &mut Vec<usize>,
+    ) {
+        let next_nfa_states = &mut scratch.sparse;
+        if state.groups.is_empty() {
+            next(
+                self.nfa,
+                &state.nfa_states,
+                b,
+                &mut scratch.stack,
+                next_nfa_states,
+            );
+            return;
+        }
+        next_nfa_states.clear();
+        let mut ends = state.groups.iter().peekable();
+        for (i, &nfa_id) in state.nfa_states.iter().enumerate() {
+            while ends.peek() == Some(&&i) {
+                ends.next();
+                groups.push(next_nfa_states.len());
+            }
+            transition(
+                self.nfa,
+                nfa_id,
+                b,
+                &mut scratch.stack,
+                next_nfa_states,
+            );
+        }
+        for _ in ends {
+            groups.push(next_nfa_states.len());
+        }
+    }
+
+    /// Compute the DFA state reached from the given DFA state on the given
+    /// byte, or at the end of the input when the byte is `None`, when the NFA
+    /// has look-around assertions.
+    ///
+    /// This first resolves the assertions at the current position, given the
+    /// byte preceding it and the byte following it, and records the patterns
+    /// matching at that position. Then the NFA states that are still alive
+    /// step over the byte. See `State::look_behind` for more details.
+    ///
+    /// The given scratch space and groups are used for scratch space.
+    fn next_lookaround(
+        &self,
+        state: &State,
+        input: Option<u8>,
+        scratch: &mut Scratch,
+        groups: &mut Vec<usize>,
+    ) -> State {
+        scratch.sparse.clear();
+        let mut ends = state.groups.iter().peekable();
+        for (i, &nfa_id) in state.nfa_states.iter().enumerate() {
+            while ends.peek() == Some(&&i) {
+                ends.next();
+                groups.push(scratch.sparse.len());
+            }
+            look_closure(
+                self.nfa,
+                nfa_id,
+                state.look_behind,
+                input,
+                &mut scratch.stack,
+                &mut scratch.sparse,
+            );
+        }
+        for _ in ends {
+            groups.push(scratch.sparse.len());
+        }
+        let resolved = self.new_state(scratch, groups);
+        let b = match input {
+            Some(b) => b,
+            // Nothing follows the end of the input, so all that's left is
+            // whether there's a match. Without one, this is the dead state.
+            None => {
+                return State {
+                    matches: resolved.matches,
+                    nfa_states: vec![],
+                    groups: vec![],
+                    look_behind: 0,
+                };
+            }
+        };
+
+        groups.clear();
+        self.next(&resolved, b, scratch, groups);
+        let mut next = State {
+            matches: resolved.matches,
+            nfa_states: vec![],
+            groups: vec![],
+            look_behind: 0,
+        };
+        let mut ends = groups.iter().peekable();
+        for (i, &nfa_id) in scratch.sparse.into_iter().enumerate() {
+            while ends.peek() == Some(&&i) {
+                ends.next();
+                next.groups.push(next.nfa_states.len());
+            }
+            match *self.nfa.state(nfa_id) {
+                nfa::State::Union { .. } => {}
+                nfa::State::Look { .. } => {
+                    next.look_behind = look_behind(Some(b));
+                    next.nfa_states.push(nfa_id);
+                }
+                _ => next.nfa_states.push(nfa_id),
+            }
+        }
+        for _ in ends {
+            next.groups.push(next.nfa_states.len());
+        }
+        next
+    }
+
+    /// Convert the ordered set of NFA states in the given scratch space to a
+    /// DFA state.
+    ///
+    /// When finding the leftmost longest match in an unanchored DFA, `groups`
+    /// contains the end of each group of NFA states in the given set, as
+    /// computed by `next`.
+    fn new_state(&self, scratch: &mut Scratch, groups: &[usize]) -> State {
         let mut state = State {
-            is_match: false,
-            nfa_states: mem::replace(&mut self.scratch_nfa_states, vec![]),
+            matches: vec![],
+            nfa_states: mem::replace(&mut scratch.nfa_states, vec![]),
+            groups: vec![],
+            look_behind: 0,
         };
         state.nfa_states.clear();
+        if !self.prefix.is_empty() {
+            self.new_grouped_state(&scratch.sparse, groups, &mut state);
+            return state;
+        }
 
-        for &id in set {
+        for &id in &scratch.sparse {
             match *self.nfa.state(id) {
                 nfa::State::Range { .. } => {
                     state.nfa_states.push(id);
@@ -260,27 +1347,789 @@ impl<'a, S: StateID> Determinizer<'a, S> {
                 nfa::State::Fail => {
                     break;
                 }
-                nfa::State::Match => {
-                    state.is_match = true;
-                    if !self.longest_match {
+                nfa::State::Match { pattern_id } => {
+                    state.matches.push(pattern_id);
+                    if self.match_only {
+                        state.nfa_states.clear();
+                        break;
+                    }
+                    if !self.longest_match && !self.all_matches {
                         break;
                     }
                 }
-                nfa::State::Union { .. } => {}
+                nfa::State::Union { .. } | nfa::State::Look { .. } => {}
             }
         }
+        // When every match is kept, no match is preferred over another, so
+        // report them in the order in which their patterns were given.
+        if self.longest_match || self.all_matches {
+            state.matches.sort();
+        }
         state
     }
 
-    /// Create a new sparse set with enough capacity to hold all NFA states.
-    fn new_sparse_set(&self) -> SparseSet {
-        SparseSet::new(self.nfa.len())
+    /// Populate the given DFA state with the NFA states in the given set
+    /// while tracking the position at which their matches would start.
+    ///
+    /// This is how the leftmost longest match is found in an unanchored DFA.
+    /// Since the unanchored prefix is non-greedy, NFA states whose matches
+    /// start earlier always precede those whose matches start later. We can
+    /// therefore treat the NFA states in each group as having equivalent
+    /// priority, while giving them greater priority than the states in all
+    /// following groups. Once a match is seen, every group following it,
+    /// including the unanchored prefix, is dropped, since the matches of
+    /// those groups can only start later.
+    fn new_grouped_state(
+        &self,
+        set: &SparseSet,
+        groups: &[usize],
+        state: &mut State,
+    ) {
+        let mut prefix = vec![];
+        let mut ends = groups.iter().peekable();
+        let mut matched = false;
+        for (i, &id) in set.into_iter().enumerate() {
+            while ends.peek() == Some(&&i) {
+                ends.next();
+                state.close_group();
+                if matched {
+                    state.matches.sort();
+                    return;
+                }
+            }
+            match *self.nfa.state(id) {
+                nfa::State::Range { .. } | nfa::State::Sparse { .. } => {
+                    if self.prefix[id] {
+                        prefix.push(id);
+                    } else {
+                        state.nfa_states.push(id);
+                    }
+                }
+                nfa::State::Match { pattern_id } => {
+                    state.matches.push(pattern_id);
+                    if self.match_only {
+                        state.nfa_states.clear();
+                        state.groups.clear();
+                        return;
+                    }
+                    matched = true;
+                }
+                nfa::State::Union { .. }
+                | nfa::State::Look { .. }
+                | nfa::State::Fail => {}
+            }
+        }
+        state.close_group();
+        state.matches.sort();
+        if !matched {
+            state.nfa_states.extend(prefix);
+        }
+    }
+}
+
+impl Scratch {
+    /// Create scratch space for computing DFA states from an NFA with the
+    /// given number of states.
+    fn new(nfa_len: usize) -> Scratch {
+        Scratch {
+            sparse: SparseSet::new(nfa_len),
+            stack: vec![],
+            nfa_states: vec![],
+        }
+    }
+}
+
+impl BuildCache {
+    /// Create a new empty cache, from which a build reuses nothing.
+    pub fn new() -> BuildCache {
+        BuildCache {
+            flags: 0,
+            nfa: vec![],
+            prefix: vec![],
+            classes: ByteClasses::singletons(),
+            states: vec![],
+            trans: vec![],
+            reused: 0,
+        }
+    }
+
+    /// Returns the number of DFA states recorded in this cache.
+    ///
+    /// This is the number of states of the DFA this cache was built with,
+    /// before minimization.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the number of transitions that were reused from the previous
+    /// cache when building the DFA this cache was built with, instead of
+    /// being computed.
+    ///
+    /// This is zero for a cache read with
+    /// [`from_bytes_unchecked`](struct.BuildCache.html#method.from_bytes_unchecked).
+    pub fn reused_transitions(&self) -> usize {
+        self.reused
+    }
+
+    /// Serialize this cache to bytes, which can be read back with
+    /// [`from_bytes_unchecked`](struct.BuildCache.html#method.from_bytes_unchecked).
+    ///
+    /// The bytes are portable across targets and include a checksum, such
+    /// that reading corrupted bytes fails instead of producing a cache that
+    /// leads to an incorrect DFA.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = vec![];
+        write_varu64(&mut body, self.flags as u64);
+        write_varu64(&mut body, self.nfa.len() as u64);
+        for state in &self.nfa {
+            write_nfa_state(&mut body, state);
+        }
+        write_varu64(&mut body, self.prefix.len() as u64);
+        body.extend(self.prefix.iter().map(|&yes| yes as u8));
+        body.extend((0..256).map(|b| self.classes.get(b as u8)));
+        write_varu64(&mut body, self.states.len() as u64);
+        for state in &self.states {
+            write_varu64(&mut body, state.matches.len() as u64);
+            for &pid in &state.matches {
+                write_varu64(&mut body, pid as u64);
+            }
+            write_varu64(&mut body, state.nfa_states.len() as u64);
+            for &id in &state.nfa_states {
+                write_varu64(&mut body, id as u64);
+            }
+            write_varu64(&mut body, state.groups.len() as u64);
+            for &end in &state.groups {
+                write_varu64(&mut body, end as u64);
+            }
+            body.push(state.look_behind);
+        }
+        for &next in &self.trans {
+            write_varu64(&mut body, next.wrapping_add(1) as u64);
+        }
+
+        let mut buf = BUILD_CACHE_LABEL.to_vec();
+        let mut fields = [0; 6];
+        LittleEndian::write_u16(&mut fields, BUILD_CACHE_VERSION);
+        LittleEndian::write_u32(&mut fields[2..], crc32(0, &body));
+        buf.extend_from_slice(&fields);
+        buf.extend(body);
+        buf
+    }
+
+    /// Deserialize a cache written by
+    /// [`to_bytes`](struct.BuildCache.html#method.to_bytes), without checking
+    /// the transitions it records.
+    ///
+    /// If the bytes aren't a valid cache, or were written by an
+    /// incompatible version of this crate, then an error is returned.
+    /// Otherwise, the transitions recorded in the cache are trusted to be the
+    /// ones its NFA states lead to, and are reused as is by the next build.
+    /// Bytes that pass the checksum but record other transitions make that
+    /// build produce an incorrect DFA, so the bytes must come from a trusted
+    /// source, such as a file written by this program.
+    pub fn from_bytes_unchecked(
+        buf: &[u8],
+    ) -> ::std::result::Result<BuildCache, DeserializeError> {
+        if !buf.starts_with(BUILD_CACHE_LABEL) {
+            return Err(DeserializeError::InvalidLabel);
+        }
+        let header_len = BUILD_CACHE_LABEL.len() + 6;
+        if buf.len() < header_len {
+            return Err(DeserializeError::BufferTooSmall {
+                needed: header_len,
+                got: buf.len(),
+            });
+        }
+        let fields = &buf[BUILD_CACHE_LABEL.len()..];
+        let version = LittleEndian::read_u16(fields);
+        if version != BUILD_CACHE_VERSION {
+            return Err(DeserializeError::UnsupportedVersion {
+                expected: BUILD_CACHE_VERSION,
+                found: version,
+            });
+        }
+        let expected = LittleEndian::read_u32(&fields[2..]);
+        let body = &buf[header_len..];
+        let found = crc32(0, body);
+        if expected != found {
+            return Err(DeserializeError::ChecksumMismatch {
+                expected,
+                found,
+            });
+        }
+        BuildCache::read_body(&mut Reader { buf: body })
+            .ok_or(DeserializeError::InvalidBuildCache)
+    }
+
+    /// Read the body of a serialized cache, checking that it's consistent.
+    fn read_body(r: &mut Reader) -> Option<BuildCache> {
+        let flags = r.byte_varint()?;
+        let nfa_len = r.len()?;
+        let mut nfa = Vec::with_capacity(nfa_len);
+        for _ in 0..nfa_len {
+            nfa.push(read_nfa_state(r, nfa_len)?);
+        }
+        let prefix_len = r.len()?;
+        if prefix_len != 0 && prefix_len != nfa_len {
+            return None;
+        }
+        let mut prefix = Vec::with_capacity(prefix_len);
+        for _ in 0..prefix_len {
+            prefix.push(match r.byte()? {
+                0 => false,
+                1 => true,
+                _ => return None,
+            });
+        }
+        let mut classes = ByteClasses::singletons();
+        for b in 0..256 {
+            classes.set(b as u8, r.byte()?);
+        }
+        if (0..256).any(|b| classes.get(b as u8) > classes.get(255)) {
+            return None;
+        }
+        let state_count = r.len()?;
+        let mut states = Vec::with_capacity(state_count);
+        for _ in 0..state_count {
+            let mut state = State::dead();
+            for _ in 0..r.len()? {
+                state.matches.push(r.usize()?);
+            }
+            for _ in 0..r.len()? {
+                state.nfa_states.push(r.id(nfa_len)?);
+            }
+            for _ in 0..r.len()? {
+                state.groups.push(r.id(state.nfa_states.len() + 1)?);
+            }
+            state.look_behind = r.byte()?;
+            states.push(state);
+        }
+        let trans_len = state_count.checked_mul(classes.alphabet_len() + 1)?;
+        let mut trans = Vec::with_capacity(trans_len.min(r.buf.len()));
+        for _ in 0..trans_len {
+            trans.push(match r.usize()? {
+                0 => NONE,
+                next if next <= state_count => next - 1,
+                _ => return None,
+            });
+        }
+        if !r.buf.is_empty() {
+            return None;
+        }
+        Some(BuildCache {
+            flags,
+            nfa,
+            prefix,
+            classes,
+            states,
+            trans,
+            reused: 0,
+        })
+    }
+}
+
+impl Default for BuildCache {
+    fn default() -> BuildCache {
+        BuildCache::new()
+    }
+}
+
+impl<'a> Reuse<'a> {
+    /// Prepare to reuse the transitions of a previous build, given how
+    /// transitions are computed by the current build. If the previous build
+    /// computed transitions differently, then nothing can be reused and
+    /// `None` is returned.
+    fn new(prior: &'a BuildCache, step: &Step) -> Option<Reuse<'a>> {
+        if prior.states.is_empty() || prior.flags != step.cache_flags() {
+            return None;
+        }
+        // An NFA state is clean when it's the same in both NFAs and every
+        // NFA state reachable from it is clean, since the transitions of a
+        // DFA state depend on every NFA state reachable from its own.
+        let nfa = step.nfa;
+        let mut clean: Vec<bool> = (0..nfa.len())
+            .map(|id| {
+                prior.nfa.get(id) == Some(nfa.state(id))
+                    && prior.prefix.get(id) == step.prefix.get(id)
+            })
+            .collect();
+        let mut preds = vec![vec![]; nfa.len()];
+        for id in 0..nfa.len() {
+            match *nfa.state(id) {
+                nfa::State::Range { ref range } => preds[range.next].push(id),
+                nfa::State::Sparse { ref ranges } => {
+                    for r in ranges.iter() {
+                        preds[r.next].push(id);
+                    }
+                }
+                nfa::State::Union { ref alternates } => {
+                    for &alt in alternates.iter() {
+                        preds[alt].push(id);
+                    }
+                }
+                nfa::State::Look { next, .. } => preds[next].push(id),
+                nfa::State::Fail | nfa::State::Match { .. } => {}
+            }
+        }
+        let mut stack: Vec<nfa::StateID> =
+            (0..nfa.len()).filter(|&id| !clean[id]).collect();
+        while let Some(id) = stack.pop() {
+            for &pred in &preds[id] {
+                if clean[pred] {
+                    clean[pred] = false;
+                    stack.push(pred);
+                }
+            }
+        }
+
+        let mut index = HashMap::default();
+        for (i, state) in prior.states.iter().enumerate() {
+            index.entry(state).or_insert(i);
+        }
+        let reusable = prior
+            .states
+            .iter()
+            .map(|state| {
+                state
+                    .nfa_states
+                    .iter()
+                    .all(|&id| clean.get(id).cloned().unwrap_or(false))
+            })
+            .collect();
+        Some(Reuse {
+            prior,
+            index,
+            reusable,
+            new_to_old: vec![],
+            old_to_new: vec![NONE; prior.states.len()],
+            reused: 0,
+        })
+    }
+
+    /// Record that the given DFA state of the current build was added with
+    /// the given identifier, matching it with the same DFA state of the
+    /// previous build, if any.
+    fn register<S: StateID>(&mut self, state: &State, id: S) {
+        let old = match self.index.get(state) {
+            Some(&old) if self.old_to_new[old] == NONE => old,
+            _ => NONE,
+        };
+        if old != NONE {
+            self.old_to_new[old] = id.to_usize();
+        }
+        debug_assert_eq!(self.new_to_old.len(), id.to_usize());
+        self.new_to_old.push(old);
+    }
+
+    /// Returns the index, in the cache of the previous build, of the DFA
+    /// state reached from the given DFA state of the current build on the
+    /// given byte, or at the end of the input when it is `None`. If that
+    /// transition can't be reused, then `None` is returned.
+    fn successor(&self, id: usize, input: Option<u8>) -> Option<usize> {
+        let old = self.new_to_old.get(id).cloned().unwrap_or(NONE);
+        if old == NONE || !self.reusable[old] {
+            return None;
+        }
+        let stride = self.prior.classes.alphabet_len() + 1;
+        let slot = match input {
+            Some(b) => self.prior.classes.get(b) as usize,
+            None => stride - 1,
+        };
+        match self.prior.trans[old * stride + slot] {
+            NONE => None,
+            next => Some(next),
+        }
+    }
+}
+
+/// A cursor over the body of a serialized build cache.
+struct Reader<'b> {
+    buf: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    /// Read a single byte.
+    fn byte(&mut self) -> Option<u8> {
+        let (&b, rest) = self.buf.split_first()?;
+        self.buf = rest;
+        Some(b)
+    }
+
+    /// Read a varint.
+    fn usize(&mut self) -> Option<usize> {
+        let (n, nread) = read_varu64(self.buf);
+        if nread == 0 || n as usize as u64 != n {
+            return None;
+        }
+        self.buf = &self.buf[nread..];
+        Some(n as usize)
+    }
+
+    /// Read a varint that must fit in a byte.
+    fn byte_varint(&mut self) -> Option<u8> {
+        let n = self.usize()?;
+        if n > 0xFF {
+            return None;
+        }
+        Some(n as u8)
+    }
+
+    /// Read the length of a sequence, each element of which takes at least
+    /// one byte, such that a corrupted length can't lead to a huge
+    /// allocation.
+    fn len(&mut self) -> Option<usize> {
+        let len = self.usize()?;
+        if len > self.buf.len() {
+            return None;
+        }
+        Some(len)
+    }
+
+    /// Read a varint that must be less than `bound`.
+    fn id(&mut self, bound: usize) -> Option<usize> {
+        let id = self.usize()?;
+        if id >= bound {
+            return None;
+        }
+        Some(id)
+    }
+}
+
+/// Write the given NFA state to the body of a serialized build cache.
+fn write_nfa_state(buf: &mut Vec<u8>, state: &nfa::State) {
+    let write_range = |buf: &mut Vec<u8>, r: &nfa::Transition| {
+        buf.push(r.start);
+        buf.push(r.end);
+        write_varu64(buf, r.next as u64);
+    };
+    match *state {
+        nfa::State::Range { ref range } => {
+            buf.push(0);
+            write_range(buf, range);
+        }
+        nfa::State::Sparse { ref ranges } => {
+            buf.push(1);
+            write_varu64(buf, ranges.len() as u64);
+            for r in ranges.iter() {
+                write_range(buf, r);
+            }
+        }
+        nfa::State::Union { ref alternates } => {
+            buf.push(2);
+            write_varu64(buf, alternates.len() as u64);
+            for &alt in alternates.iter() {
+                write_varu64(buf, alt as u64);
+            }
+        }
+        nfa::State::Look { look, next } => {
+            buf.push(3);
+            buf.push(look_code(look));
+            write_varu64(buf, next as u64);
+        }
+        nfa::State::Fail => buf.push(4),
+        nfa::State::Match { pattern_id } => {
+            buf.push(5);
+            write_varu64(buf, pattern_id as u64);
+        }
+    }
+}
+
+/// Read an NFA state written by `write_nfa_state`, from an NFA with the
+/// given number of states.
+fn read_nfa_state(r: &mut Reader, nfa_len: usize) -> Option<nfa::State> {
+    let read_range = |r: &mut Reader| {
+        Some(nfa::Transition {
+            start: r.byte()?,
+            end: r.byte()?,
+            next: r.id(nfa_len)?,
+        })
+    };
+    Some(match r.byte()? {
+        0 => nfa::State::Range { range: read_range(r)? },
+        1 => {
+            let mut ranges = vec![];
+            for _ in 0..r.len()? {
+                ranges.push(read_range(r)?);
+            }
+            nfa::State::Sparse { ranges: ranges.into_boxed_slice() }
+        }
+        2 => {
+            let mut alternates = vec![];
+            for _ in 0..r.len()? {
+                alternates.push(r.id(nfa_len)?);
+            }
+            nfa::State::Union { alternates: alternates.into_boxed_slice() }
+        }
+        3 => {
+            let look = look_from_code(r.byte()?)?;
+            nfa::State::Look { look, next: r.id(nfa_len)? }
+        }
+        4 => nfa::State::Fail,
+        5 => nfa::State::Match { pattern_id: r.usize()? },
+        _ => return None,
+    })
+}
+
+/// Returns the code of the given assertion in a serialized build cache.
+fn look_code(look: Look) -> u8 {
+    match look {
+        Look::StartText => 0,
+        Look::EndText => 1,
+        Look::StartLine => 2,
+        Look::EndLine => 3,
+        Look::StartLineCRLF => 4,
+        Look::EndLineCRLF => 5,
+        Look::WordBoundaryAscii => 6,
+        Look::NotWordBoundaryAscii => 7,
+        Look::WordBoundaryUnicode => 8,
+        Look::NotWordBoundaryUnicode => 9,
+    }
+}
+
+/// Returns the assertion with the given code in a serialized build cache.
+fn look_from_code(code: u8) -> Option<Look> {
+    Some(match code {
+        0 => Look::StartText,
+        1 => Look::EndText,
+        2 => Look::StartLine,
+        3 => Look::EndLine,
+        4 => Look::StartLineCRLF,
+        5 => Look::EndLineCRLF,
+        6 => Look::WordBoundaryAscii,
+        7 => Look::NotWordBoundaryAscii,
+        8 => Look::WordBoundaryUnicode,
+        9 => Look::NotWordBoundaryUnicode,
+        _ => return None,
+    })
+}
+
+/// Compute the set of all reachable NFA states, including the full epsilon
+/// closure, from the given set of NFA states for a single byte of input.
+///
+/// The given stack is used for scratch space. Its contents are unspecified.
+pub(crate) fn next(
+    nfa: &NFA,
+    nfa_states: &[nfa::StateID],
+    b: u8,
+    stack: &mut Vec<nfa::StateID>,
+    next_nfa_states: &mut SparseSet,
+) {
+    next_nfa_states.clear();
+    for &nfa_id in nfa_states {
+        transition(nfa, nfa_id, b, stack, next_nfa_states);
+    }
+}
+
+/// Add the full epsilon closure of the state reached from the given NFA state
+/// on a single byte of input to the given set. If the NFA state has no
+/// transition for the given byte, then this does nothing.
+///
+/// The given stack is used for scratch space. Its contents are unspecified.
+fn transition(
+    nfa: &NFA,
+    nfa_id: nfa::StateID,
+    b: u8,
+    stack: &mut Vec<nfa::StateID>,
+    next_nfa_states: &mut SparseSet,
+) {
+    match *nfa.state(nfa_id) {
+        nfa::State::Union { .. }
+        | nfa::State::Look { .. }
+        | nfa::State::Fail
+        | nfa::State::Match { .. } => {}
+        nfa::State::Range { range: ref r } => {
+            if r.start <= b && b <= r.end {
+                epsilon_closure(nfa, r.next, stack, next_nfa_states);
+            }
+        }
+        nfa::State::Sparse { ref ranges } => {
+            for r in ranges.iter() {
+                if r.start > b {
+                    break;
+                } else if r.start <= b && b <= r.end {
+                    epsilon_closure(nfa, r.next, stack, next_nfa_states);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Compute the epsilon closure for the given NFA state and add it to the
+/// given set.
+///
+/// The given stack is used for scratch space. Its contents are unspecified.
+pub(crate) fn epsilon_closure(
+    nfa: &NFA,
+    start: nfa::StateID,
+    stack: &mut Vec<nfa::StateID>,
+    set: &mut SparseSet,
+) {
+    if !nfa.state(start).is_epsilon() {
+        set.insert(start);
+        return;
+    }
+
+    stack.push(start);
+    while let Some(mut id) = stack.pop() {
+        loop {
+            if set.contains(id) {
+                break;
+            }
+            set.insert(id);
+            match *nfa.state(id) {
+                nfa::State::Range { .. }
+                | nfa::State::Sparse { .. }
+                | nfa::State::Look { .. }
+                | nfa::State::Fail
+                | nfa::State::Match { .. } => break,
+                nfa::State::Union { ref alternates } => {
+                    id = match alternates.get(0) {
+                        None => break,
+                        Some(&id) => id,
+                    };
+                    stack.extend(alternates[1..].iter().rev());
+                }
+            }
+        }
+    }
+}
+
+/// Compute the closure for the given NFA state and add it to the given set,
+/// following both epsilon transitions and the look-around assertions that
+/// hold between the byte preceding the current position, as given by the
+/// `look_behind` flags, and the byte following it, or the end of the input
+/// when it is `None`. Assertions that don't hold are added to the set, but
+/// not followed.
+///
+/// The given stack is used for scratch space. Its contents are unspecified.
+fn look_closure(
+    nfa: &NFA,
+    start: nfa::StateID,
+    look_behind: u8,
+    input: Option<u8>,
+    stack: &mut Vec<nfa::StateID>,
+    set: &mut SparseSet,
+) {
+    stack.push(start);
+    while let Some(mut id) = stack.pop() {
+        loop {
+            if set.contains(id) {
+                break;
+            }
+            set.insert(id);
+            match *nfa.state(id) {
+                nfa::State::Range { .. }
+                | nfa::State::Sparse { .. }
+                | nfa::State::Fail
+                | nfa::State::Match { .. } => break,
+                nfa::State::Union { ref alternates } => {
+                    id = match alternates.first() {
+                        None => break,
+                        Some(&id) => id,
+                    };
+                    stack.extend(alternates[1..].iter().rev());
+                }
+                nfa::State::Look { look, next } => {
+                    if !look_holds(look, look_behind, input, nfa.is_reverse())
+                    {
+                        break;
+                    }
+                    id = next;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the `LOOK_*` flags satisfied by the given preceding byte, or by
+/// the beginning of the input when it is `None`.
+fn look_behind(prev: Option<u8>) -> u8 {
+    match prev {
+        None => LOOK_START_TEXT,
+        Some(b'\n') => LOOK_LF,
+        Some(b'\r') => LOOK_CR,
+        Some(b) if is_word_byte(b) => LOOK_WORD,
+        Some(_) => 0,
+    }
+}
+
+/// Returns true if and only if the given assertion holds between the byte
+/// preceding the current position, as given by the `look_behind` flags, and
+/// the byte following it, or the end of the input when it is `None`.
+///
+/// When `reverse` is true, the input is read from its end to its beginning,
+/// such that the preceding byte comes after the current position in the
+/// input, and the following byte comes before it.
+///
+/// Unicode word boundaries only get here when they are compiled heuristically,
+/// in which case every non-ASCII byte is a quit byte. On the remaining bytes,
+/// they agree with ASCII word boundaries.
+fn look_holds(
+    look: Look,
+    look_behind: u8,
+    input: Option<u8>,
+    reverse: bool,
+) -> bool {
+    let has = |flags: u8| look_behind & flags != 0;
+    let before = has(LOOK_WORD);
+    let after = input.map(is_word_byte).unwrap_or(false);
+    // Read in reverse, `\r\n` becomes `\n\r`, so `\r` and `\n` swap roles
+    // for the CRLF-aware line anchors.
+    let (cr, lf, after_cr, after_lf) = if reverse {
+        (b'\n', b'\r', LOOK_LF, LOOK_CR)
+    } else {
+        (b'\r', b'\n', LOOK_CR, LOOK_LF)
+    };
+    match look {
+        Look::StartText => has(LOOK_START_TEXT),
+        Look::EndText => input.is_none(),
+        Look::StartLine => has(LOOK_START_TEXT | LOOK_LF),
+        Look::EndLine => input.map(|b| b == b'\n').unwrap_or(true),
+        Look::StartLineCRLF => {
+            has(LOOK_START_TEXT | after_lf)
+                || (has(after_cr) && input != Some(lf))
+        }
+        Look::EndLineCRLF => match input {
+            None => true,
+            Some(b) => b == cr || (b == lf && !has(after_cr)),
+        },
+        Look::WordBoundaryAscii | Look::WordBoundaryUnicode => before != after,
+        Look::NotWordBoundaryAscii | Look::NotWordBoundaryUnicode => {
+            before == after
+        }
     }
 }
 
 impl State {
     /// Create a new empty dead state.
     fn dead() -> State {
-        State { nfa_states: vec![], is_match: false }
+        State {
+            nfa_states: vec![],
+            matches: vec![],
+            groups: vec![],
+            look_behind: 0,
+        }
+    }
+
+    /// Returns the approximate memory used by this state, in bytes. Since
+    /// each state is stored both in the list of states and in the cache,
+    /// this includes the size of two pointers to it.
+    fn memory_usage(&self) -> usize {
+        mem::size_of::<State>()
+            + 2 * mem::size_of::<Rc<State>>()
+            + self.nfa_states.len() * mem::size_of::<nfa::StateID>()
+            + self.matches.len() * mem::size_of::<PatternID>()
+            + self.groups.len() * mem::size_of::<usize>()
+    }
+
+    /// End the current group of NFA states in this state. If no NFA states
+    /// were added since the end of the previous group, then this does
+    /// nothing.
+    fn close_group(&mut self) {
+        let start = self.groups.last().cloned().unwrap_or(0);
+        if self.nfa_states.len() > start {
+            self.groups.push(self.nfa_states.len());
+        }
     }
 }
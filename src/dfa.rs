@@ -1,4 +1,30 @@
-use state_id::StateID;
+#[cfg(feature = "std")]
+use core::iter;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use core::slice;
+#[cfg(feature = "std")]
+use std::io;
+
+use accel::find_needle;
+use error::SearchError;
+use state_id::{dead_id, StateID};
+use stats;
+
+/// The representation for a pattern identifier.
+///
+/// When a DFA is built from several patterns, e.g., with
+/// [`dense::Builder::build_many`](dense/struct.Builder.html#method.build_many),
+/// the patterns are numbered sequentially in the order in which they were
+/// given, starting at `0`. A DFA built from a single pattern only ever
+/// reports the pattern `0`.
+///
+/// Since this is a `usize`, the constructors and iterators of the
+/// [`StateID`](trait.StateID.html) trait, such as
+/// [`StateID::new`](trait.StateID.html#method.new) and
+/// [`StateID::iter`](trait.StateID.html#method.iter), are available on it
+/// when that trait is in scope.
+pub type PatternID = usize;
 
 /// A trait describing the interface of a deterministic finite automaton (DFA).
 ///
@@ -50,6 +76,20 @@ pub trait DFA {
     /// start at index `0`.
     fn is_anchored(&self) -> bool;
 
+    /// Returns true if and only if this DFA was built in UTF-8 mode.
+    ///
+    /// In UTF-8 mode, a DFA only matches valid UTF-8, and an unanchored DFA
+    /// only skips over whole codepoints before the start of a match. So
+    /// when searching valid UTF-8 from a codepoint boundary, every match it
+    /// reports begins and ends at a codepoint boundary. Otherwise, a DFA
+    /// may match any sequence of bytes, including part of a codepoint.
+    ///
+    /// The default implementation returns false, which is always correct.
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        false
+    }
+
     /// Given the current state that this DFA is in and the next input byte,
     /// this method returns the identifier of the next state. The identifier
     /// returned is always valid, but it may correspond to a dead state.
@@ -66,6 +106,105 @@ pub trait DFA {
         input: u8,
     ) -> Self::ID;
 
+    /// Returns the number of patterns that match in the given state.
+    ///
+    /// This returns `0` if and only if the given identifier does not
+    /// correspond to a match state. A DFA built with leftmost first match
+    /// semantics reports at most one pattern for each match state, namely,
+    /// the pattern with the highest priority. A DFA built with longest match
+    /// semantics may report more than one.
+    ///
+    /// The default implementation is suitable for DFAs that are built from a
+    /// single pattern.
+    #[inline]
+    fn match_count(&self, id: Self::ID) -> usize {
+        if self.is_match_state(id) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Returns the identifier of the pattern at the given index among all
+    /// of the patterns that match in the given state.
+    ///
+    /// The index given must be less than `match_count(id)`, otherwise the
+    /// result is unspecified and this may panic.
+    ///
+    /// The default implementation is suitable for DFAs that are built from a
+    /// single pattern.
+    #[inline]
+    fn match_pattern(&self, _id: Self::ID, _index: usize) -> PatternID {
+        0
+    }
+
+    /// Returns true if and only if a search with this DFA quits when it sees
+    /// the given byte. Every transition on a quit byte leads to a dead state.
+    ///
+    /// The default implementation is suitable for DFAs without quit bytes.
+    #[inline]
+    fn is_quit_byte(&self, _byte: u8) -> bool {
+        false
+    }
+
+    /// Returns the bytes on which the given state transitions to a state
+    /// other than itself, if this DFA accelerates the given state. Otherwise,
+    /// this returns an empty slice.
+    ///
+    /// An accelerated state transitions to itself on all but one, two or
+    /// three bytes. When a search enters such a state, it skips ahead to the
+    /// next of those bytes with `memchr`, instead of following a transition
+    /// for every byte in between. Match states and dead states are never
+    /// accelerated.
+    ///
+    /// The default implementation doesn't accelerate any state.
+    #[inline]
+    fn accelerator(&self, _id: Self::ID) -> &[u8] {
+        &[]
+    }
+
+    /// Returns true if and only if this DFA evaluates look-around assertions,
+    /// such as `(?-u:\b)`.
+    ///
+    /// Whether an assertion holds at a position depends on the bytes on
+    /// either side of it, so such a DFA delays each match by one byte: it
+    /// enters a match state after reading the byte *following* the end of
+    /// the match. The end of the input is then handled with one more
+    /// transition, given by `next_eoi_state`, and each search begins in the
+    /// state given by `start_state_after` rather than in `start_state`.
+    ///
+    /// The default implementation is suitable for DFAs without look-around.
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        false
+    }
+
+    /// Given the current state that this DFA is in, this returns the
+    /// identifier of the state reached at the end of the input. When that
+    /// state is a match state, there is a match ending at the end of the
+    /// input.
+    ///
+    /// This always returns a dead state when this DFA has no look-around.
+    ///
+    /// The default implementation is suitable for DFAs without look-around.
+    #[inline]
+    fn next_eoi_state(&self, _current: Self::ID) -> Self::ID {
+        dead_id()
+    }
+
+    /// Return the identifier of the state in which a search begins, given
+    /// the byte immediately preceding the search, or `None` when the search
+    /// begins at the start of the input.
+    ///
+    /// This is always the same as `start_state` when this DFA has no
+    /// look-around.
+    ///
+    /// The default implementation is suitable for DFAs without look-around.
+    #[inline]
+    fn start_state_after(&self, _prev: Option<u8>) -> Self::ID {
+        self.start_state()
+    }
+
     /// Returns true if and only if the given bytes match this DFA.
     ///
     /// This routine may short circuit if it knows that scanning future input
@@ -172,6 +311,61 @@ pub trait DFA {
         self.find_at(bytes, 0)
     }
 
+    /// Returns the same as `find`, but returns an error if the search quit
+    /// on a [quit byte](dense/struct.Builder.html#method.quit) before any
+    /// match was found.
+    ///
+    /// When a match is found before a quit byte is seen, the match is
+    /// returned just like `find` would. Otherwise, this returns
+    /// `SearchError::Quit`, since a match may have been found had the search
+    /// continued past the quit byte.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, SearchError, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().quit(0, true).build("[0-9]+")?;
+    /// assert_eq!(Ok(Some(6)), dfa.try_find(b"abc123"));
+    /// assert_eq!(Ok(Some(5)), dfa.try_find(b"abc12\x00345"));
+    /// assert_eq!(
+    ///     Err(SearchError::Quit { byte: 0, offset: 3 }),
+    ///     dfa.try_find(b"abc\x00123"),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn try_find(&self, bytes: &[u8]) -> Result<Option<usize>, SearchError> {
+        self.try_find_at(bytes, 0)
+    }
+
+    /// Returns the end offset of the leftmost first match, along with the
+    /// pattern that matched. If no match exists, then `None` is returned.
+    ///
+    /// This has the same match semantics as `find`. When more than one
+    /// pattern matches at the end offset returned, the pattern reported is
+    /// the first one returned by `match_pattern`.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to use this method with a
+    /// [`DenseDFA`](enum.DenseDFA.html) built from several patterns.
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build_many(&["[0-9]+", "[a-z]+"])?;
+    /// assert_eq!(Some((1, 3)), dfa.find_pattern(b"abc123"));
+    /// assert_eq!(Some((0, 3)), dfa.find_pattern(b"123abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_pattern(&self, bytes: &[u8]) -> Option<(PatternID, usize)> {
+        self.find_pattern_at(bytes, 0)
+    }
+
     /// Returns the start offset of the longest match in reverse, by searching
     /// from the end of the input towards the start of the input. If no match
     /// exists, then `None` is returned. In other words, this has the same
@@ -208,65 +402,177 @@ pub trait DFA {
     /// a match can only occur when `start == 0`.
     #[inline]
     fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
+        self.is_match_in(bytes, start, bytes.len())
+    }
+
+    /// Returns the same as `shortest_match`, but starts the search at the
+    /// given offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == 0`.
+    #[inline]
+    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        self.shortest_match_in(bytes, start, bytes.len())
+    }
+
+    /// Returns the same as `find`, but starts the search at the given
+    /// offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == 0`.
+    #[inline]
+    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        self.find_in(bytes, start, bytes.len())
+    }
+
+    /// Returns the same as `is_match`, but only searches `bytes[start..end]`.
+    ///
+    /// Unlike searching the subslice `&bytes[start..end]` directly, the
+    /// bytes surrounding the range are taken into consideration. Namely,
+    /// look-around assertions such as `(?-u:\b)`, `(?m:^)` and `(?m:$)` are
+    /// evaluated at `start` using the byte before it, and at `end` using the
+    /// byte after it. As with `is_match_at`, if the DFA is anchored, then a
+    /// match can only occur when `start == 0`.
+    ///
+    /// This makes it possible to correctly search a window of a larger
+    /// haystack, e.g., when searching the remainder of a haystack after a
+    /// previous match.
+    ///
+    /// This panics if `start > end` or if `end > bytes.len()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build(r"(?-u:\b)foo(?-u:\b)")?;
+    /// // The subslice "foo" matches on its own...
+    /// assert!(dfa.is_match(&b"xfoox"[1..4]));
+    /// // ...but not when its surrounding context is taken into account.
+    /// assert!(!dfa.is_match_in(b"xfoox", 1, 4));
+    /// assert!(dfa.is_match_in(b" foo ", 1, 4));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn is_match_in(&self, bytes: &[u8], start: usize, end: usize) -> bool {
+        assert!(start <= end && end <= bytes.len(), "invalid search range");
         if self.is_anchored() && start > 0 {
             return false;
         }
 
-        let mut state = self.start_state();
+        let mut state = self.start_state_after(byte_before(bytes, start));
         if self.is_match_or_dead_state(state) {
             return self.is_match_state(state);
         }
-        for &b in bytes[start..].iter() {
-            state = unsafe { self.next_state_unchecked(state, b) };
+        let mut at = start;
+        while at < end {
+            let prev =
+                next_state_unrolled(self, &mut state, &bytes[..end], &mut at);
             if self.is_match_or_dead_state(state) {
                 return self.is_match_state(state);
+            } else if state == prev {
+                let needles = self.accelerator(state);
+                if !needles.is_empty() {
+                    at = find_needle(needles, &bytes[..end], at);
+                }
             }
         }
-        false
+        self.is_match_state(next_end_state(
+            self,
+            state,
+            bytes.get(end).cloned(),
+        ))
     }
 
-    /// Returns the same as `shortest_match`, but starts the search at the
-    /// given offset.
+    /// Returns the same as `shortest_match`, but only searches
+    /// `bytes[start..end]`.
     ///
-    /// The significance of the starting point is that it takes the surrounding
-    /// context into consideration. For example, if the DFA is anchored, then
-    /// a match can only occur when `start == 0`.
+    /// As with
+    /// [`is_match_in`](trait.DFA.html#method.is_match_in), the bytes
+    /// surrounding the range are taken into consideration.
+    ///
+    /// This panics if `start > end` or if `end > bytes.len()`.
     #[inline]
-    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+    fn shortest_match_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        assert!(start <= end && end <= bytes.len(), "invalid search range");
         if self.is_anchored() && start > 0 {
             return None;
         }
 
-        let mut state = self.start_state();
+        let mut state = self.start_state_after(byte_before(bytes, start));
         if self.is_match_or_dead_state(state) {
             return if self.is_dead_state(state) { None } else { Some(start) };
         }
-        for (i, &b) in bytes[start..].iter().enumerate() {
-            state = unsafe { self.next_state_unchecked(state, b) };
+        let delay = self.has_lookaround() as usize;
+        let mut at = start;
+        while at < end {
+            let prev =
+                next_state_unrolled(self, &mut state, &bytes[..end], &mut at);
             if self.is_match_or_dead_state(state) {
                 return if self.is_dead_state(state) {
                     None
                 } else {
-                    Some(start + i + 1)
+                    Some(at - delay)
                 };
+            } else if state == prev {
+                let needles = self.accelerator(state);
+                if !needles.is_empty() {
+                    at = find_needle(needles, &bytes[..end], at);
+                }
             }
         }
+        if self.is_match_state(next_end_state(
+            self,
+            state,
+            bytes.get(end).cloned(),
+        )) {
+            return Some(end);
+        }
         None
     }
 
-    /// Returns the same as `find`, but starts the search at the given
-    /// offset.
+    /// Returns the same as `find`, but only searches `bytes[start..end]`.
     ///
-    /// The significance of the starting point is that it takes the surrounding
-    /// context into consideration. For example, if the DFA is anchored, then
-    /// a match can only occur when `start == 0`.
+    /// As with
+    /// [`is_match_in`](trait.DFA.html#method.is_match_in), the bytes
+    /// surrounding the range are taken into consideration.
+    ///
+    /// This panics if `start > end` or if `end > bytes.len()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build(r"(?m)[a-z]+$")?;
+    /// let haystack = b"abc\nxyz123";
+    /// assert_eq!(Some(3), dfa.find_in(haystack, 0, 5));
+    /// // The end of the range is not the end of a line.
+    /// assert_eq!(None, dfa.find_in(haystack, 4, 7));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+    fn find_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        assert!(start <= end && end <= bytes.len(), "invalid search range");
         if self.is_anchored() && start > 0 {
             return None;
         }
 
-        let mut state = self.start_state();
+        let mut state = self.start_state_after(byte_before(bytes, start));
         let mut last_match = if self.is_dead_state(state) {
             return None;
         } else if self.is_match_state(state) {
@@ -274,77 +580,679 @@ pub trait DFA {
         } else {
             None
         };
-        for (i, &b) in bytes[start..].iter().enumerate() {
-            state = unsafe { self.next_state_unchecked(state, b) };
+        let delay = self.has_lookaround() as usize;
+        let mut at = start;
+        while at < end {
+            let prev =
+                next_state_unrolled(self, &mut state, &bytes[..end], &mut at);
             if self.is_match_or_dead_state(state) {
                 if self.is_dead_state(state) {
                     return last_match;
                 }
-                last_match = Some(start + i + 1);
+                last_match = Some(at - delay);
+            } else if state == prev {
+                let needles = self.accelerator(state);
+                if !needles.is_empty() {
+                    at = find_needle(needles, &bytes[..end], at);
+                }
             }
         }
+        if self.is_match_state(next_end_state(
+            self,
+            state,
+            bytes.get(end).cloned(),
+        )) {
+            last_match = Some(end);
+        }
         last_match
     }
 
-    /// Returns the same as `rfind`, but starts the search at the given
+    /// Returns the same as `try_find`, but starts the search at the given
     /// offset.
     ///
     /// The significance of the starting point is that it takes the surrounding
     /// context into consideration. For example, if the DFA is anchored, then
-    /// a match can only occur when `start == bytes.len()`.
-    #[inline(never)]
-    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        if self.is_anchored() && start < bytes.len() {
+    /// a match can only occur when `start == 0`.
+    #[inline]
+    fn try_find_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+    ) -> Result<Option<usize>, SearchError> {
+        if self.is_anchored() && start > 0 {
+            return Ok(None);
+        }
+
+        let prev = byte_before(bytes, start);
+        let mut state = self.start_state_after(prev);
+        let mut last_match = if self.is_dead_state(state) {
+            // With look-around, the byte before the start of the search
+            // determines the start state, and it may be a quit byte.
+            return match prev {
+                Some(byte) if self.is_quit_byte(byte) => {
+                    stats::quit();
+                    Err(SearchError::Quit { byte, offset: start - 1 })
+                }
+                _ => Ok(None),
+            };
+        } else if self.is_match_state(state) {
+            Some(start)
+        } else {
+            None
+        };
+        let delay = self.has_lookaround() as usize;
+        let mut at = start;
+        while at < bytes.len() {
+            let prev = next_state_unrolled(self, &mut state, bytes, &mut at);
+            let b = bytes[at - 1];
+            if self.is_match_or_dead_state(state) {
+                if self.is_dead_state(state) {
+                    if last_match.is_none() && self.is_quit_byte(b) {
+                        let offset = at - 1;
+                        stats::quit();
+                        return Err(SearchError::Quit { byte: b, offset });
+                    }
+                    return Ok(last_match);
+                }
+                last_match = Some(at - delay);
+            } else if state == prev {
+                let needles = self.accelerator(state);
+                if !needles.is_empty() {
+                    at = find_needle(needles, bytes, at);
+                }
+            }
+        }
+        if self.is_match_state(self.next_eoi_state(state)) {
+            last_match = Some(bytes.len());
+        }
+        Ok(last_match)
+    }
+
+    /// Returns the same as `find_pattern`, but starts the search at the
+    /// given offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == 0`.
+    #[inline]
+    fn find_pattern_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<(PatternID, usize)> {
+        if self.is_anchored() && start > 0 {
             return None;
         }
 
-        let mut state = self.start_state();
+        let mut state = self.start_state_after(byte_before(bytes, start));
         let mut last_match = if self.is_dead_state(state) {
             return None;
         } else if self.is_match_state(state) {
-            Some(start)
+            Some((self.match_pattern(state, 0), start))
         } else {
             None
         };
-        for (i, &b) in bytes[..start].iter().enumerate().rev() {
-            state = unsafe { self.next_state_unchecked(state, b) };
+        let delay = self.has_lookaround() as usize;
+        let mut at = start;
+        while at < bytes.len() {
+            let prev = next_state_unrolled(self, &mut state, bytes, &mut at);
             if self.is_match_or_dead_state(state) {
                 if self.is_dead_state(state) {
                     return last_match;
                 }
-                last_match = Some(i);
+                last_match = Some((self.match_pattern(state, 0), at - delay));
+            } else if state == prev {
+                let needles = self.accelerator(state);
+                if !needles.is_empty() {
+                    at = find_needle(needles, bytes, at);
+                }
             }
         }
+        let eoi = self.next_eoi_state(state);
+        if self.is_match_state(eoi) {
+            last_match = Some((self.match_pattern(eoi, 0), bytes.len()));
+        }
         last_match
     }
-}
-
-impl<'a, T: DFA> DFA for &'a T {
-    type ID = T::ID;
 
+    /// Returns the next overlapping match, along with the pattern that
+    /// matched, by resuming the search recorded in the given state. If no
+    /// further match exists, then `None` is returned.
+    ///
+    /// Unlike the other search routines, this reports every match of every
+    /// pattern, in the order of their end offsets. When more than one
+    /// pattern matches at the same end offset, each of them is reported in
+    /// the order given by `match_pattern`. Since the search resumes exactly
+    /// where the previous call stopped, reporting all matches takes a single
+    /// scan of the input.
+    ///
+    /// A fresh state, created by `OverlappingState::start`, begins the
+    /// search at `start`. Once a search has begun, `start` is ignored, and
+    /// the same input must be given to every call with the same state.
+    ///
+    /// Overlapping searches are only meaningful with a DFA that keeps
+    /// searching after a match is found, such as a dense DFA built with
+    /// [`dense::Builder::all_matches`](dense/struct.Builder.html#method.all_matches).
+    /// Otherwise, matches of lower priority than an earlier match, such as
+    /// matches starting after it, are never seen.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, OverlappingState, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .all_matches(true)
+    ///     .build_many(&["[a-z]+", "[a-z]+[0-9]"])?;
+    /// let mut state = OverlappingState::start();
+    /// let mut matches = vec![];
+    /// while let Some(m) = dfa.find_overlapping_at(b"ab1", 0, &mut state) {
+    ///     matches.push(m);
+    /// }
+    /// assert_eq!(matches, vec![(0, 1), (0, 2), (1, 3)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn start_state(&self) -> Self::ID {
-        (**self).start_state()
+    fn find_overlapping_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        state: &mut OverlappingState<Self::ID>,
+    ) -> Option<(PatternID, usize)> {
+        let (mut id, mut at) = match state.id {
+            Some(id) => (id, state.at),
+            None => {
+                if self.is_anchored() && start > 0 {
+                    state.id = Some(dead_id());
+                    return None;
+                }
+                let id = self.start_state_after(byte_before(bytes, start));
+                state.id = Some(id);
+                state.at = start;
+                state.end = start;
+                state.next_match = 0;
+                (id, start)
+            }
+        };
+        if self.is_dead_state(id) {
+            return None;
+        }
+        if state.next_match < self.match_count(id) {
+            state.next_match += 1;
+            let pattern = self.match_pattern(id, state.next_match - 1);
+            return Some((pattern, state.end));
+        }
+        let delay = self.has_lookaround() as usize;
+        while at < bytes.len() {
+            id = unsafe { self.next_state_unchecked(id, bytes[at]) };
+            at += 1;
+            stats::transitions(1);
+            if self.is_match_or_dead_state(id) {
+                state.id = Some(id);
+                state.at = at;
+                if self.is_dead_state(id) {
+                    return None;
+                }
+                state.end = at - delay;
+                state.next_match = 1;
+                return Some((self.match_pattern(id, 0), state.end));
+            }
+        }
+        state.at = at;
+        // Once the state reached at the end of the input has reported its
+        // matches, its own transition at the end of the input always leads
+        // to the dead state.
+        let eoi = self.next_eoi_state(id);
+        if self.is_match_state(eoi) {
+            id = eoi;
+            state.end = at;
+            state.next_match = 1;
+            state.id = Some(id);
+            return Some((self.match_pattern(id, 0), at));
+        }
+        state.id = Some(id);
+        None
     }
 
+    /// Returns an iterator over every overlapping match in the given input,
+    /// along with the pattern that matched.
+    ///
+    /// Each item yielded is a pair of the pattern that matched and the end
+    /// offset of the match. See
+    /// [`find_overlapping_at`](trait.DFA.html#method.find_overlapping_at)
+    /// for more details on the match semantics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().all_matches(true).build("a+")?;
+    /// let ends: Vec<usize> =
+    ///     dfa.find_overlapping_iter(b"baaa").map(|(_, end)| end).collect();
+    /// assert_eq!(ends, vec![2, 3, 4]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn is_match_state(&self, id: Self::ID) -> bool {
-        (**self).is_match_state(id)
+    fn find_overlapping_iter<'d, 't>(
+        &'d self,
+        bytes: &'t [u8],
+    ) -> OverlappingMatches<'d, 't, Self>
+    where
+        Self: Sized,
+    {
+        OverlappingMatches {
+            dfa: self,
+            bytes,
+            state: OverlappingState::start(),
+        }
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: Self::ID) -> bool {
-        (**self).is_match_or_dead_state(id)
+    /// Returns the set of patterns that match anywhere in the given input.
+    ///
+    /// This is like
+    /// [`find_overlapping_iter`](trait.DFA.html#method.find_overlapping_iter),
+    /// except it only reports which patterns matched, rather than where
+    /// each of their matches end. The input is scanned only once, regardless
+    /// of how many patterns there are.
+    ///
+    /// As with overlapping searches, every matching pattern is only reported
+    /// by a DFA that keeps searching after a match is found, such as a dense
+    /// DFA built with
+    /// [`dense::Builder::all_matches`](dense/struct.Builder.html#method.all_matches).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .all_matches(true)
+    ///     .build_many(&[r"\w+", r"\d+", r"[A-Z]+", r"foo"])?;
+    /// let set = dfa.which_overlapping_matches(b"abc 123");
+    /// assert_eq!(vec![0, 1], set.iter().collect::<Vec<_>>());
+    /// assert!(!set.contains(2));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn which_overlapping_matches(&self, bytes: &[u8]) -> PatternSet {
+        let mut set = PatternSet::new();
+        let mut state = OverlappingState::start();
+        while let Some((pattern, _)) =
+            self.find_overlapping_at(bytes, 0, &mut state)
+        {
+            set.insert(pattern);
+        }
+        set
     }
 
+    /// Returns an iterator over the end offsets of every non-overlapping
+    /// match in the given input.
+    ///
+    /// Each search resumes where the previous match ended. When a match is
+    /// empty, the search following it starts one byte later, and an empty
+    /// match ending where the previous match ended is never reported. This
+    /// guarantees that the iterator always makes progress.
+    ///
+    /// Since a DFA only reports where a match ends, this is typically used
+    /// with a DFA built with leftmost first semantics, which is the default.
+    /// To also find where each match starts, use
+    /// [`Regex::find_iter`](struct.Regex.html#method.find_iter).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build("foo[0-9]+")?;
+    /// let ends: Vec<usize> = dfa.find_iter(b"foo1 foo12 foo123").collect();
+    /// assert_eq!(ends, vec![4, 10, 17]);
+    ///
+    /// // Empty matches are reported, but never twice at the same offset.
+    /// let dfa = dense::Builder::new().build("a*")?;
+    /// let ends: Vec<usize> = dfa.find_iter(b"baab").collect();
+    /// assert_eq!(ends, vec![0, 3, 4]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn is_dead_state(&self, id: Self::ID) -> bool {
-        (**self).is_dead_state(id)
+    fn find_iter<'d, 't>(&'d self, bytes: &'t [u8]) -> MatchEnds<'d, 't, Self>
+    where
+        Self: Sized,
+    {
+        MatchEnds { it: self.find_leftmost_iter(bytes) }
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        (**self).is_anchored()
+    /// Returns an iterator over every non-overlapping match in the given
+    /// input, along with the pattern that matched.
+    ///
+    /// Each item yielded is a pair of the pattern that matched and the end
+    /// offset of the match. The matches reported are the same as the ones
+    /// reported by [`find_iter`](trait.DFA.html#method.find_iter), and the
+    /// pattern reported for each is the same as the one reported by
+    /// [`find_pattern_at`](trait.DFA.html#method.find_pattern_at).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build_many(&["[0-9]+", "[a-z]+"])?;
+    /// let matches: Vec<(usize, usize)> =
+    ///     dfa.find_leftmost_iter(b"abc 123 xyz").collect();
+    /// assert_eq!(matches, vec![(1, 3), (0, 7), (1, 11)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_leftmost_iter<'d, 't>(
+        &'d self,
+        bytes: &'t [u8],
+    ) -> LeftmostMatches<'d, 't, Self>
+    where
+        Self: Sized,
+    {
+        LeftmostMatches { dfa: self, bytes, last_end: 0, last_match: None }
+    }
+
+    /// Continue a search over a stream of input with the next chunk of the
+    /// stream.
+    ///
+    /// This permits searching input that isn't available as a single
+    /// contiguous slice, by feeding it to the DFA one chunk at a time. The
+    /// given state records the progress of the search between chunks. It is
+    /// created with `SearchState::start` and must be passed to every call for
+    /// the same stream, with the chunks given in order.
+    ///
+    /// This returns the end of the leftmost first match, as reported by
+    /// [`find`](trait.DFA.html#method.find) on the concatenation of every
+    /// chunk, as soon as it is known. The offset is relative to the beginning
+    /// of the stream. Otherwise, `None` is returned, and either more input is
+    /// needed, or the search is done and no match exists, as reported by
+    /// [`SearchState::is_done`](struct.SearchState.html#method.is_done).
+    ///
+    /// When the stream ends before the match is known, then the match is
+    /// given by
+    /// [`finish_stream`](trait.DFA.html#method.finish_stream).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, SearchState, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build("foo[0-9]+")?;
+    /// let mut state = SearchState::start();
+    /// assert_eq!(None, dfa.find_chunk(b"xyzfo", &mut state));
+    /// assert_eq!(None, dfa.find_chunk(b"o12", &mut state));
+    /// assert_eq!(Some(8), state.last_match());
+    /// // The match isn't known until it can't be extended anymore.
+    /// assert_eq!(Some(9), dfa.find_chunk(b"3 foo4", &mut state));
+    /// assert!(state.is_done());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_chunk(
+        &self,
+        chunk: &[u8],
+        state: &mut SearchState<Self::ID>,
+    ) -> Option<usize> {
+        if state.done {
+            return state.last_match;
+        }
+        let mut id = match state.id {
+            Some(id) => id,
+            None => {
+                let id = self.start_state_after(None);
+                if self.is_match_state(id) {
+                    state.last_match = Some(state.offset);
+                }
+                id
+            }
+        };
+        if self.is_dead_state(id) {
+            state.id = Some(id);
+            state.done = true;
+            return state.last_match;
+        }
+        let delay = self.has_lookaround() as usize;
+        for (i, &b) in chunk.iter().enumerate() {
+            // The first transition is checked, since the state may have been
+            // deserialized from arbitrary bytes. Every state after it comes
+            // from the DFA itself.
+            id = if i == 0 {
+                self.next_state(id, b)
+            } else {
+                unsafe { self.next_state_unchecked(id, b) }
+            };
+            stats::transitions(1);
+            if self.is_match_or_dead_state(id) {
+                if self.is_dead_state(id) {
+                    state.id = Some(id);
+                    state.offset += i;
+                    state.done = true;
+                    return state.last_match;
+                }
+                state.last_match = Some(state.offset + i + 1 - delay);
+            }
+        }
+        state.id = Some(id);
+        state.offset += chunk.len();
+        None
+    }
+
+    /// Finish a search over a stream of input once the stream has ended,
+    /// and return the end of the leftmost first match, if one exists.
+    ///
+    /// This must be called after the last chunk has been given to
+    /// [`find_chunk`](trait.DFA.html#method.find_chunk), unless the search is
+    /// already done. For a DFA with look-around, this reports a match ending
+    /// at the end of the stream, which can't be known before the stream
+    /// ends. Otherwise, this returns the same as
+    /// [`SearchState::last_match`](struct.SearchState.html#method.last_match).
+    ///
+    /// Once this is called, the search is done.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, SearchState, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build(r"foo(?-u:\b)")?;
+    /// let mut state = SearchState::start();
+    /// assert_eq!(None, dfa.find_chunk(b"xyz foo", &mut state));
+    /// assert_eq!(None, state.last_match());
+    /// assert_eq!(Some(7), dfa.finish_stream(&mut state));
+    /// assert!(state.is_done());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn finish_stream(
+        &self,
+        state: &mut SearchState<Self::ID>,
+    ) -> Option<usize> {
+        if state.done {
+            return state.last_match;
+        }
+        let id = match state.id {
+            Some(id) => id,
+            None => {
+                let id = self.start_state_after(None);
+                if self.is_match_state(id) {
+                    state.last_match = Some(state.offset);
+                }
+                id
+            }
+        };
+        let eoi = self.next_eoi_state(id);
+        if self.is_match_state(eoi) {
+            state.last_match = Some(state.offset);
+        }
+        state.id = Some(eoi);
+        state.done = true;
+        state.last_match
+    }
+
+    /// Returns true if and only if the given reader has a match.
+    ///
+    /// The reader is searched incrementally with a fixed size buffer, so the
+    /// input never needs to be in memory all at once. Reading stops as soon
+    /// as a match is found, or as soon as no match is possible.
+    ///
+    /// If there was a problem reading from the given reader, then an error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dfa = dense::Builder::new().build("foo[0-9]+")?;
+    /// assert!(dfa.is_match_reader(&b"xyz foo12"[..])?);
+    /// assert!(!dfa.is_match_reader(&b"xyz foo"[..])?);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn is_match_reader<R: io::Read>(&self, rdr: R) -> io::Result<bool>
+    where
+        Self: Sized,
+    {
+        let mut state = SearchState::start();
+        read_chunks(rdr, |chunk| {
+            self.find_chunk(chunk, &mut state);
+            state.is_done() || state.last_match().is_some()
+        })?;
+        if state.last_match().is_some() {
+            return Ok(true);
+        }
+        Ok(self.finish_stream(&mut state).is_some())
+    }
+
+    /// Returns the end offset of the leftmost first match in the given
+    /// reader, if one exists.
+    ///
+    /// This reports the same match as [`find`](trait.DFA.html#method.find)
+    /// on the entire contents of the reader. The reader is searched
+    /// incrementally with a fixed size buffer, so the input never needs to be
+    /// in memory all at once. Reading stops as soon as the match is known, or
+    /// as soon as no match is possible.
+    ///
+    /// If there was a problem reading from the given reader, then an error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dfa = dense::Builder::new().build("foo[0-9]+")?;
+    /// assert_eq!(Some(9), dfa.find_reader(&b"xyz foo12 foo3"[..])?);
+    /// assert_eq!(None, dfa.find_reader(&b"xyz foo"[..])?);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn find_reader<R: io::Read>(&self, rdr: R) -> io::Result<Option<usize>>
+    where
+        Self: Sized,
+    {
+        let mut state = SearchState::start();
+        read_chunks(rdr, |chunk| {
+            self.find_chunk(chunk, &mut state).is_some() || state.is_done()
+        })?;
+        Ok(self.finish_stream(&mut state))
+    }
+
+    /// Returns the same as `rfind`, but starts the search at the given
+    /// offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == bytes.len()`.
+    #[inline]
+    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        self.rfind_in(bytes, 0, start)
+    }
+
+    /// Returns the same as `rfind`, but only searches `bytes[start..end]`,
+    /// beginning at `end`.
+    ///
+    /// As with
+    /// [`is_match_in`](trait.DFA.html#method.is_match_in), the bytes
+    /// surrounding the range are taken into consideration. If the DFA is
+    /// anchored, then a match can only occur when `end == bytes.len()`.
+    ///
+    /// This panics if `start > end` or if `end > bytes.len()`.
+    #[inline(never)]
+    fn rfind_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        assert!(start <= end && end <= bytes.len(), "invalid search range");
+        if self.is_anchored() && end < bytes.len() {
+            return None;
+        }
+
+        let mut state = self.start_state_after(bytes.get(end).cloned());
+        let mut last_match = if self.is_dead_state(state) {
+            return None;
+        } else if self.is_match_state(state) {
+            Some(end)
+        } else {
+            None
+        };
+        let delay = self.has_lookaround() as usize;
+        for (i, &b) in bytes[start..end].iter().enumerate().rev() {
+            state = unsafe { self.next_state_unchecked(state, b) };
+            stats::transitions(1);
+            if self.is_match_or_dead_state(state) {
+                if self.is_dead_state(state) {
+                    return last_match;
+                }
+                last_match = Some(start + i + delay);
+            }
+        }
+        let prev = byte_before(bytes, start);
+        if self.is_match_state(next_end_state(self, state, prev)) {
+            last_match = Some(start);
+        }
+        last_match
+    }
+}
+
+impl<'a, T: DFA> DFA for &'a T {
+    type ID = T::ID;
+
+    #[inline]
+    fn start_state(&self) -> Self::ID {
+        (**self).start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: Self::ID) -> bool {
+        (**self).is_match_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: Self::ID) -> bool {
+        (**self).is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: Self::ID) -> bool {
+        (**self).is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        (**self).is_anchored()
     }
 
     #[inline]
@@ -360,4 +1268,599 @@ impl<'a, T: DFA> DFA for &'a T {
     ) -> Self::ID {
         (**self).next_state_unchecked(current, input)
     }
+
+    #[inline]
+    fn match_count(&self, id: Self::ID) -> usize {
+        (**self).match_count(id)
+    }
+
+    #[inline]
+    fn match_pattern(&self, id: Self::ID, index: usize) -> PatternID {
+        (**self).match_pattern(id, index)
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        (**self).is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: Self::ID) -> &[u8] {
+        (**self).accelerator(id)
+    }
+
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        (**self).is_utf8()
+    }
+
+    #[inline]
+    fn has_lookaround(&self) -> bool {
+        (**self).has_lookaround()
+    }
+
+    #[inline]
+    fn next_eoi_state(&self, current: Self::ID) -> Self::ID {
+        (**self).next_eoi_state(current)
+    }
+
+    #[inline]
+    fn start_state_after(&self, prev: Option<u8>) -> Self::ID {
+        (**self).start_state_after(prev)
+    }
+}
+
+/// The state of an overlapping search, which records where to resume it.
+///
+/// This is created with `OverlappingState::start` and then passed to each
+/// call of
+/// [`DFA::find_overlapping_at`](trait.DFA.html#method.find_overlapping_at)
+/// on the same input. The state is updated by each call, so that the next
+/// call reports the next match. Since the state is a small plain value, it
+/// can be copied to save a search and resume it later.
+///
+/// The type parameter `S` is the state identifier representation of the DFA
+/// being searched.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OverlappingState<S> {
+    /// The current DFA state, or `None` if the search hasn't begun.
+    id: Option<S>,
+    /// The offset of the next byte to read.
+    at: usize,
+    /// The end of the match reported by the current match state.
+    end: usize,
+    /// The index of the next pattern to report in the current match state.
+    next_match: usize,
+}
+
+impl<S: StateID> OverlappingState<S> {
+    /// Create the state of an overlapping search that hasn't begun yet.
+    pub fn start() -> OverlappingState<S> {
+        OverlappingState { id: None, at: 0, end: 0, next_match: 0 }
+    }
+
+    /// Returns the offset in the input at which the search will resume, or
+    /// `None` if the search hasn't begun.
+    pub fn offset(&self) -> Option<usize> {
+        self.id.map(|_| self.at)
+    }
+}
+
+/// The state of a search over a stream of input, which records where to
+/// resume it.
+///
+/// This is created with `SearchState::start` and then passed to each call of
+/// [`DFA::find_chunk`](trait.DFA.html#method.find_chunk) for the same stream.
+/// The state is updated by each call, so that the next call continues the
+/// search where the previous one stopped. Since the state is a small plain
+/// value, it can be copied to save a search and resume it later.
+///
+/// The type parameter `S` is the state identifier representation of the DFA
+/// being searched.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SearchState<S> {
+    /// The current DFA state, or `None` if the search hasn't begun.
+    pub(crate) id: Option<S>,
+    /// The number of bytes of the stream consumed so far.
+    pub(crate) offset: usize,
+    /// The end of the last match seen, if any.
+    pub(crate) last_match: Option<usize>,
+    /// Whether the search has entered a dead state.
+    pub(crate) done: bool,
+}
+
+impl<S: StateID> SearchState<S> {
+    /// Create the state of a stream search that hasn't begun yet.
+    pub fn start() -> SearchState<S> {
+        SearchState { id: None, offset: 0, last_match: None, done: false }
+    }
+
+    /// Returns the number of bytes of the stream consumed by the search so
+    /// far. Once the search is done, this no longer increases.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the end of the last match seen by the search so far, relative
+    /// to the beginning of the stream.
+    ///
+    /// Once the search is done, this is the end of the leftmost first match,
+    /// if one exists. For a DFA without look-around, this is also the case
+    /// once the stream has ended.
+    pub fn last_match(&self) -> Option<usize> {
+        self.last_match
+    }
+
+    /// Returns true if and only if feeding more input to the search can't
+    /// change its outcome, since the DFA has entered a dead state.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// A breakdown of the memory used by a DFA, per component.
+///
+/// This is returned by
+/// [`DenseDFA::memory_usage_breakdown`](enum.DenseDFA.html#method.memory_usage_breakdown)
+/// and
+/// [`SparseDFA::memory_usage_breakdown`](enum.SparseDFA.html#method.memory_usage_breakdown).
+/// The transition table and the match table are stored in the DFA's buffer,
+/// which is usually on the heap, while the remaining components are stored
+/// inline in the DFA itself. All sizes are in bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoryUsage {
+    pub(crate) transitions: usize,
+    pub(crate) matches: usize,
+    pub(crate) byte_classes: usize,
+    pub(crate) quit_bytes: usize,
+    pub(crate) accelerators: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the size of the transition table.
+    ///
+    /// For a sparse DFA, the transition table also records which patterns
+    /// match in each match state.
+    pub fn transitions(&self) -> usize {
+        self.transitions
+    }
+
+    /// Returns the size of the table recording which patterns match in each
+    /// match state. This is zero for a dense DFA built from a single pattern
+    /// and for every sparse DFA.
+    pub fn matches(&self) -> usize {
+        self.matches
+    }
+
+    /// Returns the size of the map from bytes to their equivalence classes.
+    pub fn byte_classes(&self) -> usize {
+        self.byte_classes
+    }
+
+    /// Returns the size of the set of bytes on which a search quits.
+    pub fn quit_bytes(&self) -> usize {
+        self.quit_bytes
+    }
+
+    /// Returns the size of the table of accelerated states.
+    pub fn accelerators(&self) -> usize {
+        self.accelerators
+    }
+
+    /// Returns the total size of the components stored in the DFA's buffer,
+    /// which is the same as the DFA's `memory_usage`.
+    pub fn heap(&self) -> usize {
+        self.transitions + self.matches
+    }
+
+    /// Returns the total size of all components.
+    pub fn total(&self) -> usize {
+        self.heap() + self.byte_classes + self.quit_bytes + self.accelerators
+    }
+}
+
+/// The start and end offsets of a match.
+///
+/// This is the result of searches that report where a match begins, such as
+/// [`Regex::find`](struct.Regex.html#method.find). The start offset is
+/// inclusive and the end offset is exclusive, so that a match can be used to
+/// slice the input it was found in via
+/// [`range`](struct.Match.html#method.range).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Match {
+    start: usize,
+    end: usize,
+}
+
+impl Match {
+    /// Create a new match from the given start and end offsets.
+    ///
+    /// This panics if `start > end`.
+    pub fn new(start: usize, end: usize) -> Match {
+        assert!(start <= end, "invalid match span");
+        Match { start, end }
+    }
+
+    /// Returns the starting offset of this match, inclusive.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending offset of this match, exclusive.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the span of this match as a range, which can be used to slice
+    /// the input that was searched.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the length, in bytes, of this match.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if and only if this match is empty.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// The pattern, start and end offsets of a match reported by a search over
+/// several patterns.
+///
+/// This is the result of searches that report which of several patterns
+/// matched, such as
+/// [`PrioritizedRegex::find_iter_prioritized`](struct.PrioritizedRegex.html#method.find_iter_prioritized).
+/// Otherwise, it is the same as a [`Match`](struct.Match.html), to which it
+/// can be converted with [`to_match`](struct.MultiMatch.html#method.to_match).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MultiMatch {
+    pattern: PatternID,
+    start: usize,
+    end: usize,
+}
+
+impl MultiMatch {
+    /// Create a new match of the given pattern from the given start and end
+    /// offsets.
+    ///
+    /// This panics if `start > end`.
+    pub fn new(pattern: PatternID, start: usize, end: usize) -> MultiMatch {
+        assert!(start <= end, "invalid match span");
+        MultiMatch { pattern, start, end }
+    }
+
+    /// Returns the identifier of the pattern that matched.
+    pub fn pattern(&self) -> PatternID {
+        self.pattern
+    }
+
+    /// Returns the starting offset of this match, inclusive.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending offset of this match, exclusive.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the span of this match as a range, which can be used to slice
+    /// the input that was searched.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the length, in bytes, of this match.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if and only if this match is empty.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the span of this match without its pattern.
+    pub fn to_match(&self) -> Match {
+        Match { start: self.start, end: self.end }
+    }
+}
+
+/// A set of patterns, as reported by
+/// [`DFA::which_overlapping_matches`](trait.DFA.html#method.which_overlapping_matches).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PatternSet {
+    /// Whether each pattern is in this set, indexed by pattern identifier.
+    /// This is never longer than the largest pattern in the set plus one.
+    which: Vec<bool>,
+    /// The number of patterns in this set.
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl PatternSet {
+    /// Create a new empty set of patterns.
+    pub fn new() -> PatternSet {
+        PatternSet::default()
+    }
+
+    /// Add the given pattern to this set. This returns true if and only if
+    /// the pattern was not already in this set.
+    pub fn insert(&mut self, pattern: PatternID) -> bool {
+        if pattern >= self.which.len() {
+            self.which.resize(pattern + 1, false);
+        }
+        if self.which[pattern] {
+            return false;
+        }
+        self.which[pattern] = true;
+        self.len += 1;
+        true
+    }
+
+    /// Returns true if and only if the given pattern is in this set.
+    pub fn contains(&self, pattern: PatternID) -> bool {
+        self.which.get(pattern) == Some(&true)
+    }
+
+    /// Returns the number of patterns in this set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if and only if this set contains no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the patterns in this set, in ascending order.
+    pub fn iter<'a>(&'a self) -> PatternSetIter<'a> {
+        PatternSetIter { it: self.which.iter().enumerate() }
+    }
+}
+
+/// An iterator over the patterns in a
+/// [`PatternSet`](struct.PatternSet.html), in ascending order.
+///
+/// This iterator is created by
+/// [`PatternSet::iter`](struct.PatternSet.html#method.iter).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct PatternSetIter<'a> {
+    it: iter::Enumerate<slice::Iter<'a, bool>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> Iterator for PatternSetIter<'a> {
+    type Item = PatternID;
+
+    fn next(&mut self) -> Option<PatternID> {
+        for (pattern, &yes) in &mut self.it {
+            if yes {
+                return Some(pattern);
+            }
+        }
+        None
+    }
+}
+
+/// Return the state reached once a search has consumed every byte in its
+/// range, given the byte just beyond the range, or `None` when the range
+/// extends to the edge of the input.
+///
+/// A DFA with look-around only knows whether a match ends at the edge of the
+/// range once it has seen the byte beyond it, if any. Every other DFA gets a
+/// dead state.
+#[inline(always)]
+pub(crate) fn next_end_state<D: DFA + ?Sized>(
+    dfa: &D,
+    current: D::ID,
+    next: Option<u8>,
+) -> D::ID {
+    match next {
+        Some(b) if dfa.has_lookaround() => dfa.next_state(current, b),
+        _ => dfa.next_eoi_state(current),
+    }
+}
+
+/// Run the given DFA over `bytes` from `*at`, updating `state` and
+/// advancing `*at` past the bytes consumed, and return the state preceding
+/// the last transition taken.
+///
+/// At least one transition is always taken, so `*at` must be less than
+/// `bytes.len()`. As long as each transition leads to a state that is
+/// neither a match state, a dead state nor the state it came from, no work
+/// is needed beyond following it, so when the `unroll` feature is enabled,
+/// further transitions are taken four bytes at a time until one of them
+/// needs attention from the caller. Unrolling the loop permits loading the
+/// bytes and computing the offsets of the transitions without waiting on the
+/// branch taken after each one, which matters most on CPUs without a deep
+/// speculative pipeline, such as many aarch64 cores. Without the feature,
+/// exactly one transition is taken.
+///
+/// Every transition computed is taken, since a lazy DFA may invalidate the
+/// state it came from when computing it.
+#[inline(always)]
+pub(crate) fn next_state_unrolled<D: DFA + ?Sized>(
+    dfa: &D,
+    state: &mut D::ID,
+    bytes: &[u8],
+    at: &mut usize,
+) -> D::ID {
+    let (mut prev, mut cur, mut i) = (*state, *state, *at);
+    cur = unsafe { dfa.next_state_unchecked(cur, bytes[i]) };
+    i += 1;
+    if cfg!(feature = "unroll") {
+        while i + 4 <= bytes.len()
+            && cur != prev
+            && !dfa.is_match_or_dead_state(cur)
+        {
+            let s1 = unsafe { dfa.next_state_unchecked(cur, bytes[i]) };
+            if s1 == cur || dfa.is_match_or_dead_state(s1) {
+                prev = cur;
+                cur = s1;
+                i += 1;
+                break;
+            }
+            let s2 = unsafe { dfa.next_state_unchecked(s1, bytes[i + 1]) };
+            if s2 == s1 || dfa.is_match_or_dead_state(s2) {
+                prev = s1;
+                cur = s2;
+                i += 2;
+                break;
+            }
+            let s3 = unsafe { dfa.next_state_unchecked(s2, bytes[i + 2]) };
+            if s3 == s2 || dfa.is_match_or_dead_state(s3) {
+                prev = s2;
+                cur = s3;
+                i += 3;
+                break;
+            }
+            prev = s3;
+            cur = unsafe { dfa.next_state_unchecked(s3, bytes[i + 3]) };
+            i += 4;
+        }
+    }
+    stats::transitions(i - *at);
+    *state = cur;
+    *at = i;
+    prev
+}
+
+/// Returns the byte immediately preceding the given offset, or `None` when
+/// the offset is at the start of the input.
+#[inline(always)]
+pub(crate) fn byte_before(bytes: &[u8], at: usize) -> Option<u8> {
+    if at == 0 {
+        None
+    } else {
+        Some(bytes[at - 1])
+    }
+}
+
+/// Read the given reader in chunks, and call the given closure with each
+/// chunk until either the reader is exhausted or the closure returns true.
+#[cfg(feature = "std")]
+fn read_chunks<R: io::Read, F: FnMut(&[u8]) -> bool>(
+    mut rdr: R,
+    mut f: F,
+) -> io::Result<()> {
+    let mut buf = vec![0; 8 * (1 << 10)];
+    loop {
+        let n = match rdr.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                continue
+            }
+            Err(err) => return Err(err),
+        };
+        if f(&buf[..n]) {
+            return Ok(());
+        }
+    }
+}
+
+/// An iterator over every overlapping match in a byte string.
+///
+/// Each item yielded is a pair of the pattern that matched and the end
+/// offset of the match.
+///
+/// This iterator is created by
+/// [`DFA::find_overlapping_iter`](trait.DFA.html#method.find_overlapping_iter).
+///
+/// The lifetime variables are as follows:
+///
+/// * `'d` is the lifetime of the DFA being searched.
+/// * `'t` is the lifetime of the input being searched.
+#[derive(Clone, Debug)]
+pub struct OverlappingMatches<'d, 't, D: DFA + 'd> {
+    dfa: &'d D,
+    bytes: &'t [u8],
+    state: OverlappingState<D::ID>,
+}
+
+impl<'d, 't, D: DFA> Iterator for OverlappingMatches<'d, 't, D> {
+    type Item = (PatternID, usize);
+
+    fn next(&mut self) -> Option<(PatternID, usize)> {
+        self.dfa.find_overlapping_at(self.bytes, 0, &mut self.state)
+    }
+}
+
+/// An iterator over every non-overlapping match in a byte string, along with
+/// the pattern that matched.
+///
+/// Each item yielded is a pair of the pattern that matched and the end
+/// offset of the match.
+///
+/// This iterator is created by
+/// [`DFA::find_leftmost_iter`](trait.DFA.html#method.find_leftmost_iter).
+///
+/// The lifetime variables are as follows:
+///
+/// * `'d` is the lifetime of the DFA being searched.
+/// * `'t` is the lifetime of the input being searched.
+#[derive(Clone, Debug)]
+pub struct LeftmostMatches<'d, 't, D: DFA + 'd> {
+    dfa: &'d D,
+    bytes: &'t [u8],
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl<'d, 't, D: DFA> Iterator for LeftmostMatches<'d, 't, D> {
+    type Item = (PatternID, usize);
+
+    fn next(&mut self) -> Option<(PatternID, usize)> {
+        while self.last_end <= self.bytes.len() {
+            let (pattern, end) =
+                match self.dfa.find_pattern_at(self.bytes, self.last_end) {
+                    None => {
+                        self.last_end = self.bytes.len() + 1;
+                        return None;
+                    }
+                    Some(m) => m,
+                };
+            if Some(end) == self.last_match {
+                // This can only be an empty match immediately following the
+                // previous match, so skip it and start the next search one
+                // byte later to ensure we make progress.
+                self.last_end = end + 1;
+                continue;
+            }
+            self.last_end = end;
+            self.last_match = Some(end);
+            return Some((pattern, end));
+        }
+        None
+    }
+}
+
+/// An iterator over the end offsets of every non-overlapping match in a byte
+/// string.
+///
+/// This iterator is created by
+/// [`DFA::find_iter`](trait.DFA.html#method.find_iter).
+///
+/// The lifetime variables are as follows:
+///
+/// * `'d` is the lifetime of the DFA being searched.
+/// * `'t` is the lifetime of the input being searched.
+#[derive(Clone, Debug)]
+pub struct MatchEnds<'d, 't, D: DFA + 'd> {
+    it: LeftmostMatches<'d, 't, D>,
+}
+
+impl<'d, 't, D: DFA> Iterator for MatchEnds<'d, 't, D> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.it.next().map(|(_, end)| end)
+    }
 }
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::slice;
+
+/// Non-fatal warnings reported while building a DFA or a regex.
+///
+/// Some configurations permit a builder to succeed by quietly doing
+/// something other than what was asked, e.g., by compiling a Unicode word
+/// boundary as an ASCII one, or by giving up on a prefilter because a
+/// pattern has too many prefix literals. The result is still correct, but
+/// it may quit on inputs it was expected to handle, or search much more
+/// slowly than expected. Builders report each such downgrade here, beside
+/// the DFA or regex they return, e.g., from
+/// [`dense::Builder::build_with_diagnostics`](dense/struct.Builder.html#method.build_with_diagnostics)
+/// or
+/// [`RegexBuilder::build_with_diagnostics`](struct.RegexBuilder.html#method.build_with_diagnostics).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense, BuildWarning};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let (_, diagnostics) = dense::Builder::new()
+///     .unicode_word_boundary(true)
+///     .build_with_diagnostics(r"\bfoo\b")?;
+/// assert_eq!(
+///     &[BuildWarning::UnicodeWordBoundaryAsAscii],
+///     diagnostics.warnings(),
+/// );
+///
+/// let (_, diagnostics) = dense::Builder::new()
+///     .unicode_word_boundary(true)
+///     .build_with_diagnostics(r"(?-u:\b)foo")?;
+/// assert!(diagnostics.is_empty());
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Diagnostics {
+    warnings: Vec<BuildWarning>,
+}
+
+/// A single warning reported while building a DFA or a regex.
+///
+/// Each warning describes a condition under which a build succeeded but
+/// behaves differently from what its configuration might suggest. New
+/// variants may be added in the future.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BuildWarning {
+    /// The pattern contains a Unicode word boundary, which was compiled as
+    /// an ASCII word boundary, and every non-ASCII byte became a
+    /// [quit byte](dense/struct.Builder.html#method.quit). Searches quit
+    /// on any haystack containing non-ASCII text. See
+    /// [`unicode_word_boundary`](dense/struct.Builder.html#method.unicode_word_boundary).
+    UnicodeWordBoundaryAsAscii,
+    /// The pattern has prefix literals, but there were too many of them, or
+    /// they were too long, for a prefilter to search for them. The
+    /// prefilter, if any, only knows of a literal that every match contains
+    /// or ends with, or no prefilter is used at all.
+    PrefilterLiteralLimit,
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl Diagnostics {
+    /// Create an empty set of diagnostics.
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    /// Returns the warnings reported, in the order they were first
+    /// reported. Each warning is reported at most once.
+    pub fn warnings(&self) -> &[BuildWarning] {
+        &self.warnings
+    }
+
+    /// Returns true if and only if no warnings were reported.
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Report the given warning, unless it was already reported.
+    pub(crate) fn warn(&mut self, warning: BuildWarning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a BuildWarning;
+    type IntoIter = slice::Iter<'a, BuildWarning>;
+
+    fn into_iter(self) -> slice::Iter<'a, BuildWarning> {
+        self.warnings.iter()
+    }
+}
+
+impl fmt::Display for BuildWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildWarning::UnicodeWordBoundaryAsAscii => write!(
+                f,
+                "Unicode word boundary downgraded to an ASCII word \
+                 boundary, searches quit on non-ASCII bytes"
+            ),
+            BuildWarning::PrefilterLiteralLimit => write!(
+                f,
+                "prefix literals exceed the prefilter's limits, \
+                 prefilter doesn't use them"
+            ),
+            BuildWarning::__Nonexhaustive => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::ascii;
+
+/// A writer for a directed graph in the Graphviz DOT language.
+///
+/// Nodes are identified by the index of the state they correspond to. Each
+/// edge is labelled with a list of byte ranges, so that all of the
+/// transitions between a pair of states are drawn as a single edge.
+pub struct Writer {
+    buf: String,
+}
+
+impl Writer {
+    /// Begin a new graph.
+    pub fn new() -> Writer {
+        let mut buf = String::new();
+        buf.push_str("digraph {\n");
+        buf.push_str("  rankdir=LR;\n");
+        buf.push_str("  node [shape=circle];\n");
+        Writer { buf }
+    }
+
+    /// Add an arrow that points at the given start state from nowhere, with
+    /// an optional label. The name must be unique among start arrows.
+    pub fn start(&mut self, name: &str, id: usize, label: Option<&str>) {
+        self.buf.push_str(&format!("  {} [shape=point];\n", name));
+        self.buf.push_str(&format!("  {} -> {}", name, id));
+        if let Some(label) = label {
+            self.buf.push_str(&format!(" [label=\"{}\"]", quote(label)));
+        }
+        self.buf.push_str(";\n");
+    }
+
+    /// Add a state with the given label. If `shape` is given, then it
+    /// overrides the default circle.
+    pub fn node(&mut self, id: usize, label: &str, shape: Option<&str>) {
+        self.buf.push_str(&format!("  {} [label=\"{}\"", id, quote(label)));
+        if let Some(shape) = shape {
+            self.buf.push_str(&format!(", shape={}", shape));
+        }
+        self.buf.push_str("];\n");
+    }
+
+    /// Add a transition between two states on the given inclusive byte
+    /// ranges.
+    pub fn edge(&mut self, from: usize, to: usize, ranges: &[(u8, u8)]) {
+        self.buf.push_str(&format!(
+            "  {} -> {} [label=\"{}\"];\n",
+            from,
+            to,
+            quote(&ranges_label(ranges))
+        ));
+    }
+
+    /// Add an epsilon transition between two states. The given priority,
+    /// where smaller is preferred, is used as the label.
+    pub fn epsilon(&mut self, from: usize, to: usize, priority: usize) {
+        self.buf.push_str(&format!(
+            "  {} -> {} [label=\"ε{}\", style=dashed];\n",
+            from, to, priority
+        ));
+    }
+
+    /// Add an epsilon transition between two states that may only be
+    /// followed where the given look-around assertion holds. The assertion
+    /// is used as the label.
+    pub fn look(&mut self, from: usize, to: usize, assertion: &str) {
+        self.buf.push_str(&format!(
+            "  {} -> {} [label=\"{}\", style=dashed];\n",
+            from,
+            to,
+            quote(assertion)
+        ));
+    }
+
+    /// Add the transition taken at the end of the input between two states.
+    pub fn eoi(&mut self, from: usize, to: usize) {
+        self.buf.push_str(&format!(
+            "  {} -> {} [label=\"EOI\", style=dotted];\n",
+            from, to
+        ));
+    }
+
+    /// Finish the graph and return it.
+    pub fn finish(mut self) -> String {
+        self.buf.push_str("}\n");
+        self.buf
+    }
+}
+
+/// Group the given transitions, which must be given for every byte in
+/// ascending order, into the byte ranges leading to each state. Transitions
+/// to `skip`, usually the dead state, are dropped. The states are returned in
+/// the order in which they're first seen.
+pub fn group_ranges<I>(
+    transitions: I,
+    skip: usize,
+) -> Vec<(usize, Vec<(u8, u8)>)>
+where
+    I: IntoIterator<Item = (u8, usize)>,
+{
+    let mut groups: Vec<(usize, Vec<(u8, u8)>)> = vec![];
+    let mut last: Option<(u8, usize)> = None;
+    for (b, next) in transitions {
+        let contiguous = match last {
+            Some((prev, prev_next)) => prev_next == next && prev + 1 == b,
+            None => false,
+        };
+        last = Some((b, next));
+        if next == skip {
+            continue;
+        }
+        if contiguous {
+            let group = groups.iter_mut().find(|g| g.0 == next).unwrap();
+            group.1.last_mut().unwrap().1 = b;
+            continue;
+        }
+        match groups.iter_mut().find(|g| g.0 == next) {
+            Some(group) => group.1.push((b, b)),
+            None => groups.push((next, vec![(b, b)])),
+        }
+    }
+    groups
+}
+
+/// Format the given byte ranges as a comma separated list, where each byte
+/// is escaped in the same way as `std::ascii::escape_default`.
+fn ranges_label(ranges: &[(u8, u8)]) -> String {
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                escape(start)
+            } else {
+                format!("{}-{}", escape(start), escape(end))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Escape the given text for use in a quoted DOT string.
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Return the given byte as its escaped string form.
+fn escape(b: u8) -> String {
+    String::from_utf8(ascii::escape_default(b).collect::<Vec<_>>()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_ranges, quote, ranges_label};
+
+    #[test]
+    fn groups() {
+        let trans = (0..=255u8).map(|b| match b {
+            b'a'..=b'c' | b'x' => (b, 1),
+            b'0'..=b'9' => (b, 2),
+            _ => (b, 0),
+        });
+        let groups = group_ranges(trans, 0);
+        assert_eq!(
+            groups,
+            vec![
+                (2, vec![(b'0', b'9')]),
+                (1, vec![(b'a', b'c'), (b'x', b'x')]),
+            ]
+        );
+    }
+
+    #[test]
+    fn labels() {
+        let label = ranges_label(&[(b'a', b'z'), (b'"', b'"'), (0, 0)]);
+        assert_eq!(r#"a-z, \", \x00"#, label);
+        assert_eq!(r#"a-z, \\\", \\x00"#, quote(&label));
+    }
+}
@@ -1,33 +1,58 @@
+use core::fmt;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
+#[cfg(feature = "std")]
+use std::ops::Range;
+#[cfg(feature = "std")]
 use std::result;
 
+#[cfg(feature = "std")]
 use regex_syntax;
 
+#[cfg(feature = "std")]
 pub type Result<T> = result::Result<T, Error>;
 
 /// An error that occurred during the construction of a DFA.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 pub struct Error {
     kind: ErrorKind,
 }
 
 /// The kind of error that occurred.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 pub enum ErrorKind {
     /// An error that occurred while parsing a regular expression. Note that
-    /// this error may be printed over multiple lines, and is generally
+    /// the message may be printed over multiple lines, and is generally
     /// intended to be end user readable on its own.
-    Syntax(String),
+    Syntax {
+        /// A description of the error, which includes the pattern and
+        /// points at the span of the error in it.
+        message: String,
+        /// The span of the pattern in which the error occurred, as a range
+        /// of byte offsets into the pattern.
+        ///
+        /// When a DFA is built from several patterns, this is relative to
+        /// the pattern that failed to parse.
+        span: Option<Range<usize>>,
+    },
     /// An error that occurred because an unsupported regex feature was used.
     /// The message string describes which unsupported feature was used.
     ///
-    /// The primary regex features that are unsupported are those that require
-    /// look-around, such as the `^` and `$` anchors and the word boundary
-    /// assertion `\b`. These may be supported in the future.
+    /// The primary regex features that are unsupported are the Unicode word
+    /// boundary assertions `\b` and `\B` in a DFA, and every look-around
+    /// assertion, such as `^`, `$` or `(?-u:\b)`, in a sparse or lazy DFA.
+    /// A [`BoundedBacktracker`](backtrack/struct.BoundedBacktracker.html)
+    /// supports every look-around assertion.
     Unsupported(String),
     /// An error that occurred when attempting to serialize a DFA to bytes.
     Serialize(String),
+    /// An error that occurred because the parts given to
+    /// [`dense::Builder::build_from_parts`](dense/struct.Builder.html#method.build_from_parts)
+    /// don't describe a valid DFA. The message string describes which part
+    /// is invalid.
+    InvalidParts(String),
     /// An error that occurs when constructing a DFA would require the use of
     /// a state ID that overflows the chosen state ID representation. For
     /// example, if one is using `u8` for state IDs and builds a DFA with
@@ -40,6 +65,10 @@ pub enum ErrorKind {
     StateIDOverflow {
         /// The maximum possible state ID.
         max: usize,
+        /// The number of states that needed an identifier, which is one more
+        /// than the largest identifier required. This saturates at
+        /// `usize::MAX`.
+        count: usize,
     },
     /// An error that occurs when premultiplication of state IDs is requested,
     /// but doing so would overflow the chosen state ID representation.
@@ -51,31 +80,166 @@ pub enum ErrorKind {
         /// The maximum ID required by premultiplication.
         requested_max: usize,
     },
+    /// An error that occurs when determinization is aborted because the DFA
+    /// being built exceeded the configured size limit.
+    ///
+    /// The size limit is set with
+    /// [`dense::Builder::dfa_size_limit`](dense/struct.Builder.html#method.dfa_size_limit).
+    DFASizeLimitExceeded {
+        /// The size limit, in bytes.
+        limit: usize,
+        /// The approximate size of the DFA and of the states being built
+        /// when the limit was exceeded, in bytes.
+        actual: usize,
+    },
+    /// An error that occurs when compiling a pattern to an NFA is aborted
+    /// because the NFA exceeded the configured size limit.
+    ///
+    /// The size limit is set with
+    /// [`dense::Builder::nfa_size_limit`](dense/struct.Builder.html#method.nfa_size_limit).
+    NFASizeLimitExceeded {
+        /// The size limit, in bytes.
+        limit: usize,
+        /// The approximate size of the NFA when the limit was exceeded, in
+        /// bytes.
+        actual: usize,
+    },
+    /// An error that occurs when building a DFA is abandoned, either because
+    /// the configured time limit elapsed or because the cancellation flag
+    /// was set.
+    ///
+    /// These are set with
+    /// [`dense::Builder::time_limit`](dense/struct.Builder.html#method.time_limit)
+    /// and
+    /// [`dense::Builder::cancel_flag`](dense/struct.Builder.html#method.cancel_flag).
+    Cancelled,
 }
 
+#[cfg(feature = "std")]
 impl Error {
     /// Return the kind of this error.
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
 
+    /// Returns the span of the pattern in which a syntax error occurred, as
+    /// a range of byte offsets into the pattern.
+    ///
+    /// This returns `None` for errors that aren't syntax errors, and for
+    /// syntax errors that aren't attributed to a particular part of the
+    /// pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// let err = Regex::new("foo(bar").unwrap_err();
+    /// assert_eq!(Some(3..4), err.span());
+    /// ```
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self.kind {
+            ErrorKind::Syntax { ref span, .. } => span.clone(),
+            _ => None,
+        }
+    }
+
+    /// Returns the size limit, in bytes, that was exceeded while building a
+    /// DFA or compiling an NFA.
+    ///
+    /// This returns `None` for errors other than
+    /// [`DFASizeLimitExceeded`](enum.ErrorKind.html#variant.DFASizeLimitExceeded)
+    /// and
+    /// [`NFASizeLimitExceeded`](enum.ErrorKind.html#variant.NFASizeLimitExceeded).
+    pub fn size_limit(&self) -> Option<usize> {
+        match self.kind {
+            ErrorKind::DFASizeLimitExceeded { limit, .. }
+            | ErrorKind::NFASizeLimitExceeded { limit, .. } => Some(limit),
+            _ => None,
+        }
+    }
+
+    /// Returns the approximate size, in bytes, reached by the DFA or NFA
+    /// being built when it exceeded its size limit.
+    ///
+    /// This returns `None` for errors other than
+    /// [`DFASizeLimitExceeded`](enum.ErrorKind.html#variant.DFASizeLimitExceeded)
+    /// and
+    /// [`NFASizeLimitExceeded`](enum.ErrorKind.html#variant.NFASizeLimitExceeded).
+    pub fn actual_size(&self) -> Option<usize> {
+        match self.kind {
+            ErrorKind::DFASizeLimitExceeded { actual, .. }
+            | ErrorKind::NFASizeLimitExceeded { actual, .. } => Some(actual),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of states that needed an identifier when the
+    /// chosen state identifier representation was too small.
+    ///
+    /// This returns `None` for errors other than
+    /// [`StateIDOverflow`](enum.ErrorKind.html#variant.StateIDOverflow).
+    pub fn state_count(&self) -> Option<usize> {
+        match self.kind {
+            ErrorKind::StateIDOverflow { count, .. } => Some(count),
+            _ => None,
+        }
+    }
+
     pub(crate) fn syntax(err: regex_syntax::Error) -> Error {
-        Error { kind: ErrorKind::Syntax(err.to_string()) }
+        let span = match err {
+            regex_syntax::Error::Parse(ref err) => Some(err.span()),
+            regex_syntax::Error::Translate(ref err) => Some(err.span()),
+            _ => None,
+        };
+        Error {
+            kind: ErrorKind::Syntax {
+                message: err.to_string(),
+                span: span.map(|span| span.start.offset..span.end.offset),
+            },
+        }
     }
 
-    pub(crate) fn unsupported_anchor() -> Error {
-        let msg = r"anchors such as ^, $, \A and \z are not supported";
+    pub(crate) fn unsupported_word() -> Error {
+        let msg = "Unicode word boundary assertions (\\b and \\B) are not \
+                   supported in a DFA, use (?-u:\\b) and (?-u:\\B), \
+                   enable unicode_word_boundary or use a \
+                   BoundedBacktracker instead";
         Error { kind: ErrorKind::Unsupported(msg.to_string()) }
     }
 
-    pub(crate) fn unsupported_word() -> Error {
-        let msg = r"word boundary assertions (\b and \B) are not supported";
+    pub(crate) fn unsupported_multiple_patterns() -> Error {
+        let msg = "sparse DFAs built from multiple patterns are not supported";
+        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+    }
+
+    pub(crate) fn unsupported_sparse_lookaround() -> Error {
+        let msg = "sparse DFAs with look-around assertions, such as anchors \
+                   and word boundaries, are not supported";
+        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+    }
+
+    pub(crate) fn unsupported_lazy_look() -> Error {
+        let msg = "lazy DFAs with look-around assertions, such as anchors \
+                   and word boundaries, are not supported";
+        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+    }
+
+    pub(crate) fn unsupported_shared_byte_classes() -> Error {
+        let msg = "the shared byte classes don't distinguish every byte \
+                   that the pattern or the quit bytes distinguish";
+        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+    }
+
+    pub(crate) fn unsupported_product() -> Error {
+        let msg = "intersection, union and complement are only supported on \
+                   anchored DFAs without look-around assertions or quit bytes";
         Error { kind: ErrorKind::Unsupported(msg.to_string()) }
     }
 
-    pub(crate) fn unsupported_longest_match() -> Error {
-        let msg = "unachored searches with longest match \
-                   semantics are not supported";
+    pub(crate) fn unsupported_priorities() -> Error {
+        let msg = "pattern priorities are only supported with leftmost \
+                   longest match semantics or when keeping all matches";
         Error { kind: ErrorKind::Unsupported(msg.to_string()) }
     }
 
@@ -83,8 +247,12 @@ impl Error {
         Error { kind: ErrorKind::Serialize(message.to_string()) }
     }
 
-    pub(crate) fn state_id_overflow(max: usize) -> Error {
-        Error { kind: ErrorKind::StateIDOverflow { max } }
+    pub(crate) fn invalid_parts(message: String) -> Error {
+        Error { kind: ErrorKind::InvalidParts(message) }
+    }
+
+    pub(crate) fn state_id_overflow(max: usize, count: usize) -> Error {
+        Error { kind: ErrorKind::StateIDOverflow { max, count } }
     }
 
     pub(crate) fn premultiply_overflow(
@@ -93,38 +261,69 @@ impl Error {
     ) -> Error {
         Error { kind: ErrorKind::PremultiplyOverflow { max, requested_max } }
     }
+
+    pub(crate) fn dfa_size_limit_exceeded(
+        limit: usize,
+        actual: usize,
+    ) -> Error {
+        Error { kind: ErrorKind::DFASizeLimitExceeded { limit, actual } }
+    }
+
+    pub(crate) fn nfa_size_limit_exceeded(
+        limit: usize,
+        actual: usize,
+    ) -> Error {
+        Error { kind: ErrorKind::NFASizeLimitExceeded { limit, actual } }
+    }
+
+    pub(crate) fn cancelled() -> Error {
+        Error { kind: ErrorKind::Cancelled }
+    }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn description(&self) -> &str {
         match self.kind {
-            ErrorKind::Syntax(_) => "syntax error",
+            ErrorKind::Syntax { .. } => "syntax error",
             ErrorKind::Unsupported(_) => "unsupported syntax",
             ErrorKind::Serialize(_) => "serialization error",
+            ErrorKind::InvalidParts(_) => "invalid DFA parts",
             ErrorKind::StateIDOverflow { .. } => {
                 "state id representation too small"
             }
             ErrorKind::PremultiplyOverflow { .. } => {
                 "state id representation too small for premultiplication"
             }
+            ErrorKind::DFASizeLimitExceeded { .. } => {
+                "DFA exceeded size limit"
+            }
+            ErrorKind::NFASizeLimitExceeded { .. } => {
+                "NFA exceeded size limit"
+            }
+            ErrorKind::Cancelled => "DFA construction cancelled",
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.kind {
-            ErrorKind::Syntax(ref msg) => write!(f, "{}", msg),
+            ErrorKind::Syntax { ref message, .. } => write!(f, "{}", message),
             ErrorKind::Unsupported(ref msg) => write!(f, "{}", msg),
             ErrorKind::Serialize(ref msg) => {
                 write!(f, "DFA serialization error: {}", msg)
             }
-            ErrorKind::StateIDOverflow { max } => write!(
+            ErrorKind::InvalidParts(ref msg) => {
+                write!(f, "invalid DFA parts: {}", msg)
+            }
+            ErrorKind::StateIDOverflow { max, count } => write!(
                 f,
-                "building the DFA failed because it required building \
-                 more states that can be identified, where the maximum \
-                 ID for the chosen representation is {}",
-                max,
+                "building the DFA failed because it required identifying \
+                 {} states, where the maximum ID for the chosen \
+                 representation is {}",
+                count, max,
             ),
             ErrorKind::PremultiplyOverflow { max, requested_max } => {
                 if max == requested_max {
@@ -145,6 +344,345 @@ impl fmt::Display for Error {
                     )
                 }
             }
+            ErrorKind::DFASizeLimitExceeded { limit, actual } => write!(
+                f,
+                "building the DFA failed because it exceeded the size \
+                 limit of {} bytes, reaching {} bytes",
+                limit, actual,
+            ),
+            ErrorKind::NFASizeLimitExceeded { limit, actual } => write!(
+                f,
+                "compiling the NFA failed because it exceeded the size \
+                 limit of {} bytes, reaching {} bytes",
+                limit, actual,
+            ),
+            ErrorKind::Cancelled => {
+                write!(f, "building the DFA was cancelled")
+            }
+        }
+    }
+}
+
+/// An error that occurred during a search.
+///
+/// Unlike [`Error`](struct.Error.html), which is only returned when building
+/// a DFA, a search error is returned by search routines that may refuse to
+/// complete a search. For example,
+/// [`Regex::find_bounded`](struct.Regex.html#method.find_bounded) refuses to
+/// search inputs that exceed a configured length.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchError {
+    /// The input given to a search was longer than the configured maximum,
+    /// and thus, no search was performed.
+    InputTooLong {
+        /// The maximum permitted length of the input, in bytes.
+        max: usize,
+        /// The actual length of the input, in bytes.
+        got: usize,
+    },
+    /// The search reached a part of a partially built DFA that was never
+    /// explored, and thus, the outcome of the search is unknown.
+    Incomplete {
+        /// The offset of the byte that led to the unexplored part of the DFA.
+        offset: usize,
+    },
+    /// The search needed more state transitions than it was permitted to
+    /// perform, and thus, gave up before completing.
+    OutOfFuel {
+        /// The offset of the byte at which the search gave up.
+        offset: usize,
+    },
+    /// The search saw one of the DFA's quit bytes before finding a match,
+    /// and thus, stopped without knowing whether a match exists.
+    Quit {
+        /// The quit byte that was seen.
+        byte: u8,
+        /// The offset of the quit byte.
+        offset: usize,
+    },
+    /// A lexer found no token, nor a match of its recovery pattern, at the
+    /// given offset, and thus, could not continue scanning.
+    NoToken {
+        /// The offset at which no token begins.
+        offset: usize,
+    },
+}
+
+#[cfg(feature = "std")]
+impl error::Error for SearchError {
+    fn description(&self) -> &str {
+        match *self {
+            SearchError::InputTooLong { .. } => "search input too long",
+            SearchError::Incomplete { .. } => {
+                "search reached unexplored state"
+            }
+            SearchError::OutOfFuel { .. } => "search ran out of fuel",
+            SearchError::Quit { .. } => "search quit on a quit byte",
+            SearchError::NoToken { .. } => "no token found",
+        }
+    }
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SearchError::InputTooLong { max, got } => write!(
+                f,
+                "search input of {} bytes exceeds the maximum of {} bytes",
+                got, max,
+            ),
+            SearchError::Incomplete { offset } => write!(
+                f,
+                "search could not complete because the byte at offset {} \
+                 leads to a part of the DFA that was never built",
+                offset,
+            ),
+            SearchError::OutOfFuel { offset } => write!(
+                f,
+                "search exceeded its maximum number of state transitions \
+                 at offset {}",
+                offset,
+            ),
+            SearchError::Quit { byte, offset } => write!(
+                f,
+                "search quit on byte 0x{:02X} at offset {}",
+                byte, offset,
+            ),
+            SearchError::NoToken { offset } => {
+                write!(f, "no token begins at offset {}", offset)
+            }
+        }
+    }
+}
+
+/// An error that occurred while reading a serialized DFA.
+///
+/// This is returned by [`peek`](fn.peek.html) when the bytes given don't
+/// start with a valid DFA header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeserializeError {
+    /// The bytes don't start with the label of the expected kind of DFA, of
+    /// a search state or of an archive.
+    InvalidLabel,
+    /// The bytes end before the end of the header.
+    BufferTooSmall {
+        /// The number of bytes required.
+        needed: usize,
+        /// The number of bytes available.
+        got: usize,
+    },
+    /// The DFA was serialized with an endianness different from the
+    /// endianness of the current target.
+    EndiannessMismatch,
+    /// The DFA was serialized with a different version of the format.
+    UnsupportedVersion {
+        /// The version supported by this crate.
+        expected: u16,
+        /// The version found in the header.
+        found: u16,
+    },
+    /// The size of the state identifier representation in the header is not
+    /// one of 1, 2, 4 or 8.
+    InvalidStateIDSize {
+        /// The size found in the header.
+        size: usize,
+    },
+    /// The size of the state identifier representation in the header is
+    /// different from the size of the representation requested.
+    StateIDSizeMismatch {
+        /// The size of the requested representation.
+        expected: usize,
+        /// The size found in the header.
+        found: usize,
+    },
+    /// The bytes, or the transition table within them, are not aligned to
+    /// the alignment of the state identifier representation.
+    Misaligned,
+    /// The start state, the last match state or a transition refers to a
+    /// state that doesn't exist.
+    InvalidStateID {
+        /// The invalid state identifier.
+        id: u64,
+    },
+    /// The DFA has no states, or its dead state has a transition to a state
+    /// other than itself.
+    InvalidDeadState,
+    /// The table recording which patterns match in each match state is
+    /// inconsistent with the rest of the DFA.
+    InvalidMatchTable,
+    /// The fields of a serialized search state are inconsistent with one
+    /// another.
+    InvalidSearchState,
+    /// There are too many accelerated states, or an accelerated state has
+    /// fewer than one or more than three needles.
+    InvalidAccelerators,
+    /// The DFA requires features that aren't supported by this version of
+    /// the crate.
+    UnsupportedFeatures {
+        /// The flags of the unsupported features, as found in the header.
+        flags: u16,
+    },
+    /// The index of an archive refers to bytes outside of the archive, or an
+    /// archive lacks an entry that was expected.
+    InvalidArchive,
+    /// The checksum recorded in the header doesn't match the contents of
+    /// the DFA, which indicates that the bytes were corrupted.
+    ChecksumMismatch {
+        /// The checksum recorded in the header.
+        expected: u32,
+        /// The checksum of the bytes given.
+        found: u32,
+    },
+    /// The DFA is not a sparse DFA serialized in the compact format, or its
+    /// compact transition table is truncated or malformed.
+    InvalidCompactTable,
+    /// A serialized build cache is truncated, or its contents are
+    /// inconsistent with one another.
+    InvalidBuildCache,
+    /// The DFA's map from bytes to equivalence classes is not contiguous,
+    /// i.e., it doesn't start at class `0`, or some byte's class is more
+    /// than one greater than the class of the byte before it.
+    InvalidByteClasses,
+}
+
+#[cfg(feature = "std")]
+impl error::Error for DeserializeError {
+    fn description(&self) -> &str {
+        match *self {
+            DeserializeError::InvalidLabel => "invalid DFA label",
+            DeserializeError::BufferTooSmall { .. } => "DFA buffer too small",
+            DeserializeError::EndiannessMismatch => "DFA endianness mismatch",
+            DeserializeError::UnsupportedVersion { .. } => {
+                "unsupported DFA format version"
+            }
+            DeserializeError::InvalidStateIDSize { .. } => {
+                "invalid DFA state id size"
+            }
+            DeserializeError::StateIDSizeMismatch { .. } => {
+                "DFA state id size mismatch"
+            }
+            DeserializeError::Misaligned => "DFA bytes are misaligned",
+            DeserializeError::InvalidStateID { .. } => "invalid DFA state id",
+            DeserializeError::InvalidDeadState => "invalid DFA dead state",
+            DeserializeError::InvalidMatchTable => {
+                "invalid DFA match pattern table"
+            }
+            DeserializeError::InvalidSearchState => "invalid search state",
+            DeserializeError::InvalidAccelerators => {
+                "invalid DFA accelerated states"
+            }
+            DeserializeError::UnsupportedFeatures { .. } => {
+                "unsupported DFA features"
+            }
+            DeserializeError::InvalidArchive => "invalid archive",
+            DeserializeError::ChecksumMismatch { .. } => {
+                "DFA checksum mismatch"
+            }
+            DeserializeError::InvalidCompactTable => {
+                "invalid compact DFA transition table"
+            }
+            DeserializeError::InvalidBuildCache => "invalid build cache",
+            DeserializeError::InvalidByteClasses => "invalid DFA byte classes",
+        }
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeserializeError::InvalidLabel => {
+                write!(
+                    f,
+                    "could not find the label of a dense or sparse DFA, of a \
+                     search state or of an archive",
+                )
+            }
+            DeserializeError::BufferTooSmall { needed, got } => write!(
+                f,
+                "DFA header requires at least {} bytes, but only {} bytes \
+                 are available",
+                needed, got,
+            ),
+            DeserializeError::EndiannessMismatch => write!(
+                f,
+                "endianness mismatch, the DFA was serialized with an \
+                 endianness different from this platform",
+            ),
+            DeserializeError::UnsupportedVersion { expected, found } => {
+                write!(
+                    f,
+                    "expected version {}, but found unsupported version {}",
+                    expected, found,
+                )
+            }
+            DeserializeError::InvalidStateIDSize { size } => write!(
+                f,
+                "state size of {} not supported, must be 1, 2, 4 or 8",
+                size,
+            ),
+            DeserializeError::StateIDSizeMismatch { expected, found } => {
+                write!(
+                    f,
+                    "state size of DFA ({}) does not match requested state \
+                     size ({})",
+                    found, expected,
+                )
+            }
+            DeserializeError::Misaligned => write!(
+                f,
+                "DFA is not aligned to the alignment of its state \
+                 identifiers",
+            ),
+            DeserializeError::InvalidStateID { id } => {
+                write!(f, "DFA refers to invalid state id {}", id)
+            }
+            DeserializeError::InvalidDeadState => write!(
+                f,
+                "DFA has no dead state, or its dead state can transition to \
+                 another state",
+            ),
+            DeserializeError::InvalidMatchTable => write!(
+                f,
+                "DFA's table of patterns for each match state is invalid",
+            ),
+            DeserializeError::InvalidSearchState => {
+                write!(f, "search state has inconsistent fields")
+            }
+            DeserializeError::InvalidAccelerators => write!(
+                f,
+                "DFA has too many accelerated states, or an accelerated \
+                 state with an invalid number of needles",
+            ),
+            DeserializeError::UnsupportedFeatures { flags } => write!(
+                f,
+                "DFA requires features that are not supported by this \
+                 version of regex-automata (flags 0x{:04X})",
+                flags,
+            ),
+            DeserializeError::InvalidArchive => write!(
+                f,
+                "archive index is inconsistent with its contents, or a \
+                 required entry is missing",
+            ),
+            DeserializeError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "DFA checksum mismatch, expected 0x{:08X} but computed \
+                 0x{:08X}, the DFA bytes may be corrupted",
+                expected, found,
+            ),
+            DeserializeError::InvalidCompactTable => write!(
+                f,
+                "DFA is not in the compact format, or its compact transition \
+                 table is malformed",
+            ),
+            DeserializeError::InvalidBuildCache => {
+                write!(f, "build cache is truncated or malformed")
+            }
+            DeserializeError::InvalidByteClasses => write!(
+                f,
+                "DFA's map from bytes to equivalence classes is not \
+                 contiguous",
+            ),
         }
     }
 }
@@ -0,0 +1,369 @@
+use std::collections::hash_map::{Entry, HashMap};
+
+use dfa::DFA;
+
+/// The default maximum length of a generated input.
+const DEFAULT_MAX_LEN: usize = 32;
+
+/// The default probability of ending an input once it would be matched.
+const DEFAULT_STOP_PROBABILITY: f64 = 0.25;
+
+/// The number of random walks attempted for each generated input before
+/// giving up.
+const ATTEMPTS: usize = 64;
+
+/// A builder for constructing a generator of random inputs matched by a
+/// DFA.
+///
+/// This builder permits configuring the bounds on the length of each input,
+/// how eagerly inputs end and the seed of the random number generator.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    min_len: usize,
+    max_len: usize,
+    stop_probability: f64,
+    match_bias: f64,
+    seed: u64,
+}
+
+impl Builder {
+    /// Create a new generator builder with the default configuration.
+    pub fn new() -> Builder {
+        Builder {
+            min_len: 0,
+            max_len: DEFAULT_MAX_LEN,
+            stop_probability: DEFAULT_STOP_PROBABILITY,
+            match_bias: 0.0,
+            seed: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+
+    /// Build a generator of random inputs matched by the given DFA.
+    ///
+    /// This explores every state reachable from the DFA's start state in
+    /// order to find, for each state, the length of the shortest input that
+    /// leads from it to a match.
+    pub fn build<'a, D: DFA>(&self, dfa: &'a D) -> Generator<'a, D> {
+        Generator {
+            dfa,
+            config: self.clone(),
+            rng: XorShift::new(self.seed),
+            distances: distances(dfa),
+        }
+    }
+
+    /// Set the minimum length, in bytes, of each generated input.
+    ///
+    /// By default, this is `0`.
+    pub fn min_len(&mut self, len: usize) -> &mut Builder {
+        self.min_len = len;
+        self
+    }
+
+    /// Set the maximum length, in bytes, of each generated input.
+    ///
+    /// By default, this is `32`.
+    pub fn max_len(&mut self, len: usize) -> &mut Builder {
+        self.max_len = len;
+        self
+    }
+
+    /// Set the probability, between `0.0` and `1.0`, of ending an input as
+    /// soon as it is matched and no shorter than the minimum length. Lower
+    /// probabilities tend to produce longer inputs.
+    ///
+    /// By default, this is `0.25`.
+    pub fn stop_probability(&mut self, probability: f64) -> &mut Builder {
+        self.stop_probability = probability;
+        self
+    }
+
+    /// Set the probability, between `0.0` and `1.0`, of choosing each byte
+    /// among those that lead closer to a match, instead of among all bytes
+    /// that permit a match within the maximum length. Higher probabilities
+    /// tend to produce shorter inputs that reach match states sooner.
+    ///
+    /// By default, this is `0.0`, such that every byte that permits a match
+    /// is equally likely.
+    pub fn match_bias(&mut self, probability: f64) -> &mut Builder {
+        self.match_bias = probability;
+        self
+    }
+
+    /// Set the seed of the random number generator. Generators built with
+    /// the same seed and configuration from the same DFA produce the same
+    /// inputs.
+    pub fn seed(&mut self, seed: u64) -> &mut Builder {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// A generator of random inputs matched by a DFA.
+///
+/// Each input is produced by a random walk over the DFA's transitions,
+/// beginning at its start state. A walk only follows transitions from which
+/// a match remains reachable within the maximum length, and it may end at
+/// any state in which the input walked so far is matched. For an anchored
+/// DFA, every input generated is therefore matched in its entirety. For an
+/// unanchored DFA, every input generated contains a match that ends at the
+/// end of the input.
+///
+/// This type is an iterator over generated inputs. The iterator ends when
+/// no input within the configured length bounds could be generated, which
+/// is always the case when the DFA never matches anything.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense, gen, DFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = dense::Builder::new()
+///     .anchored(true)
+///     .build(r"[a-z]{2,4}@[a-z]+\.(?:com|org)")?;
+/// let inputs: Vec<Vec<u8>> =
+///     gen::Builder::new().max_len(16).build(&dfa).take(100).collect();
+/// assert_eq!(100, inputs.len());
+/// for input in inputs {
+///     assert!(input.len() <= 16);
+///     assert_eq!(Some(input.len()), dfa.find(&input));
+/// }
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct Generator<'a, D: 'a + DFA> {
+    dfa: &'a D,
+    config: Builder,
+    rng: XorShift,
+    distances: HashMap<D::ID, usize>,
+}
+
+impl<'a, D: DFA> Generator<'a, D> {
+    /// Generate a random input matched by this generator's DFA.
+    ///
+    /// This returns `None` when no input could be generated within the
+    /// configured length bounds.
+    pub fn generate(&mut self) -> Option<Vec<u8>> {
+        for _ in 0..ATTEMPTS {
+            if let Some(input) = self.walk() {
+                return Some(input);
+            }
+        }
+        None
+    }
+
+    /// Perform a single random walk, returning the input walked if it
+    /// satisfies the configured length bounds.
+    fn walk(&mut self) -> Option<Vec<u8>> {
+        let (min_len, max_len) = (self.config.min_len, self.config.max_len);
+        let mut input = vec![];
+        let mut state = self.dfa.start_state_after(None);
+        let mut candidates = [0u8; 256];
+        let mut closer = [0u8; 256];
+        loop {
+            let dist = *self.distances.get(&state)?;
+            if dist == 0
+                && input.len() >= min_len
+                && (input.len() == max_len
+                    || self.rng.chance(self.config.stop_probability))
+            {
+                return Some(input);
+            }
+            let (mut ncandidates, mut ncloser) = (0, 0);
+            for byte in (0..256).map(|b| b as u8) {
+                let next = self.dfa.next_state(state, byte);
+                let d = match self.distances.get(&next) {
+                    None => continue,
+                    Some(&d) => d,
+                };
+                if input.len() + 1 + d > max_len {
+                    continue;
+                }
+                candidates[ncandidates] = byte;
+                ncandidates += 1;
+                if d < dist {
+                    closer[ncloser] = byte;
+                    ncloser += 1;
+                }
+            }
+            let byte =
+                if ncloser > 0 && self.rng.chance(self.config.match_bias) {
+                    closer[self.rng.below(ncloser)]
+                } else if ncandidates > 0 {
+                    candidates[self.rng.below(ncandidates)]
+                } else if dist == 0 && input.len() >= min_len {
+                    return Some(input);
+                } else {
+                    return None;
+                };
+            input.push(byte);
+            state = self.dfa.next_state(state, byte);
+        }
+    }
+}
+
+impl<'a, D: DFA> Iterator for Generator<'a, D> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        self.generate()
+    }
+}
+
+/// Returns the length of the shortest input leading to a match from each
+/// state reachable from the start state of the given DFA. States from which
+/// no match is reachable are omitted.
+fn distances<D: DFA>(dfa: &D) -> HashMap<D::ID, usize> {
+    let is_accepting = |id| {
+        if dfa.has_lookaround() {
+            dfa.is_match_state(dfa.next_eoi_state(id))
+        } else {
+            dfa.is_match_state(id)
+        }
+    };
+
+    // Discover every reachable state along with its incoming transitions.
+    let start = dfa.start_state_after(None);
+    let mut incoming: HashMap<D::ID, Vec<D::ID>> = HashMap::new();
+    incoming.insert(start, vec![]);
+    let mut stack = vec![start];
+    while let Some(id) = stack.pop() {
+        for byte in 0..256 {
+            let next = dfa.next_state(id, byte as u8);
+            if dfa.is_dead_state(next) {
+                continue;
+            }
+            match incoming.entry(next) {
+                Entry::Occupied(mut e) => e.get_mut().push(id),
+                Entry::Vacant(e) => {
+                    e.insert(vec![id]);
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    // Walk backwards, breadth first, from every accepting state.
+    let mut distances = HashMap::new();
+    let mut queue: Vec<D::ID> =
+        incoming.keys().cloned().filter(|&id| is_accepting(id)).collect();
+    for &id in &queue {
+        distances.insert(id, 0);
+    }
+    let mut i = 0;
+    while i < queue.len() {
+        let id = queue[i];
+        let dist = distances[&id];
+        for &prev in &incoming[&id] {
+            if let Entry::Vacant(e) = distances.entry(prev) {
+                e.insert(dist + 1);
+                queue.push(prev);
+            }
+        }
+        i += 1;
+    }
+    distances
+}
+
+/// A small, fast and deterministic pseudo random number generator, using
+/// the xorshift64* algorithm.
+#[derive(Clone, Debug)]
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // The state of a xorshift generator must never be zero.
+        XorShift(if seed == 0 { 1 } else { seed })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number uniformly distributed in `0..n`, which must not be
+    /// empty.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns true with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Builder;
+    use dense;
+    use dfa::DFA;
+
+    #[test]
+    fn respects_length_bounds() {
+        let dfa =
+            dense::Builder::new().anchored(true).build("(?:ab|c)+").unwrap();
+        let mut builder = Builder::new();
+        builder.min_len(5).max_len(7).stop_probability(0.5);
+        let mut lens = vec![];
+        for input in builder.build(&dfa).take(200) {
+            assert_eq!(Some(input.len()), dfa.find(&input));
+            assert!(5 <= input.len() && input.len() <= 7, "{:?}", input);
+            lens.push(input.len());
+        }
+        assert_eq!(200, lens.len());
+        assert!(lens.contains(&5) && lens.contains(&6) && lens.contains(&7));
+
+        builder.min_len(8).max_len(8);
+        for input in builder.build(&dfa).take(50) {
+            assert_eq!(8, input.len());
+        }
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let dfa = dense::Builder::new().anchored(true).build("a{5}").unwrap();
+        let mut builder = Builder::new();
+        assert_eq!(None, builder.max_len(4).build(&dfa).next());
+        assert_eq!(None, builder.min_len(6).max_len(10).build(&dfa).next());
+        assert_eq!(
+            Some(b"aaaaa".to_vec()),
+            builder.min_len(0).build(&dfa).next()
+        );
+
+        let dfa = dense::Builder::new().build("a$b").unwrap();
+        assert_eq!(None, Builder::new().build(&dfa).next());
+    }
+
+    #[test]
+    fn deterministic_and_biased() {
+        let dfa = dense::Builder::new()
+            .anchored(true)
+            .build(r"[a-z]{1,20}(?-u:\b)")
+            .unwrap();
+        let mut builder = Builder::new();
+        builder.stop_probability(0.0).seed(42);
+        let first: Vec<Vec<u8>> = builder.build(&dfa).take(20).collect();
+        let again: Vec<Vec<u8>> = builder.build(&dfa).take(20).collect();
+        assert_eq!(first, again);
+        for input in &first {
+            assert_eq!(20, input.len());
+        }
+
+        builder.stop_probability(1.0).match_bias(1.0);
+        for input in builder.build(&dfa).take(20) {
+            assert_eq!(1, input.len());
+            assert!(dfa.is_match(&input));
+        }
+    }
+}
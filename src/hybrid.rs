@@ -0,0 +1,890 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use classes::ByteClasses;
+use dense;
+use determinize::{epsilon_closure, next};
+use dfa::{PatternID, DFA};
+use error::{Error, Result};
+use nfa::{self, NFA};
+use sparse_set::SparseSet;
+
+/// The default capacity of a lazy DFA's cache, in bytes.
+const DEFAULT_CACHE_CAPACITY: usize = 2 * (1 << 20);
+
+/// The identifier of a transition that hasn't been computed yet.
+const UNKNOWN: usize = !0;
+
+/// The identifier of the dead state, which is always the first state in the
+/// cache.
+const DEAD: usize = 0;
+
+/// A lazy DFA, which builds its states on the fly during a search.
+///
+/// Unlike a [`DenseDFA`](../enum.DenseDFA.html), a lazy DFA doesn't convert
+/// its NFA to a DFA up front. Instead, each DFA state is computed from the NFA
+/// the first time a search needs it, and then stored in a
+/// [`Cache`](struct.Cache.html) so that later searches can reuse it. Since a
+/// search only ever visits at most one new state per byte of input, building
+/// a lazy DFA never takes exponential time, and its memory usage is bounded
+/// by the capacity of its cache. When the cache is full, it is cleared and
+/// states are rebuilt as they are needed again.
+///
+/// In exchange, searching with a lazy DFA is slower than searching with a
+/// fully built DFA, especially when its cache is too small to hold the states
+/// a search needs and must be cleared frequently.
+///
+/// Since its states change during a search, a lazy DFA doesn't implement
+/// the [`DFA`](../trait.DFA.html) trait. Instead, its search routines mirror
+/// those of `DFA`, except each one accepts the cache to use. A lazy DFA
+/// itself is never mutated, so it can be shared by many threads, as long as
+/// each one uses its own cache. To use a lazy DFA with code that is generic
+/// over the `DFA` trait, pair it with a cache using
+/// [`with_cache`](struct.LazyDFA.html#method.with_cache).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::hybrid::LazyDFA;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = LazyDFA::new(r"\w+@\w+")?;
+/// let mut cache = dfa.new_cache();
+/// assert!(dfa.is_match(&mut cache, b"send to foo@example"));
+/// assert_eq!(Some(19), dfa.find(&mut cache, b"send to foo@example"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct LazyDFA {
+    nfa: NFA,
+    byte_classes: ByteClasses,
+    cache_capacity: usize,
+}
+
+impl LazyDFA {
+    /// Parse the given regular expression using a default configuration and
+    /// return the corresponding lazy DFA.
+    ///
+    /// The default configuration uses byte classes and a cache capacity of
+    /// 2MB. To use a different configuration, use a
+    /// [`hybrid::Builder`](struct.Builder.html).
+    pub fn new(pattern: &str) -> Result<LazyDFA> {
+        Builder::new().build(pattern)
+    }
+
+    /// Create a new cache for searching with this lazy DFA.
+    ///
+    /// A cache should only be used with the lazy DFA that created it.
+    pub fn new_cache(&self) -> Cache {
+        Cache::new(self)
+    }
+
+    /// Pair this lazy DFA with the given cache, such that the result
+    /// implements the [`DFA`](../trait.DFA.html) trait.
+    ///
+    /// This permits searching with a lazy DFA from code that is generic over
+    /// dense, sparse and lazy DFAs. The cache should have been created by
+    /// this lazy DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{hybrid::LazyDFA, DenseDFA, DFA};
+    ///
+    /// fn find_word<D: DFA>(dfa: D, haystack: &[u8]) -> Option<usize> {
+    ///     dfa.find(haystack)
+    /// }
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let haystack = b"send to foo@example";
+    /// let dense = DenseDFA::new(r"\w+@\w+")?;
+    /// let lazy = LazyDFA::new(r"\w+@\w+")?;
+    /// let mut cache = lazy.new_cache();
+    /// assert_eq!(Some(19), find_word(&dense, haystack));
+    /// assert_eq!(Some(19), find_word(lazy.with_cache(&mut cache), haystack));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn with_cache<'a>(&'a self, cache: &'a mut Cache) -> CachedDFA<'a> {
+        CachedDFA { dfa: self, cache: RefCell::new(cache) }
+    }
+
+    /// Returns true if and only if this lazy DFA is anchored.
+    ///
+    /// When a lazy DFA is anchored, it is only allowed to report matches that
+    /// start at index `0`.
+    pub fn is_anchored(&self) -> bool {
+        self.nfa.is_anchored()
+    }
+
+    /// Returns true if and only if this lazy DFA was built in UTF-8 mode, in
+    /// which case every match it reports on valid UTF-8 begins and ends at a
+    /// codepoint boundary.
+    pub fn is_utf8(&self) -> bool {
+        self.nfa.is_utf8()
+    }
+
+    /// Returns the maximum number of bytes that a cache for this lazy DFA
+    /// may use before it is cleared.
+    pub fn cache_capacity(&self) -> usize {
+        self.cache_capacity
+    }
+
+    /// Returns true if and only if the given bytes match this lazy DFA.
+    ///
+    /// This has the same semantics as
+    /// [`DFA::is_match`](../trait.DFA.html#method.is_match).
+    pub fn is_match(&self, cache: &mut Cache, bytes: &[u8]) -> bool {
+        self.is_match_at(cache, bytes, 0)
+    }
+
+    /// Returns the first position at which a match is found.
+    ///
+    /// This has the same semantics as
+    /// [`DFA::shortest_match`](../trait.DFA.html#method.shortest_match).
+    pub fn shortest_match(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+    ) -> Option<usize> {
+        self.shortest_match_at(cache, bytes, 0)
+    }
+
+    /// Returns the end offset of the leftmost first match.
+    ///
+    /// This has the same semantics as
+    /// [`DFA::find`](../trait.DFA.html#method.find).
+    pub fn find(&self, cache: &mut Cache, bytes: &[u8]) -> Option<usize> {
+        self.find_at(cache, bytes, 0)
+    }
+
+    /// Returns the same as `is_match`, but starts the search at the given
+    /// offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the lazy DFA is anchored,
+    /// then a match can only occur when `start == 0`.
+    pub fn is_match_at(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+        start: usize,
+    ) -> bool {
+        self.shortest_match_at(cache, bytes, start).is_some()
+    }
+
+    /// Returns the same as `shortest_match`, but starts the search at the
+    /// given offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the lazy DFA is anchored,
+    /// then a match can only occur when `start == 0`.
+    pub fn shortest_match_at(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<usize> {
+        if self.is_anchored() && start > 0 {
+            return None;
+        }
+
+        let mut state = cache.start_state(self);
+        if cache.is_match_state(state) {
+            return Some(start);
+        } else if state == DEAD {
+            return None;
+        }
+        for (i, &b) in bytes[start..].iter().enumerate() {
+            state = cache.next_state(self, state, b);
+            if cache.is_match_state(state) {
+                return Some(start + i + 1);
+            } else if state == DEAD {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Returns the same as `find`, but starts the search at the given
+    /// offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the lazy DFA is anchored,
+    /// then a match can only occur when `start == 0`.
+    pub fn find_at(
+        &self,
+        cache: &mut Cache,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<usize> {
+        if self.is_anchored() && start > 0 {
+            return None;
+        }
+
+        let mut state = cache.start_state(self);
+        let mut last_match = if state == DEAD {
+            return None;
+        } else if cache.is_match_state(state) {
+            Some(start)
+        } else {
+            None
+        };
+        for (i, &b) in bytes[start..].iter().enumerate() {
+            state = cache.next_state(self, state, b);
+            if state == DEAD {
+                return last_match;
+            } else if cache.is_match_state(state) {
+                last_match = Some(start + i + 1);
+            }
+        }
+        last_match
+    }
+}
+
+/// A lazy DFA paired with a cache, which implements the
+/// [`DFA`](../trait.DFA.html) trait.
+///
+/// This is created by
+/// [`LazyDFA::with_cache`](struct.LazyDFA.html#method.with_cache). Each call
+/// to `start_state` or `next_state` may build a new state in the cache.
+///
+/// Since building a state may clear the cache, a state identifier is only
+/// valid until the next call to `start_state` or `next_state`, except for
+/// the identifier returned by that call. Every search routine of the `DFA`
+/// trait that completes in a single call respects this. Searches that carry
+/// a state identifier from one call to the next, such as
+/// [`DFA::find_overlapping_at`](../trait.DFA.html#method.find_overlapping_at)
+/// and [`DFA::find_chunk`](../trait.DFA.html#method.find_chunk), may report
+/// incorrect results or panic if the cache is cleared in between.
+///
+/// `'a` corresponds to the lifetime of both the lazy DFA and its cache.
+#[derive(Debug)]
+pub struct CachedDFA<'a> {
+    dfa: &'a LazyDFA,
+    cache: RefCell<&'a mut Cache>,
+}
+
+impl<'a> DFA for CachedDFA<'a> {
+    type ID = usize;
+
+    fn start_state(&self) -> usize {
+        self.cache.borrow_mut().start_state(self.dfa)
+    }
+
+    fn is_match_state(&self, id: usize) -> bool {
+        self.cache.borrow().is_match_state(id)
+    }
+
+    fn is_dead_state(&self, id: usize) -> bool {
+        id == DEAD
+    }
+
+    fn is_match_or_dead_state(&self, id: usize) -> bool {
+        id == DEAD || self.is_match_state(id)
+    }
+
+    fn is_anchored(&self) -> bool {
+        self.dfa.is_anchored()
+    }
+
+    fn is_utf8(&self) -> bool {
+        self.dfa.is_utf8()
+    }
+
+    fn next_state(&self, current: usize, input: u8) -> usize {
+        self.cache.borrow_mut().next_state(self.dfa, current, input)
+    }
+
+    unsafe fn next_state_unchecked(&self, current: usize, input: u8) -> usize {
+        self.next_state(current, input)
+    }
+}
+
+/// The states and transitions built by a lazy DFA during its searches.
+///
+/// A cache is created for a specific lazy DFA with
+/// [`LazyDFA::new_cache`](struct.LazyDFA.html#method.new_cache), and must only
+/// be used with that lazy DFA. Reusing the same cache for many searches
+/// permits each search to benefit from the states built by previous ones.
+///
+/// Once the memory used by a cache reaches the capacity configured on its
+/// lazy DFA, the cache is cleared. A cache always retains at least the
+/// states needed to make progress, so its memory usage may exceed a
+/// very small capacity.
+///
+/// When a pattern is added to a lazy DFA with
+/// [`Builder::add_pattern`](struct.Builder.html#method.add_pattern), its
+/// existing caches may still be used. Each of them is cleared the next time
+/// it's used for a search.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    /// The transition table of every state built so far, where each state
+    /// identifier is the index of its first transition.
+    trans: Vec<usize>,
+    /// The NFA states and match status of each state built so far, indexed
+    /// by state identifier divided by the alphabet length.
+    states: Vec<Rc<State>>,
+    /// A map from the states built so far to their identifiers.
+    map: HashMap<Rc<State>, usize>,
+    /// The identifier of the start state, if it has been built.
+    start: Option<usize>,
+    /// The number of equivalence classes of bytes.
+    alphabet_len: usize,
+    /// The approximate number of bytes used by this cache.
+    memory_usage: usize,
+    /// The number of times this cache has been cleared.
+    clear_count: usize,
+    /// The number of patterns in the lazy DFA when this cache was created.
+    /// If patterns were added since, then every state in this cache is
+    /// stale.
+    pattern_count: usize,
+    /// Scratch space for computing the set of NFA states in a state.
+    sparse: SparseSet,
+    /// Scratch space for computing epsilon closures.
+    stack: Vec<nfa::StateID>,
+}
+
+/// An intermediate representation for a lazy DFA state.
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct State {
+    /// Whether this state is a match state.
+    is_match: bool,
+    /// An ordered sequence of NFA states that make up this state.
+    nfa_states: Vec<nfa::StateID>,
+}
+
+impl Cache {
+    fn new(dfa: &LazyDFA) -> Cache {
+        let mut cache = Cache {
+            trans: vec![],
+            states: vec![],
+            map: HashMap::new(),
+            start: None,
+            alphabet_len: dfa.byte_classes.alphabet_len(),
+            memory_usage: 0,
+            clear_count: 0,
+            pattern_count: dfa.nfa.pattern_count(),
+            sparse: SparseSet::new(dfa.nfa.len()),
+            stack: vec![],
+        };
+        cache.reset();
+        cache
+    }
+
+    /// Remove every state from this cache.
+    ///
+    /// This is done automatically when the cache is full, but may also be
+    /// done explicitly, e.g., to release the memory used by states that are
+    /// unlikely to be needed again.
+    pub fn clear(&mut self) {
+        self.reset();
+        self.clear_count += 1;
+    }
+
+    /// Returns the number of times this cache has been cleared, including
+    /// when it was cleared explicitly.
+    ///
+    /// A count that grows quickly means that the cache capacity is too small
+    /// for the searches executed, which degrades search performance.
+    pub fn clear_count(&self) -> usize {
+        self.clear_count
+    }
+
+    /// Returns the number of states currently stored in this cache,
+    /// including the dead state.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the approximate number of bytes used by the states currently
+    /// stored in this cache.
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage
+    }
+
+    /// Remove every state from this cache except for the dead state, without
+    /// counting it as a clearing.
+    fn reset(&mut self) {
+        self.trans.clear();
+        self.states.clear();
+        self.map.clear();
+        self.start = None;
+        self.memory_usage = 0;
+        let dead =
+            self.add_state(State { is_match: false, nfa_states: vec![] });
+        debug_assert_eq!(DEAD, dead);
+        for t in &mut self.trans {
+            *t = DEAD;
+        }
+    }
+
+    /// Returns true if and only if the given identifier corresponds to a
+    /// match state.
+    fn is_match_state(&self, id: usize) -> bool {
+        self.states[id / self.alphabet_len].is_match
+    }
+
+    /// Return the identifier of the start state, building it if necessary.
+    ///
+    /// Every search begins here, so this is also where a cache notices that
+    /// patterns were added to its lazy DFA, in which case it's cleared and
+    /// made large enough for the lazy DFA's NFA.
+    fn start_state(&mut self, dfa: &LazyDFA) -> usize {
+        if self.pattern_count != dfa.nfa.pattern_count() {
+            let clear_count = self.clear_count + 1;
+            *self = Cache::new(dfa);
+            self.clear_count = clear_count;
+        }
+        if let Some(id) = self.start {
+            return id;
+        }
+        self.sparse.clear();
+        epsilon_closure(
+            &dfa.nfa,
+            dfa.nfa.start(),
+            &mut self.stack,
+            &mut self.sparse,
+        );
+        let state = self.new_state(dfa);
+        let id = self.cached_state(dfa, state);
+        self.start = Some(id);
+        id
+    }
+
+    /// Return the identifier of the state that the given state transitions
+    /// to on the given byte, building it if necessary.
+    ///
+    /// Building a state may clear the cache, which invalidates every
+    /// identifier except for the one returned.
+    #[inline(always)]
+    fn next_state(&mut self, dfa: &LazyDFA, id: usize, b: u8) -> usize {
+        let class = dfa.byte_classes.get(b) as usize;
+        let next_id = self.trans[id + class];
+        if next_id != UNKNOWN {
+            return next_id;
+        }
+        self.build_next_state(dfa, id, b)
+    }
+
+    #[inline(never)]
+    fn build_next_state(&mut self, dfa: &LazyDFA, id: usize, b: u8) -> usize {
+        let class = dfa.byte_classes.get(b) as usize;
+        let current = self.states[id / self.alphabet_len].clone();
+        next(
+            &dfa.nfa,
+            &current.nfa_states,
+            b,
+            &mut self.stack,
+            &mut self.sparse,
+        );
+        let state = self.new_state(dfa);
+        let before = self.clear_count;
+        let next_id = self.cached_state(dfa, state);
+        // If the cache was cleared, then the current state no longer exists,
+        // so there is no transition to record.
+        if self.clear_count == before {
+            self.trans[id + class] = next_id;
+        }
+        next_id
+    }
+
+    /// Return the identifier of the given state, adding it to the cache if
+    /// it doesn't exist yet. If the cache is full, then it is cleared first.
+    fn cached_state(&mut self, dfa: &LazyDFA, state: State) -> usize {
+        if let Some(&id) = self.map.get(&state) {
+            return id;
+        }
+        if self.state_count() > 1
+            && self.memory_usage + self.state_memory_usage(&state)
+                > dfa.cache_capacity
+        {
+            self.clear();
+        }
+        self.add_state(state)
+    }
+
+    /// Add the given state to the cache with all of its transitions unknown,
+    /// and return its identifier.
+    fn add_state(&mut self, state: State) -> usize {
+        let id = self.trans.len();
+        self.memory_usage += self.state_memory_usage(&state);
+        self.trans.extend((0..self.alphabet_len).map(|_| UNKNOWN));
+        let state = Rc::new(state);
+        self.states.push(state.clone());
+        self.map.insert(state, id);
+        id
+    }
+
+    /// Convert the set of NFA states in this cache's scratch space to a
+    /// state.
+    fn new_state(&self, dfa: &LazyDFA) -> State {
+        let mut state = State { is_match: false, nfa_states: vec![] };
+        for &id in &self.sparse {
+            match *dfa.nfa.state(id) {
+                nfa::State::Range { .. } | nfa::State::Sparse { .. } => {
+                    state.nfa_states.push(id);
+                }
+                nfa::State::Fail => break,
+                nfa::State::Match { .. } => {
+                    state.is_match = true;
+                    break;
+                }
+                nfa::State::Union { .. } | nfa::State::Look { .. } => {}
+            }
+        }
+        state
+    }
+
+    /// Returns the approximate number of bytes needed to store the given
+    /// state in this cache. This accounts for the state's transitions, its
+    /// NFA states and its entries in the state list and the state map.
+    fn state_memory_usage(&self, state: &State) -> usize {
+        self.alphabet_len * size_of::<usize>()
+            + state.nfa_states.len() * size_of::<nfa::StateID>()
+            + size_of::<State>()
+            + 2 * size_of::<Rc<State>>()
+            + size_of::<usize>()
+    }
+}
+
+/// A builder for constructing a lazy DFA.
+///
+/// This builder permits configuring the syntax of the regular expression,
+/// whether matches must be anchored and how much memory each cache of the
+/// lazy DFA may use.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    dfa: dense::Builder,
+    byte_classes: bool,
+    cache_capacity: usize,
+}
+
+impl Builder {
+    /// Create a new lazy DFA builder with the default configuration.
+    pub fn new() -> Builder {
+        Builder {
+            dfa: dense::Builder::new(),
+            byte_classes: true,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Build a lazy DFA from the given pattern.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned. Since a lazy DFA doesn't support look-around, this
+    /// includes patterns with word boundary assertions.
+    pub fn build(&self, pattern: &str) -> Result<LazyDFA> {
+        let nfa = self.dfa.build_nfa(pattern)?;
+        if nfa.has_look() {
+            return Err(Error::unsupported_lazy_look());
+        }
+        Ok(self.build_from_nfa(&nfa))
+    }
+
+    /// Build a lazy DFA from the given NFA.
+    ///
+    /// This is useful for building a lazy DFA from an NFA built from many
+    /// patterns, e.g., via
+    /// [`nfa::NFA::add_pattern`](../nfa/struct.NFA.html#method.add_pattern).
+    /// The lazy DFA matches if any of the patterns match.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the NFA has look-around assertions, as reported by
+    /// [`NFA::has_look`](../nfa/struct.NFA.html#method.has_look).
+    pub fn build_from_nfa(&self, nfa: &NFA) -> LazyDFA {
+        assert!(!nfa.has_look(), "lazy DFAs don't support look-around");
+        let nfa = nfa.clone();
+        let byte_classes = if self.byte_classes {
+            *nfa.byte_classes()
+        } else {
+            ByteClasses::singletons()
+        };
+        LazyDFA { nfa, byte_classes, cache_capacity: self.cache_capacity }
+    }
+
+    /// Parse the given pattern using the syntax options of this builder and
+    /// add it as a new pattern to the given lazy DFA, returning the new
+    /// pattern's identifier.
+    ///
+    /// Only the new pattern is compiled, and since a lazy DFA builds its
+    /// states during a search, nothing is determinized up front. This makes
+    /// adding a pattern to a lazy DFA with many patterns cheap, compared to
+    /// building a [`DenseDFA`](../enum.DenseDFA.html) from all of them
+    /// again. The lazy DFA then matches if any of its patterns match.
+    ///
+    /// Every cache of the lazy DFA is cleared the next time it's used for a
+    /// search, since its states were built without the new pattern.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned and the lazy DFA is left unchanged. Since a lazy DFA
+    /// doesn't support look-around, this includes patterns with word
+    /// boundary assertions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::hybrid;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let builder = hybrid::Builder::new();
+    /// let mut dfa = builder.build("foo[0-9]+")?;
+    /// let mut cache = dfa.new_cache();
+    /// assert!(!dfa.is_match(&mut cache, b"bar"));
+    ///
+    /// assert_eq!(1, builder.add_pattern(&mut dfa, "bar")?);
+    /// assert!(dfa.is_match(&mut cache, b"bar"));
+    /// assert!(dfa.is_match(&mut cache, b"foo1"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn add_pattern(
+        &self,
+        dfa: &mut LazyDFA,
+        pattern: &str,
+    ) -> Result<PatternID> {
+        let mut nfa = dfa.nfa.clone();
+        let id = self.dfa.add_pattern(&mut nfa, pattern)?;
+        if nfa.has_look() {
+            return Err(Error::unsupported_lazy_look());
+        }
+        *dfa = self.build_from_nfa(&nfa);
+        Ok(id)
+    }
+
+    /// Set the maximum number of bytes that each cache of the lazy DFA may
+    /// use before it is cleared.
+    ///
+    /// A larger capacity means fewer states need to be rebuilt, which makes
+    /// searching faster, at the expense of more memory. Regardless of the
+    /// capacity, a cache always retains at least the states needed to make
+    /// progress in a search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::hybrid;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = hybrid::Builder::new().cache_capacity(0).build(r"[a-z]+9")?;
+    /// let mut cache = dfa.new_cache();
+    /// assert_eq!(Some(7), dfa.find(&mut cache, b"1 abcd9"));
+    /// assert!(cache.clear_count() > 0);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default, this is 2MB.
+    pub fn cache_capacity(&mut self, bytes: usize) -> &mut Builder {
+        self.cache_capacity = bytes;
+        self
+    }
+
+    /// Set whether matching must be anchored at the beginning of the input.
+    ///
+    /// When enabled, a match must begin at the start of the input. When
+    /// disabled, the lazy DFA will act as if the pattern started with a
+    /// `.*?`, which enables a match to appear anywhere.
+    ///
+    /// By default this is disabled.
+    pub fn anchored(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.anchored(yes);
+        self
+    }
+
+    /// Enable or disable the case insensitive flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `i` flag.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.case_insensitive(yes);
+        self
+    }
+
+    /// Enable or disable the "dot matches any character" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `s` flag.
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.dot_matches_new_line(yes);
+        self
+    }
+
+    /// Enable or disable the Unicode flag (`u`) by default.
+    ///
+    /// By default this is **enabled**. It may alternatively be selectively
+    /// disabled in the regular expression itself via the `u` flag.
+    pub fn unicode(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.unicode(yes);
+        self
+    }
+
+    /// When enabled, the builder will permit the construction of a lazy DFA
+    /// that may match invalid UTF-8.
+    ///
+    /// By default this is disabled.
+    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut Builder {
+        self.dfa.allow_invalid_utf8(yes);
+        self
+    }
+
+    /// Shrink the size of the lazy DFA's alphabet by mapping bytes to their
+    /// equivalence classes.
+    ///
+    /// This reduces the memory used by each state in a cache, which permits
+    /// a cache of the same capacity to hold more states.
+    ///
+    /// This option is enabled by default.
+    pub fn byte_classes(&mut self, yes: bool) -> &mut Builder {
+        self.byte_classes = yes;
+        self
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dfa::DFA;
+
+    const PATTERNS: &[&str] = &[
+        r"a",
+        r"[a-z]+[0-9]",
+        r"foo|foobar",
+        r"(?i)Σ+\w+",
+        r"[0-9]{2,4}-[0-9]{2}",
+        r"",
+        r"a*",
+        r"\pL{3}",
+    ];
+
+    const HAYSTACKS: &[&[u8]] = &[
+        b"",
+        b"a",
+        b"xyz abc9 foobar",
+        b"12-345-6789-01",
+        b"\xCE\xA3\xCF\x83x \xCE\xB1\xCE\xB2\xCE\xB3",
+        b"bbbbaaaa",
+    ];
+
+    fn check(builder: &Builder, dense: &dense::Builder) {
+        for &pattern in PATTERNS {
+            let lazy = builder.build(pattern).unwrap();
+            let dfa = dense.build(pattern).unwrap();
+            let mut cache = lazy.new_cache();
+            for &haystack in HAYSTACKS {
+                for start in 0..haystack.len() + 1 {
+                    assert_eq!(
+                        dfa.find_at(haystack, start),
+                        lazy.find_at(&mut cache, haystack, start),
+                        "find_at for {:?} at {} on {:?}",
+                        pattern,
+                        start,
+                        haystack,
+                    );
+                    assert_eq!(
+                        dfa.shortest_match_at(haystack, start),
+                        lazy.shortest_match_at(&mut cache, haystack, start),
+                        "shortest_match_at for {:?} at {} on {:?}",
+                        pattern,
+                        start,
+                        haystack,
+                    );
+                    let cached = lazy.with_cache(&mut cache);
+                    assert_eq!(
+                        dfa.find_at(haystack, start),
+                        cached.find_at(haystack, start),
+                        "DFA::find_at for {:?} at {} on {:?}",
+                        pattern,
+                        start,
+                        haystack,
+                    );
+                    assert_eq!(
+                        dfa.is_match_at(haystack, start),
+                        cached.is_match_at(haystack, start),
+                        "DFA::is_match_at for {:?} at {} on {:?}",
+                        pattern,
+                        start,
+                        haystack,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn agrees_with_dense() {
+        check(&Builder::new(), &dense::Builder::new());
+        check(
+            Builder::new().anchored(true),
+            dense::Builder::new().anchored(true),
+        );
+        check(Builder::new().byte_classes(false), &dense::Builder::new());
+    }
+
+    #[test]
+    fn agrees_with_dense_when_cache_is_tiny() {
+        check(Builder::new().cache_capacity(0), &dense::Builder::new());
+        check(
+            Builder::new().cache_capacity(0).anchored(true),
+            dense::Builder::new().anchored(true),
+        );
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        let dfa =
+            Builder::new().cache_capacity(10_000).build(r"\w{10}").unwrap();
+        let mut cache = dfa.new_cache();
+        let haystack = "αβγδεζηθικλμνξοπρστυφχψω".repeat(10);
+        assert!(dfa.is_match(&mut cache, haystack.as_bytes()));
+        assert!(cache.clear_count() > 0);
+        assert!(cache.memory_usage() <= 10_000);
+
+        cache.clear();
+        assert_eq!(1, cache.state_count());
+        assert_eq!(Some(20), dfa.find(&mut cache, &haystack.as_bytes()[..20]));
+    }
+
+    #[test]
+    fn add_pattern() {
+        let builder = Builder::new();
+        let mut lazy = builder.build(PATTERNS[1]).unwrap();
+        let mut cache = lazy.new_cache();
+        for (i, &pattern) in PATTERNS.iter().enumerate().skip(2) {
+            assert_eq!(
+                i - 1,
+                builder.add_pattern(&mut lazy, pattern).unwrap()
+            );
+            let dfa =
+                dense::Builder::new().build_many(&PATTERNS[1..=i]).unwrap();
+            for &haystack in HAYSTACKS {
+                assert_eq!(
+                    dfa.find(haystack),
+                    lazy.find(&mut cache, haystack),
+                    "find with {} patterns on {:?}",
+                    i,
+                    haystack,
+                );
+                assert_eq!(
+                    dfa.is_match(haystack),
+                    lazy.with_cache(&mut cache).is_match(haystack),
+                    "DFA::is_match with {} patterns on {:?}",
+                    i,
+                    haystack,
+                );
+            }
+        }
+        assert_eq!(PATTERNS.len() - 2, cache.clear_count());
+
+        assert!(builder.add_pattern(&mut lazy, r"\bfoo").is_err());
+        assert!(builder.add_pattern(&mut lazy, r"(").is_err());
+        assert_eq!(PATTERNS.len() - 1, lazy.nfa.pattern_count());
+    }
+}
@@ -0,0 +1,469 @@
+use core::result;
+
+#[cfg(feature = "std")]
+use dense::{self, DenseDFA};
+use dfa::{self, MultiMatch, PatternID, DFA};
+#[cfg(feature = "std")]
+use error::Result;
+use error::SearchError;
+#[cfg(feature = "std")]
+use state_id::StateID;
+
+/// A scanner that splits its input into a stream of tokens, where each token
+/// is a match of one of several patterns.
+///
+/// A lexer repeatedly finds the longest match of any of its patterns that
+/// begins exactly where the previous token ended, which is commonly known as
+/// "maximal munch." When more than one pattern matches the longest token, the
+/// pattern given first wins. Thus, keywords should be given before the more
+/// general patterns that also match them, such as identifiers.
+///
+/// Each token is reported as a [`MultiMatch`](struct.MultiMatch.html), whose
+/// pattern is the index of the pattern that matched. Tokens are never empty,
+/// so an empty match of a pattern is never reported as a token.
+///
+/// When no pattern matches at the current position, the lexer either stops
+/// with an error, or, if it was given a recovery pattern, skips the longest
+/// match of the recovery pattern and carries on. See
+/// [`LexerBuilder::recovery`](struct.LexerBuilder.html#method.recovery).
+///
+/// A lexer is built with a [`LexerBuilder`](struct.LexerBuilder.html), or
+/// from existing DFAs with [`Lexer::from_dfas`](#method.from_dfas). The type
+/// parameter `D` refers to the type of the DFAs used by the lexer.
+///
+/// When using this crate without the standard library, the `Lexer` type has
+/// no default type parameter.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{Lexer, MultiMatch};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let lexer = Lexer::new(&["let", "[a-z]+", "[0-9]+", "=", " +"])?;
+/// let tokens: Vec<MultiMatch> =
+///     lexer.tokens(b"let letter = 42").collect::<Result<_, _>>().unwrap();
+/// assert_eq!(tokens, vec![
+///     MultiMatch::new(0, 0, 3),
+///     MultiMatch::new(4, 3, 4),
+///     MultiMatch::new(1, 4, 10),
+///     MultiMatch::new(4, 10, 11),
+///     MultiMatch::new(3, 11, 12),
+///     MultiMatch::new(4, 12, 13),
+///     MultiMatch::new(2, 13, 15),
+/// ]);
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Lexer<D: DFA = DenseDFA<Vec<usize>, usize>> {
+    tokens: D,
+    recovery: Option<D>,
+}
+
+/// A scanner that splits its input into a stream of tokens, where each token
+/// is a match of one of several patterns.
+///
+/// A lexer repeatedly finds the longest match of any of its patterns that
+/// begins exactly where the previous token ended. When no pattern matches,
+/// the lexer either stops with an error, or skips the longest match of its
+/// recovery pattern.
+///
+/// When using this crate without the standard library, the `Lexer` type has
+/// no default type parameter.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Debug)]
+pub struct Lexer<D> {
+    tokens: D,
+    recovery: Option<D>,
+}
+
+#[cfg(feature = "std")]
+impl Lexer {
+    /// Build a lexer from the given patterns using a default configuration.
+    ///
+    /// Each pattern is identified by its index in the slice given. Without a
+    /// recovery pattern, the lexer stops with an error on the first position
+    /// at which none of the patterns match. Use a
+    /// [`LexerBuilder`](struct.LexerBuilder.html) to set a recovery pattern
+    /// or to change the configuration.
+    ///
+    /// If there was a problem parsing or compiling any of the patterns, then
+    /// an error is returned.
+    pub fn new(patterns: &[&str]) -> Result<Lexer> {
+        LexerBuilder::new().build(patterns)
+    }
+}
+
+impl<D: DFA> Lexer<D> {
+    /// Build a lexer from a DFA for its tokens and an optional DFA for its
+    /// recovery pattern.
+    ///
+    /// Both DFAs must be anchored and must use
+    /// [`MatchKind::LeftmostLongest`](dense/enum.MatchKind.html#variant.LeftmostLongest)
+    /// semantics, or else the lexer will not report the longest token at
+    /// each position. When the tokens DFA was built from several patterns,
+    /// then each token reports the pattern given by
+    /// [`DFA::match_pattern`](trait.DFA.html#method.match_pattern).
+    ///
+    /// Quit bytes are not supported. A DFA that quits on a byte is treated
+    /// as if none of its patterns match at that byte.
+    ///
+    /// # Example
+    ///
+    /// This builds a lexer whose DFAs borrow their transition tables.
+    ///
+    /// ```
+    /// use regex_automata::{dense::{self, MatchKind}, Lexer, MultiMatch};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true).match_kind(MatchKind::LeftmostLongest);
+    /// let tokens = builder.build_many(&["[a-z]+", "[0-9]+"])?;
+    /// let recovery = builder.build(r"\s+")?;
+    /// let lexer = Lexer::from_dfas(tokens.as_ref(), Some(recovery.as_ref()));
+    ///
+    /// let mut it = lexer.tokens(b"abc  123");
+    /// assert_eq!(Some(Ok(MultiMatch::new(0, 0, 3))), it.next());
+    /// assert_eq!(Some(Ok(MultiMatch::new(1, 5, 8))), it.next());
+    /// assert_eq!(None, it.next());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn from_dfas(tokens: D, recovery: Option<D>) -> Lexer<D> {
+        Lexer { tokens, recovery }
+    }
+
+    /// Returns the longest token that begins at `start`, if one exists.
+    ///
+    /// Unlike [`tokens`](#method.tokens), this never tries the recovery
+    /// pattern. Since a token is never empty, this always returns `None`
+    /// when `start` is at the end of the input.
+    ///
+    /// The significance of the starting point is that it takes the
+    /// surrounding context into consideration. For example, a pattern that
+    /// begins with `\b` only matches at a word boundary in the input given.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Lexer, MultiMatch};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let lexer = Lexer::new(&["if", "[a-z]+", "==?"])?;
+    /// assert_eq!(Some(MultiMatch::new(0, 0, 2)), lexer.token_at(b"if", 0));
+    /// assert_eq!(Some(MultiMatch::new(1, 0, 3)), lexer.token_at(b"ifs", 0));
+    /// assert_eq!(Some(MultiMatch::new(2, 1, 3)), lexer.token_at(b"a==!", 1));
+    /// assert_eq!(None, lexer.token_at(b"a==!", 3));
+    /// assert_eq!(None, lexer.token_at(b"a==!", 4));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn token_at(&self, input: &[u8], start: usize) -> Option<MultiMatch> {
+        longest_at(&self.tokens, input, start)
+            .map(|(pattern, end)| MultiMatch::new(pattern, start, end))
+    }
+
+    /// Returns an iterator over the tokens in the given input.
+    ///
+    /// Each item yielded is either a token or an error. The first token
+    /// begins at the start of the input, and each subsequent token begins
+    /// where the previous one ended, unless the recovery pattern was used to
+    /// skip over the input between them.
+    ///
+    /// When neither a token nor a non-empty match of the recovery pattern
+    /// begins at the current position, the iterator yields a
+    /// [`SearchError::NoToken`](enum.SearchError.html#variant.NoToken) error
+    /// with that position, and then stops.
+    pub fn tokens<'l, 't>(&'l self, input: &'t [u8]) -> Tokens<'l, 't, D> {
+        Tokens { lexer: self, input, at: 0 }
+    }
+
+    /// Return the underlying DFA responsible for matching tokens.
+    pub fn token_dfa(&self) -> &D {
+        &self.tokens
+    }
+
+    /// Return the underlying DFA responsible for matching the recovery
+    /// pattern, if this lexer has one.
+    pub fn recovery_dfa(&self) -> Option<&D> {
+        self.recovery.as_ref()
+    }
+}
+
+/// An iterator over the tokens in some input.
+///
+/// This iterator is created by
+/// [`Lexer::tokens`](struct.Lexer.html#method.tokens). Each item is a
+/// [`MultiMatch`](struct.MultiMatch.html) for each token, or an error if no
+/// token could be found. No items are yielded after an error.
+///
+/// The lifetime variables are as follows:
+///
+/// * `'l` is the lifetime of the lexer itself.
+/// * `'t` is the lifetime of the input being scanned.
+#[derive(Clone, Debug)]
+pub struct Tokens<'l, 't, D: DFA + 'l> {
+    lexer: &'l Lexer<D>,
+    input: &'t [u8],
+    /// The start of the next token, or more than the length of the input
+    /// once an error has been yielded.
+    at: usize,
+}
+
+impl<'l, 't, D: DFA> Tokens<'l, 't, D> {
+    /// Returns the offset at which the next token is expected to begin.
+    ///
+    /// Once all tokens have been yielded, this is the length of the input.
+    /// After an error, this is the offset at which no token was found.
+    pub fn offset(&self) -> usize {
+        if self.at > self.input.len() {
+            self.at - self.input.len() - 1
+        } else {
+            self.at
+        }
+    }
+}
+
+impl<'l, 't, D: DFA> Iterator for Tokens<'l, 't, D> {
+    type Item = result::Result<MultiMatch, SearchError>;
+
+    fn next(&mut self) -> Option<result::Result<MultiMatch, SearchError>> {
+        while self.at < self.input.len() {
+            if let Some(m) = self.lexer.token_at(self.input, self.at) {
+                self.at = m.end();
+                return Some(Ok(m));
+            }
+            let skip = match self.lexer.recovery {
+                None => None,
+                Some(ref dfa) => longest_at(dfa, self.input, self.at),
+            };
+            match skip {
+                Some((_, end)) => self.at = end,
+                None => {
+                    let offset = self.at;
+                    // Remember the offset of the error while ensuring that
+                    // nothing else is yielded.
+                    self.at += self.input.len() + 1;
+                    return Some(Err(SearchError::NoToken { offset }));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns the pattern and end offset of the longest non-empty match that
+/// begins at `start`, using the given anchored leftmost-longest DFA.
+fn longest_at<D: DFA>(
+    dfa: &D,
+    bytes: &[u8],
+    start: usize,
+) -> Option<(PatternID, usize)> {
+    let mut state = dfa.start_state_after(dfa::byte_before(bytes, start));
+    if dfa.is_dead_state(state) {
+        return None;
+    }
+    let delay = dfa.has_lookaround() as usize;
+    let mut last_match = None;
+    let mut at = start;
+    while at < bytes.len() {
+        if dfa.is_quit_byte(bytes[at]) {
+            return last_match;
+        }
+        state = dfa.next_state(state, bytes[at]);
+        at += 1;
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                return last_match;
+            }
+            if at - delay > start {
+                last_match = Some((dfa.match_pattern(state, 0), at - delay));
+            }
+        }
+    }
+    let eoi = dfa.next_eoi_state(state);
+    if dfa.is_match_state(eoi) && bytes.len() > start {
+        last_match = Some((dfa.match_pattern(eoi, 0), bytes.len()));
+    }
+    last_match
+}
+
+/// A builder for a lexer.
+///
+/// This builder permits configuring the recovery pattern used when no token
+/// matches, along with several aspects of how the patterns are compiled. The
+/// patterns themselves are given to
+/// [`build`](struct.LexerBuilder.html#method.build).
+///
+/// Every lexer is built from anchored DFAs with
+/// [`MatchKind::LeftmostLongest`](dense/enum.MatchKind.html#variant.LeftmostLongest)
+/// semantics, which is what gives a lexer its maximal munch behavior.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct LexerBuilder {
+    dfa: dense::Builder,
+    recovery: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl LexerBuilder {
+    /// Create a new lexer builder with the default configuration.
+    pub fn new() -> LexerBuilder {
+        let mut dfa = dense::Builder::new();
+        dfa.anchored(true).match_kind(dense::MatchKind::LeftmostLongest);
+        LexerBuilder { dfa, recovery: None }
+    }
+
+    /// Build a lexer from the given patterns.
+    ///
+    /// Each pattern is identified by its index in the slice given. When more
+    /// than one pattern matches the longest token, the one with the smallest
+    /// index wins.
+    ///
+    /// If there was a problem parsing or compiling any of the patterns, or
+    /// the recovery pattern, then an error is returned.
+    pub fn build(&self, patterns: &[&str]) -> Result<Lexer> {
+        self.build_with_size::<usize>(patterns)
+    }
+
+    /// Build a lexer from the given patterns using a specific representation
+    /// for the underlying DFA state IDs.
+    ///
+    /// See
+    /// [`RegexBuilder::build_with_size`](struct.RegexBuilder.html#method.build_with_size)
+    /// for how the representation of state IDs is chosen.
+    pub fn build_with_size<S: StateID>(
+        &self,
+        patterns: &[&str],
+    ) -> Result<Lexer<DenseDFA<Vec<S>, S>>> {
+        let tokens = self.dfa.build_many_with_size(patterns)?;
+        let recovery = match self.recovery {
+            None => None,
+            Some(ref pattern) => Some(self.dfa.build_with_size(pattern)?),
+        };
+        Ok(Lexer::from_dfas(tokens, recovery))
+    }
+
+    /// Set the pattern used to recover when no token matches.
+    ///
+    /// When none of the lexer's patterns match at the current position, the
+    /// longest non-empty match of the recovery pattern at that position is
+    /// skipped, and scanning resumes where it ends. Skipped input is not
+    /// reported. If the recovery pattern doesn't match either, then the
+    /// lexer stops with an error.
+    ///
+    /// The recovery pattern is only tried after every token pattern has
+    /// failed, so it need not exclude the input matched by tokens. For
+    /// example, `(?s:.)` skips one character at a time, while `[^\s]+` skips
+    /// up to the next whitespace.
+    ///
+    /// This is disabled by default, which means a lexer stops on the first
+    /// position at which no token matches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{LexerBuilder, MultiMatch};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let patterns = &["[a-z]+", "[0-9]+", " "];
+    /// let input = b"ab !?! 12";
+    ///
+    /// let lexer = LexerBuilder::new().build(patterns)?;
+    /// let mut it = lexer.tokens(input);
+    /// assert_eq!(Some(Ok(MultiMatch::new(0, 0, 2))), it.next());
+    /// assert_eq!(Some(Ok(MultiMatch::new(2, 2, 3))), it.next());
+    /// assert!(it.next().unwrap().is_err());
+    /// assert_eq!(None, it.next());
+    ///
+    /// let lexer = LexerBuilder::new().recovery("[^ ]+").build(patterns)?;
+    /// let tokens: Vec<MultiMatch> =
+    ///     lexer.tokens(input).collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(tokens, vec![
+    ///     MultiMatch::new(0, 0, 2),
+    ///     MultiMatch::new(2, 2, 3),
+    ///     MultiMatch::new(2, 6, 7),
+    ///     MultiMatch::new(1, 7, 9),
+    /// ]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn recovery(&mut self, pattern: &str) -> &mut LexerBuilder {
+        self.recovery = Some(pattern.to_string());
+        self
+    }
+
+    /// Enable or disable the case insensitive flag by default.
+    ///
+    /// See
+    /// [`RegexBuilder::case_insensitive`](struct.RegexBuilder.html#method.case_insensitive).
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut LexerBuilder {
+        self.dfa.case_insensitive(yes);
+        self
+    }
+
+    /// Enable or disable the Unicode flag (`u`) by default.
+    ///
+    /// See
+    /// [`RegexBuilder::unicode`](struct.RegexBuilder.html#method.unicode).
+    pub fn unicode(&mut self, yes: bool) -> &mut LexerBuilder {
+        self.dfa.unicode(yes);
+        self
+    }
+
+    /// When enabled, the builder will permit the construction of a lexer
+    /// whose patterns can match invalid UTF-8.
+    ///
+    /// See
+    /// [`RegexBuilder::allow_invalid_utf8`](struct.RegexBuilder.html#method.allow_invalid_utf8).
+    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut LexerBuilder {
+        self.dfa.allow_invalid_utf8(yes);
+        self
+    }
+
+    /// Minimize the DFAs.
+    ///
+    /// See
+    /// [`RegexBuilder::minimize`](struct.RegexBuilder.html#method.minimize).
+    pub fn minimize(&mut self, yes: bool) -> &mut LexerBuilder {
+        self.dfa.minimize(yes);
+        self
+    }
+
+    /// Premultiply state identifiers in the DFAs' transition tables.
+    ///
+    /// See
+    /// [`RegexBuilder::premultiply`](struct.RegexBuilder.html#method.premultiply).
+    pub fn premultiply(&mut self, yes: bool) -> &mut LexerBuilder {
+        self.dfa.premultiply(yes);
+        self
+    }
+
+    /// Shrink the size of the DFAs' alphabets by mapping bytes to their
+    /// equivalence classes.
+    ///
+    /// See
+    /// [`RegexBuilder::byte_classes`](struct.RegexBuilder.html#method.byte_classes).
+    pub fn byte_classes(&mut self, yes: bool) -> &mut LexerBuilder {
+        self.dfa.byte_classes(yes);
+        self
+    }
+
+    /// Set a limit, in bytes, on the approximate heap memory used by each of
+    /// the DFAs.
+    ///
+    /// See
+    /// [`RegexBuilder::dfa_size_limit`](struct.RegexBuilder.html#method.dfa_size_limit).
+    pub fn dfa_size_limit(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut LexerBuilder {
+        self.dfa.dfa_size_limit(limit);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for LexerBuilder {
+    fn default() -> LexerBuilder {
+        LexerBuilder::new()
+    }
+}
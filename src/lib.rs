@@ -30,12 +30,12 @@ This example shows how to compile a regex using the default configuration
 and then use it to find matches in a byte string:
 
 ```
-use regex_automata::Regex;
+use regex_automata::{Match, Regex};
 
 let re = Regex::new(r"[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap();
 let text = b"2018-12-24 2016-10-08";
-let matches: Vec<(usize, usize)> = re.find_iter(text).collect();
-assert_eq!(matches, vec![(0, 10), (11, 21)]);
+let matches: Vec<Match> = re.find_iter(text).collect();
+assert_eq!(matches, vec![Match::new(0, 10), Match::new(11, 21)]);
 ```
 
 # Example: use sparse DFAs
@@ -49,13 +49,13 @@ Using sparse DFAs is as easy as using `Regex::new_sparse` instead of
 `Regex::new`:
 
 ```
-use regex_automata::Regex;
+use regex_automata::{Match, Regex};
 
 # fn example() -> Result<(), regex_automata::Error> {
 let re = Regex::new_sparse(r"[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap();
 let text = b"2018-12-24 2016-10-08";
-let matches: Vec<(usize, usize)> = re.find_iter(text).collect();
-assert_eq!(matches, vec![(0, 10), (11, 21)]);
+let matches: Vec<Match> = re.find_iter(text).collect();
+assert_eq!(matches, vec![Match::new(0, 10), Match::new(11, 21)]);
 # Ok(()) }; example().unwrap()
 ```
 
@@ -63,7 +63,7 @@ If you already have dense DFAs for some reason, they can be converted to sparse
 DFAs and used to build a new `Regex`. For example:
 
 ```
-use regex_automata::Regex;
+use regex_automata::{Match, Regex};
 
 # fn example() -> Result<(), regex_automata::Error> {
 let dense_re = Regex::new(r"[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap();
@@ -72,8 +72,34 @@ let sparse_re = Regex::from_dfas(
     dense_re.reverse().to_sparse()?,
 );
 let text = b"2018-12-24 2016-10-08";
-let matches: Vec<(usize, usize)> = sparse_re.find_iter(text).collect();
-assert_eq!(matches, vec![(0, 10), (11, 21)]);
+let matches: Vec<Match> = sparse_re.find_iter(text).collect();
+assert_eq!(matches, vec![Match::new(0, 10), Match::new(11, 21)]);
+# Ok(()) }; example().unwrap()
+```
+
+# Example: search for several patterns at once
+
+A single dense DFA can be built from several patterns with
+[`dense::Builder::build_many`](dense/struct.Builder.html#method.build_many).
+Each of its match states records which patterns match there, such that a
+search can report which pattern matched along with the end of the match.
+When built with
+[`all_matches`](dense/struct.Builder.html#method.all_matches) enabled,
+[`DFA::which_overlapping_matches`](trait.DFA.html#method.which_overlapping_matches)
+reports every pattern that matches anywhere in the input in a single pass,
+much like `RegexSet` in the regex crate:
+
+```
+use regex_automata::{dense, DFA};
+
+# fn example() -> Result<(), regex_automata::Error> {
+let patterns = &[r"[a-z]+", r"[0-9]+", r"[A-Z]+"];
+let dfa = dense::Builder::new().build_many(patterns)?;
+assert_eq!(Some((1, 3)), dfa.find_pattern(b"123abc"));
+
+let dfa = dense::Builder::new().all_matches(true).build_many(patterns)?;
+let set = dfa.which_overlapping_matches(b"abc 123");
+assert_eq!(vec![0, 1], set.iter().collect::<Vec<_>>());
 # Ok(()) }; example().unwrap()
 ```
 
@@ -85,9 +111,12 @@ contrived, this same technique can be used in your program to deserialize a
 DFA at start up time or by memory mapping a file. In particular,
 deserialization is guaranteed to be cheap because it will always be a constant
 time operation.
+On Unix, enabling the `mmap` feature provides [`Mmap`](struct.Mmap.html),
+which maps a file into memory such that the bytes of a serialized DFA are
+always suitably aligned.
 
 ```
-use regex_automata::{DenseDFA, Regex};
+use regex_automata::{DenseDFA, Match, Regex};
 
 # fn example() -> Result<(), regex_automata::Error> {
 let re1 = Regex::new(r"[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap();
@@ -102,8 +131,8 @@ let re2 = Regex::from_dfas(fwd, rev);
 
 // we can use it like normal
 let text = b"2018-12-24 2016-10-08";
-let matches: Vec<(usize, usize)> = re2.find_iter(text).collect();
-assert_eq!(matches, vec![(0, 10), (11, 21)]);
+let matches: Vec<Match> = re2.find_iter(text).collect();
+assert_eq!(matches, vec![Match::new(0, 10), Match::new(11, 21)]);
 # Ok(()) }; example().unwrap()
 ```
 
@@ -113,6 +142,10 @@ There are a few points worth noting here:
   can build the DFAs manually yourself using
   [`dense::Builder`](dense/struct.Builder.html), but using the DFAs from a
   `Regex` guarantees that the DFAs are built correctly.
+  Alternatively,
+  [`Regex::to_bytes_native_endian`](struct.Regex.html#method.to_bytes_native_endian)
+  serializes both DFAs into a single [`Archive`](struct.Archive.html), which
+  [`Regex::from_bytes`](struct.Regex.html#method.from_bytes) loads back.
 * We specifically convert the dense DFA to a representation that uses `u16`
   for its state identifiers using
   [`DenseDFA::to_u16`](enum.DenseDFA.html#method.to_u16). While this isn't
@@ -129,6 +162,15 @@ There are a few points worth noting here:
   depending on which platform you're deserializing your DFA from. If you intend
   to deserialize on either platform, then you'll need to serialize both and
   deserialize the right one depending on your target's endianness.
+  Alternatively,
+  [`DenseDFA::to_bytes_portable`](enum.DenseDFA.html#method.to_bytes_portable)
+  produces a single artifact that
+  [`DenseDFA::from_bytes_portable`](enum.DenseDFA.html#method.from_bytes_portable)
+  can load on any platform, at the cost of copying the DFA when loading it.
+  For sparse DFAs,
+  [`SparseDFA::to_bytes_compact`](enum.SparseDFA.html#method.to_bytes_compact)
+  goes further and also shrinks the artifact, at the cost of decoding it with
+  [`SparseDFA::from_bytes_compact`](enum.SparseDFA.html#method.from_bytes_compact).
 * Deserializing a DFA requires the use of `unsafe` because the raw bytes must
   be *trusted*. In particular, while some degree of sanity checks are
   performed, nothing guarantees the integrity of the DFA's transition table
@@ -140,7 +182,7 @@ There are a few points worth noting here:
 The same process can be achieved with sparse DFAs as well:
 
 ```
-use regex_automata::{SparseDFA, Regex};
+use regex_automata::{Match, Regex, SparseDFA};
 
 # fn example() -> Result<(), regex_automata::Error> {
 let re1 = Regex::new(r"[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap();
@@ -155,8 +197,8 @@ let re2 = Regex::from_dfas(fwd, rev);
 
 // we can use it like normal
 let text = b"2018-12-24 2016-10-08";
-let matches: Vec<(usize, usize)> = re2.find_iter(text).collect();
-assert_eq!(matches, vec![(0, 10), (11, 21)]);
+let matches: Vec<Match> = re2.find_iter(text).collect();
+assert_eq!(matches, vec![Match::new(0, 10), Match::new(11, 21)]);
 # Ok(()) }; example().unwrap()
 ```
 
@@ -185,24 +227,39 @@ The intended workflow for `no_std` environments is thus as follows:
   as you would any regex.
 
 Deserialization can happen anywhere. For example, with bytes embedded into a
-binary or with a file memory mapped at runtime.
+binary or with a file memory mapped at runtime. The
+[`include_dfa!`](macro.include_dfa.html) macro embeds a serialized dense DFA
+into a binary with the alignment that deserializing it requires.
 
 Note that the
 [`ucd-generate`](https://github.com/BurntSushi/ucd-generate)
 tool will do the first step for you with its `dfa` or `regex` sub-commands.
 
+Without the `std` feature, this crate doesn't depend on `alloc` either, so it
+works on targets without an allocator, such as `wasm32-unknown-unknown` built
+without `std`. Deserializing and searching never format anything, so as long
+as your panic handler doesn't format its message, none of `core::fmt` ends up
+in your binary. Disabling the `unroll` feature, which is also enabled by
+default, makes the search loops somewhat slower but smaller.
+
 # Syntax
 
 This crate supports the same syntax as the `regex` crate, since they share the
 same parser. You can find an exhaustive list of supported syntax in the
 [documentation for the `regex` crate](https://docs.rs/regex/1.1/regex/#syntax).
 
-Currently, there are a couple limitations. In general, this crate does not
-support zero-width assertions, although they may be added in the future. This
-includes:
-
-* Anchors such as `^`, `$`, `\A` and `\z`.
-* Word boundary assertions such as `\b` and `\B`.
+Currently, there are a couple limitations on zero-width assertions. Anchors,
+i.e., `^`, `$`, `\A` and `\z`, and ASCII word boundary assertions, i.e.,
+`(?-u:\b)` and `(?-u:\B)`, are supported by dense DFAs, but not by sparse
+DFAs or lazy DFAs. `^` and `$` match at line boundaries in
+[multi-line mode](struct.RegexBuilder.html#method.multi_line), where lines may
+also be terminated by `\r\n` in
+[CRLF mode](struct.RegexBuilder.html#method.crlf). Unicode word
+boundary assertions, i.e., `\b` and `\B`, are only supported by the
+[`BoundedBacktracker`](backtrack/struct.BoundedBacktracker.html). Building a
+DFA from a pattern containing them returns an error, unless
+[`dense::Builder::unicode_word_boundary`](dense/struct.Builder.html#method.unicode_word_boundary)
+is enabled, in which case the DFA quits when it sees a non-ASCII byte.
 
 It is possible to run a search that is anchored at the beginning of the input.
 To do that, set the
@@ -238,9 +295,11 @@ Here are some specific negative differences:
   without Unicode support, e.g., `(?-u)\w{3}`, takes under 1 millisecond and
   less than 5KB of memory. For this reason, you should only use Unicode
   character classes if you absolutely need them!
-* This crate does not support regex sets.
-* This crate does not support zero-width assertions such as `^`, `$`, `\b` or
-  `\B`.
+* There is no `RegexSet` type. Instead, a single DFA can be built from several
+  patterns, which reports which of them matched. See the multi-pattern example
+  above.
+* Only dense DFAs support zero-width assertions such as `^` or `$`, and
+  only ASCII word boundaries are supported in DFAs.
 * As a lower level crate, this library does not do literal optimizations. In
   exchange, you get predictable performance regardless of input. The
   philosophy here is that literal optimizations should be applied at a higher
@@ -292,42 +351,130 @@ extern crate core;
 extern crate bstr;
 #[cfg(feature = "transducer")]
 extern crate fst;
+#[cfg(all(feature = "mmap", unix))]
+extern crate libc;
+extern crate memchr;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 #[cfg(feature = "std")]
 extern crate regex_syntax;
 
+pub use bytes::{
+    peek, Archive, ArchiveIter, DfaHeaderInfo, FORMAT_VERSION,
+    SEARCH_STATE_LEN,
+};
+#[cfg(feature = "std")]
+pub use bytes::{AlignedBytes, ArchiveBuilder};
+#[cfg(feature = "std")]
+pub use classes::ByteClassRepresentatives;
+pub use classes::ByteClasses;
 pub use dense::DenseDFA;
-pub use dfa::DFA;
+pub use dfa::{
+    LeftmostMatches, Match, MatchEnds, MemoryUsage, MultiMatch,
+    OverlappingMatches, OverlappingState, PatternID, SearchState, DFA,
+};
+#[cfg(feature = "std")]
+pub use dfa::{PatternSet, PatternSetIter};
+#[cfg(feature = "std")]
+pub use diagnostics::{BuildWarning, Diagnostics};
+pub use error::{DeserializeError, SearchError};
 #[cfg(feature = "std")]
 pub use error::{Error, ErrorKind};
-pub use regex::Regex;
 #[cfg(feature = "std")]
-pub use regex::RegexBuilder;
+pub use lexer::LexerBuilder;
+pub use lexer::{Lexer, Tokens};
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::Mmap;
+#[cfg(feature = "std")]
+pub use prefilter::Prefilter;
+#[cfg(feature = "std")]
+pub use regex::{PrioritizedRegex, RegexBuilder, Replacer};
+pub use regex::{Regex, Split, SplitN};
 pub use sparse::SparseDFA;
-pub use state_id::StateID;
+pub use state_id::{StateID, StateIDIter};
+#[cfg(feature = "stats")]
+pub use stats::SearchStats;
 
+mod accel;
+#[cfg(feature = "std")]
+mod analysis;
+#[cfg(feature = "std")]
+#[path = "backtrack.rs"]
+mod backtrack_imp;
 mod byteorder;
+mod bytes;
 mod classes;
+#[cfg(feature = "std")]
+mod cpu;
 #[path = "dense.rs"]
 mod dense_imp;
 #[cfg(feature = "std")]
 mod determinize;
 mod dfa;
 #[cfg(feature = "std")]
+mod diagnostics;
+#[cfg(feature = "std")]
+mod dot;
 mod error;
+/// Types and routines for generating random inputs matched by a DFA.
+///
+/// This module is the home of [`Generator`](struct.Generator.html), which
+/// produces inputs by randomly walking a DFA's transitions, and of a
+/// [builder](struct.Builder.html) for configuring their length and the
+/// random number generator's seed. This is useful for generating test data
+/// for property based testing.
+#[cfg(feature = "std")]
+pub mod gen;
+#[cfg(feature = "std")]
+#[path = "hybrid.rs"]
+mod hybrid_imp;
+mod lexer;
 #[cfg(feature = "std")]
 mod minimize;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+/// Types and routines for building and inspecting NFAs.
+///
+/// This module is the home of the Thompson [`NFA`](struct.NFA.html), which is
+/// the intermediate representation from which every DFA is built, and of a
+/// [builder](struct.Builder.html) that compiles a `regex-syntax` HIR into an
+/// NFA. Building an NFA once permits deriving several DFAs from it, e.g.,
+/// with
+/// [`dense::Builder::build_from_nfa`](../dense/struct.Builder.html#method.build_from_nfa).
 #[cfg(feature = "std")]
-#[doc(hidden)]
 pub mod nfa;
+#[cfg(feature = "std")]
+mod prefilter;
+#[cfg(feature = "std")]
+mod product;
 mod regex;
 #[path = "sparse.rs"]
 mod sparse_imp;
 #[cfg(feature = "std")]
 mod sparse_set;
 mod state_id;
+mod stats;
+#[cfg(feature = "std")]
+mod teddy;
 #[cfg(feature = "transducer")]
 mod transducer;
 
+/// Types and routines specific to bounded backtracking.
+///
+/// This module is the home of
+/// [`BoundedBacktracker`](struct.BoundedBacktracker.html), which reports the
+/// start and end of a match by walking an NFA depth first, and of the
+/// [`Cache`](struct.Cache.html) it uses during a search. A bounded
+/// backtracker is cheap to build and fast on short inputs, but can only
+/// search inputs whose length fits within its memory budget.
+///
+/// This module also contains a [builder](struct.Builder.html) for
+/// configuring the construction of a bounded backtracker.
+#[cfg(feature = "std")]
+pub mod backtrack {
+    pub use backtrack_imp::*;
+}
+
 /// Types and routines specific to dense DFAs.
 ///
 /// This module is the home of [`DenseDFA`](enum.DenseDFA.html) and each of its
@@ -340,6 +487,21 @@ pub mod dense {
     pub use dense_imp::*;
 }
 
+/// Types and routines specific to lazy DFAs.
+///
+/// This module is the home of [`LazyDFA`](struct.LazyDFA.html), which builds
+/// its states during a search instead of ahead of time, and of the
+/// [`Cache`](struct.Cache.html) that stores those states. A lazy DFA is
+/// useful when fully building a DFA would take too much time or memory,
+/// which is common for large Unicode patterns.
+///
+/// This module also contains a [builder](struct.Builder.html) for
+/// configuring the construction of a lazy DFA.
+#[cfg(feature = "std")]
+pub mod hybrid {
+    pub use hybrid_imp::*;
+}
+
 /// Types and routines specific to sparse DFAs.
 ///
 /// This module is the home of [`SparseDFA`](enum.SparseDFA.html) and each of
@@ -1,9 +1,9 @@
-use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::mem;
-use std::rc::Rc;
 
 use dense;
+use determinize::Cancel;
+use dfa::PatternID;
 use state_id::{dead_id, StateID};
 
 type DFARepr<S> = dense::Repr<Vec<S>, S>;
@@ -13,117 +13,156 @@ type DFARepr<S> = dense::Repr<Vec<S>, S>;
 /// The algorithm implemented here is mostly taken from Wikipedia:
 /// https://en.wikipedia.org/wiki/DFA_minimization#Hopcroft's_algorithm
 ///
-/// This code has had some light optimization attention paid to it,
-/// particularly in the form of reducing allocation as much as possible.
-/// However, it is still generally slow. Future optimization work should
-/// probably focus on the bigger picture rather than micro-optimizations. For
-/// example:
+/// The partition of states is represented using the refinable partition data
+/// structure described in "Efficient minimization of DFAs with partial
+/// transition functions" by Valmari and Lehtinen. This permits splitting
+/// every block of the partition against a splitter in time proportional to
+/// the number of transitions into the splitter, which makes the algorithm run
+/// in `O(kn log n)` time, where `k` is the size of the alphabet and `n` is the
+/// number of states.
 ///
-/// 1. Figure out how to more intelligently create initial partitions. That is,
-///    Hopcroft's algorithm starts by creating two partitions of DFA states
-///    that are known to NOT be equivalent: match states and non-match states.
-///    The algorithm proceeds by progressively refining these partitions into
-///    smaller partitions. If we could start with more partitions, then we
-///    could reduce the amount of work that Hopcroft's algorithm needs to do.
-/// 2. For every partition that we visit, we find all incoming transitions to
-///    every state in the partition for *every* element in the alphabet. (This
-///    is why using byte classes can significantly decrease minimization times,
-///    since byte classes shrink the alphabet.) This is quite costly and there
-///    is perhaps some redundant work being performed depending on the specific
-///    states in the set. For example, we might be able to only visit some
-///    elements of the alphabet based on the transitions.
-/// 3. Move parts of minimization into determinization. If minimization has
-///    fewer states to deal with, then it should run faster. A prime example
-///    of this might be large Unicode classes, which are generated in way that
-///    can create a lot of redundant states. (Some work has been done on this
-///    point during NFA compilation via the algorithm described in the
-///    "Incremental Construction of MinimalAcyclic Finite-State Automata"
-///    paper.)
+/// Since the cost of minimization is proportional to the size of the
+/// alphabet, we only consider one alphabet element for each class of elements
+/// on which every state has the same transitions. When the DFA doesn't use
+/// byte classes, this typically shrinks the alphabet considerably. When it
+/// does, this usually has no effect.
 pub(crate) struct Minimizer<'a, S: 'a> {
     dfa: &'a mut DFARepr<S>,
-    in_transitions: Vec<Vec<Vec<S>>>,
-    partitions: Vec<StateSet<S>>,
-    waiting: Vec<StateSet<S>>,
+    /// One representative alphabet element for each class of elements on
+    /// which every state has the same transitions.
+    classes: Vec<usize>,
+    /// The incoming transitions of every state, in a compressed
+    /// representation. The states with a transition into the state `id` on
+    /// the `i`th class are given by
+    /// `in_sources[in_starts[id * k + i]..in_starts[id * k + i + 1]]`, where
+    /// `k` is the number of classes.
+    in_starts: Vec<usize>,
+    in_sources: Vec<S>,
+    partition: Partition<S>,
+    /// The blocks of the partition that have yet to be used as splitters.
+    waiting: Vec<usize>,
+    /// Whether each block of the partition is in `waiting`.
+    in_waiting: Vec<bool>,
+    cancel: Cancel,
 }
 
 impl<'a, S: StateID> fmt::Debug for Minimizer<'a, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Minimizer")
             .field("dfa", &self.dfa)
-            .field("in_transitions", &self.in_transitions)
-            .field("partitions", &self.partitions)
+            .field("classes", &self.classes)
+            .field("in_starts", &self.in_starts)
+            .field("in_sources", &self.in_sources)
+            .field("partition", &self.partition)
             .field("waiting", &self.waiting)
+            .field("cancel", &self.cancel)
             .finish()
     }
 }
 
-/// A set of states. A state set makes up a single partition in Hopcroft's
-/// algorithm.
+/// A partition of the states of a DFA into disjoint blocks, where each block
+/// is identified by its index.
 ///
-/// It is represented by an ordered set of state identifiers. We use shared
-/// ownership so that a single state set can be in both the set of partitions
-/// and in the set of waiting sets simultaneously without an additional
-/// allocation. Generally, once a state set is built, it becomes immutable.
-///
-/// We use this representation because it avoids the overhead of more
-/// traditional set data structures (HashSet/BTreeSet), and also because
-/// computing intersection/subtraction on this representation is especially
-/// fast.
-#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
-struct StateSet<S>(Rc<RefCell<Vec<S>>>);
+/// All states are stored in a single vector, ordered such that the states in
+/// each block are contiguous. A partition is refined by marking states, which
+/// moves each marked state to the front of its block, and then splitting off
+/// the marked states of every block that has both marked and unmarked states
+/// into a new block. Both operations take time proportional to the number of
+/// states marked.
+#[derive(Debug)]
+struct Partition<S> {
+    /// The states, grouped by block.
+    elements: Vec<S>,
+    /// The index of each state in `elements`.
+    locations: Vec<usize>,
+    /// The block containing each state.
+    blocks: Vec<usize>,
+    /// The start of the range of each block in `elements`.
+    starts: Vec<usize>,
+    /// The end, exclusive, of the range of each block in `elements`.
+    ends: Vec<usize>,
+    /// The number of marked states at the front of each block.
+    marked: Vec<usize>,
+    /// The blocks containing at least one marked state.
+    touched: Vec<usize>,
+}
 
 impl<'a, S: StateID> Minimizer<'a, S> {
     pub fn new(dfa: &'a mut DFARepr<S>) -> Minimizer<'a, S> {
-        let in_transitions = Minimizer::incoming_transitions(dfa);
-        let partitions = Minimizer::initial_partitions(dfa);
-        let waiting = vec![partitions[0].clone()];
-
-        Minimizer { dfa, in_transitions, partitions, waiting }
+        let classes = Minimizer::alphabet_classes(dfa);
+        let (in_starts, in_sources) =
+            Minimizer::incoming_transitions(dfa, &classes);
+        let sets = Minimizer::initial_partitions(dfa);
+        // Every initial partition except for the largest one needs to be
+        // used as a splitter. When there are only two partitions (match and
+        // non-match states), this is just the smaller of the two.
+        let waiting: Vec<usize> = (0..sets.len() - 1).collect();
+        let mut in_waiting = vec![true; sets.len()];
+        in_waiting[sets.len() - 1] = false;
+        let partition = Partition::new(dfa.state_count(), sets);
+
+        let cancel = Cancel::default();
+        Minimizer {
+            dfa,
+            classes,
+            in_starts,
+            in_sources,
+            partition,
+            waiting,
+            in_waiting,
+            cancel,
+        }
     }
 
-    pub fn run(mut self) {
-        let mut incoming = StateSet::empty();
-        let mut scratch1 = StateSet::empty();
-        let mut scratch2 = StateSet::empty();
-        let mut newparts = vec![];
-
-        while let Some(set) = self.waiting.pop() {
-            for b in (0..self.dfa.alphabet_len()).map(|b| b as u8) {
-                self.find_incoming_to(b, &set, &mut incoming);
-
-                for p in 0..self.partitions.len() {
-                    self.partitions[p].intersection(&incoming, &mut scratch1);
-                    if scratch1.is_empty() {
-                        newparts.push(self.partitions[p].clone());
-                        continue;
-                    }
+    /// Abandon minimization once the given cancellation condition is
+    /// triggered.
+    pub fn cancel(mut self, cancel: Cancel) -> Minimizer<'a, S> {
+        self.cancel = cancel;
+        self
+    }
 
-                    self.partitions[p].subtract(&incoming, &mut scratch2);
-                    if scratch2.is_empty() {
-                        newparts.push(self.partitions[p].clone());
-                        continue;
-                    }
+    /// Minimize the DFA. If minimization is cancelled, then this returns
+    /// false and the DFA is left unchanged.
+    pub fn run(mut self) -> bool {
+        let k = self.classes.len();
+        let mut splitter = vec![];
+        let mut splits = vec![];
 
-                    let (x, y) =
-                        (scratch1.deep_clone(), scratch2.deep_clone());
-                    newparts.push(x.clone());
-                    newparts.push(y.clone());
-                    match self.find_waiting(&self.partitions[p]) {
-                        Some(i) => {
-                            self.waiting[i] = x;
-                            self.waiting.push(y);
-                        }
-                        None => {
-                            if x.len() <= y.len() {
-                                self.waiting.push(x);
-                            } else {
-                                self.waiting.push(y);
-                            }
-                        }
+        while let Some(block) = self.waiting.pop() {
+            if self.cancel.is_cancelled() {
+                return false;
+            }
+            self.in_waiting[block] = false;
+            // The splitter may itself be split below, but it must be used
+            // as it was when it was taken from the waiting list.
+            splitter.clear();
+            splitter.extend_from_slice(self.partition.block(block));
+            for i in 0..k {
+                for &id in &splitter {
+                    let t = id.to_usize() * k + i;
+                    let (start, end) =
+                        (self.in_starts[t], self.in_starts[t + 1]);
+                    for &source in &self.in_sources[start..end] {
+                        self.partition.mark(source);
                     }
                 }
-                newparts = mem::replace(&mut self.partitions, newparts);
-                newparts.clear();
+                self.partition.split(&mut splits);
+                for (old, new) in splits.drain(..) {
+                    // If the old block was still waiting, then both halves
+                    // need to be used as splitters. Otherwise, it suffices
+                    // to use the smaller half.
+                    self.in_waiting.push(false);
+                    let next = if self.in_waiting[old]
+                        || self.partition.block_len(new)
+                            <= self.partition.block_len(old)
+                    {
+                        new
+                    } else {
+                        old
+                    };
+                    self.in_waiting[next] = true;
+                    self.waiting.push(next);
+                }
             }
         }
 
@@ -136,101 +175,72 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         // equivalence class to which it belongs. The representative ID of an
         // equivalence class of states is the minimum ID in that class.
         let mut state_to_part = vec![dead_id(); self.dfa.state_count()];
-        for p in &self.partitions {
-            p.iter(|id| state_to_part[id.to_usize()] = p.min());
-        }
-
-        // Generate a new contiguous sequence of IDs for minimal states, and
-        // create a map from equivalence IDs to the new IDs. Thus, the new
-        // minimal ID of *any* state in the unminimized DFA can be obtained
-        // with minimals_ids[state_to_part[old_id]].
-        let mut minimal_ids = vec![dead_id(); self.dfa.state_count()];
-        let mut new_id = S::from_usize(0);
-        for (id, _) in self.dfa.states() {
-            if state_to_part[id.to_usize()] == id {
-                minimal_ids[id.to_usize()] = new_id;
-                new_id = S::from_usize(new_id.to_usize() + 1);
+        for b in 0..self.partition.len() {
+            let ids = self.partition.block(b);
+            let min = *ids.iter().min().unwrap();
+            for &id in ids {
+                state_to_part[id.to_usize()] = min;
             }
         }
-        // The total number of states in the minimal DFA.
-        let minimal_count = new_id.to_usize();
-
-        // Re-map this DFA in place such that the only states remaining
-        // correspond to the representative states of every equivalence class.
-        for id in (0..self.dfa.state_count()).map(S::from_usize) {
-            // If this state isn't a representative for an equivalence class,
-            // then we skip it since it won't appear in the minimal DFA.
-            if state_to_part[id.to_usize()] != id {
-                continue;
-            }
-            for (_, next) in self.dfa.get_state_mut(id).iter_mut() {
-                *next = minimal_ids[state_to_part[next.to_usize()].to_usize()];
+
+        merge_states(self.dfa, &state_to_part);
+        true
+    }
+
+    /// Returns one representative alphabet element for each class of
+    /// elements on which every state in the given DFA has the same
+    /// transitions.
+    fn alphabet_classes(dfa: &DFARepr<S>) -> Vec<usize> {
+        let alphabet_len = dfa.alphabet_len();
+        // Refine the classes one state at a time, such that after visiting a
+        // state, two elements are in the same class only if they were in the
+        // same class before and the state has the same transition on both.
+        let mut classes = vec![0; alphabet_len];
+        let mut refined: HashMap<(usize, S), usize> = HashMap::new();
+        for (_, state) in dfa.states() {
+            refined.clear();
+            for (b, next) in state.transitions() {
+                let len = refined.len();
+                let class = &mut classes[b];
+                *class = *refined.entry((*class, next)).or_insert(len);
             }
-            self.dfa.swap_states(id, minimal_ids[id.to_usize()]);
-        }
-        // Trim off all unused states from the pre-minimized DFA. This
-        // represents all states that were merged into a non-singleton
-        // equivalence class of states, and appeared after the first state
-        // in each such class. (Because the state with the smallest ID in each
-        // equivalence class is its representative ID.)
-        self.dfa.truncate_states(minimal_count);
-
-        // Update the new start state, which is now just the minimal ID of
-        // whatever state the old start state was collapsed into.
-        let old_start = self.dfa.start_state();
-        self.dfa.set_start_state(
-            minimal_ids[state_to_part[old_start.to_usize()].to_usize()],
-        );
-
-        // In order to update the ID of the maximum match state, we need to
-        // find the maximum ID among all of the match states in the minimized
-        // DFA. This is not necessarily the new ID of the unminimized maximum
-        // match state, since that could have been collapsed with a much
-        // earlier match state. Therefore, to find the new max match state,
-        // we iterate over all previous match states, find their corresponding
-        // new minimal ID, and take the maximum of those.
-        let old_max = self.dfa.max_match_state();
-        self.dfa.set_max_match_state(dead_id());
-        for id in (0..(old_max.to_usize() + 1)).map(S::from_usize) {
-            let part = state_to_part[id.to_usize()];
-            let new_id = minimal_ids[part.to_usize()];
-            if new_id > self.dfa.max_match_state() {
-                self.dfa.set_max_match_state(new_id);
+            if refined.len() == alphabet_len {
+                break;
             }
         }
-    }
-
-    fn find_waiting(&self, set: &StateSet<S>) -> Option<usize> {
-        self.waiting.iter().position(|s| s == set)
-    }
 
-    fn find_incoming_to(
-        &self,
-        b: u8,
-        set: &StateSet<S>,
-        incoming: &mut StateSet<S>,
-    ) {
-        incoming.clear();
-        set.iter(|id| {
-            for &inid in &self.in_transitions[id.to_usize()][b as usize] {
-                incoming.add(inid);
+        let mut seen = vec![false; alphabet_len];
+        let mut reps = vec![];
+        for (b, &class) in classes.iter().enumerate() {
+            if !seen[class] {
+                seen[class] = true;
+                reps.push(b);
             }
-        });
-        incoming.canonicalize();
+        }
+        reps
     }
 
-    fn initial_partitions(dfa: &DFARepr<S>) -> Vec<StateSet<S>> {
-        let mut is_match = StateSet::empty();
-        let mut no_match = StateSet::empty();
+    fn initial_partitions(dfa: &DFARepr<S>) -> Vec<Vec<S>> {
+        // Match states can only be equivalent if they match the same
+        // patterns, so they are initially partitioned by their patterns.
+        let mut sets: Vec<Vec<S>> = vec![];
+        let mut by_patterns: BTreeMap<Vec<PatternID>, usize> = BTreeMap::new();
+        let mut no_match = vec![];
         for (id, _) in dfa.states() {
             if dfa.is_match_state(id) {
-                is_match.add(id);
+                let patterns = (0..dfa.match_count(id))
+                    .map(|i| dfa.match_pattern(id, i))
+                    .collect();
+                let i = *by_patterns.entry(patterns).or_insert_with(|| {
+                    sets.push(vec![]);
+                    sets.len() - 1
+                });
+                sets[i].push(id);
             } else {
-                no_match.add(id);
+                no_match.push(id);
             }
         }
 
-        let mut sets = vec![is_match];
         if !no_match.is_empty() {
             sets.push(no_match);
         }
@@ -238,136 +248,290 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         sets
     }
 
-    fn incoming_transitions(dfa: &DFARepr<S>) -> Vec<Vec<Vec<S>>> {
-        let mut incoming = vec![];
-        for _ in dfa.states() {
-            incoming.push(vec![vec![]; dfa.alphabet_len()]);
+    /// Returns the incoming transitions of every state on each of the given
+    /// classes, in the representation used by `in_starts` and `in_sources`.
+    fn incoming_transitions(
+        dfa: &DFARepr<S>,
+        classes: &[usize],
+    ) -> (Vec<usize>, Vec<S>) {
+        let k = classes.len();
+        let mut class_index = vec![None; dfa.alphabet_len()];
+        for (i, &b) in classes.iter().enumerate() {
+            class_index[b] = Some(i);
+        }
+
+        // Count the transitions into each state on each class, and then turn
+        // the counts into the end of each range. Each range's end is then
+        // decremented as its transitions are added, which leaves it pointing
+        // at its start.
+        let mut starts = vec![0; dfa.state_count() * k + 1];
+        for (_, state) in dfa.states() {
+            for (b, next) in state.transitions() {
+                if let Some(i) = class_index[b] {
+                    starts[next.to_usize() * k + i] += 1;
+                }
+            }
         }
+        for t in 1..starts.len() {
+            starts[t] += starts[t - 1];
+        }
+        let mut sources = vec![dead_id(); starts[starts.len() - 1]];
         for (id, state) in dfa.states() {
             for (b, next) in state.transitions() {
-                incoming[next.to_usize()][b as usize].push(id);
+                if let Some(i) = class_index[b] {
+                    let t = next.to_usize() * k + i;
+                    starts[t] -= 1;
+                    sources[starts[t]] = id;
+                }
             }
         }
-        incoming
+        (starts, sources)
     }
 }
 
-impl<S: StateID> StateSet<S> {
-    fn empty() -> StateSet<S> {
-        StateSet(Rc::new(RefCell::new(vec![])))
-    }
+/// Merge states of the given DFA that are obviously equivalent, because they
+/// have identical transitions and match the same patterns.
+///
+/// Unlike minimization, this is done in a single pass over the states. The
+/// states are visited in depth first post-order, so that the successors of a
+/// state have usually been merged by the time it is visited. This makes it
+/// possible to merge entire chains of states, such as the common suffixes of
+/// literals. However, states within cycles are only merged if their
+/// transitions are exactly identical, so the DFA produced isn't necessarily
+/// minimal.
+pub(crate) fn reduce<S: StateID>(dfa: &mut DFARepr<S>) {
+    let state_count = dfa.state_count();
+    // The state that each visited state is merged into. This is always the
+    // first state visited with the same key.
+    let mut merged: Vec<Option<S>> = vec![None; state_count];
+    {
+        let dfa = &*dfa;
+        let mut reps: HashMap<(Vec<S>, bool, Vec<PatternID>), S> =
+            HashMap::new();
+        let mut visited = vec![false; state_count];
+        let mut stack = vec![];
+        for root in (0..state_count).map(S::from_usize) {
+            if visited[root.to_usize()] {
+                continue;
+            }
+            visited[root.to_usize()] = true;
+            stack.push((root, dfa.get_state(root).transitions()));
+            while !stack.is_empty() {
+                let unvisited = {
+                    let &mut (_, ref mut transitions) =
+                        stack.last_mut().unwrap();
+                    transitions
+                        .map(|(_, next)| next)
+                        .find(|next| !visited[next.to_usize()])
+                };
+                if let Some(next) = unvisited {
+                    visited[next.to_usize()] = true;
+                    stack.push((next, dfa.get_state(next).transitions()));
+                    continue;
+                }
 
-    fn add(&mut self, id: S) {
-        self.0.borrow_mut().push(id);
+                let (id, _) = stack.pop().unwrap();
+                // Successors that are still on the stack, because they're in
+                // a cycle with this state, haven't been merged yet. They are
+                // used as is.
+                let transitions = dfa
+                    .get_state(id)
+                    .transitions()
+                    .map(|(_, next)| merged[next.to_usize()].unwrap_or(next))
+                    .collect();
+                let is_match = dfa.is_match_state(id);
+                let patterns = (0..dfa.match_count(id))
+                    .map(|i| dfa.match_pattern(id, i))
+                    .collect();
+                let key = (transitions, is_match, patterns);
+                merged[id.to_usize()] = Some(*reps.entry(key).or_insert(id));
+            }
+        }
     }
 
-    fn min(&self) -> S {
-        self.0.borrow()[0]
+    // The representative of each set of merged states must be the one with
+    // the smallest ID, which isn't necessarily the one visited first.
+    let mut smallest: Vec<Option<S>> = vec![None; state_count];
+    let mut state_to_part = vec![dead_id(); state_count];
+    for id in (0..state_count).map(S::from_usize) {
+        let first = merged[id.to_usize()].unwrap().to_usize();
+        state_to_part[id.to_usize()] = *smallest[first].get_or_insert(id);
     }
+    merge_states(dfa, &state_to_part);
+}
 
-    fn canonicalize(&mut self) {
-        self.0.borrow_mut().sort();
-        self.0.borrow_mut().dedup();
+/// Update the given DFA to only contain one state for each set of equivalent
+/// states, where `state_to_part` maps every state to the representative ID of
+/// the set to which it belongs. The representative ID of a set must be the
+/// minimum ID in that set.
+fn merge_states<S: StateID>(dfa: &mut DFARepr<S>, state_to_part: &[S]) {
+    // Generate a new contiguous sequence of IDs for minimal states, and
+    // create a map from equivalence IDs to the new IDs. Thus, the new
+    // minimal ID of *any* state in the unminimized DFA can be obtained
+    // with minimals_ids[state_to_part[old_id]].
+    let mut minimal_ids = vec![dead_id(); dfa.state_count()];
+    let mut new_id = S::from_usize(0);
+    for (id, _) in dfa.states() {
+        if state_to_part[id.to_usize()] == id {
+            minimal_ids[id.to_usize()] = new_id;
+            new_id = S::from_usize(new_id.to_usize() + 1);
+        }
+    }
+    // The total number of states in the minimal DFA.
+    let minimal_count = new_id.to_usize();
+
+    // Re-map this DFA in place such that the only states remaining
+    // correspond to the representative states of every equivalence class.
+    for id in (0..dfa.state_count()).map(S::from_usize) {
+        // If this state isn't a representative for an equivalence class,
+        // then we skip it since it won't appear in the minimal DFA.
+        if state_to_part[id.to_usize()] != id {
+            continue;
+        }
+        for (_, next) in dfa.get_state_mut(id).iter_mut() {
+            *next = minimal_ids[state_to_part[next.to_usize()].to_usize()];
+        }
+        dfa.swap_states(id, minimal_ids[id.to_usize()]);
+    }
+    // Trim off all unused states from the pre-minimized DFA. This
+    // represents all states that were merged into a non-singleton
+    // equivalence class of states, and appeared after the first state
+    // in each such class. (Because the state with the smallest ID in each
+    // equivalence class is its representative ID.)
+    let old_patterns = dfa.match_state_patterns();
+    dfa.truncate_states(minimal_count);
+
+    // Update the new start state, which is now just the minimal ID of
+    // whatever state the old start state was collapsed into.
+    let old_start = dfa.start_state();
+    dfa.set_start_state(
+        minimal_ids[state_to_part[old_start.to_usize()].to_usize()],
+    );
+    if let Some(old_start) = dfa.anchored_start_state() {
+        if !dfa.is_anchored() {
+            dfa.set_anchored_start_state(
+                minimal_ids[state_to_part[old_start.to_usize()].to_usize()],
+            );
+        }
+    }
+    let starts = dfa
+        .pattern_start_states()
+        .iter()
+        .map(|old| minimal_ids[state_to_part[old.to_usize()].to_usize()])
+        .collect();
+    dfa.set_pattern_start_states(starts);
+
+    // In order to update the ID of the maximum match state, we need to
+    // find the maximum ID among all of the match states in the minimized
+    // DFA. This is not necessarily the new ID of the unminimized maximum
+    // match state, since that could have been collapsed with a much
+    // earlier match state. Therefore, to find the new max match state,
+    // we iterate over all previous match states, find their corresponding
+    // new minimal ID, and take the maximum of those.
+    let old_max = dfa.max_match_state();
+    dfa.set_max_match_state(dead_id());
+    for id in (0..(old_max.to_usize() + 1)).map(S::from_usize) {
+        let part = state_to_part[id.to_usize()];
+        let new_id = minimal_ids[part.to_usize()];
+        if new_id > dfa.max_match_state() {
+            dfa.set_max_match_state(new_id);
+        }
     }
 
-    fn clear(&mut self) {
-        self.0.borrow_mut().clear();
+    // Since states are only ever merged with states that match the same
+    // patterns, the patterns of each new match state can be taken from
+    // any of the old match states that were merged into it. The size of
+    // the new table can't exceed the size of the old one, so recording it
+    // can't fail.
+    let mut new_patterns = vec![vec![]; dfa.max_match_state().to_usize()];
+    for (i, patterns) in old_patterns.into_iter().enumerate() {
+        let new_id = minimal_ids[state_to_part[i + 1].to_usize()];
+        new_patterns[new_id.to_usize() - 1] = patterns;
     }
+    dfa.set_match_patterns(&new_patterns).unwrap();
+}
 
-    fn len(&self) -> usize {
-        self.0.borrow().len()
+impl<S: StateID> Partition<S> {
+    /// Create a partition of the states `0..state_count` from the given
+    /// blocks, which must be disjoint and cover every state.
+    fn new(state_count: usize, sets: Vec<Vec<S>>) -> Partition<S> {
+        let mut partition = Partition {
+            elements: Vec::with_capacity(state_count),
+            locations: vec![0; state_count],
+            blocks: vec![0; state_count],
+            starts: vec![],
+            ends: vec![],
+            marked: vec![],
+            touched: vec![],
+        };
+        for (block, set) in sets.into_iter().enumerate() {
+            partition.starts.push(partition.elements.len());
+            for id in set {
+                partition.locations[id.to_usize()] = partition.elements.len();
+                partition.blocks[id.to_usize()] = block;
+                partition.elements.push(id);
+            }
+            partition.ends.push(partition.elements.len());
+            partition.marked.push(0);
+        }
+        partition
     }
 
-    fn is_empty(&self) -> bool {
-        self.len() == 0
+    /// Returns the number of blocks in this partition.
+    fn len(&self) -> usize {
+        self.starts.len()
     }
 
-    fn deep_clone(&self) -> StateSet<S> {
-        let ids = self.0.borrow().iter().cloned().collect();
-        StateSet(Rc::new(RefCell::new(ids)))
+    /// Returns the states in the given block.
+    fn block(&self, block: usize) -> &[S] {
+        &self.elements[self.starts[block]..self.ends[block]]
     }
 
-    fn iter<F: FnMut(S)>(&self, mut f: F) {
-        for &id in self.0.borrow().iter() {
-            f(id);
-        }
+    /// Returns the number of states in the given block.
+    fn block_len(&self, block: usize) -> usize {
+        self.ends[block] - self.starts[block]
     }
 
-    fn intersection(&self, other: &StateSet<S>, dest: &mut StateSet<S>) {
-        dest.clear();
-        if self.is_empty() || other.is_empty() {
+    /// Mark the given state, if it isn't already marked.
+    fn mark(&mut self, id: S) {
+        let block = self.blocks[id.to_usize()];
+        let loc = self.locations[id.to_usize()];
+        let first_unmarked = self.starts[block] + self.marked[block];
+        if loc < first_unmarked {
             return;
         }
-
-        let (seta, setb) = (self.0.borrow(), other.0.borrow());
-        let (mut ita, mut itb) = (seta.iter().cloned(), setb.iter().cloned());
-        let (mut a, mut b) = (ita.next().unwrap(), itb.next().unwrap());
-        loop {
-            if a == b {
-                dest.add(a);
-                a = match ita.next() {
-                    None => break,
-                    Some(a) => a,
-                };
-                b = match itb.next() {
-                    None => break,
-                    Some(b) => b,
-                };
-            } else if a < b {
-                a = match ita.next() {
-                    None => break,
-                    Some(a) => a,
-                };
-            } else {
-                b = match itb.next() {
-                    None => break,
-                    Some(b) => b,
-                };
-            }
+        let other = self.elements[first_unmarked];
+        self.elements.swap(loc, first_unmarked);
+        self.locations[other.to_usize()] = loc;
+        self.locations[id.to_usize()] = first_unmarked;
+        if self.marked[block] == 0 {
+            self.touched.push(block);
         }
+        self.marked[block] += 1;
     }
 
-    fn subtract(&self, other: &StateSet<S>, dest: &mut StateSet<S>) {
-        dest.clear();
-        if self.is_empty() || other.is_empty() {
-            self.iter(|s| dest.add(s));
-            return;
-        }
-
-        let (seta, setb) = (self.0.borrow(), other.0.borrow());
-        let (mut ita, mut itb) = (seta.iter().cloned(), setb.iter().cloned());
-        let (mut a, mut b) = (ita.next().unwrap(), itb.next().unwrap());
-        loop {
-            if a == b {
-                a = match ita.next() {
-                    None => break,
-                    Some(a) => a,
-                };
-                b = match itb.next() {
-                    None => {
-                        dest.add(a);
-                        break;
-                    }
-                    Some(b) => b,
-                };
-            } else if a < b {
-                dest.add(a);
-                a = match ita.next() {
-                    None => break,
-                    Some(a) => a,
-                };
-            } else {
-                b = match itb.next() {
-                    None => {
-                        dest.add(a);
-                        break;
-                    }
-                    Some(b) => b,
-                };
+    /// Split every block with both marked and unmarked states, such that its
+    /// marked states are moved to a new block, and unmark all states. Each
+    /// split is recorded in `splits` as a pair of the old block and the new
+    /// block.
+    fn split(&mut self, splits: &mut Vec<(usize, usize)>) {
+        for &block in &self.touched {
+            let marked = self.marked[block];
+            self.marked[block] = 0;
+            if marked == self.block_len(block) {
+                continue;
             }
+            let new = self.starts.len();
+            let start = self.starts[block];
+            self.starts.push(start);
+            self.ends.push(start + marked);
+            self.marked.push(0);
+            self.starts[block] = start + marked;
+            for &id in &self.elements[start..start + marked] {
+                self.blocks[id.to_usize()] = new;
+            }
+            splits.push((block, new));
         }
-        for a in ita {
-            dest.add(a);
-        }
+        self.touched.clear();
     }
 }
@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use libc;
+
+/// A read-only memory map of an entire file.
+///
+/// This makes it cheap to load serialized DFAs, regexes and archives from
+/// disk, since the operating system pages in only the parts of the file
+/// that a search actually touches. A memory map always starts at a page
+/// boundary, so its contents satisfy the alignment required by every state
+/// identifier representation, and no padding or copying is needed before
+/// deserializing a dense DFA from it.
+///
+/// An `Mmap` dereferences to the bytes of the file. Anything deserialized
+/// from those bytes borrows the map, so the borrow checker guarantees that
+/// the map outlives it.
+///
+/// This is only available on Unix when the `mmap` feature is enabled.
+///
+/// # Example
+///
+/// ```
+/// use std::fs;
+///
+/// use regex_automata::{DenseDFA, Mmap, DFA};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let dfa = DenseDFA::new("foo[0-9]+")?.to_u32()?;
+/// let path = std::env::temp_dir().join("regex-automata-mmap-doc.dfa");
+/// fs::write(&path, dfa.to_bytes_native_endian()?)?;
+///
+/// let map = unsafe { Mmap::open(&path)? };
+/// let dfa: DenseDFA<&[u32], u32> = DenseDFA::try_from_bytes(&map)?;
+/// assert_eq!(Some(8), dfa.find(b"foo12345"));
+/// # fs::remove_file(&path)?;
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Debug)]
+pub struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// SAFETY: The map is read-only and owned by this value, so it can be shared
+// and sent across threads like a `Box<[u8]>`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Map the entire file at the given path into memory.
+    ///
+    /// If the file can't be opened or mapped, then an error is returned.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the map change if the file is modified, even after
+    /// the map is created, which would invalidate anything deserialized from
+    /// it and could result in memory unsafety. Callers must ensure that the
+    /// file is not modified, or truncated, for as long as the map is alive.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Mmap> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        if len > !0usize as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file is too big to map into memory",
+            ));
+        }
+        let len = len as usize;
+        if len == 0 {
+            // Empty maps are not permitted, so an empty file gets a null
+            // pointer instead, which is never dereferenced.
+            return Ok(Mmap { ptr: ptr::null_mut(), len: 0 });
+        }
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr, len })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: The map is readable and `len` bytes long, and it lives as
+        // long as `self`.
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: The map was created by `mmap` with this length, and
+            // nothing can borrow it anymore.
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
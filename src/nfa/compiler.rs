@@ -31,7 +31,7 @@
 // borrow `self` mutably both inside and outside the closure at the same
 // time.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::mem;
 
 use regex_syntax::hir::{self, Hir, HirKind};
@@ -41,7 +41,7 @@ use classes::ByteClassSet;
 use error::{Error, Result};
 use nfa::map::{Utf8BoundedMap, Utf8SuffixKey, Utf8SuffixMap};
 use nfa::range_trie::RangeTrie;
-use nfa::{State, StateID, Transition, NFA};
+use nfa::{Look, PatternID, State, StateID, Transition, NFA};
 
 /// Config knobs for the NFA compiler. See the builder's methods for more
 /// docs on each one.
@@ -50,7 +50,9 @@ struct Config {
     anchored: bool,
     allow_invalid_utf8: bool,
     reverse: bool,
+    crlf: bool,
     shrink: bool,
+    size_limit: Option<usize>,
 }
 
 impl Default for Config {
@@ -59,7 +61,9 @@ impl Default for Config {
             anchored: false,
             allow_invalid_utf8: false,
             reverse: false,
+            crlf: false,
             shrink: true,
+            size_limit: None,
         }
     }
 }
@@ -112,6 +116,110 @@ impl Builder {
         compiler.compile(nfa, expr)
     }
 
+    /// Compile the given high level intermediate representation of a regular
+    /// expression and add it as a new pattern to the given NFA.
+    ///
+    /// The new pattern's states are appended to the NFA and its start state
+    /// becomes a new alternative of the NFA's initial state, with a lower
+    /// priority than all patterns already in the NFA. The existing states of
+    /// the NFA are left as they are. Whether the new pattern is anchored or
+    /// not is determined by the given NFA and not by this builder.
+    ///
+    /// Upon success, the identifier of the new pattern is returned. Pattern
+    /// identifiers are assigned sequentially, starting at the number of
+    /// patterns already in the NFA.
+    ///
+    /// If there was a problem building the NFA, then an error is returned
+    /// and the given NFA is left unchanged.
+    pub fn add_pattern(&self, nfa: &mut NFA, expr: &Hir) -> Result<PatternID> {
+        let mut builder = self.clone();
+        let pattern = builder.anchored(true).build(expr)?;
+        if let Some(limit) = self.config.size_limit {
+            let usage = nfa.memory_usage() + pattern.memory_usage();
+            if usage > limit {
+                return Err(Error::nfa_size_limit_exceeded(limit, usage));
+            }
+        }
+        let id = nfa.pattern_count;
+        let offset = nfa.states.len();
+        let pattern_start = nfa.append(&pattern);
+        nfa.utf8 = nfa.utf8 && pattern.utf8;
+        for state in &mut nfa.states[offset..] {
+            if let State::Match { ref mut pattern_id } = *state {
+                *pattern_id = id;
+            }
+        }
+
+        // Find the state that should be the start of an anchored search.
+        // If the NFA has no patterns yet, then this is simply the new
+        // pattern's start state. Otherwise, we need a union of every
+        // pattern's start state. We create this union at most once and add
+        // alternates to it as patterns are added.
+        let start_anchored = if nfa.pattern_count == 0 {
+            pattern_start
+        } else if let Some(union_id) = nfa.pattern_union {
+            if let State::Union { ref mut alternates } = nfa.states[union_id] {
+                let mut alts = alternates.to_vec();
+                alts.push(pattern_start);
+                *alternates = alts.into_boxed_slice();
+            }
+            union_id
+        } else {
+            let union_id = nfa.states.len();
+            nfa.states.push(State::Union {
+                alternates: vec![nfa.start_anchored, pattern_start]
+                    .into_boxed_slice(),
+            });
+            nfa.pattern_union = Some(union_id);
+            union_id
+        };
+        if start_anchored != nfa.start_anchored {
+            nfa.start_anchored = start_anchored;
+            nfa.start = start_anchored;
+            if !nfa.anchored {
+                // An unanchored NFA for the empty regex is just the `.*?`
+                // prefix followed by a match state. So we copy that into our
+                // NFA and replace its match state with an epsilon transition
+                // to the new anchored start state. Any previous prefix simply
+                // becomes unreachable.
+                builder.anchored(false);
+                let prefix = builder.build(&Hir::empty())?;
+                let offset = nfa.states.len();
+                nfa.start = nfa.append(&prefix);
+                for state in &mut nfa.states[offset..] {
+                    if let State::Match { .. } = *state {
+                        *state = State::Union {
+                            alternates: vec![start_anchored]
+                                .into_boxed_slice(),
+                        };
+                    }
+                }
+            }
+        }
+
+        let mut byteset = ByteClassSet::new();
+        for state in &nfa.states {
+            match *state {
+                State::Range { ref range } => {
+                    byteset.set_range(range.start, range.end);
+                }
+                State::Sparse { ref ranges } => {
+                    for r in ranges.iter() {
+                        byteset.set_range(r.start, r.end);
+                    }
+                }
+                State::Look { look, .. } => {
+                    look.set_byte_classes(&mut byteset)
+                }
+                State::Union { .. } | State::Fail | State::Match { .. } => {}
+            }
+        }
+        nfa.byte_classes = byteset.byte_classes();
+
+        nfa.pattern_count += 1;
+        Ok(id)
+    }
+
     /// Set whether matching must be anchored at the beginning of the input.
     ///
     /// When enabled, a match must begin at the start of the input. When
@@ -149,6 +257,37 @@ impl Builder {
         self
     }
 
+    /// Compile the line anchors `(?m:^)` and `(?m:$)` such that they treat
+    /// `\r\n` as a line terminator, in addition to `\n` and `\r` on their
+    /// own. That is, `$` matches before `\r\n` and `^` matches after it,
+    /// but neither matches between `\r` and `\n`.
+    ///
+    /// This has no effect on the anchors `\A` and `\z`, nor on `^` and `$`
+    /// outside of multi-line mode.
+    ///
+    /// This is disabled by default.
+    pub fn crlf(&mut self, yes: bool) -> &mut Builder {
+        self.config.crlf = yes;
+        self
+    }
+
+    /// Set a limit, in bytes, on the approximate memory used by an NFA
+    /// during compilation.
+    ///
+    /// When the limit is exceeded, compilation stops and an error of kind
+    /// [`ErrorKind::NFASizeLimitExceeded`](../enum.ErrorKind.html#variant.NFASizeLimitExceeded)
+    /// is returned. This permits rejecting pathological patterns, such as
+    /// large bounded repetitions of Unicode classes, before spending any time
+    /// on determinization. When adding a pattern to an existing NFA with
+    /// [`add_pattern`](struct.Builder.html#method.add_pattern), the limit
+    /// applies to the combined NFA.
+    ///
+    /// By default, there is no limit.
+    pub fn size_limit(&mut self, limit: Option<usize>) -> &mut Builder {
+        self.config.size_limit = limit;
+        self
+    }
+
     /// Apply best effort heuristics to shrink the NFA at the expense of more
     /// time/memory.
     ///
@@ -195,6 +334,10 @@ pub struct Compiler {
     /// A map used to re-map state IDs when translating the compiler's internal
     /// NFA state representation to the external NFA representation.
     remap: RefCell<Vec<StateID>>,
+    /// The approximate memory used by the transitions of sparse and union
+    /// states, in bytes. Along with the number of states, this is used to
+    /// enforce the size limit.
+    transitions_memory: Cell<usize>,
     /// A set of compiler internal state IDs that correspond to states that are
     /// exclusively epsilon transitions, i.e., goto instructions, combined with
     /// the state that they point to. This is used to record said states while
@@ -235,6 +378,9 @@ enum CState {
     /// into one Union type of state, where the latter has its epsilon
     /// transitions reversed to reflect the priority inversion.
     UnionReverse { alternates: Vec<StateID> },
+    /// A look-around assertion, which is an epsilon transition to `next`
+    /// that may only be followed where the assertion holds.
+    Look { look: Look, next: StateID },
     /// A match state. There is exactly one such occurrence of this state in
     /// an NFA.
     Match,
@@ -259,6 +405,7 @@ impl Compiler {
             trie_state: RefCell::new(RangeTrie::new()),
             utf8_suffix: RefCell::new(Utf8SuffixMap::new(1000)),
             remap: RefCell::new(vec![]),
+            transitions_memory: Cell::new(0),
             empties: RefCell::new(vec![]),
         }
     }
@@ -270,6 +417,7 @@ impl Compiler {
     /// allocations.
     fn clear(&self) {
         self.states.borrow_mut().clear();
+        self.transitions_memory.set(0);
         // We don't need to clear anything else since they are cleared on
         // their own and only when they are used.
     }
@@ -285,6 +433,8 @@ impl Compiler {
     /// Convert the current intermediate NFA to its final compiled form.
     fn compile(&self, nfa: &mut NFA, expr: &Hir) -> Result<()> {
         nfa.anchored = self.config.anchored;
+        nfa.reverse = self.config.reverse;
+        nfa.utf8 = !self.config.allow_invalid_utf8 && expr.is_always_utf8();
 
         let mut start = self.add_empty();
         if !nfa.anchored {
@@ -297,10 +447,14 @@ impl Compiler {
             start = compiled.end;
         }
         let compiled = self.c(&expr)?;
+        self.check_size_limit()?;
         let match_id = self.add_match();
         self.patch(start, compiled.start);
         self.patch(compiled.end, match_id);
         self.finish(nfa);
+        nfa.start_anchored = self.remap.borrow()[compiled.start];
+        nfa.pattern_count = 1;
+        nfa.pattern_union = None;
         Ok(())
     }
 
@@ -364,9 +518,14 @@ impl Compiler {
                         alternates: alternates.into_boxed_slice(),
                     });
                 }
+                CState::Look { look, next } => {
+                    remap[id] = nfa.states.len();
+                    look.set_byte_classes(&mut byteset);
+                    nfa.states.push(State::Look { look, next });
+                }
                 CState::Match => {
                     remap[id] = nfa.states.len();
-                    nfa.states.push(State::Match);
+                    nfa.states.push(State::Match { pattern_id: 0 });
                 }
             }
         }
@@ -390,6 +549,7 @@ impl Compiler {
     }
 
     fn c(&self, expr: &Hir) -> Result<ThompsonRef> {
+        self.check_size_limit()?;
         match *expr.kind() {
             HirKind::Empty => {
                 let id = self.add_empty();
@@ -419,8 +579,24 @@ impl Compiler {
             HirKind::Alternation(ref exprs) => {
                 self.c_alternation(exprs.iter().map(|e| self.c(e)))
             }
-            HirKind::Anchor(_) => Err(Error::unsupported_anchor()),
-            HirKind::WordBoundary(_) => Err(Error::unsupported_word()),
+            HirKind::Anchor(ref anchor) => Ok(self.c_look(match *anchor {
+                hir::Anchor::StartText => Look::StartText,
+                hir::Anchor::EndText => Look::EndText,
+                hir::Anchor::StartLine if self.config.crlf => {
+                    Look::StartLineCRLF
+                }
+                hir::Anchor::EndLine if self.config.crlf => Look::EndLineCRLF,
+                hir::Anchor::StartLine => Look::StartLine,
+                hir::Anchor::EndLine => Look::EndLine,
+            })),
+            HirKind::WordBoundary(ref wb) => Ok(self.c_look(match *wb {
+                hir::WordBoundary::Ascii => Look::WordBoundaryAscii,
+                hir::WordBoundary::AsciiNegate => Look::NotWordBoundaryAscii,
+                hir::WordBoundary::Unicode => Look::WordBoundaryUnicode,
+                hir::WordBoundary::UnicodeNegate => {
+                    Look::NotWordBoundaryUnicode
+                }
+            })),
         }
     }
 
@@ -761,6 +937,12 @@ impl Compiler {
         ThompsonRef { start: id, end: id }
     }
 
+    fn c_look(&self, look: Look) -> ThompsonRef {
+        let look = if self.config.reverse { look.reversed() } else { look };
+        let id = self.add_look(look);
+        ThompsonRef { start: id, end: id }
+    }
+
     fn c_empty(&self) -> ThompsonRef {
         let id = self.add_empty();
         ThompsonRef { start: id, end: id }
@@ -782,6 +964,19 @@ impl Compiler {
         }))
     }
 
+    /// Return an error if the approximate memory used by the states compiled
+    /// so far exceeds the configured size limit.
+    fn check_size_limit(&self) -> Result<()> {
+        if let Some(limit) = self.config.size_limit {
+            let usage = self.states.borrow().len() * mem::size_of::<CState>()
+                + self.transitions_memory.get();
+            if usage > limit {
+                return Err(Error::nfa_size_limit_exceeded(limit, usage));
+            }
+        }
+        Ok(())
+    }
+
     fn patch(&self, from: StateID, to: StateID) {
         match self.states.borrow_mut()[from] {
             CState::Empty { ref mut next } => {
@@ -795,14 +990,23 @@ impl Compiler {
             }
             CState::Union { ref mut alternates } => {
                 alternates.push(to);
+                self.add_transitions_memory(mem::size_of::<StateID>());
             }
             CState::UnionReverse { ref mut alternates } => {
                 alternates.push(to);
+                self.add_transitions_memory(mem::size_of::<StateID>());
+            }
+            CState::Look { ref mut next, .. } => {
+                *next = to;
             }
             CState::Match => {}
         }
     }
 
+    fn add_transitions_memory(&self, bytes: usize) {
+        self.transitions_memory.set(self.transitions_memory.get() + bytes);
+    }
+
     fn add_empty(&self) -> StateID {
         let id = self.states.borrow().len();
         self.states.borrow_mut().push(CState::Empty { next: 0 });
@@ -825,6 +1029,9 @@ impl Compiler {
             return id;
         }
         let id = self.states.borrow().len();
+        self.add_transitions_memory(
+            ranges.len() * mem::size_of::<Transition>(),
+        );
         let state = CState::Sparse { ranges };
         self.states.borrow_mut().push(state);
         id
@@ -844,6 +1051,12 @@ impl Compiler {
         id
     }
 
+    fn add_look(&self, look: Look) -> StateID {
+        let id = self.states.borrow().len();
+        self.states.borrow_mut().push(CState::Look { look, next: 0 });
+        id
+    }
+
     fn add_match(&self) -> StateID {
         let id = self.states.borrow().len();
         self.states.borrow_mut().push(CState::Match);
@@ -1002,7 +1215,7 @@ mod tests {
     use regex_syntax::hir::Hir;
     use regex_syntax::ParserBuilder;
 
-    use super::{Builder, State, StateID, Transition, NFA};
+    use super::{Builder, Look, State, StateID, Transition, NFA};
 
     fn parse(pattern: &str) -> Hir {
         ParserBuilder::new().build().parse(pattern).unwrap()
@@ -1034,22 +1247,40 @@ mod tests {
         State::Union { alternates: alts.to_vec().into_boxed_slice() }
     }
 
+    fn s_look(look: Look, next: StateID) -> State {
+        State::Look { look, next }
+    }
+
     fn s_match() -> State {
-        State::Match
+        State::Match { pattern_id: 0 }
     }
 
     #[test]
-    fn errors() {
-        // unsupported anchors
-        assert!(Builder::new().build(&parse(r"^")).is_err());
-        assert!(Builder::new().build(&parse(r"$")).is_err());
-        assert!(Builder::new().build(&parse(r"\A")).is_err());
-        assert!(Builder::new().build(&parse(r"\z")).is_err());
+    fn compile_anchors() {
+        assert_eq!(
+            build(r"^a$").states,
+            &[
+                s_look(Look::StartText, 1),
+                s_byte(b'a', 2),
+                s_look(Look::EndText, 3),
+                s_match()
+            ]
+        );
+        assert_eq!(
+            build(r"(?m)^$").states,
+            &[s_look(Look::StartLine, 1), s_look(Look::EndLine, 2), s_match()]
+        );
 
-        // unsupported word boundaries
-        assert!(Builder::new().build(&parse(r"\b")).is_err());
-        assert!(Builder::new().build(&parse(r"\B")).is_err());
-        assert!(Builder::new().build(&parse(r"(?-u)\b")).is_err());
+        // Anchors swap places in a reverse NFA.
+        let nfa = Builder::new()
+            .anchored(true)
+            .reverse(true)
+            .build(&parse(r"^a"))
+            .unwrap();
+        assert_eq!(
+            nfa.states,
+            &[s_byte(b'a', 1), s_look(Look::EndText, 2), s_match()]
+        );
     }
 
     // Test that building an unanchored NFA has an appropriate `.*?` prefix.
@@ -1111,6 +1342,32 @@ mod tests {
         assert_eq!(nfa.states, &[s_byte(b'\xFF', 1), s_match(),]);
     }
 
+    #[test]
+    fn compile_look() {
+        assert_eq!(
+            build(r"a\b").states,
+            &[
+                s_byte(b'a', 1),
+                s_look(Look::WordBoundaryUnicode, 2),
+                s_match()
+            ]
+        );
+        assert_eq!(
+            build(r"(?-u:\b)a").states,
+            &[s_look(Look::WordBoundaryAscii, 1), s_byte(b'a', 2), s_match()]
+        );
+        assert_eq!(
+            build(r"\B").states,
+            &[s_look(Look::NotWordBoundaryUnicode, 1), s_match()]
+        );
+
+        let nfa = build(r"(?-u:\b)a");
+        assert!(nfa.has_look());
+        assert!(!nfa.has_unicode_look());
+        assert!(build(r"a\B").has_unicode_look());
+        assert!(!build(r"a").has_look());
+    }
+
     #[test]
     fn compile_class() {
         assert_eq!(
@@ -1190,4 +1447,18 @@ mod tests {
             &[s_byte(b'a', 2), s_union(&[0, 2]), s_match(),]
         );
     }
+
+    #[test]
+    fn size_limit() {
+        let nfa = build(r"\w{3}");
+        let mut builder = Builder::new();
+        builder.anchored(true).size_limit(Some(nfa.memory_usage() * 10));
+        assert!(builder.build(&parse(r"\w{3}")).is_ok());
+        assert!(builder.build(&parse(r"\w{100}")).is_err());
+
+        let mut nfa = builder.build(&parse(r"\w{3}")).unwrap();
+        let before = nfa.states.len();
+        assert!(builder.add_pattern(&mut nfa, &parse(r"\w{50}")).is_err());
+        assert_eq!(before, nfa.states.len());
+    }
 }
@@ -0,0 +1,165 @@
+// A literal trie is used to build the HIR of an alternation of literals with
+// their common prefixes factored out, such that compiling it to an NFA and
+// then determinizing it does much less work than the equivalent alternation
+// written out in full. For example, `foo|foobar|fox` becomes `fo(o(|bar)|x)`.
+//
+// The tricky part is preserving the priority of the literals, on which
+// leftmost-first match semantics depend. A plain trie doesn't: given the
+// literals `abxq`, `ab` and `abxy`, in that order, a plain trie produces
+// `ab(x(q|y)|)`, which prefers `abxy` over `ab` even though `ab` comes first.
+// So the transitions of each state are split into chunks, where a new chunk
+// begins whenever a literal ends in that state. A literal only follows an
+// existing transition in the last chunk of a state, and otherwise adds a new
+// one there. The example above then becomes `ab(xq||xy)`. Within a chunk,
+// every transition is on a different byte, so at most one of them can match
+// and their relative order doesn't matter.
+
+use regex_syntax::hir::{self, Hir};
+
+/// A trie of literals that preserves their priority.
+#[derive(Debug)]
+pub(crate) struct LiteralTrie {
+    /// The states of the trie, where the first state is the root.
+    states: Vec<State>,
+    /// Whether ASCII letters match regardless of case.
+    ascii_case_insensitive: bool,
+    /// Whether no literal has been inserted yet.
+    empty: bool,
+}
+
+/// A single state of a literal trie.
+#[derive(Debug)]
+struct State {
+    /// The transitions out of this state, in chunks ordered by priority. A
+    /// literal ends in this state before each chunk but the first, so a
+    /// state in which no literal ends has exactly one chunk. Each transition
+    /// is a byte, which is lowercase in case insensitive mode, and the index
+    /// of the state it leads to.
+    chunks: Vec<Vec<(u8, usize)>>,
+}
+
+impl State {
+    fn new() -> State {
+        State { chunks: vec![vec![]] }
+    }
+
+    /// Returns the only transition out of this state, if this state has
+    /// exactly one transition and no literal ends in it.
+    fn single(&self) -> Option<(u8, usize)> {
+        if self.chunks.len() == 1 && self.chunks[0].len() == 1 {
+            Some(self.chunks[0][0])
+        } else {
+            None
+        }
+    }
+}
+
+impl LiteralTrie {
+    /// Create a new empty trie.
+    pub fn new(ascii_case_insensitive: bool) -> LiteralTrie {
+        LiteralTrie {
+            states: vec![State::new()],
+            ascii_case_insensitive,
+            empty: true,
+        }
+    }
+
+    /// Insert the given literal, with a lower priority than every literal
+    /// inserted before it.
+    pub fn insert(&mut self, literal: &[u8]) {
+        self.empty = false;
+        let mut id = 0;
+        for &byte in literal {
+            let byte = self.fold(byte);
+            let found = self.states[id]
+                .chunks
+                .last()
+                .unwrap()
+                .iter()
+                .find(|&&(b, _)| b == byte)
+                .map(|&(_, next)| next);
+            id = match found {
+                Some(next) => next,
+                None => {
+                    let next = self.states.len();
+                    self.states.push(State::new());
+                    self.states[id]
+                        .chunks
+                        .last_mut()
+                        .unwrap()
+                        .push((byte, next));
+                    next
+                }
+            };
+        }
+        // A literal ending right after another one that ended in the same
+        // state, with no transitions in between, is a duplicate that can
+        // never be preferred.
+        let chunks = &mut self.states[id].chunks;
+        if chunks.len() == 1 || !chunks.last().unwrap().is_empty() {
+            chunks.push(vec![]);
+        }
+    }
+
+    /// Returns the HIR of an alternation of the literals in this trie, in
+    /// order of priority. If no literal was inserted, then the HIR returned
+    /// never matches.
+    pub fn to_hir(&self) -> Hir {
+        if self.empty {
+            return Hir::class(hir::Class::Bytes(hir::ClassBytes::empty()));
+        }
+        self.state_hir(0)
+    }
+
+    /// Returns the HIR matching the rest of the literals in the given state.
+    fn state_hir(&self, id: usize) -> Hir {
+        let mut alts = vec![];
+        for (i, chunk) in self.states[id].chunks.iter().enumerate() {
+            if i > 0 {
+                alts.push(Hir::empty());
+            }
+            for &(byte, next) in chunk {
+                alts.push(self.transition_hir(byte, next));
+            }
+        }
+        Hir::alternation(alts)
+    }
+
+    /// Returns the HIR matching the given byte followed by the rest of the
+    /// literals in the given state.
+    ///
+    /// Runs of states with a single transition are concatenated, which
+    /// keeps the nesting of the HIR proportional to the number of states in
+    /// which literals diverge rather than to the length of the literals.
+    fn transition_hir(&self, byte: u8, mut next: usize) -> Hir {
+        let mut concat = vec![self.byte_hir(byte)];
+        while let Some((byte, to)) = self.states[next].single() {
+            concat.push(self.byte_hir(byte));
+            next = to;
+        }
+        let rest = self.state_hir(next);
+        if *rest.kind() != hir::HirKind::Empty {
+            concat.push(rest);
+        }
+        Hir::concat(concat)
+    }
+
+    /// Returns the HIR matching the given byte.
+    fn byte_hir(&self, byte: u8) -> Hir {
+        let mut cls =
+            hir::ClassBytes::new(vec![hir::ClassBytesRange::new(byte, byte)]);
+        if self.ascii_case_insensitive && byte.is_ascii_lowercase() {
+            let upper = byte.to_ascii_uppercase();
+            cls.push(hir::ClassBytesRange::new(upper, upper));
+        }
+        Hir::class(hir::Class::Bytes(cls))
+    }
+
+    fn fold(&self, byte: u8) -> u8 {
+        if self.ascii_case_insensitive {
+            byte.to_ascii_lowercase()
+        } else {
+            byte
+        }
+    }
+}
@@ -1,19 +1,61 @@
+use std::collections::BTreeMap;
 use std::fmt;
+use std::mem;
+use std::str;
 
-use classes::ByteClasses;
+use regex_syntax::{is_word_byte, is_word_character, ParserBuilder};
+
+use classes::{ByteClassSet, ByteClasses};
+use dot;
+use error::{Error, Result};
 pub use nfa::compiler::Builder;
+pub(crate) use nfa::literal_trie::LiteralTrie;
 
 mod compiler;
+mod literal_trie;
 mod map;
 mod range_trie;
 
 /// The representation for an NFA state identifier.
 pub type StateID = usize;
 
+pub use dfa::PatternID;
+
 /// A final compiled NFA.
 ///
 /// The states of the NFA are indexed by state IDs, which are how transitions
-/// are expressed.
+/// are expressed. Each state is either a transition on a range of bytes, a
+/// set of such transitions, an epsilon transition to one or more states in
+/// order of preference, a look-around assertion, a failure state or a match
+/// state. See
+/// [`State`](enum.State.html) for details.
+///
+/// An NFA is typically built with
+/// [`dense::Builder::build_nfa`](../dense/struct.Builder.html#method.build_nfa),
+/// and can then be converted to any number of DFAs with
+/// [`dense::Builder::build_from_nfa`](../dense/struct.Builder.html#method.build_from_nfa).
+///
+/// # Example
+///
+/// This example shows how to inspect the states of an NFA.
+///
+/// ```
+/// use regex_automata::dense;
+/// use regex_automata::nfa::State;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let nfa = dense::Builder::new().anchored(true).build_nfa("ab")?;
+/// let ranges: Vec<(u8, u8)> = nfa
+///     .states()
+///     .iter()
+///     .filter_map(|state| match *state {
+///         State::Range { ref range } => Some((range.start, range.end)),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(ranges, vec![(b'a', b'a'), (b'b', b'b')]);
+/// # Ok(()) }; example().unwrap()
+/// ```
 #[derive(Clone)]
 pub struct NFA {
     /// Whether this NFA can only match at the beginning of input or not.
@@ -21,11 +63,27 @@ pub struct NFA {
     /// When true, a match should only be reported if it begins at the 0th
     /// index of the haystack.
     anchored: bool,
+    /// Whether this NFA matches the reverse of its patterns, i.e., whether
+    /// it is meant to read the input from its end to its beginning.
+    reverse: bool,
+    /// Whether every match of this NFA on valid UTF-8 begins and ends at a
+    /// codepoint boundary. This is false when the NFA was compiled with
+    /// support for matching invalid UTF-8.
+    utf8: bool,
     /// The starting state of this NFA.
     start: StateID,
+    /// The starting state of this NFA, excluding any unanchored prefix. When
+    /// the NFA is anchored, this is always equivalent to `start`.
+    start_anchored: StateID,
+    /// The total number of patterns in this NFA.
+    pattern_count: usize,
+    /// The union state whose alternates are the start states of each
+    /// pattern, if one exists. This is created when a second pattern is
+    /// added to an NFA.
+    pattern_union: Option<StateID>,
     /// The state list. This list is guaranteed to be indexable by the starting
     /// state ID, and it is also guaranteed to contain exactly one `Match`
-    /// state.
+    /// state for each pattern.
     states: Vec<State>,
     /// A mapping from any byte value to its corresponding equivalence class
     /// identifier. Two bytes in the same equivalence class cannot discriminate
@@ -46,8 +104,13 @@ impl NFA {
     pub fn always_match() -> NFA {
         NFA {
             anchored: false,
+            reverse: false,
+            utf8: true,
             start: 0,
-            states: vec![State::Match],
+            start_anchored: 0,
+            pattern_count: 1,
+            pattern_union: None,
+            states: vec![State::Match { pattern_id: 0 }],
             byte_classes: ByteClasses::empty(),
         }
     }
@@ -56,27 +119,365 @@ impl NFA {
     pub fn never_match() -> NFA {
         NFA {
             anchored: false,
+            reverse: false,
+            utf8: true,
             start: 0,
+            start_anchored: 0,
+            pattern_count: 0,
+            pattern_union: None,
             states: vec![State::Fail],
             byte_classes: ByteClasses::empty(),
         }
     }
 
+    /// Parse the given pattern using the default syntax configuration and add
+    /// it to this NFA, returning the new pattern's identifier.
+    ///
+    /// This only appends the pattern's states to this NFA, and thus does not
+    /// require recompiling any of the patterns already present. Once all
+    /// patterns have been added, the NFA can be converted to a DFA with a
+    /// single determinization, which will match if any of the patterns
+    /// match.
+    ///
+    /// To add a pattern with a non-default configuration, use
+    /// [`Builder::add_pattern`](struct.Builder.html#method.add_pattern).
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned and this NFA is left unchanged.
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<PatternID> {
+        let hir = ParserBuilder::new()
+            .build()
+            .parse(pattern)
+            .map_err(Error::syntax)?;
+        Builder::new().add_pattern(self, &hir)
+    }
+
     /// Returns true if and only if this NFA is anchored.
     pub fn is_anchored(&self) -> bool {
         self.anchored
     }
 
+    /// Returns true if and only if this NFA matches the reverse of its
+    /// patterns, as when it is built with
+    /// [`Builder::reverse`](struct.Builder.html#method.reverse) or by
+    /// [`NFA::reverse`](struct.NFA.html#method.reverse).
+    ///
+    /// Some look-around assertions, such as CRLF-aware line anchors, need
+    /// to know the direction in which the input is read.
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+
+    /// Returns true if and only if this NFA was compiled in UTF-8 mode, in
+    /// which case every match it reports on valid UTF-8 begins and ends at a
+    /// codepoint boundary.
+    ///
+    /// This is false when the NFA was compiled with
+    /// [`Builder::allow_invalid_utf8`](struct.Builder.html#method.allow_invalid_utf8)
+    /// enabled, in which case it may match any sequence of bytes.
+    pub fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
     /// Return the number of states in this NFA.
     pub fn len(&self) -> usize {
         self.states.len()
     }
 
+    /// Return the total number of patterns in this NFA.
+    pub fn pattern_count(&self) -> usize {
+        self.pattern_count
+    }
+
     /// Return the ID of the initial state of this NFA.
     pub fn start(&self) -> StateID {
         self.start
     }
 
+    /// Return every state in this NFA, such that the state with ID `id` is at
+    /// index `id`.
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    /// Returns the approximate heap memory used by this NFA's states, in
+    /// bytes.
+    pub fn memory_usage(&self) -> usize {
+        let mut usage = self.states.len() * mem::size_of::<State>();
+        for state in &self.states {
+            usage += match *state {
+                State::Sparse { ref ranges } => {
+                    ranges.len() * mem::size_of::<Transition>()
+                }
+                State::Union { ref alternates } => {
+                    alternates.len() * mem::size_of::<StateID>()
+                }
+                State::Range { .. }
+                | State::Look { .. }
+                | State::Fail
+                | State::Match { .. } => 0,
+            };
+        }
+        usage
+    }
+
+    /// Returns true if and only if this NFA contains a look-around
+    /// assertion, such as a word boundary.
+    pub fn has_look(&self) -> bool {
+        self.states.iter().any(State::is_look)
+    }
+
+    /// Returns true if and only if this NFA contains a look-around assertion
+    /// that needs to decode UTF-8 to be evaluated, such as a Unicode word
+    /// boundary.
+    pub fn has_unicode_look(&self) -> bool {
+        self.states.iter().any(|s| match *s {
+            State::Look { look, .. } => look.is_unicode(),
+            _ => false,
+        })
+    }
+
+    /// Return the ID of the initial state of this NFA for anchored searches.
+    /// This is the same as `start`, except it excludes any unanchored prefix.
+    pub fn start_anchored(&self) -> StateID {
+        self.start_anchored
+    }
+
+    /// Render this NFA as a directed graph in the Graphviz DOT language.
+    ///
+    /// Each state is drawn as a node labelled with its ID. Byte range
+    /// transitions are labelled with their ranges, while the epsilon
+    /// transitions of a union state are drawn dashed and labelled with their
+    /// order of preference. Match states are drawn with a double circle and
+    /// fail states as a box.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let nfa = dense::Builder::new().anchored(true).build_nfa("a|b")?;
+    /// let dot = nfa.to_dot();
+    /// assert!(dot.starts_with("digraph {"));
+    /// assert!(dot.contains("[label=\"a\"]"));
+    /// assert!(dot.contains("style=dashed"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = dot::Writer::new();
+        dot.start("start", self.start, None);
+        if self.start_anchored != self.start {
+            dot.start("anchored", self.start_anchored, Some("anchored"));
+        }
+        for (id, state) in self.states.iter().enumerate() {
+            match *state {
+                State::Range { ref range } => {
+                    dot.node(id, &id.to_string(), None);
+                    dot.edge(id, range.next, &[(range.start, range.end)]);
+                }
+                State::Sparse { ref ranges } => {
+                    dot.node(id, &id.to_string(), None);
+                    for t in ranges.iter() {
+                        dot.edge(id, t.next, &[(t.start, t.end)]);
+                    }
+                }
+                State::Union { ref alternates } => {
+                    dot.node(id, &id.to_string(), None);
+                    for (i, &alt) in alternates.iter().enumerate() {
+                        dot.epsilon(id, alt, i);
+                    }
+                }
+                State::Look { look, next } => {
+                    dot.node(id, &id.to_string(), None);
+                    dot.look(id, next, look.as_str());
+                }
+                State::Fail => dot.node(id, &id.to_string(), Some("box")),
+                State::Match { pattern_id } => {
+                    let label = if self.pattern_count > 1 {
+                        format!("{} ({})", id, pattern_id)
+                    } else {
+                        id.to_string()
+                    };
+                    dot.node(id, &label, Some("doublecircle"));
+                }
+            }
+        }
+        dot.finish()
+    }
+
+    /// Return a new NFA that matches the reverse of every string matched by
+    /// this NFA.
+    ///
+    /// The NFA returned is always anchored, and its match states report the
+    /// same patterns as this NFA. This permits building a reverse DFA, which
+    /// finds the start of a match from its end, without parsing or compiling
+    /// the patterns again. The unanchored prefix of this NFA, if any, is not
+    /// part of the reverse NFA.
+    ///
+    /// Reversing an NFA preserves the strings it matches, but not its
+    /// preferences among alternatives. A DFA built from the reverse NFA
+    /// should therefore use longest match semantics, e.g., with
+    /// [`dense::Builder::longest_match`](../dense/struct.Builder.html#method.longest_match),
+    /// which is how the reverse DFA of a
+    /// [`Regex`](../struct.Regex.html) is built.
+    pub fn reverse(&self) -> NFA {
+        let starts = self.pattern_starts();
+        let len = self.states.len();
+
+        // Only the states that are part of a pattern are reversed. This
+        // excludes the unanchored prefix and the union of pattern starts.
+        let mut reachable = vec![false; len];
+        let mut stack = starts.clone();
+        while let Some(id) = stack.pop() {
+            if reachable[id] {
+                continue;
+            }
+            reachable[id] = true;
+            match self.states[id] {
+                State::Range { ref range } => stack.push(range.next),
+                State::Sparse { ref ranges } => {
+                    stack.extend(ranges.iter().map(|r| r.next));
+                }
+                State::Union { ref alternates } => {
+                    stack.extend(alternates.iter().cloned());
+                }
+                State::Look { next, .. } => stack.push(next),
+                State::Fail | State::Match { .. } => {}
+            }
+        }
+
+        // Each state in the reverse NFA keeps the ID it has in this NFA, and
+        // becomes a union of every state that led to it. Transitions on bytes
+        // become new states following those.
+        let mut states: Vec<State> = vec![State::Fail; len];
+        let mut incoming: Vec<Vec<StateID>> = vec![vec![]; len];
+        for (pattern_id, &start) in starts.iter().enumerate() {
+            incoming[start].push(states.len());
+            states.push(State::Match { pattern_id });
+        }
+        let mut matches = vec![];
+        for id in (0..len).filter(|&id| reachable[id]) {
+            match self.states[id] {
+                State::Range { ref range } => {
+                    incoming[range.next].push(states.len());
+                    states.push(State::Range {
+                        range: Transition { next: id, ..*range },
+                    });
+                }
+                State::Sparse { ref ranges } => {
+                    let mut by_next: BTreeMap<StateID, Vec<Transition>> =
+                        BTreeMap::new();
+                    for r in ranges.iter() {
+                        let rev = Transition { next: id, ..*r };
+                        by_next.entry(r.next).or_default().push(rev);
+                    }
+                    for (next, mut rev) in by_next {
+                        incoming[next].push(states.len());
+                        if rev.len() == 1 {
+                            states.push(State::Range {
+                                range: rev.pop().unwrap(),
+                            });
+                        } else {
+                            states.push(State::Sparse {
+                                ranges: rev.into_boxed_slice(),
+                            });
+                        }
+                    }
+                }
+                State::Union { ref alternates } => {
+                    for &alt in alternates.iter() {
+                        incoming[alt].push(id);
+                    }
+                }
+                State::Look { look, next } => {
+                    incoming[next].push(states.len());
+                    states
+                        .push(State::Look { look: look.reversed(), next: id });
+                }
+                State::Match { pattern_id } => matches.push((pattern_id, id)),
+                State::Fail => {}
+            }
+        }
+        for (id, alternates) in incoming.into_iter().enumerate() {
+            if !alternates.is_empty() {
+                states[id] =
+                    State::Union { alternates: alternates.into_boxed_slice() };
+            }
+        }
+        matches.sort();
+        let start = states.len();
+        states.push(State::Union {
+            alternates: matches.into_iter().map(|(_, id)| id).collect(),
+        });
+
+        NFA {
+            anchored: true,
+            reverse: !self.reverse,
+            utf8: self.utf8,
+            start,
+            start_anchored: start,
+            pattern_count: self.pattern_count,
+            pattern_union: None,
+            states,
+            byte_classes: self.byte_classes,
+        }
+    }
+
+    /// Return the start state of each pattern in this NFA, in the order of
+    /// their identifiers.
+    fn pattern_starts(&self) -> Vec<StateID> {
+        if self.pattern_count == 0 {
+            return vec![];
+        }
+        match self.pattern_union {
+            Some(id) => match self.states[id] {
+                State::Union { ref alternates } => alternates.to_vec(),
+                _ => unreachable!("pattern union must be a union state"),
+            },
+            None => vec![self.start_anchored],
+        }
+    }
+
+    /// Return the start state of each pattern in this NFA for anchored
+    /// searches, in the order of their identifiers. A pattern whose start
+    /// state is `None` can never match.
+    ///
+    /// This works for reverse NFAs too, where the start of each pattern is
+    /// the reversed match state of that pattern, if it is reachable.
+    pub(crate) fn anchored_pattern_starts(&self) -> Vec<Option<StateID>> {
+        if self.pattern_count <= 1 || self.pattern_union.is_some() {
+            return self.pattern_starts().into_iter().map(Some).collect();
+        }
+        // Only a reverse NFA has more than one pattern but no pattern union.
+        // Its start state is a union of the reversed match states.
+        let mut starts = vec![None; self.pattern_count];
+        if let State::Union { ref alternates } =
+            self.states[self.start_anchored]
+        {
+            for &id in alternates.iter() {
+                if let State::Match { pattern_id } = self.states[id] {
+                    starts[pattern_id] = Some(id);
+                }
+            }
+        }
+        starts
+    }
+
+    /// Append all of the states in the given NFA to this one, and return the
+    /// ID of the given NFA's start state in this NFA.
+    fn append(&mut self, other: &NFA) -> StateID {
+        let offset = self.states.len();
+        let remap: Vec<StateID> =
+            (0..other.states.len()).map(|id| id + offset).collect();
+        for state in &other.states {
+            let mut state = state.clone();
+            state.remap(&remap);
+            self.states.push(state);
+        }
+        remap[other.start]
+    }
+
     /// Return the NFA state corresponding to the given ID.
     pub fn state(&self, id: StateID) -> &State {
         &self.states[id]
@@ -108,31 +509,56 @@ pub enum State {
     ///
     /// This is a special case of Sparse in that it encodes only one transition
     /// (and therefore avoids the allocation).
-    Range { range: Transition },
+    Range {
+        /// The single transition out of this state.
+        range: Transition,
+    },
     /// A state with possibly many transitions, represented in a sparse
     /// fashion. Transitions are ordered lexicographically by input range.
     /// As such, this may only be used when every transition has equal
     /// priority. (In practice, this is only used for encoding large UTF-8
     /// automata.)
-    Sparse { ranges: Box<[Transition]> },
+    Sparse {
+        /// The transitions out of this state, in order of input range.
+        ranges: Box<[Transition]>,
+    },
     /// An alternation such that there exists an epsilon transition to all
     /// states in `alternates`, where matches found via earlier transitions
     /// are preferred over later transitions.
-    Union { alternates: Box<[StateID]> },
+    Union {
+        /// The states reachable from this one, in order of preference.
+        alternates: Box<[StateID]>,
+    },
+    /// A look-around assertion, which is an epsilon transition to `next`
+    /// that may only be followed at positions in the input where the
+    /// assertion holds.
+    Look {
+        /// The assertion that must hold.
+        look: Look,
+        /// The state to transition to when the assertion holds.
+        next: StateID,
+    },
     /// A fail state. When encountered, the automaton is guaranteed to never
     /// reach a match state.
     Fail,
     /// A match state. There is exactly one such occurrence of this state in
-    /// an NFA.
-    Match,
+    /// an NFA for each pattern, which records the identifier of the pattern
+    /// that matched.
+    Match {
+        /// The identifier of the pattern that matched.
+        pattern_id: PatternID,
+    },
 }
 
 /// A transition to another state, only if the given byte falls in the
 /// inclusive range specified.
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Transition {
+    /// The inclusive start of the byte range.
     pub start: u8,
+    /// The inclusive end of the byte range.
     pub end: u8,
+    /// The identifier of the state to transition to.
     pub next: StateID,
 }
 
@@ -144,8 +570,20 @@ impl State {
             State::Range { .. }
             | State::Sparse { .. }
             | State::Fail
-            | State::Match => false,
-            State::Union { .. } => true,
+            | State::Match { .. } => false,
+            State::Union { .. } | State::Look { .. } => true,
+        }
+    }
+
+    /// Returns true if and only if this state is a look-around assertion.
+    pub fn is_look(&self) -> bool {
+        match *self {
+            State::Range { .. }
+            | State::Sparse { .. }
+            | State::Union { .. }
+            | State::Fail
+            | State::Match { .. } => false,
+            State::Look { .. } => true,
         }
     }
 
@@ -168,8 +606,9 @@ impl State {
                     *alt = remap[*alt];
                 }
             }
+            State::Look { ref mut next, .. } => *next = remap[*next],
             State::Fail => {}
-            State::Match => {}
+            State::Match { .. } => {}
         }
     }
 }
@@ -194,12 +633,214 @@ impl fmt::Debug for State {
                     .join(", ");
                 write!(f, "alt({})", alts)
             }
+            State::Look { look, next } => {
+                write!(f, "{} => {}", look.as_str(), next)
+            }
             State::Fail => write!(f, "FAIL"),
-            State::Match => write!(f, "MATCH"),
+            State::Match { pattern_id } => write!(f, "MATCH({})", pattern_id),
         }
     }
 }
 
+/// A look-around assertion, which matches the empty string at a position in
+/// the input if and only if the bytes surrounding that position satisfy it.
+///
+/// The anchors and the ASCII assertions only inspect the bytes immediately before and after
+/// the position, which permits a DFA to evaluate them. The Unicode
+/// assertions inspect the codepoints before and after the position, where
+/// invalid UTF-8 is never part of a word. A DFA can't evaluate those, but an
+/// NFA engine such as a
+/// [`BoundedBacktracker`](../backtrack/struct.BoundedBacktracker.html) can.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Look {
+    /// Holds at the beginning of the input, as with `\A`.
+    StartText,
+    /// Holds at the end of the input, as with `\z`.
+    EndText,
+    /// Holds at the beginning of the input and after every `\n`, as with
+    /// `(?m:^)`.
+    StartLine,
+    /// Holds at the end of the input and before every `\n`, as with
+    /// `(?m:$)`.
+    EndLine,
+    /// Like `StartLine`, but also holds after every `\r` that isn't
+    /// followed by `\n`. In particular, it holds after `\r\n`, but not
+    /// between `\r` and `\n`.
+    StartLineCRLF,
+    /// Like `EndLine`, but also holds before every `\r`, and not before a
+    /// `\n` that follows `\r`. In particular, it holds before `\r\n`, but
+    /// not between `\r` and `\n`.
+    EndLineCRLF,
+    /// Holds between an ASCII word byte and either a byte that isn't an
+    /// ASCII word byte or the edge of the input, as with `(?-u:\b)`.
+    WordBoundaryAscii,
+    /// Holds wherever `WordBoundaryAscii` doesn't, as with `(?-u:\B)`.
+    NotWordBoundaryAscii,
+    /// Holds between a Unicode word character and either a codepoint that
+    /// isn't a Unicode word character or the edge of the input, as with
+    /// `\b`.
+    WordBoundaryUnicode,
+    /// Holds wherever `WordBoundaryUnicode` doesn't, as with `\B`.
+    NotWordBoundaryUnicode,
+}
+
+impl Look {
+    /// Returns true if and only if this assertion holds at the given position
+    /// in the given bytes.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `at > bytes.len()`.
+    pub fn is_match(&self, bytes: &[u8], at: usize) -> bool {
+        match *self {
+            Look::StartText => at == 0,
+            Look::EndText => at == bytes.len(),
+            Look::StartLine => at == 0 || bytes[at - 1] == b'\n',
+            Look::EndLine => at == bytes.len() || bytes[at] == b'\n',
+            Look::StartLineCRLF => {
+                at == 0
+                    || bytes[at - 1] == b'\n'
+                    || (bytes[at - 1] == b'\r'
+                        && bytes.get(at) != Some(&b'\n'))
+            }
+            Look::EndLineCRLF => {
+                at == bytes.len()
+                    || bytes[at] == b'\r'
+                    || (bytes[at] == b'\n'
+                        && (at == 0 || bytes[at - 1] != b'\r'))
+            }
+            Look::WordBoundaryAscii => is_ascii_boundary(bytes, at),
+            Look::NotWordBoundaryAscii => !is_ascii_boundary(bytes, at),
+            Look::WordBoundaryUnicode => is_unicode_boundary(bytes, at),
+            Look::NotWordBoundaryUnicode => !is_unicode_boundary(bytes, at),
+        }
+    }
+
+    /// Returns true if and only if evaluating this assertion requires
+    /// decoding the UTF-8 surrounding a position, which a DFA can't do.
+    pub fn is_unicode(&self) -> bool {
+        match *self {
+            Look::StartText
+            | Look::EndText
+            | Look::StartLine
+            | Look::EndLine
+            | Look::StartLineCRLF
+            | Look::EndLineCRLF
+            | Look::WordBoundaryAscii
+            | Look::NotWordBoundaryAscii => false,
+            Look::WordBoundaryUnicode | Look::NotWordBoundaryUnicode => true,
+        }
+    }
+
+    /// Returns the assertion that holds at the same positions as this one
+    /// when the input is reversed. This is used when compiling a reverse
+    /// NFA.
+    pub fn reversed(&self) -> Look {
+        match *self {
+            Look::StartText => Look::EndText,
+            Look::EndText => Look::StartText,
+            Look::StartLine => Look::EndLine,
+            Look::EndLine => Look::StartLine,
+            Look::StartLineCRLF => Look::EndLineCRLF,
+            Look::EndLineCRLF => Look::StartLineCRLF,
+            Look::WordBoundaryAscii
+            | Look::NotWordBoundaryAscii
+            | Look::WordBoundaryUnicode
+            | Look::NotWordBoundaryUnicode => *self,
+        }
+    }
+
+    /// Return the syntax of this assertion, for use in debug output.
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Look::StartText => r"\A",
+            Look::EndText => r"\z",
+            Look::StartLine => r"(?m:^)",
+            Look::EndLine => r"(?m:$)",
+            Look::StartLineCRLF => r"(?mR:^)",
+            Look::EndLineCRLF => r"(?mR:$)",
+            Look::WordBoundaryAscii => r"(?-u:\b)",
+            Look::NotWordBoundaryAscii => r"(?-u:\B)",
+            Look::WordBoundaryUnicode => r"\b",
+            Look::NotWordBoundaryUnicode => r"\B",
+        }
+    }
+
+    /// Split the ranges of bytes that this assertion distinguishes into their
+    /// own equivalence classes, so that a DFA can tell them apart from the
+    /// class of the byte it reads.
+    pub(crate) fn set_byte_classes(&self, set: &mut ByteClassSet) {
+        match *self {
+            Look::StartText | Look::EndText => {}
+            Look::StartLine | Look::EndLine => {
+                set.set_range(b'\n', b'\n');
+            }
+            Look::StartLineCRLF | Look::EndLineCRLF => {
+                set.set_range(b'\n', b'\n');
+                set.set_range(b'\r', b'\r');
+            }
+            Look::WordBoundaryAscii
+            | Look::NotWordBoundaryAscii
+            | Look::WordBoundaryUnicode
+            | Look::NotWordBoundaryUnicode => {
+                set.set_range(b'0', b'9');
+                set.set_range(b'A', b'Z');
+                set.set_range(b'_', b'_');
+                set.set_range(b'a', b'z');
+            }
+        }
+    }
+}
+
+/// Returns true if and only if exactly one of the bytes on either side of
+/// the given position is an ASCII word byte.
+fn is_ascii_boundary(bytes: &[u8], at: usize) -> bool {
+    let before = at > 0 && is_word_byte(bytes[at - 1]);
+    let after = at < bytes.len() && is_word_byte(bytes[at]);
+    before != after
+}
+
+/// Returns true if and only if exactly one of the codepoints on either side
+/// of the given position is a Unicode word character.
+fn is_unicode_boundary(bytes: &[u8], at: usize) -> bool {
+    let before =
+        decode_last_utf8(&bytes[..at]).map(is_word_character).unwrap_or(false);
+    let after =
+        decode_utf8(&bytes[at..]).map(is_word_character).unwrap_or(false);
+    before != after
+}
+
+/// Decode the codepoint at the beginning of the given bytes, if they begin
+/// with valid UTF-8.
+fn decode_utf8(bytes: &[u8]) -> Option<char> {
+    let len = match *bytes.first()? {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return None,
+    };
+    if bytes.len() < len {
+        return None;
+    }
+    str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next())
+}
+
+/// Decode the codepoint at the end of the given bytes, if they end with
+/// valid UTF-8.
+fn decode_last_utf8(bytes: &[u8]) -> Option<char> {
+    let end = bytes.len();
+    let mut start = end.checked_sub(1)?;
+    while start > 0 && end - start < 4 && bytes[start] & 0xC0 == 0x80 {
+        start -= 1;
+    }
+    let slice = &bytes[start..];
+    match str::from_utf8(slice) {
+        Ok(s) if s.chars().count() == 1 => s.chars().next(),
+        _ => None,
+    }
+}
+
 impl fmt::Debug for Transition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Transition { start, end, next } = *self;
@@ -249,4 +890,123 @@ mod tests {
         assert_eq!(None, dfa.find_at(b"ab", 1));
         assert_eq!(None, dfa.find_at(b"ab", 2));
     }
+
+    #[test]
+    fn add_patterns() {
+        let mut nfa = NFA::never_match();
+        assert_eq!(0, nfa.add_pattern(r"foo[0-9]+").unwrap());
+        assert_eq!(1, nfa.add_pattern(r"bar").unwrap());
+        assert_eq!(2, nfa.add_pattern(r"[a-z]+z").unwrap());
+        assert_eq!(3, nfa.pattern_count());
+        assert!(nfa.add_pattern(r"(").is_err());
+        assert_eq!(3, nfa.pattern_count());
+
+        let dfa = dense::Builder::new().build_from_nfa::<usize>(&nfa).unwrap();
+        assert_eq!(Some(6), dfa.find(b"xfoo12"));
+        assert_eq!(Some(4), dfa.find(b"xbar"));
+        assert_eq!(Some(3), dfa.find(b"xyz"));
+        assert_eq!(None, dfa.find(b"foo"));
+
+        let mut nfa =
+            dense::Builder::new().anchored(true).build_nfa("a").unwrap();
+        nfa.add_pattern(r"b").unwrap();
+        nfa.add_pattern(r"c").unwrap();
+        let dfa = dense::Builder::new()
+            .anchored(true)
+            .build_from_nfa::<usize>(&nfa)
+            .unwrap();
+        assert_eq!(Some(1), dfa.find(b"a"));
+        assert_eq!(Some(1), dfa.find(b"b"));
+        assert_eq!(Some(1), dfa.find(b"c"));
+        assert_eq!(None, dfa.find(b"xc"));
+    }
+
+    #[test]
+    fn anchored_dfa_from_unanchored_nfa() {
+        let nfa = dense::Builder::new().build_nfa("[a-z]+").unwrap();
+        assert!(!nfa.is_anchored());
+
+        let dfa = dense::Builder::new().build_from_nfa::<usize>(&nfa).unwrap();
+        assert_eq!(Some(4), dfa.find(b"12ab"));
+        let dfa = dense::Builder::new()
+            .anchored(true)
+            .build_from_nfa::<usize>(&nfa)
+            .unwrap();
+        assert_eq!(None, dfa.find(b"12ab"));
+        assert_eq!(Some(2), dfa.find(b"ab12"));
+    }
+
+    #[test]
+    fn reverse_agrees_with_reverse_builder() {
+        let patterns = &[
+            "a",
+            "abc",
+            "[a-z]+[0-9]",
+            "(foo|foobar)baz",
+            "a*b*c*",
+            "(ab|cd)+e?",
+            "\\w+\\s+\\w+",
+            "[^a]{2,3}z",
+            "^ab|cd$",
+            "(?m)^[a-z]+$",
+            "(?-u:\\b)[a-z]+",
+        ];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"abc",
+            b"xyz9",
+            b"foobarbaz",
+            b"foobaz",
+            b"aabbcc",
+            b"abcdabe",
+            b"hello  world",
+            b"\xce\xb2\xce\xb2z",
+            b"ab\ncd",
+        ];
+        for &pattern in patterns {
+            let nfa = dense::Builder::new().build_nfa(pattern).unwrap();
+            let got = dense::Builder::new()
+                .anchored(true)
+                .longest_match(true)
+                .build_from_nfa::<usize>(&nfa.reverse())
+                .unwrap();
+            let expected = dense::Builder::new()
+                .anchored(true)
+                .longest_match(true)
+                .reverse(true)
+                .build(pattern)
+                .unwrap();
+            for &haystack in haystacks {
+                assert_eq!(
+                    expected.rfind(haystack),
+                    got.rfind(haystack),
+                    "pattern: {:?}, haystack: {:?}",
+                    pattern,
+                    haystack,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_preserves_patterns() {
+        let mut nfa = NFA::never_match();
+        nfa.add_pattern("abc").unwrap();
+        nfa.add_pattern("[a-z]+").unwrap();
+        nfa.add_pattern("[0-9]+y").unwrap();
+
+        let rev = nfa.reverse();
+        assert!(rev.is_anchored());
+        assert_eq!(3, rev.pattern_count());
+
+        let dfa = dense::Builder::new()
+            .anchored(true)
+            .longest_match(true)
+            .build_from_nfa::<usize>(&rev)
+            .unwrap();
+        assert_eq!(Some((0, 3)), dfa.find_pattern(b"cba"));
+        assert_eq!(Some((1, 3)), dfa.find_pattern(b"zzz"));
+        assert_eq!(Some((2, 3)), dfa.find_pattern(b"y12"));
+        assert_eq!(None, dfa.find_pattern(b"12y"));
+    }
 }
@@ -0,0 +1,667 @@
+use memchr::{memchr, memchr2, memchr3, memmem};
+use regex_syntax::hir::literal::Literals;
+use regex_syntax::hir::{self, Hir, HirKind};
+
+use dfa::{byte_before, next_end_state, PatternID, DFA};
+use diagnostics::{BuildWarning, Diagnostics};
+use stats;
+use teddy::Teddy;
+
+/// The maximum number of prefix literals considered when building a
+/// prefilter. Beyond this, a prefilter is unlikely to skip much of anything.
+const LIMIT_LITERALS: usize = 64;
+
+/// The size limit, in bytes, used when checking whether a pattern has prefix
+/// literals that exceed the limits of a prefilter. This bounds the work done
+/// for patterns whose prefix literals explode combinatorially.
+const LIMIT_SIZE_GENEROUS: usize = 1 << 16;
+
+/// A prefilter for quickly skipping over parts of a haystack that cannot
+/// contain a match.
+///
+/// A prefilter is built from literals extracted from a pattern. When every
+/// match is known to start with one of a small set of literals, the
+/// prefilter reports candidate starting positions using `memchr` or
+/// `memmem`, and the DFA only needs to be run from those positions. Failing
+/// that, when every match is known to contain some literal, the prefilter
+/// can rule out haystacks that don't contain it without running the DFA at
+/// all.
+///
+/// A [`Regex`](struct.Regex.html) built with a
+/// [`RegexBuilder`](struct.RegexBuilder.html) uses a prefilter by default
+/// whenever one can be built. A prefilter may also be built separately,
+/// e.g., with
+/// [`RegexBuilder::build_prefilter`](struct.RegexBuilder.html#method.build_prefilter),
+/// and attached to a regex built from deserialized DFAs with
+/// [`Regex::with_prefilter`](struct.Regex.html#method.with_prefilter).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::RegexBuilder;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let pre = RegexBuilder::new().build_prefilter("foo[0-9]+")?.unwrap();
+/// assert_eq!(Some(4), pre.find(b"xyz foo123", 0));
+/// assert_eq!(None, pre.find(b"xyz foo123", 5));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct Prefilter {
+    /// How to find positions at which a match may start, if known.
+    start: Option<Start>,
+    /// A literal that every match contains, if known and if no prefix
+    /// literals are available.
+    inner: Option<memmem::Finder<'static>>,
+    /// A literal that every match ends with, if known and if no prefix
+    /// literals are available.
+    suffix: Option<memmem::Finder<'static>>,
+}
+
+/// A strategy for finding candidate starting positions of a match.
+#[derive(Clone, Debug)]
+enum Start {
+    /// Every match starts with this byte.
+    Byte1(u8),
+    /// Every match starts with one of these bytes.
+    Byte2(u8, u8),
+    /// Every match starts with one of these bytes.
+    Byte3(u8, u8, u8),
+    /// Every match starts with this string.
+    Substring(Box<memmem::Finder<'static>>),
+    /// Every match starts with one of a small set of strings, which are
+    /// found with a packed searcher.
+    Teddy(Box<Teddy>),
+}
+
+impl Prefilter {
+    /// Build a prefilter from the given high-level intermediate
+    /// representation of a pattern.
+    ///
+    /// If no useful literals could be extracted from the pattern, then `None`
+    /// is returned.
+    pub fn from_hir(hir: &Hir) -> Option<Prefilter> {
+        Prefilter::from_hirs(::std::slice::from_ref(hir))
+    }
+
+    /// Build a prefilter from the high-level intermediate representations
+    /// of several patterns, such as those of a DFA built with
+    /// [`dense::Builder::build_many`](dense/struct.Builder.html#method.build_many).
+    ///
+    /// Candidates reported by the prefilter are candidates for the start of
+    /// a match of any of the patterns. When there is more than one pattern,
+    /// only prefix literals are used.
+    ///
+    /// If no useful literals could be extracted from the patterns, then
+    /// `None` is returned.
+    pub fn from_hirs(hirs: &[Hir]) -> Option<Prefilter> {
+        Prefilter::from_hirs_with_diagnostics(hirs, &mut Diagnostics::new())
+    }
+
+    /// Like `from_hirs`, but reports the prefix literals that were given up
+    /// on to the given diagnostics.
+    pub(crate) fn from_hirs_with_diagnostics(
+        hirs: &[Hir],
+        diagnostics: &mut Diagnostics,
+    ) -> Option<Prefilter> {
+        let start = prefix_start(hirs, diagnostics);
+        let (inner, suffix) = if start.is_some() || hirs.len() != 1 {
+            (None, None)
+        } else {
+            (
+                required_literal(&hirs[0])
+                    .map(|lit| memmem::Finder::new(&lit).into_owned()),
+                suffix_literal(&hirs[0])
+                    .map(|lit| memmem::Finder::new(&lit).into_owned()),
+            )
+        };
+        if start.is_none() && inner.is_none() && suffix.is_none() {
+            return None;
+        }
+        Some(Prefilter { start, inner, suffix })
+    }
+
+    /// Returns the position of the first candidate for the start of a match
+    /// in `haystack` at or after `at`. If no match can start at or after
+    /// `at`, then `None` is returned.
+    ///
+    /// A candidate is not necessarily the start of a match, but no match
+    /// starts at any position between `at` and the candidate. When this
+    /// prefilter only knows of a literal that every match contains, the
+    /// candidate is always `at`, unless the literal doesn't occur in the rest
+    /// of the haystack.
+    pub fn find(&self, haystack: &[u8], at: usize) -> Option<usize> {
+        let rest = &haystack[at..];
+        let i = match self.start {
+            Some(Start::Byte1(b1)) => memchr(b1, rest),
+            Some(Start::Byte2(b1, b2)) => memchr2(b1, b2, rest),
+            Some(Start::Byte3(b1, b2, b3)) => memchr3(b1, b2, b3, rest),
+            Some(Start::Substring(ref finder)) => finder.find(rest),
+            Some(Start::Teddy(ref teddy)) => return teddy.find(haystack, at),
+            None => match self.inner {
+                Some(ref finder) => finder.find(rest).map(|_| 0),
+                None => Some(0),
+            },
+        };
+        i.map(|i| at + i)
+    }
+
+    /// Returns true if and only if the candidates reported by this prefilter
+    /// narrow down where a match starts, rather than only whether a match
+    /// exists.
+    pub fn is_prefix(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Returns true if and only if this prefilter knows of a literal that
+    /// every match ends with. In that case, a regex decides whether a match
+    /// exists by searching for the literal and running its reverse DFA from
+    /// the end of each occurrence, instead of running its forward DFA over
+    /// the whole haystack.
+    pub fn is_suffix(&self) -> bool {
+        self.suffix.is_some()
+    }
+
+    /// Returns the same as
+    /// [`DFA::find_pattern_at`](trait.DFA.html#method.find_pattern_at) on
+    /// the given DFA, but uses this prefilter to skip over the parts of the
+    /// haystack in which no match can start.
+    ///
+    /// The prefilter must have been built from the same patterns as the
+    /// DFA, e.g., with
+    /// [`dense::Builder::build_prefilter_many`](dense/struct.Builder.html#method.build_prefilter_many).
+    /// Otherwise, matches may be missed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let patterns = &["foo[0-9]+", "bar[a-z]+", "quux", "Sherlock"];
+    /// let builder = dense::Builder::new();
+    /// let dfa = builder.build_many(patterns)?;
+    /// let pre = builder.build_prefilter_many(patterns)?.unwrap();
+    /// let haystack = b"the quick brown fox: Sherlock Holmes";
+    /// assert_eq!(Some((3, 29)), pre.find_pattern_at(&dfa, haystack, 0));
+    /// assert_eq!(dfa.find_pattern_at(haystack, 5), pre.find_pattern_at(&dfa, haystack, 5));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_pattern_at<D: DFA>(
+        &self,
+        dfa: &D,
+        haystack: &[u8],
+        start: usize,
+    ) -> Option<(PatternID, usize)> {
+        let end = haystack.len();
+        find_fwd_imp(self, dfa, haystack, start, end, false)
+            .map(|(state, end)| (dfa.match_pattern(state, 0), end))
+    }
+}
+
+/// Run the given unanchored DFA over `bytes[start..end]`, using the
+/// prefilter to skip ahead whenever the DFA is in its start state and hasn't
+/// seen a match.
+///
+/// When the DFA has look-around, its start state depends on the byte
+/// preceding the current position, so after skipping ahead, the search
+/// resumes in the start state for the byte preceding the candidate.
+///
+/// When `earliest` is true, this returns as soon as any match is seen, like
+/// `DFA::shortest_match_in`. Otherwise, this returns the same as
+/// `DFA::find_in`.
+pub(crate) fn find_fwd<D: DFA>(
+    pre: &Prefilter,
+    dfa: &D,
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+    earliest: bool,
+) -> Option<usize> {
+    find_fwd_imp(pre, dfa, bytes, start, end, earliest).map(|(_, end)| end)
+}
+
+/// Like `find_fwd`, but also returns the match state in which the match was
+/// seen, from which the matching pattern can be read.
+fn find_fwd_imp<D: DFA>(
+    pre: &Prefilter,
+    dfa: &D,
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+    earliest: bool,
+) -> Option<(D::ID, usize)> {
+    assert!(start <= end && end <= bytes.len(), "invalid search range");
+    if dfa.is_anchored() && start > 0 {
+        return None;
+    }
+    let start_state_at = |at| dfa.start_state_after(byte_before(bytes, at));
+    let mut state = start_state_at(start);
+    let mut last_match = if dfa.is_dead_state(state) {
+        return None;
+    } else if dfa.is_match_state(state) {
+        Some((state, start))
+    } else {
+        None
+    };
+    if earliest && last_match.is_some() {
+        return last_match;
+    }
+
+    // The start state of an anchored DFA may be revisited in the middle of
+    // a match, so skipping ahead from it would be wrong.
+    let skip = !dfa.is_anchored();
+    let delay = dfa.has_lookaround() as usize;
+    // Whether the DFA is running from a candidate reported by the prefilter
+    // that hasn't yet led to a match. This is only tracked for statistics.
+    let mut pending = false;
+    let mut at = start;
+    while at < end {
+        if skip && last_match.is_none() && state == start_state_at(at) {
+            if pending {
+                stats::prefilter_miss();
+            }
+            let candidate = match pre.find(&bytes[..end], at) {
+                Some(candidate) => candidate,
+                None => {
+                    stats::skipped(end - at);
+                    return None;
+                }
+            };
+            pending = pre.is_prefix();
+            if candidate > at {
+                stats::skipped(candidate - at);
+                at = candidate;
+                state = start_state_at(at);
+            }
+            if at >= end {
+                break;
+            }
+        }
+        state = unsafe { dfa.next_state_unchecked(state, bytes[at]) };
+        at += 1;
+        stats::transitions(1);
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                if pending {
+                    stats::prefilter_miss();
+                }
+                return last_match;
+            }
+            if pending {
+                stats::prefilter_hit();
+                pending = false;
+            }
+            last_match = Some((state, at - delay));
+            if earliest {
+                return last_match;
+            }
+        }
+    }
+    let eoi = next_end_state(dfa, state, bytes.get(end).cloned());
+    if dfa.is_match_state(eoi) {
+        last_match = Some((eoi, end));
+        if pending {
+            stats::prefilter_hit();
+        }
+    } else if pending {
+        stats::prefilter_miss();
+    }
+    last_match
+}
+
+/// Return the end of the earliest match in `bytes[start..end]`, by searching
+/// for the literal that every match ends with and running the given reverse
+/// DFA back from the end of each occurrence.
+///
+/// The reverse DFA must be anchored, such that it only finds matches ending
+/// where its search begins. Since every match ends with the literal, the
+/// first occurrence from which the reverse DFA finds a match is the end of
+/// the earliest match.
+///
+/// To avoid taking quadratic time, a reverse search never revisits the bytes
+/// before the end of the previous occurrence. When it would need to, this
+/// gives up and returns `None`, in which case the caller should fall back to
+/// a forward search. `None` is also returned when the prefilter knows of no
+/// suffix.
+pub(crate) fn find_earliest_rev<R: DFA>(
+    pre: &Prefilter,
+    rev: &R,
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) -> Option<Option<usize>> {
+    let suffix = pre.suffix.as_ref()?;
+    let mut at = start;
+    let mut floor = start;
+    while let Some(i) = suffix.find(&bytes[at..end]) {
+        stats::skipped(i);
+        let lit_end = at + i + suffix.needle().len();
+        let mut state = rev.start_state_after(bytes.get(lit_end).cloned());
+        let mut matched = rev.is_match_state(state);
+        let mut dead = rev.is_dead_state(state);
+        let mut pos = lit_end;
+        while !matched && !dead && pos > floor {
+            pos -= 1;
+            state = rev.next_state(state, bytes[pos]);
+            stats::transitions(1);
+            matched = rev.is_match_state(state);
+            dead = rev.is_dead_state(state);
+        }
+        if !matched && !dead {
+            if floor > start {
+                stats::prefilter_miss();
+                return None;
+            }
+            matched = rev.is_match_state(next_end_state(
+                rev,
+                state,
+                byte_before(bytes, start),
+            ));
+        }
+        if matched {
+            stats::prefilter_hit();
+            return Some(Some(lit_end));
+        }
+        stats::prefilter_miss();
+        at += i + 1;
+        floor = lit_end;
+    }
+    Some(None)
+}
+
+/// Build a strategy for finding candidate starting positions from the
+/// prefix literals of the given patterns, if possible.
+///
+/// When the patterns have prefix literals but too many of them, a warning is
+/// reported to the given diagnostics.
+fn prefix_start(hirs: &[Hir], diagnostics: &mut Diagnostics) -> Option<Start> {
+    let mut lits = Literals::empty();
+    lits.set_limit_size(250).set_limit_class(10);
+    for hir in hirs {
+        if !lits.union_prefixes(hir) {
+            if has_prefixes(hirs, LIMIT_SIZE_GENEROUS) {
+                diagnostics.warn(BuildWarning::PrefilterLiteralLimit);
+            }
+            return None;
+        }
+    }
+    if lits.literals().len() > LIMIT_LITERALS {
+        diagnostics.warn(BuildWarning::PrefilterLiteralLimit);
+        return None;
+    }
+    if lits.literals().is_empty() || lits.contains_empty() {
+        return None;
+    }
+    if lits.literals().len() == 1 || lits.longest_common_prefix().len() > 1 {
+        let prefix = lits.longest_common_prefix();
+        return Some(match prefix.len() {
+            1 => Start::Byte1(prefix[0]),
+            _ => Start::Substring(Box::new(
+                memmem::Finder::new(prefix).into_owned(),
+            )),
+        });
+    }
+    let mut first = vec![];
+    for lit in lits.literals() {
+        if !first.contains(&lit[0]) {
+            first.push(lit[0]);
+        }
+    }
+    match first.len() {
+        1 => Some(Start::Byte1(first[0])),
+        2 => Some(Start::Byte2(first[0], first[1])),
+        3 => Some(Start::Byte3(first[0], first[1], first[2])),
+        _ => Teddy::new(lits.literals()).map(|t| Start::Teddy(Box::new(t))),
+    }
+}
+
+/// Returns true if and only if every one of the given patterns has a set of
+/// prefix literals that fits in `limit_size` bytes and doesn't include the
+/// empty string.
+///
+/// This is used to tell patterns whose prefix literals were given up on
+/// because there are too many of them apart from patterns without any.
+fn has_prefixes(hirs: &[Hir], limit_size: usize) -> bool {
+    let mut lits = Literals::empty();
+    lits.set_limit_size(limit_size).set_limit_class(10);
+    hirs.iter().all(|hir| lits.union_prefixes(hir))
+}
+
+/// Return the longest literal that every match of the given pattern ends
+/// with, if one exists.
+fn suffix_literal(hir: &Hir) -> Option<Vec<u8>> {
+    let mut lits = Literals::empty();
+    lits.set_limit_size(250).set_limit_class(10);
+    lits.union_suffixes(hir);
+    if lits.literals().is_empty() || lits.contains_empty() {
+        return None;
+    }
+    let suffix = lits.longest_common_suffix();
+    if suffix.is_empty() {
+        None
+    } else {
+        Some(suffix.to_vec())
+    }
+}
+
+/// Return the longest literal that every match of the given pattern must
+/// contain, if one of at least two bytes exists.
+///
+/// This only looks at the literals appearing in the concatenation at the
+/// top of the pattern, which is where a required literal is usually found.
+fn required_literal(hir: &Hir) -> Option<Vec<u8>> {
+    let mut items = vec![];
+    flatten_concat(hir, &mut items);
+
+    let mut best: Vec<u8> = vec![];
+    let mut cur: Vec<u8> = vec![];
+    for item in items {
+        match *item.kind() {
+            HirKind::Literal(hir::Literal::Unicode(c)) => {
+                let mut buf = [0; 4];
+                cur.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            HirKind::Literal(hir::Literal::Byte(b)) => cur.push(b),
+            _ => {
+                if cur.len() > best.len() {
+                    best = cur.clone();
+                }
+                cur.clear();
+            }
+        }
+    }
+    if cur.len() > best.len() {
+        best = cur;
+    }
+    if best.len() < 2 {
+        None
+    } else {
+        Some(best)
+    }
+}
+
+/// Push the sequence of expressions that make up the given expression when
+/// it is a concatenation, looking through groups. Any other expression is
+/// pushed as is.
+fn flatten_concat<'h>(hir: &'h Hir, items: &mut Vec<&'h Hir>) {
+    match *hir.kind() {
+        HirKind::Concat(ref hirs) => {
+            for h in hirs {
+                flatten_concat(h, items);
+            }
+        }
+        HirKind::Group(ref group) => flatten_concat(&group.hir, items),
+        _ => items.push(hir),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::ParserBuilder;
+
+    use super::{required_literal, suffix_literal, Prefilter};
+    use dense;
+    use dfa::DFA;
+
+    fn prefilter(pattern: &str) -> Option<Prefilter> {
+        let hir = ParserBuilder::new().build().parse(pattern).unwrap();
+        Prefilter::from_hir(&hir)
+    }
+
+    fn required(pattern: &str) -> Option<Vec<u8>> {
+        let hir = ParserBuilder::new().build().parse(pattern).unwrap();
+        required_literal(&hir)
+    }
+
+    fn suffix(pattern: &str) -> Option<Vec<u8>> {
+        let hir = ParserBuilder::new().build().parse(pattern).unwrap();
+        suffix_literal(&hir)
+    }
+
+    #[test]
+    fn prefix_candidates() {
+        let pre = prefilter("foo[0-9]+").unwrap();
+        assert!(pre.is_prefix());
+        assert_eq!(Some(4), pre.find(b"abc foo1", 0));
+        assert_eq!(None, pre.find(b"abc foo1", 5));
+
+        let pre = prefilter("foo|bar").unwrap();
+        assert!(pre.is_prefix());
+        assert_eq!(Some(2), pre.find(b"zzbar", 0));
+
+        let pre = prefilter("(?i)a").unwrap();
+        assert_eq!(Some(1), pre.find(b"zAa", 0));
+
+        let pre = prefilter("foo|bar|quux|Sherlock[0-9]").unwrap();
+        assert!(pre.is_prefix());
+        assert_eq!(Some(6), pre.find(b"fo qu quux", 0));
+        assert_eq!(Some(8), pre.find(b"Sherloc Sherlock", 2));
+        assert_eq!(None, pre.find(b"Sherloc ba qux", 0));
+    }
+
+    #[test]
+    fn no_prefilter() {
+        assert!(prefilter("[a-z]+").is_none());
+        assert!(prefilter("a*").is_none());
+        assert!(prefilter("a|b|c|d").is_none());
+    }
+
+    #[test]
+    fn inner_literal() {
+        assert_eq!(
+            Some(b"@example.com".to_vec()),
+            required(r"\w+@example\.com")
+        );
+        assert_eq!(Some(b"foo".to_vec()), required(r"[a-z]+(foo)[0-9]"));
+        assert_eq!(None, required(r"[a-z]+x[0-9]"));
+
+        let pre = prefilter(r"[a-z]+foo[0-9]").unwrap();
+        assert!(!pre.is_prefix());
+        assert_eq!(Some(3), pre.find(b"abcdfoo1", 3));
+        assert_eq!(None, pre.find(b"abcdfo1", 0));
+    }
+
+    #[test]
+    fn suffix_literals() {
+        assert_eq!(Some(b".tar.gz".to_vec()), suffix(r".*\.tar\.gz"));
+        assert_eq!(Some(b".gz".to_vec()), suffix(r"[a-z]+(?:\.tar|\.t)\.gz$"));
+        assert_eq!(None, suffix(r"[a-z]+\.(?:gz|bz2)"));
+        assert_eq!(None, suffix(r"[a-z]+(?:\.gz)?"));
+
+        assert!(prefilter(r".*\.tar\.gz").unwrap().is_suffix());
+        assert!(!prefilter("foo[a-z]+bar").unwrap().is_suffix());
+        assert!(prefilter(r"[a-z]+\.(?:gz|bz2)").is_none());
+    }
+
+    #[test]
+    fn find_fwd_agrees() {
+        let patterns = &[
+            "foo[0-9]+",
+            "foo|bar",
+            "[a-z]+foo",
+            "ab|a",
+            "xyz",
+            r"(?-u:\b)foo[0-9]*(?-u:\b)",
+            r"foo(?m:$)",
+            r"[a-z]+foo(?-u:\b)",
+            "foo|bar|quux|Sherlock[0-9]*",
+            r"(?:ab|cd|ef|gh)(?-u:\b)",
+        ];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"foo",
+            b"xfoo1 foo23",
+            b"zzbarfoo",
+            b"abab xyzfoo",
+            b"fofofoo9",
+            b"foo\nxfoo foo1x",
+            b"zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz ab Sherlock4",
+            b"cdef gh quux Sherloc ba bar ab",
+        ];
+        for &pattern in patterns {
+            let hir = ParserBuilder::new().build().parse(pattern).unwrap();
+            let pre = Prefilter::from_hir(&hir).unwrap();
+            let dfa = dense::Builder::new().build(pattern).unwrap();
+            for &haystack in haystacks {
+                for end in 0..haystack.len() + 1 {
+                    for start in 0..end + 1 {
+                        assert_eq!(
+                            dfa.find_in(haystack, start, end),
+                            super::find_fwd(
+                                &pre, &dfa, haystack, start, end, false
+                            ),
+                            "pattern: {:?}, range: {}..{}",
+                            pattern,
+                            start,
+                            end,
+                        );
+                        assert_eq!(
+                            dfa.shortest_match_in(haystack, start, end),
+                            super::find_fwd(
+                                &pre, &dfa, haystack, start, end, true
+                            ),
+                            "pattern: {:?}, range: {}..{}",
+                            pattern,
+                            start,
+                            end,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_pattern_agrees() {
+        let patterns = &["foo[0-9]+", "bar|baz", "quux", r"Sher\w+", "ab$"];
+        let builder = dense::Builder::new();
+        let dfa = builder.build_many(patterns).unwrap();
+        let pre = builder.build_prefilter_many(patterns).unwrap().unwrap();
+        assert!(pre.is_prefix());
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz foo12",
+            b"ba qu Sherlock Holmes bar quux",
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxab",
+        ];
+        for &haystack in haystacks {
+            for start in 0..haystack.len() + 1 {
+                assert_eq!(
+                    dfa.find_pattern_at(haystack, start),
+                    pre.find_pattern_at(&dfa, haystack, start),
+                    "haystack: {:?}, start: {}",
+                    haystack,
+                    start,
+                );
+            }
+        }
+
+        assert!(builder
+            .build_prefilter_many(&["foo", "[a-z]"])
+            .unwrap()
+            .is_none());
+        let anchored = dense::Builder::new().anchored(true).clone();
+        assert!(anchored.build_prefilter_many(patterns).unwrap().is_none());
+    }
+}
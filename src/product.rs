@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use classes::ByteClasses;
+use dense;
+use dfa::{PatternID, DFA};
+use error::Result;
+use state_id::{dead_id, StateID};
+
+type DFARepr<S> = dense::Repr<Vec<S>, S>;
+
+/// Build the product of the two anchored DFAs given, where a pair of states
+/// is a match state when `is_match` returns true for the match status of
+/// each state in the pair.
+///
+/// Both DFAs are treated as total: the dead state of each is an ordinary
+/// non-match state that transitions to itself on every byte. The pair of
+/// dead states is only mapped to the dead state of the product when it
+/// isn't a match state. When it is, as in a complement, the product gets a
+/// match state that transitions to itself on every byte instead.
+///
+/// The given byte classes must refine the byte classes of both DFAs. The
+/// product is built from a single pattern, and is neither minimized nor
+/// premultiplied.
+pub fn product<A: DFA, B: DFA, S: StateID>(
+    a: &A,
+    b: &B,
+    classes: ByteClasses,
+    is_match: fn(bool, bool) -> bool,
+) -> Result<DFARepr<S>> {
+    let mut dfa = DFARepr::empty_with_byte_classes(classes).patterns(1);
+    let dead = (dead_id::<A::ID>(), dead_id::<B::ID>());
+    let dead_matches = is_match(false, false);
+
+    let mut ids: HashMap<(A::ID, B::ID), S> = HashMap::new();
+    let mut pairs = vec![dead];
+    ids.insert(dead, dead_id());
+    if dead_matches {
+        ids.insert(dead, dfa.add_empty_state()?);
+        pairs.push(dead);
+    }
+    let start = (a.start_state(), b.start_state());
+    let start_id = match ids.get(&start) {
+        Some(&id) => id,
+        None => {
+            let id = dfa.add_empty_state()?;
+            ids.insert(start, id);
+            pairs.push(start);
+            id
+        }
+    };
+    dfa.set_start_state(start_id);
+
+    // States are added in the order in which pairs are discovered, so the
+    // pair of the state with identifier `i` is always at `pairs[i]`.
+    let mut i = 1;
+    while i < pairs.len() {
+        let (sa, sb) = pairs[i];
+        let from = S::from_usize(i);
+        for byte in classes.representatives() {
+            let next = (a.next_state(sa, byte), b.next_state(sb, byte));
+            let to = match ids.get(&next) {
+                Some(&to) => to,
+                None => {
+                    let to = dfa.add_empty_state()?;
+                    ids.insert(next, to);
+                    pairs.push(next);
+                    to
+                }
+            };
+            dfa.add_transition(from, byte, to);
+        }
+        i += 1;
+    }
+
+    let matches: Vec<&[PatternID]> = pairs
+        .iter()
+        .enumerate()
+        .map(|(i, &(sa, sb))| {
+            let matched = is_match(a.is_match_state(sa), b.is_match_state(sb));
+            if i > 0 && matched {
+                &[0][..]
+            } else {
+                &[][..]
+            }
+        })
+        .collect();
+    dfa.shuffle_match_states(&matches)?;
+    Ok(dfa)
+}
@@ -1,11 +1,36 @@
+use core::result;
+use core::slice;
 #[cfg(feature = "std")]
-use dense::{self, DenseDFA};
-use dfa::DFA;
+use std::borrow::Cow;
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::mem;
+#[cfg(feature = "std")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+use bytes::Archive;
+#[cfg(feature = "std")]
+use bytes::{AlignedBytes, ArchiveBuilder};
+#[cfg(feature = "std")]
+use dense;
+use dense::DenseDFA;
+#[cfg(feature = "std")]
+use dfa::{self, MultiMatch, PatternID};
+use dfa::{Match, DFA};
+#[cfg(feature = "std")]
+use diagnostics::Diagnostics;
 #[cfg(feature = "std")]
 use error::Result;
+use error::{DeserializeError, SearchError};
 #[cfg(feature = "std")]
-use sparse::SparseDFA;
+use prefilter::{self, Prefilter};
 #[cfg(feature = "std")]
+use sparse::SparseDFA;
 use state_id::StateID;
 
 /// A regular expression that uses deterministic finite automata for fast
@@ -18,11 +43,13 @@ use state_id::StateID;
 /// search must first be run followed by a reverse search. A match found by
 /// the forward DFA guarantees that the reverse DFA will also find a match.
 ///
-/// The type of the DFA used by a `Regex` corresponds to the `D` type
-/// parameter, which must satisfy the [`DFA`](trait.DFA.html) trait. Typically,
-/// `D` is either a [`DenseDFA`](enum.DenseDFA.html) or a
-/// [`SparseDFA`](enum.SparseDFA.html), where dense DFAs use more memory but
-/// search faster, while sparse DFAs use less memory but search more slowly.
+/// The types of the forward and reverse DFAs used by a `Regex` correspond to
+/// the `D` and `R` type parameters, respectively, which must satisfy the
+/// [`DFA`](trait.DFA.html) trait. Typically, each is either a
+/// [`DenseDFA`](enum.DenseDFA.html) or a [`SparseDFA`](enum.SparseDFA.html),
+/// where dense DFAs use more memory but search faster, while sparse DFAs use
+/// less memory but search more slowly. When `R` is omitted, both DFAs have
+/// the same type.
 ///
 /// By default, a regex's DFA type parameter is set to
 /// `DenseDFA<Vec<usize>, usize>`. For most in-memory work loads, this is the
@@ -53,11 +80,35 @@ use state_id::StateID;
 /// assert_eq!(true, sparse_re.is_match(b"foo123"));
 /// # Ok(()) }; example().unwrap()
 /// ```
+///
+/// # Mixing DFAs
+///
+/// The forward and reverse DFAs need not have the same type. For example,
+/// since the reverse DFA only runs after the forward DFA finds a match, it
+/// can make sense to keep a dense forward DFA for speed while shrinking the
+/// reverse DFA into a sparse one:
+///
+/// ```
+/// use regex_automata::{Match, Regex};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dense_re = Regex::new("foo[0-9]+")?;
+/// let fwd = dense_re.forward().clone();
+/// let rev = dense_re.reverse().to_sparse()?;
+/// let mixed_re = Regex::from_dfas(fwd, rev);
+/// assert_eq!(Some(Match::new(3, 9)), mixed_re.find(b"zzzfoo123"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+///
+/// A regex can also use a
+/// [lazy DFA](hybrid/struct.LazyDFA.html#method.with_cache) paired with
+/// its cache as either of its DFAs.
 #[cfg(feature = "std")]
 #[derive(Clone, Debug)]
-pub struct Regex<D: DFA = DenseDFA<Vec<usize>, usize>> {
+pub struct Regex<D: DFA = DenseDFA<Vec<usize>, usize>, R: DFA = D> {
     forward: D,
-    reverse: D,
+    reverse: R,
+    prefilter: Option<Prefilter>,
 }
 
 /// A regular expression that uses deterministic finite automata for fast
@@ -70,11 +121,13 @@ pub struct Regex<D: DFA = DenseDFA<Vec<usize>, usize>> {
 /// search must first be run followed by a reverse search. A match found by
 /// the forward DFA guarantees that the reverse DFA will also find a match.
 ///
-/// The type of the DFA used by a `Regex` corresponds to the `D` type
-/// parameter, which must satisfy the [`DFA`](trait.DFA.html) trait. Typically,
-/// `D` is either a [`DenseDFA`](enum.DenseDFA.html) or a
-/// [`SparseDFA`](enum.SparseDFA.html), where dense DFAs use more memory but
-/// search faster, while sparse DFAs use less memory but search more slowly.
+/// The types of the forward and reverse DFAs used by a `Regex` correspond to
+/// the `D` and `R` type parameters, respectively, which must satisfy the
+/// [`DFA`](trait.DFA.html) trait. Typically, each is either a
+/// [`DenseDFA`](enum.DenseDFA.html) or a [`SparseDFA`](enum.SparseDFA.html),
+/// where dense DFAs use more memory but search faster, while sparse DFAs use
+/// less memory but search more slowly. When `R` is omitted, both DFAs have
+/// the same type.
 ///
 /// When using this crate without the standard library, the `Regex` type has
 /// no default type parameter.
@@ -106,9 +159,9 @@ pub struct Regex<D: DFA = DenseDFA<Vec<usize>, usize>> {
 /// ```
 #[cfg(not(feature = "std"))]
 #[derive(Clone, Debug)]
-pub struct Regex<D> {
+pub struct Regex<D, R = D> {
     forward: D,
-    reverse: D,
+    reverse: R,
 }
 
 #[cfg(feature = "std")]
@@ -127,11 +180,11 @@ impl Regex {
     /// # Example
     ///
     /// ```
-    /// use regex_automata::Regex;
+    /// use regex_automata::{Match, Regex};
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
     /// let re = Regex::new("foo[0-9]+bar")?;
-    /// assert_eq!(Some((3, 14)), re.find(b"zzzfoo12345barzzz"));
+    /// assert_eq!(Some(Match::new(3, 14)), re.find(b"zzzfoo12345barzzz"));
     /// # Ok(()) }; example().unwrap()
     /// ```
     pub fn new(pattern: &str) -> Result<Regex> {
@@ -155,11 +208,11 @@ impl Regex<SparseDFA<Vec<u8>, usize>> {
     /// # Example
     ///
     /// ```
-    /// use regex_automata::Regex;
+    /// use regex_automata::{Match, Regex};
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
     /// let re = Regex::new_sparse("foo[0-9]+bar")?;
-    /// assert_eq!(Some((3, 14)), re.find(b"zzzfoo12345barzzz"));
+    /// assert_eq!(Some(Match::new(3, 14)), re.find(b"zzzfoo12345barzzz"));
     /// # Ok(()) }; example().unwrap()
     /// ```
     pub fn new_sparse(
@@ -169,7 +222,215 @@ impl Regex<SparseDFA<Vec<u8>, usize>> {
     }
 }
 
-impl<D: DFA> Regex<D> {
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> Regex<DenseDFA<T, S>> {
+    /// Serialize this regex to raw bytes in little endian format.
+    ///
+    /// The bytes form an [`Archive`](struct.Archive.html) with two entries,
+    /// `forward` and `reverse`, holding the serialized forward and reverse
+    /// DFAs. Any prefilter attached to this regex is not serialized.
+    ///
+    /// If the state identifier representation of this regex's DFAs has a
+    /// size different than 1, 2, 4 or 8 bytes, then this returns an error.
+    pub fn to_bytes_little_endian(&self) -> Result<AlignedBytes> {
+        self.to_archive(
+            &self.forward().to_bytes_little_endian()?,
+            &self.reverse().to_bytes_little_endian()?,
+        )
+    }
+
+    /// Serialize this regex to raw bytes in big endian format.
+    ///
+    /// See
+    /// [`to_bytes_little_endian`](struct.Regex.html#method.to_bytes_little_endian)
+    /// for details.
+    pub fn to_bytes_big_endian(&self) -> Result<AlignedBytes> {
+        self.to_archive(
+            &self.forward().to_bytes_big_endian()?,
+            &self.reverse().to_bytes_big_endian()?,
+        )
+    }
+
+    /// Serialize this regex to raw bytes in native endian format.
+    ///
+    /// See
+    /// [`to_bytes_little_endian`](struct.Regex.html#method.to_bytes_little_endian)
+    /// for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, Match, Regex};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let initial = Regex::new("foo[0-9]+")?;
+    /// let fwd = initial.forward().to_u16()?;
+    /// let rev = initial.reverse().to_u16()?;
+    /// let bytes = Regex::from_dfas(fwd, rev).to_bytes_native_endian()?;
+    ///
+    /// let re: Regex<DenseDFA<&[u16], u16>> = Regex::try_from_bytes(&bytes)?;
+    /// assert_eq!(Some(Match::new(3, 11)), re.find(b"zzzfoo12345zzz"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_bytes_native_endian(&self) -> Result<AlignedBytes> {
+        self.to_archive(
+            &self.forward().to_bytes_native_endian()?,
+            &self.reverse().to_bytes_native_endian()?,
+        )
+    }
+
+    fn to_archive(
+        &self,
+        forward: &[u8],
+        reverse: &[u8],
+    ) -> Result<AlignedBytes> {
+        Ok(ArchiveBuilder::new()
+            .add("forward", forward)
+            .add("reverse", reverse)
+            .to_bytes())
+    }
+}
+
+impl<T: AsRef<[S]>, S: StateID> Regex<DenseDFA<T, S>> {
+    /// Return a borrowed version of this regex. Specifically, the DFAs of
+    /// the regex returned always use `&[S]` for their transition tables
+    /// while keeping the same state identifier representation.
+    ///
+    /// This never copies the transition tables. Any prefilter attached to
+    /// this regex is cloned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, Match, Regex};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// let borrowed: Regex<DenseDFA<&[usize], usize>> = re.as_ref();
+    /// assert_eq!(Some(Match::new(0, 8)), borrowed.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn as_ref(&self) -> Regex<DenseDFA<&[S], S>> {
+        Regex {
+            forward: self.forward.as_ref(),
+            reverse: self.reverse.as_ref(),
+            #[cfg(feature = "std")]
+            prefilter: self.prefilter.clone(),
+        }
+    }
+
+    /// Return an owned version of this regex. Specifically, the DFAs of the
+    /// regex returned always use `Vec<S>` for their transition tables while
+    /// keeping the same state identifier representation.
+    ///
+    /// This is the way to keep a regex deserialized with
+    /// [`from_bytes`](struct.Regex.html#method.from_bytes) around after the
+    /// buffer it was deserialized from has been dropped, without serializing
+    /// it again. Any prefilter attached to this regex is cloned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, Match, Regex};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let owned: Regex<DenseDFA<Vec<usize>, usize>> = {
+    ///     let bytes = Regex::new("foo[0-9]+")?.to_bytes_native_endian()?;
+    ///     let re: Regex<DenseDFA<&[usize], usize>> =
+    ///         Regex::try_from_bytes(&bytes)?;
+    ///     re.to_owned()
+    /// };
+    /// assert_eq!(Some(Match::new(0, 8)), owned.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_owned(&self) -> Regex<DenseDFA<Vec<S>, S>> {
+        Regex {
+            forward: self.forward.to_owned(),
+            reverse: self.reverse.to_owned(),
+            prefilter: self.prefilter.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> Regex<DenseDFA<Vec<S>, S>> {
+    /// Shrink the allocations of this regex's DFAs to fit their tables
+    /// exactly.
+    ///
+    /// See
+    /// [`DenseDFA::shrink_to_fit`](enum.DenseDFA.html#method.shrink_to_fit).
+    pub fn shrink_to_fit(&mut self) {
+        self.forward.shrink_to_fit();
+        self.reverse.shrink_to_fit();
+    }
+}
+
+impl<'a, S: StateID> Regex<DenseDFA<&'a [S], S>> {
+    /// Deserialize a regex written by
+    /// [`to_bytes_native_endian`](struct.Regex.html#method.to_bytes_native_endian)
+    /// (or by the method for the endianness of the current target), with a
+    /// specific state identifier representation.
+    ///
+    /// Like
+    /// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes), this
+    /// never allocates and its cost doesn't depend on the size of the DFAs,
+    /// and the bytes must be aligned to the state identifier representation.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the bytes don't start with an archive containing
+    /// `forward` and `reverse` entries, or if either entry is rejected by
+    /// `DenseDFA::from_bytes`.
+    ///
+    /// # Safety
+    ///
+    /// This routine is unsafe for the same reasons as
+    /// `DenseDFA::from_bytes`: the bytes must be trusted.
+    pub unsafe fn from_bytes(buf: &'a [u8]) -> Regex<DenseDFA<&'a [S], S>> {
+        let (forward, reverse) = match regex_archive_entries(buf) {
+            Ok(entries) => entries,
+            Err(err) => panic!("invalid regex archive: {}", err),
+        };
+        Regex::from_dfas(
+            DenseDFA::from_bytes(forward),
+            DenseDFA::from_bytes(reverse),
+        )
+    }
+
+    /// Safely deserialize a regex written by
+    /// [`to_bytes_native_endian`](struct.Regex.html#method.to_bytes_native_endian)
+    /// (or by the method for the endianness of the current target), with a
+    /// specific state identifier representation.
+    ///
+    /// Both DFAs are validated by
+    /// [`DenseDFA::try_from_bytes`](enum.DenseDFA.html#method.try_from_bytes),
+    /// so this takes time linear in their size. If the bytes are not a valid
+    /// serialized regex, then an error is returned.
+    pub fn try_from_bytes(
+        buf: &'a [u8],
+    ) -> result::Result<Regex<DenseDFA<&'a [S], S>>, DeserializeError> {
+        let (forward, reverse) = regex_archive_entries(buf)?;
+        Ok(Regex::from_dfas(
+            DenseDFA::try_from_bytes(forward)?,
+            DenseDFA::try_from_bytes(reverse)?,
+        ))
+    }
+}
+
+/// Return the serialized forward and reverse DFAs in the archive of a
+/// serialized regex.
+fn regex_archive_entries(
+    buf: &[u8],
+) -> result::Result<(&[u8], &[u8]), DeserializeError> {
+    let archive = Archive::from_bytes(buf)?;
+    match (archive.get("forward"), archive.get("reverse")) {
+        (Some(forward), Some(reverse)) => Ok((forward, reverse)),
+        _ => Err(DeserializeError::InvalidArchive),
+    }
+}
+
+impl<D: DFA, R: DFA> Regex<D, R> {
     /// Returns true if and only if the given bytes match.
     ///
     /// This routine may short circuit if it knows that scanning future input
@@ -192,6 +453,66 @@ impl<D: DFA> Regex<D> {
         self.is_match_at(input, 0)
     }
 
+    /// Returns true if and only if the given string matches this regex.
+    ///
+    /// This is the same as `is_match`, but accepts a `&str`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("☃+")?;
+    /// assert!(re.is_match_str("snow ☃☃"));
+    /// assert!(!re.is_match_str("snow"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn is_match_str(&self, input: &str) -> bool {
+        self.is_match(input.as_bytes())
+    }
+
+    /// Returns true if and only if the whole of the given bytes match.
+    ///
+    /// This is like matching the pattern surrounded by `\A` and `\z`, but
+    /// without having to change the pattern. Wrapping a pattern in anchors
+    /// is error prone, since `\A` and `\z` bind more tightly than an
+    /// alternation: `\Afoo|foobar\z` doesn't require either branch to match
+    /// the whole input.
+    ///
+    /// Every alternative is considered, regardless of the match semantics of
+    /// this regex. So `a|ab` fully matches `ab`, even though the leftmost
+    /// first match of `a|ab` in `ab` is `a`.
+    ///
+    /// This runs the reverse DFA backwards from the end of the input, and
+    /// stops as soon as it enters a dead state, which usually happens long
+    /// before reaching the start of an input that doesn't fully match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+|[a-z]+")?;
+    /// assert!(re.is_full_match(b"abc"));
+    /// assert!(re.is_full_match(b"123"));
+    /// assert!(!re.is_full_match(b"abc123"));
+    /// assert!(!re.is_full_match(b" abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn is_full_match(&self, input: &[u8]) -> bool {
+        self.reverse().rfind(input) == Some(0)
+    }
+
+    /// Returns true if and only if the whole of the given string matches
+    /// this regex.
+    ///
+    /// This is the same as `is_full_match`, but accepts a `&str`.
+    pub fn is_full_match_str(&self, input: &str) -> bool {
+        self.is_full_match(input.as_bytes())
+    }
+
     /// Returns the first position at which a match is found.
     ///
     /// This routine stops scanning input in precisely the same circumstances
@@ -236,23 +557,139 @@ impl<D: DFA> Regex<D> {
     /// # Example
     ///
     /// ```
-    /// use regex_automata::Regex;
+    /// use regex_automata::{Match, Regex};
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
     /// let re = Regex::new("foo[0-9]+")?;
-    /// assert_eq!(Some((3, 11)), re.find(b"zzzfoo12345zzz"));
+    /// assert_eq!(Some(Match::new(3, 11)), re.find(b"zzzfoo12345zzz"));
     ///
     /// // Even though a match is found after reading the first byte (`a`),
     /// // the leftmost first match semantics demand that we find the earliest
     /// // match that prefers earlier parts of the pattern over latter parts.
     /// let re = Regex::new("abc|a")?;
-    /// assert_eq!(Some((0, 3)), re.find(b"abc"));
+    /// assert_eq!(Some(Match::new(0, 3)), re.find(b"abc"));
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn find(&self, input: &[u8]) -> Option<(usize, usize)> {
+    pub fn find(&self, input: &[u8]) -> Option<Match> {
         self.find_at(input, 0)
     }
 
+    /// Returns the start and end offset of the leftmost first match in the
+    /// given string. If no match exists, then `None` is returned.
+    ///
+    /// Since this regex is in UTF-8 mode and the input is valid UTF-8, both
+    /// offsets are guaranteed to fall on `char` boundaries, and so they can
+    /// always be used to slice `input`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if this regex was not built in UTF-8 mode, as reported by
+    /// [`is_utf8`](struct.Regex.html#method.is_utf8), since its matches may
+    /// split a codepoint.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("☃+")?;
+    /// let haystack = "snow ☃☃!";
+    /// let m = re.find_str(haystack).unwrap();
+    /// assert_eq!("☃☃", &haystack[m.range()]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_str(&self, input: &str) -> Option<Match> {
+        assert!(
+            self.is_utf8(),
+            "find_str requires a regex built in UTF-8 mode"
+        );
+        self.find(input.as_bytes())
+    }
+
+    /// Returns the same as `find`, but refuses to search inputs longer than
+    /// `max_input` bytes.
+    ///
+    /// If the input is too long, then no search is performed and a
+    /// [`SearchError::InputTooLong`](enum.SearchError.html) error is
+    /// returned. This is useful for putting an upper bound on the amount of
+    /// work done for any one search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, Regex, SearchError};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// assert_eq!(
+    ///     Ok(Some(Match::new(0, 8))),
+    ///     re.find_bounded(b"foo12345", 8),
+    /// );
+    /// assert_eq!(
+    ///     Err(SearchError::InputTooLong { max: 7, got: 8 }),
+    ///     re.find_bounded(b"foo12345", 7),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_bounded(
+        &self,
+        input: &[u8],
+        max_input: usize,
+    ) -> result::Result<Option<Match>, SearchError> {
+        if input.len() > max_input {
+            return Err(SearchError::InputTooLong {
+                max: max_input,
+                got: input.len(),
+            });
+        }
+        Ok(self.find(input))
+    }
+
+    /// Returns the same as `find`, but gives up once the search has
+    /// performed more than `max_steps` state transitions.
+    ///
+    /// Every byte visited by either the forward or the reverse DFA counts as
+    /// one transition. When the search runs out of fuel, a
+    /// [`SearchError::OutOfFuel`](enum.SearchError.html) error is returned
+    /// containing the offset at which the search stopped. Unlike
+    /// [`find_bounded`](struct.Regex.html#method.find_bounded), which limits
+    /// the length of the input, this puts a deterministic upper bound on the
+    /// actual work done by a search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, Regex, SearchError};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// // 8 forward transitions and 8 reverse transitions.
+    /// assert_eq!(
+    ///     Ok(Some(Match::new(0, 8))),
+    ///     re.find_fueled(b"foo12345", 16),
+    /// );
+    /// assert_eq!(
+    ///     Err(SearchError::OutOfFuel { offset: 3 }),
+    ///     re.find_fueled(b"foo12345", 12),
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_fueled(
+        &self,
+        input: &[u8],
+        max_steps: usize,
+    ) -> result::Result<Option<Match>, SearchError> {
+        let mut fuel = max_steps;
+        let end = match find_fwd_fueled(self.forward(), input, &mut fuel)? {
+            None => return Ok(None),
+            Some(end) => end,
+        };
+        let start = find_rev_fueled(self.reverse(), input, end, &mut fuel)?
+            .expect("reverse search must match if forward search does");
+        Ok(Some(Match::new(start, end)))
+    }
+
     /// Returns the same as `is_match`, but starts the search at the given
     /// offset.
     ///
@@ -260,7 +697,7 @@ impl<D: DFA> Regex<D> {
     /// context into consideration. For example, if the DFA is anchored, then
     /// a match can only occur when `start == 0`.
     pub fn is_match_at(&self, input: &[u8], start: usize) -> bool {
-        self.forward().is_match_at(input, start)
+        self.is_match_in(input, start, input.len())
     }
 
     /// Returns the same as `shortest_match`, but starts the search at the
@@ -274,7 +711,7 @@ impl<D: DFA> Regex<D> {
         input: &[u8],
         start: usize,
     ) -> Option<usize> {
-        self.forward().shortest_match_at(input, start)
+        self.find_fwd_in(input, start, input.len(), true)
     }
 
     /// Returns the same as `find`, but starts the search at the given
@@ -283,21 +720,62 @@ impl<D: DFA> Regex<D> {
     /// The significance of the starting point is that it takes the surrounding
     /// context into consideration. For example, if the DFA is anchored, then
     /// a match can only occur when `start == 0`.
-    pub fn find_at(
+    pub fn find_at(&self, input: &[u8], start: usize) -> Option<Match> {
+        self.find_in(input, start, input.len())
+    }
+
+    /// Returns the same as `is_match`, but only searches
+    /// `input[start..end]`.
+    ///
+    /// Unlike searching the subslice `&input[start..end]` directly, the
+    /// bytes surrounding the range are taken into consideration, so that
+    /// look-around assertions such as `(?-u:\b)`, `(?m:^)` and `(?m:$)` are
+    /// evaluated relative to the whole input. As with `is_match_at`, if the
+    /// DFA is anchored, then a match can only occur when `start == 0`.
+    ///
+    /// This panics if `start > end` or if `end > input.len()`.
+    pub fn is_match_in(&self, input: &[u8], start: usize, end: usize) -> bool {
+        self.find_fwd_in(input, start, end, true).is_some()
+    }
+
+    /// Returns the same as `find`, but only searches `input[start..end]`.
+    ///
+    /// As with [`is_match_in`](struct.Regex.html#method.is_match_in), the
+    /// bytes surrounding the range are taken into consideration. The match
+    /// returned, if any, always falls within the range.
+    ///
+    /// This panics if `start > end` or if `end > input.len()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, Regex};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new(r"(?-u:\b)[0-9]+(?-u:\b)")?;
+    /// let haystack = b"a12 345";
+    /// // Searching the subslice "12" on its own would find a match, but
+    /// // there is no word boundary between "a" and "1".
+    /// assert_eq!(Some(Match::new(0, 2)), re.find(&haystack[1..3]));
+    /// assert_eq!(None, re.find_in(haystack, 1, 3));
+    /// assert_eq!(Some(Match::new(4, 7)), re.find_in(haystack, 1, 7));
+    /// // Nor is there one between "4" and "5".
+    /// assert_eq!(None, re.find_in(haystack, 4, 5));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_in(
         &self,
         input: &[u8],
         start: usize,
-    ) -> Option<(usize, usize)> {
-        let end = match self.forward().find_at(input, start) {
+        end: usize,
+    ) -> Option<Match> {
+        let end = match self.find_fwd_in(input, start, end, false) {
             None => return None,
             Some(end) => end,
         };
-        let start = self
-            .reverse()
-            .rfind(&input[start..end])
-            .map(|i| start + i)
+        let start = find_rev_in(self.reverse(), input, start, end)
             .expect("reverse search must match if forward search does");
-        Some((start, end))
+        Some(Match::new(start, end))
     }
 
     /// Returns an iterator over all non-overlapping leftmost first matches
@@ -314,143 +792,1474 @@ impl<D: DFA> Regex<D> {
     /// # Example
     ///
     /// ```
-    /// use regex_automata::Regex;
+    /// use regex_automata::{Match, Regex};
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
     /// let re = Regex::new("foo[0-9]+")?;
     /// let text = b"foo1 foo12 foo123";
-    /// let matches: Vec<(usize, usize)> = re.find_iter(text).collect();
-    /// assert_eq!(matches, vec![(0, 4), (5, 10), (11, 17)]);
+    /// let matches: Vec<Match> = re.find_iter(text).collect();
+    /// assert_eq!(matches, vec![
+    ///     Match::new(0, 4),
+    ///     Match::new(5, 10),
+    ///     Match::new(11, 17),
+    /// ]);
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn find_iter<'r, 't>(&'r self, input: &'t [u8]) -> Matches<'r, 't, D> {
+    pub fn find_iter<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+    ) -> Matches<'r, 't, D, R> {
         Matches::new(self, input)
     }
 
-    /// Build a new regex from its constituent forward and reverse DFAs.
+    /// Returns the number of non-overlapping leftmost first matches in the
+    /// given bytes.
     ///
-    /// This is useful when deserializing a regex from some arbitrary
-    /// memory region. This is also useful for building regexes from other
-    /// types of DFAs.
+    /// This always returns the same as `find_iter(input).count()`, but is
+    /// faster. Since the start of each match doesn't need to be known, only
+    /// the forward DFA is used and nothing is allocated.
     ///
     /// # Example
     ///
-    /// This example is a bit a contrived. The usual use of these methods
-    /// would involve serializing `initial_re` somewhere and then deserializing
-    /// it later to build a regex.
-    ///
     /// ```
     /// use regex_automata::Regex;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let initial_re = Regex::new("foo[0-9]+")?;
-    /// assert_eq!(true, initial_re.is_match(b"foo123"));
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// assert_eq!(3, re.count(b"foo1 foo12 foo123"));
     ///
-    /// let (fwd, rev) = (initial_re.forward(), initial_re.reverse());
-    /// let re = Regex::from_dfas(fwd, rev);
-    /// assert_eq!(true, re.is_match(b"foo123"));
+    /// let re = Regex::new("a*")?;
+    /// assert_eq!(3, re.count(b"baab"));
     /// # Ok(()) }; example().unwrap()
     /// ```
+    pub fn count(&self, input: &[u8]) -> usize {
+        let mut count = 0;
+        let mut last_end = 0;
+        let mut last_match = None;
+        while last_end <= input.len() {
+            let end =
+                match self.find_fwd_in(input, last_end, input.len(), false) {
+                    None => break,
+                    Some(end) => end,
+                };
+            if Some(end) == last_match {
+                // This can only be an empty match immediately following the
+                // previous match, so skip it and start the next search one
+                // byte later to ensure we make progress.
+                last_end = end + 1;
+                continue;
+            }
+            last_end = end;
+            last_match = Some(end);
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns an iterator over the substrings of the given bytes delimited
+    /// by the non-overlapping matches of this regex.
     ///
-    /// This example shows how you might build smaller DFAs, and then use those
-    /// smaller DFAs to build a new regex.
+    /// Matches are found exactly as
+    /// [`find_iter`](struct.Regex.html#method.find_iter) finds them, so that
+    /// `n` matches always produce `n + 1` substrings. In particular, a match
+    /// at the very start or end of the input produces an empty substring
+    /// before or after it, and splitting an empty input yields a single empty
+    /// substring.
+    ///
+    /// # Example
     ///
     /// ```
     /// use regex_automata::Regex;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let initial_re = Regex::new("foo[0-9]+")?;
-    /// assert_eq!(true, initial_re.is_match(b"foo123"));
+    /// let re = Regex::new(r"[ \t]+")?;
+    /// let fields: Vec<&[u8]> = re.split(b"a b \t  c").collect();
+    /// assert_eq!(fields, vec![&b"a"[..], &b"b"[..], &b"c"[..]]);
     ///
-    /// let fwd = initial_re.forward().to_u16()?;
-    /// let rev = initial_re.reverse().to_u16()?;
-    /// let re = Regex::from_dfas(fwd, rev);
-    /// assert_eq!(true, re.is_match(b"foo123"));
+    /// let re = Regex::new(r",")?;
+    /// let fields: Vec<&[u8]> = re.split(b",a,").collect();
+    /// assert_eq!(fields, vec![&b""[..], &b"a"[..], &b""[..]]);
     /// # Ok(()) }; example().unwrap()
     /// ```
+    pub fn split<'r, 't>(&'r self, input: &'t [u8]) -> Split<'r, 't, D, R> {
+        Split { finder: self.find_iter(input), last: 0 }
+    }
+
+    /// Returns an iterator over at most `limit` substrings of the given bytes
+    /// delimited by the non-overlapping matches of this regex.
     ///
-    /// This example shows how to build a `Regex` that uses sparse DFAs instead
-    /// of dense DFAs:
+    /// This is the same as
+    /// [`split`](struct.Regex.html#method.split), except that the last
+    /// substring yielded contains the remainder of the input, regardless of
+    /// whether it contains more matches. If `limit` is `0`, then no
+    /// substrings are yielded.
+    ///
+    /// # Example
     ///
     /// ```
     /// use regex_automata::Regex;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let initial_re = Regex::new("foo[0-9]+")?;
-    /// assert_eq!(true, initial_re.is_match(b"foo123"));
+    /// let re = Regex::new(r"=")?;
+    /// let fields: Vec<&[u8]> = re.splitn(b"key=a=b", 2).collect();
+    /// assert_eq!(fields, vec![&b"key"[..], &b"a=b"[..]]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn splitn<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+        limit: usize,
+    ) -> SplitN<'r, 't, D, R> {
+        SplitN { splits: self.split(input), n: limit }
+    }
+
+    /// Returns an iterator over all non-overlapping leftmost first matches
+    /// in the given bytes whose length is at least `min_len`.
     ///
-    /// let fwd = initial_re.forward().to_sparse()?;
-    /// let rev = initial_re.reverse().to_sparse()?;
-    /// let re = Regex::from_dfas(fwd, rev);
-    /// assert_eq!(true, re.is_match(b"foo123"));
+    /// This is not the same as filtering the matches yielded by
+    /// [`find_iter`](struct.Regex.html#method.find_iter). Namely, when a
+    /// match is too short, the search resumes immediately after the start of
+    /// the rejected match instead of after its end. This permits finding a
+    /// longer match that overlaps with the rejected one.
+    ///
+    /// When `min_len` is `0`, this yields precisely the same matches as
+    /// `find_iter`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, Regex};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+")?;
+    /// let text = b"1 22 333";
+    /// let matches: Vec<Match> = re.find_iter_min_len(text, 2)
+    ///     .collect();
+    /// assert_eq!(matches, vec![Match::new(2, 4), Match::new(5, 8)]);
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn from_dfas(forward: D, reverse: D) -> Regex<D> {
-        Regex { forward, reverse }
+    pub fn find_iter_min_len<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+        min_len: usize,
+    ) -> MinLenMatches<'r, 't, D, R> {
+        MinLenMatches::new(self, input, min_len)
     }
 
-    /// Return the underlying DFA responsible for forward matching.
-    pub fn forward(&self) -> &D {
-        &self.forward
+    /// Returns an iterator over all non-overlapping leftmost first matches
+    /// in the given bytes, where the bytes are treated as a sequence of
+    /// fixed width records.
+    ///
+    /// Each record is `record_width` bytes long, except possibly for the
+    /// last one, which may be shorter. Each record is searched independently,
+    /// as if it were its own haystack. This implies that no match ever spans
+    /// more than one record, and that anchored regexes only match at the
+    /// beginning of each record. Match offsets are reported relative to the
+    /// beginning of `input`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `record_width` is `0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, Regex};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[a-z]+")?;
+    /// let text = b"ab  cdefgh  ";
+    /// let matches: Vec<Match> = re.find_iter_records(text, 4)
+    ///     .collect();
+    /// assert_eq!(matches, vec![
+    ///     Match::new(0, 2),
+    ///     Match::new(4, 8),
+    ///     Match::new(8, 10),
+    /// ]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_iter_records<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+        record_width: usize,
+    ) -> RecordMatches<'r, 't, D, R> {
+        RecordMatches::new(self, input, record_width)
     }
 
-    /// Return the underlying DFA responsible for reverse matching.
-    pub fn reverse(&self) -> &D {
-        &self.reverse
+    /// Returns an iterator over all non-overlapping leftmost first matches
+    /// in the text formed by concatenating the given segments, without
+    /// copying the segments into a contiguous buffer. This is useful when
+    /// the text is stored in a rope or some other segmented buffer.
+    ///
+    /// Each match is reported as a pair of positions, corresponding to the
+    /// start (inclusive) and end (exclusive) of the match. Each position is
+    /// itself a pair of a segment index and an offset into that segment. The
+    /// start of a non-empty match always refers to the segment containing
+    /// the first byte of the match, while its end always refers to the
+    /// segment containing the last byte of the match. Empty segments are
+    /// never reported. Both positions of an empty match are equivalent, and
+    /// refer to the segment containing the following byte, or to the end of
+    /// the last non-empty segment when the match is at the end of the text.
+    /// If every segment is empty, then an empty match refers to the start of
+    /// the first segment. If there are no segments at all, then there is no
+    /// position to report, so no matches are yielded, not even empty ones.
+    ///
+    /// Otherwise, this yields precisely the same matches as
+    /// [`find_iter`](struct.Regex.html#method.find_iter) on the concatenated
+    /// text.
+    ///
+    /// The segments iterator must be cheap to clone, since it is cloned to
+    /// revisit the segments spanned by each match. Segments before the end
+    /// of the previous match are never revisited, so no memory proportional
+    /// to the number of segments is allocated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// let segments: &[&[u8]] = &[b"xfoo1", b"23 foo", b"4"];
+    /// let matches: Vec<_> = re.find_iter_segments(segments.iter().cloned())
+    ///     .collect();
+    /// assert_eq!(matches, vec![
+    ///     ((0, 1), (1, 2)),
+    ///     ((1, 3), (2, 1)),
+    /// ]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn find_iter_segments<'r, 't, I>(
+        &'r self,
+        segments: I,
+    ) -> SegmentMatches<'r, I::IntoIter, D, R>
+    where
+        I: IntoIterator<Item = &'t [u8]>,
+        I::IntoIter: Clone,
+    {
+        SegmentMatches::new(self, segments.into_iter())
     }
-}
+
+    /// Replaces the leftmost first match in the given bytes with the
+    /// replacement provided. If no match exists, then the bytes are returned
+    /// unchanged, without copying them.
+    ///
+    /// The replacement can be a `&str` or a `&[u8]`, which is inserted
+    /// verbatim, or a closure given the bytes that matched, which returns
+    /// the bytes to insert. See [`Replacer`](trait.Replacer.html) for
+    /// details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+")?;
+    /// assert_eq!(&b"a# b2"[..], &*re.replace(b"a1 b2", "#"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn replace<'t, P: Replacer>(
+        &self,
+        input: &'t [u8],
+        rep: P,
+    ) -> Cow<'t, [u8]> {
+        self.replacen(input, 1, rep)
+    }
+
+    /// Replaces all non-overlapping matches in the given bytes with the
+    /// replacement provided. This is the same as calling `replacen` with a
+    /// limit of `0`.
+    ///
+    /// Matches are found exactly as
+    /// [`find_iter`](struct.Regex.html#method.find_iter) finds them. In
+    /// particular, an empty match immediately following another match is
+    /// never replaced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+")?;
+    /// let doubled = re.replace_all(b"a1 b23", |m: &[u8]| {
+    ///     let n: u32 = std::str::from_utf8(m).unwrap().parse().unwrap();
+    ///     (2 * n).to_string()
+    /// });
+    /// assert_eq!(&b"a2 b46"[..], &*doubled);
+    ///
+    /// let re = Regex::new("x*")?;
+    /// assert_eq!(&b"-a-b-"[..], &*re.replace_all(b"axxb", "-"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn replace_all<'t, P: Replacer>(
+        &self,
+        input: &'t [u8],
+        rep: P,
+    ) -> Cow<'t, [u8]> {
+        self.replacen(input, 0, rep)
+    }
+
+    /// Replaces at most `limit` non-overlapping matches in the given bytes
+    /// with the replacement provided. If `limit` is `0`, then all
+    /// non-overlapping matches are replaced.
+    ///
+    /// If no match is replaced, then the bytes are returned unchanged,
+    /// without copying them.
+    #[cfg(feature = "std")]
+    pub fn replacen<'t, P: Replacer>(
+        &self,
+        input: &'t [u8],
+        limit: usize,
+        mut rep: P,
+    ) -> Cow<'t, [u8]> {
+        let mut it = self.find_iter(input).enumerate().peekable();
+        if it.peek().is_none() {
+            return Cow::Borrowed(input);
+        }
+        let mut dst = Vec::with_capacity(input.len());
+        let mut last_end = 0;
+        for (i, m) in it {
+            if limit > 0 && i >= limit {
+                break;
+            }
+            dst.extend_from_slice(&input[last_end..m.start()]);
+            rep.replace_append(input, m, &mut dst);
+            last_end = m.end();
+        }
+        dst.extend_from_slice(&input[last_end..]);
+        Cow::Owned(dst)
+    }
+
+    /// Build a new regex from its constituent forward and reverse DFAs.
+    ///
+    /// This is useful when deserializing a regex from some arbitrary
+    /// memory region. This is also useful for building regexes from other
+    /// types of DFAs.
+    ///
+    /// # Example
+    ///
+    /// This example is a bit a contrived. The usual use of these methods
+    /// would involve serializing `initial_re` somewhere and then deserializing
+    /// it later to build a regex.
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial_re = Regex::new("foo[0-9]+")?;
+    /// assert_eq!(true, initial_re.is_match(b"foo123"));
+    ///
+    /// let (fwd, rev) = (initial_re.forward(), initial_re.reverse());
+    /// let re = Regex::from_dfas(fwd, rev);
+    /// assert_eq!(true, re.is_match(b"foo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// This example shows how you might build smaller DFAs, and then use those
+    /// smaller DFAs to build a new regex.
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial_re = Regex::new("foo[0-9]+")?;
+    /// assert_eq!(true, initial_re.is_match(b"foo123"));
+    ///
+    /// let fwd = initial_re.forward().to_u16()?;
+    /// let rev = initial_re.reverse().to_u16()?;
+    /// let re = Regex::from_dfas(fwd, rev);
+    /// assert_eq!(true, re.is_match(b"foo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// This example shows how to build a `Regex` that uses sparse DFAs instead
+    /// of dense DFAs:
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial_re = Regex::new("foo[0-9]+")?;
+    /// assert_eq!(true, initial_re.is_match(b"foo123"));
+    ///
+    /// let fwd = initial_re.forward().to_sparse()?;
+    /// let rev = initial_re.reverse().to_sparse()?;
+    /// let re = Regex::from_dfas(fwd, rev);
+    /// assert_eq!(true, re.is_match(b"foo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_dfas(forward: D, reverse: R) -> Regex<D, R> {
+        Regex { forward, reverse, prefilter: None }
+    }
+
+    /// Build a new regex from its constituent forward and reverse DFAs.
+    #[cfg(not(feature = "std"))]
+    pub fn from_dfas(forward: D, reverse: R) -> Regex<D, R> {
+        Regex { forward, reverse }
+    }
+
+    /// Return the underlying DFA responsible for forward matching.
+    pub fn forward(&self) -> &D {
+        &self.forward
+    }
+
+    /// Return the underlying DFA responsible for reverse matching.
+    pub fn reverse(&self) -> &R {
+        &self.reverse
+    }
+
+    /// Returns true if and only if both of this regex's DFAs were built in
+    /// UTF-8 mode.
+    ///
+    /// In UTF-8 mode, every match found in valid UTF-8 begins and ends at a
+    /// codepoint boundary. See
+    /// [`RegexBuilder::utf8`](struct.RegexBuilder.html#method.utf8).
+    pub fn is_utf8(&self) -> bool {
+        self.forward.is_utf8() && self.reverse.is_utf8()
+    }
+
+    /// Run the forward DFA from `start` up to `end`, returning the end of the
+    /// leftmost first match, or the end of the earliest match when
+    /// `earliest` is true.
+    #[cfg(feature = "std")]
+    fn find_fwd_in(
+        &self,
+        input: &[u8],
+        start: usize,
+        end: usize,
+        earliest: bool,
+    ) -> Option<usize> {
+        assert!(start <= end && end <= input.len(), "invalid search range");
+        if let Some(ref pre) = self.prefilter {
+            if earliest && !self.forward().is_anchored() {
+                let rev = self.reverse();
+                if let Some(m) =
+                    prefilter::find_earliest_rev(pre, rev, input, start, end)
+                {
+                    return m;
+                }
+            }
+        }
+        match self.prefilter {
+            Some(ref pre) => prefilter::find_fwd(
+                pre,
+                self.forward(),
+                input,
+                start,
+                end,
+                earliest,
+            ),
+            None if earliest => {
+                self.forward().shortest_match_in(input, start, end)
+            }
+            None => self.forward().find_in(input, start, end),
+        }
+    }
+
+    /// Run the forward DFA from `start` up to `end`, returning the end of the
+    /// leftmost first match, or the end of the earliest match when
+    /// `earliest` is true.
+    #[cfg(not(feature = "std"))]
+    fn find_fwd_in(
+        &self,
+        input: &[u8],
+        start: usize,
+        end: usize,
+        earliest: bool,
+    ) -> Option<usize> {
+        if earliest {
+            self.forward().shortest_match_in(input, start, end)
+        } else {
+            self.forward().find_in(input, start, end)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: DFA, R: DFA> Regex<D, R> {
+    /// Attach the given prefilter to this regex, replacing any prefilter it
+    /// already had. Passing `None` removes the prefilter.
+    ///
+    /// A regex built with
+    /// [`Regex::from_dfas`](struct.Regex.html#method.from_dfas) has no
+    /// prefilter. This permits restoring the prefilter of a regex whose
+    /// DFAs were deserialized, by building it from the same pattern with
+    /// [`RegexBuilder::build_prefilter`](struct.RegexBuilder.html#method.build_prefilter).
+    ///
+    /// The prefilter given must have been built from the same pattern as the
+    /// DFAs of this regex, otherwise the results of a search are
+    /// unspecified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, Regex, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let builder = RegexBuilder::new();
+    /// let fwd = builder.build_forward("foo[0-9]+")?.to_sparse()?;
+    /// let rev = builder.build_reverse("foo[0-9]+")?.to_sparse()?;
+    /// let re = Regex::from_dfas(fwd, rev)
+    ///     .with_prefilter(builder.build_prefilter("foo[0-9]+")?);
+    /// assert!(re.prefilter().is_some());
+    /// assert_eq!(Some(Match::new(4, 10)), re.find(b"bar foo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn with_prefilter(
+        mut self,
+        prefilter: Option<Prefilter>,
+    ) -> Regex<D, R> {
+        self.prefilter = prefilter;
+        self
+    }
+
+    /// Return the prefilter used by this regex, if one exists.
+    pub fn prefilter(&self) -> Option<&Prefilter> {
+        self.prefilter.as_ref()
+    }
+}
+
+/// Run a forward search, as in `DFA::find`, where every transition consumes
+/// one unit of the given fuel.
+fn find_fwd_fueled<D: DFA>(
+    dfa: &D,
+    bytes: &[u8],
+    fuel: &mut usize,
+) -> result::Result<Option<usize>, SearchError> {
+    let mut state = dfa.start_state_after(None);
+    let mut last_match = if dfa.is_dead_state(state) {
+        return Ok(None);
+    } else if dfa.is_match_state(state) {
+        Some(0)
+    } else {
+        None
+    };
+    let delay = dfa.has_lookaround() as usize;
+    for (i, &b) in bytes.iter().enumerate() {
+        if *fuel == 0 {
+            return Err(SearchError::OutOfFuel { offset: i });
+        }
+        *fuel -= 1;
+        state = dfa.next_state(state, b);
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                return Ok(last_match);
+            }
+            last_match = Some(i + 1 - delay);
+        }
+    }
+    if dfa.is_match_state(dfa.next_eoi_state(state)) {
+        last_match = Some(bytes.len());
+    }
+    Ok(last_match)
+}
+
+/// Run a reverse search, as in `DFA::rfind`, from `end` to the start of the
+/// given bytes, where every transition consumes one unit of the given fuel.
+fn find_rev_fueled<D: DFA>(
+    dfa: &D,
+    bytes: &[u8],
+    end: usize,
+    fuel: &mut usize,
+) -> result::Result<Option<usize>, SearchError> {
+    let mut state = dfa.start_state_after(bytes.get(end).cloned());
+    let mut last_match = if dfa.is_dead_state(state) {
+        return Ok(None);
+    } else if dfa.is_match_state(state) {
+        Some(end)
+    } else {
+        None
+    };
+    let delay = dfa.has_lookaround() as usize;
+    for (i, &b) in bytes[..end].iter().enumerate().rev() {
+        if *fuel == 0 {
+            return Err(SearchError::OutOfFuel { offset: i });
+        }
+        *fuel -= 1;
+        state = dfa.next_state(state, b);
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                return Ok(last_match);
+            }
+            last_match = Some(i + delay);
+        }
+    }
+    if dfa.is_match_state(dfa.next_eoi_state(state)) {
+        last_match = Some(0);
+    }
+    Ok(last_match)
+}
+
+/// Run a reverse search, as in `DFA::rfind`, over `bytes[start..end]`, and
+/// return the start of the leftmost match.
+///
+/// Unlike searching the subslice, this takes the bytes surrounding the range
+/// into account when the DFA has look-around. In that case, the byte at
+/// `start - 1` is read to decide whether a match starts at `start`.
+fn find_rev_in<D: DFA>(
+    dfa: &D,
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) -> Option<usize> {
+    if !dfa.has_lookaround() {
+        return dfa.rfind(&bytes[start..end]).map(|i| start + i);
+    }
+    let mut state = dfa.start_state_after(bytes.get(end).cloned());
+    let mut last_match = None;
+    for (i, &b) in bytes[start..end].iter().enumerate().rev() {
+        state = dfa.next_state(state, b);
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                return last_match;
+            }
+            last_match = Some(start + i + 1);
+        }
+    }
+    state = match start.checked_sub(1) {
+        None => dfa.next_eoi_state(state),
+        Some(i) => dfa.next_state(state, bytes[i]),
+    };
+    if dfa.is_match_state(state) {
+        last_match = Some(start);
+    }
+    last_match
+}
 
 /// An iterator over all non-overlapping matches for a particular search.
 ///
-/// The iterator yields a `(usize, usize)` value until no more matches could be
-/// found. The first `usize` is the start of the match (inclusive) while the
-/// second `usize` is the end of the match (exclusive).
+/// The iterator yields a [`Match`](struct.Match.html) value until no more
+/// matches could be found.
+///
+/// `S` is the type used to represent state identifiers in the underlying
+/// regex. The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the regular expression value itself.
+/// * `'t` is the lifetime of the text being searched.
+#[derive(Clone, Debug)]
+pub struct Matches<'r, 't, D: DFA + 'r, R: DFA + 'r = D> {
+    re: &'r Regex<D, R>,
+    text: &'t [u8],
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl<'r, 't, D: DFA, R: DFA> Matches<'r, 't, D, R> {
+    fn new(re: &'r Regex<D, R>, text: &'t [u8]) -> Matches<'r, 't, D, R> {
+        Matches { re, text, last_end: 0, last_match: None }
+    }
+}
+
+impl<'r, 't, D: DFA, R: DFA> Iterator for Matches<'r, 't, D, R> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        if self.last_end > self.text.len() {
+            return None;
+        }
+        let m = self.re.find_at(self.text, self.last_end)?;
+        let e = m.end();
+        if m.is_empty() {
+            // This is an empty match. To ensure we make progress, start
+            // the next search at the smallest possible starting position
+            // of the next match following this one.
+            self.last_end = e + 1;
+            // Don't accept empty matches immediately following a match.
+            // Just move on to the next match.
+            if Some(e) == self.last_match {
+                return self.next();
+            }
+        } else {
+            self.last_end = e;
+        }
+        self.last_match = Some(e);
+        Some(m)
+    }
+}
+
+/// An iterator over the substrings of some text delimited by the matches of
+/// a regex.
+///
+/// This iterator is created by
+/// [`Regex::split`](struct.Regex.html#method.split).
+///
+/// The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the regular expression value itself.
+/// * `'t` is the lifetime of the text being split.
+#[derive(Clone, Debug)]
+pub struct Split<'r, 't, D: DFA + 'r, R: DFA + 'r = D> {
+    finder: Matches<'r, 't, D, R>,
+    /// The start of the next substring, or more than the length of the text
+    /// once the last substring has been yielded.
+    last: usize,
+}
+
+impl<'r, 't, D: DFA, R: DFA> Iterator for Split<'r, 't, D, R> {
+    type Item = &'t [u8];
+
+    fn next(&mut self) -> Option<&'t [u8]> {
+        let text = self.finder.text;
+        match self.finder.next() {
+            None => {
+                if self.last > text.len() {
+                    None
+                } else {
+                    let rest = &text[self.last..];
+                    self.last = text.len() + 1;
+                    Some(rest)
+                }
+            }
+            Some(m) => {
+                let piece = &text[self.last..m.start()];
+                self.last = m.end();
+                Some(piece)
+            }
+        }
+    }
+}
+
+/// An iterator over at most `n` substrings of some text delimited by the
+/// matches of a regex.
+///
+/// This iterator is created by
+/// [`Regex::splitn`](struct.Regex.html#method.splitn).
+///
+/// The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the regular expression value itself.
+/// * `'t` is the lifetime of the text being split.
+#[derive(Clone, Debug)]
+pub struct SplitN<'r, 't, D: DFA + 'r, R: DFA + 'r = D> {
+    splits: Split<'r, 't, D, R>,
+    /// The number of substrings left to yield.
+    n: usize,
+}
+
+impl<'r, 't, D: DFA, R: DFA> Iterator for SplitN<'r, 't, D, R> {
+    type Item = &'t [u8];
+
+    fn next(&mut self) -> Option<&'t [u8]> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        if self.n > 0 {
+            return self.splits.next();
+        }
+        // The last substring is the remainder of the text.
+        let text = self.splits.finder.text;
+        if self.splits.last > text.len() {
+            None
+        } else {
+            let rest = &text[self.splits.last..];
+            self.splits.last = text.len() + 1;
+            Some(rest)
+        }
+    }
+}
+
+/// An iterator over all non-overlapping matches that satisfy a minimum
+/// length.
+///
+/// The iterator yields a [`Match`](struct.Match.html) value until no more
+/// matches could be found.
+///
+/// The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the regular expression value itself.
+/// * `'t` is the lifetime of the text being searched.
+#[derive(Clone, Debug)]
+pub struct MinLenMatches<'r, 't, D: DFA + 'r, R: DFA + 'r = D> {
+    re: &'r Regex<D, R>,
+    text: &'t [u8],
+    min_len: usize,
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl<'r, 't, D: DFA, R: DFA> MinLenMatches<'r, 't, D, R> {
+    fn new(
+        re: &'r Regex<D, R>,
+        text: &'t [u8],
+        min_len: usize,
+    ) -> MinLenMatches<'r, 't, D, R> {
+        MinLenMatches { re, text, min_len, last_end: 0, last_match: None }
+    }
+}
+
+impl<'r, 't, D: DFA, R: DFA> Iterator for MinLenMatches<'r, 't, D, R> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if self.last_end > self.text.len() {
+                return None;
+            }
+            let m = self.re.find_at(self.text, self.last_end)?;
+            if m.len() < self.min_len {
+                // This match is too short, but a longer match may begin
+                // before it ends, so resume the search just after its start.
+                self.last_end = m.start() + 1;
+                continue;
+            }
+            let e = m.end();
+            if m.is_empty() {
+                self.last_end = e + 1;
+                if Some(e) == self.last_match {
+                    continue;
+                }
+            } else {
+                self.last_end = e;
+            }
+            self.last_match = Some(e);
+            return Some(m);
+        }
+    }
+}
+
+/// An iterator over all non-overlapping matches in a sequence of fixed width
+/// records.
+///
+/// The iterator yields a [`Match`](struct.Match.html) value until no more
+/// matches could be found.
+///
+/// The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the regular expression value itself.
+/// * `'t` is the lifetime of the text being searched.
+#[derive(Clone, Debug)]
+pub struct RecordMatches<'r, 't, D: DFA + 'r, R: DFA + 'r = D> {
+    re: &'r Regex<D, R>,
+    records: slice::Chunks<'t, u8>,
+    /// The offset at which the current record starts.
+    offset: usize,
+    /// The width of every record, except possibly the last one.
+    width: usize,
+    /// The matches in the current record, if any.
+    it: Option<Matches<'r, 't, D, R>>,
+}
+
+impl<'r, 't, D: DFA, R: DFA> RecordMatches<'r, 't, D, R> {
+    fn new(
+        re: &'r Regex<D, R>,
+        text: &'t [u8],
+        width: usize,
+    ) -> RecordMatches<'r, 't, D, R> {
+        assert!(width > 0, "record width must be greater than zero");
+        RecordMatches {
+            re,
+            records: text.chunks(width),
+            offset: 0,
+            width,
+            it: None,
+        }
+    }
+}
+
+impl<'r, 't, D: DFA, R: DFA> Iterator for RecordMatches<'r, 't, D, R> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if let Some(ref mut it) = self.it {
+                if let Some(m) = it.next() {
+                    return Some(Match::new(
+                        self.offset + m.start(),
+                        self.offset + m.end(),
+                    ));
+                }
+                self.offset += self.width;
+            }
+            let record = self.records.next()?;
+            self.it = Some(self.re.find_iter(record));
+        }
+    }
+}
+
+/// An iterator over all non-overlapping matches in a segmented text.
+///
+/// The iterator yields a pair of positions for each match, where each
+/// position is a `(segment_index, offset_in_segment)` pair. The first position
+/// is the start of the match (inclusive) while the second position is the end
+/// of the match (exclusive).
+///
+/// The segments are read through a clone of the iterator `I` each time a
+/// search needs them, starting from the segment in which the previous match
+/// ended. Segments before that are never read again, and are not kept.
+///
+/// The lifetime variable `'r` is the lifetime of the regular expression
+/// value itself.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct SegmentMatches<'r, I, D: DFA + 'r, R: DFA + 'r = D> {
+    re: &'r Regex<D, R>,
+    /// The remaining segments, starting with the segment that contains the
+    /// byte immediately before `last_end`, or the first segment if there is
+    /// no such byte.
+    segments: I,
+    /// The index of the first segment in `segments`.
+    index: usize,
+    /// The offset at which the first segment in `segments` starts in the
+    /// concatenated text.
+    offset: usize,
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<'r, 't, I, D, R> SegmentMatches<'r, I, D, R>
+where
+    I: Iterator<Item = &'t [u8]> + Clone,
+    D: DFA,
+    R: DFA,
+{
+    fn new(re: &'r Regex<D, R>, segments: I) -> SegmentMatches<'r, I, D, R> {
+        SegmentMatches {
+            re,
+            segments,
+            index: 0,
+            offset: 0,
+            last_end: 0,
+            last_match: None,
+        }
+    }
+
+    /// Run the forward DFA starting at the given offset in the concatenated
+    /// text, and return the end of the leftmost first match.
+    fn find_end(&self, start: usize) -> Option<usize> {
+        let dfa = self.re.forward();
+        if dfa.is_anchored() && start > 0 {
+            return None;
+        }
+        let prev = match start.checked_sub(1) {
+            None => None,
+            // The start is past the end of the text.
+            Some(i) => Some(self.byte_at(i)?),
+        };
+        let mut state = dfa.start_state_after(prev);
+        let mut last_match = if dfa.is_dead_state(state) {
+            return None;
+        } else if dfa.is_match_state(state) {
+            Some(start)
+        } else {
+            None
+        };
+        let delay = dfa.has_lookaround() as usize;
+        let mut seg_start = self.offset;
+        for seg in self.segments.clone() {
+            let seg_end = seg_start + seg.len();
+            if seg_end > start {
+                let skip = start.saturating_sub(seg_start);
+                for (j, &b) in seg[skip..].iter().enumerate() {
+                    state = dfa.next_state(state, b);
+                    if dfa.is_match_or_dead_state(state) {
+                        if dfa.is_dead_state(state) {
+                            return last_match;
+                        }
+                        last_match = Some(seg_start + skip + j + 1 - delay);
+                    }
+                }
+            }
+            seg_start = seg_end;
+        }
+        if dfa.is_match_state(dfa.next_eoi_state(state)) {
+            last_match = Some(seg_start);
+        }
+        last_match
+    }
+
+    /// Run the reverse DFA from the given end offset back to the given start
+    /// offset in the concatenated text, and return the start of the match.
+    fn find_start(&self, start: usize, end: usize) -> Option<usize> {
+        let dfa = self.re.reverse();
+        let mut state = dfa.start_state_after(self.byte_at(end));
+        let mut last_match = if dfa.is_dead_state(state) {
+            return None;
+        } else if dfa.is_match_state(state) {
+            Some(end)
+        } else {
+            None
+        };
+        let delay = dfa.has_lookaround() as usize;
+        // Count the segments that start before the end of the match, so that
+        // they can be visited in reverse. Each one is found again by walking
+        // forward from the first, which only costs time proportional to the
+        // square of the number of segments the match spans.
+        let (mut count, mut seg_end) = (0, self.offset);
+        for seg in self.segments.clone() {
+            if seg_end >= end {
+                break;
+            }
+            count += 1;
+            seg_end += seg.len();
+        }
+        for i in (0..count).rev() {
+            if seg_end <= start {
+                break;
+            }
+            let seg = self.segments.clone().nth(i).unwrap();
+            let seg_start = seg_end - seg.len();
+            let lo = start.saturating_sub(seg_start);
+            let hi = end - seg_start;
+            let hi = if hi > seg.len() { seg.len() } else { hi };
+            for (j, &b) in seg[lo..hi].iter().enumerate().rev() {
+                state = dfa.next_state(state, b);
+                if dfa.is_match_or_dead_state(state) {
+                    if dfa.is_dead_state(state) {
+                        return last_match;
+                    }
+                    last_match = Some(seg_start + lo + j + delay);
+                }
+            }
+            seg_end = seg_start;
+        }
+        if dfa.has_lookaround() {
+            state = match start.checked_sub(1).and_then(|i| self.byte_at(i)) {
+                None => dfa.next_eoi_state(state),
+                Some(b) => dfa.next_state(state, b),
+            };
+            if dfa.is_match_state(state) {
+                last_match = Some(start);
+            }
+        }
+        last_match
+    }
+
+    /// Return the byte at the given offset in the concatenated text, if the
+    /// offset is in bounds.
+    fn byte_at(&self, offset: usize) -> Option<u8> {
+        let mut seg_start = self.offset;
+        for seg in self.segments.clone() {
+            if offset < seg_start + seg.len() {
+                return Some(seg[offset - seg_start]);
+            }
+            seg_start += seg.len();
+        }
+        None
+    }
+
+    /// Convert an offset in the concatenated text to a segment position.
+    ///
+    /// When `after` is true, the position refers to the segment containing
+    /// the byte at the given offset. Otherwise, the position refers to the
+    /// segment containing the byte immediately before the given offset.
+    /// Either way, if no such segment exists, then the other one is used.
+    /// If neither exists, then the text is empty and the position refers to
+    /// the start of the first segment.
+    ///
+    /// This returns `None` only when there are no segments at all.
+    fn segment_position(
+        &self,
+        offset: usize,
+        after: bool,
+    ) -> Option<(usize, usize)> {
+        let mut before = None;
+        let mut seg_start = self.offset;
+        for (i, seg) in self.segments.clone().enumerate() {
+            let seg_end = seg_start + seg.len();
+            if seg_start <= offset && offset < seg_end {
+                return Some((self.index + i, offset - seg_start));
+            }
+            if seg_start < offset && offset <= seg_end {
+                before = Some((self.index + i, offset - seg_start));
+                if !after {
+                    return before;
+                }
+            }
+            seg_start = seg_end;
+        }
+        if before.is_some() {
+            return before;
+        }
+        self.segments.clone().next().map(|_| (self.index, 0))
+    }
+
+    /// Drop the segments that end before the byte immediately before the
+    /// given offset, since no later search reads them.
+    fn advance(&mut self, offset: usize) {
+        while let Some(seg) = self.segments.clone().next() {
+            if self.offset + seg.len() >= offset {
+                break;
+            }
+            self.segments.next();
+            self.index += 1;
+            self.offset += seg.len();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, 't, I, D, R> Iterator for SegmentMatches<'r, I, D, R>
+where
+    I: Iterator<Item = &'t [u8]> + Clone,
+    D: DFA,
+    R: DFA,
+{
+    type Item = ((usize, usize), (usize, usize));
+
+    fn next(&mut self) -> Option<((usize, usize), (usize, usize))> {
+        let e = self.find_end(self.last_end)?;
+        let s = self
+            .find_start(self.last_end, e)
+            .expect("reverse search must match if forward search does");
+        if s == e {
+            self.last_end = e + 1;
+            if Some(e) == self.last_match {
+                self.advance(self.last_end);
+                return self.next();
+            }
+            self.last_match = Some(e);
+            let pos = self.segment_position(s, true)?;
+            self.advance(self.last_end);
+            return Some((pos, pos));
+        }
+        self.last_end = e;
+        self.last_match = Some(e);
+        let pos = (
+            self.segment_position(s, true)?,
+            self.segment_position(e, false)?,
+        );
+        self.advance(self.last_end);
+        Some(pos)
+    }
+}
+
+/// A value that can be used to replace the matches of a regex.
+///
+/// This is implemented for `&str` and `&[u8]`, which replace each match with
+/// the same bytes, and for closures of type `FnMut(&[u8]) -> T` where
+/// `T: AsRef<[u8]>`, which are given the bytes of each match and return
+/// its replacement. Implementing this trait directly also gives access to
+/// the offsets of each match.
 ///
-/// `S` is the type used to represent state identifiers in the underlying
-/// regex. The lifetime variables are as follows:
+/// This is used by
+/// [`Regex::replace`](struct.Regex.html#method.replace),
+/// [`Regex::replacen`](struct.Regex.html#method.replacen) and
+/// [`Regex::replace_all`](struct.Regex.html#method.replace_all).
 ///
-/// * `'r` is the lifetime of the regular expression value itself.
+/// # Example
+///
+/// This example replaces each match with its starting offset.
+///
+/// ```
+/// use regex_automata::{Match, Regex, Replacer};
+///
+/// struct Offsets;
+///
+/// impl Replacer for Offsets {
+///     fn replace_append(&mut self, _: &[u8], m: Match, dst: &mut Vec<u8>) {
+///         dst.extend_from_slice(m.start().to_string().as_bytes());
+///     }
+/// }
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let re = Regex::new("[a-z]+")?;
+/// assert_eq!(&b"0 4"[..], &*re.replace_all(b"foo bar", Offsets));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+pub trait Replacer {
+    /// Append the replacement for the given match to `dst`.
+    ///
+    /// `input` is the entire input being searched, such that
+    /// `&input[m.range()]` are the bytes that matched.
+    fn replace_append(&mut self, input: &[u8], m: Match, dst: &mut Vec<u8>);
+}
+
+#[cfg(feature = "std")]
+impl Replacer for &[u8] {
+    fn replace_append(&mut self, _: &[u8], _: Match, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Replacer for &str {
+    fn replace_append(&mut self, _: &[u8], _: Match, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self.as_bytes());
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F, T> Replacer for F
+where
+    F: FnMut(&[u8]) -> T,
+    T: AsRef<[u8]>,
+{
+    fn replace_append(&mut self, input: &[u8], m: Match, dst: &mut Vec<u8>) {
+        dst.extend_from_slice((*self)(&input[m.range()]).as_ref());
+    }
+}
+
+/// A set of patterns, each with a priority, whose matches are arbitrated
+/// against one another.
+///
+/// A prioritized regex is built with
+/// [`RegexBuilder::build_prioritized`](struct.RegexBuilder.html#method.build_prioritized),
+/// where each pattern is given a priority. Patterns are identified by their
+/// index in the order in which they were given to the builder.
+///
+/// Every pattern is searched with a single anchored DFA, built by
+/// [`dense::Builder::build_many_prioritized`](dense/struct.Builder.html#method.build_many_prioritized)
+/// with
+/// [`all_matches`](dense/struct.Builder.html#method.all_matches)
+/// enabled, such that it reports every match of every pattern beginning at
+/// a given position.
+///
+/// The type parameter `D` refers to the type of that DFA.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct PrioritizedRegex<D: DFA = DenseDFA<Vec<usize>, usize>> {
+    dfa: D,
+    priorities: Vec<u32>,
+}
+
+#[cfg(feature = "std")]
+impl<D: DFA> PrioritizedRegex<D> {
+    /// Returns an iterator over all matches of every pattern in the given
+    /// input, where no two matches overlap.
+    ///
+    /// Each item yielded is a [`MultiMatch`](struct.MultiMatch.html), whose
+    /// pattern is the index of the pattern that matched.
+    ///
+    /// Matches are found from left to right. At each position, every match
+    /// of every pattern beginning there is considered, except for matches
+    /// inside of which a match of a pattern with a strictly higher priority
+    /// begins. Among the rest, the match reported is chosen as follows:
+    ///
+    /// 1. Matches from patterns with a higher priority come first.
+    /// 2. Among equal priorities, the longest match comes first.
+    /// 3. Otherwise, the match from the pattern with the smaller index comes
+    ///    first.
+    ///
+    /// When no match remains, the search moves on to the next position.
+    /// Otherwise, it resumes at the end of the match reported. As with
+    /// [`Regex::find_iter`](struct.Regex.html#method.find_iter), an empty
+    /// match is never reported immediately after another match.
+    ///
+    /// Overlaps are thus resolved in favor of the match with the higher
+    /// priority, then the leftmost match, then the longest match. A match of
+    /// a lower priority pattern may be cut short to make room for a higher
+    /// priority match that begins inside of it, which happens even if that
+    /// higher priority match is in turn skipped in favor of another one.
+    ///
+    /// Since the matches beginning at each position are found by running the
+    /// DFA from that position, a search takes time proportional to the
+    /// length of the input times the length of the longest match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{MultiMatch, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new()
+    ///     .build_prioritized(&[(1, r"[a-z]+"), (10, r"secret=[a-z0-9]+")])?;
+    /// let text = b"xx secret=abc123 yy";
+    /// let matches: Vec<MultiMatch> =
+    ///     re.find_iter_prioritized(text).collect();
+    /// assert_eq!(matches, vec![
+    ///     MultiMatch::new(0, 0, 2),
+    ///     MultiMatch::new(1, 3, 16),
+    ///     MultiMatch::new(0, 17, 19),
+    /// ]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_iter_prioritized<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+    ) -> PrioritizedMatches<'r, 't, D> {
+        PrioritizedMatches::new(self, input)
+    }
+
+    /// Returns the total number of patterns in this prioritized regex.
+    pub fn pattern_count(&self) -> usize {
+        self.priorities.len()
+    }
+
+    /// Returns the priority of the pattern with the given index.
+    ///
+    /// This panics if the index is not less than `pattern_count`.
+    pub fn priority(&self, pattern: usize) -> u32 {
+        self.priorities[pattern]
+    }
+
+    /// Returns the anchored DFA used to find the matches of every pattern.
+    pub fn dfa(&self) -> &D {
+        &self.dfa
+    }
+
+    /// Replace the contents of `matches` with the pattern and end offset of
+    /// every match beginning at `start`, in order of their end offsets.
+    fn matches_at(
+        &self,
+        input: &[u8],
+        start: usize,
+        matches: &mut Vec<(PatternID, usize)>,
+    ) {
+        matches.clear();
+        let dfa = &self.dfa;
+        let mut state = dfa.start_state_after(dfa::byte_before(input, start));
+        if dfa.is_dead_state(state) {
+            return;
+        }
+        if dfa.is_match_state(state) {
+            push_matches(dfa, state, start, matches);
+        }
+        let delay = dfa.has_lookaround() as usize;
+        let mut at = start;
+        while at < input.len() {
+            if dfa.is_quit_byte(input[at]) {
+                return;
+            }
+            state = dfa.next_state(state, input[at]);
+            at += 1;
+            if dfa.is_match_or_dead_state(state) {
+                if dfa.is_dead_state(state) {
+                    return;
+                }
+                push_matches(dfa, state, at - delay, matches);
+            }
+        }
+        let eoi = dfa.next_eoi_state(state);
+        if dfa.is_match_state(eoi) {
+            push_matches(dfa, eoi, input.len(), matches);
+        }
+    }
+}
+
+/// Add every pattern matching in the given match state to `matches`, each
+/// with the given end offset.
+#[cfg(feature = "std")]
+fn push_matches<D: DFA>(
+    dfa: &D,
+    state: D::ID,
+    end: usize,
+    matches: &mut Vec<(PatternID, usize)>,
+) {
+    for i in 0..dfa.match_count(state) {
+        matches.push((dfa.match_pattern(state, i), end));
+    }
+}
+
+/// An iterator over non-overlapping matches of a prioritized regex, where
+/// overlaps have been resolved according to pattern priority.
+///
+/// Each item is a [`MultiMatch`](struct.MultiMatch.html), whose pattern is
+/// the index of the pattern that matched.
+///
+/// The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the prioritized regex value itself.
 /// * `'t` is the lifetime of the text being searched.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
-pub struct Matches<'r, 't, D: DFA + 'r> {
-    re: &'r Regex<D>,
+pub struct PrioritizedMatches<'r, 't, D: DFA + 'r> {
+    re: &'r PrioritizedRegex<D>,
     text: &'t [u8],
     last_end: usize,
     last_match: Option<usize>,
+    /// For each position starting at `highest_start`, the highest priority
+    /// of any match beginning there. Positions before the end of the last
+    /// match are never looked at again, so they are dropped.
+    highest: VecDeque<Option<u32>>,
+    highest_start: usize,
+    /// Scratch space for the matches beginning at a single position, when
+    /// choosing the match to report and when finding the highest priority.
+    matches: Vec<(PatternID, usize)>,
+    probe: Vec<(PatternID, usize)>,
 }
 
-impl<'r, 't, D: DFA> Matches<'r, 't, D> {
-    fn new(re: &'r Regex<D>, text: &'t [u8]) -> Matches<'r, 't, D> {
-        Matches { re, text, last_end: 0, last_match: None }
+#[cfg(feature = "std")]
+impl<'r, 't, D: DFA> PrioritizedMatches<'r, 't, D> {
+    fn new(
+        re: &'r PrioritizedRegex<D>,
+        text: &'t [u8],
+    ) -> PrioritizedMatches<'r, 't, D> {
+        PrioritizedMatches {
+            re,
+            text,
+            last_end: 0,
+            last_match: None,
+            highest: VecDeque::new(),
+            highest_start: 0,
+            matches: vec![],
+            probe: vec![],
+        }
+    }
+
+    /// Returns the match reported among those beginning at `start`, if any.
+    fn best_at(&mut self, start: usize) -> Option<MultiMatch> {
+        let mut matches = mem::take(&mut self.matches);
+        self.re.matches_at(self.text, start, &mut matches);
+        let re = self.re;
+        matches.sort_by(|&(i1, e1), &(i2, e2)| {
+            let (p1, p2) = (re.priority(i1), re.priority(i2));
+            p2.cmp(&p1).then(e2.cmp(&e1)).then(i1.cmp(&i2))
+        });
+        let mut best = None;
+        for &(pattern, end) in &matches {
+            if end == start && Some(start) == self.last_match {
+                continue;
+            }
+            let priority = re.priority(pattern);
+            let preempted = (start + 1..end)
+                .any(|at| self.highest_at(at) > Some(priority));
+            if !preempted {
+                best = Some(MultiMatch::new(pattern, start, end));
+                break;
+            }
+        }
+        self.matches = matches;
+        best
+    }
+
+    /// Returns the highest priority of any match beginning at `at`, which
+    /// must not precede the end of the last match.
+    fn highest_at(&mut self, at: usize) -> Option<u32> {
+        while self.highest_start + self.highest.len() <= at {
+            let pos = self.highest_start + self.highest.len();
+            self.re.matches_at(self.text, pos, &mut self.probe);
+            let re = self.re;
+            let highest =
+                self.probe.iter().map(|&(i, _)| re.priority(i)).max();
+            self.highest.push_back(highest);
+        }
+        self.highest[at - self.highest_start]
     }
 }
 
-impl<'r, 't, D: DFA> Iterator for Matches<'r, 't, D> {
-    type Item = (usize, usize);
+#[cfg(feature = "std")]
+impl<'r, 't, D: DFA> Iterator for PrioritizedMatches<'r, 't, D> {
+    type Item = MultiMatch;
 
-    fn next(&mut self) -> Option<(usize, usize)> {
-        if self.last_end > self.text.len() {
-            return None;
+    fn next(&mut self) -> Option<MultiMatch> {
+        while self.highest_start < self.last_end {
+            self.highest.pop_front();
+            self.highest_start += 1;
         }
-        let (s, e) = match self.re.find_at(self.text, self.last_end) {
-            None => return None,
-            Some((s, e)) => (s, e),
-        };
-        if s == e {
-            // This is an empty match. To ensure we make progress, start
-            // the next search at the smallest possible starting position
-            // of the next match following this one.
-            self.last_end = e + 1;
-            // Don't accept empty matches immediately following a match.
-            // Just move on to the next match.
-            if Some(e) == self.last_match {
-                return self.next();
-            }
-        } else {
-            self.last_end = e;
+        while self.last_end <= self.text.len() {
+            let start = self.last_end;
+            let m = match self.best_at(start) {
+                None => {
+                    self.last_end += 1;
+                    continue;
+                }
+                Some(m) => m,
+            };
+            let e = m.end();
+            self.last_end = if m.is_empty() { e + 1 } else { e };
+            self.last_match = Some(e);
+            return Some(m);
         }
-        self.last_match = Some(e);
-        Some((s, e))
+        None
     }
 }
 
@@ -471,13 +2280,19 @@ impl<'r, 't, D: DFA> Iterator for Matches<'r, 't, D> {
 #[derive(Clone, Debug)]
 pub struct RegexBuilder {
     dfa: dense::Builder,
+    anchored: bool,
+    prefilter: bool,
 }
 
 #[cfg(feature = "std")]
 impl RegexBuilder {
     /// Create a new regex builder with the default configuration.
     pub fn new() -> RegexBuilder {
-        RegexBuilder { dfa: dense::Builder::new() }
+        RegexBuilder {
+            dfa: dense::Builder::new(),
+            anchored: false,
+            prefilter: true,
+        }
     }
 
     /// Build a regex from the given pattern.
@@ -488,6 +2303,38 @@ impl RegexBuilder {
         self.build_with_size::<usize>(pattern)
     }
 
+    /// Build a prioritized regex from the given patterns, where each pattern
+    /// is preceded by its priority. A larger number indicates a higher
+    /// priority.
+    ///
+    /// The patterns are compiled into a single anchored DFA by
+    /// [`dense::Builder::build_many_prioritized`](dense/struct.Builder.html#method.build_many_prioritized),
+    /// which takes its patterns in the same form, using the configuration
+    /// of this builder with
+    /// [`all_matches`](dense/struct.Builder.html#method.all_matches)
+    /// enabled.
+    ///
+    /// Each pattern is identified by its index in the slice given. See
+    /// [`PrioritizedRegex::find_iter_prioritized`](struct.PrioritizedRegex.html#method.find_iter_prioritized)
+    /// for how priorities are used.
+    ///
+    /// If there was a problem parsing or compiling any of the patterns, then
+    /// an error is returned.
+    pub fn build_prioritized(
+        &self,
+        patterns: &[(u32, &str)],
+    ) -> Result<PrioritizedRegex> {
+        let dfa = self
+            .dfa
+            .clone()
+            .anchored(true)
+            .all_matches(true)
+            .build_many_prioritized(patterns)?;
+        let priorities =
+            patterns.iter().map(|&(priority, _)| priority).collect();
+        Ok(PrioritizedRegex { dfa, priorities })
+    }
+
     /// Build a regex from the given pattern using sparse DFAs.
     ///
     /// If there was a problem parsing or compiling the pattern, then an error
@@ -525,15 +2372,173 @@ impl RegexBuilder {
         &self,
         pattern: &str,
     ) -> Result<Regex<DenseDFA<Vec<S>, S>>> {
-        let forward = self.dfa.build_with_size(pattern)?;
-        let reverse = self
-            .dfa
+        let forward = self.build_forward_with_size(pattern)?;
+        let reverse = self.build_reverse_with_size(pattern)?;
+        let prefilter = self.build_prefilter(pattern)?;
+        Ok(Regex::from_dfas(forward, reverse).with_prefilter(prefilter))
+    }
+
+    /// Build a regex from the given pattern, along with the non-fatal
+    /// warnings reported while building it.
+    ///
+    /// This builds the same regex as
+    /// [`build`](struct.RegexBuilder.html#method.build). The
+    /// [`Diagnostics`](struct.Diagnostics.html) returned beside it report
+    /// each way in which the regex was quietly downgraded, such as a Unicode
+    /// word boundary compiled as an ASCII word boundary, or a prefilter that
+    /// gave up on the pattern's prefix literals because there were too many
+    /// of them.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{BuildWarning, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let words: Vec<String> =
+    ///     (0..100).map(|i| format!("word{}", i)).collect();
+    /// let (re, diagnostics) =
+    ///     RegexBuilder::new().build_with_diagnostics(&words.join("|"))?;
+    /// assert_eq!(
+    ///     &[BuildWarning::PrefilterLiteralLimit],
+    ///     diagnostics.warnings(),
+    /// );
+    /// assert!(re.is_match(b"word42"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_with_diagnostics(
+        &self,
+        pattern: &str,
+    ) -> Result<(Regex, Diagnostics)> {
+        let (forward, mut diagnostics) =
+            self.dfa.build_with_diagnostics(pattern)?;
+        let reverse = self.build_reverse(pattern)?;
+        let prefilter = if !self.prefilter || self.anchored {
+            None
+        } else {
+            let hir = self.dfa.parse(pattern)?;
+            Prefilter::from_hirs_with_diagnostics(
+                slice::from_ref(&hir),
+                &mut diagnostics,
+            )
+        };
+        Ok((
+            Regex::from_dfas(forward, reverse).with_prefilter(prefilter),
+            diagnostics,
+        ))
+    }
+
+    /// Build only the prefilter of a regex from the given pattern.
+    ///
+    /// This builds the same prefilter as
+    /// [`Regex::prefilter`](struct.Regex.html#method.prefilter) on a regex
+    /// built by this builder. If prefilters are disabled, if the regex is
+    /// anchored or if no useful literals could be extracted from the pattern,
+    /// then `None` is returned.
+    ///
+    /// If there was a problem parsing the pattern, then an error is
+    /// returned.
+    pub fn build_prefilter(&self, pattern: &str) -> Result<Option<Prefilter>> {
+        if !self.prefilter || self.anchored {
+            return Ok(None);
+        }
+        Ok(Prefilter::from_hir(&self.dfa.parse(pattern)?))
+    }
+
+    /// Build only the forward DFA of a regex from the given pattern.
+    ///
+    /// This builds the same DFA as [`Regex::forward`](struct.Regex.html#method.forward)
+    /// on a regex built by this builder. Together with
+    /// [`build_reverse`](struct.RegexBuilder.html#method.build_reverse), this
+    /// permits building, converting and serializing each DFA of a regex
+    /// independently, and then reconstituting the regex via
+    /// [`Regex::from_dfas`](struct.Regex.html#method.from_dfas).
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    pub fn build_forward(
+        &self,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_forward_with_size::<usize>(pattern)
+    }
+
+    /// Build only the reverse DFA of a regex from the given pattern.
+    ///
+    /// This builds the same DFA as [`Regex::reverse`](struct.Regex.html#method.reverse)
+    /// on a regex built by this builder. Namely, the DFA is anchored, matches
+    /// the reverse of the pattern and reports the longest match, which is
+    /// what permits it to find the start of a match found by the forward DFA.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to ship a regex's DFAs separately, and then
+    /// combine them to report the start and end of each match.
+    ///
+    /// ```
+    /// use regex_automata::{DenseDFA, Match, Regex, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let builder = RegexBuilder::new();
+    /// let fwd_bytes = builder
+    ///     .build_forward("foo[0-9]+")?
+    ///     .to_u16()?
+    ///     .to_bytes_native_endian()?;
+    /// let rev_bytes = builder
+    ///     .build_reverse("foo[0-9]+")?
+    ///     .to_u16()?
+    ///     .to_bytes_native_endian()?;
+    ///
+    /// let fwd: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes(&fwd_bytes)
+    /// };
+    /// let rev: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes(&rev_bytes)
+    /// };
+    /// let re = Regex::from_dfas(fwd, rev);
+    /// assert_eq!(Some(Match::new(3, 9)), re.find(b"xyzfoo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_reverse(
+        &self,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_reverse_with_size::<usize>(pattern)
+    }
+
+    /// Build only the forward DFA of a regex from the given pattern using a
+    /// specific representation for its state IDs.
+    ///
+    /// See [`build_with_size`](struct.RegexBuilder.html#method.build_with_size)
+    /// for more details on the choice of representation.
+    pub fn build_forward_with_size<S: StateID>(
+        &self,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.dfa.build_with_size(pattern)
+    }
+
+    /// Build only the reverse DFA of a regex from the given pattern using a
+    /// specific representation for its state IDs.
+    ///
+    /// See [`build_with_size`](struct.RegexBuilder.html#method.build_with_size)
+    /// for more details on the choice of representation.
+    pub fn build_reverse_with_size<S: StateID>(
+        &self,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.dfa
             .clone()
             .anchored(true)
             .reverse(true)
             .longest_match(true)
-            .build_with_size(pattern)?;
-        Ok(Regex::from_dfas(forward, reverse))
+            .build_with_size(pattern)
     }
 
     /// Build a regex from the given pattern using a specific representation
@@ -545,7 +2550,7 @@ impl RegexBuilder {
         let re = self.build_with_size(pattern)?;
         let fwd = re.forward().to_sparse()?;
         let rev = re.reverse().to_sparse()?;
-        Ok(Regex::from_dfas(fwd, rev))
+        Ok(Regex::from_dfas(fwd, rev).with_prefilter(re.prefilter))
     }
 
     /// Set whether matching must be anchored at the beginning of the input.
@@ -557,6 +2562,55 @@ impl RegexBuilder {
     /// By default this is disabled.
     pub fn anchored(&mut self, yes: bool) -> &mut RegexBuilder {
         self.dfa.anchored(yes);
+        self.anchored = yes;
+        self
+    }
+
+    /// Enable or disable the use of a prefilter.
+    ///
+    /// When enabled, literals are extracted from the pattern and used to
+    /// quickly skip over parts of the haystack that cannot contain a match.
+    /// Namely, when every match starts with one of a few literals, the
+    /// forward DFA is only run from positions found with `memchr` or
+    /// `memmem`. When every match contains some literal, searching a haystack
+    /// that doesn't contain it returns immediately. When every match ends
+    /// with some literal, such as in `.*\.tar\.gz`, whether a match exists
+    /// is decided by running the reverse DFA back from each occurrence of
+    /// the literal. This can make searches for rare matches much faster.
+    ///
+    /// A prefilter is never used for an anchored regex.
+    ///
+    /// By default this is enabled.
+    pub fn prefilter(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.prefilter = yes;
+        self
+    }
+
+    /// Set the match semantics of the regex.
+    ///
+    /// This determines which match is reported among the matches that start
+    /// at the leftmost position. See
+    /// [`dense::Builder::match_kind`](dense/struct.Builder.html#method.match_kind)
+    /// for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense::MatchKind, Match, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build("int|[a-z]+")?;
+    /// let matches: Vec<Match> =
+    ///     re.find_iter(b"int integer").collect();
+    /// assert_eq!(matches, vec![Match::new(0, 3), Match::new(4, 11)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// By default, this is `MatchKind::LeftmostFirst`.
+    pub fn match_kind(&mut self, kind: dense::MatchKind) -> &mut RegexBuilder {
+        self.dfa.match_kind(kind);
         self
     }
 
@@ -591,6 +2645,58 @@ impl RegexBuilder {
         self
     }
 
+    /// Enable or disable the "multi line" flag by default.
+    ///
+    /// When enabled, `^` and `$` match at the beginning and end of every
+    /// line, respectively, instead of only at the beginning and end of the
+    /// input.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `m` flag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().multi_line(true).build("^[a-z]+$")?;
+    /// let matches: Vec<_> = re.find_iter(b"abc\n123\nxyz").collect();
+    /// assert_eq!(matches, vec![Match::new(0, 3), Match::new(8, 11)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn multi_line(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.dfa.multi_line(yes);
+        self
+    }
+
+    /// Enable or disable CRLF mode for the line anchors.
+    ///
+    /// When enabled, `^` and `$` in multi-line mode treat `\r\n` as a line
+    /// terminator, in addition to `\n` and `\r` on their own, and never
+    /// match between `\r` and `\n`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{Match, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new()
+    ///     .multi_line(true)
+    ///     .crlf(true)
+    ///     .build("^[a-z]+$")?;
+    /// let matches: Vec<_> = re.find_iter(b"abc\r\n123\r\nxyz").collect();
+    /// assert_eq!(matches, vec![Match::new(0, 3), Match::new(10, 13)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// This is disabled by default.
+    pub fn crlf(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.dfa.crlf(yes);
+        self
+    }
+
     /// Enable or disable the "swap greed" flag by default.
     ///
     /// By default this is disabled. It may alternatively be selectively
@@ -624,6 +2730,20 @@ impl RegexBuilder {
         self
     }
 
+    /// Set whether patterns are compiled in UTF-8 mode or in byte mode.
+    ///
+    /// In UTF-8 mode, which is the default, every match found in valid UTF-8
+    /// begins and ends at a codepoint boundary. In byte mode, a pattern may
+    /// match any sequence of bytes. Only a regex built in UTF-8 mode can be
+    /// used with [`Regex::find_str`](struct.Regex.html#method.find_str).
+    ///
+    /// Disabling UTF-8 mode is equivalent to enabling
+    /// [`allow_invalid_utf8`](struct.RegexBuilder.html#method.allow_invalid_utf8).
+    pub fn utf8(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.dfa.utf8(yes);
+        self
+    }
+
     /// Set the nesting limit used for the regular expression parser.
     ///
     /// The nesting limit controls how deep the abstract syntax tree is allowed
@@ -694,6 +2814,86 @@ impl RegexBuilder {
         self
     }
 
+    /// Merge states with identical transitions in the underlying DFAs after
+    /// determinization.
+    ///
+    /// This is a cheaper alternative to minimization that usually captures
+    /// most of its reduction in size for patterns made up of literals. See
+    /// [`dense::Builder::reduce`](dense/struct.Builder.html#method.reduce)
+    /// for details.
+    ///
+    /// This option is disabled by default.
+    pub fn reduce(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.dfa.reduce(yes);
+        self
+    }
+
+    /// Set a limit, in bytes, on the approximate memory used while
+    /// determinizing each of the underlying DFAs. If either DFA exceeds the
+    /// limit, then building the regex fails with an error.
+    ///
+    /// See
+    /// [`dense::Builder::dfa_size_limit`](dense/struct.Builder.html#method.dfa_size_limit)
+    /// for details.
+    ///
+    /// By default, there is no limit.
+    pub fn dfa_size_limit(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut RegexBuilder {
+        self.dfa.dfa_size_limit(limit);
+        self
+    }
+
+    /// Set a limit, in bytes, on the approximate memory used by the NFA
+    /// compiled from the pattern. If the limit is exceeded, then building
+    /// the regex fails with an error before determinization begins.
+    ///
+    /// See
+    /// [`dense::Builder::nfa_size_limit`](dense/struct.Builder.html#method.nfa_size_limit)
+    /// for details.
+    ///
+    /// By default, there is no limit.
+    pub fn nfa_size_limit(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut RegexBuilder {
+        self.dfa.nfa_size_limit(limit);
+        self
+    }
+
+    /// Set a limit on the time spent building each of the underlying DFAs.
+    /// Once it has elapsed, building the regex fails with an error.
+    ///
+    /// See
+    /// [`dense::Builder::time_limit`](dense/struct.Builder.html#method.time_limit)
+    /// for details.
+    ///
+    /// By default, there is no limit.
+    pub fn time_limit(
+        &mut self,
+        limit: Option<Duration>,
+    ) -> &mut RegexBuilder {
+        self.dfa.time_limit(limit);
+        self
+    }
+
+    /// Set a flag that, once set to `true`, causes building the regex to be
+    /// abandoned with an error.
+    ///
+    /// See
+    /// [`dense::Builder::cancel_flag`](dense/struct.Builder.html#method.cancel_flag)
+    /// for details.
+    ///
+    /// By default, there is no flag.
+    pub fn cancel_flag(
+        &mut self,
+        flag: Option<Arc<AtomicBool>>,
+    ) -> &mut RegexBuilder {
+        self.dfa.cancel_flag(flag);
+        self
+    }
+
     /// Premultiply state identifiers in the underlying DFA transition tables.
     ///
     /// When enabled, state identifiers are premultiplied to point to their
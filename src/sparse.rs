@@ -5,17 +5,23 @@ use core::iter;
 use core::marker::PhantomData;
 use core::mem::size_of;
 #[cfg(feature = "std")]
+use core::result;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
 
 #[cfg(feature = "std")]
 use byteorder::{BigEndian, LittleEndian};
 use byteorder::{ByteOrder, NativeEndian};
+use bytes::FORMAT_VERSION;
+#[cfg(feature = "std")]
+use bytes::{self, AlignedBytes};
 
-use classes::ByteClasses;
+use accel::{Accels, ACCELS_LEN};
+use classes::{ByteClasses, ByteSet};
 use dense;
-use dfa::DFA;
+use dfa::{MemoryUsage, DFA};
 #[cfg(feature = "std")]
-use error::{Error, Result};
+use error::{DeserializeError, Error, Result};
 #[cfg(feature = "std")]
 use state_id::{dead_id, usize_to_state_id, write_state_id_bytes, StateID};
 #[cfg(not(feature = "std"))]
@@ -189,6 +195,39 @@ impl<S: StateID> SparseDFA<Vec<u8>, S> {
     ) -> Result<SparseDFA<Vec<u8>, A>> {
         Repr::from_dense_sized(dfa).map(|r| r.into_sparse_dfa())
     }
+
+    /// Deserialize a sparse DFA serialized in the compact format, as written
+    /// by
+    /// [`to_bytes_compact`](enum.SparseDFA.html#method.to_bytes_compact),
+    /// with a specific state identifier representation.
+    ///
+    /// Unlike `from_bytes`, this decodes the transition table into a new
+    /// allocation, and so it takes time proportional to the size of the DFA.
+    /// In exchange, every transition and every state identifier in the
+    /// header is checked to refer to a state of the DFA, so that the bytes
+    /// given need not be trusted. The bytes given also need not be aligned.
+    ///
+    /// If the bytes are not a valid sparse DFA serialized in the compact
+    /// format using the state identifier representation `S`, then an error
+    /// is returned.
+    pub fn from_bytes_compact(
+        buf: &[u8],
+    ) -> result::Result<SparseDFA<Vec<u8>, S>, DeserializeError> {
+        let native = bytes::sparse_compact_to_native_endian(buf)?;
+        let info = bytes::peek(&native)?;
+        if info.state_id_size() != size_of::<S>() {
+            return Err(DeserializeError::StateIDSizeMismatch {
+                expected: size_of::<S>(),
+                found: info.state_id_size(),
+            });
+        }
+        let accels_at = info.header_len() - 8 - ACCELS_LEN;
+        Accels::<S>::from_bytes(&native[accels_at..], |_| true)?;
+        // SAFETY: The header was checked above and every state identifier
+        // in the expanded transition table was checked to refer to a state.
+        let repr = unsafe { Repr::<&[u8], S>::from_bytes(&native) };
+        Ok(repr.to_owned().into_sparse_dfa())
+    }
 }
 
 impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
@@ -212,7 +251,10 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
     /// the same state identifier representation.
     ///
     /// Effectively, this returns a sparse DFA whose transition table lives
-    /// on the heap.
+    /// on the heap. Since the DFA returned doesn't borrow anything, this is
+    /// the way to keep a DFA deserialized with
+    /// [`from_bytes`](enum.SparseDFA.html#method.from_bytes) around after
+    /// the buffer it was deserialized from has been dropped.
     #[cfg(feature = "std")]
     pub fn to_owned(&self) -> SparseDFA<Vec<u8>, S> {
         match *self {
@@ -226,6 +268,15 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
         }
     }
 
+    /// Returns the equivalence classes of bytes used by this DFA.
+    ///
+    /// The input ranges of the transitions of this DFA are ranges of these
+    /// classes, rather than of bytes. When byte classes are disabled, every
+    /// byte is in its own class.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        &self.repr().byte_classes
+    }
+
     /// Returns the memory usage, in bytes, of this DFA.
     ///
     /// The memory usage is computed based on the number of bytes used to
@@ -238,6 +289,15 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
         self.repr().memory_usage()
     }
 
+    /// Returns the memory usage of this DFA, broken down per component.
+    ///
+    /// The total size of the components stored in this DFA's buffer is equal
+    /// to [`memory_usage`](enum.SparseDFA.html#method.memory_usage), while
+    /// the other components, such as the byte class map, are stored inline.
+    pub fn memory_usage_breakdown(&self) -> MemoryUsage {
+        self.repr().memory_usage_breakdown()
+    }
+
     fn repr(&self) -> &Repr<T, S> {
         match *self {
             SparseDFA::Standard(ref r) => &r.0,
@@ -313,8 +373,8 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
     /// than 1, 2, 4 or 8 bytes, then this returns an error. All
     /// implementations of `StateID` provided by this crate satisfy this
     /// requirement.
-    pub fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<LittleEndian>()
+    pub fn to_bytes_little_endian(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<LittleEndian>(false)
     }
 
     /// Serialize a sparse DFA to raw bytes in big endian format.
@@ -323,8 +383,8 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
     /// than 1, 2, 4 or 8 bytes, then this returns an error. All
     /// implementations of `StateID` provided by this crate satisfy this
     /// requirement.
-    pub fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<BigEndian>()
+    pub fn to_bytes_big_endian(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<BigEndian>(false)
     }
 
     /// Serialize a sparse DFA to raw bytes in native endian format.
@@ -337,8 +397,54 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
     /// than 1, 2, 4 or 8 bytes, then this returns an error. All
     /// implementations of `StateID` provided by this crate satisfy this
     /// requirement.
-    pub fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<NativeEndian>()
+    pub fn to_bytes_native_endian(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<NativeEndian>(false)
+    }
+
+    /// Serialize a sparse DFA to raw bytes in the compact format.
+    ///
+    /// The compact format encodes the number of transitions of each state
+    /// and the next state of each transition as variable length integers,
+    /// where each next state is recorded as the distance between its index
+    /// and the index of the current state. Since most transitions lead to
+    /// nearby states, most next states fit in a single byte. The input
+    /// ranges of each transition are written as is, so the savings depend
+    /// on the state identifier representation: the transition table is
+    /// typically about three times smaller with `u64` or `usize` on a 64-bit
+    /// target, but only slightly smaller with `u16`. The header is always
+    /// in little endian format, so bytes in this format can be deserialized
+    /// on targets of any endianness.
+    ///
+    /// The price is that a DFA in the compact format cannot be searched in
+    /// place. It must be decoded with
+    /// [`from_bytes_compact`](enum.SparseDFA.html#method.from_bytes_compact),
+    /// which allocates and takes time proportional to the size of the DFA,
+    /// instead of with the constant time
+    /// [`from_bytes`](enum.SparseDFA.html#method.from_bytes). This makes it
+    /// best suited for shipping DFAs where size matters more than load time.
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
+    /// implementations of `StateID` provided by this crate satisfy this
+    /// requirement.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, SparseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial = SparseDFA::new("foo[0-9]+")?.to_u32()?;
+    /// let bytes = initial.to_bytes_compact()?;
+    /// assert!(bytes.len() < initial.to_bytes_native_endian()?.len());
+    ///
+    /// let dfa: SparseDFA<Vec<u8>, u32> =
+    ///     SparseDFA::from_bytes_compact(&bytes).unwrap();
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_bytes_compact(&self) -> Result<AlignedBytes> {
+        self.repr().to_bytes::<LittleEndian>(true)
     }
 }
 
@@ -440,6 +546,21 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for SparseDFA<T, S> {
         self.repr().is_anchored()
     }
 
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.repr().is_utf8()
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.repr().is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.repr().accelerator(id)
+    }
+
     #[inline]
     fn next_state(&self, current: S, input: u8) -> S {
         match *self {
@@ -496,6 +617,61 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for SparseDFA<T, S> {
             SparseDFA::__Nonexhaustive => unreachable!(),
         }
     }
+
+    #[inline]
+    fn is_match_in(&self, bytes: &[u8], start: usize, end: usize) -> bool {
+        match *self {
+            SparseDFA::Standard(ref r) => r.is_match_in(bytes, start, end),
+            SparseDFA::ByteClass(ref r) => r.is_match_in(bytes, start, end),
+            SparseDFA::__Nonexhaustive => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn shortest_match_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        match *self {
+            SparseDFA::Standard(ref r) => {
+                r.shortest_match_in(bytes, start, end)
+            }
+            SparseDFA::ByteClass(ref r) => {
+                r.shortest_match_in(bytes, start, end)
+            }
+            SparseDFA::__Nonexhaustive => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn find_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        match *self {
+            SparseDFA::Standard(ref r) => r.find_in(bytes, start, end),
+            SparseDFA::ByteClass(ref r) => r.find_in(bytes, start, end),
+            SparseDFA::__Nonexhaustive => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn rfind_in(
+        &self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        match *self {
+            SparseDFA::Standard(ref r) => r.rfind_in(bytes, start, end),
+            SparseDFA::ByteClass(ref r) => r.rfind_in(bytes, start, end),
+            SparseDFA::__Nonexhaustive => unreachable!(),
+        }
+    }
 }
 
 /// A standard sparse DFA that does not use premultiplication or byte classes.
@@ -537,6 +713,21 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for Standard<T, S> {
         self.0.is_anchored()
     }
 
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.0.is_utf8()
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.0.is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.0.accelerator(id)
+    }
+
     #[inline]
     fn next_state(&self, current: S, input: u8) -> S {
         self.0.state(current).next(input)
@@ -598,6 +789,21 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for ByteClass<T, S> {
         self.0.is_anchored()
     }
 
+    #[inline]
+    fn is_utf8(&self) -> bool {
+        self.0.is_utf8()
+    }
+
+    #[inline]
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.0.is_quit_byte(byte)
+    }
+
+    #[inline]
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.0.accelerator(id)
+    }
+
     #[inline]
     fn next_state(&self, current: S, input: u8) -> S {
         let input = self.0.byte_classes.get(input);
@@ -616,10 +822,14 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for ByteClass<T, S> {
 #[cfg_attr(not(feature = "std"), derive(Debug))]
 struct Repr<T: AsRef<[u8]>, S: StateID = usize> {
     anchored: bool,
+    utf8: bool,
     start: S,
     state_count: usize,
     max_match: S,
+    pattern_count: usize,
     byte_classes: ByteClasses,
+    quit: ByteSet,
+    accels: Accels<S>,
     trans: T,
 }
 
@@ -635,10 +845,14 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
     fn as_ref<'a>(&'a self) -> Repr<&'a [u8], S> {
         Repr {
             anchored: self.anchored,
+            utf8: self.utf8,
             start: self.start,
             state_count: self.state_count,
             max_match: self.max_match,
+            pattern_count: self.pattern_count,
             byte_classes: self.byte_classes.clone(),
+            quit: self.quit,
+            accels: self.accels,
             trans: self.trans(),
         }
     }
@@ -647,10 +861,14 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
     fn to_owned(&self) -> Repr<Vec<u8>, S> {
         Repr {
             anchored: self.anchored,
+            utf8: self.utf8,
             start: self.start,
             state_count: self.state_count,
             max_match: self.max_match,
+            pattern_count: self.pattern_count,
             byte_classes: self.byte_classes.clone(),
+            quit: self.quit,
+            accels: self.accels,
             trans: self.trans().to_vec(),
         }
     }
@@ -683,6 +901,16 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         self.trans().len()
     }
 
+    fn memory_usage_breakdown(&self) -> MemoryUsage {
+        MemoryUsage {
+            transitions: self.trans().len(),
+            matches: 0,
+            byte_classes: size_of::<ByteClasses>(),
+            quit_bytes: size_of::<ByteSet>(),
+            accelerators: size_of::<Accels<S>>(),
+        }
+    }
+
     fn start_state(&self) -> S {
         self.start
     }
@@ -703,6 +931,18 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         self.anchored
     }
 
+    fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    fn is_quit_byte(&self, byte: u8) -> bool {
+        self.quit.contains(byte)
+    }
+
+    fn accelerator(&self, id: S) -> &[u8] {
+        self.accels.needles(id)
+    }
+
     fn trans(&self) -> &[u8] {
         self.trans.as_ref()
     }
@@ -736,10 +976,14 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
 
         let mut new = Repr {
             anchored: self.anchored,
+            utf8: self.utf8,
             start: map[&self.start],
             state_count: self.state_count,
             max_match: map[&self.max_match],
+            pattern_count: self.pattern_count,
             byte_classes: self.byte_classes.clone(),
+            quit: self.quit,
+            accels: self.accels.remap(|id| map[&id]),
             trans,
         };
         for (&old_id, &new_id) in map.iter() {
@@ -764,9 +1008,16 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
     ///
     /// Unlike dense DFAs, the result is not necessarily aligned since a
     /// sparse DFA's transition table is always read as a sequence of bytes.
+    ///
+    /// When `compact` is true, the transition table is written in the
+    /// compact format, as described by `to_bytes_compact`.
     #[cfg(feature = "std")]
-    fn to_bytes<A: ByteOrder>(&self) -> Result<Vec<u8>> {
+    fn to_bytes<A: ByteOrder>(&self, compact: bool) -> Result<AlignedBytes> {
         let label = b"rust-regex-automata-sparse-dfa\x00";
+        // The size of a compact transition table isn't known until it's
+        // encoded, so it's encoded up front.
+        let compact_trans =
+            if compact { Some(self.compact_trans()) } else { None };
         let size =
             // For human readable label.
             label.len()
@@ -777,21 +1028,34 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
             // Size of state ID representation, in bytes.
             // Must be 1, 2, 4 or 8.
             + 2
-            // For DFA misc options. (Currently unused.)
+            // For DFA flags.
             + 2
             // For start state.
             + 8
+            // For anchored start state. (Currently always zero.)
+            + 8
             // For state count.
             + 8
             // For max match state.
             + 8
+            // For pattern count.
+            + 8
+            // For the length of the table of matching patterns. (Currently
+            // always zero.)
+            + 8
             // For byte class map.
             + 256
+            // For the set of quit bytes.
+            + 32
+            // For the accelerated states.
+            + ACCELS_LEN
+            // For the checksum. (Currently always zero.)
+            + 8
             // For transition table.
-            + self.trans().len();
+            + compact_trans.as_ref().map_or(self.trans().len(), |t| t.len());
 
         let mut i = 0;
-        let mut buf = vec![0; size];
+        let mut buf = AlignedBytes::zeroed(size);
 
         // write label
         for &b in label {
@@ -802,7 +1066,7 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         A::write_u16(&mut buf[i..], 0xFEFF);
         i += 2;
         // version number
-        A::write_u16(&mut buf[i..], 1);
+        A::write_u16(&mut buf[i..], FORMAT_VERSION);
         i += 2;
         // size of state ID
         let state_size = size_of::<S>();
@@ -814,36 +1078,69 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         }
         A::write_u16(&mut buf[i..], state_size as u16);
         i += 2;
-        // DFA misc options
+        // DFA flags
         let mut options = 0u16;
         if self.anchored {
             options |= dense::MASK_ANCHORED;
         }
+        if self.utf8 {
+            options |= dense::MASK_UTF8;
+        }
+        if compact {
+            options |= dense::MASK_COMPACT;
+        }
         A::write_u16(&mut buf[i..], options);
         i += 2;
         // start state
         A::write_u64(&mut buf[i..], self.start.to_usize() as u64);
         i += 8;
+        // anchored start state, which sparse DFAs never have
+        A::write_u64(&mut buf[i..], 0);
+        i += 8;
         // state count
         A::write_u64(&mut buf[i..], self.state_count as u64);
         i += 8;
         // max match state
         A::write_u64(&mut buf[i..], self.max_match.to_usize() as u64);
         i += 8;
+        // pattern count
+        A::write_u64(&mut buf[i..], self.pattern_count as u64);
+        i += 8;
+        // length of the table of matching patterns
+        A::write_u64(&mut buf[i..], 0);
+        i += 8;
         // byte class map
         for b in (0..256).map(|b| b as u8) {
             buf[i] = self.byte_classes.get(b);
             i += 1;
         }
+        // set of quit bytes
+        buf[i..i + 32].copy_from_slice(self.quit.as_bytes());
+        i += 32;
+        // accelerated states
+        self.accels.write::<A>(&mut buf[i..]);
+        i += ACCELS_LEN;
+        // checksum, which sparse DFAs don't record
+        A::write_u64(&mut buf[i..], 0);
+        i += 8;
         // transition table
-        for (_, state) in self.states() {
-            A::write_u16(&mut buf[i..], state.ntrans as u16);
-            i += 2;
-            buf[i..i + (state.ntrans * 2)].copy_from_slice(state.input_ranges);
-            i += state.ntrans * 2;
-            for j in 0..state.ntrans {
-                write_state_id_bytes::<A, _>(&mut buf[i..], state.next_at(j));
-                i += size_of::<S>();
+        if let Some(trans) = compact_trans {
+            buf[i..i + trans.len()].copy_from_slice(&trans);
+            i += trans.len();
+        } else {
+            for (_, state) in self.states() {
+                A::write_u16(&mut buf[i..], state.ntrans as u16);
+                i += 2;
+                buf[i..i + (state.ntrans * 2)]
+                    .copy_from_slice(state.input_ranges);
+                i += state.ntrans * 2;
+                for j in 0..state.ntrans {
+                    write_state_id_bytes::<A, _>(
+                        &mut buf[i..],
+                        state.next_at(j),
+                    );
+                    i += size_of::<S>();
+                }
             }
         }
 
@@ -851,6 +1148,27 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
 
         Ok(buf)
     }
+
+    /// Encode the transition table of this DFA in the compact format, in
+    /// which each next state is recorded as the difference between its
+    /// index and the index of the current state.
+    #[cfg(feature = "std")]
+    fn compact_trans(&self) -> Vec<u8> {
+        let ids: Vec<usize> =
+            self.states().map(|(id, _)| id.to_usize()).collect();
+        let mut trans = vec![];
+        for (index, (_, state)) in self.states().enumerate() {
+            bytes::write_varu64(&mut trans, state.ntrans as u64);
+            trans.extend_from_slice(state.input_ranges);
+            for j in 0..state.ntrans {
+                let next = ids
+                    .binary_search(&state.next_at(j).to_usize())
+                    .expect("transition to a valid state");
+                bytes::write_vari64(&mut trans, next as i64 - index as i64);
+            }
+        }
+        trans
+    }
 }
 
 impl<'a, S: StateID> Repr<&'a [u8], S> {
@@ -877,10 +1195,10 @@ impl<'a, S: StateID> Repr<&'a [u8], S> {
         // check that the version number is supported
         let version = NativeEndian::read_u16(buf);
         buf = &buf[2..];
-        if version != 1 {
+        if version != FORMAT_VERSION {
             panic!(
-                "expected version 1, but found unsupported version {}",
-                version,
+                "expected version {}, but found unsupported version {}",
+                FORMAT_VERSION, version,
             );
         }
 
@@ -896,14 +1214,31 @@ impl<'a, S: StateID> Repr<&'a [u8], S> {
         }
         buf = &buf[2..];
 
-        // read miscellaneous options
+        // read flags, rejecting unknown required flags
         let opts = NativeEndian::read_u16(buf);
         buf = &buf[2..];
+        let unknown =
+            opts & dense::MASK_REQUIRED & !dense::MASK_KNOWN_REQUIRED;
+        if unknown != 0 {
+            panic!(
+                "SparseDFA requires unsupported features (flags 0x{:04X})",
+                unknown,
+            );
+        }
+        if opts & dense::MASK_COMPACT > 0 {
+            panic!(
+                "SparseDFA is in the compact format, which must be \
+                 deserialized with SparseDFA::from_bytes_compact",
+            );
+        }
 
         // read start state
         let start = S::from_usize(NativeEndian::read_u64(buf) as usize);
         buf = &buf[8..];
 
+        // skip the anchored start state, which sparse DFAs never have
+        buf = &buf[8..];
+
         // read state count
         let state_count = NativeEndian::read_u64(buf) as usize;
         buf = &buf[8..];
@@ -912,16 +1247,39 @@ impl<'a, S: StateID> Repr<&'a [u8], S> {
         let max_match = S::from_usize(NativeEndian::read_u64(buf) as usize);
         buf = &buf[8..];
 
+        // read pattern count
+        let pattern_count = NativeEndian::read_u64(buf) as usize;
+        buf = &buf[8..];
+
+        // skip the length of the table of matching patterns, which is always
+        // zero for sparse DFAs
+        buf = &buf[8..];
+
         // read byte classes
         let byte_classes = ByteClasses::from_slice(&buf[..256]);
         buf = &buf[256..];
 
+        // read set of quit bytes
+        let quit = ByteSet::from_slice(&buf[..32]);
+        buf = &buf[32..];
+
+        // read accelerated states
+        let accels = Accels::from_bytes_unchecked(&buf[..ACCELS_LEN]);
+        buf = &buf[ACCELS_LEN..];
+
+        // skip the checksum, which sparse DFAs don't record
+        buf = &buf[8..];
+
         Repr {
             anchored: opts & dense::MASK_ANCHORED > 0,
+            utf8: opts & dense::MASK_UTF8 > 0,
             start,
             state_count,
             max_match,
+            pattern_count,
             byte_classes,
+            quit,
+            accels,
             trans: buf,
         }
     }
@@ -949,6 +1307,17 @@ impl<S: StateID> Repr<Vec<u8>, S> {
         //
         // In the second pass, we fill in the transitions based on the map
         // built in the first pass.
+        //
+        // Sparse DFAs have no way of recording which patterns match in each
+        // match state, so only DFAs built from a single pattern are supported.
+        if dfa.pattern_count() > 1 {
+            return Err(Error::unsupported_multiple_patterns());
+        }
+        // Nor do they have a transition for the end of the input, which a
+        // DFA with look-around needs.
+        if dfa.has_lookaround() {
+            return Err(Error::unsupported_sparse_lookaround());
+        }
 
         let mut trans = Vec::with_capacity(size_of::<A>() * dfa.state_count());
         let mut remap: Vec<A> = vec![dead_id(); dfa.state_count()];
@@ -976,10 +1345,14 @@ impl<S: StateID> Repr<Vec<u8>, S> {
 
         let mut new = Repr {
             anchored: dfa.is_anchored(),
+            utf8: dfa.is_utf8(),
             start: remap[dfa.state_id_to_index(dfa.start_state())],
             state_count: dfa.state_count(),
             max_match: remap[dfa.state_id_to_index(dfa.max_match_state())],
+            pattern_count: dfa.pattern_count(),
             byte_classes: dfa.byte_classes().clone(),
+            quit: *dfa.quit_bytes(),
+            accels: dfa.accels().remap(|id| remap[dfa.state_id_to_index(id)]),
             trans,
         };
         for (old_id, old_state) in dfa.states() {
@@ -1,5 +1,6 @@
 use core::fmt::Debug;
 use core::hash::Hash;
+use core::marker::PhantomData;
 use core::mem::size_of;
 
 use byteorder::{ByteOrder, NativeEndian};
@@ -39,10 +40,18 @@ mod std {
     pub fn next_state_id<S: StateID>(current: S) -> Result<S> {
         let next = match current.to_usize().checked_add(1) {
             Some(next) => next,
-            None => return Err(Error::state_id_overflow(::std::usize::MAX)),
+            None => {
+                return Err(Error::state_id_overflow(
+                    ::std::usize::MAX,
+                    current.to_usize(),
+                ))
+            }
         };
         if next > S::max_id() {
-            return Err(Error::state_id_overflow(S::max_id()));
+            return Err(Error::state_id_overflow(
+                S::max_id(),
+                next.saturating_add(1),
+            ));
         }
         Ok(S::from_usize(next))
     }
@@ -52,7 +61,7 @@ mod std {
     /// representation, then an error is returned.
     pub fn usize_to_state_id<S: StateID>(value: usize) -> Result<S> {
         if value > S::max_id() {
-            Err(Error::state_id_overflow(S::max_id()))
+            Err(Error::state_id_overflow(S::max_id(), value.saturating_add(1)))
         } else {
             Ok(S::from_usize(value))
         }
@@ -151,8 +160,104 @@ pub unsafe trait StateID:
     /// Implementors may assume that the given slice has length at least
     /// `size_of::<Self>()`.
     fn write_bytes(self, slice: &mut [u8]);
+
+    /// Create an identifier from the given `usize`, or return an error if
+    /// it doesn't fit in this representation.
+    ///
+    /// Since [`PatternID`](type.PatternID.html) is a `usize`, this also
+    /// constructs pattern identifiers when this trait is in scope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{PatternID, StateID};
+    ///
+    /// assert_eq!(255, u8::new(255).unwrap());
+    /// assert!(u8::new(256).is_err());
+    /// assert_eq!(3, PatternID::new(3).unwrap());
+    /// ```
+    #[cfg(feature = "std")]
+    fn new(n: usize) -> ::error::Result<Self> {
+        usize_to_state_id(n)
+    }
+
+    /// Create an identifier from the given `usize` without checking that it
+    /// fits in this representation.
+    ///
+    /// This is safe to call, but if `n` is greater than
+    /// [`max_id`](trait.StateID.html#tymethod.max_id), then the identifier
+    /// returned is unspecified. In debug builds, this panics instead.
+    #[inline]
+    fn new_unchecked(n: usize) -> Self {
+        debug_assert!(n <= Self::max_id(), "identifier {} overflows", n);
+        Self::from_usize(n)
+    }
+
+    /// Returns an iterator over the first `count` identifiers in this
+    /// representation, in increasing order, starting at `0`.
+    ///
+    /// This is useful for visiting every state of a DFA with `count`
+    /// states, or every pattern of a DFA built from `count` patterns.
+    ///
+    /// # Panics
+    ///
+    /// This panics if some of the identifiers in `0..count` don't fit in
+    /// this representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{PatternID, StateID};
+    ///
+    /// let ids: Vec<u8> = u8::iter(3).collect();
+    /// assert_eq!(vec![0, 1, 2], ids);
+    /// assert_eq!(256, u8::iter(256).count());
+    /// assert_eq!(2, PatternID::iter(2).count());
+    /// ```
+    fn iter(count: usize) -> StateIDIter<Self> {
+        assert!(
+            count == 0 || count - 1 <= Self::max_id(),
+            "{} identifiers overflow the representation",
+            count,
+        );
+        StateIDIter { next: 0, count, _marker: PhantomData }
+    }
+}
+
+/// An iterator over the identifiers of a [`StateID`](trait.StateID.html)
+/// representation, in increasing order.
+///
+/// This is created by
+/// [`StateID::iter`](trait.StateID.html#method.iter).
+#[derive(Clone, Debug)]
+pub struct StateIDIter<S> {
+    next: usize,
+    count: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<S: StateID> Iterator for StateIDIter<S> {
+    type Item = S;
+
+    #[inline]
+    fn next(&mut self) -> Option<S> {
+        if self.next >= self.count {
+            return None;
+        }
+        let id = S::from_usize(self.next);
+        self.next += 1;
+        Some(id)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.count - self.next;
+        (len, Some(len))
+    }
 }
 
+impl<S: StateID> ExactSizeIterator for StateIDIter<S> {}
+
 unsafe impl StateID for usize {
     #[inline]
     fn from_usize(n: usize) -> usize {
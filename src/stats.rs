@@ -0,0 +1,228 @@
+#[cfg(feature = "stats")]
+use std::cell::Cell;
+
+/// Counters describing the work done by one or more searches.
+///
+/// Statistics are collected by running searches inside of
+/// [`SearchStats::record`](struct.SearchStats.html#method.record). Every
+/// search routine of the [`DFA`](trait.DFA.html) trait contributes to them,
+/// as do the searches of a [`Regex`](struct.Regex.html) and of a
+/// [`Prefilter`](struct.Prefilter.html). This makes it possible to see how
+/// often a prefilter reports candidates that don't lead to a match, or how
+/// many bytes an accelerator or prefilter skips, which helps when deciding
+/// whether a prefilter is worth using for a particular workload.
+///
+/// This is only available when the `stats` feature is enabled. Without it,
+/// searches don't pay for collecting any statistics.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{Match, Regex, SearchStats};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let re = Regex::new("foo[0-9]+")?;
+/// let (m, stats) = SearchStats::record(|| re.find(b"food foo123"));
+/// assert_eq!(Some(Match::new(5, 11)), m);
+/// assert_eq!(1, stats.prefilter_misses());
+/// assert!(stats.prefilter_hits() >= 1);
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SearchStats {
+    bytes_scanned: u64,
+    state_visits: u64,
+    prefilter_hits: u64,
+    prefilter_misses: u64,
+    quits: u64,
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    static STATS: Cell<SearchStats> = Cell::new(SearchStats::default());
+}
+
+#[cfg(feature = "stats")]
+impl SearchStats {
+    /// Run the given closure and return its result along with the
+    /// statistics of every search it ran on the current thread.
+    ///
+    /// Calls to `record` may be nested, in which case the statistics
+    /// recorded by the inner call are also included in those recorded by
+    /// the outer call.
+    pub fn record<T, F: FnOnce() -> T>(f: F) -> (T, SearchStats) {
+        let outer = STATS.with(|s| s.replace(SearchStats::default()));
+        let result = f();
+        let inner = STATS.with(|s| s.get());
+        STATS.with(|s| s.set(outer.add(&inner)));
+        (result, inner)
+    }
+
+    /// Returns the number of haystack bytes a search moved past, either by
+    /// following a transition on them or by skipping over them with an
+    /// accelerator or a prefilter.
+    ///
+    /// When a search runs a reverse DFA back from an occurrence of a suffix
+    /// literal, the bytes it visits are counted again.
+    pub fn bytes_scanned(&self) -> u64 {
+        self.bytes_scanned
+    }
+
+    /// Returns the number of transitions followed, each of which visits a
+    /// DFA state.
+    ///
+    /// The difference between this and
+    /// [`bytes_scanned`](struct.SearchStats.html#method.bytes_scanned) is
+    /// roughly the number of bytes skipped by accelerators and prefilters.
+    pub fn state_visits(&self) -> u64 {
+        self.state_visits
+    }
+
+    /// Returns the number of candidates reported by a prefilter that led to
+    /// a match.
+    ///
+    /// Only prefilters that know of prefix literals or of a suffix literal
+    /// report candidates. A prefilter that only knows of a literal that
+    /// every match contains never counts as a hit or a miss.
+    pub fn prefilter_hits(&self) -> u64 {
+        self.prefilter_hits
+    }
+
+    /// Returns the number of candidates reported by a prefilter that did not
+    /// lead to a match.
+    ///
+    /// A large number of misses compared to
+    /// [`prefilter_hits`](struct.SearchStats.html#method.prefilter_hits)
+    /// suggests that the prefilter's literals occur much more often than
+    /// matches do, and that searching without it may be faster.
+    pub fn prefilter_misses(&self) -> u64 {
+        self.prefilter_misses
+    }
+
+    /// Returns the number of searches that stopped on a
+    /// [quit byte](dense/struct.Builder.html#method.quit) before finding a
+    /// match.
+    pub fn quits(&self) -> u64 {
+        self.quits
+    }
+
+    fn add(&self, other: &SearchStats) -> SearchStats {
+        SearchStats {
+            bytes_scanned: self.bytes_scanned + other.bytes_scanned,
+            state_visits: self.state_visits + other.state_visits,
+            prefilter_hits: self.prefilter_hits + other.prefilter_hits,
+            prefilter_misses: self.prefilter_misses + other.prefilter_misses,
+            quits: self.quits + other.quits,
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+fn update<F: FnOnce(&mut SearchStats)>(f: F) {
+    STATS.with(|s| {
+        let mut stats = s.get();
+        f(&mut stats);
+        s.set(stats);
+    });
+}
+
+/// Record that a search followed `n` transitions, one for each of the next
+/// `n` bytes of the haystack.
+#[cfg(feature = "stats")]
+#[inline]
+pub(crate) fn transitions(n: usize) {
+    update(|s| {
+        s.bytes_scanned += n as u64;
+        s.state_visits += n as u64;
+    });
+}
+
+/// Record that a search skipped over `n` bytes of the haystack without
+/// following any transition.
+#[cfg(feature = "stats")]
+#[inline]
+pub(crate) fn skipped(n: usize) {
+    update(|s| s.bytes_scanned += n as u64);
+}
+
+/// Record that a candidate reported by a prefilter led to a match.
+#[cfg(feature = "stats")]
+#[inline]
+pub(crate) fn prefilter_hit() {
+    update(|s| s.prefilter_hits += 1);
+}
+
+/// Record that a candidate reported by a prefilter did not lead to a match.
+#[cfg(feature = "stats")]
+#[inline]
+pub(crate) fn prefilter_miss() {
+    update(|s| s.prefilter_misses += 1);
+}
+
+/// Record that a search stopped on a quit byte.
+#[cfg(feature = "stats")]
+#[inline]
+pub(crate) fn quit() {
+    update(|s| s.quits += 1);
+}
+
+#[cfg(not(feature = "stats"))]
+#[inline(always)]
+pub(crate) fn transitions(_: usize) {}
+
+#[cfg(not(feature = "stats"))]
+#[inline(always)]
+pub(crate) fn skipped(_: usize) {}
+
+#[cfg(all(feature = "std", not(feature = "stats")))]
+#[inline(always)]
+pub(crate) fn prefilter_hit() {}
+
+#[cfg(all(feature = "std", not(feature = "stats")))]
+#[inline(always)]
+pub(crate) fn prefilter_miss() {}
+
+#[cfg(not(feature = "stats"))]
+#[inline(always)]
+pub(crate) fn quit() {}
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use super::SearchStats;
+    use dense;
+    use dfa::DFA;
+
+    #[test]
+    fn transitions_and_accelerated_skips() {
+        let dfa = dense::Builder::new()
+            .allow_invalid_utf8(true)
+            .build(r#"(?-u)[^"]*""#)
+            .unwrap();
+        let haystack = b"abcdefghijklmnopqrstuvwxyz\"";
+        let (m, stats) = SearchStats::record(|| dfa.find(haystack));
+        assert_eq!(Some(haystack.len()), m);
+        assert_eq!(haystack.len() as u64, stats.bytes_scanned());
+        assert!(stats.state_visits() < stats.bytes_scanned());
+        assert_eq!(0, stats.quits());
+    }
+
+    #[test]
+    fn quit() {
+        let dfa = dense::Builder::new().quit(b'\n', true).build("a+").unwrap();
+        let (m, stats) = SearchStats::record(|| dfa.try_find(b"xy\nz"));
+        assert!(m.is_err());
+        assert_eq!(1, stats.quits());
+    }
+
+    #[test]
+    fn nested() {
+        let dfa = dense::Builder::new().build("[0-9]+").unwrap();
+        let ((_, inner), outer) = SearchStats::record(|| {
+            dfa.find(b"abc");
+            SearchStats::record(|| dfa.find(b"123"))
+        });
+        assert_eq!(3, inner.state_visits());
+        assert_eq!(6, outer.state_visits());
+    }
+}
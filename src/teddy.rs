@@ -0,0 +1,397 @@
+use cpu::{self, Simd};
+
+/// The number of buckets into which literals are grouped. Each bucket
+/// corresponds to one bit of the bytes in a mask.
+const BUCKETS: usize = 8;
+
+/// The maximum number of leading bytes of each literal used to find
+/// candidates.
+const MAX_MASK_LEN: usize = 3;
+
+/// A packed searcher for a small set of literals, in the style of the Teddy
+/// algorithm from Hyperscan.
+///
+/// Literals are grouped into eight buckets. For each of the first few bytes
+/// of the literals, two tables map the low and high nibbles of a haystack
+/// byte to the set of buckets containing a literal with that nibble at that
+/// position. Looking up the nibbles of consecutive haystack bytes and
+/// intersecting the results gives, for each position, the buckets whose
+/// literals may occur there. Only the literals in those buckets are then
+/// compared against the haystack.
+///
+/// With SSSE3 or NEON, the table lookups are done for 16 positions at a time
+/// with a byte shuffle or table lookup instruction, and with AVX2, for 32
+/// positions at a time. The widest
+/// implementation supported by the CPU is chosen when the searcher is built,
+/// and a scalar implementation using the same tables is used everywhere
+/// else. The NEON implementation is only compiled with the `neon` feature,
+/// since its intrinsics require a newer Rust than this crate otherwise
+/// supports.
+#[derive(Clone, Debug)]
+pub struct Teddy {
+    /// The number of leading bytes of each literal used to find candidates.
+    mask_len: usize,
+    /// For each leading byte, the buckets of each low nibble.
+    lo: [[u8; 16]; MAX_MASK_LEN],
+    /// For each leading byte, the buckets of each high nibble.
+    hi: [[u8; 16]; MAX_MASK_LEN],
+    /// The literals in each bucket.
+    buckets: Vec<Vec<Vec<u8>>>,
+    /// The SIMD instructions used to find candidates.
+    simd: Simd,
+}
+
+impl Teddy {
+    /// Build a packed searcher for the given literals, using the fastest
+    /// implementation supported by the current CPU.
+    ///
+    /// If there are no literals, more than a packed searcher handles well or
+    /// any literal shorter than two bytes, then `None` is returned. A single
+    /// leading byte doesn't discriminate well enough between candidates.
+    pub fn new<B: AsRef<[u8]>>(literals: &[B]) -> Option<Teddy> {
+        Teddy::with_simd(literals, cpu::simd())
+    }
+
+    fn with_simd<B: AsRef<[u8]>>(literals: &[B], simd: Simd) -> Option<Teddy> {
+        let min_len = literals.iter().map(|lit| lit.as_ref().len()).min()?;
+        if min_len < 2 || literals.len() > 64 {
+            return None;
+        }
+        let mask_len = ::std::cmp::min(MAX_MASK_LEN, min_len);
+
+        // Literals sharing the same leading bytes always share a bucket,
+        // since nothing could tell them apart before verification anyway.
+        let mut prefixes: Vec<&[u8]> = vec![];
+        let mut buckets = vec![vec![]; BUCKETS];
+        for lit in literals {
+            let lit = lit.as_ref();
+            let prefix = &lit[..mask_len];
+            let i = match prefixes.iter().position(|&p| p == prefix) {
+                Some(i) => i,
+                None => {
+                    prefixes.push(prefix);
+                    prefixes.len() - 1
+                }
+            };
+            let bucket: &mut Vec<Vec<u8>> = &mut buckets[i % BUCKETS];
+            if !bucket.iter().any(|b| &**b == lit) {
+                bucket.push(lit.to_vec());
+            }
+        }
+
+        let mut teddy = Teddy {
+            mask_len,
+            lo: [[0; 16]; MAX_MASK_LEN],
+            hi: [[0; 16]; MAX_MASK_LEN],
+            buckets,
+            simd,
+        };
+        for (bucket, lits) in teddy.buckets.iter().enumerate() {
+            for lit in lits {
+                for (j, &b) in lit[..mask_len].iter().enumerate() {
+                    teddy.lo[j][(b & 0xF) as usize] |= 1 << bucket;
+                    teddy.hi[j][(b >> 4) as usize] |= 1 << bucket;
+                }
+            }
+        }
+        Some(teddy)
+    }
+
+    /// Returns the position of the first occurrence of any literal in
+    /// `haystack` at or after `at`.
+    pub fn find(&self, haystack: &[u8], at: usize) -> Option<usize> {
+        match self.simd {
+            #[cfg(target_arch = "x86_64")]
+            Simd::Ssse3 => unsafe { x86::find_ssse3(self, haystack, at) },
+            #[cfg(target_arch = "x86_64")]
+            Simd::Avx2 => unsafe { x86::find_avx2(self, haystack, at) },
+            #[cfg(all(
+                feature = "neon",
+                target_arch = "aarch64",
+                target_feature = "neon"
+            ))]
+            Simd::Neon => unsafe { aarch64::find_neon(self, haystack, at) },
+            _ => self.find_scalar(haystack, at),
+        }
+    }
+
+    /// Find the first occurrence of any literal at or after `at` by looking
+    /// up each position in the masks one byte at a time.
+    fn find_scalar(&self, haystack: &[u8], mut at: usize) -> Option<usize> {
+        while at + self.mask_len <= haystack.len() {
+            let mut buckets = 0xFF;
+            for j in 0..self.mask_len {
+                let b = haystack[at + j];
+                buckets &= self.lo[j][(b & 0xF) as usize]
+                    & self.hi[j][(b >> 4) as usize];
+            }
+            if buckets != 0 && self.verify(haystack, at, buckets) {
+                return Some(at);
+            }
+            at += 1;
+        }
+        None
+    }
+
+    /// Returns true if and only if a literal in one of the given buckets
+    /// occurs in `haystack` at `at`.
+    fn verify(&self, haystack: &[u8], at: usize, mut buckets: u8) -> bool {
+        let rest = &haystack[at..];
+        while buckets != 0 {
+            let bucket = buckets.trailing_zeros() as usize;
+            buckets &= buckets - 1;
+            if self.buckets[bucket].iter().any(|lit| rest.starts_with(lit)) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::Teddy;
+
+    /// Find the first occurrence of any literal at or after `at`, looking up
+    /// 16 positions at a time.
+    ///
+    /// Callers must ensure that the CPU supports SSSE3.
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn find_ssse3(
+        teddy: &Teddy,
+        haystack: &[u8],
+        mut at: usize,
+    ) -> Option<usize> {
+        let nibble = _mm_set1_epi8(0xF);
+        let mut lo = [_mm_setzero_si128(); super::MAX_MASK_LEN];
+        let mut hi = [_mm_setzero_si128(); super::MAX_MASK_LEN];
+        for j in 0..teddy.mask_len {
+            lo[j] = _mm_loadu_si128(teddy.lo[j].as_ptr() as *const __m128i);
+            hi[j] = _mm_loadu_si128(teddy.hi[j].as_ptr() as *const __m128i);
+        }
+        let ptr = haystack.as_ptr();
+        let mut found = [0u8; 16];
+        while at + 16 + teddy.mask_len - 1 <= haystack.len() {
+            let mut buckets = _mm_set1_epi8(-1);
+            for j in 0..teddy.mask_len {
+                let chunk = _mm_loadu_si128(ptr.add(at + j) as *const __m128i);
+                let clo = _mm_and_si128(chunk, nibble);
+                let chi = _mm_and_si128(_mm_srli_epi16(chunk, 4), nibble);
+                buckets = _mm_and_si128(
+                    buckets,
+                    _mm_and_si128(
+                        _mm_shuffle_epi8(lo[j], clo),
+                        _mm_shuffle_epi8(hi[j], chi),
+                    ),
+                );
+            }
+            let empty = _mm_cmpeq_epi8(buckets, _mm_setzero_si128());
+            let mut candidates = !(_mm_movemask_epi8(empty) as u32) & 0xFFFF;
+            if candidates != 0 {
+                _mm_storeu_si128(found.as_mut_ptr() as *mut __m128i, buckets);
+                while candidates != 0 {
+                    let i = candidates.trailing_zeros() as usize;
+                    candidates &= candidates - 1;
+                    if teddy.verify(haystack, at + i, found[i]) {
+                        return Some(at + i);
+                    }
+                }
+            }
+            at += 16;
+        }
+        teddy.find_scalar(haystack, at)
+    }
+
+    /// Find the first occurrence of any literal at or after `at`, looking up
+    /// 32 positions at a time.
+    ///
+    /// Callers must ensure that the CPU supports AVX2.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find_avx2(
+        teddy: &Teddy,
+        haystack: &[u8],
+        mut at: usize,
+    ) -> Option<usize> {
+        // Shuffles only select bytes within each 128-bit lane, so every mask
+        // is repeated in both lanes.
+        let nibble = _mm256_set1_epi8(0xF);
+        let mut lo = [_mm256_setzero_si256(); super::MAX_MASK_LEN];
+        let mut hi = [_mm256_setzero_si256(); super::MAX_MASK_LEN];
+        for j in 0..teddy.mask_len {
+            lo[j] = _mm256_broadcastsi128_si256(_mm_loadu_si128(
+                teddy.lo[j].as_ptr() as *const __m128i,
+            ));
+            hi[j] = _mm256_broadcastsi128_si256(_mm_loadu_si128(
+                teddy.hi[j].as_ptr() as *const __m128i,
+            ));
+        }
+        let ptr = haystack.as_ptr();
+        let mut found = [0u8; 32];
+        while at + 32 + teddy.mask_len - 1 <= haystack.len() {
+            let mut buckets = _mm256_set1_epi8(-1);
+            for j in 0..teddy.mask_len {
+                let chunk =
+                    _mm256_loadu_si256(ptr.add(at + j) as *const __m256i);
+                let clo = _mm256_and_si256(chunk, nibble);
+                let chi =
+                    _mm256_and_si256(_mm256_srli_epi16(chunk, 4), nibble);
+                buckets = _mm256_and_si256(
+                    buckets,
+                    _mm256_and_si256(
+                        _mm256_shuffle_epi8(lo[j], clo),
+                        _mm256_shuffle_epi8(hi[j], chi),
+                    ),
+                );
+            }
+            let empty = _mm256_cmpeq_epi8(buckets, _mm256_setzero_si256());
+            let mut candidates = !(_mm256_movemask_epi8(empty) as u32);
+            if candidates != 0 {
+                _mm256_storeu_si256(
+                    found.as_mut_ptr() as *mut __m256i,
+                    buckets,
+                );
+                while candidates != 0 {
+                    let i = candidates.trailing_zeros() as usize;
+                    candidates &= candidates - 1;
+                    if teddy.verify(haystack, at + i, found[i]) {
+                        return Some(at + i);
+                    }
+                }
+            }
+            at += 32;
+        }
+        find_ssse3(teddy, haystack, at)
+    }
+}
+
+#[cfg(all(
+    feature = "neon",
+    target_arch = "aarch64",
+    target_feature = "neon"
+))]
+mod aarch64 {
+    use std::arch::aarch64::*;
+
+    use super::Teddy;
+
+    /// Find the first occurrence of any literal at or after `at`, looking up
+    /// 16 positions at a time.
+    ///
+    /// Callers must ensure that the CPU supports NEON.
+    #[target_feature(enable = "neon")]
+    pub unsafe fn find_neon(
+        teddy: &Teddy,
+        haystack: &[u8],
+        mut at: usize,
+    ) -> Option<usize> {
+        let nibble = vdupq_n_u8(0xF);
+        let mut lo = [vdupq_n_u8(0); super::MAX_MASK_LEN];
+        let mut hi = [vdupq_n_u8(0); super::MAX_MASK_LEN];
+        for j in 0..teddy.mask_len {
+            lo[j] = vld1q_u8(teddy.lo[j].as_ptr());
+            hi[j] = vld1q_u8(teddy.hi[j].as_ptr());
+        }
+        let ptr = haystack.as_ptr();
+        let mut found = [0u8; 16];
+        while at + 16 + teddy.mask_len - 1 <= haystack.len() {
+            let mut buckets = vdupq_n_u8(0xFF);
+            for j in 0..teddy.mask_len {
+                let chunk = vld1q_u8(ptr.add(at + j));
+                let clo = vandq_u8(chunk, nibble);
+                let chi = vshrq_n_u8(chunk, 4);
+                buckets = vandq_u8(
+                    buckets,
+                    vandq_u8(vqtbl1q_u8(lo[j], clo), vqtbl1q_u8(hi[j], chi)),
+                );
+            }
+            // NEON has no equivalent of a byte mask extraction, so lanes are
+            // only inspected one at a time once some lane is known to be a
+            // candidate.
+            if vmaxvq_u8(buckets) != 0 {
+                vst1q_u8(found.as_mut_ptr(), buckets);
+                for (i, &b) in found.iter().enumerate() {
+                    if b != 0 && teddy.verify(haystack, at + i, b) {
+                        return Some(at + i);
+                    }
+                }
+            }
+            at += 16;
+        }
+        teddy.find_scalar(haystack, at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Teddy;
+    use cpu;
+
+    fn naive(literals: &[&str], haystack: &[u8], at: usize) -> Option<usize> {
+        (at..haystack.len()).find(|&i| {
+            literals
+                .iter()
+                .any(|lit| haystack[i..].starts_with(lit.as_bytes()))
+        })
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(Teddy::new::<&str>(&[]).is_none());
+        assert!(Teddy::new(&["foo", "a"][..]).is_none());
+        let many: Vec<String> = (0..65).map(|i| format!("x{}", i)).collect();
+        assert!(Teddy::new(&many[..]).is_none());
+    }
+
+    #[test]
+    fn agrees_with_naive() {
+        let sets: &[&[&str]] = &[
+            &["foo", "bar", "baz", "quux", "Sherlock", "Watson"],
+            &["ab", "cd", "ef", "gh", "ij", "kl", "mn", "op", "qr", "st"],
+            &["zz", "zy", "yz", "\u{3b1}\u{3b2}", "\u{263a}"],
+            &["aaaa", "aaab", "aaba", "abaa", "baaa"],
+        ];
+        let mut haystacks: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b"f".to_vec(),
+            b"foo".to_vec(),
+            b"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxbar".to_vec(),
+            "zzzz \u{3b1}\u{3b2} \u{263a} yz".as_bytes().to_vec(),
+        ];
+        let mut text = vec![];
+        for i in 0..300u32 {
+            let word: &[u8] = match i % 7 {
+                0 => b"Sherloc",
+                1 => b"Watso",
+                2 => b"ba",
+                3 => b"aab",
+                4 => b"q",
+                5 => b"ij",
+                _ => b"zq",
+            };
+            text.extend_from_slice(word);
+            text.push(b"xyz\xff"[(i % 4) as usize]);
+        }
+        text.extend_from_slice(b"Watson");
+        haystacks.push(text);
+
+        for &literals in sets {
+            for simd in cpu::supported() {
+                let teddy = Teddy::with_simd(literals, simd).unwrap();
+                for haystack in &haystacks {
+                    for at in 0..haystack.len() + 1 {
+                        assert_eq!(
+                            naive(literals, haystack, at),
+                            teddy.find(haystack, at),
+                            "literals: {:?}, simd: {:?}, at: {}",
+                            literals,
+                            simd,
+                            at,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
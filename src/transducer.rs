@@ -2,6 +2,27 @@ use fst::Automaton;
 
 use crate::{StateID, DFA};
 
+/// Returns the state in which the given DFA begins matching a key.
+#[inline]
+fn start<D: DFA>(dfa: &D) -> D::ID {
+    dfa.start_state_after(None)
+}
+
+/// Returns true if and only if the given DFA matches a key that leads to the
+/// given state.
+///
+/// A DFA with look-around only enters a match state once it has seen the
+/// byte following a match, so a key matches when the state reached at the
+/// end of the input is a match state.
+#[inline]
+fn is_match<D: DFA>(dfa: &D, state: D::ID) -> bool {
+    if dfa.has_lookaround() {
+        dfa.is_match_state(dfa.next_eoi_state(state))
+    } else {
+        dfa.is_match_state(state)
+    }
+}
+
 macro_rules! imp {
     ($ty:ty, $id:ty) => {
         impl<T: AsRef<[$id]>, S: StateID> Automaton for $ty {
@@ -9,12 +30,12 @@ macro_rules! imp {
 
             #[inline]
             fn start(&self) -> S {
-                self.start_state()
+                start(self)
             }
 
             #[inline]
             fn is_match(&self, state: &S) -> bool {
-                self.is_match_state(*state)
+                is_match(self, *state)
             }
 
             #[inline]
@@ -39,6 +60,30 @@ imp!(crate::sparse::SparseDFA<T, S>, u8);
 imp!(crate::sparse::Standard<T, S>, u8);
 imp!(crate::sparse::ByteClass<T, S>, u8);
 
+impl Automaton for crate::dense::SizedDFA {
+    type State = usize;
+
+    #[inline]
+    fn start(&self) -> usize {
+        start(self)
+    }
+
+    #[inline]
+    fn is_match(&self, state: &usize) -> bool {
+        is_match(self, *state)
+    }
+
+    #[inline]
+    fn accept(&self, state: &usize, byte: u8) -> usize {
+        self.next_state(*state, byte)
+    }
+
+    #[inline]
+    fn can_match(&self, state: &usize) -> bool {
+        !self.is_dead_state(*state)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bstr::BString;
@@ -104,4 +149,27 @@ mod tests {
         let got = search(&set, &dfa);
         assert_eq!(got, vec!["bar", "baz"]);
     }
+
+    #[test]
+    fn dense_lookaround() {
+        let set =
+            Set::from_iter(&["ba", "ba z", "bar", "x ba", "xba"]).unwrap();
+        let dfa = DenseDFA::new(r"(?-u:\b)ba(?-u:\b)").unwrap();
+        let got = search(&set, &dfa);
+        assert_eq!(got, vec!["ba", "x ba"]);
+
+        let dfa = DenseDFA::new(r"^ba").unwrap();
+        let got = search(&set, &dfa);
+        assert_eq!(got, vec!["ba"]);
+    }
+
+    #[test]
+    fn sized() {
+        let set =
+            Set::from_iter(&["a", "bar", "baz", "wat", "xba", "xbax", "z"])
+                .unwrap();
+        let dfa = dense::Builder::new().build_sized("ba.*").unwrap();
+        let got = search(&set, &dfa);
+        assert_eq!(got, vec!["bar", "baz", "xba", "xbax"]);
+    }
 }
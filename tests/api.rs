@@ -0,0 +1,1646 @@
+use std::borrow::Cow;
+use std::io;
+
+use regex;
+use regex_automata::backtrack::BoundedBacktracker;
+use regex_automata::hybrid::LazyDFA;
+use regex_automata::{
+    dense, peek, Archive, ArchiveBuilder, BuildWarning, DenseDFA,
+    DeserializeError, ErrorKind, Lexer, LexerBuilder, Match, MultiMatch,
+    OverlappingState, PatternID, Regex, RegexBuilder, SearchError,
+    SearchState, SparseDFA, StateID, DFA, FORMAT_VERSION, SEARCH_STATE_LEN,
+};
+
+#[test]
+fn find_iter_segments_spanning() {
+    let re = Regex::new(r"foo[0-9]+").unwrap();
+    let segments: &[&[u8]] = &[b"abc fo", b"o12", b"3 foo9"];
+    let matches: Vec<_> =
+        re.find_iter_segments(segments.iter().cloned()).collect();
+    assert_eq!(matches, vec![((0, 4), (2, 1)), ((2, 2), (2, 6))]);
+}
+
+#[test]
+fn find_iter_segments_empty() {
+    let re = Regex::new(r"a*").unwrap();
+    let none: &[&[u8]] = &[];
+    assert_eq!(0, re.find_iter_segments(none.iter().cloned()).count());
+
+    let empty: &[&[u8]] = &[b"", b""];
+    let matches: Vec<_> =
+        re.find_iter_segments(empty.iter().cloned()).collect();
+    assert_eq!(matches, vec![((0, 0), (0, 0))]);
+
+    let gaps: &[&[u8]] = &[b"", b"a", b"", b"b", b""];
+    let matches: Vec<_> =
+        re.find_iter_segments(gaps.iter().cloned()).collect();
+    assert_eq!(matches, vec![((1, 0), (1, 1)), ((3, 1), (3, 1))]);
+}
+
+#[test]
+fn which_overlapping_matches_agrees_with_regex_set() {
+    let patterns =
+        &[r"[a-z]+", r"[0-9]+", r"foo|foobar", r"bar$", r"^x", r"\w+@\w+"];
+    let haystacks: &[&str] =
+        &["", "foobar", "x 123", "abc@xyz bar", "xbar", "@@", "9 foo"];
+    let set = regex::RegexSet::new(patterns).unwrap();
+    let dfa = dense::Builder::new().all_matches(true).build_many(patterns);
+    let dfa = dfa.unwrap();
+    for haystack in haystacks {
+        let expected: Vec<usize> = set.matches(haystack).into_iter().collect();
+        let got = dfa.which_overlapping_matches(haystack.as_bytes());
+        assert_eq!(expected, got.iter().collect::<Vec<_>>(), "{:?}", haystack);
+        assert_eq!(expected.len(), got.len());
+    }
+}
+
+#[test]
+fn regex_with_mixed_dfas() {
+    let patterns = &[r"[a-z]+[0-9]", r"foo|foobar", r"a*", r"(?i)Σ+\w+"];
+    let text = "xyz abc9 foobar aaa σΣx".as_bytes();
+    for pattern in patterns {
+        let re = Regex::new(pattern).unwrap();
+        let expected: Vec<Match> = re.find_iter(text).collect();
+
+        let rev = re.reverse().to_sparse().unwrap();
+        let mixed = Regex::from_dfas(re.forward().clone(), rev.clone());
+        assert_eq!(expected, mixed.find_iter(text).collect::<Vec<_>>());
+
+        let lazy = LazyDFA::new(pattern).unwrap();
+        let mut cache = lazy.new_cache();
+        let hybrid = Regex::from_dfas(lazy.with_cache(&mut cache), rev);
+        assert_eq!(expected, hybrid.find_iter(text).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn find_iter_segments_agrees_with_find_iter() {
+    let patterns = &[r"[a-z]+", r"a*", r"\w+\s+\w+", r"z|", r"\b?"];
+    let text = b"ab cd  efg zz hij klm";
+    for pattern in patterns {
+        let re = match Regex::new(pattern) {
+            Ok(re) => re,
+            Err(_) => continue,
+        };
+        let expected: Vec<Match> = re.find_iter(text).collect();
+        for size in 1..5 {
+            let segments: Vec<&[u8]> = text.chunks(size).collect();
+            let got: Vec<Match> = re
+                .find_iter_segments(segments.iter().cloned())
+                .map(|((ss, so), (es, eo))| {
+                    Match::new(ss * size + so, es * size + eo)
+                })
+                .collect();
+            assert_eq!(
+                expected, got,
+                "pattern: {:?}, size: {}",
+                pattern, size
+            );
+        }
+    }
+}
+
+#[test]
+fn find_iter_min_len() {
+    let re = Regex::new(r"[0-9]+").unwrap();
+    let matches: Vec<_> = re.find_iter_min_len(b"7 a 123", 2).collect();
+    assert_eq!(matches, vec![Match::new(4, 7)]);
+
+    // A rejected short match must not hide a longer match that overlaps it.
+    let re = Regex::new(r"ab|b+").unwrap();
+    let matches: Vec<_> = re.find_iter_min_len(b"abbb", 3).collect();
+    assert_eq!(matches, vec![Match::new(1, 4)]);
+
+    let re = Regex::new(r"a*").unwrap();
+    let text = b"baab";
+    let all: Vec<_> = re.find_iter(text).collect();
+    let min: Vec<_> = re.find_iter_min_len(text, 0).collect();
+    assert_eq!(all, min);
+}
+
+#[test]
+fn serialized_header_contains_format_version() {
+    let dfa = DenseDFA::new("foo[0-9]+").unwrap();
+    let sparse = dfa.to_sparse().unwrap();
+    let blobs = vec![
+        dfa.to_bytes_native_endian().unwrap(),
+        sparse.to_bytes_native_endian().unwrap(),
+    ];
+    for bytes in blobs {
+        // The header starts with a NUL terminated label, followed by a u16
+        // endianness check and then the u16 format version.
+        let label_len = bytes.iter().position(|&b| b == 0).unwrap() + 1;
+        let at = label_len + 2;
+        let version = u16::from_ne_bytes([bytes[at], bytes[at + 1]]);
+        assert_eq!(FORMAT_VERSION, version);
+    }
+}
+
+#[test]
+fn match_accessors() {
+    let re = Regex::new(r"[0-9]+").unwrap();
+    let text = b"abc123";
+    let m = re.find(text).unwrap();
+    assert_eq!((3, 6), (m.start(), m.end()));
+    assert_eq!(b"123", &text[m.range()]);
+    assert_eq!(3, m.len());
+    assert!(!m.is_empty());
+    assert!(Match::new(2, 2).is_empty());
+
+    let m = MultiMatch::new(1, 3, 6);
+    assert_eq!(1, m.pattern());
+    assert_eq!(3..6, m.range());
+    assert_eq!(Match::new(3, 6), m.to_match());
+}
+
+#[test]
+fn replace_agrees_with_regex_crate() {
+    let patterns = &[r"[0-9]+", r"a*", r"", r"\w+\s*", r"x|"];
+    let haystacks: &[&[u8]] = &[b"", b"abc 123 aa9", b"aaa", b"xyx"];
+    for pattern in patterns {
+        let re = Regex::new(pattern).unwrap();
+        let expected = regex::bytes::Regex::new(pattern).unwrap();
+        for &haystack in haystacks {
+            for limit in 0..4 {
+                assert_eq!(
+                    expected.replacen(haystack, limit, &b"<>"[..]),
+                    re.replacen(haystack, limit, "<>"),
+                    "pattern: {:?}, haystack: {:?}, limit: {}",
+                    pattern,
+                    haystack,
+                    limit,
+                );
+            }
+            assert_eq!(
+                expected
+                    .replace_all(haystack, |c: &regex::bytes::Captures| {
+                        c[0].to_ascii_uppercase()
+                    }),
+                re.replace_all(haystack, |m: &[u8]| m.to_ascii_uppercase()),
+            );
+            assert_eq!(
+                expected.replace(haystack, &b"-"[..]),
+                re.replace(haystack, &b"-"[..]),
+            );
+        }
+    }
+
+    // Nothing is copied when there is nothing to replace.
+    let re = Regex::new(r"[0-9]+").unwrap();
+    match re.replace_all(b"abc", "x") {
+        Cow::Borrowed(b) => assert_eq!(b"abc", b),
+        Cow::Owned(_) => panic!("expected borrowed bytes"),
+    }
+}
+
+#[test]
+fn split_agrees_with_regex_crate() {
+    let patterns = &[r",", r"[ \t]+", r"a*", r"", r"x|"];
+    let haystacks: &[&[u8]] = &[b"", b",", b"a,b,,c,", b"aa b\t a", b"xyx"];
+    for pattern in patterns {
+        let re = Regex::new(pattern).unwrap();
+        let expected = regex::bytes::Regex::new(pattern).unwrap();
+        for &haystack in haystacks {
+            assert_eq!(
+                expected.split(haystack).collect::<Vec<_>>(),
+                re.split(haystack).collect::<Vec<_>>(),
+                "pattern: {:?}, haystack: {:?}",
+                pattern,
+                haystack,
+            );
+            for limit in 0..5 {
+                assert_eq!(
+                    expected.splitn(haystack, limit).collect::<Vec<_>>(),
+                    re.splitn(haystack, limit).collect::<Vec<_>>(),
+                    "pattern: {:?}, haystack: {:?}, limit: {}",
+                    pattern,
+                    haystack,
+                    limit,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn count_agrees_with_find_iter() {
+    let patterns =
+        &[r"[a-z]+", r"a*", r"", r"b|", r"(?-u:\b)", r"(?m)^\w*$", r"foo"];
+    let haystacks: &[&[u8]] =
+        &[b"", b"aaa", b"baaab", b"foo bar\nfoofoo", "β β\n".as_bytes()];
+    for pattern in patterns {
+        for &prefilter in &[false, true] {
+            let re = RegexBuilder::new()
+                .prefilter(prefilter)
+                .build(pattern)
+                .unwrap();
+            for &haystack in haystacks {
+                assert_eq!(
+                    re.find_iter(haystack).count(),
+                    re.count(haystack),
+                    "pattern: {:?}, haystack: {:?}",
+                    pattern,
+                    haystack,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn reverse_suffix_agrees_with_forward_search() {
+    let patterns = &[
+        r".*\.tar\.gz",
+        r"[a-z]+\.gz",
+        r"[a-z]*(?:\.t|\.tar)\.gz$",
+        r"(?-u:\b)[a-z]+\.gz",
+        r"(?m)^\w+aa",
+        r"[0-9]+ab|c*ab",
+    ];
+    let haystacks: &[&[u8]] = &[
+        b"",
+        b"a.gz",
+        b"x.tar.gz",
+        b"1.gz b.tar.gz\nc.t.gz",
+        b"..gz a.gz.gz",
+        b"aaaaa baa\n1aaa",
+        b"12ab 3cab ab",
+    ];
+    for pattern in patterns {
+        let re = Regex::new(pattern).unwrap();
+        assert!(re.prefilter().unwrap().is_suffix());
+        let plain =
+            RegexBuilder::new().prefilter(false).build(pattern).unwrap();
+        for &haystack in haystacks {
+            for end in 0..haystack.len() + 1 {
+                for start in 0..end + 1 {
+                    assert_eq!(
+                        plain.is_match_in(haystack, start, end),
+                        re.is_match_in(haystack, start, end),
+                        "pattern: {:?}, haystack: {:?}, range: {}..{}",
+                        pattern,
+                        haystack,
+                        start,
+                        end,
+                    );
+                }
+                assert_eq!(
+                    plain.shortest_match_at(haystack, end),
+                    re.shortest_match_at(haystack, end),
+                    "pattern: {:?}, haystack: {:?}, start: {}",
+                    pattern,
+                    haystack,
+                    end,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn find_bounded() {
+    let re = Regex::new(r"[0-9]+").unwrap();
+    assert_eq!(Ok(Some(Match::new(3, 6))), re.find_bounded(b"abc123", 6));
+    assert_eq!(Ok(None), re.find_bounded(b"abcdef", 6));
+    assert_eq!(
+        Err(SearchError::InputTooLong { max: 5, got: 6 }),
+        re.find_bounded(b"abc123", 5),
+    );
+}
+
+#[test]
+fn find_iter_records() {
+    let re = Regex::new(r"[0-9]+").unwrap();
+    // Without records, "1234" would match across records 0 and 1.
+    let text = b"ab1234cd56789";
+    let matches: Vec<_> = re.find_iter_records(text, 4).collect();
+    assert_eq!(
+        matches,
+        vec![
+            Match::new(2, 4),
+            Match::new(4, 6),
+            Match::new(8, 12),
+            Match::new(12, 13)
+        ]
+    );
+
+    let re = Regex::new(r"(?-u:\x00)ab").unwrap();
+    let matches: Vec<_> = re.find_iter_records(b"\x00ab \x00ab", 4).collect();
+    assert_eq!(matches, vec![Match::new(0, 3), Match::new(4, 7)]);
+    assert_eq!(None, re.find_iter_records(b"x\x00ab", 3).next());
+}
+
+#[test]
+fn find_iter_prioritized() {
+    let re = RegexBuilder::new()
+        .build_prioritized(&[
+            (1, r"[a-z]+"),
+            (5, r"key=[a-z0-9]+"),
+            (3, r"[0-9]{3,}"),
+        ])
+        .unwrap();
+    assert_eq!(3, re.pattern_count());
+    assert_eq!(5, re.priority(1));
+
+    // The high priority key pattern suppresses the words and numbers inside
+    // of it, while the word pattern still matches elsewhere.
+    let text = b"abc key=xy12345 de 9876";
+    let matches: Vec<_> = re.find_iter_prioritized(text).collect();
+    assert_eq!(
+        matches,
+        vec![
+            MultiMatch::new(0, 0, 3),
+            MultiMatch::new(1, 4, 15),
+            MultiMatch::new(0, 16, 18),
+            MultiMatch::new(2, 19, 23)
+        ]
+    );
+
+    // Flipping priorities lets the word pattern win instead, which in turn
+    // suppresses the overlapping key match.
+    let re = RegexBuilder::new()
+        .build_prioritized(&[(5, r"[a-z]+"), (1, r"key=[a-z0-9]+")])
+        .unwrap();
+    let matches: Vec<_> = re.find_iter_prioritized(b"key=abc").collect();
+    assert_eq!(
+        matches,
+        vec![MultiMatch::new(0, 0, 3), MultiMatch::new(0, 4, 7)]
+    );
+}
+
+#[test]
+fn find_iter_prioritized_ties() {
+    // Equal priorities prefer the leftmost match, then the longest match,
+    // then the pattern given first.
+    let re = RegexBuilder::new()
+        .build_prioritized(&[(1, r"bc"), (1, r"abc"), (1, r"a"), (1, r"ab")])
+        .unwrap();
+    let matches: Vec<_> = re.find_iter_prioritized(b"abc").collect();
+    assert_eq!(matches, vec![MultiMatch::new(1, 0, 3)]);
+
+    // As with find_iter, an empty match is never reported immediately after
+    // another match.
+    let re = RegexBuilder::new()
+        .build_prioritized(&[(1, r"xy"), (1, r"xy"), (0, r"")])
+        .unwrap();
+    let matches: Vec<_> = re.find_iter_prioritized(b"xy-").collect();
+    assert_eq!(
+        matches,
+        vec![MultiMatch::new(0, 0, 2), MultiMatch::new(2, 3, 3)]
+    );
+}
+
+#[test]
+fn find_iter_prioritized_dfa() {
+    // The DFA of a prioritized regex orders the patterns matching in each
+    // state by priority, just like build_many_prioritized.
+    let patterns = &[(1, r"[a-z]+"), (2, r"if")];
+    let re = RegexBuilder::new().build_prioritized(patterns).unwrap();
+    let dfa = dense::Builder::new()
+        .anchored(true)
+        .all_matches(true)
+        .build_many_prioritized(patterns)
+        .unwrap();
+    let got: Vec<_> = re.dfa().find_overlapping_iter(b"if").collect();
+    let expected: Vec<_> = dfa.find_overlapping_iter(b"if").collect();
+    assert_eq!(got, vec![(0, 1), (1, 2), (0, 2)]);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn find_iter_prioritized_cut_short() {
+    // A lower priority match gives way to a higher priority match beginning
+    // inside of it, but a shorter match of the lower priority pattern ending
+    // before it is still reported.
+    let re = RegexBuilder::new()
+        .build_prioritized(&[(1, r"[a-z]+"), (5, r"key=[a-z0-9]+")])
+        .unwrap();
+    let matches: Vec<_> = re.find_iter_prioritized(b"xkey=abc").collect();
+    assert_eq!(
+        matches,
+        vec![MultiMatch::new(0, 0, 1), MultiMatch::new(1, 1, 8)]
+    );
+
+    // Every pattern is searched again from the end of each match, so the
+    // rest of a longer lower priority match is still found.
+    let re = RegexBuilder::new()
+        .build_prioritized(&[(10, r"key"), (1, r"[a-z]+")])
+        .unwrap();
+    let matches: Vec<_> = re.find_iter_prioritized(b"keyboard").collect();
+    assert_eq!(
+        matches,
+        vec![MultiMatch::new(0, 0, 3), MultiMatch::new(1, 3, 8)]
+    );
+
+    // Look-around is evaluated against the whole input.
+    let re = RegexBuilder::new()
+        .build_prioritized(&[(1, r"[a-z]+"), (5, r"(?-u:\b)id(?-u:\b)")])
+        .unwrap();
+    let matches: Vec<_> = re.find_iter_prioritized(b"idx id").collect();
+    assert_eq!(
+        matches,
+        vec![MultiMatch::new(0, 0, 3), MultiMatch::new(1, 4, 6)]
+    );
+}
+
+#[test]
+fn find_fueled() {
+    let re = Regex::new(r"(?s).*x.*y").unwrap();
+    let mut text = vec![b'a'; 10_000];
+    assert_eq!(Ok(None), re.find_fueled(&text, 10_000));
+    assert_eq!(
+        Err(SearchError::OutOfFuel { offset: 100 }),
+        re.find_fueled(&text, 100),
+    );
+
+    // The reverse search consumes fuel too.
+    text.push(b'x');
+    text.push(b'y');
+    let len = text.len();
+    assert_eq!(Ok(Some(Match::new(0, len))), re.find_fueled(&text, 2 * len));
+    assert_eq!(
+        Err(SearchError::OutOfFuel { offset: 1 }),
+        re.find_fueled(&text, 2 * len - 2),
+    );
+}
+
+#[test]
+fn to_owned_outlives_bytes() {
+    fn load_dense(pattern: &str) -> DenseDFA<Vec<u16>, u16> {
+        let bytes = DenseDFA::new(pattern)
+            .unwrap()
+            .to_u16()
+            .unwrap()
+            .to_bytes_native_endian()
+            .unwrap();
+        let dfa: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        dfa.to_owned()
+    }
+
+    fn load_sparse(pattern: &str) -> SparseDFA<Vec<u8>, u16> {
+        let bytes = SparseDFA::new(pattern)
+            .unwrap()
+            .to_u16()
+            .unwrap()
+            .to_bytes_native_endian()
+            .unwrap();
+        let dfa: SparseDFA<&[u8], u16> =
+            unsafe { SparseDFA::from_bytes(&bytes) };
+        dfa.to_owned()
+    }
+
+    let dense = load_dense(r"foo[0-9]+");
+    assert_eq!(Some(8), dense.find(b"foo12345"));
+    assert_eq!(None, dense.find(b"foo"));
+
+    let sparse = load_sparse(r"foo[0-9]+");
+    assert_eq!(Some(8), sparse.find(b"foo12345"));
+    assert_eq!(None, sparse.find(b"foo"));
+}
+
+#[test]
+fn peek_header() {
+    let dfa = dense::Builder::new()
+        .anchored(true)
+        .build("foo|bar[0-9]+|quux")
+        .unwrap()
+        .to_u32()
+        .unwrap();
+    let bytes = dfa.to_bytes_native_endian().unwrap();
+    let info = peek(&bytes).unwrap();
+    assert!(!info.is_sparse());
+    assert!(info.is_anchored());
+    assert!(info.is_premultiplied());
+    assert_eq!(4, info.state_id_size());
+    assert_eq!(dfa.state_count(), info.state_count());
+    assert_eq!(1, info.pattern_count());
+    assert_eq!(Some(bytes.len()), info.serialized_len());
+    assert!(info.checksum().is_some());
+    assert_eq!(dfa.transition_table_bytes(), bytes.len() - info.header_len());
+    // Only the header is needed.
+    assert_eq!(info, peek(&bytes[..info.header_len()]).unwrap());
+
+    let sparse = dfa.to_sparse().unwrap();
+    let bytes = sparse.to_bytes_native_endian().unwrap();
+    let info = peek(&bytes).unwrap();
+    assert!(info.is_sparse());
+    assert!(!info.is_premultiplied());
+    assert_eq!(None, info.checksum());
+    assert_eq!(dfa.state_count(), info.state_count());
+    assert_eq!(None, info.serialized_len());
+}
+
+#[test]
+fn peek_errors() {
+    let bytes = DenseDFA::new("a").unwrap().to_bytes_native_endian().unwrap();
+    assert_eq!(Err(DeserializeError::InvalidLabel), peek(b"nope"));
+    assert_eq!(
+        Err(DeserializeError::BufferTooSmall { needed: 512, got: 100 }),
+        peek(&bytes[..100]),
+    );
+
+    let mut bad_version = bytes.clone();
+    bad_version[26] = 0xFF;
+    bad_version[27] = 0xFF;
+    assert_eq!(
+        Err(DeserializeError::UnsupportedVersion {
+            expected: FORMAT_VERSION,
+            found: 0xFFFF,
+        }),
+        peek(&bad_version),
+    );
+
+    let mut bad_endian = bytes.clone();
+    bad_endian.swap(24, 25);
+    assert_eq!(Err(DeserializeError::EndiannessMismatch), peek(&bad_endian));
+
+    // Unknown required features are rejected, but unknown optional features
+    // are ignored.
+    let flags = u16::from_ne_bytes([bytes[30], bytes[31]]);
+    let mut unknown = bytes.clone();
+    unknown[30..32].copy_from_slice(&(flags | 0x0080).to_ne_bytes());
+    assert_eq!(
+        Err(DeserializeError::UnsupportedFeatures { flags: 0x0080 }),
+        peek(&unknown),
+    );
+    unknown[30..32].copy_from_slice(&(flags | 0x8000).to_ne_bytes());
+    assert_eq!(peek(&bytes).unwrap(), peek(&unknown).unwrap());
+}
+
+#[test]
+fn build_many() {
+    let dfa = dense::Builder::new().build_many(&["foo", "foo[a-z]+"]).unwrap();
+    assert_eq!(2, dfa.pattern_count());
+    // Leftmost first semantics prefer the pattern given first.
+    assert_eq!(Some((0, 4)), dfa.find_pattern(b"xfoobar"));
+    assert_eq!(None, dfa.find_pattern(b"fo"));
+
+    // Longest match semantics report every pattern that matches.
+    let dfa = dense::Builder::new()
+        .anchored(true)
+        .longest_match(true)
+        .build_many(&["[a-z]+", "foo", "[0-9]"])
+        .unwrap();
+    let mut state = dfa.start_state();
+    for &b in b"foo" {
+        state = dfa.next_state(state, b);
+    }
+    assert!(dfa.is_match_state(state));
+    assert_eq!(2, dfa.match_count(state));
+    assert_eq!(0, dfa.match_pattern(state, 0));
+    assert_eq!(1, dfa.match_pattern(state, 1));
+    assert_eq!(Some((2, 1)), dfa.find_pattern(b"9"));
+
+    let dfa = dense::Builder::new().build_many(&[]).unwrap();
+    assert_eq!(0, dfa.pattern_count());
+    assert_eq!(None, dfa.find_pattern(b"foo"));
+}
+
+#[test]
+fn build_forward_and_reverse_separately() {
+    let mut builder = RegexBuilder::new();
+    builder.minimize(true);
+    let pattern = r"[a-z]+[0-9]*";
+    let re = builder.build(pattern).unwrap();
+    let rev = builder.build_reverse(pattern).unwrap();
+    assert!(rev.is_anchored());
+    assert_eq!(re.reverse().to_text(), rev.to_text());
+
+    let fwd_bytes = builder
+        .build_forward_with_size::<u32>(pattern)
+        .unwrap()
+        .to_bytes_native_endian()
+        .unwrap();
+    let rev_bytes = rev.to_u32().unwrap().to_bytes_native_endian().unwrap();
+    let fwd: DenseDFA<&[u32], u32> =
+        DenseDFA::try_from_bytes(&fwd_bytes).unwrap();
+    let rev: DenseDFA<&[u32], u32> =
+        DenseDFA::try_from_bytes(&rev_bytes).unwrap();
+    let loaded = Regex::from_dfas(fwd, rev);
+    let haystack = b"12 abc34 - xy";
+    let expected: Vec<_> = re.find_iter(haystack).collect();
+    assert_eq!(vec![Match::new(3, 8), Match::new(11, 13)], expected);
+    assert_eq!(expected, loaded.find_iter(haystack).collect::<Vec<_>>());
+}
+
+#[test]
+fn find_overlapping() {
+    let dfa = dense::Builder::new()
+        .all_matches(true)
+        .build_many(&["a*", "ab", "b"])
+        .unwrap();
+    let matches: Vec<_> = dfa.find_overlapping_iter(b"aab").collect();
+    assert_eq!(matches, vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 3), (2, 3)]);
+
+    // Saving the state and resuming it later picks up where it left off.
+    let mut state = OverlappingState::start();
+    assert_eq!(None, state.offset());
+    assert_eq!(Some((0, 1)), dfa.find_overlapping_at(b"aab", 1, &mut state));
+    assert_eq!(Some((0, 2)), dfa.find_overlapping_at(b"aab", 1, &mut state));
+    assert_eq!(Some((0, 3)), dfa.find_overlapping_at(b"aab", 1, &mut state));
+    let mut saved = state;
+    assert_eq!(Some(3), saved.offset());
+    assert_eq!(Some((1, 3)), dfa.find_overlapping_at(b"aab", 1, &mut state));
+    assert_eq!(Some((2, 3)), dfa.find_overlapping_at(b"aab", 1, &mut state));
+    assert_eq!(None, dfa.find_overlapping_at(b"aab", 1, &mut state));
+    assert_eq!(None, dfa.find_overlapping_at(b"aab", 1, &mut state));
+    assert_eq!(Some((1, 3)), dfa.find_overlapping_at(b"aab", 1, &mut saved));
+
+    // An anchored DFA stops as soon as no pattern can match anymore.
+    let dfa = dense::Builder::new()
+        .anchored(true)
+        .all_matches(true)
+        .build_many(&["[a-z]+", "[a-z]{2}"])
+        .unwrap();
+    let matches: Vec<_> = dfa.find_overlapping_iter(b"abc1d").collect();
+    assert_eq!(matches, vec![(0, 1), (0, 2), (1, 2), (0, 3)]);
+    let mut state = OverlappingState::start();
+    assert_eq!(None, dfa.find_overlapping_at(b"abc", 1, &mut state));
+}
+
+#[test]
+fn find_iter_empty_matches() {
+    // Every DFA should agree with the end offsets reported by a regex.
+    let cases: &[(&str, &[u8])] = &[
+        ("a*", b""),
+        ("a*", b"aaa"),
+        ("a*", b"baaab"),
+        ("", b"abc"),
+        ("b|", b"abb"),
+        ("[a-z]+", b"ab 12 cd"),
+        ("\\w*", "β β".as_bytes()),
+    ];
+    for &(pattern, haystack) in cases {
+        let re = Regex::new(pattern).unwrap();
+        let expected: Vec<usize> =
+            re.find_iter(haystack).map(|m| m.end()).collect();
+        let dense = re.forward();
+        assert_eq!(expected, dense.find_iter(haystack).collect::<Vec<_>>());
+        let sparse = dense.to_sparse().unwrap();
+        assert_eq!(expected, sparse.find_iter(haystack).collect::<Vec<_>>());
+    }
+
+    // Since `a*` is preferred over `b`, it matches the empty string
+    // before each `b`, and `b` itself is never reported.
+    let dfa = dense::Builder::new().build_many(&["a*", "b"]).unwrap();
+    let matches: Vec<_> = dfa.find_leftmost_iter(b"aabba").collect();
+    assert_eq!(matches, vec![(0, 2), (0, 3), (0, 5)]);
+}
+
+#[test]
+fn find_chunks() {
+    let cases: &[(&str, &[u8])] = &[
+        ("foo[0-9]+", b"xyz foo123 foo4"),
+        ("foo[0-9]+", b"xyz foo"),
+        ("a*", b"aaab"),
+        ("", b"abc"),
+        ("[a-z]+", b""),
+        ("\\w+", "123 βγ".as_bytes()),
+    ];
+    for &(pattern, haystack) in cases {
+        let dfa = dense::Builder::new().build(pattern).unwrap();
+        let expected = dfa.find(haystack);
+        // Every way of splitting the haystack in two should agree.
+        for i in 0..haystack.len() + 1 {
+            let mut state = SearchState::start();
+            let got = dfa
+                .find_chunk(&haystack[..i], &mut state)
+                .or_else(|| dfa.find_chunk(&haystack[i..], &mut state))
+                .or_else(|| state.last_match());
+            assert_eq!(expected, got, "{:?} split at {}", pattern, i);
+        }
+        assert_eq!(expected, dfa.find_reader(OneByte::new(haystack)).unwrap());
+        assert_eq!(
+            dfa.is_match(haystack),
+            dfa.is_match_reader(OneByte::new(haystack)).unwrap()
+        );
+    }
+}
+
+#[test]
+fn find_reader_stops_early() {
+    let dfa = dense::Builder::new().anchored(true).build("[a-z]+").unwrap();
+    let mut rdr = OneByte::new(b"abc1def");
+    assert_eq!(Some(3), dfa.find_reader(&mut rdr).unwrap());
+    assert_eq!(b"def", rdr.bytes);
+
+    let mut rdr = OneByte::new(b"123abc");
+    assert_eq!(None, dfa.find_reader(&mut rdr).unwrap());
+    assert_eq!(b"23abc", rdr.bytes);
+}
+
+#[test]
+fn quit_bytes() {
+    let dfa = dense::Builder::new()
+        .quit(b'\n', true)
+        .build(r"[^x]*bar")
+        .unwrap()
+        .to_u16()
+        .unwrap();
+    assert!(dfa.is_quit_byte(b'\n'));
+    assert!(!dfa.is_quit_byte(b'x'));
+    assert_eq!(Some(4), dfa.find(b"xbar\nbar"));
+    assert_eq!(None, dfa.find(b"foo\nbar"));
+    assert_eq!(Ok(Some(4)), dfa.try_find(b"xbar\nbar"));
+    assert_eq!(
+        Err(SearchError::Quit { byte: b'\n', offset: 3 }),
+        dfa.try_find(b"foo\nbar"),
+    );
+    assert_eq!(Ok(None), dfa.try_find(b"foo"));
+
+    let bytes = dfa.to_bytes_native_endian().unwrap();
+    let loaded: DenseDFA<&[u16], u16> =
+        unsafe { DenseDFA::from_bytes(&bytes) };
+    assert!(loaded.is_quit_byte(b'\n'));
+    assert_eq!(None, loaded.find(b"foo\nbar"));
+
+    let sparse = dfa.to_sparse().unwrap();
+    assert!(sparse.is_quit_byte(b'\n'));
+    assert_eq!(None, sparse.find(b"foo\nbar"));
+    let bytes = sparse.to_bytes_native_endian().unwrap();
+    let loaded: SparseDFA<&[u8], u16> =
+        unsafe { SparseDFA::from_bytes(&bytes) };
+    assert!(loaded.is_quit_byte(b'\n'));
+    assert_eq!(
+        Err(SearchError::Quit { byte: b'\n', offset: 3 }),
+        loaded.try_find(b"foo\nbar"),
+    );
+}
+
+#[test]
+fn search_state_bytes() {
+    let dfa =
+        dense::Builder::new().build("foo[0-9]+").unwrap().to_u16().unwrap();
+    let haystack = b"xyz foo123 foo4";
+    let expected = dfa.find(haystack);
+    for i in 0..haystack.len() + 1 {
+        let mut state = SearchState::start();
+        let got = dfa.find_chunk(&haystack[..i], &mut state).or_else(|| {
+            let bytes = state.to_bytes();
+            let mut resumed = SearchState::<u16>::from_bytes(&bytes).unwrap();
+            assert_eq!(state, resumed);
+            dfa.find_chunk(&haystack[i..], &mut resumed)
+                .or_else(|| resumed.last_match())
+        });
+        assert_eq!(expected, got, "split at {}", i);
+    }
+
+    let bytes = SearchState::<u16>::start().to_bytes();
+    assert_eq!(SEARCH_STATE_LEN, bytes.len());
+    assert_eq!(
+        Err(DeserializeError::StateIDSizeMismatch { expected: 4, found: 2 }),
+        SearchState::<u32>::from_bytes(&bytes)
+    );
+    assert_eq!(
+        Err(DeserializeError::BufferTooSmall {
+            needed: SEARCH_STATE_LEN,
+            got: SEARCH_STATE_LEN - 1
+        }),
+        SearchState::<u16>::from_bytes(&bytes[..SEARCH_STATE_LEN - 1])
+    );
+    assert_eq!(
+        Err(DeserializeError::InvalidLabel),
+        SearchState::<u16>::from_bytes(&bytes[1..])
+    );
+    // A search that hasn't begun can't have consumed any input.
+    let mut bad = bytes;
+    bad[SEARCH_STATE_LEN - 16] = 1;
+    assert_eq!(
+        Err(DeserializeError::InvalidSearchState),
+        SearchState::<u16>::from_bytes(&bad)
+    );
+    // A state identifier must fit in the chosen representation.
+    let mut bad = bytes;
+    bad[SEARCH_STATE_LEN - 24 + 2] = 1;
+    assert_eq!(
+        Err(DeserializeError::InvalidStateID { id: 1 << 16 }),
+        SearchState::<u16>::from_bytes(&bad)
+    );
+}
+
+/// A reader that yields one byte at a time, and is interrupted before each.
+struct OneByte<'a> {
+    bytes: &'a [u8],
+    interrupt: bool,
+}
+
+impl<'a> OneByte<'a> {
+    fn new(bytes: &'a [u8]) -> OneByte<'a> {
+        OneByte { bytes, interrupt: true }
+    }
+}
+
+impl<'a> io::Read for OneByte<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if !self.interrupt {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "again"));
+        }
+        if self.bytes.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.bytes[0];
+        self.bytes = &self.bytes[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn memory_usage_breakdown() {
+    let dfa =
+        dense::Builder::new().build_many(&["foo[0-9]+", "[a-z]+ing"]).unwrap();
+    let usage = dfa.memory_usage_breakdown();
+    assert_eq!(dfa.memory_usage(), usage.heap());
+    assert_eq!(dfa.transition_table_bytes(), usage.transitions());
+    assert!(usage.matches() > 0);
+    assert!(usage.total() > usage.heap());
+
+    let sparse = SparseDFA::new("foo[0-9]+").unwrap();
+    let usage = sparse.memory_usage_breakdown();
+    assert_eq!(sparse.memory_usage(), usage.heap());
+    assert_eq!(0, usage.matches());
+}
+
+#[test]
+fn ascii_word_boundary() {
+    let re = Regex::new(r"(?-u:\b)foo(?-u:\b)").unwrap();
+    assert!(re.forward().has_lookaround());
+    assert_eq!(Some(Match::new(5, 8)), re.find(b"xfoo foo"));
+    // The bytes before the starting position are taken into account.
+    assert_eq!(None, re.find_at(b"xfoo", 1));
+    assert_eq!(Some(Match::new(5, 8)), re.find_at(b"xfoo foo", 1));
+    let matches: Vec<_> = re.find_iter(b"foo foo,foox").collect();
+    assert_eq!(matches, vec![Match::new(0, 3), Match::new(4, 7)]);
+
+    let dfa = dense::Builder::new().build(r"(?-u:\b)").unwrap();
+    assert_eq!(Some(0), dfa.find(b"ab"));
+    assert_eq!(Some(2), dfa.rfind(b"ab"));
+    assert_eq!(None, dfa.find(b""));
+    assert_eq!(Some(2), dfa.find_at(b"ab cd", 1));
+
+    let bytes = dfa.to_bytes_native_endian().unwrap();
+    let loaded: DenseDFA<&[usize], usize> =
+        DenseDFA::try_from_bytes(&bytes).unwrap();
+    assert!(loaded.has_lookaround());
+    assert_eq!(Some(2), loaded.find_at(b"ab cd", 1));
+}
+
+#[test]
+fn search_in_range_uses_surrounding_context() {
+    // Without look-around, searching a range is the same as searching the
+    // corresponding subslice.
+    let haystack = b"ab12 cd345 z";
+    for pattern in &[r"[a-z]+[0-9]*", r"[0-9]{2}", r"c|cd3"] {
+        let dfa = DenseDFA::new(pattern).unwrap();
+        let sparse = dfa.to_sparse().unwrap();
+        let re = RegexBuilder::new().prefilter(true).build(pattern).unwrap();
+        for end in 0..haystack.len() + 1 {
+            for start in 0..end + 1 {
+                let sub = &haystack[start..end];
+                let expected = dfa.find(sub).map(|i| start + i);
+                assert_eq!(expected, dfa.find_in(haystack, start, end));
+                assert_eq!(expected, sparse.find_in(haystack, start, end));
+                assert_eq!(
+                    dfa.is_match(sub),
+                    dfa.is_match_in(haystack, start, end)
+                );
+                assert_eq!(
+                    re.find(sub).map(|m| (start + m.start(), start + m.end())),
+                    re.find_in(haystack, start, end)
+                        .map(|m| (m.start(), m.end())),
+                );
+            }
+        }
+    }
+
+    // With look-around, the bytes on either side of the range matter.
+    let re = Regex::new(r"(?-u:\b)[a-z]+(?-u:\b)").unwrap();
+    let dfa = re.forward();
+    assert_eq!(Some(3), dfa.find_in(b"abc def", 0, 3));
+    assert_eq!(None, dfa.find_in(b"abc def", 0, 2));
+    assert_eq!(None, dfa.find_in(b"abc def", 1, 3));
+    assert!(!dfa.is_match_in(b"abcdef", 1, 5));
+    assert_eq!(Some(Match::new(4, 7)), re.find_in(b"abc def", 1, 7));
+    assert_eq!(None, re.find_in(b"abc defg", 1, 7));
+    assert_eq!(Some(4), re.reverse().rfind_in(b"abc def", 4, 7));
+
+    let re = Regex::new(r"(?m)^[0-9]+$").unwrap();
+    let haystack = b"12\n345\n6";
+    assert_eq!(Some(Match::new(3, 6)), re.find_in(haystack, 1, 8));
+    assert_eq!(None, re.find_in(haystack, 3, 5));
+    assert_eq!(None, re.find_in(haystack, 4, 6));
+    assert_eq!(Some(Match::new(7, 8)), re.find_in(haystack, 7, 8));
+
+    // Searching up to the end of the input is the same as `find_at`.
+    for start in 0..haystack.len() + 1 {
+        assert_eq!(
+            re.find_at(haystack, start),
+            re.find_in(haystack, start, haystack.len())
+        );
+    }
+}
+
+#[test]
+#[should_panic]
+fn search_in_range_rejects_invalid_range() {
+    let dfa = DenseDFA::new("a").unwrap();
+    dfa.find_in(b"aaa", 2, 1);
+}
+
+#[test]
+fn unsupported_word_boundary() {
+    for pattern in &[r"\b", r"\B"] {
+        let err = dense::Builder::new().build(pattern).unwrap_err();
+        match *err.kind() {
+            ErrorKind::Unsupported(_) => {}
+            ref kind => panic!("unexpected error kind: {:?}", kind),
+        }
+    }
+    let dfa = dense::Builder::new().build(r"(?-u:\b)").unwrap();
+    assert!(dfa.to_sparse().is_err());
+}
+
+#[test]
+fn structured_errors() {
+    let err = Regex::new("a(b").unwrap_err();
+    match *err.kind() {
+        ErrorKind::Syntax { ref message, ref span } => {
+            assert!(message.contains("unclosed group"));
+            assert_eq!(Some(1..2), *span);
+        }
+        ref kind => panic!("unexpected error kind: {:?}", kind),
+    }
+    assert_eq!(Some(1..2), err.span());
+    assert_eq!(None, err.size_limit());
+    // Translation errors have a span too.
+    let err = dense::Builder::new().build(r"(?-u)\xFF").unwrap_err();
+    assert_eq!(Some(5..9), err.span());
+
+    let err = dense::Builder::new()
+        .nfa_size_limit(Some(100))
+        .build(r"\w{10}")
+        .unwrap_err();
+    assert_eq!(Some(100), err.size_limit());
+    assert!(err.actual_size().unwrap() > 100);
+    assert_eq!(None, err.span());
+
+    let err = dense::Builder::new()
+        .build_with_size::<u8>(r"[a-z]{300}")
+        .unwrap_err();
+    match *err.kind() {
+        ErrorKind::StateIDOverflow { max, count } => {
+            assert_eq!(255, max);
+            assert_eq!(257, count);
+        }
+        ref kind => panic!("unexpected error kind: {:?}", kind),
+    }
+    assert_eq!(Some(257), err.state_count());
+}
+
+#[test]
+fn backtrack_unicode_word_boundary() {
+    let haystacks: &[&[u8]] =
+        &[b"foo bar", "δfoo foo δ".as_bytes(), "Σσ foo,βγ".as_bytes(), b""];
+    for pattern in &[r"\b", r"\b\w+\b", r"\Bo\B", r"\w\B\w"] {
+        let re = regex::bytes::Regex::new(pattern).unwrap();
+        let bt = BoundedBacktracker::new(pattern).unwrap();
+        let mut cache = bt.new_cache();
+        for &haystack in haystacks {
+            let expected =
+                re.find(haystack).map(|m| Match::new(m.start(), m.end()));
+            assert_eq!(
+                Ok(expected),
+                bt.find(&mut cache, haystack),
+                "find for {:?} on {:?}",
+                pattern,
+                haystack,
+            );
+        }
+    }
+}
+
+#[test]
+fn heuristic_unicode_word_boundary() {
+    let dfa = dense::Builder::new()
+        .unicode_word_boundary(true)
+        .build(r"\b\w+\b")
+        .unwrap();
+    assert!(dfa.is_quit_byte(0x80));
+    assert!(!dfa.is_quit_byte(b'a'));
+    assert_eq!(Ok(Some(3)), dfa.try_find(b"foo bar"));
+    assert_eq!(Ok(Some(7)), dfa.try_find_at(b"foo bar", 3));
+    assert_eq!(
+        Err(SearchError::Quit { byte: 0xCE, offset: 3 }),
+        dfa.try_find("foo\u{03B4}".as_bytes()),
+    );
+    // The byte before the start of the search is a quit byte too.
+    assert_eq!(
+        Err(SearchError::Quit { byte: 0xB4, offset: 1 }),
+        dfa.try_find_at("\u{03B4}foo".as_bytes(), 2),
+    );
+
+    // Patterns without Unicode word boundaries are unaffected.
+    let dfa = dense::Builder::new()
+        .unicode_word_boundary(true)
+        .build(r"(?-u:\b)foo")
+        .unwrap();
+    assert!(!dfa.is_quit_byte(0x80));
+}
+
+#[test]
+fn utf8_mode() {
+    let re = Regex::new("☃+").unwrap();
+    assert!(re.is_utf8());
+    assert!(re.is_match_str("snow ☃☃"));
+    assert_eq!(Some(Match::new(5, 11)), re.find_str("snow ☃☃"));
+
+    let re = RegexBuilder::new().utf8(false).build(r"(?-u:\xE2)").unwrap();
+    assert!(!re.is_utf8());
+    assert_eq!(Some(Match::new(5, 6)), re.find(b"snow \xE2\x98\x83"));
+}
+
+#[test]
+#[should_panic]
+fn find_str_requires_utf8_mode() {
+    let re = RegexBuilder::new().utf8(false).build("a").unwrap();
+    re.find_str("a");
+}
+
+#[test]
+fn utf8_mode_roundtrips() {
+    for &utf8 in &[true, false] {
+        let dfa = dense::Builder::new()
+            .utf8(utf8)
+            .build("a")
+            .unwrap()
+            .to_u16()
+            .unwrap();
+        assert_eq!(utf8, dfa.is_utf8());
+
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        assert_eq!(utf8, peek(&bytes).unwrap().is_utf8());
+        let got: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(utf8, got.is_utf8());
+
+        let sparse = dfa.to_sparse().unwrap();
+        assert_eq!(utf8, sparse.is_utf8());
+        let bytes = sparse.to_bytes_native_endian().unwrap();
+        assert_eq!(utf8, peek(&bytes).unwrap().is_utf8());
+    }
+}
+
+#[test]
+fn portable_roundtrip() {
+    let dfa =
+        dense::Builder::new().build(r"(?-u:\b)foo[0-9]+(?-u:\b)").unwrap();
+    let bytes = dfa.to_u16().unwrap().to_bytes_portable().unwrap();
+    // The bytes need not be aligned.
+    let mut unaligned = vec![0];
+    unaligned.extend_from_slice(&bytes);
+    let got: DenseDFA<Vec<u16>, u16> =
+        DenseDFA::from_bytes_portable(&unaligned[1..]).unwrap();
+    for &haystack in &[&b"foo123"[..], b"xfoo1 foo2", b"foo", b"a foo9!"] {
+        assert_eq!(dfa.find(haystack), got.find(haystack));
+    }
+
+    let bytes = dfa.to_bytes_portable().unwrap();
+    let got: DenseDFA<Vec<usize>, usize> =
+        DenseDFA::from_bytes_portable(&bytes).unwrap();
+    assert_eq!(Some(6), got.find(b"foo123"));
+
+    let mut bad = bytes.clone();
+    let last = bad.len() - 1;
+    bad[last] ^= 0x01;
+    match DenseDFA::<Vec<usize>, usize>::from_bytes_portable(&bad) {
+        Err(DeserializeError::ChecksumMismatch { .. })
+        | Err(DeserializeError::InvalidStateID { .. }) => {}
+        got => panic!("unexpected result: {:?}", got),
+    }
+
+    let big = dfa.to_bytes_big_endian().unwrap();
+    if cfg!(target_endian = "little") {
+        assert_eq!(
+            Some(DeserializeError::EndiannessMismatch),
+            DenseDFA::<Vec<usize>, usize>::from_bytes_portable(&big).err(),
+        );
+    }
+}
+
+#[test]
+fn compact_roundtrip() {
+    let patterns = &[r"foo[0-9]+", r"\w+@\w+\.com", r"[a-z]{2,5}|bar|baz"];
+    let haystacks: &[&[u8]] =
+        &[b"foo123", b"xfoo1 foo2", b"me@mail.com", b"b", b"bazooka!"];
+    for pattern in patterns {
+        let dfa = SparseDFA::new(pattern).unwrap().to_u64().unwrap();
+        let bytes = dfa.to_bytes_compact().unwrap();
+        let fixed = dfa.to_bytes_native_endian().unwrap();
+        // The headers are the same size, so compare the transition tables.
+        let header_len = peek(&fixed).unwrap().header_len();
+        assert!(
+            (bytes.len() - header_len) * 2 < fixed.len() - header_len,
+            "{}",
+            pattern,
+        );
+
+        // The bytes need not be aligned.
+        let mut unaligned = vec![0];
+        unaligned.extend_from_slice(&bytes);
+        let got: SparseDFA<Vec<u8>, u64> =
+            SparseDFA::from_bytes_compact(&unaligned[1..]).unwrap();
+        for &haystack in haystacks {
+            assert_eq!(dfa.find(haystack), got.find(haystack));
+        }
+        assert_eq!(&*fixed, &*got.to_bytes_native_endian().unwrap());
+    }
+}
+
+#[test]
+fn compact_errors() {
+    let dfa = SparseDFA::new(r"foo[0-9]+").unwrap().to_u16().unwrap();
+    let bytes = dfa.to_bytes_compact().unwrap();
+    if cfg!(target_endian = "little") {
+        assert!(peek(&bytes).unwrap().is_compact());
+    }
+    assert_eq!(
+        Some(DeserializeError::StateIDSizeMismatch { expected: 4, found: 2 }),
+        SparseDFA::<Vec<u8>, u32>::from_bytes_compact(&bytes).err(),
+    );
+    assert_eq!(
+        Some(DeserializeError::InvalidCompactTable),
+        SparseDFA::<Vec<u8>, u16>::from_bytes_compact(
+            &dfa.to_bytes_little_endian().unwrap()
+        )
+        .err(),
+    );
+    assert_eq!(
+        Some(DeserializeError::InvalidCompactTable),
+        SparseDFA::<Vec<u8>, u16>::from_bytes_compact(
+            &bytes[..bytes.len() - 1]
+        )
+        .err(),
+    );
+    // A transition to a state past the last state is rejected.
+    let mut bad = bytes.to_vec();
+    let last = bad.len() - 1;
+    bad[last] = 0x7E;
+    match SparseDFA::<Vec<u8>, u16>::from_bytes_compact(&bad) {
+        Err(DeserializeError::InvalidStateID { .. }) => {}
+        got => panic!("unexpected result: {:?}", got),
+    }
+}
+
+#[test]
+fn archive_roundtrip() {
+    let bytes = ArchiveBuilder::new()
+        .add("a", b"x")
+        .add("empty", b"")
+        .add("b", b"12345678 and more")
+        .add("a", b"replaced")
+        .to_bytes();
+    let archive = Archive::from_bytes(&bytes).unwrap();
+    assert_eq!(3, archive.len());
+    assert_eq!(Some(&b"replaced"[..]), archive.get("a"));
+    assert_eq!(Some(&b""[..]), archive.get("empty"));
+    assert_eq!(None, archive.get("c"));
+    let names: Vec<&str> = archive.iter().map(|(name, _)| name).collect();
+    assert_eq!(vec!["a", "empty", "b"], names);
+    // Every entry starts at a multiple of 8 from the start of the archive.
+    for (_, contents) in archive.iter() {
+        let offset = contents.as_ptr() as usize - bytes.as_ptr() as usize;
+        assert_eq!(0, offset % 8);
+    }
+
+    assert_eq!(
+        Some(DeserializeError::InvalidArchive),
+        Archive::from_bytes(&bytes[..bytes.len() - 1]).err(),
+    );
+    assert_eq!(
+        Some(DeserializeError::BufferTooSmall { needed: 40, got: 30 }),
+        Archive::from_bytes(&bytes[..30]).err(),
+    );
+    assert_eq!(
+        Some(DeserializeError::InvalidLabel),
+        Archive::from_bytes(b"nope").err(),
+    );
+}
+
+#[test]
+fn regex_serialize_roundtrip() {
+    let re = Regex::new(r"(?-u:\b)[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap();
+    let re = Regex::from_dfas(
+        re.forward().to_u32().unwrap(),
+        re.reverse().to_u32().unwrap(),
+    );
+    let bytes = re.to_bytes_native_endian().unwrap();
+    let got: Regex<DenseDFA<&[u32], u32>> =
+        Regex::try_from_bytes(&bytes).unwrap();
+    let text = b"2018-12-24 x2016-10-08 2016-10-08";
+    let matches: Vec<Match> = got.find_iter(text).collect();
+    assert_eq!(matches, vec![Match::new(0, 10), Match::new(23, 33)]);
+    let got: Regex<DenseDFA<&[u32], u32>> =
+        unsafe { Regex::from_bytes(&bytes) };
+    assert_eq!(Some(Match::new(0, 10)), got.find(text));
+
+    let other = ArchiveBuilder::new().add("forward", b"").to_bytes();
+    assert_eq!(
+        Some(DeserializeError::InvalidArchive),
+        Regex::<DenseDFA<&[u32], u32>>::try_from_bytes(&other).err(),
+    );
+}
+
+#[cfg(all(feature = "mmap", unix))]
+#[test]
+fn mmap_dfa() {
+    use regex_automata::Mmap;
+    use std::fs;
+
+    let dir = std::env::temp_dir();
+    let re = Regex::new("foo[0-9]+").unwrap();
+    let re = Regex::from_dfas(
+        re.forward().to_u32().unwrap(),
+        re.reverse().to_u32().unwrap(),
+    );
+    let path =
+        dir.join(format!("regex-automata-{}.regex", std::process::id()));
+    fs::write(&path, re.to_bytes_native_endian().unwrap()).unwrap();
+    let map = unsafe { Mmap::open(&path).unwrap() };
+    let got: Regex<DenseDFA<&[u32], u32>> =
+        Regex::try_from_bytes(&map).unwrap();
+    assert_eq!(Some(Match::new(3, 8)), got.find(b"xyzfoo12"));
+    fs::remove_file(&path).unwrap();
+
+    let path =
+        dir.join(format!("regex-automata-{}.empty", std::process::id()));
+    fs::write(&path, b"").unwrap();
+    let map = unsafe { Mmap::open(&path).unwrap() };
+    assert!(map.is_empty());
+    assert_eq!(
+        Some(DeserializeError::InvalidLabel),
+        DenseDFA::<&[u32], u32>::try_from_bytes(&map).err(),
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unaligned_dfa_agrees_with_dense() {
+    use regex_automata::dense::UnalignedDFA;
+
+    let patterns = &["foo[0-9]+", r"(?-u:\b)[a-z]+(?-u:\b)", "(?m)^bar$"];
+    let haystacks: &[&[u8]] =
+        &[b"foo123", b"xyz foo9 bar", b"a\nbar\nb", b"", b"!!"];
+    for &(premultiply, byte_classes) in
+        &[(true, true), (true, false), (false, true), (false, false)]
+    {
+        let dfa = dense::Builder::new()
+            .premultiply(premultiply)
+            .byte_classes(byte_classes)
+            .build_many(patterns)
+            .unwrap()
+            .to_u32()
+            .unwrap();
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        let mut unaligned = vec![0];
+        unaligned.extend_from_slice(&bytes);
+        let got: UnalignedDFA<u32> =
+            UnalignedDFA::from_bytes(&unaligned[1..]).unwrap();
+        for &haystack in haystacks {
+            assert_eq!(dfa.find(haystack), got.find(haystack));
+            assert_eq!(dfa.find_pattern(haystack), got.find_pattern(haystack));
+            assert_eq!(dfa.rfind(haystack), got.rfind(haystack));
+        }
+    }
+
+    let bytes = DenseDFA::new("a").unwrap().to_bytes_native_endian().unwrap();
+    assert_eq!(
+        Some(DeserializeError::StateIDSizeMismatch {
+            expected: 2,
+            found: std::mem::size_of::<usize>(),
+        }),
+        UnalignedDFA::<u16>::from_bytes(&bytes).err(),
+    );
+}
+
+#[test]
+fn shared_row_dfa_agrees_with_dense() {
+    let patterns = &["foo[0-9]+", r"(?-u:\b)[a-z]+(?-u:\b)", "(?m)^bar$"];
+    let haystacks: &[&[u8]] =
+        &[b"foo123", b"xyz foo9 bar", b"a\nbar\nb", b"", b"!!"];
+    for &(premultiply, byte_classes) in
+        &[(true, true), (true, false), (false, true), (false, false)]
+    {
+        let dfa = dense::Builder::new()
+            .premultiply(premultiply)
+            .byte_classes(byte_classes)
+            .build_many(patterns)
+            .unwrap();
+        let got = dfa.to_shared_rows();
+        assert_eq!(dfa.state_count(), got.state_count());
+        for &haystack in haystacks {
+            assert_eq!(dfa.find(haystack), got.find(haystack));
+            assert_eq!(dfa.find_pattern(haystack), got.find_pattern(haystack));
+            assert_eq!(dfa.rfind(haystack), got.rfind(haystack));
+        }
+    }
+
+    // Without minimization, the states for the codepoints shared by several
+    // patterns are duplicated.
+    let dfa = dense::Builder::new()
+        .anchored(true)
+        .build_many(&[r"\w+", r"\pL+", r"\d+"])
+        .unwrap();
+    let got = dfa.to_shared_rows();
+    assert!(got.row_count() * 3 < got.state_count() * 2);
+    assert!(got.memory_usage() * 3 < dfa.memory_usage() * 2);
+    for &haystack in &["δέλτα αλφα", "123 abc", "٣٤ x"] {
+        let haystack = haystack.as_bytes();
+        assert_eq!(dfa.find_pattern(haystack), got.find_pattern(haystack));
+    }
+}
+
+#[test]
+fn shared_byte_classes() {
+    let patterns = &[r"foo[0-9]+", r"(?-u:\b)[a-z]+(?-u:\b)", r"\w+@\w+"];
+    let haystacks: &[&[u8]] =
+        &[b"foo123", b"xyz foo9 bar", b"me@example", "δ@ε".as_bytes()];
+    let classes =
+        *dense::Builder::new().build_many(patterns).unwrap().byte_classes();
+    for &byte_classes in &[true, false] {
+        for pattern in patterns {
+            let expected = dense::Builder::new().build(pattern).unwrap();
+            let got = dense::Builder::new()
+                .byte_classes(byte_classes)
+                .shared_byte_classes(Some(classes))
+                .build(pattern)
+                .unwrap();
+            assert_eq!(&classes, got.byte_classes());
+            if let Ok(sparse) = got.to_sparse() {
+                assert_eq!(&classes, sparse.byte_classes());
+            }
+            for &haystack in haystacks {
+                assert_eq!(expected.find(haystack), got.find(haystack));
+            }
+        }
+    }
+
+    // The classes must distinguish every byte the pattern distinguishes.
+    let narrow = *DenseDFA::new("[a-z]").unwrap().byte_classes();
+    let err = dense::Builder::new()
+        .shared_byte_classes(Some(narrow))
+        .build("[a-m]")
+        .unwrap_err();
+    match *err.kind() {
+        ErrorKind::Unsupported(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+    // The same goes for quit bytes.
+    let err = dense::Builder::new()
+        .shared_byte_classes(Some(narrow))
+        .quit(b'q', true)
+        .build("[a-z]")
+        .unwrap_err();
+    match *err.kind() {
+        ErrorKind::Unsupported(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+/// The pattern of the DFA serialized in `data/serialized/date-u16-le.dfa`.
+const DATE_PATTERN: &str = r"[0-9]{4}-[0-9]{2}-[0-9]{2}";
+
+#[cfg(target_endian = "little")]
+#[test]
+fn include_dfa() {
+    let dfa: DenseDFA<&'static [u16], u16> =
+        unsafe { include_dfa!("../data/serialized/date-u16-le.dfa", u16) };
+    assert_eq!(Some(10), dfa.find(b"2018-12-24"));
+    assert_eq!(None, dfa.find(b"2018-12"));
+}
+
+/// Check that the serialized DFAs in `data/serialized` are up to date with
+/// the serialization format. When the format changes, regenerate them by
+/// running this test with `REGEX_AUTOMATA_REGENERATE=1`.
+#[test]
+fn serialized_data_is_current() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/data/serialized/date-u16-le.dfa"
+    );
+    let dfa = DenseDFA::new(DATE_PATTERN).unwrap().to_u16().unwrap();
+    let expected = dfa.to_bytes_little_endian().unwrap();
+    if std::env::var_os("REGEX_AUTOMATA_REGENERATE").is_some() {
+        std::fs::write(path, &*expected).unwrap();
+    }
+    let got = std::fs::read(path).unwrap();
+    assert!(
+        *expected == got[..],
+        "{} is out of date, regenerate it by running this test with \
+         REGEX_AUTOMATA_REGENERATE=1",
+        path,
+    );
+}
+
+#[test]
+fn search_from_offset_uses_preceding_byte() {
+    use regex_automata::backtrack::BoundedBacktracker;
+
+    // The start state of a search depends on the byte before the start
+    // offset, so assertions at the start of a search must agree with a
+    // backtracker searching the same haystack from the same offset.
+    let haystack = b"ab a\nb1 \nab\n";
+    for pattern in &[
+        r"^a",
+        r"(?m)^[ab]",
+        r"(?m)$",
+        r"(?-u:\b)[ab]",
+        r"[ab1](?-u:\b)",
+        r"(?m)(?-u:\b)b$",
+        r"\A[a-z]",
+    ] {
+        let re = Regex::new(pattern).unwrap();
+        let bt = BoundedBacktracker::new(pattern).unwrap();
+        let mut cache = bt.new_cache();
+        for start in 0..haystack.len() + 1 {
+            let expected = bt.find_at(&mut cache, haystack, start).unwrap();
+            assert_eq!(
+                expected,
+                re.find_at(haystack, start),
+                "pattern: {:?}, start: {}",
+                pattern,
+                start,
+            );
+            assert_eq!(expected.is_some(), re.is_match_at(haystack, start));
+            assert_eq!(
+                expected.map(|m| m.end()),
+                re.forward().find_at(haystack, start),
+            );
+        }
+    }
+}
+
+#[test]
+fn full_match_agrees_with_regex_crate() {
+    let haystacks: &[&[u8]] =
+        &[b"", b"a", b"ab", b"abc", b"foo", b"foobar", b"foo\n", b"12ab"];
+    for pattern in &[
+        r"a|ab",
+        r"foo|foobar",
+        r"[a-z]*",
+        r"[0-9]+[a-z]+",
+        r"(?m)^foo$",
+        r"(?-u:\b)ab",
+        r"",
+    ] {
+        let re = Regex::new(pattern).unwrap();
+        let anchored = format!(r"\A(?:{})\z", pattern);
+        let expected = regex::bytes::Regex::new(&anchored).unwrap();
+        for &haystack in haystacks {
+            assert_eq!(
+                expected.is_match(haystack),
+                re.is_full_match(haystack),
+                "pattern: {:?}, haystack: {:?}",
+                pattern,
+                haystack,
+            );
+        }
+    }
+    let re = Regex::new(r"☃+").unwrap();
+    assert!(re.is_full_match_str("☃☃"));
+    assert!(!re.is_full_match_str("☃ ☃"));
+}
+
+#[test]
+fn build_diagnostics() {
+    let (dfa, diagnostics) = dense::Builder::new()
+        .unicode_word_boundary(true)
+        .build_with_diagnostics(r"\bfoo\b")
+        .unwrap();
+    assert_eq!(
+        &[BuildWarning::UnicodeWordBoundaryAsAscii],
+        diagnostics.warnings()
+    );
+    assert_eq!(Ok(Some(7)), dfa.try_find(b"bar foo"));
+
+    let (_, diagnostics) =
+        dense::Builder::new().build_with_diagnostics(r"\w+").unwrap();
+    assert!(diagnostics.is_empty());
+
+    let words: Vec<String> = (0..100).map(|i| format!("w{}x", i)).collect();
+    let pattern = words.join("|");
+    let (re, diagnostics) =
+        RegexBuilder::new().build_with_diagnostics(&pattern).unwrap();
+    assert_eq!(&[BuildWarning::PrefilterLiteralLimit], diagnostics.warnings());
+    assert_eq!(Some(Match::new(1, 5)), re.find(b"-w42x-"));
+
+    // Patterns without literals and builders without prefilters don't warn.
+    for &(pattern, prefilter) in &[(r"\w+", true), (pattern.as_str(), false)] {
+        let (_, diagnostics) = RegexBuilder::new()
+            .prefilter(prefilter)
+            .build_with_diagnostics(pattern)
+            .unwrap();
+        assert!(diagnostics.is_empty(), "{}", pattern);
+    }
+}
+
+#[test]
+fn state_id_constructors() {
+    assert_eq!(65535, u16::new(65535).unwrap());
+    let err = u16::new(65536).unwrap_err();
+    assert_eq!(Some(65537), err.state_count());
+    assert_eq!(7, u8::new_unchecked(7));
+    assert_eq!(7, PatternID::new(7).unwrap());
+
+    let dfa = dense::Builder::new().build_many(&["a", "b", "c"]).unwrap();
+    let patterns: Vec<PatternID> =
+        PatternID::iter(dfa.pattern_count()).collect();
+    assert_eq!(vec![0, 1, 2], patterns);
+    assert_eq!(0, u8::iter(0).len());
+    assert_eq!(256, u8::iter(256).last().unwrap() as usize + 1);
+}
+
+#[test]
+fn regex_owned_and_borrowed() {
+    let mut re = RegexBuilder::new()
+        .build_with_size::<u16>(r"(?-u:\b)[a-z]+[0-9]")
+        .unwrap();
+    re.shrink_to_fit();
+    let expected: Vec<Match> = re.find_iter(b"ab1 c2 3d4").collect();
+    assert_eq!(2, expected.len());
+
+    let borrowed: Regex<DenseDFA<&[u16], u16>> = re.as_ref();
+    assert_eq!(
+        expected,
+        borrowed.find_iter(b"ab1 c2 3d4").collect::<Vec<_>>()
+    );
+    assert_eq!(re.prefilter().is_some(), borrowed.prefilter().is_some());
+
+    let owned = {
+        let bytes = re.to_bytes_native_endian().unwrap();
+        let re: Regex<DenseDFA<&[u16], u16>> =
+            Regex::try_from_bytes(&bytes).unwrap();
+        re.to_owned()
+    };
+    assert_eq!(expected, owned.find_iter(b"ab1 c2 3d4").collect::<Vec<_>>());
+}
+
+#[test]
+fn lexer_tokens() {
+    let lexer = LexerBuilder::new()
+        .allow_invalid_utf8(true)
+        .recovery(r"(?s-u:.)")
+        .build(&[r"fn(?-u:\b)", r"[a-z]+", r"[0-9]*", r"\s+", r"->"])
+        .unwrap();
+    let input = b"fn fnord -> 12 #! x";
+    let tokens: Vec<(PatternID, &[u8])> = lexer
+        .tokens(input)
+        .map(|t| t.unwrap())
+        .map(|t| (t.pattern(), &input[t.range()]))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (0, &b"fn"[..]),
+            (3, &b" "[..]),
+            (1, &b"fnord"[..]),
+            (3, &b" "[..]),
+            (4, &b"->"[..]),
+            (3, &b" "[..]),
+            (2, &b"12"[..]),
+            (3, &b" "[..]),
+            (3, &b" "[..]),
+            (1, &b"x"[..]),
+        ]
+    );
+}
+
+#[test]
+fn lexer_error() {
+    let lexer = Lexer::new(&[r"[a-z]+", r" "]).unwrap();
+    let mut it = lexer.tokens(b"ab c!d");
+    assert_eq!(Some(Ok(MultiMatch::new(0, 0, 2))), it.next());
+    assert_eq!(Some(Ok(MultiMatch::new(1, 2, 3))), it.next());
+    assert_eq!(Some(Ok(MultiMatch::new(0, 3, 4))), it.next());
+    assert_eq!(4, it.offset());
+    assert_eq!(Some(Err(SearchError::NoToken { offset: 4 })), it.next());
+    assert_eq!(None, it.next());
+    assert_eq!(4, it.offset());
+
+    let mut it = lexer.tokens(b"");
+    assert_eq!(None, it.next());
+    assert_eq!(0, it.offset());
+
+    // A recovery pattern that only matches the empty string can't make
+    // progress, so it is treated as not matching.
+    let lexer =
+        LexerBuilder::new().recovery(r"x*").build(&[r"[a-z]+"]).unwrap();
+    let tokens: Vec<_> = lexer.tokens(b"ab!x").collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Ok(MultiMatch::new(0, 0, 2)),
+            Err(SearchError::NoToken { offset: 2 })
+        ]
+    );
+}
@@ -4,7 +4,9 @@ use std::fmt::{self, Write};
 use std::thread;
 
 use regex;
-use regex_automata::{DenseDFA, ErrorKind, Regex, RegexBuilder, StateID, DFA};
+use regex_automata::{
+    dense, DenseDFA, Error, ErrorKind, Regex, RegexBuilder, StateID, DFA,
+};
 use serde_bytes;
 use toml;
 
@@ -24,11 +26,13 @@ lazy_static! {
         load!(col, "fowler/nullsubexpr.toml");
         load!(col, "fowler/repetition.toml");
         load!(col, "fowler/repetition-long.toml");
+        load!(col, "anchors.toml");
         load!(col, "crazy.toml");
         load!(col, "flags.toml");
         load!(col, "iter.toml");
         load!(col, "no-unicode.toml");
         load!(col, "unicode.toml");
+        load!(col, "word-boundary-ascii.toml");
         col
     };
 }
@@ -64,6 +68,8 @@ pub struct RegexTest {
 pub enum RegexTestOption {
     Anchored,
     CaseInsensitive,
+    MultiLine,
+    Crlf,
     NoUnicode,
     Escaped,
     #[serde(rename = "invalid-utf8")]
@@ -197,6 +203,35 @@ impl RegexTester {
         }
     }
 
+    pub fn build_dfa<T, F>(
+        &self,
+        mut builder: dense::Builder,
+        test: &RegexTest,
+        build: F,
+    ) -> Option<T>
+    where
+        F: FnOnce(&dense::Builder, &str) -> Result<T, Error>,
+    {
+        if self.skip(test) {
+            return None;
+        }
+        self.apply_dfa_options(test, &mut builder);
+
+        match build(&builder, &test.pattern) {
+            Ok(dfa) => Some(dfa),
+            Err(err) => {
+                if let ErrorKind::Unsupported(_) = *err.kind() {
+                    None
+                } else {
+                    panic!(
+                        "failed to build {:?} with pattern '{:?}': {}",
+                        test.name, test.pattern, err
+                    );
+                }
+            }
+        }
+    }
+
     pub fn test_all<'a, I, T>(&mut self, builder: RegexBuilder, tests: I)
     where
         I: IntoIterator<IntoIter = T, Item = &'a RegexTest>,
@@ -243,11 +278,31 @@ impl RegexTester {
         });
     }
 
+    pub fn test_is_match_with<F: Fn(&[u8]) -> bool>(
+        &mut self,
+        test: &RegexTest,
+        is_match: F,
+    ) {
+        self.asserted = false;
+
+        let got = is_match(&test.input);
+        let expected = !test.matches.is_empty();
+        if got == expected {
+            self.results.succeeded.push(test.clone());
+            return;
+        }
+        self.results.failed.push(RegexTestFailure {
+            test: test.clone(),
+            kind: RegexTestFailureKind::IsMatch,
+        });
+    }
+
     pub fn test_find<'a, D: DFA>(&mut self, test: &RegexTest, re: &Regex<D>) {
         self.asserted = false;
 
-        let got =
-            re.find(&test.input).map(|(start, end)| Match { start, end });
+        let got = re
+            .find(&test.input)
+            .map(|m| Match { start: m.start(), end: m.end() });
         if got == test.matches.get(0).map(|&m| m) {
             self.results.succeeded.push(test.clone());
             return;
@@ -267,7 +322,7 @@ impl RegexTester {
 
         let got: Vec<Match> = re
             .find_iter(&test.input)
-            .map(|(start, end)| Match { start, end })
+            .map(|m| Match { start: m.start(), end: m.end() })
             .collect();
         if got == test.matches {
             self.results.succeeded.push(test.clone());
@@ -307,6 +362,42 @@ impl RegexTester {
                 RegexTestOption::CaseInsensitive => {
                     builder.case_insensitive(true);
                 }
+                RegexTestOption::MultiLine => {
+                    builder.multi_line(true);
+                }
+                RegexTestOption::Crlf => {
+                    builder.crlf(true);
+                }
+                RegexTestOption::NoUnicode => {
+                    builder.unicode(false);
+                }
+                RegexTestOption::Escaped => {}
+                RegexTestOption::InvalidUTF8 => {
+                    builder.allow_invalid_utf8(true);
+                }
+            }
+        }
+    }
+
+    fn apply_dfa_options(
+        &self,
+        test: &RegexTest,
+        builder: &mut dense::Builder,
+    ) {
+        for opt in &test.options {
+            match *opt {
+                RegexTestOption::Anchored => {
+                    builder.anchored(true);
+                }
+                RegexTestOption::CaseInsensitive => {
+                    builder.case_insensitive(true);
+                }
+                RegexTestOption::MultiLine => {
+                    builder.multi_line(true);
+                }
+                RegexTestOption::Crlf => {
+                    builder.crlf(true);
+                }
                 RegexTestOption::NoUnicode => {
                     builder.unicode(false);
                 }
@@ -1,4 +1,4 @@
-use regex_automata::{DenseDFA, Regex, RegexBuilder, SparseDFA};
+use regex_automata::{dense, DenseDFA, Regex, RegexBuilder, SparseDFA, DFA};
 
 use collection::{RegexTester, SUITE};
 
@@ -61,7 +61,7 @@ fn minimized_standard() {
     let mut builder = RegexBuilder::new();
     builder.minimize(true).premultiply(false).byte_classes(false);
 
-    let mut tester = RegexTester::new().skip_expensive();
+    let mut tester = RegexTester::new();
     tester.test_all(builder, SUITE.tests());
     tester.assert();
 }
@@ -71,7 +71,7 @@ fn minimized_premultiply() {
     let mut builder = RegexBuilder::new();
     builder.minimize(true).premultiply(true).byte_classes(false);
 
-    let mut tester = RegexTester::new().skip_expensive();
+    let mut tester = RegexTester::new();
     tester.test_all(builder, SUITE.tests());
     tester.assert();
 }
@@ -105,11 +105,39 @@ fn minimized_standard_no_nfa_shrink() {
         .byte_classes(false)
         .shrink(false);
 
-    let mut tester = RegexTester::new().skip_expensive();
+    let mut tester = RegexTester::new();
     tester.test_all(builder, SUITE.tests());
     tester.assert();
 }
 
+// Test that a DFA built for is_match only agrees with the full DFA on every
+// test, and that collapsing match states never makes it bigger.
+#[test]
+fn is_match_only() {
+    let mut builder = dense::Builder::new();
+    builder.minimize(true);
+
+    let mut tester = RegexTester::new().skip_expensive();
+    let (mut full_states, mut only_states) = (0, 0);
+    for test in SUITE.tests() {
+        let full =
+            match tester.build_dfa(builder.clone(), test, |b, p| b.build(p)) {
+                None => continue,
+                Some(dfa) => dfa,
+            };
+        let only = tester
+            .build_dfa(builder.clone(), test, |b, p| b.build_match_only(p))
+            .unwrap();
+        assert!(only.state_count() <= full.state_count(), "{}", test.name);
+        full_states += full.state_count();
+        only_states += only.state_count();
+
+        tester.test_is_match_with(test, |input| only.is_match(input));
+    }
+    tester.assert();
+    assert!(only_states < full_states);
+}
+
 // A basic sanity test that checks we can convert a regex to a smaller
 // representation and that the resulting regex still passes our tests.
 //
@@ -150,6 +178,10 @@ fn sparse_unminimized_standard() {
             None => continue,
             Some(re) => re,
         };
+        // Sparse DFAs don't support word boundary assertions.
+        if re.forward().has_lookaround() {
+            continue;
+        }
         let fwd = re.forward().to_sparse().unwrap();
         let rev = re.reverse().to_sparse().unwrap();
         let sparse_re = Regex::from_dfas(fwd, rev);
@@ -173,6 +205,10 @@ fn sparse_u16() {
             None => continue,
             Some(re) => re,
         };
+        // Sparse DFAs don't support word boundary assertions.
+        if re.forward().has_lookaround() {
+            continue;
+        }
         let fwd = re.forward().to_sparse().unwrap().to_u16().unwrap();
         let rev = re.reverse().to_sparse().unwrap().to_u16().unwrap();
         let sparse_re = Regex::from_dfas(fwd, rev);
@@ -210,6 +246,34 @@ fn serialization_roundtrip() {
     tester.assert();
 }
 
+// Like serialization_roundtrip, but deserializes the DFAs using the checked
+// deserialization routine, with premultiplied state identifiers.
+#[test]
+fn serialization_roundtrip_checked() {
+    let mut builder = RegexBuilder::new();
+    builder.premultiply(true).byte_classes(true);
+
+    let mut tester = RegexTester::new().skip_expensive();
+    for test in SUITE.tests() {
+        let builder = builder.clone();
+        let re: Regex = match tester.build_regex(builder, test) {
+            None => continue,
+            Some(re) => re,
+        };
+
+        let fwd_bytes = re.forward().to_bytes_native_endian().unwrap();
+        let rev_bytes = re.reverse().to_bytes_native_endian().unwrap();
+        let fwd: DenseDFA<&[usize], usize> =
+            DenseDFA::try_from_bytes(&fwd_bytes).unwrap();
+        let rev: DenseDFA<&[usize], usize> =
+            DenseDFA::try_from_bytes(&rev_bytes).unwrap();
+        let re = Regex::from_dfas(fwd, rev);
+
+        tester.test(test, &re);
+    }
+    tester.assert();
+}
+
 // A basic sanity test that checks we can serialize and then deserialize a
 // regex using sparse DFAs, and that the resulting regex can be used for
 // searching correctly.
@@ -225,6 +289,10 @@ fn sparse_serialization_roundtrip() {
             None => continue,
             Some(re) => re,
         };
+        // Sparse DFAs don't support word boundary assertions.
+        if re.forward().has_lookaround() {
+            continue;
+        }
 
         let fwd_bytes = re
             .forward()
@@ -4,6 +4,7 @@ extern crate lazy_static;
 #[cfg(feature = "std")]
 extern crate regex;
 #[cfg(feature = "std")]
+#[macro_use]
 extern crate regex_automata;
 #[cfg(feature = "std")]
 extern crate serde;
@@ -15,6 +16,8 @@ extern crate serde_derive;
 #[cfg(feature = "std")]
 extern crate toml;
 
+#[cfg(feature = "std")]
+mod api;
 #[cfg(feature = "std")]
 mod collection;
 #[cfg(feature = "std")]